@@ -0,0 +1,10 @@
+// Generates the gRPC server types from proto/asp.proto when built with `--features grpc`
+// (needs `protoc` on the PATH)
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/asp.proto");
+        tonic_build::compile_protos("proto/asp.proto").expect("Failed to compile proto/asp.proto");
+    }
+}
@@ -1,5 +1,9 @@
+use crate::conv::{felt_to_u128, U256};
+use crate::error::BlockchainError;
 use serde::{Deserialize, Serialize};
 use once_cell::sync::Lazy;
+use starknet::core::types::{FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
 
 /// ABI Entry - represents a single entry in the ABI JSON
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -157,3 +161,308 @@ pub fn validate_erc20_abi(abi: &[AbiEntry]) -> Result<(), String> {
     Ok(())
 }
 
+/// Cairo type of a function input or output, parsed from its ABI name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    Felt,
+    Bool,
+    /// Unsigned integer of at most 128 bits (u8 ... u128), the bit width
+    Uint(u32),
+    /// Signed integer of at most 128 bits (i8 ... i128), the bit width
+    Int(u32),
+    U256,
+    ContractAddress,
+    /// Array<T> or Span<T>: length-prefixed elements
+    Array(Box<AbiType>),
+    Tuple(Vec<AbiType>),
+}
+
+/// A decoded (or to be encoded) Cairo value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    Felt(FieldElement),
+    Bool(bool),
+    Uint(u128),
+    Int(i128),
+    U256(U256),
+    ContractAddress(FieldElement),
+    Array(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+impl AbiType {
+    /// Parse an ABI type name such as `core::integer::u256` or `core::array::Array::<core::felt252>`
+    pub fn parse(name: &str) -> Result<Self, BlockchainError> {
+        let name = name.trim();
+        if let Some(inner) = name.strip_prefix('(').and_then(|n| n.strip_suffix(')')) {
+            return split_top_level(inner)
+                .into_iter()
+                .map(AbiType::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map(AbiType::Tuple);
+        }
+        for prefix in ["core::array::Array::<", "core::array::Span::<"] {
+            if let Some(inner) = name.strip_prefix(prefix).and_then(|n| n.strip_suffix('>')) {
+                return Ok(AbiType::Array(Box::new(AbiType::parse(inner)?)));
+            }
+        }
+
+        match name {
+            "core::felt252" => Ok(AbiType::Felt),
+            "core::bool" => Ok(AbiType::Bool),
+            "core::integer::u256" => Ok(AbiType::U256),
+            "core::starknet::contract_address::ContractAddress" => Ok(AbiType::ContractAddress),
+            _ => {
+                let short = name.strip_prefix("core::integer::").unwrap_or(name);
+                let bits = |digits: &str| digits.parse::<u32>().ok().filter(|b| [8, 16, 32, 64, 128].contains(b));
+                match (short.strip_prefix('u').and_then(bits), short.strip_prefix('i').and_then(bits)) {
+                    (Some(bits), _) => Ok(AbiType::Uint(bits)),
+                    (_, Some(bits)) => Ok(AbiType::Int(bits)),
+                    _ => Err(BlockchainError::Config(format!("Unsupported ABI type '{}'", name))),
+                }
+            }
+        }
+    }
+
+    /// Serialize `value` as calldata felts
+    pub fn encode(&self, value: &AbiValue, out: &mut Vec<FieldElement>) -> Result<(), BlockchainError> {
+        match (self, value) {
+            (AbiType::Felt, AbiValue::Felt(felt)) | (AbiType::ContractAddress, AbiValue::ContractAddress(felt)) => {
+                out.push(*felt)
+            }
+            (AbiType::Bool, AbiValue::Bool(b)) => out.push(if *b { FieldElement::ONE } else { FieldElement::ZERO }),
+            (AbiType::Uint(bits), AbiValue::Uint(v)) if *bits == 128 || *v >> bits == 0 => out.push(FieldElement::from(*v)),
+            (AbiType::Int(bits), AbiValue::Int(v)) if *bits == 128 || (*v >> (bits - 1)) == 0 || (*v >> (bits - 1)) == -1 => {
+                let magnitude = FieldElement::from(v.unsigned_abs());
+                out.push(if *v < 0 { FieldElement::ZERO - magnitude } else { magnitude });
+            }
+            (AbiType::U256, AbiValue::U256(v)) => {
+                out.push(FieldElement::from(v.low));
+                out.push(FieldElement::from(v.high));
+            }
+            (AbiType::Array(item), AbiValue::Array(values)) => {
+                out.push(FieldElement::from(values.len() as u64));
+                for value in values {
+                    item.encode(value, out)?;
+                }
+            }
+            (AbiType::Tuple(types), AbiValue::Tuple(values)) if types.len() == values.len() => {
+                for (ty, value) in types.iter().zip(values) {
+                    ty.encode(value, out)?;
+                }
+            }
+            _ => {
+                return Err(BlockchainError::InvalidInput(format!("{:?} is not a valid {:?}", value, self)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read one value from the front of `felts`, advancing it
+    pub fn decode(&self, felts: &mut &[FieldElement]) -> Result<AbiValue, BlockchainError> {
+        let mut next = || -> Result<FieldElement, BlockchainError> {
+            let (first, rest) = felts
+                .split_first()
+                .ok_or_else(|| BlockchainError::Decode(format!("Response too short for {:?}", self)))?;
+            *felts = rest;
+            Ok(*first)
+        };
+
+        match self {
+            AbiType::Felt => Ok(AbiValue::Felt(next()?)),
+            AbiType::ContractAddress => Ok(AbiValue::ContractAddress(next()?)),
+            AbiType::Bool => match next()? {
+                f if f == FieldElement::ZERO => Ok(AbiValue::Bool(false)),
+                f if f == FieldElement::ONE => Ok(AbiValue::Bool(true)),
+                f => Err(BlockchainError::Decode(format!("0x{:x} is not a bool", f))),
+            },
+            AbiType::Uint(bits) => {
+                let felt = next()?;
+                let v = felt_to_u128(felt)?;
+                if *bits < 128 && v >> bits != 0 {
+                    return Err(BlockchainError::Decode(format!("0x{:x} does not fit in u{}", felt, bits)));
+                }
+                Ok(AbiValue::Uint(v))
+            }
+            AbiType::Int(bits) => {
+                let felt = next()?;
+                let (negative, magnitude) = match felt_to_u128(felt) {
+                    Ok(v) => (false, v),
+                    Err(_) => (true, felt_to_u128(FieldElement::ZERO - felt)?),
+                };
+                let limit = 1u128 << (bits - 1);
+                match negative {
+                    false if magnitude < limit => Ok(AbiValue::Int(magnitude as i128)),
+                    true if magnitude <= limit => Ok(AbiValue::Int((magnitude as i128).wrapping_neg())),
+                    _ => Err(BlockchainError::Decode(format!("0x{:x} does not fit in i{}", felt, bits))),
+                }
+            }
+            AbiType::U256 => {
+                let low = next()?;
+                let high = next()?;
+                Ok(AbiValue::U256(U256::from_felts(low, high)?))
+            }
+            AbiType::Array(item) => {
+                let len = felt_to_u128(next()?)? as usize;
+                (0..len)
+                    .map(|_| item.decode(felts))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(AbiValue::Array)
+            }
+            AbiType::Tuple(types) => types
+                .iter()
+                .map(|ty| ty.decode(felts))
+                .collect::<Result<Vec<_>, _>>()
+                .map(AbiValue::Tuple),
+        }
+    }
+}
+
+/// Split `a, (b, c), Array::<d>` at the commas that aren't nested
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !list[start..].trim().is_empty() {
+        parts.push(list[start..].trim());
+    }
+    parts
+}
+
+/// A contract function with its input and output types resolved from the ABI
+#[derive(Debug, Clone)]
+pub struct AbiFunction {
+    pub name: String,
+    pub selector: FieldElement,
+    pub inputs: Vec<AbiType>,
+    pub outputs: Vec<AbiType>,
+}
+
+impl AbiFunction {
+    /// Look up `name` in `abi` and resolve its types
+    pub fn from_abi(abi: &[AbiEntry], name: &str) -> Result<Self, BlockchainError> {
+        let item = find_function(abi, name).map_err(BlockchainError::Config)?;
+        Ok(Self {
+            name: item.name.clone(),
+            selector: get_selector_from_name(&item.name)
+                .map_err(|e| BlockchainError::Config(format!("Invalid function name '{}': {}", item.name, e)))?,
+            inputs: item.inputs.iter().map(|i| AbiType::parse(&i.type_)).collect::<Result<_, _>>()?,
+            outputs: item.outputs.iter().map(|o| AbiType::parse(&o.type_)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Build a call to this function on `contract`, type-checking `args`
+    pub fn call(&self, contract: FieldElement, args: &[AbiValue]) -> Result<FunctionCall, BlockchainError> {
+        if args.len() != self.inputs.len() {
+            return Err(BlockchainError::InvalidInput(format!(
+                "{} takes {} arguments, got {}",
+                self.name,
+                self.inputs.len(),
+                args.len()
+            )));
+        }
+        let mut calldata = Vec::new();
+        for (ty, arg) in self.inputs.iter().zip(args) {
+            ty.encode(arg, &mut calldata)?;
+        }
+        Ok(FunctionCall {
+            contract_address: contract,
+            entry_point_selector: self.selector,
+            calldata,
+        })
+    }
+
+    /// Decode the felts returned by this function, rejecting trailing data
+    pub fn decode_output(&self, result: &[FieldElement]) -> Result<Vec<AbiValue>, BlockchainError> {
+        let mut felts = result;
+        let values = self
+            .outputs
+            .iter()
+            .map(|ty| ty.decode(&mut felts))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !felts.is_empty() {
+            return Err(BlockchainError::Decode(format!(
+                "{} returned {} unexpected trailing felts",
+                self.name,
+                felts.len()
+            )));
+        }
+        Ok(values)
+    }
+}
+
+/// Resolved ERC20 function (panics only if the bundled ABI itself is broken)
+pub fn erc20_function(name: &str) -> Result<AbiFunction, BlockchainError> {
+    AbiFunction::from_abi(get_erc20_abi(), name)
+}
+
+/// Resolved Zylith function
+pub fn zylith_function(name: &str) -> Result<AbiFunction, BlockchainError> {
+    AbiFunction::from_abi(get_zylith_abi(), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(AbiType::parse("core::integer::u256").unwrap(), AbiType::U256);
+        assert_eq!(AbiType::parse("core::integer::i32").unwrap(), AbiType::Int(32));
+        assert_eq!(
+            AbiType::parse("core::array::Array::<core::felt252>").unwrap(),
+            AbiType::Array(Box::new(AbiType::Felt))
+        );
+        assert_eq!(
+            AbiType::parse("(core::integer::i128, core::integer::i128)").unwrap(),
+            AbiType::Tuple(vec![AbiType::Int(128), AbiType::Int(128)])
+        );
+        assert!(AbiType::parse("core::option::Option::<core::felt252>").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let ty = AbiType::parse("(core::integer::i128, core::integer::u256, core::array::Array::<core::bool>)").unwrap();
+        let value = AbiValue::Tuple(vec![
+            AbiValue::Int(-5),
+            AbiValue::U256(U256::from_words(1, 2)),
+            AbiValue::Array(vec![AbiValue::Bool(true), AbiValue::Bool(false)]),
+        ]);
+
+        let mut felts = Vec::new();
+        ty.encode(&value, &mut felts).unwrap();
+        assert_eq!(felts.len(), 6);
+        assert_eq!(felts[0], FieldElement::ZERO - FieldElement::from(5u64));
+
+        let mut slice = felts.as_slice();
+        assert_eq!(ty.decode(&mut slice).unwrap(), value);
+        assert!(slice.is_empty());
+    }
+
+    #[test]
+    fn test_erc20_balance_of_call_and_output() {
+        let balance_of = erc20_function("balance_of").unwrap();
+        let owner = FieldElement::from(0xabcu64);
+
+        let call = balance_of.call(FieldElement::from(0x111u64), &[AbiValue::ContractAddress(owner)]).unwrap();
+        assert_eq!(call.calldata, vec![owner]);
+        assert!(matches!(balance_of.call(owner, &[]), Err(BlockchainError::InvalidInput(_))));
+        assert!(matches!(balance_of.call(owner, &[AbiValue::Bool(true)]), Err(BlockchainError::InvalidInput(_))));
+
+        let output = balance_of.decode_output(&[FieldElement::from(7u64), FieldElement::ONE]).unwrap();
+        assert_eq!(output, vec![AbiValue::U256(U256::from_words(7, 1))]);
+        // A felt above 128 bits is not a valid u256 word
+        assert!(balance_of.decode_output(&[FieldElement::ZERO - FieldElement::ONE, FieldElement::ZERO]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// Admin API
+// Maintenance actions for operators on /admin, behind ADMIN_API_KEY sent as a bearer token:
+// resync a contract's tree from a block in place (the watchdog restarts its sync task, the
+// process keeps serving), backfill a block range, prune old deposit origins, and stats on every
+// synced contract. The key is separate from API_KEYS so no client key opens these; without it
+// the routes aren't served.
+
+use crate::api_version::SyncStatusV1;
+use crate::contracts::ContractSync;
+use crate::error::BlockchainError;
+use crate::AppState;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Key the admin routes require
+pub struct AdminAuth {
+    key: String,
+}
+
+impl AdminAuth {
+    /// Key from ADMIN_API_KEY, None if unset or empty
+    pub fn from_env() -> Option<Self> {
+        let key = std::env::var("ADMIN_API_KEY").ok()?.trim().to_string();
+        (!key.is_empty()).then_some(Self { key })
+    }
+
+    fn allows(&self, headers: &HeaderMap) -> bool {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        presented.is_some_and(|presented| constant_time_eq(presented.as_bytes(), self.key.as_bytes()))
+    }
+}
+
+/// Compare without returning at the first differing byte, so timing doesn't leak the key
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Middleware rejecting requests without the admin key
+pub async fn require_admin(State(auth): State<Arc<AdminAuth>>, request: Request, next: Next) -> Response {
+    if !auth.allows(request.headers()) {
+        return (StatusCode::UNAUTHORIZED, "Admin endpoints need the admin key (Authorization: Bearer)").into_response();
+    }
+    next.run(request).await
+}
+
+/// Synced contract at `address`, the primary one if None
+fn contract<'a>(state: &'a AppState, address: Option<&str>) -> Result<&'a Arc<ContractSync>, Response> {
+    match address {
+        Some(address) => state.contracts.get(address).ok_or_else(|| crate::unknown_contract(address)),
+        None => Ok(state.contracts.primary()),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AdminResyncRequest {
+    /// Contract to resync, the primary one if unset
+    contract: Option<String>,
+    /// Block the tree is rebuilt from, the contract's deployment block if unset
+    from_block: Option<u64>,
+}
+
+/// Drop a contract's tree and sync it again from `from_block`, without restarting the ASP
+#[utoipa::path(
+    post,
+    path = "/admin/resync",
+    tag = "admin",
+    request_body = AdminResyncRequest,
+    security(("admin_key" = [])),
+    responses(
+        (status = 202, description = "Resync scheduled; the sync task restarts within seconds", body = serde_json::Value),
+        (status = 401, description = "Missing or wrong admin key"),
+        (status = 404, description = "Contract not synced by this ASP"),
+    )
+)]
+pub async fn resync(State(state): State<AppState>, Json(payload): Json<AdminResyncRequest>) -> impl IntoResponse {
+    let contract = match contract(&state, payload.contract.as_deref()) {
+        Ok(contract) => contract,
+        Err(rejection) => return rejection,
+    };
+    let from_block = match payload.from_block {
+        Some(block) => block,
+        None => match contract.blockchain.deployment_block().await {
+            Ok(block) => block,
+            Err(e) => return (StatusCode::BAD_GATEWAY, format!("Failed to find the contract deployment block: {}", e)).into_response(),
+        },
+    };
+    println!("[ASP] 🛠️  Admin resync of 0x{:x} from block {}", contract.address, from_block);
+    contract.syncer.schedule_resync(from_block);
+    (StatusCode::ACCEPTED, Json(serde_json::json!({
+        "contract": format!("0x{:064x}", contract.address),
+        "from_block": from_block,
+        "status": "resync scheduled",
+    }))).into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AdminBackfillRequest {
+    /// Contract to backfill, the primary one if unset
+    contract: Option<String>,
+    from_block: u64,
+    to_block: u64,
+}
+
+/// Rescan `from_block..=to_block` of a contract for deposits the tree missed
+#[utoipa::path(
+    post,
+    path = "/admin/backfill",
+    tag = "admin",
+    request_body = AdminBackfillRequest,
+    security(("admin_key" = [])),
+    responses(
+        (status = 200, description = "Deposits found and applied", body = serde_json::Value),
+        (status = 400, description = "Invalid block range"),
+        (status = 401, description = "Missing or wrong admin key"),
+        (status = 404, description = "Contract not synced by this ASP"),
+    )
+)]
+pub async fn backfill(State(state): State<AppState>, Json(payload): Json<AdminBackfillRequest>) -> impl IntoResponse {
+    let contract = match contract(&state, payload.contract.as_deref()) {
+        Ok(contract) => contract,
+        Err(rejection) => return rejection,
+    };
+    println!("[ASP] 🛠️  Admin backfill of 0x{:x}, blocks {}-{}", contract.address, payload.from_block, payload.to_block);
+    match contract.syncer.backfill(payload.from_block, payload.to_block).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e @ BlockchainError::InvalidInput(_)) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, format!("Backfill failed: {}", e)).into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AdminPruneRequest {
+    /// Contract to prune, the primary one if unset
+    contract: Option<String>,
+    /// Blocks of deposit events to keep behind the synced block; the configured retention if unset
+    keep_blocks: Option<u64>,
+}
+
+/// Drop the block and transaction of a contract's old deposits from the sync state; leaves and roots stay
+#[utoipa::path(
+    post,
+    path = "/admin/prune",
+    tag = "admin",
+    request_body = AdminPruneRequest,
+    security(("admin_key" = [])),
+    responses(
+        (status = 200, description = "Deposits whose origin was pruned", body = serde_json::Value),
+        (status = 400, description = "No keep_blocks and no HISTORY_RETENTION_BLOCKS"),
+        (status = 401, description = "Missing or wrong admin key"),
+        (status = 404, description = "Contract not synced by this ASP"),
+    )
+)]
+pub async fn prune(State(state): State<AppState>, Json(payload): Json<AdminPruneRequest>) -> impl IntoResponse {
+    let contract = match contract(&state, payload.contract.as_deref()) {
+        Ok(contract) => contract,
+        Err(rejection) => return rejection,
+    };
+    let Some(keep_blocks) = payload.keep_blocks.or(contract.syncer.history_retention) else {
+        return (StatusCode::BAD_REQUEST, "keep_blocks is required when no HISTORY_RETENTION_BLOCKS is set").into_response();
+    };
+    let before_block = contract.syncer.commitments.synced_block().saturating_sub(keep_blocks);
+    println!("[ASP] 🛠️  Admin prune of 0x{:x}, origins before block {}", contract.address, before_block);
+    let pruned = contract.syncer.prune_history(before_block);
+    Json(serde_json::json!({
+        "contract": format!("0x{:064x}", contract.address),
+        "pruned": pruned,
+        "before_block": before_block,
+    })).into_response()
+}
+
+/// Tree, index and sync figures of every synced contract
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    tag = "admin",
+    security(("admin_key" = [])),
+    responses(
+        (status = 200, description = "Uptime and per-contract tree, index and sync figures", body = serde_json::Value),
+        (status = 401, description = "Missing or wrong admin key"),
+    )
+)]
+pub async fn stats(State(state): State<AppState>) -> impl IntoResponse {
+    let contracts: Vec<_> = state
+        .contracts
+        .iter()
+        .map(|contract| {
+            let (root, leaf_count) = {
+                let tree = contract.deposit_tree.lock().unwrap();
+                (format!("0x{:x}", tree.get_root()), tree.get_leaf_count())
+            };
+            let syncer = &contract.syncer;
+            serde_json::json!({
+                "address": format!("0x{:064x}", contract.address),
+                "primary": Arc::ptr_eq(contract, state.contracts.primary()),
+                "root": root,
+                "leaf_count": leaf_count,
+                "commitments_indexed": syncer.commitments.len(),
+                "indexed_through_block": syncer.commitments.synced_block(),
+                "spent_nullifiers": syncer.nullifier_tree.lock().unwrap().spent_count(),
+                "roots_recorded": syncer.roots.recent(usize::MAX).len(),
+                "persistent_state": syncer.checkpoints.is_some(),
+                "sync": SyncStatusV1::from(contract.sync_status.status()),
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "uptime_seconds": state.started.elapsed().as_secs(),
+        "contracts": contracts,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_admin_key() {
+        let auth = AdminAuth { key: "operator-secret".to_string() };
+        let mut headers = HeaderMap::new();
+        assert!(!auth.allows(&headers));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer operator-secret"));
+        assert!(auth.allows(&headers));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer operator-secreT"));
+        assert!(!auth.allows(&headers));
+        // A client API key header doesn't count
+        headers.remove(header::AUTHORIZATION);
+        headers.insert("x-api-key", HeaderValue::from_static("operator-secret"));
+        assert!(!auth.allows(&headers));
+    }
+}
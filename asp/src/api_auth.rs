@@ -0,0 +1,261 @@
+// API keys and per-client rate limits
+// With API_KEYS set, every route but a few read-only checks (roots, nullifiers, health) needs
+// a key in `x-api-key` or `Authorization: Bearer`, and each key draws from its own token
+// bucket. The open routes are limited per client IP instead, at a lower rate, so a public
+// deployment answers wallets' root and nullifier checks without being scraped for proofs and
+// deposit listings. Without API_KEYS every route stays open and unlimited. The gRPC API has no
+// open calls: with API_KEYS set each one needs a key, sent as `x-api-key` metadata.
+
+use crate::admin::constant_time_eq;
+use crate::api_version::V1_PREFIX;
+use crate::rate_limit::{RateLimit, TokenBucket};
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+/// Routes served without a key, limited per client IP
+const OPEN_ROUTES: &[&str] = &[
+    "/health",
+    "/healthz",
+    "/readyz",
+    "/openapi.json",
+    "/docs",
+    "/root",
+    "/deposit/root",
+    "/roots/:root/known",
+    "/nullifier/root",
+    "/nullifier/:nullifier",
+    "/api/nullifier/:nullifier",
+    "/api/pool/root",
+];
+
+/// Client IPs tracked before the ones with full buckets are forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Requests per second of a key unless set
+const DEFAULT_KEY_RATE: u64 = 20;
+/// Requests per second of a client IP on the open routes unless set
+const DEFAULT_OPEN_RATE: u64 = 5;
+
+/// Configured keys and the buckets they and anonymous clients draw from
+pub struct ApiAuth {
+    keys: HashMap<String, TokenBucket>,
+    open_limit: RateLimit,
+    clients: Mutex<HashMap<IpAddr, Arc<TokenBucket>>>,
+}
+
+impl ApiAuth {
+    /// Keys from API_KEYS (comma-separated) or API_KEYS_FILE (one per line), each optionally
+    /// `key:rps`; None without keys. API_KEY_RATE_LIMIT_RPS / _BURST set the default rate of a
+    /// key, OPEN_RATE_LIMIT_RPS / _BURST the per-IP rate of the open routes.
+    pub fn from_env() -> std::io::Result<Option<Self>> {
+        let keys = match (std::env::var("API_KEYS"), std::env::var("API_KEYS_FILE")) {
+            (Ok(keys), _) => keys,
+            (Err(_), Ok(path)) => std::fs::read_to_string(path)?,
+            (Err(_), Err(_)) => return Ok(None),
+        };
+        Ok(Self::from_lookup(&keys, |key| std::env::var(key).ok()))
+    }
+
+    fn from_lookup(keys: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let number = |key: &str| lookup(key).and_then(|value| value.trim().parse::<u64>().ok()).filter(|n| *n > 0);
+        let rate = |rps_key: &str, burst_key: &str, default: u64| {
+            let rps = number(rps_key).unwrap_or(default);
+            RateLimit::new(rps as u32, number(burst_key).unwrap_or(rps) as u32)
+        };
+        let key_limit = rate("API_KEY_RATE_LIMIT_RPS", "API_KEY_RATE_LIMIT_BURST", DEFAULT_KEY_RATE);
+
+        let keys: HashMap<String, TokenBucket> = keys
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty() && !entry.starts_with('#'))
+            .map(|entry| match entry.split_once(':') {
+                Some((key, rps)) => match rps.trim().parse::<u32>() {
+                    Ok(rps) if rps > 0 => (key.trim().to_string(), RateLimit::new(rps, rps)),
+                    _ => {
+                        eprintln!("⚠️  Invalid rate for API key {}…, using the default", redact_key(key));
+                        (key.trim().to_string(), key_limit)
+                    }
+                },
+                None => (entry.to_string(), key_limit),
+            })
+            .map(|(key, limit)| (key, TokenBucket::new(limit)))
+            .collect();
+        if keys.is_empty() {
+            return None;
+        }
+        Some(Self {
+            keys,
+            open_limit: rate("OPEN_RATE_LIMIT_RPS", "OPEN_RATE_LIMIT_BURST", DEFAULT_OPEN_RATE),
+            clients: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether a request to `route` from `client`, with `key` if it sent one, may go ahead
+    fn check(&self, route: &str, key: Option<&str>, client: IpAddr) -> Result<(), Response> {
+        if let Some(key) = key {
+            return self.check_key(key).map_err(Rejection::into_response);
+        }
+        let route = route.strip_prefix(V1_PREFIX).unwrap_or(route);
+        if !OPEN_ROUTES.contains(&route) {
+            return Err(Rejection::KeyRequired.into_response());
+        }
+        self.client_bucket(client).try_acquire().map_err(|retry_after| Rejection::RateLimited(retry_after).into_response())
+    }
+
+    /// Draw from the bucket of `key`; every configured key is compared, in constant time, so
+    /// the timing tells nothing about how close a guess came
+    fn check_key(&self, key: &str) -> Result<(), Rejection> {
+        let bucket = self.keys.iter().fold(None, |found, (candidate, bucket)| {
+            if constant_time_eq(candidate.as_bytes(), key.as_bytes()) {
+                Some(bucket)
+            } else {
+                found
+            }
+        });
+        match bucket {
+            Some(bucket) => bucket.try_acquire().map_err(Rejection::RateLimited),
+            None => Err(Rejection::InvalidKey),
+        }
+    }
+
+    /// Whether a gRPC call with `metadata` may go ahead: it needs a key, `x-api-key` or a bearer token
+    #[cfg(feature = "grpc")]
+    pub fn check_grpc(&self, metadata: &tonic::metadata::MetadataMap) -> Result<(), tonic::Status> {
+        let value = |name: &str| metadata.get(name).and_then(|value| value.to_str().ok());
+        let key = value("x-api-key").or_else(|| value("authorization").and_then(|value| value.strip_prefix("Bearer ")));
+        match key {
+            Some(key) => self.check_key(key.trim()).map_err(Rejection::into_status),
+            None => Err(Rejection::KeyRequired.into_status()),
+        }
+    }
+
+    fn client_bucket(&self, client: IpAddr) -> Arc<TokenBucket> {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&client) {
+            // A full bucket is the same as a fresh one
+            clients.retain(|_, bucket| !bucket.is_full());
+        }
+        clients.entry(client).or_insert_with(|| Arc::new(TokenBucket::new(self.open_limit))).clone()
+    }
+}
+
+/// Key of a request: `x-api-key`, or a bearer token
+fn request_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get("x-api-key").and_then(|value| value.to_str().ok()) {
+        return Some(key.trim());
+    }
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Why a request was turned away
+enum Rejection {
+    InvalidKey,
+    KeyRequired,
+    /// The bucket refills enough after this long
+    RateLimited(std::time::Duration),
+}
+
+impl Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::InvalidKey => (StatusCode::UNAUTHORIZED, "Invalid API key").into_response(),
+            Rejection::KeyRequired => (StatusCode::UNAUTHORIZED, "This endpoint needs an API key (x-api-key header)").into_response(),
+            Rejection::RateLimited(retry_after) => {
+                (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_secs(retry_after).to_string())], "Rate limit exceeded").into_response()
+            }
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    fn into_status(self) -> tonic::Status {
+        match self {
+            Rejection::InvalidKey => tonic::Status::unauthenticated("Invalid API key"),
+            Rejection::KeyRequired => tonic::Status::unauthenticated("The gRPC API needs an API key (x-api-key metadata)"),
+            Rejection::RateLimited(retry_after) => {
+                tonic::Status::resource_exhausted(format!("Rate limit exceeded, retry in {}s", retry_secs(retry_after)))
+            }
+        }
+    }
+}
+
+fn retry_secs(retry_after: std::time::Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+fn redact_key(key: &str) -> String {
+    key.chars().take(4).collect()
+}
+
+/// Middleware applying `auth` to every routed request
+pub async fn authorize(
+    State(auth): State<Arc<ApiAuth>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    route: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = route.as_ref().map_or(request.uri().path(), |route| route.as_str());
+    match auth.check(route, request_key(request.headers()), client.ip()) {
+        Ok(()) => next.run(request).await,
+        Err(rejection) => rejection,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_and_open_routes() {
+        let vars: HashMap<&str, &str> = [("API_KEY_RATE_LIMIT_RPS", "2"), ("OPEN_RATE_LIMIT_RPS", "1")].into_iter().collect();
+        let auth = ApiAuth::from_lookup("wallet-key, dashboard:1,\n# comment", |key| vars.get(key).map(|v| v.to_string())).unwrap();
+        assert_eq!(auth.key_count(), 2);
+        let client: IpAddr = [10, 0, 0, 1].into();
+
+        // A key works everywhere, at its own rate
+        assert!(auth.check("/deposit/list", Some("wallet-key"), client).is_ok());
+        assert!(auth.check("/deposit/list", Some("wallet-key"), client).is_ok());
+        let limited = auth.check("/deposit/list", Some("wallet-key"), client).unwrap_err();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(header::RETRY_AFTER));
+        assert!(auth.check("/root", Some("dashboard"), client).is_ok());
+        assert_eq!(auth.check("/root", Some("dashboard"), client).unwrap_err().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(auth.check("/root", Some("stolen"), client).unwrap_err().status(), StatusCode::UNAUTHORIZED);
+
+        // Without one: open routes only, limited per IP
+        assert_eq!(auth.check("/deposit/list", None, client).unwrap_err().status(), StatusCode::UNAUTHORIZED);
+        assert!(auth.check("/nullifier/:nullifier", None, client).is_ok());
+        assert_eq!(auth.check("/root", None, client).unwrap_err().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(auth.check("/root", None, [10, 0, 0, 2].into()).is_ok());
+        assert!(auth.check("/v1/nullifier/:nullifier", None, [10, 0, 0, 3].into()).is_ok());
+        assert_eq!(auth.check("/v1/deposit/list", None, client).unwrap_err().status(), StatusCode::UNAUTHORIZED);
+
+        assert!(ApiAuth::from_lookup(" , ", |_| None).is_none());
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn test_grpc_calls_need_a_key() {
+        let auth = ApiAuth::from_lookup("wallet-key:1", |_| None).unwrap();
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        assert_eq!(auth.check_grpc(&metadata).unwrap_err().code(), tonic::Code::Unauthenticated);
+        metadata.insert("x-api-key", "wallet-kez".parse().unwrap());
+        assert_eq!(auth.check_grpc(&metadata).unwrap_err().code(), tonic::Code::Unauthenticated);
+        metadata.insert("x-api-key", "wallet-key".parse().unwrap());
+        assert!(auth.check_grpc(&metadata).is_ok());
+        assert_eq!(auth.check_grpc(&metadata).unwrap_err().code(), tonic::Code::ResourceExhausted);
+    }
+}
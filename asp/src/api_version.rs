@@ -0,0 +1,115 @@
+// API versions
+// Every route is served under /v1, with the unversioned paths kept as aliases so deployed
+// wallets keep working; those answer with `Deprecation`, a `Link` to their /v1 successor and,
+// once API_UNVERSIONED_SUNSET is set, the `Sunset` date. Responses whose internal type keeps
+// growing go out through a DTO frozen for the version (SyncStatusV1): new fields land in the
+// next version's DTO instead of changing what a v1 client parses.
+
+use crate::sync_status::{SyncHealth, SyncStatus};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Prefix of the current API version
+pub const V1_PREFIX: &str = "/v1";
+
+/// Sync progress as served by v1
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SyncStatusV1 {
+    /// Every event up to this block is in the tree
+    pub current_block: u64,
+    /// Chain tip the syncer is catching up to
+    pub target_block: u64,
+    /// Leaves in the deposit tree
+    pub leaves_indexed: u32,
+    /// Event throughput of the current (or last) scan
+    pub events_per_second: f64,
+    /// Estimated seconds until `target_block` is reached, unknown until some progress is made
+    pub eta_seconds: Option<u64>,
+    /// The tree reflects the chain as of `target_block`
+    pub synced: bool,
+    /// Whether the sync task is alive, as seen by the watchdog
+    pub health: SyncHealth,
+    /// Times the watchdog restarted the sync task
+    pub restarts: u32,
+}
+
+impl From<SyncStatus> for SyncStatusV1 {
+    fn from(status: SyncStatus) -> Self {
+        Self {
+            current_block: status.current_block,
+            target_block: status.target_block,
+            leaves_indexed: status.leaves_indexed,
+            events_per_second: status.events_per_second,
+            eta_seconds: status.eta_seconds,
+            synced: status.synced,
+            health: status.health,
+            restarts: status.restarts,
+        }
+    }
+}
+
+/// Headers marking the unversioned aliases as deprecated
+pub struct Deprecation {
+    /// HTTP date after which the aliases may be removed
+    sunset: Option<HeaderValue>,
+}
+
+impl Deprecation {
+    /// Sunset date from API_UNVERSIONED_SUNSET (HTTP date, e.g. `Wed, 01 Jul 2026 00:00:00 GMT`)
+    pub fn from_env() -> Result<Self, String> {
+        let sunset = match std::env::var("API_UNVERSIONED_SUNSET") {
+            Ok(date) => Some(HeaderValue::from_str(date.trim()).map_err(|e| format!("Invalid API_UNVERSIONED_SUNSET: {}", e))?),
+            Err(_) => None,
+        };
+        Ok(Self { sunset })
+    }
+
+    fn mark(&self, path: &str, response: &mut Response) {
+        let headers = response.headers_mut();
+        headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+        if let Ok(link) = HeaderValue::from_str(&format!("<{}{}>; rel=\"successor-version\"", V1_PREFIX, path)) {
+            headers.insert(header::LINK, link);
+        }
+        if let Some(sunset) = &self.sunset {
+            headers.insert(HeaderName::from_static("sunset"), sunset.clone());
+        }
+    }
+}
+
+/// Middleware marking responses of the unversioned aliases as deprecated
+pub async fn deprecate_unversioned(State(deprecation): State<Arc<Deprecation>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let mut response = next.run(request).await;
+    deprecation.mark(&path, &mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_unversioned_responses_point_to_v1() {
+        let deprecation = Deprecation { sunset: Some(HeaderValue::from_static("Wed, 01 Jul 2026 00:00:00 GMT")) };
+        let mut response = "0x1".into_response();
+        deprecation.mark("/deposit/root", &mut response);
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(response.headers()[header::LINK], "</v1/deposit/root>; rel=\"successor-version\"");
+        assert_eq!(response.headers()["sunset"], "Wed, 01 Jul 2026 00:00:00 GMT");
+
+        // v1 keeps its fields whatever SyncStatus grows
+        let status = serde_json::to_value(SyncStatusV1::from(SyncStatus::default())).unwrap();
+        let mut fields: Vec<&str> = status.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            ["current_block", "eta_seconds", "events_per_second", "health", "leaves_indexed", "restarts", "synced", "target_block"]
+        );
+    }
+}
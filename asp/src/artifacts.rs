@@ -0,0 +1,184 @@
+// Circuit artifacts
+// Each circuit needs its witness generator (build/<c>/<c>_js/<c>.wasm), proving key
+// (build/zkeys/<c>.zkey) and verification key (build/vkeys/<c>_vk.json) under CIRCUITS_DIR.
+// Files missing locally are downloaded from CIRCUIT_ARTIFACTS_URL, laid out the same way, and
+// every file is checked against the SHA-256 pinned for it in build/artifacts.sha256 (sha256sum
+// format) before it is used, so a swapped or truncated zkey is refused instead of producing
+// proofs the on-chain verifier rejects. A file is hashed again only when it changes on disk.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Files one circuit is proven and verified with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitArtifacts {
+    pub wasm: PathBuf,
+    pub zkey: PathBuf,
+    pub vkey: PathBuf,
+}
+
+pub struct ArtifactManager {
+    circuits_dir: PathBuf,
+    /// Where missing artifacts are downloaded from
+    base_url: Option<String>,
+    /// Path relative to `circuits_dir` -> pinned SHA-256 (hex); None without a manifest
+    pins: Option<HashMap<String, String>>,
+    /// Files that matched their pin, with the length and mtime they had then
+    verified: Arc<Mutex<HashMap<PathBuf, (u64, SystemTime)>>>,
+}
+
+impl ArtifactManager {
+    pub fn new(circuits_dir: PathBuf, base_url: Option<String>, pins: Option<HashMap<String, String>>) -> Self {
+        Self { circuits_dir, base_url, pins, verified: Arc::default() }
+    }
+
+    /// CIRCUITS_DIR (../circuits by default), CIRCUIT_ARTIFACTS_URL and the pins in
+    /// CIRCUIT_ARTIFACTS_MANIFEST (<CIRCUITS_DIR>/build/artifacts.sha256 by default)
+    pub fn from_env() -> Result<Self, String> {
+        let circuits_dir = match std::env::var("CIRCUITS_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => std::env::current_dir().map_err(|e| e.to_string())?.parent().ok_or("No parent directory for circuits/")?.join("circuits"),
+        };
+        let base_url = std::env::var("CIRCUIT_ARTIFACTS_URL").ok().map(|url| url.trim_end_matches('/').to_string()).filter(|url| !url.is_empty());
+        let manifest = std::env::var("CIRCUIT_ARTIFACTS_MANIFEST").map(PathBuf::from).unwrap_or_else(|_| circuits_dir.join("build").join("artifacts.sha256"));
+        let pins = match std::fs::read_to_string(&manifest) {
+            Ok(text) => Some(parse_manifest(&text).map_err(|e| format!("{}: {}", manifest.display(), e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("[ASP] ⚠️  No {}: circuit artifacts are used without integrity checks", manifest.display());
+                None
+            }
+            Err(e) => return Err(format!("Failed to read {}: {}", manifest.display(), e)),
+        };
+        Ok(Self::new(circuits_dir, base_url, pins))
+    }
+
+    pub fn circuits_dir(&self) -> &Path {
+        &self.circuits_dir
+    }
+
+    /// The wasm, zkey and verification key of `circuit`, present and matching their pins
+    pub async fn circuit(&self, circuit: &str) -> Result<CircuitArtifacts, String> {
+        Ok(CircuitArtifacts {
+            wasm: self.artifact(&format!("build/{0}/{0}_js/{0}.wasm", circuit)).await?,
+            zkey: self.artifact(&format!("build/zkeys/{}.zkey", circuit)).await?,
+            vkey: self.artifact(&format!("build/vkeys/{}_vk.json", circuit)).await?,
+        })
+    }
+
+    /// Just the verification key of `circuit`
+    pub async fn verification_key(&self, circuit: &str) -> Result<PathBuf, String> {
+        self.artifact(&format!("build/vkeys/{}_vk.json", circuit)).await
+    }
+
+    async fn artifact(&self, relative: &str) -> Result<PathBuf, String> {
+        let path = self.circuits_dir.join(relative);
+        if !path.exists() {
+            self.download(relative, &path).await?;
+        }
+        let Some(pins) = &self.pins else { return Ok(path) };
+        let pinned = pins.get(relative).ok_or_else(|| format!("{} has no pinned SHA-256 in the artifact manifest", relative))?.clone();
+
+        let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let stamp = (metadata.len(), metadata.modified().map_err(|e| e.to_string())?);
+        if self.verified.lock().unwrap().get(&path) == Some(&stamp) {
+            return Ok(path);
+        }
+        // zkeys run to hundreds of MB: hash off the async runtime
+        let hashed = path.clone();
+        let digest = tokio::task::spawn_blocking(move || sha256_file(&hashed))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to hash {}: {}", path.display(), e))?;
+        if digest != pinned {
+            return Err(format!("{} has SHA-256 {}, pinned {}", path.display(), digest, pinned));
+        }
+        self.verified.lock().unwrap().insert(path.clone(), stamp);
+        Ok(path)
+    }
+
+    async fn download(&self, relative: &str, path: &Path) -> Result<(), String> {
+        let Some(base_url) = &self.base_url else {
+            return Err(format!("{} not found (set CIRCUIT_ARTIFACTS_URL to download it)", path.display()));
+        };
+        let url = format!("{}/{}", base_url, relative);
+        println!("[ASP] ⬇️  Downloading {}", url);
+        let bytes = reqwest::get(&url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("{}: {}", url, e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("{}: {}", url, e))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        // Written aside and renamed, so an interrupted download never looks complete
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &bytes).map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+        std::fs::rename(&partial, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// `<sha256 hex>  <relative path>` lines, as `sha256sum build/**/*` prints them
+fn parse_manifest(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut pins = HashMap::new();
+    for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#')) {
+        let (digest, path) = line.split_once(char::is_whitespace).ok_or_else(|| format!("line {}: expected `<sha256>  <path>`", number + 1))?;
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("line {}: {} is not a SHA-256", number + 1, digest));
+        }
+        // sha256sum marks binary mode with a leading `*`
+        let path = path.trim().trim_start_matches('*').trim_start_matches("./");
+        pins.insert(path.to_string(), digest.to_ascii_lowercase());
+    }
+    Ok(pins)
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_artifacts_must_match_their_pins() {
+        let dir = std::env::temp_dir().join(format!("zylith_artifacts_{}", std::process::id()));
+        for (relative, contents) in [("build/m/m_js/m.wasm", "wasm"), ("build/zkeys/m.zkey", "zkey"), ("build/vkeys/m_vk.json", "{}")] {
+            std::fs::create_dir_all(dir.join(relative).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(relative), contents).unwrap();
+        }
+        let digest = |contents: &str| hex::encode(Sha256::digest(contents.as_bytes()));
+        let manifest = format!(
+            "{}  build/m/m_js/m.wasm\n{} *./build/zkeys/m.zkey\n{}  build/vkeys/m_vk.json\n",
+            digest("wasm"),
+            digest("zkey"),
+            digest("{}")
+        );
+        let manager = ArtifactManager::new(dir.clone(), None, Some(parse_manifest(&manifest).unwrap()));
+        let artifacts = manager.circuit("m").await.unwrap();
+        assert_eq!(artifacts.zkey, dir.join("build/zkeys/m.zkey"));
+
+        // A replaced zkey is refused, an unpinned or missing circuit too
+        std::fs::write(dir.join("build/zkeys/m.zkey"), "other zkey").unwrap();
+        assert!(manager.circuit("m").await.unwrap_err().contains("pinned"));
+        assert!(manager.circuit("n").await.unwrap_err().contains("not found"));
+        assert!(parse_manifest("abc  build/zkeys/m.zkey").is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
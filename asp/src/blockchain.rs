@@ -1,93 +1,936 @@
+use crate::abi::{erc20_function, AbiType, AbiValue};
+use crate::config::ClientConfig;
+use crate::commitment_index::CommitmentIndex;
+use crate::dedupe::EventDeduper;
+use crate::deployment::find_deployment_block;
+use crate::conv::{felt_to_u128, felt_to_u32, format_felt, AddressFormat, U256};
+use crate::error::BlockchainError;
+use crate::events::{deposit_selector, DepositEvent};
+use crate::failover::{FailoverProvider, DEFAULT_FAILOVER_COOLDOWN};
+use crate::instrument::InstrumentedProvider;
+use crate::merkle::{MerkleTree, TreeConfig, TreeHash, TreeSnapshot};
+use crate::multicall::Multicall;
+use crate::nullifier_cache::{NullifierCache, DEFAULT_NULLIFIER_CACHE_CAPACITY, DEFAULT_UNSPENT_TTL};
+use crate::page_size::{AdaptivePageSize, PageSizeBounds};
+use crate::pinned::PinnedClient;
+use crate::provider::{CallResult, HttpPoolConfig, HttpProvider, StarknetProvider, TxReceipt};
+use crate::rate_limit::RateLimit;
+use crate::redact::{RedactionPolicy, Sensitive};
+use crate::response_cache::{ResponseCache, DEFAULT_RESPONSE_CACHE_CAPACITY};
+use crate::retry::{RetryPolicy, RetryProvider};
+use crate::storage::{slot_offset, storage_address_of, PathSegment};
+use crate::timeouts::{with_timeout, TimeoutProfile};
+use num_bigint::BigUint;
 use starknet::core::types::{BlockId, BlockTag, FieldElement, FunctionCall};
-use starknet::core::utils::starknet_keccak;
-use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider};
-use starknet_crypto::{pedersen_hash, FieldElement as CryptoFieldElement};
-use url::Url;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::OnceCell;
 
-pub struct BlockchainClient {
-    provider: JsonRpcClient<HttpTransport>,
+/// Roots history size assumed when the contract has no getter for it
+pub const DEFAULT_ROOTS_HISTORY_SIZE: u32 = 100;
+
+/// Provider stack behind an HTTP client: retries on top of failover across the RPC endpoints
+pub type RpcProvider = RetryProvider<InstrumentedProvider<FailoverProvider<HttpProvider>>>;
+
+pub struct BlockchainClient<P = RpcProvider> {
+    provider: P,
     zylith_address: FieldElement,
+    redaction: RedactionPolicy,
+    timeouts: TimeoutProfile,
+    /// Roots history size used when the contract doesn't expose one
+    roots_history_fallback: u32,
+    /// Check the rebuilt tree against the contract root in load_all_commitments
+    verify_root_after_sync: bool,
+    /// Hex format of contract addresses returned to callers
+    address_format: AddressFormat,
+    /// Hex format of Merkle roots returned to callers
+    root_format: AddressFormat,
+    /// On-chain multicall contract; without one, multicalls are batched at a pinned block
+    multicall_aggregator: Option<FieldElement>,
+    /// Recent is_nullifier_spent results
+    nullifier_cache: Mutex<NullifierCache>,
+    /// Recent results of idempotent view calls (roots, token metadata)
+    response_cache: Mutex<ResponseCache>,
+    /// Block number seen by the first successful `connect` handshake
+    handshake: OnceCell<u64>,
+    /// Block the Zylith contract was deployed at, discovered on first use unless configured
+    deployment_block: OnceCell<u64>,
+    /// Also look at the pending block, labeling what is found there as unconfirmed
+    include_pending: bool,
+    /// Leaf indices recorded by the syncer; commitment lookups scan events without it
+    commitment_index: Option<CommitmentIndex>,
+    /// Size of event scan pages, adapted to how the provider copes
+    event_pages: AdaptivePageSize,
+    /// Depth and node hash of locally rebuilt deposit trees
+    tree_config: TreeConfig,
 }
 
-impl BlockchainClient {
-    pub fn new(rpc_url: &str, zylith_address: &str) -> Result<Self, String> {
-        let url = Url::parse(rpc_url)
-            .map_err(|e| format!("Invalid RPC URL: {}", e))?;
-        
-        let provider = JsonRpcClient::new(HttpTransport::new(url));
-        
-        let zylith_addr = parse_felt(zylith_address)
-            .map_err(|e| format!("Invalid Zylith address: {}", e))?;
+/// Builder for an HTTP JSON-RPC BlockchainClient
+pub struct BlockchainClientBuilder {
+    /// One RPC URL, or several comma-separated ones in priority order
+    rpc_url: String,
+    zylith_address: String,
+    lazy: bool,
+    failover_cooldown: Duration,
+    retry: RetryPolicy,
+    pool: HttpPoolConfig,
+    redaction: RedactionPolicy,
+    timeouts: TimeoutProfile,
+    roots_history_fallback: u32,
+    nullifier_cache_capacity: usize,
+    unspent_nullifier_ttl: Duration,
+    response_cache_capacity: usize,
+    response_cache_ttls: Vec<(String, Option<Duration>)>,
+    verify_root_after_sync: bool,
+    address_format: AddressFormat,
+    root_format: AddressFormat,
+    multicall_aggregator: Option<String>,
+    deployment_block: Option<u64>,
+    include_pending: bool,
+    event_page_bounds: PageSizeBounds,
+    tree_config: TreeConfig,
+}
 
-        Ok(Self {
-            provider,
-            zylith_address: zylith_addr,
-        })
+impl BlockchainClientBuilder {
+    pub fn new(rpc_url: &str, zylith_address: &str) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+            zylith_address: zylith_address.to_string(),
+            lazy: false,
+            failover_cooldown: DEFAULT_FAILOVER_COOLDOWN,
+            retry: RetryPolicy::default(),
+            pool: HttpPoolConfig::default(),
+            redaction: RedactionPolicy::default(),
+            timeouts: TimeoutProfile::default(),
+            roots_history_fallback: DEFAULT_ROOTS_HISTORY_SIZE,
+            nullifier_cache_capacity: DEFAULT_NULLIFIER_CACHE_CAPACITY,
+            unspent_nullifier_ttl: DEFAULT_UNSPENT_TTL,
+            response_cache_capacity: DEFAULT_RESPONSE_CACHE_CAPACITY,
+            response_cache_ttls: Vec::new(),
+            verify_root_after_sync: false,
+            address_format: AddressFormat::default(),
+            root_format: AddressFormat::Trimmed,
+            multicall_aggregator: None,
+            deployment_block: None,
+            include_pending: false,
+            event_page_bounds: PageSizeBounds::default(),
+            tree_config: TreeConfig::default(),
+        }
     }
 
-    /// Get Merkle root from contract
-    pub async fn get_merkle_root(&self) -> Result<String, String> {
-        let call = FunctionCall {
-            contract_address: self.zylith_address,
-            entry_point_selector: get_selector("get_merkle_root"),
-            calldata: vec![],
+    /// Create the HTTP provider on first use instead of at build time
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// How long a failing RPC endpoint is ranked behind the others
+    pub fn failover_cooldown(mut self, cooldown: Duration) -> Self {
+        self.failover_cooldown = cooldown;
+        self
+    }
+
+    /// Retry policy applied to every RPC request (`RetryPolicy::none()` disables retries)
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Apply connect/request timeouts, connection limit and keep-alive from `config`
+    /// Finer-grained setters called afterwards still override it
+    pub fn client_config(mut self, config: ClientConfig) -> Self {
+        self.pool = HttpPoolConfig {
+            endpoint_rate_limits: std::mem::take(&mut self.pool.endpoint_rate_limits),
+            ..config.pool()
         };
+        self.timeouts.default = config.request_timeout;
+        self
+    }
+
+    /// Rate limit of every RPC endpoint without a specific one
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.pool.rate_limit = Some(limit);
+        self
+    }
+
+    /// Rate limit of the endpoint at `rpc_url`
+    pub fn endpoint_rate_limit(mut self, rpc_url: &str, limit: RateLimit) -> Self {
+        self.pool.endpoint_rate_limits.push((rpc_url.to_string(), limit));
+        self
+    }
+
+    /// Max idle HTTP connections kept per host
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool.max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// How long idle HTTP connections are kept open
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// TCP keep-alive interval for open HTTP connections
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.pool.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Set the log redaction policy for sensitive values
+    pub fn redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Replace the whole timeout profile
+    pub fn timeouts(mut self, timeouts: TimeoutProfile) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Global timeout for operations without a specific override
+    pub fn call_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = timeout;
+        self
+    }
+
+    /// Timeout for view function calls
+    pub fn read_call_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read_call = Some(timeout);
+        self
+    }
+
+    /// Timeout for raw storage reads
+    pub fn storage_read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.storage_read = Some(timeout);
+        self
+    }
+
+    /// Timeout for each page of an event scan
+    pub fn event_page_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.event_page = Some(timeout);
+        self
+    }
+
+    /// Roots history size to assume if the contract has no getter for it
+    pub fn roots_history_fallback(mut self, size: u32) -> Self {
+        self.roots_history_fallback = size;
+        self
+    }
+
+    /// Max nullifiers kept in the is_nullifier_spent cache (0 disables it)
+    pub fn nullifier_cache_capacity(mut self, capacity: usize) -> Self {
+        self.nullifier_cache_capacity = capacity;
+        self
+    }
+
+    /// How long an "unspent" answer is trusted before re-querying
+    pub fn unspent_nullifier_ttl(mut self, ttl: Duration) -> Self {
+        self.unspent_nullifier_ttl = ttl;
+        self
+    }
+
+    /// Max view call results kept in the response cache (0 disables it)
+    pub fn response_cache_capacity(mut self, capacity: usize) -> Self {
+        self.response_cache_capacity = capacity;
+        self
+    }
+
+    /// Cache results of the view function `function` for `ttl` (None: never cache it)
+    pub fn response_cache_ttl(mut self, function: &str, ttl: Option<Duration>) -> Self {
+        self.response_cache_ttls.push((function.to_string(), ttl));
+        self
+    }
+
+    /// Verify the rebuilt tree against the contract root after load_all_commitments
+    pub fn verify_root_after_sync(mut self, verify: bool) -> Self {
+        self.verify_root_after_sync = verify;
+        self
+    }
+
+    /// Hex format of returned contract addresses (default: zero-padded to 64 digits)
+    pub fn address_format(mut self, format: AddressFormat) -> Self {
+        self.address_format = format;
+        self
+    }
+
+    /// Hex format of returned Merkle roots (default: trimmed)
+    pub fn root_format(mut self, format: AddressFormat) -> Self {
+        self.root_format = format;
+        self
+    }
+
+    /// On-chain multicall contract used by `multicall` (default: pinned batch requests)
+    pub fn multicall_aggregator(mut self, address: &str) -> Self {
+        self.multicall_aggregator = Some(address.to_string());
+        self
+    }
+
+    /// Known deployment block of the Zylith contract (default: discovered on first use)
+    pub fn deployment_block(mut self, block: u64) -> Self {
+        self.deployment_block = Some(block);
+        self
+    }
+
+    /// Also read the pending block; results from it are labeled unconfirmed (default: off)
+    pub fn include_pending(mut self, include: bool) -> Self {
+        self.include_pending = include;
+        self
+    }
+
+    /// Bounds of the event scan page size (`PageSizeBounds::fixed` turns adaptation off)
+    pub fn event_page_bounds(mut self, bounds: PageSizeBounds) -> Self {
+        self.event_page_bounds = bounds;
+        self
+    }
+
+    /// Node hash of the contract's deposit tree (default: BN254 Poseidon)
+    pub fn tree_hash(mut self, hash: TreeHash) -> Self {
+        self.tree_config.hash = hash;
+        self
+    }
+
+    /// Depth, node hash and domain of the contract's deposit tree
+    pub fn tree_config(mut self, config: TreeConfig) -> Self {
+        self.tree_config = config;
+        self
+    }
+
+    pub fn build(self) -> Result<BlockchainClient, BlockchainError> {
+        let zylith_address = parse_felt(&self.zylith_address)
+            .map_err(|e| BlockchainError::Config(format!("Invalid Zylith address: {}", e)))?;
+        let failover = FailoverProvider::from_urls(&self.rpc_url, self.pool, self.lazy)?
+            .with_cooldown(self.failover_cooldown);
+        let provider = RetryProvider::new(InstrumentedProvider::new(failover), self.retry);
+        let aggregator = self
+            .multicall_aggregator
+            .as_deref()
+            .map(parse_felt)
+            .transpose()
+            .map_err(|e| BlockchainError::Config(format!("Invalid multicall aggregator address: {}", e)))?;
+
+        let mut client = BlockchainClient::with_provider(provider, zylith_address)
+            .with_redaction_policy(self.redaction)
+            .with_timeouts(self.timeouts)
+            .with_roots_history_fallback(self.roots_history_fallback)
+            .with_nullifier_cache(self.nullifier_cache_capacity, self.unspent_nullifier_ttl)
+            .with_response_cache({
+                let mut cache = ResponseCache::new(self.response_cache_capacity);
+                for (function, ttl) in &self.response_cache_ttls {
+                    cache.set_ttl(function, *ttl);
+                }
+                cache
+            })
+            .with_root_verification(self.verify_root_after_sync)
+            .with_address_format(self.address_format)
+            .with_root_format(self.root_format)
+            .with_pending(self.include_pending)
+            .with_event_page_bounds(self.event_page_bounds)
+            .with_tree_config(self.tree_config);
+        client.multicall_aggregator = aggregator;
+        if let Some(block) = self.deployment_block {
+            client = client.with_deployment_block(block);
+        }
+        Ok(client)
+    }
+
+    /// Build, then check the node is reachable and the Zylith contract is deployed
+    pub async fn build_checked(self) -> Result<BlockchainClient, BlockchainError> {
+        let client = self.build()?;
+        client.connect().await?;
+        client.ensure_contract_deployed().await?;
+        Ok(client)
+    }
+}
+
+/// Pool reserves and liquidity, as needed by swap/LP UIs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct PoolReserves {
+    /// token0 balance held by the Zylith contract
+    pub reserve0: U256,
+    /// token1 balance held by the Zylith contract
+    pub reserve1: U256,
+    /// Active liquidity of the pool (`pool.liquidity`)
+    pub total_liquidity: U256,
+}
+
+/// Options for `resolve_commitment`
+#[derive(Debug, Clone, Copy)]
+pub struct ResolveOpts {
+    /// Keep polling new blocks until the deposit appears
+    pub wait: bool,
+    /// Give up waiting after this long
+    pub timeout: Duration,
+    /// First block of the initial scan (defaults to the deployment block)
+    pub from_block: Option<u64>,
+    /// Delay between polls while waiting
+    pub poll_interval: Duration,
+}
+
+impl Default for ResolveOpts {
+    fn default() -> Self {
+        Self {
+            wait: false,
+            timeout: Duration::from_secs(120),
+            from_block: None,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Where a root stands relative to the contract's accepted roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootStatus {
+    /// The live Merkle root
+    Current,
+    /// An older root the contract still accepts for proofs
+    Historical,
+    /// Never a root of this contract
+    Unknown,
+}
 
-        let result = self.provider
-            .call(call, BlockId::Tag(BlockTag::Latest))
+/// Whether a result comes from accepted blocks or only from the pending block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// In an accepted block
+    Confirmed,
+    /// Only in the pending block: may still change or be dropped
+    Unconfirmed,
+}
+
+impl<P: StarknetProvider> BlockchainClient<P> {
+    /// Build a client on top of an already constructed provider
+    pub fn with_provider(provider: P, zylith_address: FieldElement) -> Self {
+        Self {
+            provider,
+            zylith_address,
+            redaction: RedactionPolicy::default(),
+            timeouts: TimeoutProfile::default(),
+            roots_history_fallback: DEFAULT_ROOTS_HISTORY_SIZE,
+            verify_root_after_sync: false,
+            address_format: AddressFormat::default(),
+            // Roots have always been returned trimmed; keep that unless asked otherwise
+            root_format: AddressFormat::Trimmed,
+            multicall_aggregator: None,
+            nullifier_cache: Mutex::new(NullifierCache::new(DEFAULT_NULLIFIER_CACHE_CAPACITY, DEFAULT_UNSPENT_TTL)),
+            response_cache: Mutex::new(ResponseCache::new(DEFAULT_RESPONSE_CACHE_CAPACITY)),
+            handshake: OnceCell::new(),
+            deployment_block: OnceCell::new(),
+            include_pending: false,
+            commitment_index: None,
+            event_pages: AdaptivePageSize::default(),
+            tree_config: TreeConfig::default(),
+        }
+    }
+
+    /// Set the log redaction policy for sensitive values
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Set per-operation RPC timeouts
+    pub fn with_timeouts(mut self, timeouts: TimeoutProfile) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Set the roots history size assumed when the contract has no getter for it
+    pub fn with_roots_history_fallback(mut self, size: u32) -> Self {
+        self.roots_history_fallback = size;
+        self
+    }
+
+    /// Configure the is_nullifier_spent cache (capacity 0 disables it)
+    pub fn with_nullifier_cache(mut self, capacity: usize, unspent_ttl: Duration) -> Self {
+        self.nullifier_cache = Mutex::new(NullifierCache::new(capacity, unspent_ttl));
+        self
+    }
+
+    /// Replace the cache of view call results
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.response_cache = Mutex::new(cache);
+        self
+    }
+
+    /// Report a block seen elsewhere (e.g. by the syncer); cached latest-block results are dropped
+    /// once the chain has moved past them
+    pub fn observe_block(&self, block_number: u64) {
+        self.response_cache.lock().unwrap().observe_block(block_number);
+    }
+
+    /// The chain reorganized: drop every cached read, spent nullifiers included
+    pub fn on_reorg(&self) {
+        self.response_cache.lock().unwrap().clear();
+        self.nullifier_cache.lock().unwrap().clear();
+    }
+
+    /// Verify the rebuilt tree against the contract root after load_all_commitments
+    pub fn with_root_verification(mut self, verify: bool) -> Self {
+        self.verify_root_after_sync = verify;
+        self
+    }
+
+    /// Set the hex format of returned contract addresses (token0/token1)
+    pub fn with_address_format(mut self, format: AddressFormat) -> Self {
+        self.address_format = format;
+        self
+    }
+
+    /// Set the hex format of returned Merkle roots
+    pub fn with_root_format(mut self, format: AddressFormat) -> Self {
+        self.root_format = format;
+        self
+    }
+
+    /// Use a known deployment block instead of discovering it
+    pub fn with_deployment_block(mut self, block: u64) -> Self {
+        self.deployment_block = OnceCell::new_with(Some(block));
+        self
+    }
+
+    /// Also read the pending block, for near-real-time results labeled unconfirmed
+    pub fn with_pending(mut self, include: bool) -> Self {
+        self.include_pending = include;
+        self
+    }
+
+    /// Look commitments up in `index` first; only blocks after its sync head are scanned
+    pub fn with_commitment_index(mut self, index: CommitmentIndex) -> Self {
+        self.commitment_index = Some(index);
+        self
+    }
+
+    /// Adapt event scan page sizes within `bounds`
+    pub fn with_event_page_bounds(mut self, bounds: PageSizeBounds) -> Self {
+        self.event_pages = AdaptivePageSize::new(bounds);
+        self
+    }
+
+    /// Rebuild deposit trees with `hash`, the one the contract uses
+    pub fn with_tree_hash(mut self, hash: TreeHash) -> Self {
+        self.tree_config.hash = hash;
+        self
+    }
+
+    /// Rebuild deposit trees with `config`, the contract's depth and hashing
+    pub fn with_tree_config(mut self, config: TreeConfig) -> Self {
+        self.tree_config = config;
+        self
+    }
+
+    pub fn tree_config(&self) -> TreeConfig {
+        self.tree_config
+    }
+
+    /// Whether the pending block is read too
+    pub fn includes_pending(&self) -> bool {
+        self.include_pending
+    }
+
+    /// Run multicalls through an on-chain aggregator contract instead of a pinned batch
+    pub fn with_multicall_aggregator(mut self, aggregator: FieldElement) -> Self {
+        self.multicall_aggregator = Some(aggregator);
+        self
+    }
+
+    /// The underlying provider
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Check the node is reachable with a block_number handshake
+    /// The first successful handshake is cached, later calls return immediately
+    pub async fn connect(&self) -> Result<(), BlockchainError> {
+        self.handshake
+            .get_or_try_init(|| async {
+                let block = with_timeout(self.timeouts.read_call(), "block_number", self.provider.block_number()).await?;
+                println!("[ASP] 🔌 Connected to RPC (latest block {})", block);
+                Ok::<u64, BlockchainError>(block)
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Block the Zylith contract was deployed at (first block worth scanning for events)
+    /// Discovered with a binary search on the first call, then cached
+    pub async fn deployment_block(&self) -> Result<u64, BlockchainError> {
+        self.deployment_block
+            .get_or_try_init(|| async {
+                // A few dozen sequential lookups: runs under the event_page budget like scans
+                let block = with_timeout(self.timeouts.event_page(), "find_deployment_block", async {
+                    let latest_block = self.provider.block_number().await?;
+                    find_deployment_block(&self.provider, self.zylith_address, latest_block).await
+                })
+                .await?;
+                println!("[ASP] 📍 Zylith contract deployed at block {}", block);
+                Ok::<u64, BlockchainError>(block)
+            })
             .await
-            .map_err(|e| format!("Failed to call get_merkle_root: {}", e))?;
+            .copied()
+    }
+
+    /// Confirm a contract is actually deployed at the configured Zylith address
+    /// Returns ContractNotFound for the zero address or when the node has no class there
+    pub async fn ensure_contract_deployed(&self) -> Result<(), BlockchainError> {
+        let address = format!("0x{:x}", self.zylith_address);
+        if self.zylith_address == FieldElement::ZERO {
+            return Err(BlockchainError::ContractNotFound(address));
+        }
+
+        let class_hash = with_timeout(
+            self.timeouts.read_call(),
+            "get_class_hash_at",
+            self.provider.get_class_hash_at(BlockId::Tag(BlockTag::Latest), self.zylith_address),
+        )
+        .await?;
+
+        if class_hash == FieldElement::ZERO {
+            return Err(BlockchainError::ContractNotFound(address));
+        }
+
+        Ok(())
+    }
+
+    /// Address and class hash of the Garaga verifier Zylith calls for `circuit`
+    /// The verifiers are constructor arguments without getters; `<circuit>_verifier` is read from storage
+    pub async fn verifier_class_hash(&self, circuit: &str) -> Result<(FieldElement, FieldElement), BlockchainError> {
+        let field = format!("{}_verifier", circuit);
+        let verifier = self.read_storage(storage_address_of(&field, &[])).await?;
+        if verifier == FieldElement::ZERO {
+            return Err(BlockchainError::MissingData(format!("{} is not set on the Zylith contract", field)));
+        }
+        let class_hash = with_timeout(
+            self.timeouts.read_call(),
+            "get_class_hash_at",
+            self.provider.get_class_hash_at(BlockId::Tag(BlockTag::Latest), verifier),
+        )
+        .await?;
+        Ok((verifier, class_hash))
+    }
+}
+
+impl BlockchainClient {
+    pub fn new(rpc_url: &str, zylith_address: &str) -> Result<Self, BlockchainError> {
+        Self::builder(rpc_url, zylith_address).build()
+    }
+
+    pub fn builder(rpc_url: &str, zylith_address: &str) -> BlockchainClientBuilder {
+        BlockchainClientBuilder::new(rpc_url, zylith_address)
+    }
+
+    /// Same as `new`, but also checks that a contract lives at the Zylith address
+    /// This catches the "wrong address / wrong network" misconfiguration at startup
+    /// instead of on the first contract call
+    pub async fn new_checked(rpc_url: &str, zylith_address: &str) -> Result<Self, BlockchainError> {
+        Self::builder(rpc_url, zylith_address).build_checked().await
+    }
+}
+
+impl<P: StarknetProvider> BlockchainClient<P> {
+
+    /// Get Merkle root from contract
+    pub async fn get_merkle_root(&self) -> Result<String, BlockchainError> {
+        self.get_merkle_root_at(BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Merkle root as of `block`, e.g. to check a root a proof was built against
+    pub async fn get_merkle_root_at(&self, block: BlockId) -> Result<String, BlockchainError> {
+        let result = self.call_view_at("get_merkle_root", vec![], block).await?;
 
         if result.is_empty() {
-            return Err("Empty response from get_merkle_root".to_string());
+            return Err(BlockchainError::Decode("Empty response from get_merkle_root".to_string()));
         }
 
-        Ok(format!("0x{:x}", result[0]))
+        Ok(format_felt(result[0], self.root_format))
+    }
+
+    /// Merkle root as of the pending block when pending reads are on, else the latest one
+    pub async fn get_merkle_root_labeled(&self) -> Result<(String, Confirmation), BlockchainError> {
+        if self.include_pending {
+            let root = self.get_merkle_root_at(BlockId::Tag(BlockTag::Pending)).await?;
+            Ok((root, Confirmation::Unconfirmed))
+        } else {
+            Ok((self.get_merkle_root().await?, Confirmation::Confirmed))
+        }
     }
 
     /// Check if nullifier is spent
-    pub async fn is_nullifier_spent(&self, nullifier: &str) -> Result<bool, String> {
+    /// Spent results are cached until evicted, unspent ones only for a short TTL
+    pub async fn is_nullifier_spent(&self, nullifier: &str) -> Result<bool, BlockchainError> {
         let nullifier_felt = parse_felt(nullifier)?;
 
-        let call = FunctionCall {
-            contract_address: self.zylith_address,
-            entry_point_selector: get_selector("is_nullifier_spent"),
-            calldata: vec![nullifier_felt],
-        };
+        if let Some(spent) = self.nullifier_cache.lock().unwrap().get(&nullifier_felt) {
+            return Ok(spent);
+        }
 
-        let result = self.provider
-            .call(call, BlockId::Tag(BlockTag::Latest))
-            .await
-            .map_err(|e| format!("Failed to call is_nullifier_spent: {}", e))?;
+        let result = self.call_view("is_nullifier_spent", vec![nullifier_felt]).await?;
 
         if result.is_empty() {
-            return Err("Empty response from is_nullifier_spent".to_string());
+            return Err(BlockchainError::Decode("Empty response from is_nullifier_spent".to_string()));
         }
 
         // Cairo bool: 0 = false, 1 = true
-        Ok(result[0] != FieldElement::ZERO)
+        let spent = result[0] != FieldElement::ZERO;
+        self.nullifier_cache.lock().unwrap().insert(nullifier_felt, spent);
+        Ok(spent)
+    }
+
+    /// Nullifier status at `block`, bypassing the cache for reads
+    /// A nullifier spent at any block stays spent, so positive answers still feed the cache
+    pub async fn is_nullifier_spent_at(&self, nullifier: &str, block: BlockId) -> Result<bool, BlockchainError> {
+        let nullifier_felt = parse_felt(nullifier)?;
+
+        let result = self.call_view_at("is_nullifier_spent", vec![nullifier_felt], block).await?;
+        let spent = *result
+            .first()
+            .ok_or_else(|| BlockchainError::Decode("Empty response from is_nullifier_spent".to_string()))?
+            != FieldElement::ZERO;
+        if spent {
+            self.nullifier_cache.lock().unwrap().insert(nullifier_felt, true);
+        }
+        Ok(spent)
+    }
+
+    /// Check several nullifiers with a single batched request
+    /// Cached results are served locally; only the rest goes to the node
+    pub async fn are_nullifiers_spent(&self, nullifiers: &[&str]) -> Result<Vec<bool>, BlockchainError> {
+        let felts = nullifiers.iter().map(|n| parse_felt(n)).collect::<Result<Vec<_>, _>>()?;
+
+        let mut spent: Vec<Option<bool>> = {
+            let mut cache = self.nullifier_cache.lock().unwrap();
+            felts.iter().map(|felt| cache.get(felt)).collect()
+        };
+        let missing: Vec<usize> = (0..felts.len()).filter(|i| spent[*i].is_none()).collect();
+        let calls = missing
+            .iter()
+            .map(|i| FunctionCall {
+                contract_address: self.zylith_address,
+                entry_point_selector: get_selector("is_nullifier_spent"),
+                calldata: vec![felts[*i]],
+            })
+            .collect();
+
+        let results = self.batch_call(calls).await?;
+        for (i, result) in missing.into_iter().zip(results) {
+            let value = *result?.first().ok_or_else(|| {
+                BlockchainError::Decode("Empty response from is_nullifier_spent".to_string())
+            })?;
+            let is_spent = value != FieldElement::ZERO;
+            self.nullifier_cache.lock().unwrap().insert(felts[i], is_spent);
+            spent[i] = Some(is_spent);
+        }
+
+        Ok(spent.into_iter().map(|s| s.unwrap_or(false)).collect())
+    }
+
+    /// Call several view functions at the latest block in one round trip
+    /// Sent as a single JSON-RPC batch when the node supports it, one by one otherwise;
+    /// each call gets its own result, so one revert doesn't fail the others
+    pub async fn batch_call(&self, calls: Vec<FunctionCall>) -> Result<Vec<CallResult>, BlockchainError> {
+        self.batch_call_at(calls, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Same as `batch_call`, with every call evaluated at `block`
+    pub async fn batch_call_at(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        with_timeout(self.timeouts.read_call(), "batch call", self.provider.batch_call(calls, block)).await
+    }
+
+    /// Latest block number known to the node
+    pub async fn latest_block_number(&self) -> Result<u64, BlockchainError> {
+        with_timeout(self.timeouts.read_call(), "block_number", self.provider.block_number()).await
+    }
+
+    /// Start a group of view calls evaluated together at one block
+    pub fn multicall(&self) -> Multicall<'_, P> {
+        Multicall::new(self, self.multicall_aggregator)
+    }
+
+    /// Scope reads to `block`, so related reads see the same chain state
+    pub fn at_block(&self, block: BlockId) -> PinnedClient<'_, P> {
+        PinnedClient::new(self, block)
+    }
+
+    /// Resolve the latest block number once and scope reads to it
+    pub async fn pin_latest(&self) -> Result<PinnedClient<'_, P>, BlockchainError> {
+        let block_number = self.latest_block_number().await?;
+        Ok(self.at_block(BlockId::Number(block_number)))
     }
 
     /// Check if root is known (historical root)
-    pub async fn is_root_known(&self, root: &str) -> Result<bool, String> {
+    pub async fn is_root_known(&self, root: &str) -> Result<bool, BlockchainError> {
+        self.is_root_known_at(root, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Whether `root` was an accepted root as of `block`
+    pub async fn is_root_known_at(&self, root: &str, block: BlockId) -> Result<bool, BlockchainError> {
         let root_felt = parse_felt(root)?;
 
+        let result = self.call_view_at("is_root_known", vec![root_felt], block).await?;
+
+        if result.is_empty() {
+            return Err(BlockchainError::Decode("Empty response from is_root_known".to_string()));
+        }
+
+        Ok(result[0] != FieldElement::ZERO)
+    }
+
+    /// Tell whether `root` is the current root, an accepted historical one, or unknown
+    /// Relayers prefer the current root but can still prove against a recent one
+    pub async fn classify_root(&self, root: &str) -> Result<RootStatus, BlockchainError> {
+        self.classify_root_at(root, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Same as `classify_root`, with both reads evaluated at `block`
+    pub async fn classify_root_at(&self, root: &str, block: BlockId) -> Result<RootStatus, BlockchainError> {
+        let root_felt = FieldElement::from_hex_be(root)
+            .map_err(|e| BlockchainError::InvalidInput(format!("root '{}': {}", root, e)))?;
+
+        let current = self.call_view_at("get_merkle_root", vec![], block).await?;
+        if current.first() == Some(&root_felt) {
+            return Ok(RootStatus::Current);
+        }
+
+        let known = self.call_view_at("is_root_known", vec![root_felt], block).await?;
+        match known.first() {
+            Some(value) if *value != FieldElement::ZERO => Ok(RootStatus::Historical),
+            Some(_) => Ok(RootStatus::Unknown),
+            None => Err(BlockchainError::Decode("Empty response from is_root_known".to_string())),
+        }
+    }
+
+    /// Call a Zylith view function at the latest block, under the read_call timeout
+    async fn call_view(&self, function: &str, calldata: Vec<FieldElement>) -> Result<Vec<FieldElement>, BlockchainError> {
+        self.call_view_at(function, calldata, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Call a Zylith view function at `block`, under the read_call timeout
+    async fn call_view_at(&self, function: &str, calldata: Vec<FieldElement>, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
         let call = FunctionCall {
             contract_address: self.zylith_address,
-            entry_point_selector: get_selector("is_root_known"),
-            calldata: vec![root_felt],
+            entry_point_selector: get_selector(function),
+            calldata,
         };
 
-        let result = self.provider
-            .call(call, BlockId::Tag(BlockTag::Latest))
-            .await
-            .map_err(|e| format!("Failed to call is_root_known: {}", e))?;
+        self.cached_call(call, block, function).await
+    }
 
-        if result.is_empty() {
-            return Err("Empty response from is_root_known".to_string());
+    /// View call under the read_call timeout, served from the response cache when possible
+    async fn cached_call(&self, call: FunctionCall, block: BlockId, operation: &str) -> Result<Vec<FieldElement>, BlockchainError> {
+        if let Some(result) = self.response_cache.lock().unwrap().get(&call, block) {
+            return Ok(result);
         }
 
-        Ok(result[0] != FieldElement::ZERO)
+        let result = with_timeout(self.timeouts.read_call(), operation, self.provider.call(call.clone(), block)).await?;
+        self.response_cache.lock().unwrap().insert(&call, block, result.clone());
+        Ok(result)
+    }
+
+    /// Check a locally built tree against the contract's current root
+    /// Returns Ok(true) on match and RootMismatch otherwise, so a wrong hash, leaf order
+    /// or depth is reported instead of producing proofs the contract rejects
+    pub async fn verify_local_root(&self, tree: &MerkleTree) -> Result<bool, BlockchainError> {
+        self.verify_local_root_at(tree, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Check a locally built tree against the contract root as of `block`
+    pub async fn verify_local_root_at(&self, tree: &MerkleTree, block: BlockId) -> Result<bool, BlockchainError> {
+        let result = self.call_view_at("get_merkle_root", vec![], block).await?;
+        let on_chain = result
+            .first()
+            .ok_or_else(|| BlockchainError::Decode("Empty response from get_merkle_root".to_string()))?;
+
+        let local = format!("0x{:x}", tree.get_root());
+        let on_chain = format!("0x{:x}", on_chain);
+        if local != on_chain {
+            return Err(BlockchainError::RootMismatch { local, on_chain });
+        }
+        Ok(true)
+    }
+
+    /// Root the contract will hold once `new_leaf` is deposited on top of `tree`
+    /// Lets a UI show "your deposit will produce root X" and confirm it against the chain afterwards
+    pub fn preview_root_after(&self, tree: &MerkleTree, new_leaf: FieldElement) -> FieldElement {
+        let root = tree.preview_append(BigUint::from_bytes_be(&new_leaf.to_bytes_be())).expect("felts are valid leaves");
+        FieldElement::from_byte_slice_be(&root.to_bytes_be()).expect("Merkle root must be a felt252")
+    }
+
+    /// Number of historical roots the contract accepts
+    /// Zylith keeps every root ever produced, counted by `get_known_roots_count`;
+    /// deployments without that getter fall back to the configured default
+    pub async fn get_roots_history_size(&self) -> Result<u32, BlockchainError> {
+        match self.call_view("get_known_roots_count", vec![]).await {
+            Ok(result) => {
+                let count = result.first().ok_or_else(|| {
+                    BlockchainError::Decode("Empty response from get_known_roots_count".to_string())
+                })?;
+                felt_to_u32(*count)
+            }
+            Err(BlockchainError::EntryPointNotFound(_)) => {
+                eprintln!(
+                    "[ASP] ⚠️  Contract has no get_known_roots_count, assuming {} historical roots",
+                    self.roots_history_fallback
+                );
+                Ok(self.roots_history_fallback)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Roots the contract currently accepts, oldest first: the newest `get_roots_history_size`
+    /// of the initial empty root (0) and the roots carried by Deposit events
+    /// `known_roots` is a map and can't be enumerated from storage, so roots are rebuilt from
+    /// the events. Swap and LP inserts produce roots too but their events don't carry them:
+    /// those roots are missing from the list, and it may reach back further than the contract
+    /// when it counts them in its history.
+    pub async fn get_known_roots(&self) -> Result<Vec<FieldElement>, BlockchainError> {
+        use starknet::core::types::EventFilter;
+
+        let history_size = self.get_roots_history_size().await? as usize;
+        let mut roots = std::collections::VecDeque::with_capacity(history_size + 1);
+        if history_size == 0 {
+            return Ok(Vec::new());
+        }
+        roots.push_back(FieldElement::ZERO);
+
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(self.deployment_block().await?)),
+            to_block: Some(BlockId::Tag(BlockTag::Latest)),
+            address: Some(self.zylith_address),
+            keys: None,
+        };
+        let mut continuation_token = None;
+
+        loop {
+            let page = self
+                .event_pages
+                .next_page(|page_size| {
+                    with_timeout(
+                        self.timeouts.event_page(),
+                        "event page",
+                        self.provider.get_events(filter.clone(), continuation_token, page_size),
+                    )
+                })
+                .await?;
+
+            for event in &page.events {
+                if let Some(deposit) = DepositEvent::decode(&event.keys, &event.data)? {
+                    // Older roots fall out of the contract's bounded history
+                    roots.push_back(deposit.root);
+                    if roots.len() > history_size {
+                        roots.pop_front();
+                    }
+                }
+            }
+
+            continuation_token = page.continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(roots.into())
     }
 
     /// Get token balance (ERC20) - returns (low, high) for u256
@@ -95,48 +938,24 @@ impl BlockchainClient {
         &self,
         token_address: &str,
         owner: &str,
-    ) -> Result<(u128, u128), String> {
+    ) -> Result<(u128, u128), BlockchainError> {
+        self.get_token_balance_at(token_address, owner, BlockId::Tag(BlockTag::Latest)).await
+    }
+
+    /// Token balance as of `block` - returns (low, high) for u256
+    pub async fn get_token_balance_at(
+        &self,
+        token_address: &str,
+        owner: &str,
+        block: BlockId,
+    ) -> Result<(u128, u128), BlockchainError> {
         let token_addr = parse_felt(token_address)?;
         let owner_addr = parse_felt(owner)?;
 
-        // ERC20 uses balance_of (snake_case in Cairo)
-        let call = FunctionCall {
-            contract_address: token_addr,
-            entry_point_selector: get_selector("balance_of"),
-            calldata: vec![owner_addr],
-        };
-
-        let result = self.provider
-            .call(call, BlockId::Tag(BlockTag::Latest))
-            .await
-            .map_err(|e| format!("Failed to call balance_of: {}", e))?;
-
-        if result.len() < 2 {
-            return Err("Invalid response from balance_of (expected u256)".to_string());
-        }
-
-        // u256 is returned as [low, high]
-        let low = result[0];
-        let high = result[1];
-        
-        // Convert FieldElement to u128
-        let low_bytes = low.to_bytes_be();
-        let high_bytes = high.to_bytes_be();
-        
-        let low_u128 = u128::from_be_bytes([
-            low_bytes[16], low_bytes[17], low_bytes[18], low_bytes[19],
-            low_bytes[20], low_bytes[21], low_bytes[22], low_bytes[23],
-            low_bytes[24], low_bytes[25], low_bytes[26], low_bytes[27],
-            low_bytes[28], low_bytes[29], low_bytes[30], low_bytes[31],
-        ]);
-        let high_u128 = u128::from_be_bytes([
-            high_bytes[16], high_bytes[17], high_bytes[18], high_bytes[19],
-            high_bytes[20], high_bytes[21], high_bytes[22], high_bytes[23],
-            high_bytes[24], high_bytes[25], high_bytes[26], high_bytes[27],
-            high_bytes[28], high_bytes[29], high_bytes[30], high_bytes[31],
-        ]);
-
-        Ok((low_u128, high_u128))
+        let balance = self
+            .erc20_u256(token_addr, "balance_of", &[AbiValue::ContractAddress(owner_addr)], block)
+            .await?;
+        Ok((balance.low, balance.high))
     }
 
     /// Get token allowance (ERC20) - returns (low, high) for u256
@@ -145,233 +964,479 @@ impl BlockchainClient {
         token_address: &str,
         owner: &str,
         spender: &str,
-    ) -> Result<(u128, u128), String> {
+    ) -> Result<(u128, u128), BlockchainError> {
         let token_addr = parse_felt(token_address)?;
         let owner_addr = parse_felt(owner)?;
         let spender_addr = parse_felt(spender)?;
 
-        let call = FunctionCall {
-            contract_address: token_addr,
-            entry_point_selector: get_selector("allowance"),
-            calldata: vec![owner_addr, spender_addr],
-        };
+        let allowance = self
+            .erc20_u256(
+                token_addr,
+                "allowance",
+                &[AbiValue::ContractAddress(owner_addr), AbiValue::ContractAddress(spender_addr)],
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await?;
+        Ok((allowance.low, allowance.high))
+    }
+
+    /// Check if pool is initialized
+    pub async fn is_pool_initialized(&self) -> Result<bool, BlockchainError> {
+        let initialized_selector = storage_address_of("initialized", &[]);
+        
+        let storage_value = with_timeout(
+            self.timeouts.storage_read(),
+            "storage read",
+            self.provider.get_storage_at(self.zylith_address, initialized_selector, BlockId::Tag(BlockTag::Latest)),
+        )
+        .await?;
+
+        // Cairo bool: 0 = false, 1 = true
+        Ok(storage_value != FieldElement::ZERO)
+    }
+
+    /// Get pool token0 address
+    pub async fn get_pool_token0(&self) -> Result<String, BlockchainError> {
+        self.get_pool_token("token0").await
+    }
+
+    /// Get pool token1 address
+    pub async fn get_pool_token1(&self) -> Result<String, BlockchainError> {
+        self.get_pool_token("token1").await
+    }
+
+    /// Read `pool.token0` / `pool.token1` from storage
+    /// The contract has no token getters; the storage-node slot is the only source, decoded as a ContractAddress
+    async fn get_pool_token(&self, field: &str) -> Result<String, BlockchainError> {
+        if !self.is_pool_initialized().await? {
+            return Err(BlockchainError::PoolNotInitialized);
+        }
+
+        let raw = self.read_pool_field(field).await?;
+        match AbiType::ContractAddress.decode(&mut &[raw][..])? {
+            AbiValue::ContractAddress(address) if address != FieldElement::ZERO => {
+                Ok(format_felt(address, self.address_format))
+            }
+            _ => Err(BlockchainError::MissingData(format!(
+                "pool.{} is zero at storage address 0x{:x}; the initialization transaction may not be confirmed yet",
+                field,
+                pool_field_address(field)
+            ))),
+        }
+    }
 
-        let result = self.provider
-            .call(call, BlockId::Tag(BlockTag::Latest))
+    /// Get pool reserves (token balances held by Zylith) and active liquidity
+    pub async fn get_pool_reserves(&self) -> Result<PoolReserves, BlockchainError> {
+        if !self.is_pool_initialized().await? {
+            return Err(BlockchainError::PoolNotInitialized);
+        }
+
+        let token0 = self.read_pool_field("token0").await?;
+        let token1 = self.read_pool_field("token1").await?;
+        // pool.liquidity is a u128, stored in a single slot
+        let liquidity = felt_to_u128(self.read_pool_field("liquidity").await?)?;
+
+        // The contract custodies all pool tokens, so its balances are the reserves
+        let reserve0 = self.balance_of(token0, self.zylith_address).await?;
+        let reserve1 = self.balance_of(token1, self.zylith_address).await?;
+
+        Ok(PoolReserves {
+            reserve0,
+            reserve1,
+            total_liquidity: U256::from(liquidity),
+        })
+    }
+
+    /// Current pool price, `pool.sqrt_price_x128`
+    pub async fn get_pool_sqrt_price_x128(&self) -> Result<U256, BlockchainError> {
+        if !self.is_pool_initialized().await? {
+            return Err(BlockchainError::PoolNotInitialized);
+        }
+        self.read_pool_u256("sqrt_price_x128").await
+    }
+
+    /// Read a single-slot member of the `pool` storage node
+    async fn read_pool_field(&self, field: &str) -> Result<FieldElement, BlockchainError> {
+        self.read_storage(pool_field_address(field)).await
+    }
+
+    /// Read a u256 member of the `pool` storage node: low word at the member address, high word in the next slot
+    async fn read_pool_u256(&self, field: &str) -> Result<U256, BlockchainError> {
+        let address = pool_field_address(field);
+        let low = self.read_storage(address).await?;
+        let high = self.read_storage(slot_offset(address, 1)).await?;
+        U256::from_felts(low, high)
+    }
+
+    async fn read_storage(&self, address: FieldElement) -> Result<FieldElement, BlockchainError> {
+        with_timeout(
+            self.timeouts.storage_read(),
+            "storage read",
+            self.provider.get_storage_at(self.zylith_address, address, BlockId::Tag(BlockTag::Latest)),
+        )
+        .await
+    }
+
+    /// ERC20 balance_of, decoded as u256
+    async fn balance_of(&self, token: FieldElement, owner: FieldElement) -> Result<U256, BlockchainError> {
+        self.erc20_u256(token, "balance_of", &[AbiValue::ContractAddress(owner)], BlockId::Tag(BlockTag::Latest))
             .await
-            .map_err(|e| format!("Failed to call allowance: {}", e))?;
+    }
+
+    /// Call an ERC20 view returning a single u256, encoded and decoded from the ERC20 ABI
+    async fn erc20_u256(
+        &self,
+        token: FieldElement,
+        function: &str,
+        args: &[AbiValue],
+        block: BlockId,
+    ) -> Result<U256, BlockchainError> {
+        let function = erc20_function(function)?;
+        let call = function.call(token, args)?;
+
+        let result = self.cached_call(call, block, &function.name).await?;
+
+        match function.decode_output(&result)?.as_slice() {
+            [AbiValue::U256(value)] => Ok(*value),
+            _ => Err(BlockchainError::Decode(format!("Invalid response from {} (expected u256)", function.name))),
+        }
+    }
+
+    /// Get the leaf index of the deposit made by a transaction, from its receipt
+    /// Much cheaper than find_commitment_in_events when the tx hash is known
+    pub async fn deposit_index_for_tx(&self, tx_hash: &str) -> Result<u32, BlockchainError> {
+        let tx_hash_felt = FieldElement::from_hex_be(tx_hash)
+            .map_err(|e| BlockchainError::InvalidInput(format!("tx hash '{}': {}", tx_hash, e)))?;
+
+        let receipt = with_timeout(
+            self.timeouts.read_call(),
+            "get_transaction_receipt",
+            self.provider.get_transaction_receipt(tx_hash_felt),
+        )
+        .await?;
+        if receipt.pending {
+            return Err(BlockchainError::TransactionPending(format!("0x{:x}", tx_hash_felt)));
+        }
+
+        let deposit = deposit_from_receipt(&receipt, self.zylith_address)?.ok_or_else(|| {
+            BlockchainError::NoDepositEvent(format!("0x{:x}", tx_hash_felt))
+        })?;
+        Ok(deposit.leaf_index)
+    }
+
+    /// The Deposit a transaction made, with its amount when tokens were transferred in; None if it made none
+    pub async fn deposit_of_tx(&self, tx_hash: FieldElement) -> Result<Option<DepositEvent>, BlockchainError> {
+        let receipt = with_timeout(
+            self.timeouts.read_call(),
+            "get_transaction_receipt",
+            self.provider.get_transaction_receipt(tx_hash),
+        )
+        .await?;
+        deposit_from_receipt(&receipt, self.zylith_address)
+    }
+
+    /// Find the leaf index of a commitment, optionally waiting for its deposit to be mined
+    /// Does one scan up to the latest block, then (with `opts.wait`) polls only new blocks
+    pub async fn resolve_commitment(&self, commitment: &str, opts: ResolveOpts) -> Result<u32, BlockchainError> {
+        let commitment_felt = FieldElement::from_hex_be(commitment)
+            .map_err(|e| BlockchainError::InvalidInput(format!("commitment '{}': {}", commitment, e)))?;
+        let deadline = tokio::time::Instant::now() + opts.timeout;
+        let mut from_block = match opts.from_block {
+            Some(block) => block,
+            None => self.deployment_block().await?,
+        };
+
+        loop {
+            let latest_block = with_timeout(self.timeouts.read_call(), "block_number", self.provider.block_number()).await?;
+            if latest_block >= from_block {
+                let deposits = self.scan_deposits_range(from_block, latest_block).await?;
+                if let Some(deposit) = deposits.iter().find(|d| d.commitment == commitment_felt) {
+                    println!(
+                        "[ASP] ✅ Resolved commitment at index {}",
+                        self.redaction.redact(Sensitive::LeafIndex, deposit.leaf_index)
+                    );
+                    return Ok(deposit.leaf_index);
+                }
+                from_block = latest_block + 1;
+            }
+
+            if !opts.wait {
+                return Err(BlockchainError::CommitmentNotFound(format!("0x{:x}", commitment_felt)));
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(BlockchainError::Timeout(format!(
+                    "commitment not deposited after {:?}",
+                    opts.timeout
+                )));
+            }
+            tokio::time::sleep(opts.poll_interval.min(deadline - now)).await;
+        }
+    }
+
+    /// All Deposit events of the contract up to the latest block, in leaf order
+    /// With `verify_root_after_sync`, the rebuilt tree is checked against the contract root
+    /// at the same block before returning
+    pub async fn load_all_commitments(&self) -> Result<Vec<DepositEvent>, BlockchainError> {
+        let latest_block = with_timeout(self.timeouts.read_call(), "block_number", self.provider.block_number()).await?;
+        let deposits = self.scan_deposits(latest_block).await?;
+
+        if self.verify_root_after_sync {
+            let leaves: Vec<BigUint> = leaves_from_deposits(&deposits, self.tree_config.depth)?
+                .iter()
+                .map(|leaf| BigUint::from_bytes_be(&leaf.to_bytes_be()))
+                .collect();
+            let tree = MerkleTree::from_leaves_with_hasher(self.tree_config.depth, &leaves, self.tree_config.hasher());
+            self.verify_local_root_at(&tree, BlockId::Number(latest_block)).await?;
+        }
+
+        Ok(deposits)
+    }
+
+    /// Snapshot of the deposit tree rebuilt from Deposit events
+    /// The root is the one the contract reported with the last deposit
+    pub async fn export_snapshot(&self) -> Result<TreeSnapshot, BlockchainError> {
+        let latest_block = with_timeout(self.timeouts.read_call(), "block_number", self.provider.block_number()).await?;
+        let deposits = self.scan_deposits(latest_block).await?;
+
+        let leaves = leaves_from_deposits(&deposits, self.tree_config.depth)?;
+        let root = deposits.last().map_or(FieldElement::ZERO, |d| d.root);
+
+        Ok(TreeSnapshot {
+            depth: self.tree_config.depth,
+            leaves,
+            root: format!("0x{:x}", root),
+            synced_block: latest_block,
+        })
+    }
+
+    /// Check the configured tree depth and hashing against the contract
+    /// An empty contract must report the empty-tree root; otherwise the root emitted with the
+    /// first deposit must be the one-leaf tree of its commitment. A mismatch means the ASP
+    /// would build proofs against roots the contract never knew
+    pub async fn validate_tree_config(&self) -> Result<(), BlockchainError> {
+        let config = self.tree_config;
+        let described = format!(
+            "depth {}, {:?} hash{}",
+            config.depth,
+            config.hash,
+            config.domain.map_or(String::new(), |domain| format!(", domain 0x{:x}", domain))
+        );
+        let on_chain = *self
+            .call_view_at("get_merkle_root", vec![], BlockId::Tag(BlockTag::Latest))
+            .await?
+            .first()
+            .ok_or_else(|| BlockchainError::Decode("Empty response from get_merkle_root".to_string()))?;
+        let empty_root = config.empty_root();
+        if on_chain == empty_root {
+            println!("[ASP] 🌳 Deposit tree is empty, config {} matches", described);
+            return Ok(());
+        }
+
+        let first = match self.first_deposit().await? {
+            Some(deposit) => deposit,
+            None => {
+                return Err(BlockchainError::Config(format!(
+                    "contract root 0x{:x} is not the empty-tree root 0x{:x} of {} and no deposit was found",
+                    on_chain, empty_root, described
+                )))
+            }
+        };
+        let expected = config.root_after_first_leaf(&first.commitment);
+        if expected != first.root {
+            return Err(BlockchainError::Config(format!(
+                "tree config {} gives root 0x{:x} after the first deposit, the contract emitted 0x{:x}",
+                described, expected, first.root
+            )));
+        }
+        println!("[ASP] 🌳 Deposit tree config {} matches the contract", described);
+        Ok(())
+    }
+
+    /// Deposit event of leaf 0, scanning forward from deployment in windows
+    async fn first_deposit(&self) -> Result<Option<DepositEvent>, BlockchainError> {
+        const WINDOW: u64 = 10_000;
+        let latest_block = self.latest_block_number().await?;
+        let mut from = self.deployment_block().await?;
+        while from <= latest_block {
+            let to = from.saturating_add(WINDOW - 1).min(latest_block);
+            let deposits = self.scan_deposits_range(from, to).await?;
+            if let Some(first) = deposits.into_iter().find(|deposit| deposit.leaf_index == 0) {
+                return Ok(Some(first));
+            }
+            from = to + 1;
+        }
+        Ok(None)
+    }
+
+    /// Deposit events of one block, read from execution traces
+    /// Traces include events of inner and library calls that some providers don't surface
+    /// in starknet_getEvents; falls back to get_events if the node can't trace, any other
+    /// failure (timeout, transport) is returned so the caller retries the block
+    pub async fn scan_deposits_via_trace(&self, block: u64) -> Result<Vec<DepositEvent>, BlockchainError> {
+        let traces = match with_timeout(self.timeouts.event_page(), "trace_block", self.provider.trace_block(block)).await {
+            Ok(traces) => traces,
+            Err(e @ (BlockchainError::Unsupported(_) | BlockchainError::EntryPointNotFound(_))) => {
+                eprintln!("[ASP] ⚠️  Tracing unavailable for block {} ({}), falling back to get_events", block, e);
+                return self.scan_deposits_range(block, block).await;
+            }
+            Err(e) => return Err(e),
+        };
 
-        if result.len() < 2 {
-            return Err("Invalid response from allowance (expected u256)".to_string());
+        let mut deposits = Vec::new();
+        for trace in &traces {
+            for event in trace.flatten_events() {
+                if event.from_address != self.zylith_address {
+                    continue;
+                }
+                if let Some(deposit) = DepositEvent::decode(&event.keys, &event.data)? {
+                    deposits.push(deposit);
+                }
+            }
         }
+        deposits.sort_by_key(|d| d.leaf_index);
+        deposits.dedup_by_key(|d| d.leaf_index);
+        Ok(deposits)
+    }
 
-        // u256 is returned as [low, high]
-        let low = result[0];
-        let high = result[1];
-        
-        let low_bytes = low.to_bytes_be();
-        let high_bytes = high.to_bytes_be();
-        
-        let low_u128 = u128::from_be_bytes([
-            low_bytes[16], low_bytes[17], low_bytes[18], low_bytes[19],
-            low_bytes[20], low_bytes[21], low_bytes[22], low_bytes[23],
-            low_bytes[24], low_bytes[25], low_bytes[26], low_bytes[27],
-            low_bytes[28], low_bytes[29], low_bytes[30], low_bytes[31],
-        ]);
-        let high_u128 = u128::from_be_bytes([
-            high_bytes[16], high_bytes[17], high_bytes[18], high_bytes[19],
-            high_bytes[20], high_bytes[21], high_bytes[22], high_bytes[23],
-            high_bytes[24], high_bytes[25], high_bytes[26], high_bytes[27],
-            high_bytes[28], high_bytes[29], high_bytes[30], high_bytes[31],
-        ]);
-
-        Ok((low_u128, high_u128))
+    /// Scan Deposit events from deployment up to `to_block`, sorted and deduplicated by leaf index
+    pub(crate) async fn scan_deposits(&self, to_block: u64) -> Result<Vec<DepositEvent>, BlockchainError> {
+        self.scan_deposits_range(self.deployment_block().await?, to_block).await
     }
 
-    /// Check if pool is initialized
-    pub async fn is_pool_initialized(&self) -> Result<bool, String> {
-        // Check initialized field: sn_keccak("initialized")
-        let initialized_selector = starknet_keccak("initialized".as_bytes());
-        
-        let storage_value = self.provider
-            .get_storage_at(self.zylith_address, initialized_selector, BlockId::Tag(BlockTag::Latest))
-            .await
-            .map_err(|e| format!("Failed to read initialized storage: {}", e))?;
+    /// Scan Deposit events in [from_block, to_block], sorted and deduplicated by leaf index
+    async fn scan_deposits_range(&self, from_block: u64, to_block: u64) -> Result<Vec<DepositEvent>, BlockchainError> {
+        use starknet::core::types::EventFilter;
 
-        // Cairo bool: 0 = false, 1 = true
-        Ok(storage_value != FieldElement::ZERO)
-    }
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(from_block)),
+            to_block: Some(BlockId::Number(to_block)),
+            address: Some(self.zylith_address),
+            keys: None,
+        };
+        let mut continuation_token = None;
+        let mut deposits = Vec::new();
 
-    /// Get pool token0 address by reading storage directly
-    /// In Cairo, for storage nodes, the address calculation is complex.
-    /// We try multiple methods: pedersen_hash and direct base address
-    pub async fn get_pool_token0(&self) -> Result<String, String> {
-        // First check if pool is initialized
-        let is_initialized = self.is_pool_initialized().await
-            .map_err(|e| format!("Failed to check if pool is initialized: {}", e))?;
-        
-        if !is_initialized {
-            return Err("Pool is not initialized. Please initialize the pool first.".to_string());
-        }
+        loop {
+            let page = self
+                .event_pages
+                .next_page(|page_size| {
+                    with_timeout(
+                        self.timeouts.event_page(),
+                        "event page",
+                        self.provider.get_events(filter.clone(), continuation_token, page_size),
+                    )
+                })
+                .await?;
 
-        let pool_base = starknet_keccak("pool".as_bytes());
-        let token0_field = starknet_keccak("token0".as_bytes());
-        
-        // Method 1: Try pedersen_hash (standard for storage nodes)
-        let pool_base_crypto = CryptoFieldElement::from_bytes_be(&pool_base.to_bytes_be())
-            .map_err(|e| format!("Failed to convert pool_base: {}", e))?;
-        let token0_field_crypto = CryptoFieldElement::from_bytes_be(&token0_field.to_bytes_be())
-            .map_err(|e| format!("Failed to convert token0_field: {}", e))?;
-        
-        let storage_address_pedersen = pedersen_hash(&pool_base_crypto, &token0_field_crypto);
-        let storage_address1 = FieldElement::from_bytes_be(&storage_address_pedersen.to_bytes_be())
-            .map_err(|e| format!("Failed to convert pedersen result: {}", e))?;
-        
-        // Method 2: Try direct base (first field in storage node)
-        let storage_address2 = pool_base;
-        
-        // Method 3: Try base + field (alternative calculation)
-        let storage_address3 = pool_base + token0_field;
-        
-        // Try pedersen_hash first (most likely correct for storage nodes)
-        // Use tokio::time::timeout to avoid hanging on slow RPC calls
-        match tokio::time::timeout(
-            tokio::time::Duration::from_secs(5),
-            self.provider.get_storage_at(self.zylith_address, storage_address1, BlockId::Tag(BlockTag::Latest))
-        ).await {
-            Ok(Ok(value)) if value != FieldElement::ZERO => {
-                // Normalize to 64 hex chars (remove leading zeros)
-                let hex_str = format!("{:064x}", value);
-                // Remove leading zeros but keep at least one char
-                let trimmed = hex_str.trim_start_matches('0');
-                let normalized = if trimmed.is_empty() { "0" } else { trimmed };
-                return Ok(format!("0x{}", normalized));
-            }
-            Ok(Ok(_)) => {
-                // Value is zero, try direct_base as fallback
-            }
-            Ok(Err(e)) => {
-                eprintln!("Warning: Failed to read storage using pedersen_hash: {}", e);
-            }
-            Err(_) => {
-                eprintln!("Warning: Timeout reading storage using pedersen_hash");
+            for event in &page.events {
+                if let Some(deposit) = DepositEvent::decode(&event.keys, &event.data)? {
+                    deposits.push(deposit);
+                }
             }
-        }
-        
-        // Fallback: Try direct_base (faster, less likely but worth trying)
-        match tokio::time::timeout(
-            tokio::time::Duration::from_secs(3),
-            self.provider.get_storage_at(self.zylith_address, storage_address2, BlockId::Tag(BlockTag::Latest))
-        ).await {
-            Ok(Ok(value)) if value != FieldElement::ZERO => {
-                let hex_str = format!("{:064x}", value);
-                let trimmed = hex_str.trim_start_matches('0');
-                let normalized = if trimmed.is_empty() { "0" } else { trimmed };
-                return Ok(format!("0x{}", normalized));
+
+            continuation_token = page.continuation_token;
+            if continuation_token.is_none() {
+                break;
             }
-            _ => {}
-        }
-        
-        // All methods failed
-        Err(format!(
-            "token0 is zero at all attempted storage addresses. This usually means:\n1. The pool initialization transaction hasn't been confirmed yet (wait 10-30 seconds)\n2. The initialization transaction failed\n3. There's a delay in state propagation\n4. The storage address calculation is incorrect\n\nPlease verify the initialization transaction was successful at https://sepolia.starkscan.co and wait a few seconds before trying again.\n\nTried addresses:\n- pedersen_hash: 0x{:x}\n- direct_base: 0x{:x}\n- base_plus_field: 0x{:x}",
-            storage_address1, storage_address2, storage_address3
-        ))
-    }
-
-    /// Get pool token1 address by reading storage directly
-    /// In Cairo, for storage nodes, the address calculation is complex.
-    /// We try multiple methods: pedersen_hash and direct base address
-    pub async fn get_pool_token1(&self) -> Result<String, String> {
-        // First check if pool is initialized
-        let is_initialized = self.is_pool_initialized().await
-            .map_err(|e| format!("Failed to check if pool is initialized: {}", e))?;
-        
-        if !is_initialized {
-            return Err("Pool is not initialized. Please initialize the pool first.".to_string());
         }
 
-        let pool_base = starknet_keccak("pool".as_bytes());
-        let token1_field = starknet_keccak("token1".as_bytes());
-        
-        // Method 1: Try pedersen_hash (standard for storage nodes)
-        let pool_base_crypto = CryptoFieldElement::from_bytes_be(&pool_base.to_bytes_be())
-            .map_err(|e| format!("Failed to convert pool_base: {}", e))?;
-        let token1_field_crypto = CryptoFieldElement::from_bytes_be(&token1_field.to_bytes_be())
-            .map_err(|e| format!("Failed to convert token1_field: {}", e))?;
-        
-        let storage_address_pedersen = pedersen_hash(&pool_base_crypto, &token1_field_crypto);
-        let storage_address1 = FieldElement::from_bytes_be(&storage_address_pedersen.to_bytes_be())
-            .map_err(|e| format!("Failed to convert pedersen result: {}", e))?;
-        
-        // Method 2: Try direct base + 1 (second field in storage node)
-        let storage_address2 = pool_base + FieldElement::ONE;
-        
-        // Try pedersen_hash first (most likely correct for storage nodes)
-        // Use tokio::time::timeout to avoid hanging on slow RPC calls
-        match tokio::time::timeout(
-            tokio::time::Duration::from_secs(5),
-            self.provider.get_storage_at(self.zylith_address, storage_address1, BlockId::Tag(BlockTag::Latest))
-        ).await {
-            Ok(Ok(value)) if value != FieldElement::ZERO => {
-                // Normalize to 64 hex chars (remove leading zeros)
-                let hex_str = format!("{:064x}", value);
-                // Remove leading zeros but keep at least one char
-                let trimmed = hex_str.trim_start_matches('0');
-                let normalized = if trimmed.is_empty() { "0" } else { trimmed };
-                return Ok(format!("0x{}", normalized));
-            }
-            Ok(Ok(_)) => {
-                // Value is zero, try direct_base_plus_one as fallback
-            }
-            Ok(Err(e)) => {
-                eprintln!("Warning: Failed to read storage using pedersen_hash: {}", e);
+        deposits.sort_by_key(|d| d.leaf_index);
+        deposits.dedup_by_key(|d| d.leaf_index);
+        Ok(deposits)
+    }
+
+    /// Deposits of the pending block, in leaf order (none unless pending reads are on)
+    pub async fn pending_deposits(&self) -> Result<Vec<DepositEvent>, BlockchainError> {
+        use starknet::core::types::EventFilter;
+
+        if !self.include_pending {
+            return Ok(Vec::new());
+        }
+        let filter = EventFilter {
+            from_block: Some(BlockId::Tag(BlockTag::Pending)),
+            to_block: Some(BlockId::Tag(BlockTag::Pending)),
+            address: Some(self.zylith_address),
+            keys: None,
+        };
+        let mut deposits = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .event_pages
+                .next_page(|page_size| {
+                    with_timeout(
+                        self.timeouts.event_page(),
+                        "event page",
+                        self.provider.get_events(filter.clone(), continuation_token, page_size),
+                    )
+                })
+                .await?;
+            // Accepted events carry a block number, pending ones don't
+            for event in page.events.iter().filter(|event| event.block_number.is_none()) {
+                if let Some(deposit) = DepositEvent::decode(&event.keys, &event.data)? {
+                    deposits.push(deposit);
+                }
             }
-            Err(_) => {
-                eprintln!("Warning: Timeout reading storage using pedersen_hash");
+            continuation_token = page.continuation_token;
+            if continuation_token.is_none() {
+                break;
             }
         }
-        
-        // Fallback: Try direct_base_plus_one (faster, less likely but worth trying)
-        match tokio::time::timeout(
-            tokio::time::Duration::from_secs(3),
-            self.provider.get_storage_at(self.zylith_address, storage_address2, BlockId::Tag(BlockTag::Latest))
-        ).await {
-            Ok(Ok(value)) if value != FieldElement::ZERO => {
-                let hex_str = format!("{:064x}", value);
-                let trimmed = hex_str.trim_start_matches('0');
-                let normalized = if trimmed.is_empty() { "0" } else { trimmed };
-                return Ok(format!("0x{}", normalized));
-            }
-            _ => {}
+        deposits.sort_by_key(|d| d.leaf_index);
+        deposits.dedup_by_key(|d| d.leaf_index);
+        Ok(deposits)
+    }
+
+    /// Leaf index of a commitment, from accepted blocks or (with pending reads) the pending block
+    pub async fn find_commitment_labeled(&self, commitment: &str) -> Result<Option<(u32, Confirmation)>, BlockchainError> {
+        if let Some(index) = self.find_commitment_in_events(commitment).await? {
+            return Ok(Some((index, Confirmation::Confirmed)));
         }
-        
-        // All methods failed
-        Err(format!(
-            "token1 is zero at all attempted storage addresses. Pool may not be properly initialized.\n\nTried addresses:\n- pedersen_hash: 0x{:x}\n- direct_base_plus_one: 0x{:x}",
-            storage_address1, storage_address2
-        ))
+        let commitment_felt = parse_felt(commitment)?;
+        Ok(self
+            .pending_deposits()
+            .await?
+            .into_iter()
+            .find(|d| d.commitment == commitment_felt)
+            .map(|d| (d.leaf_index, Confirmation::Unconfirmed)))
     }
 
     /// Search for a specific commitment in Deposit events
-    /// Returns the leaf_index if found
+    /// Returns the leaf_index if found. With a commitment index, indexed commitments are
+    /// answered locally and only the blocks the index doesn't cover yet are scanned.
     /// This is much faster than waiting for full sync when looking for a specific commitment
-    pub async fn find_commitment_in_events(&self, commitment: &str) -> Result<Option<u32>, String> {
+    pub async fn find_commitment_in_events(&self, commitment: &str) -> Result<Option<u32>, BlockchainError> {
         use starknet::core::types::EventFilter;
-        use num_bigint::BigUint;
         
         let commitment_felt = parse_felt(commitment)?;
         let commitment_bigint = BigUint::from_bytes_be(&commitment_felt.to_bytes_be());
         
-        // Deposit event selector (same as in syncer.rs)
-        let deposit_selector = FieldElement::from_hex_be("0x9149d2123147c5f43d258257fef0b7b969db78269369ebcf5ebb9eef8592f2")
-            .map_err(|e| format!("Failed to parse deposit selector: {}", e))?;
+        let deposit_selector = deposit_selector();
+
+        let indexed_up_to = match &self.commitment_index {
+            Some(index) => {
+                if let Some(leaf_index) = index.get(&commitment_felt) {
+                    println!("[ASP] ✅ Found commitment in the index at {}", self.redaction.redact(Sensitive::LeafIndex, leaf_index));
+                    return Ok(Some(leaf_index));
+                }
+                index.synced_block()
+            }
+            None => 0,
+        };
         
-        // Always search from contract deployment block to ensure we find all deposits
-        // This is critical - even if syncer missed events, we can still find them here
-        let from_block = 4438440u64;
-        let latest_block = self.provider.block_number().await
-            .map_err(|e| format!("Failed to get latest block: {}", e))?;
+        // Without an index (or before its first sync), search from the contract deployment
+        // block to find all deposits; otherwise only deposits newer than the index can be missing
+        let from_block = if indexed_up_to > 0 {
+            indexed_up_to + 1
+        } else {
+            self.deployment_block().await?
+        };
+        // The whole scan runs under the event_page budget, it is expected to be slow
+        let latest_block = with_timeout(self.timeouts.event_page(), "block_number", self.provider.block_number()).await?;
+        if from_block > latest_block {
+            return Ok(None);
+        }
         
         // Filter for all events from our contract
         // We can't filter by commitment in keys, so we'll search through all Deposit events
@@ -382,20 +1447,30 @@ impl BlockchainClient {
             keys: None, // We'll check all events and filter by Deposit selector + commitment
         };
         
-        let chunk_size = 1000;
         let mut continuation_token = None;
         let mut events_searched = 0u32;
         let mut deposit_events_found = 0u32;
+        // Pages may overlap: count every event once
+        let mut deduper = EventDeduper::new();
         
         println!("[ASP] 🔍 Searching events from block {} to {}", from_block, latest_block);
         
         loop {
-            let events_page = self.provider
-                .get_events(filter.clone(), continuation_token.clone(), chunk_size)
-                .await
-                .map_err(|e| format!("Failed to get events: {}", e))?;
+            let events_page = self
+                .event_pages
+                .next_page(|page_size| {
+                    with_timeout(
+                        self.timeouts.event_page(),
+                        "event page",
+                        self.provider.get_events(filter.clone(), continuation_token.clone(), page_size),
+                    )
+                })
+                .await?;
             
             for event in events_page.events {
+                if deduper.accept(&event).is_none() {
+                    continue;
+                }
                 events_searched += 1;
                 
                 // Check if this is a Deposit event (for nested events, selector can be in any key)
@@ -408,8 +1483,6 @@ impl BlockchainClient {
                     let event_commitment_felt = event.data[0];
                     let event_commitment_bigint = BigUint::from_bytes_be(&event_commitment_felt.to_bytes_be());
                     
-                    // Skip logging commitment details
-                    
                     if event_commitment_bigint == commitment_bigint {
                         // Found it! Extract leaf_index from data[1]
                         let leaf_index_felt = event.data[1];
@@ -421,7 +1494,7 @@ impl BlockchainClient {
                             u32::from_be_bytes(arr)
                         };
                         
-                        println!("[ASP] ✅ Found commitment in events at index {} (searched {} events, {} deposit events)", leaf_index, events_searched, deposit_events_found);
+                        println!("[ASP] ✅ Found commitment in events at index {} (searched {} events, {} deposit events)", self.redaction.redact(Sensitive::LeafIndex, leaf_index), events_searched, deposit_events_found);
                         return Ok(Some(leaf_index));
                     }
                 }
@@ -438,6 +1511,43 @@ impl BlockchainClient {
     }
 }
 
+/// Find the Deposit event emitted by the Zylith contract in a receipt
+/// Reverted transactions never count as deposits
+fn deposit_from_receipt(receipt: &TxReceipt, zylith_address: FieldElement) -> Result<Option<DepositEvent>, BlockchainError> {
+    if receipt.reverted {
+        return Ok(None);
+    }
+
+    DepositEvent::from_receipt_events(&receipt.events, zylith_address)
+}
+
+/// Leaves in index order from leaf-sorted deposits
+/// Leaf indices are contiguous in the contract, so each must fit a tree of `depth` and stay
+/// below the number of deposits; a hole left by a repeated index is filled with the empty leaf
+fn leaves_from_deposits(deposits: &[DepositEvent], depth: usize) -> Result<Vec<FieldElement>, BlockchainError> {
+    let capacity = 1u64.checked_shl(depth as u32).unwrap_or(u64::MAX);
+    let mut leaves = vec![FieldElement::ZERO; deposits.len()];
+    for deposit in deposits {
+        if u64::from(deposit.leaf_index) >= capacity {
+            return Err(BlockchainError::LeafOrder(format!(
+                "leaf index {} doesn't fit a depth-{} tree",
+                deposit.leaf_index, depth
+            )));
+        }
+        let leaf = leaves.get_mut(deposit.leaf_index as usize).ok_or_else(|| {
+            BlockchainError::LeafOrder(format!("leaf index {} after only {} deposits", deposit.leaf_index, deposits.len()))
+        })?;
+        *leaf = deposit.commitment;
+    }
+    leaves.truncate(deposits.last().map_or(0, |d| d.leaf_index as usize + 1));
+    Ok(leaves)
+}
+
+/// Storage address of a member of the `pool: PoolStorage` storage node
+fn pool_field_address(field: &str) -> FieldElement {
+    storage_address_of("pool", &[PathSegment::Member(field)])
+}
+
 /// Get function selector from function name
 fn get_selector(function_name: &str) -> FieldElement {
     use starknet::core::utils::get_selector_from_name;
@@ -445,8 +1555,767 @@ fn get_selector(function_name: &str) -> FieldElement {
 }
 
 /// Parse felt252 from hex string
-fn parse_felt(hex_str: &str) -> Result<FieldElement, String> {
+fn parse_felt(hex_str: &str) -> Result<FieldElement, BlockchainError> {
     FieldElement::from_hex_be(hex_str)
-        .map_err(|e| format!("Failed to parse felt252 '{}': {}", hex_str, e))
+        .map_err(|e| BlockchainError::InvalidInput(format!("Failed to parse felt252 '{}': {}", hex_str, e)))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+    use crate::mock_provider::{deposit_emitted_event, MockProvider};
+    use starknet::core::types::Event;
+    use starknet::core::utils::starknet_keccak;
+
+    /// Block the fixture deposits start at
+    const ZYLITH_DEPLOYMENT_BLOCK: u64 = 4438440;
+
+    fn zylith_address() -> FieldElement {
+        FieldElement::from_hex_be("0x05c1fecd5025d2e3d71482ac26ecf9138b08c9483f43761543cf71960373ce0b").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ensure_contract_deployed_missing_contract() {
+        // Node has no contract at the configured address (e.g. mainnet address on Sepolia)
+        let client = BlockchainClient::with_provider(MockProvider::new(), zylith_address());
+
+        let err = client.ensure_contract_deployed().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ContractNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_ensure_contract_deployed_ok() {
+        let provider = MockProvider::new().with_contract(zylith_address(), FieldElement::from(42u64));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert!(client.ensure_contract_deployed().await.is_ok());
+    }
+
+    fn initialized_pool(token0: FieldElement, token1: FieldElement) -> MockProvider {
+        let zylith = zylith_address();
+        MockProvider::new()
+            .with_storage(zylith, starknet_keccak("initialized".as_bytes()), FieldElement::ONE)
+            .with_storage(zylith, pool_field_address("token0"), token0)
+            .with_storage(zylith, pool_field_address("token1"), token1)
+            .with_storage(zylith, pool_field_address("liquidity"), FieldElement::from(5_000u64))
+    }
+
+    #[tokio::test]
+    async fn test_get_pool_reserves() {
+        let token0 = FieldElement::from(0x111u64);
+        let token1 = FieldElement::from(0x222u64);
+        let provider = initialized_pool(token0, token1)
+            .with_call_result(token0, "balance_of", vec![FieldElement::from(1_000u64), FieldElement::ZERO])
+            .with_call_result(token1, "balance_of", vec![FieldElement::from(7u64), FieldElement::ONE]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let reserves = client.get_pool_reserves().await.unwrap();
+        assert_eq!(reserves.reserve0, U256::from(1_000u128));
+        assert_eq!(reserves.reserve1, U256::from_words(7, 1));
+        assert_eq!(reserves.total_liquidity, U256::from(5_000u128));
+    }
+
+    #[tokio::test]
+    async fn test_get_pool_sqrt_price_reads_both_words() {
+        let price = pool_field_address("sqrt_price_x128");
+        let provider = initialized_pool(FieldElement::from(0x111u64), FieldElement::from(0x222u64))
+            .with_storage(zylith_address(), price, FieldElement::from(3u64))
+            .with_storage(zylith_address(), slot_offset(price, 1), FieldElement::from(1u64));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.get_pool_sqrt_price_x128().await.unwrap(), U256::from_words(3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_get_pool_reserves_uninitialized() {
+        let provider = MockProvider::new().with_contract(zylith_address(), FieldElement::from(42u64));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let err = client.get_pool_reserves().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::PoolNotInitialized));
+    }
+
+    /// Receipt of a deposit tx: ETH fee transfer, then the nested PrivacyEvent::Deposit
+    fn deposit_receipt(leaf_index: u64) -> TxReceipt {
+        let eth = FieldElement::from_hex_be("0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7").unwrap();
+        TxReceipt {
+            pending: false,
+            reverted: false,
+            events: vec![
+                Event {
+                    from_address: eth,
+                    keys: vec![starknet_keccak("Transfer".as_bytes())],
+                    data: vec![FieldElement::from(1u64), FieldElement::from(2u64), FieldElement::from(3u64)],
+                },
+                Event {
+                    from_address: zylith_address(),
+                    keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), deposit_selector()],
+                    data: vec![
+                        FieldElement::from_hex_be("0xabc123").unwrap(),
+                        FieldElement::from(leaf_index),
+                        FieldElement::from_hex_be("0xdef456").unwrap(),
+                    ],
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deposit_index_for_tx() {
+        let tx_hash = FieldElement::from(0x7777u64);
+        let provider = MockProvider::new().with_receipt(tx_hash, deposit_receipt(12));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.deposit_index_for_tx("0x7777").await.unwrap(), 12);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_index_for_tx_errors() {
+        let mut no_deposit = deposit_receipt(0);
+        no_deposit.events.truncate(1);
+        let mut pending = deposit_receipt(0);
+        pending.pending = true;
+        let provider = MockProvider::new()
+            .with_receipt(FieldElement::from(1u64), no_deposit)
+            .with_receipt(FieldElement::from(2u64), pending);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let err = client.deposit_index_for_tx("0x1").await.unwrap_err();
+        assert!(matches!(err, BlockchainError::NoDepositEvent(_)));
+        let err = client.deposit_index_for_tx("0x2").await.unwrap_err();
+        assert!(matches!(err, BlockchainError::TransactionPending(_)));
+        let err = client.deposit_index_for_tx("0x3").await.unwrap_err();
+        assert!(matches!(err, BlockchainError::TransactionNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_surfaces_rpc_error_early() {
+        let provider = MockProvider::new()
+            .with_contract(zylith_address(), FieldElement::from(42u64))
+            .failing(BlockchainError::Transport("connection refused".to_string()));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let err = client.connect().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Transport(_)));
+        // A failed handshake is not cached
+        assert!(client.handshake.get().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_connect_caches_handshake() {
+        let provider = MockProvider::new().with_block_number(4_500_000);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        client.connect().await.unwrap();
+        client.connect().await.unwrap();
+        assert_eq!(client.handshake.get(), Some(&4_500_000));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_url() {
+        let err = BlockchainClient::builder("not a url", "0x1").lazy(true).build().err().unwrap();
+        assert!(matches!(err, BlockchainError::Config(_)));
+    }
+
+    #[test]
+    fn test_builder_applies_client_config() {
+        let config = ClientConfig {
+            request_timeout: Duration::from_secs(7),
+            ..ClientConfig::default()
+        };
+        let client = BlockchainClient::builder("https://rpc.example", "0x1")
+            .lazy(true)
+            .client_config(config)
+            .event_page_timeout(Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        assert_eq!(client.timeouts.read_call(), Duration::from_secs(7));
+        assert_eq!(client.timeouts.event_page(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_builder_accepts_several_rpc_urls() {
+        let client = BlockchainClient::builder("https://rpc-a.example, https://rpc-b.example", "0x1")
+            .lazy(true)
+            .build()
+            .unwrap();
+        let endpoints: Vec<String> = client.provider.inner().inner().health().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(endpoints, vec!["https://rpc-a.example", "https://rpc-b.example"]);
+
+        let err = BlockchainClient::builder("https://rpc-a.example,not a url", "0x1").lazy(true).build().err().unwrap();
+        assert!(matches!(err, BlockchainError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_profile_per_operation() {
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "is_nullifier_spent", vec![FieldElement::ZERO])
+            .with_block_number(10)
+            .with_latency(Duration::from_millis(50));
+        let timeouts = TimeoutProfile {
+            read_call: Some(Duration::from_millis(5)),
+            event_page: Some(Duration::from_secs(5)),
+            ..TimeoutProfile::default()
+        };
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_timeouts(timeouts);
+
+        // Fast-fail budget trips...
+        let err = client.is_nullifier_spent("0x1").await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Timeout(_)), "unexpected error: {}", err);
+        // ...while the event scan has room to finish
+        assert_eq!(client.find_commitment_in_events("0x1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_abi_decoded_token_reads() {
+        let token = FieldElement::from(0x111u64);
+        let provider = initialized_pool(token, FieldElement::ZERO)
+            .with_call_result(token, "balance_of", vec![FieldElement::from(5u64), FieldElement::ONE])
+            .with_call_result(token, "allowance", vec![FieldElement::from(3u64)]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.get_token_balance("0x111", "0x2a1").await.unwrap(), (5, 1));
+        // A single felt is not a u256
+        assert!(matches!(client.get_token_allowance("0x111", "0x2a1", "0x3").await, Err(BlockchainError::Decode(_))));
+        // token1 was never written: no guessing at other slots
+        assert!(matches!(client.get_pool_token1().await, Err(BlockchainError::MissingData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_historical_reads_use_the_given_block() {
+        let token = FieldElement::from(0x111u64);
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)])
+            .with_call_result(zylith_address(), "is_root_known", vec![FieldElement::ONE])
+            .with_call_result(token, "balance_of", vec![FieldElement::from(5u64), FieldElement::ZERO]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+        let block = BlockId::Number(ZYLITH_DEPLOYMENT_BLOCK + 10);
+
+        assert_eq!(client.get_merkle_root_at(block).await.unwrap(), "0xabc");
+        assert!(client.is_root_known_at("0xabc", block).await.unwrap());
+        assert_eq!(client.get_token_balance_at("0x111", "0x2a1", block).await.unwrap(), (5, 0));
+        assert!(client
+            .provider
+            .call_blocks()
+            .iter()
+            .all(|b| matches!(b, BlockId::Number(n) if *n == ZYLITH_DEPLOYMENT_BLOCK + 10)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_call_keeps_per_call_results() {
+        let token = FieldElement::from(0x111u64);
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(7u64)])
+            .with_call_result(token, "balance_of", vec![FieldElement::from(5u64), FieldElement::ZERO]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+        let call = |contract_address, function| FunctionCall {
+            contract_address,
+            entry_point_selector: get_selector(function),
+            calldata: vec![],
+        };
+
+        let results = client
+            .batch_call(vec![
+                call(zylith_address(), "get_merkle_root"),
+                call(zylith_address(), "no_such_function"),
+                call(token, "balance_of"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &vec![FieldElement::from(7u64)]);
+        assert!(matches!(results[1], Err(BlockchainError::EntryPointNotFound(_))));
+        assert_eq!(results[2].as_ref().unwrap()[0], FieldElement::from(5u64));
+    }
+
+    #[tokio::test]
+    async fn test_are_nullifiers_spent_skips_cached() {
+        let provider = MockProvider::new().with_call_result(zylith_address(), "is_nullifier_spent", vec![FieldElement::ONE]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert!(client.is_nullifier_spent("0x1").await.unwrap());
+        assert_eq!(client.are_nullifiers_spent(&["0x1", "0x2", "0x3"]).await.unwrap(), vec![true, true, true]);
+        // 0x1 came from the cache
+        assert_eq!(client.provider.call_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_typed() {
+        let client = BlockchainClient::with_provider(MockProvider::new().with_contract(zylith_address(), FieldElement::ONE), zylith_address());
+
+        assert!(matches!(client.is_nullifier_spent("not hex").await, Err(BlockchainError::InvalidInput(_))));
+        assert!(matches!(client.get_pool_token0().await, Err(BlockchainError::PoolNotInitialized)));
+        // Older deployment without the getter
+        assert!(matches!(client.get_merkle_root().await, Err(BlockchainError::EntryPointNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_address_format() {
+        let token0 = FieldElement::from(0x1u64);
+        let padded = BlockchainClient::with_provider(initialized_pool(token0, FieldElement::from(0x2u64)), zylith_address());
+        assert_eq!(padded.get_pool_token0().await.unwrap(), format!("0x{}1", "0".repeat(63)));
+
+        let trimmed = BlockchainClient::with_provider(initialized_pool(token0, FieldElement::from(0x2u64)), zylith_address())
+            .with_address_format(AddressFormat::Trimmed);
+        assert_eq!(trimmed.get_pool_token0().await.unwrap(), "0x1");
+    }
+
+    #[tokio::test]
+    async fn test_root_and_address_formats_are_independent() {
+        let provider = initialized_pool(FieldElement::from(0x1u64), FieldElement::from(0x2u64))
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)]);
+        let client = BlockchainClient::with_provider(provider, zylith_address())
+            .with_address_format(AddressFormat::Trimmed)
+            .with_root_format(AddressFormat::Padded64);
+
+        assert_eq!(client.get_pool_token0().await.unwrap(), "0x1");
+        assert_eq!(client.get_merkle_root().await.unwrap(), format!("0x{}abc", "0".repeat(61)));
+    }
+
+    #[tokio::test]
+    async fn test_pool_token_reads_use_the_storage_budget() {
+        let token0 = FieldElement::from(0x111u64);
+        // A node slower than the storage budget but well within the view call one
+        let provider = initialized_pool(token0, FieldElement::from(0x222u64)).with_latency(Duration::from_millis(100));
+        let timeouts = TimeoutProfile {
+            storage_read: Some(Duration::from_millis(5)),
+            ..TimeoutProfile::default()
+        };
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_timeouts(timeouts);
+
+        let err = client.get_pool_token0().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Timeout(_)), "unexpected error: {}", err);
+        let err = client.get_pool_token1().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Timeout(_)), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_release_connections() {
+        let token0 = FieldElement::from(0x111u64);
+        // Two connections and a node slower than every storage budget
+        let provider = initialized_pool(token0, FieldElement::from(0x222u64))
+            .with_connection_limit(2)
+            .with_latency(Duration::from_millis(100));
+        let timeouts = TimeoutProfile {
+            storage_read: Some(Duration::from_millis(5)),
+            ..TimeoutProfile::default()
+        };
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_timeouts(timeouts);
+
+        for _ in 0..20 {
+            let err = client.get_pool_token0().await.unwrap_err();
+            assert!(matches!(err, BlockchainError::Timeout(_)), "unexpected error: {}", err);
+        }
+
+        // Every timed-out request gave its connection back
+        client.provider.set_latency(None);
+        assert_eq!(client.get_pool_token0().await.unwrap(), format_felt(token0, AddressFormat::Padded64));
+    }
+
+    fn deposits(count: u32) -> Vec<starknet::core::types::EmittedEvent> {
+        (0..count)
+            .map(|i| {
+                deposit_emitted_event(
+                    zylith_address(),
+                    FieldElement::from(1000 + i),
+                    i,
+                    FieldElement::from(2000 + i),
+                    ZYLITH_DEPLOYMENT_BLOCK + i as u64,
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_get_known_roots_reads_history_size() {
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "get_known_roots_count", vec![FieldElement::from(3u64)])
+            .with_events(deposits(5));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.get_roots_history_size().await.unwrap(), 3);
+        // The newest roots are the ones still accepted
+        let roots = client.get_known_roots().await.unwrap();
+        assert_eq!(
+            roots,
+            vec![FieldElement::from(2002u64), FieldElement::from(2003u64), FieldElement::from(2004u64)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roots_history_size_fallback() {
+        let provider = MockProvider::new()
+            .with_contract(zylith_address(), FieldElement::from(42u64))
+            .with_events(deposits(5));
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_roots_history_fallback(2);
+
+        assert_eq!(client.get_roots_history_size().await.unwrap(), 2);
+        assert_eq!(client.get_known_roots().await.unwrap(), vec![FieldElement::from(2003u64), FieldElement::from(2004u64)]);
+    }
+
+    #[tokio::test]
+    async fn test_classify_root() {
+        let current = FieldElement::from(0xc0ffeeu64);
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "get_merkle_root", vec![current])
+            .with_call_result(zylith_address(), "is_root_known", vec![FieldElement::ONE]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.classify_root("0xc0ffee").await.unwrap(), RootStatus::Current);
+        assert_eq!(client.classify_root("0xbeef").await.unwrap(), RootStatus::Historical);
+    }
+
+    #[tokio::test]
+    async fn test_classify_root_unknown() {
+        let provider = MockProvider::new()
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xc0ffeeu64)])
+            .with_call_result(zylith_address(), "is_root_known", vec![FieldElement::ZERO]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.classify_root("0xbad").await.unwrap(), RootStatus::Unknown);
+        assert!(matches!(
+            client.classify_root("not hex").await.unwrap_err(),
+            BlockchainError::InvalidInput(_)
+        ));
+    }
+
+    #[test]
+    fn test_leaves_from_deposits_bounds_the_leaf_index() {
+        let deposit = |leaf_index: u32| DepositEvent {
+            commitment: FieldElement::from(1000 + leaf_index),
+            leaf_index,
+            root: FieldElement::ZERO,
+            amount: None,
+        };
+        assert_eq!(
+            leaves_from_deposits(&[deposit(0), deposit(1)], TREE_DEPTH).unwrap(),
+            vec![FieldElement::from(1000u64), FieldElement::from(1001u64)]
+        );
+        // An index from event data is checked before anything is allocated for it
+        let err = leaves_from_deposits(&[deposit(0), deposit(u32::MAX)], TREE_DEPTH).unwrap_err();
+        assert!(matches!(err, BlockchainError::LeafOrder(_)), "unexpected error: {}", err);
+        let err = leaves_from_deposits(&[deposit(0), deposit(5)], TREE_DEPTH).unwrap_err();
+        assert!(matches!(err, BlockchainError::LeafOrder(_)), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    async fn test_export_snapshot_round_trip() {
+        let provider = MockProvider::new().with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 10).with_events(deposits(3));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let snapshot = client.export_snapshot().await.unwrap();
+        assert_eq!(snapshot.depth, TREE_DEPTH);
+        assert_eq!(snapshot.leaves, vec![FieldElement::from(1000u64), FieldElement::from(1001u64), FieldElement::from(1002u64)]);
+        assert_eq!(snapshot.root, "0x7d2");
+        assert_eq!(snapshot.synced_block, ZYLITH_DEPLOYMENT_BLOCK + 10);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"0x3e8\""));
+        let decoded: TreeSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_root_cached_until_new_block() {
+        let provider = MockProvider::new().with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        client.get_merkle_root().await.unwrap();
+        client.get_merkle_root().await.unwrap();
+        assert_eq!(client.provider.call_count(), 1);
+
+        // The syncer saw a new block: the next read goes to the node
+        client.observe_block(ZYLITH_DEPLOYMENT_BLOCK + 1);
+        client.get_merkle_root().await.unwrap();
+        assert_eq!(client.provider.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_spent_nullifier_is_cached() {
+        let provider = MockProvider::new().with_call_result(zylith_address(), "is_nullifier_spent", vec![FieldElement::ONE]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert!(client.is_nullifier_spent("0xdead").await.unwrap());
+        assert!(client.is_nullifier_spent("0xdead").await.unwrap());
+        assert_eq!(client.provider.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unspent_nullifier_requeried_after_ttl() {
+        let provider = MockProvider::new().with_call_result(zylith_address(), "is_nullifier_spent", vec![FieldElement::ZERO]);
+        let client = BlockchainClient::with_provider(provider, zylith_address())
+            .with_nullifier_cache(16, Duration::from_millis(20));
+
+        assert!(!client.is_nullifier_spent("0xdead").await.unwrap());
+        assert!(!client.is_nullifier_spent("0xdead").await.unwrap());
+        assert_eq!(client.provider.call_count(), 1);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!client.is_nullifier_spent("0xdead").await.unwrap());
+        assert_eq!(client.provider.call_count(), 2);
+    }
+
+    fn wait_opts(timeout_ms: u64) -> ResolveOpts {
+        ResolveOpts {
+            wait: true,
+            timeout: Duration::from_millis(timeout_ms),
+            from_block: None,
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_commitment_found_immediately() {
+        let provider = MockProvider::new().with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 5).with_events(deposits(3));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.resolve_commitment("0x3e9", ResolveOpts::default()).await.unwrap(), 1);
+        let err = client.resolve_commitment("0x1", ResolveOpts::default()).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::CommitmentNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_commitment_waits_for_deposit() {
+        let provider = MockProvider::new().with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 1).with_events(deposits(1));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let mine_deposit = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            client.provider.push_events(deposits(2).split_off(1));
+            client.provider.set_block_number(ZYLITH_DEPLOYMENT_BLOCK + 2);
+        };
+        let (index, _) = tokio::join!(client.resolve_commitment("0x3e9", wait_opts(2_000)), mine_deposit);
+        assert_eq!(index.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_commitment_wait_timeout() {
+        let provider = MockProvider::new().with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 1).with_events(deposits(1));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let err = client.resolve_commitment("0x3e9", wait_opts(50)).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Timeout(_)));
+    }
+
+    fn leaves(values: &[u64]) -> Vec<BigUint> {
+        values.iter().map(|v| BigUint::from(*v)).collect()
+    }
+
+    #[test]
+    fn test_preview_root_after_matches_append() {
+        let client = BlockchainClient::with_provider(MockProvider::new(), zylith_address());
+        let mut tree = MerkleTree::from_leaves(TREE_DEPTH, &leaves(&[1000, 1001, 1002]));
+        let root_before = tree.get_root();
+
+        let preview = client.preview_root_after(&tree, FieldElement::from(1003u64));
+        // Previewing leaves the tree as it was
+        assert_eq!(tree.get_root(), root_before);
+        assert_eq!(tree.get_leaf_count(), 3);
+
+        let root = tree.append(BigUint::from(1003u64)).unwrap();
+        assert_eq!(format!("0x{:x}", preview), format!("0x{:x}", root));
+    }
+
+    #[tokio::test]
+    async fn test_verify_local_root() {
+        let on_chain = MerkleTree::from_leaves(TREE_DEPTH, &leaves(&[1000, 1001, 1002])).get_root();
+        let on_chain = FieldElement::from_byte_slice_be(&on_chain.to_bytes_be()).unwrap();
+        let provider = MockProvider::new().with_call_result(zylith_address(), "get_merkle_root", vec![on_chain]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let good = MerkleTree::from_leaves(TREE_DEPTH, &leaves(&[1000, 1001, 1002]));
+        assert!(client.verify_local_root(&good).await.unwrap());
+
+        // Same leaves, wrong order
+        let wrong_order = MerkleTree::from_leaves(TREE_DEPTH, &leaves(&[1001, 1000, 1002]));
+        match client.verify_local_root(&wrong_order).await.unwrap_err() {
+            BlockchainError::RootMismatch { local, on_chain: reported } => {
+                assert_eq!(reported, format!("0x{:x}", on_chain));
+                assert_eq!(local, format!("0x{:x}", wrong_order.get_root()));
+            }
+            other => panic!("expected RootMismatch, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_all_commitments_verifies_root() {
+        let provider = MockProvider::new()
+            .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 5)
+            .with_events(deposits(3))
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xbadu64)]);
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_root_verification(true);
+
+        let err = client.load_all_commitments().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::RootMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_validate_tree_config_against_first_deposit() {
+        let commitment = FieldElement::from(1000u64);
+        let root = TreeConfig::default().root_after_first_leaf(&commitment);
+        let provider = || {
+            MockProvider::new()
+                .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 5)
+                .with_events(vec![deposit_emitted_event(zylith_address(), commitment, 0, root, ZYLITH_DEPLOYMENT_BLOCK)])
+                .with_call_result(zylith_address(), "get_merkle_root", vec![root])
+        };
+        let client = BlockchainClient::with_provider(provider(), zylith_address()).with_deployment_block(ZYLITH_DEPLOYMENT_BLOCK);
+        client.validate_tree_config().await.unwrap();
+
+        // Another hash or depth doesn't reproduce the contract's first root
+        let pedersen = TreeConfig { hash: TreeHash::Pedersen, ..TreeConfig::default() };
+        let shallow = TreeConfig { depth: 20, ..TreeConfig::default() };
+        for config in [pedersen, shallow] {
+            let client = BlockchainClient::with_provider(provider(), zylith_address())
+                .with_deployment_block(ZYLITH_DEPLOYMENT_BLOCK)
+                .with_tree_config(config);
+            assert!(matches!(client.validate_tree_config().await.unwrap_err(), BlockchainError::Config(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_deposits_via_trace() {
+        use crate::provider::{InvocationTrace, TracedEvent};
+
+        let block = ZYLITH_DEPLOYMENT_BLOCK + 7;
+        let account = FieldElement::from(0xacc0u64);
+        let eth = FieldElement::from(0xe7e7u64);
+        // account __execute__ -> Zylith.private_deposit -> library call emitting the Deposit
+        let trace = InvocationTrace {
+            contract_address: account,
+            events: vec![],
+            calls: vec![
+                InvocationTrace {
+                    contract_address: zylith_address(),
+                    events: vec![],
+                    calls: vec![InvocationTrace {
+                        contract_address: zylith_address(),
+                        events: vec![TracedEvent {
+                            order: 1,
+                            keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), deposit_selector()],
+                            data: vec![FieldElement::from(0xc0u64), FieldElement::from(9u64), FieldElement::from(0x4007u64)],
+                        }],
+                        calls: vec![],
+                    }],
+                },
+                InvocationTrace {
+                    contract_address: eth,
+                    events: vec![TracedEvent {
+                        order: 0,
+                        keys: vec![starknet_keccak("Transfer".as_bytes())],
+                        data: vec![account, zylith_address(), FieldElement::from(5u64), FieldElement::ZERO],
+                    }],
+                    calls: vec![],
+                },
+            ],
+        };
+        let provider = MockProvider::new().with_contract(zylith_address(), FieldElement::ONE).with_block_trace(block, vec![trace]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let deposits = client.scan_deposits_via_trace(block).await.unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].commitment, FieldElement::from(0xc0u64));
+        assert_eq!(deposits[0].leaf_index, 9);
+    }
+
+    #[tokio::test]
+    async fn test_deployment_block_discovery() {
+        let provider = MockProvider::new()
+            .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 1000)
+            .with_contract_deployed_at(zylith_address(), FieldElement::from(42u64), ZYLITH_DEPLOYMENT_BLOCK)
+            .with_events(deposits(2))
+            .with_from_block_filter();
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        assert_eq!(client.deployment_block().await.unwrap(), ZYLITH_DEPLOYMENT_BLOCK);
+        // Scans start at the discovered block
+        assert_eq!(client.load_all_commitments().await.unwrap().len(), 2);
+
+        // A configured block skips the discovery
+        let client = BlockchainClient::with_provider(MockProvider::new(), zylith_address()).with_deployment_block(7);
+        assert_eq!(client.deployment_block().await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_pending_deposits_are_unconfirmed() {
+        let provider = MockProvider::new()
+            .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 2)
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)])
+            .with_events(deposits(2))
+            .with_pending_events(deposits(3).split_off(2));
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_pending(true);
+
+        assert_eq!(client.find_commitment_labeled("0x3e9").await.unwrap(), Some((1, Confirmation::Confirmed)));
+        assert_eq!(client.find_commitment_labeled("0x3ea").await.unwrap(), Some((2, Confirmation::Unconfirmed)));
+        assert_eq!(client.get_merkle_root_labeled().await.unwrap().1, Confirmation::Unconfirmed);
+        assert!(client
+            .provider
+            .call_blocks()
+            .iter()
+            .any(|block| matches!(block, BlockId::Tag(BlockTag::Pending))));
+
+        // Off by default: the pending block is ignored
+        let client = BlockchainClient::with_provider(client.provider, zylith_address());
+        assert_eq!(client.find_commitment_labeled("0x3ea").await.unwrap(), None);
+        assert_eq!(client.get_merkle_root_labeled().await.unwrap().1, Confirmation::Confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_commitment_index_limits_the_event_scan() {
+        let provider = MockProvider::new()
+            .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 10)
+            .with_events(deposits(3))
+            .with_from_block_filter();
+        // The syncer indexed the first two deposits, up to the block before the third
+        let index = CommitmentIndex::new();
+        index.rebuild(&[FieldElement::from(1000u64), FieldElement::from(1001u64)], &[], ZYLITH_DEPLOYMENT_BLOCK + 1);
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_commitment_index(index.clone());
+
+        assert_eq!(client.find_commitment_in_events("0x3e9").await.unwrap(), Some(1));
+        assert_eq!(client.find_commitment_in_events("0x3ea").await.unwrap(), Some(2));
+        // Older than the index and not in it: not scanned for again
+        client.provider.push_events(vec![deposit_emitted_event(
+            zylith_address(),
+            FieldElement::from(0x777u64),
+            9,
+            FieldElement::ONE,
+            ZYLITH_DEPLOYMENT_BLOCK,
+        )]);
+        assert_eq!(client.find_commitment_in_events("0x777").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_scan_deposits_via_trace_falls_back_to_events() {
+        // No traces registered: the mock reports tracing as unsupported
+        let provider = MockProvider::new().with_events(deposits(2));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let deposits = client.scan_deposits_via_trace(ZYLITH_DEPLOYMENT_BLOCK).await.unwrap();
+        assert_eq!(deposits.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_scan_deposits_via_trace_returns_transport_errors() {
+        // A node that traces but drops the request: not a reason to switch to get_events
+        let provider = MockProvider::new()
+            .with_events(deposits(2))
+            .with_block_trace(ZYLITH_DEPLOYMENT_BLOCK, vec![])
+            .with_transient_failures(1, BlockchainError::Transport("connection reset".to_string()));
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let err = client.scan_deposits_via_trace(ZYLITH_DEPLOYMENT_BLOCK).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Transport(_)), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_contract_deployed_zero_address() {
+        let provider = MockProvider::new().with_contract(FieldElement::ZERO, FieldElement::from(42u64));
+        let client = BlockchainClient::with_provider(provider, FieldElement::ZERO);
+
+        let err = client.ensure_contract_deployed().await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ContractNotFound(_)));
+    }
+}
@@ -0,0 +1,121 @@
+// Snapshot bootstrap
+// A cold sync replays every Deposit since the deployment. A fresh ASP can instead start from
+// a published snapshot: the JSON /deposit/snapshot or binary /deposit/snapshot.bin of another
+// ASP, served or saved to a file.
+// Nothing in it is taken on trust: the leaves must hash to the snapshot root, and that root
+// must be the contract's root at the snapshot block, before the syncer continues from there.
+
+use crate::checkpoint::SyncCheckpoint;
+use crate::error::BlockchainError;
+use crate::hasher::Hasher;
+use crate::merkle::TreeSnapshot;
+use crate::provider::StarknetProvider;
+use crate::snapshot::{import_snapshot, parse_snapshot};
+use starknet::core::types::{BlockId, FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
+use std::sync::Arc;
+
+/// Read a snapshot, JSON or binary, from an http(s) URL or a file path
+pub async fn load_snapshot(source: &str) -> Result<TreeSnapshot, BlockchainError> {
+    if !(source.starts_with("http://") || source.starts_with("https://")) {
+        return import_snapshot(source);
+    }
+    let bytes = reqwest::get(source)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| BlockchainError::Transport(format!("snapshot {}: {}", source, e)))?
+        .bytes()
+        .await
+        .map_err(|e| BlockchainError::Transport(format!("snapshot {}: {}", source, e)))?;
+    parse_snapshot(&bytes).map_err(|e| BlockchainError::Decode(format!("{} ({})", e, source)))
+}
+
+/// Checkpoint at the snapshot block holding its leaves, once they check out locally and on-chain
+pub async fn verify_snapshot<P: StarknetProvider + ?Sized>(
+    provider: &P,
+    contract_address: FieldElement,
+    snapshot: &TreeSnapshot,
+    depth: usize,
+    hasher: Arc<dyn Hasher>,
+) -> Result<SyncCheckpoint, BlockchainError> {
+    if snapshot.depth != depth {
+        return Err(BlockchainError::InvalidInput(format!(
+            "snapshot tree depth {} (expected {})",
+            snapshot.depth, depth
+        )));
+    }
+    let expected = FieldElement::from_hex_be(&snapshot.root)
+        .map_err(|e| BlockchainError::InvalidInput(format!("snapshot root {}: {}", snapshot.root, e)))?;
+
+    let checkpoint = SyncCheckpoint {
+        leaves: snapshot.leaves.clone(),
+        last_leaf_index: snapshot.leaves.len().checked_sub(1).map(|index| index as u32),
+        ..SyncCheckpoint::at_block(snapshot.synced_block)
+    };
+    let local = format!("0x{:x}", checkpoint.restore_tree_with(depth, hasher).get_root());
+    if local != format!("0x{:x}", expected) {
+        return Err(BlockchainError::InvalidInput(format!(
+            "snapshot leaves hash to {}, not to its root {}",
+            local, snapshot.root
+        )));
+    }
+
+    let call = FunctionCall {
+        contract_address,
+        entry_point_selector: get_selector_from_name("get_merkle_root").unwrap(),
+        calldata: vec![],
+    };
+    let result = provider.call(call, BlockId::Number(snapshot.synced_block)).await?;
+    let on_chain = result
+        .first()
+        .ok_or_else(|| BlockchainError::Decode("Empty response from get_merkle_root".to_string()))?;
+    if *on_chain != expected {
+        return Err(BlockchainError::RootMismatch {
+            local,
+            on_chain: format!("0x{:x}", on_chain),
+        });
+    }
+    Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{MerkleTree, TreeHash, TREE_DEPTH};
+    use crate::mock_provider::MockProvider;
+    use num_bigint::BigUint;
+
+    fn snapshot() -> TreeSnapshot {
+        let leaves = vec![FieldElement::from(1000u64), FieldElement::from(1001u64)];
+        let tree = MerkleTree::from_leaves(TREE_DEPTH, &[BigUint::from(1000u64), BigUint::from(1001u64)]);
+        TreeSnapshot {
+            depth: TREE_DEPTH,
+            leaves,
+            root: format!("0x{:x}", tree.get_root()),
+            synced_block: 500,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_is_checked_against_its_root_and_the_chain() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let snapshot = snapshot();
+        let root = FieldElement::from_hex_be(&snapshot.root).unwrap();
+        let provider = MockProvider::new().with_call_result(zylith, "get_merkle_root", vec![root]);
+
+        let checkpoint = verify_snapshot(&provider, zylith, &snapshot, TREE_DEPTH, TreeHash::default().hasher()).await.unwrap();
+        assert_eq!(checkpoint.last_synced_block, 500);
+        assert_eq!(checkpoint.last_leaf_index, Some(1));
+        assert_eq!(provider.call_blocks(), vec![BlockId::Number(500)]);
+
+        // Leaves that don't produce the advertised root
+        let tampered = TreeSnapshot { leaves: vec![FieldElement::from(1001u64), FieldElement::from(1000u64)], ..snapshot.clone() };
+        let err = verify_snapshot(&provider, zylith, &tampered, TREE_DEPTH, TreeHash::default().hasher()).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::InvalidInput(_)), "unexpected error: {}", err);
+
+        // Consistent, but not what the contract had at that block
+        let provider = MockProvider::new().with_call_result(zylith, "get_merkle_root", vec![FieldElement::from(7u64)]);
+        let err = verify_snapshot(&provider, zylith, &snapshot, TREE_DEPTH, TreeHash::default().hasher()).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::RootMismatch { .. }), "unexpected error: {}", err);
+    }
+}
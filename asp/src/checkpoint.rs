@@ -0,0 +1,597 @@
+// Persistent sync checkpoints
+// The syncer saves its progress after every page of events: the last fully scanned block,
+// the range and continuation token of the scan in progress and the deposit leaves seen so
+// far. A restart restores the tree and resumes at the exact page it stopped at, instead of
+// rescanning every block since the deployment. The hashes of the last scanned blocks are
+// kept too, to find the common ancestor when the chain reorganizes.
+// The leaves go to a log next to the checkpoint file, appended to as the tree grows, so a
+// save only writes the leaves added since the previous one; the checkpoint file itself stays
+// small. Only a rollback or a pruned origin rewrites leaves already saved.
+
+use crate::at_rest::{is_encrypted, AtRestKey};
+use crate::commitment_index::LeafOrigin;
+use crate::dedupe::EventDeduper;
+use crate::hasher::Hasher;
+use crate::merkle::{MerkleTree, TreeHash};
+use crate::nullifier_tree::NullifierTree;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::FieldElement;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Default checkpoint file, relative to the working directory
+pub const DEFAULT_CHECKPOINT_FILE: &str = "asp_state.json";
+
+/// Scanned blocks remembered for reorg detection (one per completed scan)
+pub const REORG_WINDOW: usize = 64;
+
+/// End block of a completed scan and the tree size right after it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockMark {
+    pub number: u64,
+    #[serde(with = "crate::conv::felt_hex")]
+    pub hash: FieldElement,
+    pub leaf_count: u32,
+    /// Spent nullifiers right after it
+    #[serde(default)]
+    pub nullifier_count: u32,
+}
+
+/// Persisted sync progress
+/// Every field but `last_synced_block` is optional on disk, so older state files (down to a
+/// bare `{"last_synced_block": N}`) still load.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncCheckpoint {
+    /// Every event up to this block is in the tree
+    pub last_synced_block: u64,
+    /// Leaf index of the last Deposit processed
+    #[serde(default)]
+    pub last_leaf_index: Option<u32>,
+    /// Last block of the scan in progress (the range is `last_synced_block + 1..=scan_to_block`)
+    #[serde(default)]
+    pub scan_to_block: Option<u64>,
+    /// Token of the next page of the scan in progress
+    #[serde(default)]
+    pub continuation_token: Option<String>,
+    /// Deposit tree leaves in index order, as of this checkpoint
+    #[serde(default, with = "crate::conv::felt_hex_vec")]
+    pub leaves: Vec<FieldElement>,
+    /// Block and transaction of each leaf, same order (null where unknown)
+    #[serde(default)]
+    pub leaf_origins: Vec<Option<LeafOrigin>>,
+    /// Last scanned blocks, oldest first (at most REORG_WINDOW)
+    #[serde(default)]
+    pub recent_blocks: Vec<BlockMark>,
+    /// Events applied from the newest block, so they aren't applied twice
+    #[serde(default)]
+    pub applied_events: EventDeduper,
+    /// Spent nullifiers in the order they were seen, as of this checkpoint
+    #[serde(default, with = "crate::conv::felt_hex_vec")]
+    pub nullifiers: Vec<FieldElement>,
+}
+
+impl SyncCheckpoint {
+    /// Fresh checkpoint starting after `block`, without leaves
+    pub fn at_block(block: u64) -> Self {
+        Self {
+            last_synced_block: block,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a scan stopped between two pages
+    pub fn scan_in_progress(&self) -> bool {
+        self.scan_to_block.is_some() && self.continuation_token.is_some()
+    }
+
+    /// Remember a completed scan, forgetting the oldest mark past REORG_WINDOW
+    pub fn push_mark(&mut self, mark: BlockMark) {
+        self.recent_blocks.retain(|m| m.number < mark.number);
+        self.recent_blocks.push(mark);
+        let excess = self.recent_blocks.len().saturating_sub(REORG_WINDOW);
+        self.recent_blocks.drain(..excess);
+    }
+
+    /// Go back to the state right after `mark`: every later leaf and mark is dropped
+    pub fn roll_back_to(&mut self, mark: BlockMark) {
+        self.last_synced_block = mark.number;
+        self.scan_to_block = None;
+        self.continuation_token = None;
+        self.leaves.truncate(mark.leaf_count as usize);
+        self.leaf_origins.truncate(mark.leaf_count as usize);
+        self.last_leaf_index = mark.leaf_count.checked_sub(1);
+        self.nullifiers.truncate(mark.nullifier_count as usize);
+        self.recent_blocks.retain(|m| m.number <= mark.number);
+        self.applied_events = EventDeduper::default();
+    }
+
+    /// Copy the leaves of `tree` into the checkpoint
+    pub fn record_tree(&mut self, tree: &MerkleTree) {
+        self.leaves = tree
+            .leaves()
+            .iter()
+            .map(|leaf| FieldElement::from_byte_slice_be(&leaf.to_bytes_be()).expect("tree leaves are masked to 250 bits"))
+            .collect();
+    }
+
+    /// Copy the leaves `tree` appended since the last one in the checkpoint, returning the index
+    /// of the first leaf copied; earlier leaves are taken as unchanged (`record_tree` copies
+    /// them all). A tree smaller than the checkpoint is copied whole.
+    pub fn record_appended(&mut self, tree: &MerkleTree) -> usize {
+        if (tree.get_leaf_count() as usize) < self.leaves.len() {
+            self.record_tree(tree);
+            return 0;
+        }
+        let first = self.leaves.len();
+        self.leaves.extend(
+            tree.leaves_from(first as u32)
+                .iter()
+                .map(|leaf| FieldElement::from_byte_slice_be(&leaf.to_bytes_be()).expect("tree leaves are masked to 250 bits")),
+        );
+        first
+    }
+
+    /// Copy of the checkpoint without its leaves and their origins
+    fn without_leaves(&self) -> Self {
+        Self {
+            last_synced_block: self.last_synced_block,
+            last_leaf_index: self.last_leaf_index,
+            scan_to_block: self.scan_to_block,
+            continuation_token: self.continuation_token.clone(),
+            leaves: Vec::new(),
+            leaf_origins: Vec::new(),
+            recent_blocks: self.recent_blocks.clone(),
+            applied_events: self.applied_events.clone(),
+            nullifiers: self.nullifiers.clone(),
+        }
+    }
+
+    /// Copy the spent nullifiers of `tree` into the checkpoint
+    pub fn record_nullifiers(&mut self, tree: &NullifierTree) {
+        self.nullifiers = tree.nullifiers().to_vec();
+    }
+
+    /// Rebuild the deposit tree from the saved leaves
+    pub fn restore_tree(&self, depth: usize) -> MerkleTree {
+        self.restore_tree_with(depth, TreeHash::default().hasher())
+    }
+
+    /// Rebuild the deposit tree from the saved leaves, hashing nodes with `hasher`
+    pub fn restore_tree_with(&self, depth: usize, hasher: Arc<dyn Hasher>) -> MerkleTree {
+        let leaves: Vec<BigUint> = self.leaves.iter().map(|leaf| BigUint::from_bytes_be(&leaf.to_bytes_be())).collect();
+        MerkleTree::from_leaves_with_hasher(depth, &leaves, hasher)
+    }
+}
+
+/// Checkpoint file whose leaves are in the leaf log: the first `leaf_log` records of it
+#[derive(Serialize, Deserialize)]
+struct LoggedCheckpoint {
+    checkpoint: SyncCheckpoint,
+    leaf_log: usize,
+    /// Logged origins of deposits before this block were pruned since they were logged
+    #[serde(default)]
+    origins_before: u64,
+}
+
+/// Record of the leaf log
+#[derive(Serialize, Deserialize)]
+struct LoggedLeaf {
+    #[serde(with = "crate::conv::felt_hex")]
+    commitment: FieldElement,
+    origin: Option<LeafOrigin>,
+}
+
+/// Leaves the checkpoint file on disk points to in the leaf log, with where each record ends
+#[derive(Debug, Default)]
+struct LeafLog {
+    leaves: Vec<(FieldElement, Option<LeafOrigin>)>,
+    ends: Vec<u64>,
+}
+
+/// Checkpoint file on disk
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    path: PathBuf,
+    /// Key the file is encrypted with; plaintext JSON if unset
+    key: Option<AtRestKey>,
+    /// Leaf log as of the last save or load; None while the checkpoint file on disk doesn't
+    /// point to it (legacy file with inline leaves, nothing saved yet), so it may be rewritten
+    log: Arc<Mutex<Option<LeafLog>>>,
+}
+
+impl Default for CheckpointStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHECKPOINT_FILE)
+    }
+}
+
+impl CheckpointStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            key: None,
+            log: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Encrypt the file with `key` from the next save (a plaintext file is still read)
+    pub fn with_encryption(mut self, key: AtRestKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Fails if the file is encrypted and this store can't decrypt it, rather than starting
+    /// over from an unreadable checkpoint
+    pub fn check_readable(&self) -> io::Result<()> {
+        let Ok(bytes) = fs::read(&self.path) else {
+            return Ok(());
+        };
+        match &self.key {
+            Some(key) if is_encrypted(&bytes) => key.decrypt(&bytes).map(|_| ()),
+            None if is_encrypted(&bytes) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is encrypted: set STATE_ENCRYPTION_KEY or STATE_ENCRYPTION_KEY_FILE", self.path.display()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checkpoint file of the contract at `address`, for contracts synced next to the primary one
+    /// (which keeps DEFAULT_CHECKPOINT_FILE)
+    pub fn for_contract(address: FieldElement) -> Self {
+        Self::new(format!("asp_state_0x{:064x}.json", address))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Leaf log kept next to the checkpoint file
+    pub fn leaf_log_path(&self) -> PathBuf {
+        self.path.with_extension("leaves")
+    }
+
+    /// Saved checkpoint; a missing or unreadable file starts from scratch
+    pub fn load(&self) -> SyncCheckpoint {
+        let mut log = self.log.lock().unwrap();
+        *log = None;
+        let Ok(bytes) = fs::read(&self.path) else {
+            return SyncCheckpoint::default();
+        };
+        let json = match self.plaintext(&bytes) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read {}: {}", self.path.display(), e);
+                return SyncCheckpoint::default();
+            }
+        };
+        // Files from before the leaf log carry the leaves inline
+        let logged = match serde_json::from_slice::<LoggedCheckpoint>(&json) {
+            Ok(logged) => logged,
+            Err(_) => {
+                return serde_json::from_slice(&json).unwrap_or_else(|e| {
+                    eprintln!("[Syncer] ⚠️  Unreadable checkpoint {} ({}), starting over", self.path.display(), e);
+                    SyncCheckpoint::default()
+                })
+            }
+        };
+        let mut checkpoint = logged.checkpoint;
+        match self.read_leaf_log(logged.leaf_log) {
+            Ok(read) => {
+                checkpoint.leaves = read.leaves.iter().map(|(commitment, _)| *commitment).collect();
+                checkpoint.leaf_origins = read
+                    .leaves
+                    .iter()
+                    .map(|(_, origin)| origin.filter(|origin| origin.block_number >= logged.origins_before))
+                    .collect();
+                *log = Some(read);
+                checkpoint
+            }
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Unreadable leaf log {} ({}), starting over", self.leaf_log_path().display(), e);
+                SyncCheckpoint::default()
+            }
+        }
+    }
+
+    /// Write the checkpoint atomically, so a crash mid-write keeps the previous one
+    /// Leaves appended since the last save go to the leaf log first, then the checkpoint file
+    /// points to them. If saved leaves changed (a rollback, a backfill), the checkpoint is
+    /// written whole with its leaves inline and the log starts over on the next save.
+    pub fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let mut log = self.log.lock().unwrap();
+        let logged = match log.as_mut() {
+            Some(current) => match current.pruned_before(checkpoint) {
+                Some(origins_before) => {
+                    self.append_leaves(current, checkpoint)?;
+                    Some(origins_before)
+                }
+                None => None,
+            },
+            // The file on disk doesn't point to the log: it can be started over
+            None => {
+                let mut fresh = LeafLog::default();
+                self.append_leaves(&mut fresh, checkpoint)?;
+                *log = Some(fresh);
+                Some(0)
+            }
+        };
+        let json = match logged {
+            Some(origins_before) => serde_json::to_vec(&LoggedCheckpoint {
+                checkpoint: checkpoint.without_leaves(),
+                leaf_log: checkpoint.leaves.len(),
+                origins_before,
+            })?,
+            // Rewriting records the file on disk points to would break it if the process died
+            // before the new file is written
+            None => serde_json::to_vec(checkpoint)?,
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        match &self.key {
+            Some(key) => write_durably(&self.path, &tmp, &key.encrypt(&json))?,
+            None => write_durably(&self.path, &tmp, &json)?,
+        }
+        if logged.is_none() {
+            *log = None;
+        }
+        Ok(())
+    }
+
+    /// Decrypted contents of a file or log record, as is if they aren't encrypted
+    fn plaintext(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match &self.key {
+            Some(key) if is_encrypted(bytes) => key.decrypt(bytes),
+            _ => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// First `count` records of the leaf log
+    fn read_leaf_log(&self, count: usize) -> io::Result<LeafLog> {
+        let mut log = LeafLog::default();
+        if count == 0 {
+            return Ok(log);
+        }
+        let bytes = fs::read(self.leaf_log_path())?;
+        let mut offset = 0usize;
+        while log.leaves.len() < count {
+            let Some(header) = bytes.get(offset..offset + 4) else {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} of {} leaves in the log", log.leaves.len(), count)));
+            };
+            let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let Some(record) = bytes.get(offset + 4..offset + 4 + len) else {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} of {} leaves in the log", log.leaves.len(), count)));
+            };
+            let leaf: LoggedLeaf = serde_json::from_slice(&self.plaintext(record)?)?;
+            offset += 4 + len;
+            log.leaves.push((leaf.commitment, leaf.origin));
+            log.ends.push(offset as u64);
+        }
+        Ok(log)
+    }
+
+    /// Write the leaves of `checkpoint` past those of `log` at the end of its records, synced
+    /// to disk; records past them (written before a crash) are overwritten
+    fn append_leaves(&self, log: &mut LeafLog, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let first = log.leaves.len();
+        let end = log.ends.last().copied().unwrap_or(0);
+        let mut records = Vec::new();
+        let mut ends = Vec::new();
+        for (index, commitment) in checkpoint.leaves.iter().enumerate().skip(first) {
+            let origin = checkpoint.leaf_origins.get(index).copied().flatten();
+            let json = serde_json::to_vec(&LoggedLeaf { commitment: *commitment, origin })?;
+            let record = match &self.key {
+                Some(key) => key.encrypt(&json),
+                None => json,
+            };
+            records.extend_from_slice(&(record.len() as u32).to_be_bytes());
+            records.extend_from_slice(&record);
+            ends.push(end + records.len() as u64);
+        }
+        // Nothing new, and the log already ends where the file on disk says
+        if records.is_empty() && first > 0 {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(self.leaf_log_path())?;
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        file.write_all(&records)?;
+        file.sync_all()?;
+        for ((index, commitment), end) in checkpoint.leaves.iter().enumerate().skip(first).zip(ends) {
+            log.leaves.push((*commitment, checkpoint.leaf_origins.get(index).copied().flatten()));
+            log.ends.push(end);
+        }
+        Ok(())
+    }
+}
+
+impl LeafLog {
+    /// If every logged leaf is still in `checkpoint` at the same index, with the same origin
+    /// or one pruned since, the block before which origins were pruned; None otherwise
+    fn pruned_before(&self, checkpoint: &SyncCheckpoint) -> Option<u64> {
+        if self.leaves.len() > checkpoint.leaves.len() {
+            return None;
+        }
+        let (mut pruned_before, mut oldest_kept) = (0u64, u64::MAX);
+        for (index, (commitment, logged)) in self.leaves.iter().enumerate() {
+            if checkpoint.leaves[index] != *commitment {
+                return None;
+            }
+            match (logged, checkpoint.leaf_origins.get(index).copied().flatten()) {
+                (Some(logged), Some(kept)) if *logged == kept => oldest_kept = oldest_kept.min(kept.block_number),
+                (Some(logged), None) => pruned_before = pruned_before.max(logged.block_number + 1),
+                (None, None) => {}
+                _ => return None,
+            }
+        }
+        // Origins are pruned by block: a kept one older than a pruned one is another change
+        (pruned_before <= oldest_kept).then_some(pruned_before)
+    }
+}
+
+/// Replace `path` with `contents` through `tmp`: the file holds either the old or the new
+/// contents after a crash or power loss, never a truncated mix. Without the syncs a rename
+/// can reach the disk before the data it points to.
+pub fn write_durably(path: &Path, tmp: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = fs::File::create(tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(tmp, path)?;
+    // The rename itself is durable once the directory is synced (not possible on Windows)
+    #[cfg(unix)]
+    {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+
+    #[test]
+    fn test_round_trip_and_legacy_format() {
+        let dir = std::env::temp_dir().join(format!("asp_checkpoint_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = CheckpointStore::new(dir.join("state.json"));
+
+        assert_eq!(store.load(), SyncCheckpoint::default());
+
+        let tree = MerkleTree::from_leaves(TREE_DEPTH, &[BigUint::from(11u8), BigUint::from(12u8)]);
+        let mut checkpoint = SyncCheckpoint {
+            last_synced_block: 100,
+            last_leaf_index: Some(1),
+            scan_to_block: Some(250),
+            continuation_token: Some("2".to_string()),
+            leaves: Vec::new(),
+            leaf_origins: vec![Some(LeafOrigin { block_number: 80, transaction_hash: FieldElement::from(0xabcu64) }), None],
+            recent_blocks: vec![BlockMark { number: 90, hash: FieldElement::from(0x90u64), leaf_count: 1, nullifier_count: 0 }],
+            applied_events: EventDeduper::default(),
+            nullifiers: vec![FieldElement::from(0x3f1au64)],
+        };
+        checkpoint.record_tree(&tree);
+        store.save(&checkpoint).unwrap();
+        let loaded = store.load();
+        assert_eq!(loaded, checkpoint);
+        assert!(loaded.scan_in_progress());
+        assert_eq!(loaded.restore_tree(TREE_DEPTH).get_root(), tree.get_root());
+
+        // State files from before checkpoints only carry the block
+        fs::write(dir.join("state.json"), r#"{"last_synced_block":4438440}"#).unwrap();
+        assert_eq!(store.load(), SyncCheckpoint::at_block(4438440));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_checkpoint_file() {
+        let dir = std::env::temp_dir().join(format!("asp_checkpoint_encrypted_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        fs::write(&path, r#"{"last_synced_block":4438440}"#).unwrap();
+        let key = AtRestKey::from_hex(&"5a".repeat(32)).unwrap();
+        let store = CheckpointStore::new(&path).with_encryption(key);
+
+        // A plaintext file is read, then encrypted by the next save
+        assert_eq!(store.load(), SyncCheckpoint::at_block(4438440));
+        let mut checkpoint = SyncCheckpoint::at_block(4438500);
+        checkpoint.leaves = vec![FieldElement::from(0xc0ffeeu64)];
+        checkpoint.leaf_origins = vec![None];
+        store.save(&checkpoint).unwrap();
+        assert!(is_encrypted(&fs::read(&path).unwrap()));
+        assert!(!fs::read(store.leaf_log_path()).unwrap().windows(6).any(|window| window == b"c0ffee"));
+        assert_eq!(store.load(), checkpoint);
+        assert!(store.check_readable().is_ok());
+
+        // Without the key the file is refused instead of starting over
+        assert!(CheckpointStore::new(&path).check_readable().is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_leaf_log_appends_and_rewrites() {
+        let dir = std::env::temp_dir().join(format!("asp_checkpoint_leaf_log_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = CheckpointStore::new(dir.join("state.json"));
+        let origin = |block_number: u64| Some(LeafOrigin { block_number, transaction_hash: FieldElement::from(block_number) });
+        let mut checkpoint = SyncCheckpoint::at_block(10);
+        checkpoint.leaves = vec![FieldElement::from(1000u64), FieldElement::from(1001u64)];
+        checkpoint.leaf_origins = vec![origin(5), origin(8)];
+        store.save(&checkpoint).unwrap();
+
+        // Appending only writes the new leaves; the checkpoint file doesn't hold any
+        let log_len = fs::metadata(store.leaf_log_path()).unwrap().len();
+        checkpoint.last_synced_block = 20;
+        checkpoint.leaves.push(FieldElement::from(1002u64));
+        checkpoint.leaf_origins.push(origin(15));
+        store.save(&checkpoint).unwrap();
+        assert!(fs::metadata(store.leaf_log_path()).unwrap().len() > log_len);
+        assert!(!fs::read_to_string(store.path()).unwrap().contains("0x3ea"));
+        assert_eq!(CheckpointStore::new(store.path()).load(), checkpoint);
+
+        // Pruned origins stay in the log but not in the loaded checkpoint
+        checkpoint.leaf_origins[0] = None;
+        store.save(&checkpoint).unwrap();
+        assert_eq!(CheckpointStore::new(store.path()).load(), checkpoint);
+
+        // A rollback is saved whole, then the log starts over
+        checkpoint.leaves.truncate(1);
+        checkpoint.leaf_origins.truncate(1);
+        store.save(&checkpoint).unwrap();
+        assert!(fs::read_to_string(store.path()).unwrap().contains("0x3e8"));
+        assert_eq!(CheckpointStore::new(store.path()).load(), checkpoint);
+        checkpoint.leaves.push(FieldElement::from(2222u64));
+        checkpoint.leaf_origins.push(origin(16));
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load(), checkpoint);
+
+        // Records written before a crash, past what the checkpoint file points to, are ignored
+        let mut log = OpenOptions::new().append(true).open(store.leaf_log_path()).unwrap();
+        log.write_all(&[0, 0, 0, 9, b'{']).unwrap();
+        drop(log);
+        assert_eq!(store.load(), checkpoint);
+        checkpoint.leaves.push(FieldElement::from(2223u64));
+        checkpoint.leaf_origins.push(origin(17));
+        store.save(&checkpoint).unwrap();
+        assert_eq!(CheckpointStore::new(store.path()).load(), checkpoint);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_contract_stores_are_separate() {
+        let first = CheckpointStore::for_contract(FieldElement::from(0x2a1u64));
+        let second = CheckpointStore::for_contract(FieldElement::from(0x2a2u64));
+        assert_ne!(first.path, second.path);
+        assert_ne!(first.path, CheckpointStore::default().path);
+    }
+
+    #[test]
+    fn test_marks_window_and_roll_back() {
+        let mark = |number: u64, leaf_count: u32| BlockMark { number, hash: FieldElement::from(number), leaf_count, nullifier_count: 0 };
+        let mut checkpoint = SyncCheckpoint::at_block(0);
+        for number in 1..=(REORG_WINDOW as u64 + 2) {
+            checkpoint.push_mark(mark(number, number as u32));
+        }
+        assert_eq!(checkpoint.recent_blocks.len(), REORG_WINDOW);
+        assert_eq!(checkpoint.recent_blocks[0].number, 3);
+
+        checkpoint.last_synced_block = 66;
+        checkpoint.leaves = vec![FieldElement::ONE; 66];
+        checkpoint.roll_back_to(mark(40, 40));
+        assert_eq!(checkpoint.last_synced_block, 40);
+        assert_eq!(checkpoint.leaves.len(), 40);
+        assert_eq!(checkpoint.last_leaf_index, Some(39));
+        assert_eq!(checkpoint.recent_blocks.last().unwrap().number, 40);
+    }
+}
@@ -0,0 +1,312 @@
+// snarkjs Groth16 artifacts for the in-process prover
+// Reads the proving key and constraints of a snarkjs zkey, computes witnesses with the
+// generate_witness.js circom writes next to each circuit's wasm, and reduces R1CS to QAP the way
+// snarkjs does, so arkworks proofs verify against the same verification keys.
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, BigInteger256, PrimeField, Zero};
+use ark_groth16::r1cs_to_qap::{evaluate_constraint, LibsnarkReduction, R1CSToQAP};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::EvaluationDomain;
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSystemRef, SynthesisError};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Proving key and A/B constraint matrices of a snarkjs Groth16 zkey
+pub fn read_zkey(path: &Path) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>), String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let sections = sections(&bytes, b"zkey")?;
+
+    let mut header = section(&sections, 2)?;
+    let (n8q, q) = (header.u32()?, header.take(32)?);
+    let (n8r, r) = (header.u32()?, header.take(32)?);
+    if n8q != 32 || n8r != 32 || q != Fq::MODULUS.to_bytes_le() || r != Fr::MODULUS.to_bytes_le() {
+        return Err("not a BN254 zkey".to_string());
+    }
+    let n_vars = header.u32()? as usize;
+    let n_public = header.u32()? as usize;
+    let domain_size = header.u32()? as usize;
+    if n_public >= n_vars {
+        return Err(format!("{} public signals out of {} variables", n_public, n_vars));
+    }
+    let alpha_g1 = header.g1()?;
+    let beta_g1 = header.g1()?;
+    let beta_g2 = header.g2()?;
+    let gamma_g2 = header.g2()?;
+    let delta_g1 = header.g1()?;
+    let delta_g2 = header.g2()?;
+
+    let proving_key = ProvingKey {
+        vk: VerifyingKey { alpha_g1, beta_g2, gamma_g2, delta_g2, gamma_abc_g1: section(&sections, 3)?.g1s(n_public + 1)? },
+        beta_g1,
+        delta_g1,
+        a_query: section(&sections, 5)?.g1s(n_vars)?,
+        b_g1_query: section(&sections, 6)?.g1s(n_vars)?,
+        b_g2_query: section(&sections, 7)?.g2s(n_vars)?,
+        l_query: section(&sections, 8)?.g1s(n_vars - n_public - 1)?,
+        h_query: section(&sections, 9)?.g1s(domain_size)?,
+    };
+    let matrices = matrices(section(&sections, 4)?, n_vars, n_public, domain_size)?;
+    Ok((proving_key, matrices))
+}
+
+/// A and B matrices from the zkey's coefficients section; CircomReduction doesn't use C
+fn matrices(mut coeffs: Reader, n_vars: usize, n_public: usize, domain_size: usize) -> Result<ConstraintMatrices<Fr>, String> {
+    let mut rows = [vec![Vec::new(); domain_size], vec![Vec::new(); domain_size]];
+    let mut last_constraint = 0;
+    for _ in 0..coeffs.u32()? {
+        let matrix = coeffs.u32()? as usize;
+        let constraint = coeffs.u32()? as usize;
+        let signal = coeffs.u32()? as usize;
+        // Coefficients are stored in Montgomery form twice over
+        let value = Fr::new_unchecked(Fr::new_unchecked(coeffs.bigint::<Fr>()?).into_bigint());
+        let row = rows
+            .get_mut(matrix)
+            .and_then(|matrix| matrix.get_mut(constraint))
+            .filter(|_| signal < n_vars)
+            .ok_or_else(|| format!("coefficient out of range: matrix {}, constraint {}, signal {}", matrix, constraint, signal))?;
+        row.push((value, signal));
+        last_constraint = last_constraint.max(constraint);
+    }
+
+    // The last n_public + 1 rows are the input constraints snarkjs adds, which the reduction puts back
+    let num_constraints = last_constraint
+        .checked_sub(n_public)
+        .ok_or_else(|| format!("{} constraints for {} public signals", last_constraint, n_public))?;
+    let [mut a, mut b] = rows;
+    a.truncate(num_constraints);
+    b.truncate(num_constraints);
+    Ok(ConstraintMatrices {
+        num_instance_variables: n_public + 1,
+        num_witness_variables: n_vars - n_public,
+        num_constraints,
+        a_num_non_zero: a.iter().map(Vec::len).sum(),
+        b_num_non_zero: b.iter().map(Vec::len).sum(),
+        c_num_non_zero: 0,
+        a,
+        b,
+        c: Vec::new(),
+    })
+}
+
+/// Witness values of a snarkjs wtns file
+fn read_wtns(bytes: &[u8]) -> Result<Vec<Fr>, String> {
+    let sections = sections(bytes, b"wtns")?;
+    let mut header = section(&sections, 1)?;
+    let n8 = header.u32()? as usize;
+    if header.take(n8)? != Fr::MODULUS.to_bytes_le() {
+        return Err("the witness is not over the BN254 scalar field".to_string());
+    }
+    let count = header.u32()? as usize;
+    let mut values = section(&sections, 2)?;
+    (0..count).map(|_| values.take(n8).map(Fr::from_le_bytes_mod_order)).collect()
+}
+
+/// Witness calculator of a circom circuit: the generate_witness.js next to its wasm, run with node
+pub struct WitnessCalculator {
+    wasm: PathBuf,
+    script: PathBuf,
+}
+
+impl WitnessCalculator {
+    pub fn new(wasm: &Path) -> Result<Self, String> {
+        let script = wasm.with_file_name("generate_witness.js");
+        for file in [wasm, script.as_path()] {
+            if !file.exists() {
+                return Err(format!("{} not found", file.display()));
+            }
+        }
+        Ok(Self { wasm: wasm.to_path_buf(), script })
+    }
+
+    /// Full assignment for `inputs`: the constant 1, the public signals, then the private ones
+    pub fn calculate(&self, inputs: Vec<(String, Vec<BigInt>)>) -> Result<Vec<Fr>, String> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let id = format!("{}_{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let input_file = std::env::temp_dir().join(format!("prover_input_{}.json", id));
+        let witness_file = std::env::temp_dir().join(format!("prover_witness_{}.wtns", id));
+
+        let input: serde_json::Map<String, serde_json::Value> = inputs
+            .into_iter()
+            .map(|(name, values)| (name, values.iter().map(BigInt::to_string).collect()))
+            .collect();
+        std::fs::write(&input_file, serde_json::Value::Object(input).to_string())
+            .map_err(|e| format!("Failed to write {}: {}", input_file.display(), e))?;
+        let output = Command::new("node").arg(&self.script).arg(&self.wasm).arg(&input_file).arg(&witness_file).output();
+        let _ = std::fs::remove_file(&input_file);
+        let output = output.map_err(|e| format!("Failed to run node: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&witness_file);
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        let witness = std::fs::read(&witness_file).map_err(|e| format!("Failed to read {}: {}", witness_file.display(), e));
+        let _ = std::fs::remove_file(&witness_file);
+        read_wtns(&witness?)
+    }
+}
+
+/// The R1CS to QAP reduction of snarkjs. arkworks computes H's coefficients as (AB - C)/Z over the
+/// evaluation domain; snarkjs evaluates AB - C on the odd half of a domain twice as large, against
+/// powers of tau precomputed in Lagrange form, and that evaluation stands for HZ in C.
+pub struct CircomReduction;
+
+impl R1CSToQAP for CircomReduction {
+    #[allow(clippy::type_complexity)]
+    fn instance_map_with_evaluation<F: PrimeField, D: EvaluationDomain<F>>(
+        cs: ConstraintSystemRef<F>,
+        t: &F,
+    ) -> Result<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize), SynthesisError> {
+        LibsnarkReduction::instance_map_with_evaluation::<F, D>(cs, t)
+    }
+
+    fn witness_map_from_matrices<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        num_inputs: usize,
+        num_constraints: usize,
+        full_assignment: &[F],
+    ) -> Result<Vec<F>, SynthesisError> {
+        let domain = D::new(num_constraints + num_inputs).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let mut a = vec![F::zero(); domain.size()];
+        let mut b = vec![F::zero(); domain.size()];
+        for (i, (at_i, bt_i)) in matrices.a.iter().zip(&matrices.b).enumerate().take(num_constraints) {
+            a[i] = evaluate_constraint(at_i, full_assignment);
+            b[i] = evaluate_constraint(bt_i, full_assignment);
+        }
+        // The input constraints
+        a[num_constraints..num_constraints + num_inputs].clone_from_slice(&full_assignment[..num_inputs]);
+
+        let mut c = vec![F::zero(); domain.size()];
+        for ((c_i, a_i), b_i) in c.iter_mut().zip(&a).zip(&b).take(num_constraints) {
+            *c_i = *a_i * b_i;
+        }
+
+        // Move each evaluation to the odd points of the double domain
+        let root_of_unity = D::new(2 * domain.size()).ok_or(SynthesisError::PolynomialDegreeTooLarge)?.element(1);
+        for evaluations in [&mut a, &mut b, &mut c] {
+            domain.ifft_in_place(evaluations);
+            D::distribute_powers_and_mul_by_const(evaluations, root_of_unity, F::one());
+            domain.fft_in_place(evaluations);
+        }
+
+        let mut ab = domain.mul_polynomials_in_evaluation_domain(&a, &b);
+        for (ab_i, c_i) in ab.iter_mut().zip(c) {
+            *ab_i -= c_i;
+        }
+        Ok(ab)
+    }
+
+    fn h_query_scalars<F: PrimeField, D: EvaluationDomain<F>>(
+        max_power: usize,
+        t: F,
+        _: F,
+        delta_inverse: F,
+    ) -> Result<Vec<F>, SynthesisError> {
+        // H has domain - 1 powers and Z has domain powers, so HZ has 2 * domain - 1
+        let mut scalars: Vec<F> = (0..2 * max_power + 1).map(|i| delta_inverse * t.pow([i as u64])).collect();
+        let domain = D::new(scalars.len()).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        // Lagrange coefficients over the double domain, of which the odd points are used
+        domain.ifft_in_place(&mut scalars);
+        Ok(scalars.into_iter().skip(1).step_by(2).collect())
+    }
+}
+
+/// Sections of a snarkjs binary file by id, after checking its magic
+fn sections<'a>(bytes: &'a [u8], magic: &[u8; 4]) -> Result<HashMap<u32, &'a [u8]>, String> {
+    let mut reader = Reader { bytes };
+    if reader.take(4)? != magic {
+        return Err(format!("not a {} file", String::from_utf8_lossy(magic)));
+    }
+    let _version = reader.u32()?;
+    let mut sections = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let id = reader.u32()?;
+        let size = usize::try_from(reader.u64()?).map_err(|_| "section too large".to_string())?;
+        let data = reader.take(size)?;
+        sections.entry(id).or_insert(data);
+    }
+    Ok(sections)
+}
+
+fn section<'a>(sections: &HashMap<u32, &'a [u8]>, id: u32) -> Result<Reader<'a>, String> {
+    sections.get(&id).map(|bytes| Reader { bytes }).ok_or_else(|| format!("missing section {}", id))
+}
+
+/// Little-endian reads over a section
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.bytes.len() {
+            return Err("file is truncated".to_string());
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A 256-bit integer below the modulus of `F`
+    fn bigint<F: PrimeField<BigInt = BigInteger256>>(&mut self) -> Result<BigInteger256, String> {
+        let mut limbs = [0u64; 4];
+        for (limb, bytes) in limbs.iter_mut().zip(self.take(32)?.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        let value = BigInteger256::new(limbs);
+        if value >= F::MODULUS {
+            return Err("field element out of range".to_string());
+        }
+        Ok(value)
+    }
+
+    /// Base field element, stored in Montgomery form
+    fn fq(&mut self) -> Result<Fq, String> {
+        Ok(Fq::new_unchecked(self.bigint::<Fq>()?))
+    }
+
+    /// Curve point, with (0, 0) for the point at infinity
+    fn g1(&mut self) -> Result<G1Affine, String> {
+        let (x, y) = (self.fq()?, self.fq()?);
+        if x.is_zero() && y.is_zero() {
+            return Ok(G1Affine::identity());
+        }
+        let point = G1Affine::new_unchecked(x, y);
+        if !point.is_on_curve() {
+            return Err("G1 point is not on the curve".to_string());
+        }
+        Ok(point)
+    }
+
+    fn g2(&mut self) -> Result<G2Affine, String> {
+        let x = Fq2::new(self.fq()?, self.fq()?);
+        let y = Fq2::new(self.fq()?, self.fq()?);
+        if x.is_zero() && y.is_zero() {
+            return Ok(G2Affine::identity());
+        }
+        let point = G2Affine::new_unchecked(x, y);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return Err("G2 point is not in the prime order subgroup".to_string());
+        }
+        Ok(point)
+    }
+
+    fn g1s(&mut self, count: usize) -> Result<Vec<G1Affine>, String> {
+        (0..count).map(|_| self.g1()).collect()
+    }
+
+    fn g2s(&mut self, count: usize) -> Result<Vec<G2Affine>, String> {
+        (0..count).map(|_| self.g2()).collect()
+    }
+}
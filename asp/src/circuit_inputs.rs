@@ -0,0 +1,422 @@
+// Withdraw and swap circuit inputs
+// Typed inputs of circuits/withdraw.circom and swap.circom. WithdrawWitnessBuilder and
+// SwapWitnessBuilder assemble them from the notes, the Merkle path and the swap quote by name
+// instead of positional JSON, and `check` applies the circuit's constraints (the note opens to
+// the leaf the path leads from, the output commitment, the CLMM price move), so a malformed
+// input fails here with a reason instead of in the witness calculator after the zkey loaded.
+
+use crate::merkle::MerkleProof;
+use crate::witness::{mask250, poseidon, reduce, NoteOpening, MEMBERSHIP_DEPTH};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
+
+/// Inputs of withdraw.circom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawInputs {
+    // Public inputs
+    pub nullifier: BigUint,
+    pub root: BigUint,
+    pub recipient: BigUint,
+    pub amount: BigUint,
+    // Private inputs
+    pub secret: BigUint,
+    pub path_elements: Vec<BigUint>,
+    pub path_indices: Vec<u8>,
+}
+
+impl WithdrawInputs {
+    /// The constraints of withdraw.circom: the note leads to `root`, the amount fits 252 bits
+    pub fn check(&self) -> Result<(), String> {
+        let commitment = commitment(&self.secret, &self.nullifier, &self.amount);
+        check_path(&commitment, &self.path_elements, &self.path_indices, &self.root)?;
+        if self.amount.bits() > 252 {
+            return Err("The amount doesn't fit in 252 bits".to_string());
+        }
+        Ok(())
+    }
+
+    /// Input file of the witness calculator
+    pub fn input_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "nullifier": self.nullifier.to_str_radix(10),
+            "root": self.root.to_str_radix(10),
+            "recipient": self.recipient.to_str_radix(10),
+            "amount": self.amount.to_str_radix(10),
+            "secret": self.secret.to_str_radix(10),
+            "pathElements": decimal(&self.path_elements),
+            "pathIndices": self.path_indices.iter().map(|index| index.to_string()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Inputs of swap.circom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapInputs {
+    // Public inputs
+    pub nullifier: BigUint,
+    pub root: BigUint,
+    pub new_commitment: BigUint,
+    pub amount_specified: BigUint,
+    pub zero_for_one: BigUint,
+    pub amount0_delta: BigUint,
+    pub amount1_delta: BigUint,
+    pub new_sqrt_price_x128: BigUint,
+    pub new_tick: BigUint,
+    // Private inputs
+    pub secret_in: BigUint,
+    pub amount_in: BigUint,
+    pub secret_out: BigUint,
+    pub nullifier_out: BigUint,
+    pub amount_out: BigUint,
+    pub path_elements: Vec<BigUint>,
+    pub path_indices: Vec<u8>,
+    pub sqrt_price_old: BigUint,
+    pub liquidity: BigUint,
+}
+
+impl SwapInputs {
+    /// Inputs from the snarkjs input file /api/proof/swap builds (decimal, negative or 0x hex)
+    pub fn from_input_json(input: &serde_json::Value) -> Result<Self, String> {
+        let get = |name: &str| input.get(name).ok_or_else(|| format!("Missing swap input {}", name));
+        let field = |name: &str| get(name).and_then(|value| json_field(name, value));
+        let list = |name: &str| {
+            get(name)?.as_array().ok_or_else(|| format!("Swap input {} is not a list", name))?.iter().map(|value| json_field(name, value)).collect::<Result<Vec<_>, _>>()
+        };
+        let path_indices = list("pathIndices")?
+            .into_iter()
+            .map(|index| u8::try_from(index).ok().filter(|index| *index <= 1).ok_or("Path indices must be 0 or 1".to_string()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            nullifier: field("nullifier")?,
+            root: field("root")?,
+            new_commitment: field("new_commitment")?,
+            amount_specified: field("amount_specified")?,
+            zero_for_one: field("zero_for_one")?,
+            amount0_delta: field("amount0_delta")?,
+            amount1_delta: field("amount1_delta")?,
+            new_sqrt_price_x128: field("new_sqrt_price_x128")?,
+            new_tick: field("new_tick")?,
+            secret_in: field("secret_in")?,
+            amount_in: field("amount_in")?,
+            secret_out: field("secret_out")?,
+            nullifier_out: field("nullifier_out")?,
+            amount_out: field("amount_out")?,
+            path_elements: list("pathElements")?,
+            path_indices,
+            sqrt_price_old: field("sqrt_price_old")?,
+            liquidity: field("liquidity")?,
+        })
+    }
+
+    /// The constraints of swap.circom: the input note leads to `root`, the output note opens to
+    /// `new_commitment`, and amount_out * 2^128 == liquidity * (sqrt_price_old - new_sqrt_price_x128)
+    pub fn check(&self) -> Result<(), String> {
+        if self.zero_for_one > BigUint::from(1u8) {
+            return Err("zero_for_one must be 0 or 1".to_string());
+        }
+        let commitment_in = commitment(&self.secret_in, &self.nullifier, &self.amount_in);
+        check_path(&commitment_in, &self.path_elements, &self.path_indices, &self.root)?;
+        let commitment_out = commitment(&self.secret_out, &self.nullifier_out, &self.amount_out);
+        if commitment_out != self.new_commitment {
+            return Err(format!("The output note opens to 0x{:x}, not to new_commitment 0x{:x}", commitment_out, self.new_commitment));
+        }
+        let modulus = BigUint::from(Fr::MODULUS);
+        let price_move = (&self.sqrt_price_old + &modulus - &self.new_sqrt_price_x128 % &modulus) % &modulus;
+        if (&self.liquidity * price_move) % &modulus != (&self.amount_out << 128u32) % &modulus {
+            return Err("amount_out doesn't match the price move for this liquidity".to_string());
+        }
+        Ok(())
+    }
+
+    /// Input file of the witness calculator
+    pub fn input_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "nullifier": self.nullifier.to_str_radix(10),
+            "root": self.root.to_str_radix(10),
+            "new_commitment": self.new_commitment.to_str_radix(10),
+            "amount_specified": self.amount_specified.to_str_radix(10),
+            "zero_for_one": self.zero_for_one.to_str_radix(10),
+            "amount0_delta": self.amount0_delta.to_str_radix(10),
+            "amount1_delta": self.amount1_delta.to_str_radix(10),
+            "new_sqrt_price_x128": self.new_sqrt_price_x128.to_str_radix(10),
+            "new_tick": self.new_tick.to_str_radix(10),
+            "secret_in": self.secret_in.to_str_radix(10),
+            "amount_in": self.amount_in.to_str_radix(10),
+            "secret_out": self.secret_out.to_str_radix(10),
+            "nullifier_out": self.nullifier_out.to_str_radix(10),
+            "amount_out": self.amount_out.to_str_radix(10),
+            "pathElements": decimal(&self.path_elements),
+            "pathIndices": self.path_indices.iter().map(|index| index.to_string()).collect::<Vec<_>>(),
+            "sqrt_price_old": self.sqrt_price_old.to_str_radix(10),
+            "liquidity": self.liquidity.to_str_radix(10),
+        })
+    }
+}
+
+/// Merkle path of the spent note: the root it leads to, siblings from the leaf up, and 0 where
+/// the node is a left child, 1 where it is a right one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotePath {
+    pub root: BigUint,
+    pub elements: Vec<BigUint>,
+    pub indices: Vec<u8>,
+}
+
+impl NotePath {
+    /// Path of a leaf as the ASP serves it (/api/merkle/proof)
+    pub fn from_proof(proof: &MerkleProof) -> Result<Self, String> {
+        let hex = |name: &str, value: &str| {
+            BigUint::from_str_radix(value.trim_start_matches("0x"), 16).map_err(|e| format!("Invalid {} {}: {}", name, value, e))
+        };
+        Ok(Self {
+            root: hex("root", &proof.root)?,
+            elements: proof.path.iter().map(|element| hex("path element", element)).collect::<Result<_, _>>()?,
+            indices: proof.path_indices.iter().map(|index| u8::try_from(*index).map_err(|_| format!("Path index {} is not 0 or 1", index))).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Builds withdraw.circom inputs from the note, its path and the recipient
+#[derive(Debug, Clone, Default)]
+pub struct WithdrawWitnessBuilder {
+    note: Option<NoteOpening>,
+    path: Option<NotePath>,
+    recipient: Option<BigUint>,
+}
+
+impl WithdrawWitnessBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The note withdrawn in full
+    pub fn with_note(mut self, note: NoteOpening) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    pub fn with_path(mut self, path: NotePath) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Starknet address receiving the funds
+    pub fn with_recipient(mut self, recipient: BigUint) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    pub fn build(self) -> Result<WithdrawInputs, String> {
+        let note = self.note.ok_or("Missing withdraw note")?;
+        let path = self.path.ok_or("Missing withdraw Merkle path")?;
+        let recipient = self.recipient.ok_or("Missing withdraw recipient")?;
+        if recipient.is_zero() || recipient.bits() > 251 {
+            return Err(format!("0x{:x} is not a Starknet address", recipient));
+        }
+        let inputs = WithdrawInputs {
+            nullifier: reduce(&note.nullifier),
+            root: path.root,
+            recipient,
+            amount: BigUint::from(note.amount),
+            secret: reduce(&note.secret),
+            path_elements: path.elements,
+            path_indices: path.indices,
+        };
+        inputs.check()?;
+        Ok(inputs)
+    }
+}
+
+/// Outcome of the swap as the pool computes it, from `sqrt_price_old` to `new_sqrt_price_x128`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_specified: u128,
+    pub zero_for_one: bool,
+    pub amount0_delta: i128,
+    pub amount1_delta: i128,
+    pub sqrt_price_old: BigUint,
+    pub new_sqrt_price_x128: BigUint,
+    pub new_tick: i32,
+    pub liquidity: u128,
+}
+
+/// Builds swap.circom inputs from the spent note, its path, the output note and the quote
+#[derive(Debug, Clone, Default)]
+pub struct SwapWitnessBuilder {
+    note: Option<NoteOpening>,
+    path: Option<NotePath>,
+    output: Option<NoteOpening>,
+    quote: Option<SwapQuote>,
+}
+
+impl SwapWitnessBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The note spent by the swap
+    pub fn with_note(mut self, note: NoteOpening) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    pub fn with_path(mut self, path: NotePath) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// The note the swap output goes to
+    pub fn with_output_note(mut self, output: NoteOpening) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    pub fn with_quote(mut self, quote: SwapQuote) -> Self {
+        self.quote = Some(quote);
+        self
+    }
+
+    pub fn build(self) -> Result<SwapInputs, String> {
+        let note = self.note.ok_or("Missing swap input note")?;
+        let path = self.path.ok_or("Missing swap Merkle path")?;
+        let output = self.output.ok_or("Missing swap output note")?;
+        let quote = self.quote.ok_or("Missing swap quote")?;
+        if quote.amount_specified == 0 || quote.amount_specified > note.amount {
+            return Err(format!("amount_specified {} must be between 1 and the note's {}", quote.amount_specified, note.amount));
+        }
+        if quote.liquidity == 0 {
+            return Err("The pool has no liquidity in range".to_string());
+        }
+        let inputs = SwapInputs {
+            nullifier: reduce(&note.nullifier),
+            root: path.root,
+            new_commitment: output.commitment(),
+            amount_specified: BigUint::from(quote.amount_specified),
+            zero_for_one: BigUint::from(quote.zero_for_one as u8),
+            amount0_delta: signed(quote.amount0_delta),
+            amount1_delta: signed(quote.amount1_delta),
+            new_sqrt_price_x128: quote.new_sqrt_price_x128,
+            new_tick: signed(quote.new_tick.into()),
+            secret_in: reduce(&note.secret),
+            amount_in: BigUint::from(note.amount),
+            secret_out: reduce(&output.secret),
+            nullifier_out: reduce(&output.nullifier),
+            amount_out: BigUint::from(output.amount),
+            path_elements: path.elements,
+            path_indices: path.indices,
+            sqrt_price_old: quote.sqrt_price_old,
+            liquidity: BigUint::from(quote.liquidity),
+        };
+        inputs.check()?;
+        Ok(inputs)
+    }
+}
+
+/// Mask(Poseidon(Poseidon(secret, nullifier), amount)), as both circuits open a note
+fn commitment(secret: &BigUint, nullifier: &BigUint, amount: &BigUint) -> BigUint {
+    mask250(poseidon(&poseidon(secret, nullifier), amount))
+}
+
+/// MerkleTreeChecker: `leaf` and the path lead to `root`
+fn check_path(leaf: &BigUint, elements: &[BigUint], indices: &[u8], root: &BigUint) -> Result<(), String> {
+    if elements.len() != MEMBERSHIP_DEPTH || indices.len() != MEMBERSHIP_DEPTH {
+        return Err(format!("The circuit takes a path of {} levels, got {}", MEMBERSHIP_DEPTH, elements.len()));
+    }
+    let mut current = leaf.clone();
+    for (sibling, index) in elements.iter().zip(indices) {
+        current = match index {
+            0 => mask250(poseidon(&current, sibling)),
+            1 => mask250(poseidon(sibling, &current)),
+            _ => return Err(format!("Path index {} is not 0 or 1", index)),
+        };
+    }
+    if &current != root {
+        return Err(format!("The note leads to 0x{:x}, not to the root 0x{:x}", current, root));
+    }
+    Ok(())
+}
+
+/// A signed value as the circuit reads it: negative ones as p - |value|
+fn signed(value: i128) -> BigUint {
+    let modulus = BigUint::from(Fr::MODULUS);
+    match value < 0 {
+        true => modulus - BigUint::from(value.unsigned_abs()),
+        false => BigUint::from(value as u128),
+    }
+}
+
+fn decimal(values: &[BigUint]) -> Vec<String> {
+    values.iter().map(|value| value.to_str_radix(10)).collect()
+}
+
+/// A signal as snarkjs reads it: decimal, negative decimal or 0x hex, reduced modulo the
+/// BN254 scalar field (so -1 is p - 1)
+pub fn parse_field(name: &str, value: &str) -> Result<BigUint, String> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let parsed = match digits.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16),
+        None => BigUint::from_str_radix(digits, 10),
+    }
+    .map_err(|e| format!("Invalid {} {}: {}", name, value, e))?;
+    let modulus = BigUint::from(Fr::MODULUS);
+    let reduced = parsed % &modulus;
+    Ok(if negative && reduced != BigUint::default() { modulus - reduced } else { reduced })
+}
+
+pub fn json_field(name: &str, value: &serde_json::Value) -> Result<BigUint, String> {
+    match value {
+        serde_json::Value::String(value) => parse_field(name, value),
+        serde_json::Value::Number(value) => parse_field(name, &value.to_string()),
+        _ => Err(format!("Input {} is not a number", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    #[test]
+    fn test_builders_check_the_circuit_constraints() {
+        let note = NoteOpening::from_hex("0x1234567890abcdef", "0xfedcba0987654321", 1_000_000).unwrap();
+        let mut tree = MerkleTree::new(MEMBERSHIP_DEPTH);
+        tree.insert(BigUint::from(7u8)).unwrap();
+        tree.insert(note.commitment()).unwrap();
+        let path = NotePath::from_proof(&tree.get_proof(1).unwrap()).unwrap();
+
+        let withdraw = WithdrawWitnessBuilder::new().with_note(note.clone()).with_path(path.clone()).with_recipient(BigUint::from(0xabcu32));
+        assert_eq!(withdraw.clone().build().unwrap().input_json()["amount"], "1000000");
+        assert!(WithdrawWitnessBuilder::new().with_note(note.clone()).with_path(path.clone()).build().unwrap_err().contains("recipient"));
+        let other = NoteOpening { amount: 5, ..note.clone() };
+        assert!(withdraw.with_note(other).build().unwrap_err().contains("leads to"));
+
+        // Liquidity 2^127 moving the price down by 800_000 pays out 400_000
+        let output = NoteOpening::from_hex("0xaa", "0xbb", 400_000).unwrap();
+        let new_sqrt_price_x128 = BigUint::from(1u8) << 130u32;
+        let quote = SwapQuote {
+            amount_specified: 500_000,
+            zero_for_one: true,
+            amount0_delta: 500_000,
+            amount1_delta: -400_000,
+            sqrt_price_old: &new_sqrt_price_x128 + 800_000u32,
+            new_sqrt_price_x128,
+            new_tick: -12,
+            liquidity: 1 << 127,
+        };
+        let swap = |quote: SwapQuote| SwapWitnessBuilder::new().with_note(note.clone()).with_path(path.clone()).with_output_note(output.clone()).with_quote(quote).build();
+        let inputs = swap(quote.clone()).unwrap();
+        assert_eq!(inputs.new_commitment, output.commitment());
+        assert_eq!(inputs.amount1_delta, parse_field("amount1_delta", "-400000").unwrap());
+        assert_eq!(SwapInputs::from_input_json(&inputs.input_json()).unwrap(), inputs);
+        assert!(swap(SwapQuote { liquidity: 1 << 126, ..quote.clone() }).unwrap_err().contains("price move"));
+        assert!(swap(SwapQuote { amount_specified: 2_000_000, ..quote }).unwrap_err().contains("amount_specified"));
+        let tampered = SwapInputs { new_commitment: BigUint::from(1u8), ..inputs };
+        assert!(tampered.check().unwrap_err().contains("new_commitment"));
+    }
+}
@@ -42,21 +42,10 @@ pub fn generate_commitment(secret: &str, nullifier: &str, amount: u128) -> Resul
     Ok(format!("0x{:x}", safe_val))
 }
 
-/// Generate random secret and nullifier
+/// Generate random secret and nullifier (below 2^248, see notes::random_value)
 pub fn generate_note() -> (String, String) {
-    use rand::Rng;
-    
-    let mut rng = rand::thread_rng();
-    
-    // Generate 32 random bytes for secret
-    let secret_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-    let secret = format!("0x{}", hex::encode(secret_bytes));
-    
-    // Generate 32 random bytes for nullifier
-    let nullifier_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-    let nullifier = format!("0x{}", hex::encode(nullifier_bytes));
-    
-    (secret, nullifier)
+    use crate::notes::{random_value, to_hex};
+    (to_hex(&random_value()), to_hex(&random_value()))
 }
 
 /// Parse felt252 from hex string to Fr
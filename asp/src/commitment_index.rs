@@ -0,0 +1,151 @@
+// Commitment lookups
+// The syncer records the leaf index of every deposit it applies, so "which leaf is this
+// commitment?" is a map lookup instead of a scan of every event since the deployment. The
+// map is rebuilt from the leaves saved in the sync checkpoint on start, and after rollbacks.
+// Only commitments newer than `synced_block` can be missing from it. The block and transaction
+// of each deposit are kept alongside and saved with the checkpoint leaves.
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::FieldElement;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Where a deposit was made
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafOrigin {
+    pub block_number: u64,
+    #[serde(with = "crate::conv::felt_hex")]
+    pub transaction_hash: FieldElement,
+}
+
+/// Synced deposit of a commitment
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct LeafInfo {
+    pub leaf_index: u32,
+    /// Unknown for leaves synced before origins were recorded
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<String>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    leaves: HashMap<FieldElement, u32>,
+    origins: HashMap<FieldElement, LeafOrigin>,
+    /// Every deposit up to this block is in `leaves`
+    synced_block: u64,
+}
+
+/// Shared commitment -> leaf index map; clones see the same entries
+#[derive(Clone, Default)]
+pub struct CommitmentIndex {
+    state: Arc<RwLock<IndexState>>,
+}
+
+impl CommitmentIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaf index of `commitment`, if it was deposited up to `synced_block`
+    pub fn get(&self, commitment: &FieldElement) -> Option<u32> {
+        self.state.read().unwrap().leaves.get(commitment).copied()
+    }
+
+    /// Leaf index, block and transaction of `commitment`, if it was deposited up to `synced_block`
+    pub fn contains(&self, commitment: &FieldElement) -> Option<LeafInfo> {
+        let state = self.state.read().unwrap();
+        let leaf_index = *state.leaves.get(commitment)?;
+        let origin = state.origins.get(commitment);
+        Some(LeafInfo {
+            leaf_index,
+            block_number: origin.map(|origin| origin.block_number),
+            transaction_hash: origin.map(|origin| format!("0x{:x}", origin.transaction_hash)),
+        })
+    }
+
+    /// Origin of each of `leaves` (in leaf index order), to save with them
+    pub fn origins(&self, leaves: &[FieldElement]) -> Vec<Option<LeafOrigin>> {
+        let state = self.state.read().unwrap();
+        leaves.iter().map(|leaf| state.origins.get(leaf).copied()).collect()
+    }
+
+    /// Block the index is complete up to (0 before the first sync)
+    pub fn synced_block(&self) -> u64 {
+        self.state.read().unwrap().synced_block
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().leaves.len()
+    }
+
+    pub fn insert(&self, commitment: FieldElement, leaf_index: u32, origin: LeafOrigin) {
+        let mut state = self.state.write().unwrap();
+        state.leaves.insert(commitment, leaf_index);
+        state.origins.insert(commitment, origin);
+    }
+
+    /// Every deposit up to `block` has been inserted
+    pub fn set_synced_block(&self, block: u64) {
+        self.state.write().unwrap().synced_block = block;
+    }
+
+    /// Forget the origin of deposits made before `before_block`; their leaf index stays
+    pub fn prune_origins(&self, before_block: u64) -> usize {
+        let mut state = self.state.write().unwrap();
+        let before = state.origins.len();
+        state.origins.retain(|_, origin| origin.block_number >= before_block);
+        before - state.origins.len()
+    }
+
+    /// Replace the entries with `leaves` (in leaf index order, zero for gaps) and their
+    /// `origins` (same order, possibly shorter)
+    pub fn rebuild(&self, leaves: &[FieldElement], origins: &[Option<LeafOrigin>], synced_block: u64) {
+        let mut state = self.state.write().unwrap();
+        state.leaves = leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| **leaf != FieldElement::ZERO)
+            .map(|(index, leaf)| (*leaf, index as u32))
+            .collect();
+        state.origins = leaves
+            .iter()
+            .zip(origins)
+            .filter_map(|(leaf, origin)| Some((*leaf, (*origin)?)))
+            .filter(|(leaf, _)| *leaf != FieldElement::ZERO)
+            .collect();
+        state.synced_block = synced_block;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuild_skips_gaps() {
+        let index = CommitmentIndex::new();
+        let shared = index.clone();
+        let origin = LeafOrigin { block_number: 38, transaction_hash: FieldElement::from(0xabcu64) };
+        index.rebuild(&[FieldElement::from(7u64), FieldElement::ZERO, FieldElement::from(9u64)], &[Some(origin)], 40);
+
+        assert_eq!(shared.get(&FieldElement::from(9u64)), Some(2));
+        assert_eq!(shared.get(&FieldElement::ZERO), None);
+        assert_eq!(shared.len(), 2);
+        assert_eq!(shared.synced_block(), 40);
+
+        index.insert(FieldElement::from(11u64), 3, LeafOrigin { block_number: 41, transaction_hash: FieldElement::from(0xdefu64) });
+        assert_eq!(shared.get(&FieldElement::from(11u64)), Some(3));
+        assert_eq!(
+            shared.contains(&FieldElement::from(11u64)),
+            Some(LeafInfo { leaf_index: 3, block_number: Some(41), transaction_hash: Some("0xdef".to_string()) })
+        );
+        // Leaf 2 was saved without an origin
+        assert_eq!(shared.contains(&FieldElement::from(9u64)).unwrap().block_number, None);
+        assert_eq!(shared.origins(&[FieldElement::from(7u64), FieldElement::from(9u64)]), vec![Some(origin), None]);
+
+        // Pruned origins go, their leaf indexes stay
+        assert_eq!(index.prune_origins(41), 1);
+        assert_eq!(shared.contains(&FieldElement::from(7u64)), Some(LeafInfo { leaf_index: 0, block_number: None, transaction_hash: None }));
+        assert_eq!(shared.contains(&FieldElement::from(11u64)).unwrap().block_number, Some(41));
+    }
+}
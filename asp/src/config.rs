@@ -0,0 +1,116 @@
+// Transport settings for the RPC client
+// One place for the knobs operators actually tune (connect/request timeouts, concurrency,
+// keep-alive, request rate), applied to every endpoint and every request of a BlockchainClient.
+
+use crate::provider::HttpPoolConfig;
+use crate::rate_limit::RateLimit;
+use crate::timeouts::DEFAULT_CALL_TIMEOUT;
+use std::time::Duration;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Max time to establish a connection to an RPC endpoint
+    pub connect_timeout: Duration,
+    /// Budget of a request without a per-operation override (see TimeoutProfile)
+    pub request_timeout: Duration,
+    /// Max concurrent requests per RPC endpoint
+    pub max_connections: usize,
+    /// TCP keep-alive interval, also how long idle connections are kept
+    pub keep_alive: Duration,
+    /// Max request rate per RPC endpoint (unlimited if unset)
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_CALL_TIMEOUT,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            rate_limit: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Defaults overridden by RPC_CONNECT_TIMEOUT_MS, RPC_REQUEST_TIMEOUT_MS,
+    /// RPC_MAX_CONNECTIONS, RPC_KEEP_ALIVE_SECS and RPC_RATE_LIMIT_RPS (with RPC_RATE_LIMIT_BURST,
+    /// defaulting to the per-second rate)
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let ms = |key: &str, default: Duration| parse_var(&lookup, key).map_or(default, Duration::from_millis);
+        Self {
+            connect_timeout: ms("RPC_CONNECT_TIMEOUT_MS", defaults.connect_timeout),
+            request_timeout: ms("RPC_REQUEST_TIMEOUT_MS", defaults.request_timeout),
+            max_connections: parse_var(&lookup, "RPC_MAX_CONNECTIONS").map_or(defaults.max_connections, |n| n as usize),
+            keep_alive: parse_var(&lookup, "RPC_KEEP_ALIVE_SECS").map_or(defaults.keep_alive, Duration::from_secs),
+            rate_limit: parse_var(&lookup, "RPC_RATE_LIMIT_RPS").map(|rps| {
+                let burst = parse_var(&lookup, "RPC_RATE_LIMIT_BURST").unwrap_or(rps);
+                RateLimit::new(rps as u32, burst as u32)
+            }),
+        }
+    }
+
+    /// Connection pool settings of each HTTP endpoint
+    pub fn pool(&self) -> HttpPoolConfig {
+        HttpPoolConfig {
+            max_idle_per_host: Some(self.max_connections),
+            idle_timeout: Some(self.keep_alive),
+            tcp_keepalive: Some(self.keep_alive),
+            connect_timeout: Some(self.connect_timeout),
+            max_connections: Some(self.max_connections),
+            rate_limit: self.rate_limit,
+            endpoint_rate_limits: Vec::new(),
+        }
+    }
+}
+
+/// A positive integer variable; invalid values are reported and ignored
+fn parse_var(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Option<u64> {
+    let value = lookup(key)?;
+    match value.trim().parse::<u64>() {
+        Ok(n) if n > 0 => Some(n),
+        _ => {
+            eprintln!("⚠️  Invalid {} '{}' - using default", key, value);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_env_overrides() {
+        let vars: HashMap<&str, &str> = [
+            ("RPC_CONNECT_TIMEOUT_MS", "2500"),
+            ("RPC_MAX_CONNECTIONS", "8"),
+            ("RPC_KEEP_ALIVE_SECS", "not a number"),
+            ("RPC_REQUEST_TIMEOUT_MS", "0"),
+            ("RPC_RATE_LIMIT_RPS", "25"),
+        ]
+        .into_iter()
+        .collect();
+
+        let config = ClientConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string()));
+        assert_eq!(config.connect_timeout, Duration::from_millis(2500));
+        assert_eq!(config.max_connections, 8);
+        // Invalid values fall back to the defaults
+        assert_eq!(config.keep_alive, DEFAULT_KEEP_ALIVE);
+        assert_eq!(config.request_timeout, DEFAULT_CALL_TIMEOUT);
+        assert_eq!(config.pool().max_connections, Some(8));
+        // The burst defaults to one second worth of requests
+        assert_eq!(config.rate_limit, Some(RateLimit::new(25, 25)));
+    }
+}
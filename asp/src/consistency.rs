@@ -0,0 +1,129 @@
+// Tree vs chain consistency
+// The syncer's checkpoint says "every deposit up to block N is in the tree". This checks
+// that claim: the root of the checkpoint leaves must be the contract's root at block N. When
+// it isn't, the Deposit events of the last blocks are rescanned to tell why: leaves the tree
+// never got, leaves holding another commitment, or a leaf count off from the contract's.
+
+use crate::events::DepositEvent;
+use serde::Serialize;
+use starknet::core::types::FieldElement;
+use std::collections::BTreeSet;
+
+/// Blocks before the checkpoint block rescanned for Deposit events when the roots differ
+pub const CONSISTENCY_LOOKBACK_BLOCKS: u64 = 1_000;
+
+/// One way the local tree differs from the contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Divergence {
+    RootMismatch { local: String, on_chain: String },
+    LeafCountMismatch { local: u32, on_chain: u32 },
+    /// Leaves the contract has and the tree doesn't (from the rescanned events or the leaf count)
+    MissingEvents { leaf_indices: Vec<u32> },
+    /// Leaves holding another commitment than their Deposit event
+    DifferingLeaves { leaf_indices: Vec<u32> },
+}
+
+/// Local tree at the checkpoint block compared with the contract at the same block
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsistencyReport {
+    /// Checkpoint block both roots are taken at
+    pub block: u64,
+    pub local_root: String,
+    pub on_chain_root: String,
+    pub local_leaf_count: u32,
+    /// Leaves the contract had at `block`, when the rescan or the root history tells
+    pub on_chain_leaf_count: Option<u32>,
+    /// Blocks rescanned for Deposit events, if the roots differed
+    pub scanned_blocks: Option<(u64, u64)>,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// Explain a root mismatch from the checkpoint `leaves` and the `deposits` rescanned up to
+    /// `block`; `known_leaf_count` is the leaf count the local tree had at the on-chain root, if
+    /// it ever produced it
+    pub fn diagnose(&mut self, leaves: &[FieldElement], deposits: &[DepositEvent], known_leaf_count: Option<u32>) {
+        let on_chain_root = FieldElement::from_hex_be(&self.on_chain_root).ok();
+        // The deposit that produced the on-chain root is the last one up to the block
+        self.on_chain_leaf_count = deposits
+            .iter()
+            .find(|deposit| Some(deposit.root) == on_chain_root)
+            .map(|deposit| deposit.leaf_index + 1)
+            .or(known_leaf_count)
+            .or_else(|| deposits.iter().map(|deposit| deposit.leaf_index + 1).max());
+
+        let mut missing = BTreeSet::new();
+        let mut differing = BTreeSet::new();
+        for deposit in deposits {
+            match leaves.get(deposit.leaf_index as usize) {
+                None | Some(&FieldElement::ZERO) => {
+                    missing.insert(deposit.leaf_index);
+                }
+                Some(leaf) if *leaf != deposit.commitment => {
+                    differing.insert(deposit.leaf_index);
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(on_chain) = self.on_chain_leaf_count {
+            if on_chain != self.local_leaf_count {
+                self.divergences.push(Divergence::LeafCountMismatch { local: self.local_leaf_count, on_chain });
+            }
+            // Deposits older than the rescan the tree is short of
+            missing.extend(self.local_leaf_count..on_chain);
+        }
+        if !missing.is_empty() {
+            self.divergences.push(Divergence::MissingEvents { leaf_indices: missing.into_iter().collect() });
+        }
+        if !differing.is_empty() {
+            self.divergences.push(Divergence::DifferingLeaves { leaf_indices: differing.into_iter().collect() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit(commitment: u64, leaf_index: u32, root: u64) -> DepositEvent {
+        DepositEvent {
+            commitment: FieldElement::from(commitment),
+            leaf_index,
+            root: FieldElement::from(root),
+            amount: None,
+        }
+    }
+
+    #[test]
+    fn test_diagnose_missing_and_differing_leaves() {
+        let mut report = ConsistencyReport {
+            block: 50,
+            local_root: "0x1".to_string(),
+            on_chain_root: "0x5".to_string(),
+            local_leaf_count: 3,
+            on_chain_leaf_count: None,
+            scanned_blocks: Some((1, 50)),
+            divergences: vec![Divergence::RootMismatch { local: "0x1".to_string(), on_chain: "0x5".to_string() }],
+        };
+        // Leaf 1 was never applied, leaf 2 holds another commitment and leaves 3 and 4 are missing
+        let leaves = [FieldElement::from(1000u64), FieldElement::ZERO, FieldElement::from(9u64)];
+        let deposits = [deposit(1001, 1, 2), deposit(1002, 2, 3), deposit(1004, 4, 5)];
+        report.diagnose(&leaves, &deposits, None);
+
+        assert_eq!(report.on_chain_leaf_count, Some(5));
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.divergences[1..],
+            [
+                Divergence::LeafCountMismatch { local: 3, on_chain: 5 },
+                Divergence::MissingEvents { leaf_indices: vec![1, 3, 4] },
+                Divergence::DifferingLeaves { leaf_indices: vec![2] },
+            ]
+        );
+    }
+}
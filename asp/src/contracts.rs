@@ -0,0 +1,252 @@
+// Multi-contract sync
+// One ASP process can follow several Zylith deployments (several pools, staging and prod).
+// Each contract gets its own ContractSync: blockchain client, deposit tree, commitment index,
+// sync progress and syncer, persisted to its own checkpoint file. ContractRegistry finds them
+// by address; the first configured contract is the primary one, served by the unprefixed
+// endpoints as before.
+
+use crate::blockchain::BlockchainClient;
+use crate::checkpoint::CheckpointStore;
+use crate::commitment_index::CommitmentIndex;
+use crate::config::ClientConfig;
+use crate::error::BlockchainError;
+use crate::event_fetcher::FetchConfig;
+use crate::maintenance::{spawn_maintenance, MaintenanceConfig};
+use crate::merkle::{MerkleTree, TreeConfig};
+use crate::page_size::PageSizeBounds;
+use crate::redact::RedactionPolicy;
+use crate::state_store::StateBackend;
+use crate::sync_status::SyncTracker;
+use crate::syncer::Syncer;
+use crate::tree_store::TreeStore;
+use crate::watchdog::{Watchdog, WatchdogConfig};
+use starknet::core::types::FieldElement;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Contract synced when neither CONTRACT_ADDRESSES nor CONTRACT_ADDRESS is set
+pub const DEFAULT_CONTRACT_ADDRESS: &str = "0x05c1fecd5025d2e3d71482ac26ecf9138b08c9483f43761543cf71960373ce0b";
+
+/// Everything synced for one Zylith contract
+pub struct ContractSync {
+    pub address: FieldElement,
+    /// Blockchain client bound to this contract
+    pub blockchain: Arc<BlockchainClient>,
+    /// Deposit tree rebuilt from this contract's events
+    pub deposit_tree: Arc<Mutex<MerkleTree>>,
+    /// Progress of this contract's syncer
+    pub sync_status: SyncTracker,
+    pub syncer: Arc<Syncer>,
+}
+
+impl ContractSync {
+    /// Client, tree and syncer for the contract at `address`, saving progress to `store`
+    /// A fresh syncer starts from `snapshot` when given. Fails if no contract is deployed there.
+    pub async fn connect(
+        rpc_url: &str,
+        address: &str,
+        store: CheckpointStore,
+        snapshot: Option<String>,
+        redaction: RedactionPolicy,
+    ) -> Result<Self, BlockchainError> {
+        // MERKLE_DEPTH / MERKLE_HASH / MERKLE_DOMAIN: the contract's tree, so local roots and
+        // paths match its own; checked against the contract before syncing
+        let tree_config = TreeConfig::from_env();
+        // Filled by the syncer, read by commitment lookups before falling back to event scans
+        let commitments = CommitmentIndex::new();
+        let blockchain = BlockchainClient::builder(rpc_url, address)
+            .client_config(ClientConfig::from_env())
+            // INCLUDE_PENDING=true: also answer from the pending block, labeled unconfirmed
+            .include_pending(std::env::var("INCLUDE_PENDING").is_ok_and(|v| v == "true" || v == "1"))
+            .event_page_bounds(PageSizeBounds::from_env())
+            .tree_config(tree_config)
+            .build_checked()
+            .await?
+            .with_redaction_policy(redaction)
+            .with_commitment_index(commitments.clone());
+        blockchain.validate_tree_config().await?;
+        let blockchain = Arc::new(blockchain);
+
+        // STATE_BACKEND: checkpoint file (json), a database next to it (sqlite, rocksdb) or one
+        // shared with other instances (postgres)
+        let state_store = StateBackend::from_env()
+            .open(&store)
+            .map_err(|e| BlockchainError::Config(format!("sync state {}: {}", store.path().display(), e)))?;
+
+        let deposit_tree = Arc::new(Mutex::new(tree_config.empty_tree()));
+        let sync_status = SyncTracker::new();
+        // Syncer for the deposit tree, with the blockchain client for root verification
+        let syncer = Syncer::new(rpc_url, address, deposit_tree.clone())
+            .with_checkpoint_store(state_store)
+            .with_fetch_config(FetchConfig {
+                page_size: PageSizeBounds::from_env(),
+                ..FetchConfig::default()
+            })
+            .with_blockchain_client(blockchain.clone())
+            .with_redaction_policy(redaction)
+            .with_status_tracker(sync_status.clone())
+            .with_commitment_index(commitments);
+        // HISTORY_RETENTION_BLOCKS: keep the block and tx of deposits for that many blocks only
+        // The node file holds the tree in the clear: only kept when the checkpoint isn't encrypted
+        let syncer = match store.is_encrypted() {
+            true => syncer,
+            false => syncer.with_tree_store(TreeStore::for_checkpoint(&store)),
+        };
+        let syncer = match std::env::var("HISTORY_RETENTION_BLOCKS").ok().and_then(|blocks| blocks.parse().ok()) {
+            Some(blocks) => syncer.with_history_retention(blocks),
+            None => syncer,
+        };
+        let syncer = match snapshot {
+            Some(source) => syncer.with_snapshot_source(source),
+            None => syncer,
+        };
+
+        // React to new blocks as they are produced when a WebSocket endpoint is configured
+        #[cfg(feature = "ws")]
+        let syncer = match std::env::var("RPC_WS_URL") {
+            Ok(ws_url) => match crate::ws::WsTransport::new(&ws_url) {
+                Ok(transport) => {
+                    // Deposits are pushed by an event subscription, get_events is polled while it is down
+                    let syncer = syncer.with_event_source(Arc::new(transport.clone()));
+                    match transport.subscribe_new_heads().await {
+                        Ok(new_heads) => {
+                            println!("✓ Subscribed to new blocks over WebSocket");
+                            syncer.with_new_heads(new_heads)
+                        }
+                        Err(e) => {
+                            eprintln!("⚠️  New block subscription failed ({}) - falling back to polling", e);
+                            syncer
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("⚠️  {} - falling back to polling", e);
+                    syncer
+                }
+            },
+            Err(_) => syncer,
+        };
+
+        Ok(Self {
+            address: syncer.contract_address,
+            blockchain,
+            deposit_tree,
+            sync_status,
+            syncer: Arc::new(syncer),
+        })
+    }
+
+    /// Run the syncer in the background, restarted by a watchdog if it crashes or stops making progress
+    pub fn spawn_sync(&self, config: WatchdogConfig) {
+        let watchdog = Watchdog::new(config, self.sync_status.clone());
+        let syncer = self.syncer.clone();
+        tokio::spawn(async move {
+            watchdog
+                .supervise(move || {
+                    let syncer = syncer.clone();
+                    async move { syncer.run().await }
+                })
+                .await;
+        });
+    }
+
+    /// Compact the sync state store on the schedule of `config`
+    pub fn spawn_maintenance(&self, config: MaintenanceConfig) {
+        if let Some(store) = self.syncer.checkpoints.clone() {
+            spawn_maintenance(store, config);
+        }
+    }
+
+    /// Every `interval`, check `sample` of the recorded roots with `is_root_known`
+    pub fn spawn_root_checks(&self, interval: Duration, sample: usize) {
+        let roots = self.syncer.roots.clone();
+        let blockchain = self.blockchain.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                roots
+                    .validate_sample(sample, |root| {
+                        let blockchain = blockchain.clone();
+                        async move { blockchain.is_root_known(&root).await }
+                    })
+                    .await;
+            }
+        });
+    }
+}
+
+/// Synced contracts by address, the primary one first
+pub struct ContractRegistry {
+    contracts: Vec<Arc<ContractSync>>,
+}
+
+impl ContractRegistry {
+    /// Registry of `contracts`; the first one is the primary contract
+    pub fn new(contracts: Vec<ContractSync>) -> Self {
+        assert!(!contracts.is_empty(), "at least one contract is synced");
+        Self {
+            contracts: contracts.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn primary(&self) -> &Arc<ContractSync> {
+        &self.contracts[0]
+    }
+
+    /// Contract at `address`, which may be given with or without leading zeros
+    pub fn get(&self, address: &str) -> Option<&Arc<ContractSync>> {
+        let address = FieldElement::from_hex_be(address).ok()?;
+        self.contracts.iter().find(|contract| contract.address == address)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<ContractSync>> {
+        self.contracts.iter()
+    }
+}
+
+/// Contracts to sync: CONTRACT_ADDRESSES (comma separated, primary first), else CONTRACT_ADDRESS
+pub fn contract_addresses_from_env() -> Vec<String> {
+    addresses_from_lookup(|key| std::env::var(key).ok())
+}
+
+fn addresses_from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    let mut seen: Vec<FieldElement> = Vec::new();
+    let listed = lookup("CONTRACT_ADDRESSES").unwrap_or_default();
+    for address in listed.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+        // The same contract written twice (e.g. with and without padding) is synced once
+        match FieldElement::from_hex_be(address) {
+            Ok(felt) if seen.contains(&felt) => continue,
+            Ok(felt) => seen.push(felt),
+            Err(_) => {}
+        }
+        addresses.push(address.to_string());
+    }
+    if addresses.is_empty() {
+        addresses.push(lookup("CONTRACT_ADDRESS").unwrap_or_else(|| DEFAULT_CONTRACT_ADDRESS.to_string()));
+    }
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_addresses() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string())
+        };
+
+        assert_eq!(addresses_from_lookup(env(&[])), vec![DEFAULT_CONTRACT_ADDRESS.to_string()]);
+        assert_eq!(addresses_from_lookup(env(&[("CONTRACT_ADDRESS", "0x2a1")])), vec!["0x2a1".to_string()]);
+        // The list wins, keeps its order and drops repeats
+        assert_eq!(
+            addresses_from_lookup(env(&[
+                ("CONTRACT_ADDRESS", "0x2a1"),
+                ("CONTRACT_ADDRESSES", "0x2a2, 0x2a3,,0x00002a2"),
+            ])),
+            vec!["0x2a2".to_string(), "0x2a3".to_string()]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+// Conversions between felts and the Rust integer types the contract encodes in them
+
+use crate::error::BlockchainError;
+use num_bigint::BigUint;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet::core::types::FieldElement;
+use std::fmt;
+
+/// Cairo u256: two felts, low 128 bits first, then high 128 bits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256 {
+    pub low: u128,
+    pub high: u128,
+}
+
+impl U256 {
+    pub const ZERO: U256 = U256 { low: 0, high: 0 };
+
+    pub fn from_words(low: u128, high: u128) -> Self {
+        Self { low, high }
+    }
+
+    /// Decode a u256 from its (low, high) felt pair
+    pub fn from_felts(low: FieldElement, high: FieldElement) -> Result<Self, BlockchainError> {
+        Ok(Self {
+            low: felt_to_u128(low)?,
+            high: felt_to_u128(high)?,
+        })
+    }
+
+    pub fn checked_add(self, other: U256) -> Option<U256> {
+        let (low, carry) = self.low.overflowing_add(other.low);
+        let high = self.high.checked_add(other.high)?.checked_add(carry as u128)?;
+        Some(Self { low, high })
+    }
+
+    pub fn to_biguint(&self) -> BigUint {
+        (BigUint::from(self.high) << 128u32) + BigUint::from(self.low)
+    }
+}
+
+impl From<u128> for U256 {
+    fn from(value: u128) -> Self {
+        Self { low: value, high: 0 }
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_biguint())
+    }
+}
+
+/// Serialized as a decimal string (JSON numbers can't hold 256 bits)
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let value = BigUint::parse_bytes(s.as_bytes(), 10)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid u256 '{}'", s)))?;
+        let digits = value.to_u64_digits();
+        if digits.len() > 4 {
+            return Err(serde::de::Error::custom(format!("u256 overflow '{}'", s)));
+        }
+        let word = |i: usize| *digits.get(i).unwrap_or(&0) as u128;
+        Ok(Self {
+            low: word(0) | (word(1) << 64),
+            high: word(2) | (word(3) << 64),
+        })
+    }
+}
+
+/// Hex rendering of felts handed to clients (addresses, roots)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressFormat {
+    /// Shortest form without leading zeros, e.g. `0x1`
+    Trimmed,
+    /// Zero-padded to 64 hex digits, the canonical Starknet form used by explorers and SDKs
+    #[default]
+    Padded64,
+}
+
+impl std::str::FromStr for AddressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trimmed" => Ok(AddressFormat::Trimmed),
+            "padded" | "padded64" => Ok(AddressFormat::Padded64),
+            other => Err(format!(
+                "Invalid address format '{}' (expected trimmed or padded)",
+                other
+            )),
+        }
+    }
+}
+
+/// Format a felt as 0x-prefixed hex in the given format
+pub fn format_felt(felt: FieldElement, format: AddressFormat) -> String {
+    match format {
+        AddressFormat::Trimmed => format!("0x{:x}", felt),
+        AddressFormat::Padded64 => format!("0x{:064x}", felt),
+    }
+}
+
+/// Serde for FieldElement as a 0x-prefixed hex string
+/// Use with `#[serde(with = "crate::conv::felt_hex")]`
+pub mod felt_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use starknet::core::types::FieldElement;
+
+    pub fn serialize<S: Serializer>(felt: &FieldElement, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{:x}", felt))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FieldElement, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        FieldElement::from_hex_be(&s).map_err(|e| D::Error::custom(format!("invalid felt '{}': {}", s, e)))
+    }
+}
+
+/// Serde for Vec<FieldElement> as a list of 0x-prefixed hex strings
+pub mod felt_hex_vec {
+    use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+    use starknet::core::types::FieldElement;
+
+    pub fn serialize<S: Serializer>(felts: &[FieldElement], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(felts.len()))?;
+        for felt in felts {
+            seq.serialize_element(&format!("0x{:x}", felt))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<FieldElement>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| FieldElement::from_hex_be(s).map_err(|e| D::Error::custom(format!("invalid felt '{}': {}", s, e))))
+            .collect()
+    }
+}
+
+/// Convert a felt to u128, failing if it doesn't fit
+pub fn felt_to_u128(felt: FieldElement) -> Result<u128, BlockchainError> {
+    let bytes = felt.to_bytes_be();
+    if bytes[..16].iter().any(|b| *b != 0) {
+        return Err(BlockchainError::Decode(format!(
+            "Felt 0x{:x} does not fit in u128",
+            felt
+        )));
+    }
+    let mut arr = [0u8; 16];
+    arr.copy_from_slice(&bytes[16..]);
+    Ok(u128::from_be_bytes(arr))
+}
+
+/// Convert a felt to u32, failing if it doesn't fit
+pub fn felt_to_u32(felt: FieldElement) -> Result<u32, BlockchainError> {
+    let value = felt_to_u128(felt)?;
+    u32::try_from(value)
+        .map_err(|_| BlockchainError::Decode(format!("Felt 0x{:x} does not fit in u32", felt)))
+}
+
+/// Decode a Cairo short string (up to 31 ASCII bytes packed big-endian into a felt)
+pub fn felt_to_short_string(felt: FieldElement) -> Result<String, BlockchainError> {
+    let bytes = felt.to_bytes_be();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let content = &bytes[start..];
+
+    if !content.is_ascii() {
+        return Err(BlockchainError::Decode(format!(
+            "Felt 0x{:x} is not an ASCII short string",
+            felt
+        )));
+    }
+    // ASCII is valid UTF-8
+    Ok(String::from_utf8(content.to_vec()).unwrap())
+}
+
+/// Encode an ASCII string of at most 31 bytes as a Cairo short string felt
+pub fn short_string_to_felt(s: &str) -> Result<FieldElement, BlockchainError> {
+    if !s.is_ascii() {
+        return Err(BlockchainError::InvalidInput(format!(
+            "Short string '{}' is not ASCII",
+            s
+        )));
+    }
+    if s.len() > 31 {
+        return Err(BlockchainError::InvalidInput(format!(
+            "Short string '{}' is {} bytes, max is 31",
+            s,
+            s.len()
+        )));
+    }
+
+    let mut bytes = [0u8; 32];
+    bytes[32 - s.len()..].copy_from_slice(s.as_bytes());
+    // 31 bytes always fit below the field modulus
+    Ok(FieldElement::from_bytes_be(&bytes).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_felt() {
+        let address = FieldElement::from(0x1u64);
+        assert_eq!(format_felt(address, AddressFormat::Trimmed), "0x1");
+        assert_eq!(
+            format_felt(address, AddressFormat::Padded64),
+            format!("0x{}1", "0".repeat(63))
+        );
+        assert_eq!(format_felt(FieldElement::ZERO, AddressFormat::Trimmed), "0x0");
+        assert_eq!("padded".parse::<AddressFormat>().unwrap(), AddressFormat::default());
+    }
+
+    #[test]
+    fn test_short_string_round_trip() {
+        let eth = short_string_to_felt("ETH").unwrap();
+        assert_eq!(eth, FieldElement::from(0x455448u64));
+        assert_eq!(felt_to_short_string(eth).unwrap(), "ETH");
+
+        let empty = short_string_to_felt("").unwrap();
+        assert_eq!(empty, FieldElement::ZERO);
+        assert_eq!(felt_to_short_string(empty).unwrap(), "");
+
+        let max = "a".repeat(31);
+        assert_eq!(felt_to_short_string(short_string_to_felt(&max).unwrap()).unwrap(), max);
+    }
+
+    #[test]
+    fn test_short_string_too_long() {
+        let err = short_string_to_felt(&"a".repeat(32)).unwrap_err();
+        assert!(matches!(err, BlockchainError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_felt_to_short_string_rejects_non_ascii() {
+        let err = felt_to_short_string(FieldElement::from(0xff41u64)).unwrap_err();
+        assert!(matches!(err, BlockchainError::Decode(_)));
+    }
+}
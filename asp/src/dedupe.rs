@@ -0,0 +1,125 @@
+// Event deduplication
+// Event scans can serve an event twice: overlapping pages across continuation tokens,
+// a restart replaying part of a range, a subscription pushing events a poll already got.
+// Each event is identified by (block_number, transaction_hash, event_index), the index
+// counting the contract's distinct events within the transaction. Events come in chain
+// order, so only the newest block's events need remembering: anything older is a replay.
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{EmittedEvent, FieldElement};
+use starknet::core::utils::starknet_keccak;
+
+/// Position of an event on the chain
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventKey {
+    pub block_number: u64,
+    #[serde(with = "crate::conv::felt_hex")]
+    pub transaction_hash: FieldElement,
+    /// Index among the contract's events in this transaction
+    pub event_index: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct SeenEvent {
+    key: EventKey,
+    /// Hash of the event keys and data, to tell a replay from the next event of a transaction
+    #[serde(with = "crate::conv::felt_hex")]
+    fingerprint: FieldElement,
+}
+
+/// Events applied so far; persisted with the sync checkpoint so replays after a restart are
+/// dropped too
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventDeduper {
+    /// Newest block an event was applied from
+    newest_block: Option<u64>,
+    /// Events of `newest_block`, in order
+    seen: Vec<SeenEvent>,
+}
+
+impl EventDeduper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key `event` would be applied under, None if it was seen already
+    /// Pending events have no block yet: they are never keyed.
+    pub fn key_for(&self, event: &EmittedEvent) -> Option<EventKey> {
+        let block_number = event.block_number?;
+        let seen: &[SeenEvent] = match self.newest_block {
+            Some(newest) if block_number < newest => return None,
+            Some(newest) if block_number == newest => &self.seen,
+            _ => &[],
+        };
+        let fingerprint = fingerprint(event);
+        let same_transaction = || seen.iter().filter(|seen| seen.key.transaction_hash == event.transaction_hash);
+        if same_transaction().any(|seen| seen.fingerprint == fingerprint) {
+            return None;
+        }
+        Some(EventKey {
+            block_number,
+            transaction_hash: event.transaction_hash,
+            event_index: same_transaction().count() as u32,
+        })
+    }
+
+    /// Remember `event`, applied under `key`
+    pub fn remember(&mut self, event: &EmittedEvent, key: EventKey) {
+        if self.newest_block != Some(key.block_number) {
+            self.newest_block = Some(key.block_number);
+            self.seen.clear();
+        }
+        self.seen.push(SeenEvent {
+            key,
+            fingerprint: fingerprint(event),
+        });
+    }
+
+    /// Key of `event` if it wasn't seen yet, remembering it; None for a replay
+    pub fn accept(&mut self, event: &EmittedEvent) -> Option<EventKey> {
+        let key = self.key_for(event)?;
+        self.remember(event, key);
+        Some(key)
+    }
+}
+
+fn fingerprint(event: &EmittedEvent) -> FieldElement {
+    let mut bytes = Vec::with_capacity(8 + 32 * (event.keys.len() + event.data.len()));
+    bytes.extend_from_slice(&(event.keys.len() as u64).to_be_bytes());
+    for felt in event.keys.iter().chain(&event.data) {
+        bytes.extend_from_slice(&felt.to_bytes_be());
+    }
+    starknet_keccak(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::deposit_emitted_event;
+
+    #[test]
+    fn test_replays_are_dropped_and_indices_assigned() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let first = deposit_emitted_event(zylith, FieldElement::from(1000u64), 0, FieldElement::ONE, 10);
+        // A second deposit in the same transaction
+        let second = EmittedEvent {
+            transaction_hash: first.transaction_hash,
+            ..deposit_emitted_event(zylith, FieldElement::from(1001u64), 1, FieldElement::ONE, 10)
+        };
+        let mut deduper = EventDeduper::new();
+
+        assert_eq!(deduper.accept(&first).unwrap().event_index, 0);
+        // Served again at a page boundary
+        assert_eq!(deduper.accept(&first), None);
+        assert_eq!(deduper.accept(&second).unwrap().event_index, 1);
+
+        let later = deposit_emitted_event(zylith, FieldElement::from(1002u64), 2, FieldElement::ONE, 11);
+        assert!(deduper.accept(&later).is_some());
+        // Older blocks are behind us
+        assert_eq!(deduper.accept(&second), None);
+
+        // Survives a restart
+        let restored: EventDeduper = serde_json::from_str(&serde_json::to_string(&deduper).unwrap()).unwrap();
+        assert_eq!(restored, deduper);
+    }
+}
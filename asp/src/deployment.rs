@@ -0,0 +1,93 @@
+// Contract deployment block discovery
+// Event scans start at the block the Zylith contract was deployed at. Instead of a constant
+// that goes stale on every redeployment, the block is found by binary search over
+// starknet_getClassHashAt: the contract has a class from its deployment block onwards.
+// Nodes that cannot answer for old blocks (pruned state) fall back to the contract's first
+// emitted event.
+
+use crate::error::BlockchainError;
+use crate::provider::StarknetProvider;
+use starknet::core::types::{BlockId, EventFilter, FieldElement};
+
+/// First block at which `address` has a class, searched in `0..=latest_block`
+/// Falls back to the block of the first event emitted by `address` when the class lookups fail.
+pub async fn find_deployment_block<P: StarknetProvider + ?Sized>(
+    provider: &P,
+    address: FieldElement,
+    latest_block: u64,
+) -> Result<u64, BlockchainError> {
+    match search_class_history(provider, address, latest_block).await {
+        Ok(block) => Ok(block),
+        Err(class_error) => match first_event_block(provider, address, latest_block).await {
+            Ok(Some(block)) => Ok(block),
+            Ok(None) | Err(_) => Err(class_error),
+        },
+    }
+}
+
+/// Binary search for the first block where `address` has a class
+async fn search_class_history<P: StarknetProvider + ?Sized>(
+    provider: &P,
+    address: FieldElement,
+    latest_block: u64,
+) -> Result<u64, BlockchainError> {
+    // Not deployed at the tip: nothing to search for
+    provider.get_class_hash_at(BlockId::Number(latest_block), address).await?;
+
+    let (mut low, mut high) = (0, latest_block);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match provider.get_class_hash_at(BlockId::Number(mid), address).await {
+            Ok(_) => high = mid,
+            Err(BlockchainError::ContractNotFound(_)) => low = mid + 1,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(low)
+}
+
+/// Block of the first event emitted by `address` up to `latest_block`, if any
+async fn first_event_block<P: StarknetProvider + ?Sized>(
+    provider: &P,
+    address: FieldElement,
+    latest_block: u64,
+) -> Result<Option<u64>, BlockchainError> {
+    let filter = EventFilter {
+        from_block: Some(BlockId::Number(0)),
+        to_block: Some(BlockId::Number(latest_block)),
+        address: Some(address),
+        keys: None,
+    };
+    let page = provider.get_events(filter, None, 1).await?;
+    Ok(page.events.first().and_then(|event| event.block_number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::{deposit_emitted_event, MockProvider};
+
+    #[tokio::test]
+    async fn test_finds_the_first_block_with_a_class() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let provider = MockProvider::new()
+            .with_block_number(5_000_000)
+            .with_contract_deployed_at(zylith, FieldElement::from(42u64), 4_438_440);
+
+        assert_eq!(find_deployment_block(&provider, zylith, 5_000_000).await.unwrap(), 4_438_440);
+        // Searching before the deployment finds nothing
+        let err = find_deployment_block(&provider, zylith, 4_000_000).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ContractNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_the_first_event() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let provider = MockProvider::new()
+            .with_block_number(900)
+            .with_events(vec![deposit_emitted_event(zylith, FieldElement::from(7u64), 0, FieldElement::ONE, 812)]);
+
+        // No class known to the node, but the contract has emitted events
+        assert_eq!(find_deployment_block(&provider, zylith, 900).await.unwrap(), 812);
+    }
+}
@@ -0,0 +1,123 @@
+use thiserror::Error;
+
+/// Errors returned by the blockchain client and the providers behind it
+#[derive(Debug, Clone, Error)]
+pub enum BlockchainError {
+    /// Invalid client configuration (bad RPC URL, unparsable address, ...)
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    /// No contract is deployed at the given address on the connected network
+    #[error("No contract deployed at {0} (wrong address or wrong network?)")]
+    ContractNotFound(String),
+
+    /// The contract has no such entry point (older deployment or wrong ABI)
+    #[error("Entry point not found: {0}")]
+    EntryPointNotFound(String),
+
+    /// The contract call was executed but failed (revert, panic, bad calldata)
+    #[error("Contract call failed: {0}")]
+    ContractCall(String),
+
+    /// The node doesn't support this RPC method (e.g. tracing disabled)
+    #[error("Unsupported by the RPC node: {0}")]
+    Unsupported(String),
+
+    /// The RPC request itself failed (network, HTTP, JSON-RPC error)
+    #[error("RPC transport error: {0}")]
+    Transport(String),
+
+    /// The request didn't complete within its timeout
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// The node answered but the payload couldn't be decoded into the expected type
+    #[error("Failed to decode response: {0}")]
+    Decode(String),
+
+    /// The node answered, but the expected on-chain data isn't there (unset storage, ...)
+    #[error("Missing on-chain data: {0}")]
+    MissingData(String),
+
+    /// A user-supplied value (hash, address, felt) couldn't be parsed
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// The node doesn't know this transaction hash
+    #[error("Transaction {0} not found (not yet submitted or wrong network?)")]
+    TransactionNotFound(String),
+
+    /// The transaction is known but not yet accepted on L2
+    #[error("Transaction {0} is still pending, retry once it is accepted on L2")]
+    TransactionPending(String),
+
+    /// The transaction didn't emit a Deposit event from the Zylith contract
+    #[error("Transaction {0} has no Zylith Deposit event")]
+    NoDepositEvent(String),
+
+    /// No Deposit event carries this commitment (yet)
+    #[error("Commitment {0} not found in Deposit events")]
+    CommitmentNotFound(String),
+
+    /// The locally rebuilt Merkle root differs from the contract's
+    #[error("Local Merkle root {local} does not match on-chain root {on_chain}")]
+    RootMismatch { local: String, on_chain: String },
+
+    /// A deposit doesn't follow the tree in leaf index order (missed or conflicting deposit)
+    #[error("Deposits out of order: {0}")]
+    LeafOrder(String),
+
+    /// The pool hasn't been initialized yet, so pool state is meaningless
+    #[error("Pool is not initialized. Please initialize the pool first.")]
+    PoolNotInitialized,
+}
+
+impl BlockchainError {
+    /// Whether the same request may succeed if sent again (network hiccup, overloaded node)
+    pub fn is_transient(&self) -> bool {
+        matches!(self, BlockchainError::Transport(_) | BlockchainError::Timeout(_))
+    }
+
+    /// Whether the node is struggling with the load: timed out or rate limited (HTTP 429)
+    pub fn is_overload(&self) -> bool {
+        match self {
+            BlockchainError::Timeout(_) => true,
+            BlockchainError::Transport(msg) => msg.contains("429") || msg.to_lowercase().contains("too many requests"),
+            _ => false,
+        }
+    }
+
+    /// Whether the node refused an event page for asking too many events at once
+    pub fn is_page_too_big(&self) -> bool {
+        match self {
+            BlockchainError::Transport(msg) => {
+                let msg = msg.to_lowercase();
+                msg.contains("pagesizetoobig") || msg.contains("page size is too big")
+            }
+            _ => false,
+        }
+    }
+
+    /// Short stable name of the variant, used as a metrics label
+    pub fn class(&self) -> &'static str {
+        match self {
+            BlockchainError::Config(_) => "config",
+            BlockchainError::ContractNotFound(_) => "contract_not_found",
+            BlockchainError::EntryPointNotFound(_) => "entry_point_not_found",
+            BlockchainError::ContractCall(_) => "contract_call",
+            BlockchainError::Unsupported(_) => "unsupported",
+            BlockchainError::Transport(_) => "transport",
+            BlockchainError::Timeout(_) => "timeout",
+            BlockchainError::Decode(_) => "decode",
+            BlockchainError::MissingData(_) => "missing_data",
+            BlockchainError::InvalidInput(_) => "invalid_input",
+            BlockchainError::TransactionNotFound(_) => "transaction_not_found",
+            BlockchainError::TransactionPending(_) => "transaction_pending",
+            BlockchainError::NoDepositEvent(_) => "no_deposit_event",
+            BlockchainError::CommitmentNotFound(_) => "commitment_not_found",
+            BlockchainError::RootMismatch { .. } => "root_mismatch",
+            BlockchainError::LeafOrder(_) => "leaf_order",
+            BlockchainError::PoolNotInitialized => "pool_not_initialized",
+        }
+    }
+}
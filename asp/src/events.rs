@@ -0,0 +1,264 @@
+// Decoders for events emitted by the Zylith contract
+
+use crate::conv::{felt_to_u32, U256};
+use crate::error::BlockchainError;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Event, FieldElement};
+use starknet::core::utils::starknet_keccak;
+
+/// Deposit event selector: starknet_keccak("Deposit")
+pub const DEPOSIT_EVENT_SELECTOR: &str =
+    "0x9149d2123147c5f43d258257fef0b7b969db78269369ebcf5ebb9eef8592f2";
+
+pub fn deposit_selector() -> FieldElement {
+    FieldElement::from_hex_be(DEPOSIT_EVENT_SELECTOR).unwrap()
+}
+
+/// Number of data fields of a Zylith Deposit event
+const DEPOSIT_DATA_LEN: usize = 3;
+
+/// Zylith `Deposit` event (Event::PrivacyEvent::Deposit)
+/// data = [commitment, leaf_index, root]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositEvent {
+    #[serde(with = "crate::conv::felt_hex")]
+    pub commitment: FieldElement,
+    pub leaf_index: u32,
+    /// Merkle root right after this deposit was inserted
+    #[serde(with = "crate::conv::felt_hex")]
+    pub root: FieldElement,
+    /// Deposited amount. The Deposit event doesn't carry it, so it is only known when
+    /// decoding a whole receipt (from the ERC20 Transfer into Zylith); commitments
+    /// inserted by swaps or LP operations have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<U256>,
+}
+
+impl DepositEvent {
+    /// Decode a Deposit event from raw keys/data
+    /// Returns Ok(None) if the event is not a Deposit
+    pub fn decode(keys: &[FieldElement], data: &[FieldElement]) -> Result<Option<Self>, BlockchainError> {
+        // For nested enum events the Deposit selector can be in any key
+        let selector = deposit_selector();
+        if !keys.iter().any(|key| *key == selector) {
+            return Ok(None);
+        }
+
+        if data.len() != DEPOSIT_DATA_LEN {
+            return Err(BlockchainError::Decode(format!(
+                "Deposit event has {} data fields, expected {}",
+                data.len(),
+                DEPOSIT_DATA_LEN
+            )));
+        }
+
+        Ok(Some(Self {
+            commitment: data[0],
+            leaf_index: felt_to_u32(data[1])?,
+            root: data[2],
+            amount: None,
+        }))
+    }
+
+    /// Decode the Deposit emitted by `zylith_address` in a transaction's events,
+    /// taking the amount from the ERC20 Transfer into the contract
+    pub fn from_receipt_events(events: &[Event], zylith_address: FieldElement) -> Result<Option<Self>, BlockchainError> {
+        let mut deposit = None;
+        for event in events.iter().filter(|e| e.from_address == zylith_address) {
+            if let Some(decoded) = Self::decode(&event.keys, &event.data)? {
+                deposit = Some(decoded);
+                break;
+            }
+        }
+        let Some(mut deposit) = deposit else { return Ok(None) };
+
+        deposit.amount = events
+            .iter()
+            .filter_map(TransferEvent::decode)
+            .find(|t| t.to == zylith_address)
+            .map(|t| t.value);
+        Ok(Some(deposit))
+    }
+}
+
+/// Zylith `NullifierSpent` event (Event::PrivacyEvent::NullifierSpent)
+/// data = [nullifier]
+pub fn nullifier_spent_selector() -> FieldElement {
+    starknet_keccak("NullifierSpent".as_bytes())
+}
+
+/// ERC20 `Transfer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferEvent {
+    pub token: FieldElement,
+    pub from: FieldElement,
+    pub to: FieldElement,
+    pub value: U256,
+}
+
+impl TransferEvent {
+    /// Decode both ERC20 layouts seen on Starknet:
+    /// - Cairo 1 (OpenZeppelin): keys = [selector, from, to], data = [value.low, value.high]
+    /// - Cairo 0 (legacy ETH): keys = [selector], data = [from, to, value.low, value.high]
+    ///
+    /// Returns None for anything else, so unrelated events never fail a receipt decode
+    pub fn decode(event: &Event) -> Option<Self> {
+        if event.keys.first() != Some(&starknet_keccak("Transfer".as_bytes())) {
+            return None;
+        }
+
+        let (from, to, low, high) = match (event.keys.len(), event.data.len()) {
+            (3, 2) => (event.keys[1], event.keys[2], event.data[0], event.data[1]),
+            (1, 4) => (event.data[0], event.data[1], event.data[2], event.data[3]),
+            _ => return None,
+        };
+
+        Some(Self {
+            token: event.from_address,
+            from,
+            to,
+            value: U256::from_felts(low, high).ok()?,
+        })
+    }
+}
+
+/// A private withdrawal, assembled from one transaction's events
+/// Zylith only emits the nullifier; token, recipient and amount come from the
+/// ERC20 Transfer the contract makes to the recipient in the same transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawEvent {
+    #[serde(with = "crate::conv::felt_hex")]
+    pub nullifier: FieldElement,
+    #[serde(with = "crate::conv::felt_hex")]
+    pub token: FieldElement,
+    #[serde(with = "crate::conv::felt_hex")]
+    pub recipient: FieldElement,
+    pub amount: U256,
+    /// Paid out by Zylith to anyone other than the recipient (relayer fee), zero if none
+    pub fee: U256,
+}
+
+impl WithdrawEvent {
+    /// Decode the withdrawal made by `zylith_address` in a transaction's events
+    /// Returns Ok(None) if the transaction spent no nullifier
+    pub fn from_receipt_events(events: &[Event], zylith_address: FieldElement) -> Result<Option<Self>, BlockchainError> {
+        let selector = nullifier_spent_selector();
+        let Some(spent) = events
+            .iter()
+            .find(|e| e.from_address == zylith_address && e.keys.iter().any(|key| *key == selector))
+        else {
+            return Ok(None);
+        };
+        if spent.data.len() != 1 {
+            return Err(BlockchainError::Decode(format!(
+                "NullifierSpent event has {} data fields, expected 1",
+                spent.data.len()
+            )));
+        }
+
+        let payouts: Vec<TransferEvent> = events
+            .iter()
+            .filter_map(TransferEvent::decode)
+            .filter(|t| t.from == zylith_address)
+            .collect();
+        // The first payout goes to the recipient, any later one is a fee
+        let Some(payout) = payouts.first() else {
+            return Err(BlockchainError::Decode(
+                "NullifierSpent without a token transfer out of Zylith".to_string(),
+            ));
+        };
+        let mut fee = U256::ZERO;
+        for extra in payouts[1..].iter().filter(|t| t.token == payout.token) {
+            fee = fee
+                .checked_add(extra.value)
+                .ok_or_else(|| BlockchainError::Decode("Withdraw fee overflows u256".to_string()))?;
+        }
+
+        Ok(Some(Self {
+            nullifier: spent.data[0],
+            token: payout.token,
+            recipient: payout.to,
+            amount: payout.value,
+            fee,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zylith() -> FieldElement {
+        FieldElement::from(0x2a11u64)
+    }
+
+    fn token() -> FieldElement {
+        FieldElement::from(0x70c3u64)
+    }
+
+    fn transfer(from: FieldElement, to: FieldElement, low: u64) -> Event {
+        Event {
+            from_address: token(),
+            keys: vec![starknet_keccak("Transfer".as_bytes()), from, to],
+            data: vec![FieldElement::from(low), FieldElement::ZERO],
+        }
+    }
+
+    #[test]
+    fn test_decode_deposit_with_amount() {
+        let user = FieldElement::from(0xa11ceu64);
+        let events = vec![
+            transfer(user, zylith(), 500),
+            Event {
+                from_address: zylith(),
+                keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), deposit_selector()],
+                data: vec![FieldElement::from(0xc0u64), FieldElement::from(4u64), FieldElement::from(0x4007u64)],
+            },
+        ];
+
+        let deposit = DepositEvent::from_receipt_events(&events, zylith()).unwrap().unwrap();
+        assert_eq!(deposit.commitment, FieldElement::from(0xc0u64));
+        assert_eq!(deposit.leaf_index, 4);
+        assert_eq!(deposit.amount, Some(U256::from(500u128)));
+    }
+
+    #[test]
+    fn test_decode_deposit_rejects_short_payload() {
+        let err = DepositEvent::decode(&[deposit_selector()], &[FieldElement::ONE, FieldElement::ZERO]).unwrap_err();
+        assert!(matches!(err, BlockchainError::Decode(_)));
+    }
+
+    #[test]
+    fn test_decode_withdraw() {
+        let recipient = FieldElement::from(0xb0bu64);
+        let relayer = FieldElement::from(0x4e1au64);
+        let nullifier = FieldElement::from(0x5eedu64);
+        let events = vec![
+            transfer(zylith(), recipient, 900),
+            transfer(zylith(), relayer, 100),
+            Event {
+                from_address: zylith(),
+                keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), nullifier_spent_selector()],
+                data: vec![nullifier],
+            },
+        ];
+
+        let withdraw = WithdrawEvent::from_receipt_events(&events, zylith()).unwrap().unwrap();
+        assert_eq!(withdraw.nullifier, nullifier);
+        assert_eq!(withdraw.token, token());
+        assert_eq!(withdraw.recipient, recipient);
+        assert_eq!(withdraw.amount, U256::from(900u128));
+        assert_eq!(withdraw.fee, U256::from(100u128));
+    }
+
+    #[test]
+    fn test_decode_withdraw_rejects_short_payload() {
+        let events = vec![Event {
+            from_address: zylith(),
+            keys: vec![nullifier_spent_selector()],
+            data: vec![],
+        }];
+        let err = WithdrawEvent::from_receipt_events(&events, zylith()).unwrap_err();
+        assert!(matches!(err, BlockchainError::Decode(_)));
+    }
+}
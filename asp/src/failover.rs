@@ -0,0 +1,333 @@
+// RPC failover across several endpoints
+// Endpoints are tried healthiest first. Transport errors and timeouts move on to the next
+// endpoint, while answers every node would give alike (unknown contract, revert, ...) are
+// returned as is. Health is a smoothed latency and error rate per endpoint.
+
+use crate::error::BlockchainError;
+use crate::provider::{CallResult, HttpPoolConfig, HttpProvider, InvocationTrace, StarknetProvider, TxReceipt};
+use async_trait::async_trait;
+use starknet::core::types::{BlockId, EventFilter, EventsPage, FieldElement, FunctionCall};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How long an endpoint that just failed is ranked last
+pub const DEFAULT_FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Weight of the newest sample in the smoothed latency and error rate
+const SMOOTHING: f64 = 0.3;
+
+/// Error rate from which an endpoint is considered unhealthy (a single recent failure)
+const UNHEALTHY_ERROR_RATE: f64 = 0.25;
+
+/// Observed health of one endpoint
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EndpointHealth {
+    /// Smoothed round-trip time of successful requests (None until the first success)
+    pub latency: Option<Duration>,
+    /// Smoothed share of failed requests, from 0.0 to 1.0
+    pub error_rate: f64,
+    last_failure: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn record_success(&mut self, rtt: Duration) {
+        self.latency = Some(match self.latency {
+            Some(latency) => latency.mul_f64(1.0 - SMOOTHING) + rtt.mul_f64(SMOOTHING),
+            None => rtt,
+        });
+        self.error_rate *= 1.0 - SMOOTHING;
+    }
+
+    fn record_failure(&mut self) {
+        self.error_rate = self.error_rate * (1.0 - SMOOTHING) + SMOOTHING;
+        self.last_failure = Some(Instant::now());
+    }
+
+    /// Failing recently; such endpoints are only tried once the others failed too
+    fn is_unhealthy(&self, cooldown: Duration) -> bool {
+        self.error_rate >= UNHEALTHY_ERROR_RATE && self.last_failure.is_some_and(|at| at.elapsed() < cooldown)
+    }
+
+    /// Ranking key: healthy first, then fastest; endpoints never measured keep their configured order
+    fn rank(&self, cooldown: Duration) -> (bool, f64) {
+        let latency = self.latency.map_or(f64::INFINITY, |latency| latency.as_secs_f64());
+        (self.is_unhealthy(cooldown), latency)
+    }
+}
+
+struct Endpoint<P> {
+    /// Shown in logs (the RPC URL for HTTP endpoints)
+    name: String,
+    provider: P,
+    health: Mutex<EndpointHealth>,
+}
+
+/// StarknetProvider that spreads requests over several endpoints, preferring the healthiest
+pub struct FailoverProvider<P> {
+    endpoints: Vec<Endpoint<P>>,
+    cooldown: Duration,
+}
+
+impl<P: StarknetProvider> FailoverProvider<P> {
+    /// Endpoints in priority order, as (name, provider)
+    pub fn new(endpoints: Vec<(String, P)>) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(name, provider)| Endpoint {
+                    name,
+                    provider,
+                    health: Mutex::new(EndpointHealth::default()),
+                })
+                .collect(),
+            cooldown: DEFAULT_FAILOVER_COOLDOWN,
+        }
+    }
+
+    /// Set how long a failed endpoint is ranked last
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Current health of every endpoint, in configured order
+    pub fn health(&self) -> Vec<(String, EndpointHealth)> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| (endpoint.name.clone(), *endpoint.health.lock().unwrap()))
+            .collect()
+    }
+
+    /// Endpoint indices, healthiest first
+    fn ranked(&self) -> Vec<usize> {
+        let ranks: Vec<(bool, f64)> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.health.lock().unwrap().rank(self.cooldown))
+            .collect();
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        // Stable sort: ties keep the configured priority
+        order.sort_by(|a, b| ranks[*a].partial_cmp(&ranks[*b]).unwrap_or(std::cmp::Ordering::Equal));
+        order
+    }
+
+    /// Run `request` on each endpoint in rank order until one gives a definitive answer
+    async fn with_failover<'a, T, F, Fut>(&'a self, request: F) -> Result<T, BlockchainError>
+    where
+        F: Fn(&'a P) -> Fut,
+        Fut: Future<Output = Result<T, BlockchainError>>,
+    {
+        let mut last_error = None;
+        for index in self.ranked() {
+            let endpoint = &self.endpoints[index];
+            let attempt = Attempt::start(&endpoint.health);
+            match request(&endpoint.provider).await {
+                Err(e) if fails_over(&e) => {
+                    attempt.failed();
+                    eprintln!("[ASP] ⚠️  RPC endpoint {} failed ({}), trying the next one", endpoint.name, e);
+                    last_error = Some(e);
+                }
+                result => {
+                    attempt.succeeded();
+                    return result;
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| BlockchainError::Config("No RPC endpoint configured".to_string())))
+    }
+}
+
+impl FailoverProvider<HttpProvider> {
+    /// HTTP endpoints from a comma-separated list of RPC URLs, in priority order
+    pub fn from_urls(rpc_urls: &str, pool: HttpPoolConfig, lazy: bool) -> Result<Self, BlockchainError> {
+        let mut endpoints = Vec::new();
+        for rpc_url in rpc_urls.split(',').map(str::trim).filter(|url| !url.is_empty()) {
+            let url = Url::parse(rpc_url)
+                .map_err(|e| BlockchainError::Config(format!("Invalid RPC URL '{}': {}", rpc_url, e)))?;
+            let provider = if lazy {
+                HttpProvider::lazy(url, pool.clone())
+            } else {
+                HttpProvider::new(url, pool.clone())?
+            };
+            endpoints.push((rpc_url.to_string(), provider));
+        }
+        if endpoints.is_empty() {
+            return Err(BlockchainError::Config("Invalid RPC URL: no endpoint given".to_string()));
+        }
+        Ok(Self::new(endpoints))
+    }
+}
+
+/// Errors that another endpoint might not give
+fn fails_over(error: &BlockchainError) -> bool {
+    error.is_transient() || matches!(error, BlockchainError::Unsupported(_))
+}
+
+/// Records the outcome of one request on an endpoint
+/// Dropped without an outcome (the caller's timeout cancelled the request), it counts as a failure
+struct Attempt<'a> {
+    health: &'a Mutex<EndpointHealth>,
+    started: Instant,
+    finished: bool,
+}
+
+impl<'a> Attempt<'a> {
+    fn start(health: &'a Mutex<EndpointHealth>) -> Self {
+        Self {
+            health,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    fn succeeded(mut self) {
+        self.finished = true;
+        self.health.lock().unwrap().record_success(self.started.elapsed());
+    }
+
+    fn failed(mut self) {
+        self.finished = true;
+        self.health.lock().unwrap().record_failure();
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.health.lock().unwrap().record_failure();
+        }
+    }
+}
+
+#[async_trait]
+impl<P: StarknetProvider> StarknetProvider for FailoverProvider<P> {
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        self.with_failover(|provider| provider.call(call.clone(), block)).await
+    }
+
+    async fn batch_call(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        self.with_failover(|provider| provider.batch_call(calls.clone(), block)).await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.with_failover(|provider| provider.get_storage_at(address, key, block)).await
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.with_failover(|provider| provider.get_class_hash_at(block, address)).await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        self.with_failover(|provider| provider.get_transaction_receipt(tx_hash)).await
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        self.with_failover(|provider| provider.block_number()).await
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        self.with_failover(|provider| provider.block_hash(block)).await
+    }
+
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        self.with_failover(|provider| provider.get_events(filter.clone(), continuation_token.clone(), chunk_size))
+            .await
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        self.with_failover(|provider| provider.trace_block(block)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+    use crate::timeouts::with_timeout;
+    use starknet::core::types::BlockTag;
+    use starknet::core::utils::get_selector_from_name;
+
+    fn zylith_address() -> FieldElement {
+        FieldElement::from(0x2a1u64)
+    }
+
+    fn root_call() -> FunctionCall {
+        FunctionCall {
+            contract_address: zylith_address(),
+            entry_point_selector: get_selector_from_name("get_merkle_root").unwrap(),
+            calldata: vec![],
+        }
+    }
+
+    fn healthy(root: u64) -> MockProvider {
+        MockProvider::new().with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(root)])
+    }
+
+    fn failover(primary: MockProvider, secondary: MockProvider) -> FailoverProvider<MockProvider> {
+        FailoverProvider::new(vec![("primary".to_string(), primary), ("secondary".to_string(), secondary)])
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_on_transport_error() {
+        let provider = failover(
+            healthy(1).failing(BlockchainError::Transport("connection refused".to_string())),
+            healthy(2),
+        );
+
+        let root = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        assert_eq!(root, vec![FieldElement::from(2u64)]);
+
+        // The failed endpoint is now ranked last and not retried first
+        provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        assert_eq!(provider.endpoints[0].provider.call_count(), 1);
+        assert_eq!(provider.endpoints[1].provider.call_count(), 2);
+        let health = provider.health();
+        assert!(health[0].1.error_rate > health[1].1.error_rate);
+        assert!(health[1].1.latency.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_definitive_errors_do_not_fail_over() {
+        // No contract on the primary: every node would say the same
+        let provider = failover(MockProvider::new(), healthy(2));
+
+        let err = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ContractNotFound(_)));
+        assert_eq!(provider.endpoints[1].provider.call_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_marks_endpoint_unhealthy() {
+        let provider = failover(healthy(1).with_latency(Duration::from_millis(200)), healthy(2));
+
+        // The caller gives up on the stalled primary...
+        let err = with_timeout(
+            Duration::from_millis(10),
+            "get_merkle_root",
+            provider.call(root_call(), BlockId::Tag(BlockTag::Latest)),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, BlockchainError::Timeout(_)));
+
+        // ...so the next request goes to the secondary straight away
+        let root = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        assert_eq!(root, vec![FieldElement::from(2u64)]);
+    }
+}
@@ -0,0 +1,144 @@
+// Garaga calldata
+// `verify_groth16_proof_bn254(full_proof_with_hints: Span<felt252>)` of the verifiers Garaga
+// 1.0.1 generated (circuits/build/garaga) deserializes, in order: the proof points as u384 (four
+// 96-bit limbs, least significant first), G2 coordinates as (c0, c1) pairs, the public inputs as
+// a Span<u256> (low and high halves), then the pairing and MSM hints. The proof and public inputs
+// are laid out here; the hints come from the Garaga CLI, whose own proof section must match ours.
+
+use crate::prover::Groth16Proof;
+use num_bigint::BigUint;
+use starknet::core::types::FieldElement;
+use std::path::Path;
+
+/// Bits per u384 limb
+const LIMB_BITS: u32 = 96;
+
+/// `value` as a Cairo u384: limb0..limb3, least significant first
+fn u384_limbs(value: &BigUint) -> [FieldElement; 4] {
+    let mask = (BigUint::from(1u8) << LIMB_BITS) - 1u8;
+    std::array::from_fn(|i| felt(&((value >> (LIMB_BITS * i as u32)) & &mask)))
+}
+
+/// `value` as a Cairo u256: low, high
+fn u256_halves(value: &BigUint) -> [FieldElement; 2] {
+    let mask = (BigUint::from(1u8) << 128u32) - 1u8;
+    [felt(&(value & &mask)), felt(&(value >> 128u32))]
+}
+
+/// Fits by construction: callers pass values below 2^128 or 2^96
+fn felt(value: &BigUint) -> FieldElement {
+    FieldElement::from_byte_slice_be(&value.to_bytes_be()).expect("value below 2^251")
+}
+
+/// The `Groth16Proof` part of full_proof_with_hints: a, b, c, public_inputs
+pub fn proof_section(proof: &Groth16Proof) -> Vec<FieldElement> {
+    let [[bx0, bx1], [by0, by1]] = &proof.b;
+    let mut calldata = Vec::new();
+    for coordinate in [&proof.a[0], &proof.a[1], bx0, bx1, by0, by1, &proof.c[0], &proof.c[1]] {
+        calldata.extend(u384_limbs(coordinate));
+    }
+    calldata.push(FieldElement::from(proof.public_inputs.len() as u64));
+    for input in &proof.public_inputs {
+        calldata.extend(u256_halves(input));
+    }
+    calldata
+}
+
+/// Calldata of `verify_groth16_proof_bn254` for `proof` and the hints that go with it, with the
+/// length prefix of the span, ready for an invoke transaction
+pub fn full_proof_with_hints(proof: &Groth16Proof, hints: &[FieldElement]) -> Vec<FieldElement> {
+    let mut body = proof_section(proof);
+    body.extend_from_slice(hints);
+    let mut calldata = Vec::with_capacity(body.len() + 1);
+    calldata.push(FieldElement::from(body.len() as u64));
+    calldata.extend(body);
+    calldata
+}
+
+/// Hints of `proof` from `garaga calldata` (pip install garaga==1.0.1) against the circuit's
+/// verification key, checking the CLI lays out the proof exactly as `proof_section` does
+pub async fn cli_hints(vk_path: &Path, proof: &Groth16Proof) -> Result<Vec<FieldElement>, String> {
+    let temp_dir = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+    let proof_file = temp_dir.join(format!("garaga_proof_{}.json", timestamp));
+    let public_file = temp_dir.join(format!("garaga_public_{}.json", timestamp));
+    let (proof_json, public_json) = proof.to_snarkjs();
+    std::fs::write(&proof_file, proof_json.to_string()).map_err(|e| format!("Failed to write proof file: {}", e))?;
+    std::fs::write(&public_file, public_json.to_string()).map_err(|e| format!("Failed to write public signals: {}", e))?;
+
+    let output = tokio::process::Command::new("garaga")
+        .args(["calldata", "--system", "groth16", "--format", "array"])
+        .arg("--vk")
+        .arg(vk_path)
+        .arg("--proof")
+        .arg(&proof_file)
+        .arg("--public-inputs")
+        .arg(&public_file)
+        .output()
+        .await;
+    let _ = std::fs::remove_file(&proof_file);
+    let _ = std::fs::remove_file(&public_file);
+    let output = output.map_err(|e| format!("Failed to run garaga: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("garaga calldata failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let values = crate::proof::parse_garaga_array_output(&String::from_utf8_lossy(&output.stdout))?;
+    let calldata = values
+        .iter()
+        .map(|value| match value.strip_prefix("0x") {
+            Some(_) => FieldElement::from_hex_be(value),
+            None => FieldElement::from_dec_str(value),
+        }
+        .map_err(|e| format!("garaga returned {}, not a felt: {}", value, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    split_hints(&calldata, proof)
+}
+
+/// The hints in a full calldata (with or without its length prefix) whose proof section is `proof`'s
+fn split_hints(calldata: &[FieldElement], proof: &Groth16Proof) -> Result<Vec<FieldElement>, String> {
+    let body = match calldata.split_first() {
+        Some((len, rest)) if *len == FieldElement::from(rest.len() as u64) => rest,
+        _ => calldata,
+    };
+    let section = proof_section(proof);
+    if !body.starts_with(&section) {
+        return Err("garaga laid out the proof differently than the serializer; check the Garaga version".to_string());
+    }
+    Ok(body[section.len()..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limbs_and_layout() {
+        let value = (BigUint::from(3u8) << 192u32) + (BigUint::from(2u8) << 96u32) + 1u8;
+        assert_eq!(u384_limbs(&value), [1u8, 2, 3, 0].map(FieldElement::from));
+        let input = (BigUint::from(5u8) << 128u32) + 7u8;
+        assert_eq!(u256_halves(&input), [FieldElement::from(7u8), FieldElement::from(5u8)]);
+
+        let n = |value: u8| BigUint::from(value);
+        let proof = Groth16Proof {
+            a: [n(1), n(2)],
+            b: [[n(3), n(4)], [n(5), n(6)]],
+            c: [n(7), n(8)],
+            public_inputs: vec![input, n(9)],
+        };
+        let section = proof_section(&proof);
+        // 8 coordinates of 4 limbs, the span length, 2 halves per input
+        assert_eq!(section.len(), 32 + 1 + 4);
+        assert_eq!(section[8], FieldElement::from(3u8)); // b.x0
+        assert_eq!(section[32], FieldElement::from(2u8));
+        assert_eq!(section[33..35], [FieldElement::from(7u8), FieldElement::from(5u8)]);
+
+        let hints = [FieldElement::from(0xabcu64), FieldElement::from(0xdefu64)];
+        let calldata = full_proof_with_hints(&proof, &hints);
+        assert_eq!(calldata[0], FieldElement::from(39u8));
+        assert_eq!(split_hints(&calldata, &proof).unwrap(), hints);
+        assert_eq!(split_hints(&calldata[1..], &proof).unwrap(), hints);
+        let other = Groth16Proof { b: [[n(4), n(3)], [n(6), n(5)]], ..proof.clone() };
+        assert!(split_hints(&calldata, &other).is_err());
+    }
+}
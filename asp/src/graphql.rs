@@ -0,0 +1,166 @@
+// GraphQL API
+// Ad-hoc queries over the synced state for dashboards (`--features graphql`, POST /graphql,
+// GraphiQL on GET /graphql): deposits in a block range, leaves by commitment prefix and the
+// roots the tree went through, without a bespoke REST endpoint for each question. Deposits
+// are read from the state store a page at a time (leaf_query), roots from the in-memory root
+// history.
+
+use crate::contracts::ContractSync;
+use crate::leaf_query::{self, LeafFilter, LeafPage};
+use crate::root_history::RootRecord;
+use crate::state_store::{StateStore, StoredLeaf};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject};
+use axum::response::{Html, IntoResponse};
+use starknet::core::types::FieldElement;
+use std::sync::Arc;
+
+/// Results per query unless `first` says otherwise
+const DEFAULT_FIRST: usize = 100;
+/// Most results a query returns
+const MAX_FIRST: usize = 1000;
+
+pub type AspSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Schema over the primary contract's state
+pub fn schema(contract: Arc<ContractSync>) -> AspSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription).data(contract).finish()
+}
+
+/// GraphiQL page querying /graphql
+pub async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+#[derive(SimpleObject)]
+struct Deposit {
+    leaf_index: u32,
+    commitment: String,
+    /// Unknown for leaves synced before origins were recorded, or pruned
+    block_number: Option<u64>,
+    transaction_hash: Option<String>,
+}
+
+impl From<StoredLeaf> for Deposit {
+    fn from(leaf: StoredLeaf) -> Self {
+        Self {
+            leaf_index: leaf.index,
+            commitment: format!("0x{:x}", leaf.commitment),
+            block_number: leaf.origin.map(|origin| origin.block_number),
+            transaction_hash: leaf.origin.map(|origin| format!("0x{:x}", origin.transaction_hash)),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct DepositPage {
+    deposits: Vec<Deposit>,
+    /// `after` of the next page, null on the last one
+    next_after: Option<u32>,
+}
+
+impl From<LeafPage> for DepositPage {
+    fn from(page: LeafPage) -> Self {
+        Self {
+            deposits: page.leaves.into_iter().map(Deposit::from).collect(),
+            next_after: page.next_after,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Root {
+    root: String,
+    block_number: u64,
+    leaf_count: u32,
+    /// Result of the last `is_root_known` check, null until checked
+    known_on_chain: Option<bool>,
+}
+
+impl From<RootRecord> for Root {
+    fn from(record: RootRecord) -> Self {
+        Self {
+            root: record.root,
+            block_number: record.block_number,
+            leaf_count: record.leaf_count,
+            known_on_chain: record.known_on_chain,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Leaves in the deposit tree
+    async fn leaf_count(&self, ctx: &Context<'_>) -> u32 {
+        ctx.data_unchecked::<Arc<ContractSync>>().deposit_tree.lock().unwrap().get_leaf_count()
+    }
+
+    /// Deposits matching every given filter, in leaf order; pass `nextAfter` as `after` for
+    /// the next page
+    #[allow(clippy::too_many_arguments)]
+    async fn deposits(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<u32>,
+        after_block: Option<u64>,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        transaction_hash: Option<String>,
+        commitment: Option<String>,
+        commitment_prefix: Option<String>,
+        first: Option<usize>,
+    ) -> Result<DepositPage> {
+        let filter = LeafFilter {
+            after,
+            after_block,
+            from_block,
+            to_block,
+            transaction_hash: transaction_hash.as_deref().map(|hash| parse_felt("transactionHash", hash)).transpose()?,
+            commitment: commitment.as_deref().map(|commitment| parse_felt("commitment", commitment)).transpose()?,
+            commitment_prefix: commitment_prefix
+                .map(|prefix| leaf_query::commitment_prefix(&prefix).ok_or("commitmentPrefix must be hex"))
+                .transpose()?,
+        };
+        query_deposits(ctx, filter, first).await
+    }
+
+    /// Leaves whose commitment starts with `commitmentPrefix` (hex, 0x optional), in leaf order
+    async fn leaves_by_prefix(&self, ctx: &Context<'_>, commitment_prefix: String, after: Option<u32>, first: Option<usize>) -> Result<DepositPage> {
+        let filter = LeafFilter {
+            after,
+            commitment_prefix: Some(leaf_query::commitment_prefix(&commitment_prefix).ok_or("commitmentPrefix must be hex")?),
+            ..LeafFilter::default()
+        };
+        query_deposits(ctx, filter, first).await
+    }
+
+    /// Roots the tree reached in blocks `fromBlock..=toBlock`, oldest first (recent roots only,
+    /// as kept by the root history)
+    async fn roots(&self, ctx: &Context<'_>, from_block: Option<u64>, to_block: Option<u64>, first: Option<usize>) -> Vec<Root> {
+        let roots = &ctx.data_unchecked::<Arc<ContractSync>>().syncer.roots;
+        let blocks = from_block.unwrap_or(0)..=to_block.unwrap_or(u64::MAX);
+        let mut records: Vec<RootRecord> = roots.recent(usize::MAX).into_iter().filter(|record| blocks.contains(&record.block_number)).collect();
+        records.reverse();
+        records.into_iter().take(limit(first)).map(Root::from).collect()
+    }
+}
+
+/// One page of the stored deposits matching `filter`
+async fn query_deposits(ctx: &Context<'_>, filter: LeafFilter, first: Option<usize>) -> Result<DepositPage> {
+    let contract = ctx.data_unchecked::<Arc<ContractSync>>();
+    let leaf_count = contract.deposit_tree.lock().unwrap().get_leaf_count();
+    let store: Arc<dyn StateStore> = contract.syncer.checkpoints.clone().ok_or("this ASP keeps no state store")?;
+    let first = limit(first);
+    let page = tokio::task::spawn_blocking(move || leaf_query::query(store.as_ref(), leaf_count, &filter, first)).await??;
+    Ok(DepositPage::from(page))
+}
+
+fn parse_felt(name: &str, value: &str) -> Result<FieldElement> {
+    FieldElement::from_hex_be(value).map_err(|e| format!("Invalid {}: {}", name, e).into())
+}
+
+fn limit(first: Option<usize>) -> usize {
+    first.unwrap_or(DEFAULT_FIRST).clamp(1, MAX_FIRST)
+}
@@ -0,0 +1,192 @@
+// gRPC API
+// The query endpoints backend integrators use most, as a tonic service generated from
+// proto/asp.proto (`--features grpc`, served on GRPC_PORT): typed clients instead of
+// hand-rolled JSON, and StreamDeposits, which replays the synced deposits from a leaf index
+// and then follows the syncer's feed, so a consumer never misses one between the two.
+
+use crate::api_auth::ApiAuth;
+use crate::contracts::ContractSync;
+use crate::sync_feed::SyncNotification;
+use starknet::core::types::FieldElement;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("zylith.asp.v1");
+}
+
+use proto::asp_server::{Asp, AspServer};
+use proto::{
+    CheckNullifierRequest, CheckNullifierResponse, Deposit, GetMerklePathRequest, GetRootRequest, GetRootResponse,
+    MerklePath, StreamDepositsRequest,
+};
+
+/// Deposits read from the tree per lock, while replaying
+const REPLAY_PAGE: u32 = 1000;
+/// Deposits buffered per stream before the sender waits for the client
+const STREAM_BUFFER: usize = 256;
+
+/// The primary contract's tree, syncer and client, behind the gRPC service
+pub struct AspService {
+    contract: Arc<ContractSync>,
+}
+
+impl AspService {
+    pub fn new(contract: Arc<ContractSync>) -> Self {
+        Self { contract }
+    }
+
+    /// Synced deposits with a leaf index in `from..from + REPLAY_PAGE`
+    fn deposits_from(&self, from: u32) -> Vec<Deposit> {
+        let tree = self.contract.deposit_tree.lock().unwrap();
+        let end = tree.get_leaf_count().min(from.saturating_add(REPLAY_PAGE));
+        (from..end)
+            .filter_map(|index| tree.nodes.get(&(0, index)).map(|leaf| (index, leaf)))
+            .map(|(index, leaf)| {
+                let commitment = FieldElement::from_byte_slice_be(&leaf.to_bytes_be()).expect("tree leaves are masked to 250 bits");
+                let info = self.contract.syncer.commitments.contains(&commitment);
+                Deposit {
+                    commitment: format!("0x{:x}", commitment),
+                    leaf_index: index,
+                    block_number: info.as_ref().and_then(|info| info.block_number),
+                    transaction_hash: info.and_then(|info| info.transaction_hash),
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_felt(name: &str, value: &str) -> Result<FieldElement, Status> {
+    FieldElement::from_hex_be(value).map_err(|e| Status::invalid_argument(format!("Invalid {}: {}", name, e)))
+}
+
+#[tonic::async_trait]
+impl Asp for Arc<AspService> {
+    type StreamDepositsStream = ReceiverStream<Result<Deposit, Status>>;
+
+    async fn get_root(&self, _request: Request<GetRootRequest>) -> Result<Response<GetRootResponse>, Status> {
+        let tree = self.contract.deposit_tree.lock().unwrap();
+        Ok(Response::new(GetRootResponse {
+            root: format!("0x{:x}", tree.get_root()),
+            leaf_count: tree.get_leaf_count(),
+        }))
+    }
+
+    async fn get_merkle_path(&self, request: Request<GetMerklePathRequest>) -> Result<Response<MerklePath>, Status> {
+        let felt = parse_felt("commitment", &request.get_ref().commitment)?;
+        let index = self
+            .contract
+            .syncer
+            .commitments
+            .get(&felt)
+            .ok_or_else(|| Status::not_found("Commitment not in the deposit tree"))?;
+        let proof = self
+            .contract
+            .deposit_tree
+            .lock()
+            .unwrap()
+            .get_proof(index)
+            .filter(|proof| proof.leaf == format!("0x{:x}", felt))
+            .ok_or_else(|| Status::unavailable("Commitment indexed but not in the tree yet"))?;
+        Ok(Response::new(MerklePath {
+            leaf: proof.leaf,
+            leaf_index: proof.leaf_index,
+            path: proof.path,
+            path_indices: proof.path_indices,
+            root: proof.root,
+        }))
+    }
+
+    async fn check_nullifier(
+        &self,
+        request: Request<CheckNullifierRequest>,
+    ) -> Result<Response<CheckNullifierResponse>, Status> {
+        match self.contract.blockchain.is_nullifier_spent(&request.get_ref().nullifier).await {
+            Ok(spent) => Ok(Response::new(CheckNullifierResponse { spent })),
+            Err(e @ crate::error::BlockchainError::InvalidInput(_)) => Err(Status::invalid_argument(e.to_string())),
+            Err(e) => Err(Status::internal(format!("Failed to check nullifier: {}", e))),
+        }
+    }
+
+    async fn stream_deposits(
+        &self,
+        request: Request<StreamDepositsRequest>,
+    ) -> Result<Response<Self::StreamDepositsStream>, Status> {
+        // Subscribed before replaying, so deposits applied meanwhile are in the feed
+        let notifications = self.contract.syncer.feed.subscribe();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(stream_deposits(self.clone(), request.get_ref().from_leaf_index, notifications, sender));
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Send `sender` every deposit from leaf `next` on: replayed from the tree, then from the feed
+/// A gap in the feed (a lagging client) is filled from the tree again
+async fn stream_deposits(
+    service: Arc<AspService>,
+    mut next: u32,
+    mut notifications: broadcast::Receiver<SyncNotification>,
+    sender: mpsc::Sender<Result<Deposit, Status>>,
+) {
+    loop {
+        // Catch up with the tree
+        loop {
+            let page = service.deposits_from(next);
+            if page.is_empty() {
+                break;
+            }
+            for deposit in page {
+                next = deposit.leaf_index + 1;
+                if sender.send(Ok(deposit)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        // Follow the feed until it skips a deposit
+        loop {
+            match notifications.recv().await {
+                Ok(SyncNotification::Deposit { commitment, leaf_index, block_number, transaction_hash }) => {
+                    if leaf_index < next {
+                        continue;
+                    }
+                    if leaf_index > next {
+                        break;
+                    }
+                    next = leaf_index + 1;
+                    let deposit = Deposit {
+                        commitment,
+                        leaf_index,
+                        block_number: Some(block_number),
+                        transaction_hash: Some(transaction_hash),
+                    };
+                    if sender.send(Ok(deposit)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => break,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+/// Serve the gRPC API on `port` until the process exits, checking every call against `auth`
+/// when API keys are configured
+pub fn spawn_server(contract: Arc<ContractSync>, port: u16, auth: Option<Arc<ApiAuth>>) {
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+    let service = AspServer::with_interceptor(Arc::new(AspService::new(contract)), move |request: Request<()>| {
+        match &auth {
+            Some(auth) => auth.check_grpc(request.metadata()).map(|()| request),
+            None => Ok(request),
+        }
+    });
+    tokio::spawn(async move {
+        println!("[ASP] 📡 gRPC API on {}", addr);
+        if let Err(e) = tonic::transport::Server::builder().add_service(service).serve(addr).await {
+            eprintln!("[ASP] ❌ gRPC server stopped: {}", e);
+        }
+    });
+}
@@ -0,0 +1,60 @@
+// Two-to-one hash functions
+// The hashing scheme is injected instead of hardcoded so storage addressing and the
+// Merkle tree can be tested with stub hashers and follow the contract if it swaps hashes.
+
+use starknet::core::types::FieldElement;
+use starknet_crypto::{pedersen_hash, poseidon_hash};
+use std::sync::Arc;
+
+pub trait Hasher: Send + Sync {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement;
+}
+
+/// Starknet Pedersen hash (storage addresses of maps and storage nodes)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PedersenHasher;
+
+impl Hasher for PedersenHasher {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+        pedersen_hash(&a, &b)
+    }
+}
+
+/// Starknet Poseidon hash (Hades permutation over the Stark field)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoseidonHasher;
+
+impl Hasher for PoseidonHasher {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+        poseidon_hash(a, b)
+    }
+}
+
+/// Hasher keyed with a domain constant: node = H(H(domain, left), right), for circuits that
+/// separate their tree hash from other uses of the same function
+pub struct DomainSeparatedHasher {
+    pub inner: Arc<dyn Hasher>,
+    pub domain: FieldElement,
+}
+
+impl Hasher for DomainSeparatedHasher {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+        self.inner.hash_two(self.inner.hash_two(self.domain, a), b)
+    }
+}
+
+/// Stub hasher for tests: records every input pair and returns a + 2b,
+/// which is cheap and sensitive to operand order
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingHasher {
+    pub calls: std::sync::Mutex<Vec<(FieldElement, FieldElement)>>,
+}
+
+#[cfg(test)]
+impl Hasher for RecordingHasher {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+        self.calls.lock().unwrap().push((a, b));
+        a + b + b
+    }
+}
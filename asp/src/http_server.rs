@@ -0,0 +1,111 @@
+// HTTP server setup
+// Browser wallets call the API cross-origin, so CORS_ALLOWED_ORIGINS narrows which pages may
+// (any origin when unset, as before). With TLS_CERT_PATH and TLS_KEY_PATH (PEM files) the API
+// is served over HTTPS by rustls directly, for deployments without a terminating proxy in
+// front; that needs a build with `--features tls`.
+
+use axum::http::{HeaderName, HeaderValue};
+use axum::Router;
+use std::io;
+use std::net::SocketAddr;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// CORS policy from CORS_ALLOWED_ORIGINS (comma-separated, e.g. `https://wallet.example`)
+pub fn cors_from_env() -> io::Result<CorsLayer> {
+    let origins = match std::env::var("CORS_ALLOWED_ORIGINS") {
+        Ok(origins) => parse_origins(&origins)?,
+        Err(_) => None,
+    };
+    // Signed bare roots carry their signature in headers the wallet has to be able to read
+    let exposed = [HeaderName::from_static("x-asp-leaf-count"), HeaderName::from_static("x-asp-signature")];
+    let cors = CorsLayer::new().allow_methods(Any).allow_headers(Any).expose_headers(exposed);
+    Ok(match origins {
+        Some(origins) => cors.allow_origin(AllowOrigin::list(origins)),
+        None => cors.allow_origin(Any),
+    })
+}
+
+/// Allowed origins, None for any (`*` or an empty list)
+fn parse_origins(origins: &str) -> io::Result<Option<Vec<HeaderValue>>> {
+    let origins: Vec<&str> = origins.split(',').map(|origin| origin.trim().trim_end_matches('/')).filter(|origin| !origin.is_empty()).collect();
+    if origins.is_empty() || origins.contains(&"*") {
+        return Ok(None);
+    }
+    origins
+        .into_iter()
+        .map(|origin| {
+            if !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("CORS origin {:?} needs a scheme", origin)));
+            }
+            HeaderValue::from_str(origin).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("CORS origin {:?}: {}", origin, e)))
+        })
+        .collect::<io::Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Certificate chain and private key to serve HTTPS with
+pub struct TlsPaths {
+    pub cert: String,
+    pub key: String,
+}
+
+impl TlsPaths {
+    /// TLS_CERT_PATH and TLS_KEY_PATH; an error if only one is set
+    pub fn from_env() -> io::Result<Option<Self>> {
+        match (std::env::var("TLS_CERT_PATH"), std::env::var("TLS_KEY_PATH")) {
+            (Ok(cert), Ok(key)) => Ok(Some(Self { cert, key })),
+            (Err(_), Err(_)) => Ok(None),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "TLS_CERT_PATH and TLS_KEY_PATH go together")),
+        }
+    }
+}
+
+/// Serve `app` on `addr` until Ctrl-C, over HTTPS when `tls` is given
+pub async fn serve(addr: SocketAddr, app: Router, tls: Option<TlsPaths>) -> io::Result<()> {
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        Some(tls) => serve_tls(addr, app, tls).await,
+        None => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    tokio::signal::ctrl_c().await.ok();
+                })
+                .await
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(addr: SocketAddr, app: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>, tls: TlsPaths) -> io::Result<()> {
+    let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?;
+    let handle = axum_server::Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c().await.ok();
+        // Let in-flight requests finish, as the plain server does
+        shutdown.graceful_shutdown(Some(std::time::Duration::from_secs(10)));
+    });
+    axum_server::bind_rustls(addr, config).handle(handle).serve(app).await
+}
+
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(_addr: SocketAddr, _app: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>, _tls: TlsPaths) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "TLS_CERT_PATH needs a build with the tls feature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cors_origins() {
+        assert_eq!(parse_origins("").unwrap(), None);
+        assert_eq!(parse_origins("https://a.example, *").unwrap(), None);
+        assert_eq!(
+            parse_origins("https://wallet.example/, http://localhost:5173").unwrap().unwrap(),
+            vec![HeaderValue::from_static("https://wallet.example"), HeaderValue::from_static("http://localhost:5173")]
+        );
+        assert!(parse_origins("wallet.example").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// Instrumentation layer for RPC calls
+// InstrumentedProvider wraps any StarknetProvider and records, for each request, the method,
+// its duration, the size of the result and the class of the error into a MetricsRegistry.
+// Placed under RetryProvider, it measures every attempt actually sent to the node.
+
+use crate::error::BlockchainError;
+use crate::metrics::MetricsRegistry;
+use crate::provider::{CallResult, InvocationTrace, StarknetProvider, TxReceipt};
+use async_trait::async_trait;
+use starknet::core::types::{BlockId, EventFilter, EventsPage, FieldElement, FunctionCall};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// StarknetProvider middleware recording per-method metrics of the inner provider
+pub struct InstrumentedProvider<P> {
+    inner: P,
+    registry: Arc<MetricsRegistry>,
+}
+
+impl<P: StarknetProvider> InstrumentedProvider<P> {
+    /// Record into the process-wide registry
+    pub fn new(inner: P) -> Self {
+        Self::with_registry(inner, MetricsRegistry::global())
+    }
+
+    pub fn with_registry(inner: P, registry: Arc<MetricsRegistry>) -> Self {
+        Self { inner, registry }
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    async fn measure<T, Fut>(&self, method: &'static str, request: Fut, size: fn(&T) -> usize) -> Result<T, BlockchainError>
+    where
+        Fut: Future<Output = Result<T, BlockchainError>>,
+    {
+        let started = Instant::now();
+        let result = request.await;
+        self.registry.record(method, started.elapsed(), result.as_ref().map(size));
+        result
+    }
+}
+
+#[async_trait]
+impl<P: StarknetProvider> StarknetProvider for InstrumentedProvider<P> {
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        self.measure("call", self.inner.call(call, block), Vec::len).await
+    }
+
+    async fn batch_call(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        self.measure("batch_call", self.inner.batch_call(calls, block), Vec::len).await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.measure("get_storage_at", self.inner.get_storage_at(address, key, block), |_| 1)
+            .await
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.measure("get_class_hash_at", self.inner.get_class_hash_at(block, address), |_| 1)
+            .await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        self.measure("get_transaction_receipt", self.inner.get_transaction_receipt(tx_hash), |receipt| {
+            receipt.events.len()
+        })
+        .await
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        self.measure("block_number", self.inner.block_number(), |_| 1).await
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        self.measure("block_hash", self.inner.block_hash(block), |_| 1).await
+    }
+
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        self.measure("get_events", self.inner.get_events(filter, continuation_token, chunk_size), |page| {
+            page.events.len()
+        })
+        .await
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        self.measure("trace_block", self.inner.trace_block(block), Vec::len).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+    use starknet::core::types::BlockTag;
+    use starknet::core::utils::get_selector_from_name;
+
+    #[tokio::test]
+    async fn test_records_successes_and_error_classes() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let registry = Arc::new(MetricsRegistry::default());
+        let provider = InstrumentedProvider::with_registry(
+            MockProvider::new()
+                .with_block_number(7)
+                .with_call_result(zylith, "get_merkle_root", vec![FieldElement::ONE]),
+            registry.clone(),
+        );
+
+        provider.block_number().await.unwrap();
+        let root_call = FunctionCall {
+            contract_address: zylith,
+            entry_point_selector: get_selector_from_name("get_merkle_root").unwrap(),
+            calldata: vec![],
+        };
+        provider.call(root_call, BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        let unknown = FunctionCall {
+            contract_address: zylith,
+            entry_point_selector: get_selector_from_name("not_an_entry_point").unwrap(),
+            calldata: vec![],
+        };
+        provider.call(unknown, BlockId::Tag(BlockTag::Latest)).await.unwrap_err();
+
+        assert_eq!(registry.method("block_number").unwrap().requests, 1);
+        let call = registry.method("call").unwrap();
+        assert_eq!(call.requests, 2);
+        assert_eq!(call.result_size.sum, 1.0);
+        assert_eq!(call.errors.get("entry_point_not_found"), Some(&1));
+    }
+}
@@ -0,0 +1,170 @@
+// Paginated leaf queries
+// Listings of deposits (REST /deposit/list, GraphQL) read the state store a page at a time in
+// leaf index order, which is also block order, and hand back a cursor to continue from
+// instead of the whole tree. Block filters bisect to their first leaf, so a page late in the
+// chain doesn't rescan the leaves before it; the other filters are applied while scanning.
+
+use crate::state_store::{StateStore, StoredLeaf};
+use starknet::core::types::FieldElement;
+use std::io;
+
+/// Leaves read from the store at once while scanning
+const SCAN_PAGE: u32 = 1000;
+
+/// Which leaves a listing returns; every field narrows it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeafFilter {
+    /// Cursor: only leaves after this index
+    pub after: Option<u32>,
+    /// Cursor: only leaves deposited after this block
+    pub after_block: Option<u64>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub transaction_hash: Option<FieldElement>,
+    pub commitment: Option<FieldElement>,
+    /// Hex digits the commitment starts with (lowercase, no 0x or leading zeros)
+    pub commitment_prefix: Option<String>,
+}
+
+/// One page of a listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafPage {
+    pub leaves: Vec<StoredLeaf>,
+    /// `after` of the next page; None once the listing is exhausted
+    pub next_after: Option<u32>,
+}
+
+impl LeafFilter {
+    /// First block a leaf may be in
+    fn min_block(&self) -> Option<u64> {
+        let after_block = self.after_block.map(|block| block.saturating_add(1));
+        after_block.max(self.from_block)
+    }
+
+    /// Whether leaves without a known block are left out
+    fn needs_origin(&self) -> bool {
+        self.min_block().is_some() || self.to_block.is_some() || self.transaction_hash.is_some()
+    }
+
+    fn matches(&self, leaf: &StoredLeaf) -> bool {
+        if self.needs_origin() && leaf.origin.is_none() {
+            return false;
+        }
+        self.commitment.is_none_or(|commitment| leaf.commitment == commitment)
+            && self.transaction_hash.is_none_or(|hash| leaf.origin.is_some_and(|origin| origin.transaction_hash == hash))
+            && self.min_block().is_none_or(|block| leaf.origin.is_some_and(|origin| origin.block_number >= block))
+            && self.commitment_prefix.as_ref().is_none_or(|prefix| format!("{:x}", leaf.commitment).starts_with(prefix.as_str()))
+    }
+
+    /// Whether `leaf`, and so every leaf after it, is past `to_block`
+    fn is_past(&self, leaf: &StoredLeaf) -> bool {
+        match (self.to_block, leaf.origin) {
+            (Some(to_block), Some(origin)) => origin.block_number > to_block,
+            _ => false,
+        }
+    }
+}
+
+/// Normalize a hex commitment prefix for `LeafFilter::commitment_prefix`; None if not hex
+pub fn commitment_prefix(prefix: &str) -> Option<String> {
+    let prefix = prefix.trim().trim_start_matches("0x").trim_start_matches('0').to_ascii_lowercase();
+    prefix.chars().all(|c| c.is_ascii_hexdigit()).then_some(prefix)
+}
+
+/// Up to `limit` stored leaves matching `filter`, in leaf index order
+/// `leaf_count` bounds the bisection of block filters (the tree's count; the store may be behind)
+pub fn query(store: &dyn StateStore, leaf_count: u32, filter: &LeafFilter, limit: usize) -> io::Result<LeafPage> {
+    let mut from = filter.after.map_or(0, |after| after.saturating_add(1));
+    if let Some(block) = filter.min_block() {
+        from = from.max(first_leaf_from_block(store, leaf_count, block)?);
+    }
+
+    let mut leaves = Vec::new();
+    loop {
+        let page = store.leaves(from, from.saturating_add(SCAN_PAGE))?;
+        let Some(last) = page.last() else {
+            return Ok(LeafPage { leaves, next_after: None });
+        };
+        from = last.index + 1;
+        for leaf in page {
+            if filter.is_past(&leaf) {
+                return Ok(LeafPage { leaves, next_after: None });
+            }
+            if !filter.matches(&leaf) {
+                continue;
+            }
+            if leaves.len() == limit {
+                // There is a next page: it starts after the last leaf returned
+                let next_after = leaves.last().map(|leaf| leaf.index);
+                return Ok(LeafPage { leaves, next_after });
+            }
+            leaves.push(leaf);
+        }
+    }
+}
+
+/// Index of the first stored leaf deposited at `block` or later
+/// Leaves are in block order; those without an origin are older than any with one (synced
+/// before origins were recorded, or pruned)
+fn first_leaf_from_block(store: &dyn StateStore, leaf_count: u32, block: u64) -> io::Result<u32> {
+    let (mut low, mut high) = (0, leaf_count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let reached = match store.leaves(mid, mid + 1)?.first() {
+            Some(leaf) => leaf.origin.is_some_and(|origin| origin.block_number >= block),
+            // Not saved yet: past every saved leaf
+            None => true,
+        };
+        match reached {
+            true => high = mid,
+            false => low = mid + 1,
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::SyncCheckpoint;
+    use crate::commitment_index::LeafOrigin;
+    use crate::state_store::MemoryStore;
+
+    #[test]
+    fn test_filtered_pages() {
+        let store = MemoryStore::default();
+        // Leaf 0 has no origin (pruned), then two deposits per block from block 10
+        let origins = (1..6u64).map(|i| Some(LeafOrigin { block_number: 10 + (i - 1) / 2, transaction_hash: FieldElement::from(i) }));
+        store
+            .save(&SyncCheckpoint {
+                leaves: [0xab01u64, 0xab02, 0xcd03, 0xab04, 0xcd05, 0xab06].into_iter().map(FieldElement::from).collect(),
+                leaf_origins: std::iter::once(None).chain(origins).collect(),
+                ..SyncCheckpoint::default()
+            })
+            .unwrap();
+        let indexes = |filter: &LeafFilter, limit| {
+            let page = query(&store, 6, filter, limit).unwrap();
+            (page.leaves.iter().map(|leaf| leaf.index).collect::<Vec<_>>(), page.next_after)
+        };
+
+        assert_eq!(indexes(&LeafFilter::default(), 4), (vec![0, 1, 2, 3], Some(3)));
+        assert_eq!(indexes(&LeafFilter { after: Some(3), ..LeafFilter::default() }, 4), (vec![4, 5], None));
+        // Exactly `limit` left: no next page
+        assert_eq!(indexes(&LeafFilter { after: Some(3), ..LeafFilter::default() }, 2), (vec![4, 5], None));
+
+        let blocks = LeafFilter { from_block: Some(11), to_block: Some(11), ..LeafFilter::default() };
+        assert_eq!(indexes(&blocks, 10), (vec![3, 4], None));
+        assert_eq!(indexes(&LeafFilter { after_block: Some(10), ..LeafFilter::default() }, 1), (vec![3], Some(3)));
+        assert_eq!(indexes(&LeafFilter { from_block: Some(13), ..LeafFilter::default() }, 10), (vec![], None));
+        // The tree may be ahead of the store
+        assert_eq!(query(&store, 9, &LeafFilter { from_block: Some(12), ..LeafFilter::default() }, 10).unwrap().leaves.len(), 1);
+
+        let tx = LeafFilter { transaction_hash: Some(FieldElement::from(4u64)), ..LeafFilter::default() };
+        assert_eq!(indexes(&tx, 10), (vec![4], None));
+        let commitment = LeafFilter { commitment: Some(FieldElement::from(0xcd03u64)), ..LeafFilter::default() };
+        assert_eq!(indexes(&commitment, 10), (vec![2], None));
+        let prefix = LeafFilter { commitment_prefix: commitment_prefix("0x0AB"), ..LeafFilter::default() };
+        assert_eq!(indexes(&prefix, 10), (vec![0, 1, 3, 5], None));
+        assert_eq!(commitment_prefix("xyz"), None);
+    }
+}
@@ -1,32 +1,119 @@
 mod abi;
+mod admin;
+mod api_auth;
+mod api_version;
+mod artifacts;
+mod at_rest;
 mod blockchain;
+mod bootstrap;
 mod calldata;
+mod checkpoint;
+#[cfg(feature = "prover")]
+mod circom;
+mod circuit_inputs;
 mod commitment;
+mod commitment_index;
+mod config;
+mod consistency;
+mod contracts;
+mod conv;
+mod dedupe;
+mod deployment;
+mod error;
+mod event_fetcher;
+mod events;
+mod failover;
+#[cfg(feature = "prover")]
+mod garaga;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hasher;
+mod http_server;
+mod instrument;
+mod leaf_query;
+mod maintenance;
 mod merkle;
+mod metrics;
+mod migrations;
+#[cfg(test)]
+mod mock_provider;
+mod multicall;
+mod note_memo;
+mod note_scanner;
+mod notes;
+mod nullifier_cache;
+mod nullifier_tree;
+mod openapi;
+mod page_size;
+mod pinned;
+#[cfg(feature = "postgres")]
+mod postgres_store;
 mod proof;
+mod proof_cache;
+mod proof_jobs;
+#[cfg(feature = "prover")]
+mod prover;
+mod prover_backend;
+mod provider;
+mod rate_limit;
+mod redact;
+mod response_cache;
+mod response_signing;
+mod retry;
+#[cfg(feature = "rocksdb")]
+mod rocksdb_store;
+mod root_history;
+mod snapshot;
+mod sqlite_store;
+mod state_export;
+mod state_store;
+mod storage;
+mod sync_feed;
+mod sync_status;
 mod syncer;
+mod timeouts;
+mod tree_store;
+mod tree_versions;
+#[cfg(feature = "prover")]
+mod verifier;
+mod vk_registry;
+mod watchdog;
+mod witness;
+#[cfg(feature = "ws")]
+mod ws;
 
 use axum::{
-    extract::{Path, State},
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use blockchain::BlockchainClient;
+use blockchain::{BlockchainClient, Confirmation};
+use contracts::{ContractRegistry, ContractSync};
 use calldata::{
     build_approve_calldata, build_burn_liquidity_calldata, build_deposit_calldata,
     build_initialize_calldata, build_mint_liquidity_calldata, build_swap_calldata,
     build_withdraw_calldata, u256_to_low_high,
 };
+use checkpoint::CheckpointStore;
+use leaf_query::LeafFilter;
+use state_store::StateStore;
+use sync_feed::{SyncNotification, Topic};
 use num_bigint::BigUint;
 use std::str::FromStr;
 use commitment::{generate_commitment, generate_note};
-use merkle::{MerkleProof, MerkleTree, TREE_DEPTH};
+use merkle::{verify_merkle_path, MerkleProof, MerkleTree, TREE_DEPTH};
+use redact::{RedactionPolicy, Sensitive};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use sync_status::SyncTracker;
 use syncer::Syncer;
-use tower_http::cors::{Any, CorsLayer};
+use watchdog::WatchdogConfig;
+use utoipa::{IntoParams, ToSchema};
 
 /// Application state with two Merkle trees and blockchain client
 #[derive(Clone)]
@@ -39,18 +126,43 @@ struct AppState {
     blockchain: Arc<BlockchainClient>,
     /// Zylith contract address
     zylith_address: String,
+    /// Log redaction policy for commitments, nullifiers and recipients
+    redaction: RedactionPolicy,
+    /// Progress of the deposit tree syncer
+    sync_status: SyncTracker,
+    /// Deposit tree syncer, for operations such as backfills
+    syncer: Arc<Syncer>,
+    /// Every synced contract; the fields above are the primary one's
+    contracts: Arc<ContractRegistry>,
+    /// Blocks a syncer may lag the chain tip before /readyz fails
+    ready_max_blocks_behind: u64,
+    /// Operator key signing Merkle paths and roots, if configured
+    signer: Option<Arc<response_signing::ResponseSigner>>,
+    /// When the server started, for uptime in /admin/stats
+    started: std::time::Instant,
+    /// Swap and LP proofs by circuit and input, while their root is fresh
+    proof_cache: Arc<proof_cache::ProofCache>,
+    /// Circuit wasm, zkeys and verification keys, checked against their pinned hashes
+    artifacts: Arc<artifacts::ArtifactManager>,
+    /// Queued and recently finished swap and LP proof jobs
+    proof_jobs: Arc<proof_jobs::ProofJobs>,
+    /// Where swap and LP proofs are generated
+    prover_backend: prover_backend::ProverBackend,
 }
 
 /// Response for tree info
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct TreeInfo {
     root: String,
     leaf_count: u32,
     depth: usize,
+    /// Operator's signature of the root and leaf count, when the ASP signs its responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<response_signing::ResponseSignature>,
 }
 
 /// Request to insert into associated set
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct InsertRequest {
     commitment: String,
 }
@@ -59,12 +171,38 @@ struct InsertRequest {
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    // `zylith-asp export|import ...`: move the sync state out of or into the configured backend
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first().filter(|command| *command == "export" || *command == "import") {
+        if let Err(e) = state_export::run(command, &args[1..]) {
+            eprintln!("❌ {} failed: {}", command, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // `zylith-asp prover bench ...`: time each circuit on every available prover backend
+    if args.first().map(String::as_str) == Some("prover") {
+        if let Err(e) = prover_backend::run(&args[1..]).await {
+            eprintln!("❌ prover failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // `NOTE_SEED=... zylith-asp notes scan`: the notes a wallet seed owns among the synced deposits
+    if args.first().map(String::as_str) == Some("notes") {
+        if let Err(e) = note_scanner::run(&args[1..]).await {
+            eprintln!("❌ notes failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Get configuration from environment
     let rpc_url = std::env::var("RPC_URL")
         .unwrap_or_else(|_| "https://api.cartridge.gg/x/starknet/sepolia".to_string());
-    let contract_address = std::env::var("CONTRACT_ADDRESS").unwrap_or_else(|_| {
-        "0x05c1fecd5025d2e3d71482ac26ecf9138b08c9483f43761543cf71960373ce0b".to_string()
-    });
+    // CONTRACT_ADDRESSES=a,b,...: sync several Zylith deployments; the first one is the primary
+    let contract_addresses = contracts::contract_addresses_from_env();
+    let contract_address = contract_addresses[0].clone();
 
     // Validate ABIs on startup
     let zylith_abi = abi::get_zylith_abi();
@@ -77,46 +215,122 @@ async fn main() {
 
     println!("✓ ABIs validated successfully");
 
-    // Initialize blockchain client
-    let blockchain = Arc::new(
-        BlockchainClient::new(&rpc_url, &contract_address)
-            .expect("Failed to initialize blockchain client"),
-    );
+    let redaction = RedactionPolicy::from_env();
+
+    // STATE_ENCRYPTION_KEY / STATE_ENCRYPTION_KEY_FILE: encrypt the sync checkpoints at rest
+    let at_rest = at_rest::AtRestKey::from_env().unwrap_or_else(|e| panic!("Invalid state encryption key: {}", e));
+
+    // Client, deposit tree and syncer of every contract (fails fast if one isn't deployed)
+    let mut synced = Vec::new();
+    for (i, address) in contract_addresses.iter().enumerate() {
+        // The primary contract keeps the original state file, so existing deployments resume
+        let store = match i {
+            0 => CheckpointStore::default(),
+            _ => CheckpointStore::for_contract(starknet::core::types::FieldElement::from_hex_be(address).expect("Invalid contract address")),
+        };
+        let store = match &at_rest {
+            Some(key) => store.with_encryption(key.clone()),
+            None => store,
+        };
+        // SNAPSHOT_URL: published snapshot (URL or file) of the primary contract's tree to start from
+        let snapshot = match i {
+            0 => std::env::var("SNAPSHOT_URL").ok(),
+            _ => None,
+        };
+        let contract = ContractSync::connect(&rpc_url, address, store, snapshot, redaction)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to initialize contract {}: {}", address, e));
+        // Run each syncer in background, restarted by the watchdog if it crashes or stops making progress
+        contract.spawn_sync(WatchdogConfig::from_env());
+        // Cross-check a few recorded roots against the contract every ROOT_CHECK_SECS
+        let root_check_secs = std::env::var("ROOT_CHECK_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60);
+        contract.spawn_root_checks(std::time::Duration::from_secs(root_check_secs), 4);
+        // MAINTENANCE_INTERVAL_SECS / MAINTENANCE_HOURS: compact the sync state store off-peak
+        if let Some(config) = maintenance::MaintenanceConfig::from_env() {
+            contract.spawn_maintenance(config);
+        }
+        synced.push(contract);
+    }
+    let contracts = Arc::new(ContractRegistry::new(synced));
+    let primary = contracts.primary().clone();
+
+    // API_KEYS / API_KEYS_FILE: a key (with its own rate limit) for everything but the read-only
+    // root and nullifier checks, on the HTTP and gRPC APIs alike
+    let api_keys = api_auth::ApiAuth::from_env().unwrap_or_else(|e| panic!("Failed to read API_KEYS_FILE: {}", e)).map(Arc::new);
 
-    // Initialize both trees
-    let deposit_tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+    // GRPC_PORT: typed gRPC API over the primary contract (GetMerklePath, CheckNullifier, StreamDeposits)
+    #[cfg(feature = "grpc")]
+    if let Some(port) = std::env::var("GRPC_PORT").ok().and_then(|port| port.parse().ok()) {
+        grpc::spawn_server(primary.clone(), port, api_keys.clone());
+    }
+
+    // Associated set tree (not tied to a contract)
     let associated_tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
 
     let state = AppState {
-        deposit_tree: deposit_tree.clone(),
+        deposit_tree: primary.deposit_tree.clone(),
         associated_tree: associated_tree.clone(),
-        blockchain: blockchain.clone(),
+        blockchain: primary.blockchain.clone(),
         zylith_address: contract_address.clone(),
+        redaction,
+        sync_status: primary.sync_status.clone(),
+        syncer: primary.syncer.clone(),
+        contracts: contracts.clone(),
+        // READY_MAX_BLOCKS_BEHIND: lag of the syncers past which load balancers stop routing here
+        ready_max_blocks_behind: std::env::var("READY_MAX_BLOCKS_BEHIND").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+        // ASP_SIGNING_KEY: operator key signing paths and roots, for wallets to verify
+        signer: response_signing::ResponseSigner::from_env().unwrap_or_else(|e| panic!("{}", e)).map(Arc::new),
+        started: std::time::Instant::now(),
+        // PROOF_CACHE_TTL_SECS / PROOF_CACHE_DIR: how long proofs are reused for retries, and where they persist
+        proof_cache: Arc::new(proof_cache::ProofCache::from_env()),
+        // CIRCUITS_DIR / CIRCUIT_ARTIFACTS_URL / CIRCUIT_ARTIFACTS_MANIFEST: where circuit files live, come from and what they must hash to
+        artifacts: Arc::new(artifacts::ArtifactManager::from_env().unwrap_or_else(|e| panic!("{}", e))),
+        // PROOF_JOB_CONCURRENCY / PROOF_JOB_QUEUE_LIMIT / PROOF_JOB_TTL_SECS: proofs run at once, pending at most, kept after finishing
+        proof_jobs: Arc::new(proof_jobs::ProofJobs::from_env()),
+        // PROVER_BACKEND: arkworks, rapidsnark, snarkjs or auto (`zylith-asp prover bench` compares them)
+        prover_backend: prover_backend::ProverBackend::from_env().unwrap_or_else(|e| panic!("{}", e)),
     };
+    println!("[Proof] ⚙️  Prover backend: {}", state.prover_backend);
+    // VK_REGISTRY: the local verification keys must be the ones the deployed verifiers were generated from
+    match vk_registry::VkRegistry::from_env(state.artifacts.circuits_dir()).unwrap_or_else(|e| panic!("Invalid VK registry: {}", e)) {
+        Some(registry) => {
+            let versions = vk_registry::check_deployed_verifiers(&registry, &state.artifacts, &*state.blockchain)
+                .await
+                .unwrap_or_else(|e| panic!("Verification keys don't match the deployed verifiers:\n{}", e));
+            let versions = versions.iter().map(|(circuit, version)| format!("{} v{}", circuit, version)).collect::<Vec<_>>();
+            println!("[Proof] 🔑 Verification keys match the deployed verifiers ({})", versions.join(", "));
+        }
+        None => println!("[Proof] ⚠️  No VK registry: verification keys aren't checked against the deployed verifiers"),
+    }
+    if let Some(signer) = &state.signer {
+        println!("[ASP] ✍️  Signing paths and roots with public key 0x{:x}", signer.public_key());
+    }
 
-    // Initialize Syncer for deposit tree with blockchain client for root verification
-    let syncer = Syncer::new(&rpc_url, &contract_address, deposit_tree)
-        .with_blockchain_client(blockchain.clone());
-    
-    // Run syncer in background
-    tokio::spawn(async move {
-        syncer.run().await;
-    });
-
-    // Configure CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    // CORS_ALLOWED_ORIGINS: pages allowed to call the API (any origin if unset)
+    let cors = http_server::cors_from_env().unwrap_or_else(|e| panic!("Invalid CORS_ALLOWED_ORIGINS: {}", e));
 
-    let app = Router::new()
+    let api = Router::new()
         // Deposit tree endpoints
         .route("/deposit/proof/:index", get(get_deposit_proof))
         .route("/deposit/root", get(get_deposit_root))
         .route("/deposit/info", get(get_deposit_info))
+        .route("/deposit/sync", get(get_sync_status))
         .route("/deposit/index/:commitment", get(get_deposit_index))
-        .route("/deposit/resync", post(force_resync))
+        .route("/deposit/contains/:commitment", get(get_deposit_leaf_info))
+        .route("/deposit/path/:commitment", get(get_deposit_path))
+        .route("/deposit/roots", get(get_root_history))
+        .route("/deposit/roots/:root/proof/:index", get(get_deposit_proof_at_root))
+        .route("/deposit/roots/:root/diff/:to", get(get_deposit_diff))
+        .route("/deposit/tx/:tx_hash", get(get_deposit_index_for_tx))
+        .route("/deposit/consistency", get(check_deposit_consistency))
         .route("/deposit/list", get(list_deposits))
+        .route("/deposit/snapshot", get(get_deposit_snapshot))
+        .route("/deposit/snapshot.bin", get(get_deposit_snapshot_binary))
+        // Per-contract deposit trees, when several contracts are synced
+        .route("/contracts", get(list_contracts))
+        .route("/contracts/:address/deposit/root", get(get_contract_deposit_root))
+        .route("/contracts/:address/deposit/proof/:index", get(get_contract_deposit_proof))
+        .route("/contracts/:address/deposit/sync", get(get_contract_sync_status))
         // Associated set tree endpoints
         .route("/associated/proof/:index", get(get_associated_proof))
         .route("/associated/root", get(get_associated_root))
@@ -125,13 +339,24 @@ async fn main() {
         // Legacy endpoints (for backwards compatibility)
         .route("/proof/:index", get(get_deposit_proof))
         .route("/root", get(get_deposit_root))
+        // Short routes of the ASP query API
+        .route("/roots/:root/known", get(get_root_known))
+        .route("/commitment/:commitment/path", get(get_deposit_path))
+        .route("/paths", post(get_batch_paths))
+        .route("/sync/status", get(get_sync_status))
+        .route("/nullifier/:nullifier", get(check_nullifier))
+        .route("/ws", get(sync_feed_socket))
+        .route("/events/stream", get(sync_feed_events))
         // Blockchain read endpoints
         .route("/api/pool/root", get(get_pool_root))
         .route("/api/pool/info", get(get_pool_info))
         .route("/api/nullifier/:nullifier", get(check_nullifier))
+        .route("/nullifier/root", get(get_nullifier_root))
+        .route("/nullifier/proof/:nullifier", get(get_nullifier_non_membership_proof))
         .route("/api/token/:address/balance/:owner", get(get_token_balance))
         .route("/api/token/:address/allowance/:owner/:spender", get(get_token_allowance))
         .route("/api/pool/initialized", get(check_pool_initialized))
+        .route("/api/pool/reserves", get(get_pool_reserves))
         // Transaction preparation endpoints
         .route("/api/deposit/prepare", post(prepare_deposit))
         .route("/api/swap/prepare", post(prepare_swap))
@@ -142,31 +367,99 @@ async fn main() {
         // ZK Proof generation endpoints
         .route("/api/proof/swap", post(generate_swap_proof_endpoint))
         .route("/api/proof/lp-mint", post(generate_lp_proof_endpoint))
+        .route("/api/proof/jobs/swap", post(submit_swap_proof_job))
+        .route("/api/proof/jobs/lp-mint", post(submit_lp_proof_job))
+        .route("/api/proof/jobs/:id", get(get_proof_job))
+        .route("/api/proof/jobs/:id/events", get(proof_job_events))
+        .route("/api/witness/membership", post(membership_witness_endpoint));
+    // Groth16 proofs generated in process (arkworks) instead of by snarkjs and rapidsnark
+    #[cfg(feature = "prover")]
+    let api = api
+        .route("/api/proof/membership", post(membership_proof_endpoint))
+        .route("/api/proof/verify", post(verify_proof_endpoint));
+    let ops = Router::new()
         // Health check
         .route("/health", get(health_check))
-        .layer(cors)
-        .with_state(state);
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::swagger_ui))
+        .route("/metrics", get(rpc_metrics));
+    // API_UNVERSIONED_SUNSET: date announced for removing the unversioned aliases of /v1
+    let deprecation = Arc::new(api_version::Deprecation::from_env().unwrap_or_else(|e| panic!("{}", e)));
+    let app = Router::new()
+        .nest(api_version::V1_PREFIX, api.clone().merge(ops.clone()))
+        .merge(api.layer(axum::middleware::from_fn_with_state(deprecation, api_version::deprecate_unversioned)))
+        .merge(ops);
+    // Ad-hoc queries over the synced state (POST), with GraphiQL on GET
+    #[cfg(feature = "graphql")]
+    let app = app.route(
+        "/graphql",
+        get(graphql::graphiql).post_service(async_graphql_axum::GraphQL::new(graphql::schema(primary.clone()))),
+    );
+    let app = match api_keys {
+        Some(auth) => {
+            println!("[ASP] 🔑 API keys required ({} configured)", auth.key_count());
+            app.route_layer(axum::middleware::from_fn_with_state(auth, api_auth::authorize))
+        }
+        None => app,
+    };
+    // ADMIN_API_KEY: maintenance endpoints for operators, behind their own key
+    let app = match admin::AdminAuth::from_env() {
+        Some(auth) => {
+            println!("[ASP] 🛠️  Admin API enabled on /admin (resync, backfill, prune, stats)");
+            let admin_routes = Router::new()
+                .route("/admin/resync", post(admin::resync))
+                .route("/admin/backfill", post(admin::backfill))
+                .route("/admin/prune", post(admin::prune))
+                .route("/admin/stats", get(admin::stats))
+                .route_layer(axum::middleware::from_fn_with_state(Arc::new(auth), admin::require_admin));
+            app.nest(api_version::V1_PREFIX, admin_routes.clone()).merge(admin_routes)
+        }
+        None => app,
+    };
+    let app = app.layer(cors).with_state(state);
 
     let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-    let addr = format!("0.0.0.0:{}", port);
+    let addr: std::net::SocketAddr = format!("0.0.0.0:{}", port).parse().expect("Invalid PORT");
+    // TLS_CERT_PATH / TLS_KEY_PATH: serve HTTPS directly
+    let tls = http_server::TlsPaths::from_env().unwrap_or_else(|e| panic!("Invalid TLS configuration: {}", e));
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    println!("ASP Server running on {}", addr);
+    println!("ASP Server running on {}://{}", if tls.is_some() { "https" } else { "http" }, addr);
     println!("Zylith Contract: {}", contract_address);
+    for address in &contract_addresses[1..] {
+        println!("Also syncing: {}", address);
+    }
     println!("RPC URL: {}", rpc_url);
-    println!("\nEndpoints:");
+    println!("\nEndpoints (also under /v1; the unversioned paths are deprecated aliases):");
     println!("  GET  /deposit/proof/:index  - Get Merkle proof for deposit");
     println!("  GET  /deposit/root          - Get current deposit tree root");
     println!("  GET  /deposit/info          - Get deposit tree info");
+    println!("  GET  /deposit/sync          - Get syncer progress and ETA");
     println!("  GET  /deposit/index/:commitment - Get leaf index for commitment");
-    println!("  POST /deposit/resync        - Force re-sync from specific block");
+    println!("  GET  /deposit/contains/:commitment - Get leaf index, block and tx of a synced commitment");
+    println!("  GET  /deposit/path/:commitment - Get Merkle path and root for a commitment");
+    println!("  GET  /deposit/roots         - Get recent roots and a validated root to prove against");
+    println!("  GET  /deposit/roots/:root/proof/:index - Get Merkle proof for deposit against a recorded root");
+    println!("  GET  /deposit/roots/:root/diff/:to - Get commitments appended between two recorded roots");
+    println!("  GET  /deposit/tx/:tx_hash - Get leaf index of a deposit transaction");
+    println!("  GET  /deposit/snapshot - Export deposit tree snapshot from contract events");
+    println!("  GET  /deposit/snapshot.bin - Export the synced deposit tree as a binary snapshot");
+    println!("  GET  /deposit/consistency   - Compare the synced tree with the on-chain root");
+    println!("  GET  /contracts             - List synced contracts with their roots and sync status");
+    println!("  GET  /contracts/:address/deposit/root - Get a contract's deposit tree root");
+    println!("  GET  /contracts/:address/deposit/proof/:index - Get Merkle proof for a contract's deposit");
+    println!("  GET  /contracts/:address/deposit/sync - Get a contract's syncer progress");
     println!("  GET  /associated/proof/:index - Get Merkle proof for associated set");
     println!("  GET  /associated/root       - Get current associated set root");
     println!("  GET  /associated/info       - Get associated set tree info");
     println!("  POST /associated/insert     - Insert commitment into associated set");
     println!("  GET  /api/pool/root         - Get Merkle root on-chain");
     println!("  GET  /api/pool/info         - Get pool info");
+    println!("  GET  /api/pool/reserves     - Get pool reserves and liquidity");
     println!("  GET  /api/nullifier/:nullifier - Check if nullifier is spent");
+    println!("  GET  /nullifier/root        - Get spent nullifier tree root");
+    println!("  GET  /nullifier/proof/:nullifier - Get non-membership proof for a nullifier");
     println!("  GET  /api/token/:address/balance/:owner - Get token balance");
     println!("  GET  /api/token/:address/allowance/:owner/:spender - Get token allowance");
     println!("  POST /api/deposit/prepare    - Prepare deposit transaction");
@@ -174,38 +467,93 @@ async fn main() {
     println!("  POST /api/withdraw/prepare  - Prepare withdraw transaction");
     println!("  POST /api/liquidity/mint/prepare - Prepare mint liquidity transaction");
     println!("  POST /api/liquidity/burn/prepare - Prepare burn liquidity transaction");
+    println!("  POST /api/proof/jobs/swap   - Queue a swap proof, answered with a job id");
+    println!("  POST /api/proof/jobs/lp-mint - Queue an LP mint proof, answered with a job id");
+    println!("  GET  /api/proof/jobs/:id    - Get a proof job's status and result");
+    println!("  GET  /api/proof/jobs/:id/events - Server-sent events of a proof job until it finishes");
+    println!("  POST /api/witness/membership - Build the membership circuit inputs for a note");
+    #[cfg(feature = "prover")]
+    println!("  POST /api/proof/membership  - Prove a note is in the deposit tree (in-process Groth16)");
+    #[cfg(feature = "prover")]
+    println!("  POST /api/proof/verify      - Check a Groth16 proof against the circuit's verification key");
+    println!("  GET  /root                  - Get current deposit tree root");
+    println!("  GET  /roots/:root/known     - Check a root locally and on-chain");
+    println!("  GET  /commitment/:commitment/path - Get Merkle path and root for a commitment");
+    println!("  POST /paths                 - Get Merkle paths for many commitments against one root");
+    println!("  GET  /nullifier/:nullifier  - Check if nullifier is spent");
+    println!("  GET  /sync/status           - Get syncer progress and ETA");
+    println!("  GET  /ws                    - WebSocket stream of deposits, roots and spent nullifiers");
+    println!("  GET  /events/stream         - Same notifications as server-sent events, resumable with Last-Event-ID");
     println!("  GET  /health                - Health check");
-
-    axum::serve(listener, app).await.unwrap();
+    println!("  GET  /healthz               - Liveness probe");
+    println!("  GET  /readyz                - Readiness probe: syncers near the chain tip and RPC reachable");
+    println!("  GET  /metrics               - RPC metrics (Prometheus)");
+    println!("  GET  /openapi.json          - OpenAPI 3 document of this API");
+    println!("  GET  /docs                  - Swagger UI");
+    #[cfg(feature = "graphql")]
+    println!("  POST /graphql               - GraphQL queries over deposits, leaves and roots (GraphiQL on GET)");
+
+    let syncer = primary.syncer.clone();
+    http_server::serve(addr, app, tls).await.unwrap_or_else(|e| panic!("Server failed: {}", e));
+
+    // SNAPSHOT_EXPORT_PATH: leave a binary snapshot of the deposit tree on shutdown, for another
+    // instance to start from (SNAPSHOT_URL)
+    if let Ok(path) = std::env::var("SNAPSHOT_EXPORT_PATH") {
+        match syncer.export_snapshot(&path) {
+            Ok(()) => println!("[ASP] 📦 Snapshot written to {}", path),
+            Err(e) => eprintln!("[ASP] ❌ Failed to write snapshot to {}: {}", path, e),
+        }
+    }
 }
 
 // ==================== Deposit Tree Endpoints ====================
 
+#[utoipa::path(
+    get,
+    path = "/deposit/proof/{index}",
+    tag = "deposit",
+    params(("index" = u32, Path, description = "Leaf index")),
+    responses(
+        (status = 200, description = "Merkle proof of the leaf", body = MerkleProof),
+        (status = 404, description = "No leaf at this index"),
+    )
+)]
 async fn get_deposit_proof(
     Path(index): Path<u32>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
-    println!("[ASP] 📥 GET /deposit/proof/{}", index);
-    println!("[ASP] 🔄 Processing proof request for index {}...", index);
+    println!("[ASP] 📥 GET /deposit/proof/{}", state.redaction.redact(Sensitive::LeafIndex, index));
+    println!("[ASP] 🔄 Processing proof request for index {}...", state.redaction.redact(Sensitive::LeafIndex, index));
     
     let tree = state.deposit_tree.lock().unwrap();
     let leaf_count = tree.get_leaf_count();
 
     match tree.get_proof(index) {
-        Some(proof) => {
-            println!("[ASP] ✅ Proof generated successfully for index {}", index);
+        Some(proof) if !verify_merkle_path(&tree.get_root(), &tree.nodes[&(0, index)], &proof, tree.hasher.as_ref()) => {
+            // A path the circuit would reject: better an error than a failed proof later
+            eprintln!("[ASP] ❌ Proof for index {} doesn't lead to the root {}", state.redaction.redact(Sensitive::LeafIndex, index), proof.root);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Generated path does not verify against the tree root",
+                "index": index,
+            }))).into_response()
+        },
+        Some(mut proof) => {
+            if let Some(signer) = &state.signer {
+                signer.sign_proof(&mut proof);
+            }
+            println!("[ASP] ✅ Proof generated successfully for index {}", state.redaction.redact(Sensitive::LeafIndex, index));
             println!("[ASP]    Root: {}", proof.root);
             println!("[ASP]    Path length: {}", proof.path.len());
-            println!("[ASP]    Leaf: {}", proof.leaf);
+            println!("[ASP]    Leaf: {}", state.redaction.redact(Sensitive::Commitment, &proof.leaf));
             println!("[ASP] 📤 Sending proof response to client...");
             println!("[ASP]    Response data: root={}, leaf={}, path_len={}, path_indices_len={}", 
-                proof.root, proof.leaf, proof.path.len(), proof.path_indices.len());
+                proof.root, state.redaction.redact(Sensitive::Commitment, &proof.leaf), proof.path.len(), proof.path_indices.len());
             let response = Json(proof).into_response();
             println!("[ASP] ✅ Proof response sent successfully (status 200)");
             response
         },
         None => {
-            println!("[ASP] ❌ Proof generation failed - leaf not found at index {}", index);
+            println!("[ASP] ❌ Proof generation failed - leaf not found at index {}", state.redaction.redact(Sensitive::LeafIndex, index));
             println!("[ASP]    Tree has {} leaves (indices 0-{})", leaf_count, leaf_count.saturating_sub(1));
             (StatusCode::NOT_FOUND, Json(serde_json::json!({
                 "error": "Leaf not found at index",
@@ -217,12 +565,355 @@ async fn get_deposit_proof(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/deposit/root",
+    tag = "deposit",
+    responses(
+        (status = 200, description = "Current deposit tree root", body = String, headers(("x-asp-leaf-count" = u32, description = "Leaves under the root, with ASP_SIGNING_KEY"), ("x-asp-signature" = String, description = "Operator signature of the root (ResponseSignature JSON), with ASP_SIGNING_KEY"))),
+    )
+)]
 async fn get_deposit_root(State(state): State<AppState>) -> impl IntoResponse {
     let tree = state.deposit_tree.lock().unwrap();
-    let root = tree.get_root();
-    Json(format!("0x{:x}", root))
+    signed_root(&state, format!("0x{:x}", tree.get_root()), tree.get_leaf_count())
+}
+
+/// A bare root response; with a signer configured the signature (and the leaf count it
+/// covers) travel in `X-ASP-Leaf-Count` / `X-ASP-Signature`, so the body stays the string v1
+/// clients parse
+fn signed_root(state: &AppState, root: String, leaf_count: u32) -> axum::response::Response {
+    let signature = state.signer.as_ref().and_then(|signer| {
+        signer
+            .sign(response_signing::Statement::Root { root: &root, leaf_count })
+            .map_err(|e| eprintln!("[ASP] ⚠️  Failed to sign root {}: {}", root, e))
+            .ok()
+    });
+    let mut response = Json(root).into_response();
+    if let Some(signature) = signature {
+        let headers = response.headers_mut();
+        headers.insert("x-asp-leaf-count", leaf_count.into());
+        let signature = serde_json::to_string(&signature).expect("signature serializes");
+        headers.insert("x-asp-signature", signature.parse().expect("signature is a valid header value"));
+    }
+    response
+}
+
+/// Syncer progress: whether the deposit tree (and proofs built from it) is at the chain tip
+#[utoipa::path(
+    get,
+    path = "/deposit/sync",
+    tag = "sync",
+    responses(
+        (status = 200, description = "Syncer progress and ETA", body = api_version::SyncStatusV1),
+    )
+)]
+async fn get_sync_status(State(state): State<AppState>) -> Json<api_version::SyncStatusV1> {
+    Json(state.sync_status.status().into())
+}
+
+/// Whether `root` is a root the local tree went through (with its block and leaf count) and
+/// one the contract accepts
+#[utoipa::path(
+    get,
+    path = "/roots/{root}/known",
+    tag = "deposit",
+    params(("root" = String, Path, description = "Merkle root, hex")),
+    responses(
+        (status = 200, description = "Local record of the root and whether the contract knows it", body = serde_json::Value),
+        (status = 400, description = "Invalid root"),
+    )
+)]
+async fn get_root_known(
+    Path(root): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let root = match starknet::core::types::FieldElement::from_hex_be(&root) {
+        Ok(felt) => format!("0x{:x}", felt),
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid root format: {}", e)).into_response(),
+    };
+    let local = state.syncer.roots.get(&root);
+    match state.blockchain.is_root_known(&root).await {
+        Ok(known) => Json(serde_json::json!({
+            "root": root,
+            "known": known,
+            "local": local,
+        })).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, format!("Failed to check root: {}", e)).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct FeedQuery {
+    /// Comma separated topics (deposit, root, nullifier_spent); all of them if unset
+    topics: Option<String>,
+    /// Only the deposit of this commitment, for a wallet waiting on its own note
+    commitment: Option<String>,
+}
+
+/// Notifications a feed client asked for
+#[derive(Clone)]
+struct FeedFilter {
+    topics: Vec<Topic>,
+    commitment: Option<String>,
+}
+
+impl FeedFilter {
+    fn from_query(query: &FeedQuery) -> Result<Self, axum::response::Response> {
+        let topics: Vec<Topic> = match &query.topics {
+            Some(names) => match names.split(',').map(Topic::parse).collect::<Option<_>>() {
+                Some(topics) => topics,
+                None => return Err((StatusCode::BAD_REQUEST, "Unknown topic (deposit, root, nullifier_spent)").into_response()),
+            },
+            None => vec![Topic::Deposit, Topic::Root, Topic::NullifierSpent],
+        };
+        let commitment = match query.commitment.as_deref().map(starknet::core::types::FieldElement::from_hex_be) {
+            Some(Ok(felt)) => Some(format!("0x{:x}", felt)),
+            Some(Err(e)) => return Err((StatusCode::BAD_REQUEST, format!("Invalid commitment format: {}", e)).into_response()),
+            None => None,
+        };
+        Ok(Self { topics, commitment })
+    }
+
+    fn wants(&self, notification: &SyncNotification) -> bool {
+        self.topics.contains(&notification.topic())
+            && match (notification, &self.commitment) {
+                (SyncNotification::Deposit { commitment: deposited, .. }, Some(commitment)) => deposited == commitment,
+                _ => true,
+            }
+    }
+}
+
+/// WebSocket pushing sync notifications as JSON text messages, e.g.
+/// `/ws?topics=deposit&commitment=0x...` to wait for a commitment's inclusion
+async fn sync_feed_socket(
+    ws: WebSocketUpgrade,
+    Query(query): Query<FeedQuery>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let filter = match FeedFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(rejection) => return rejection,
+    };
+    let notifications = state.syncer.feed.subscribe();
+    ws.on_upgrade(move |socket| stream_notifications(socket, notifications, filter))
+}
+
+async fn stream_notifications(
+    mut socket: WebSocket,
+    mut notifications: tokio::sync::broadcast::Receiver<SyncNotification>,
+    filter: FeedFilter,
+) {
+    loop {
+        tokio::select! {
+            notification = notifications.recv() => {
+                let message = match notification {
+                    Ok(notification) => {
+                        if !filter.wants(&notification) {
+                            continue;
+                        }
+                        serde_json::to_string(&notification).expect("notifications serialize")
+                    }
+                    // Too slow to keep up: say how much was missed, the client can catch up over REST
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                        serde_json::json!({ "type": "lagged", "missed": missed }).to_string()
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                };
+                if socket.send(Message::Text(message)).await.is_err() {
+                    return;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Server-sent events with the same notifications as `/ws`, for clients behind proxies that
+/// break WebSockets; each carries its sequence number as the event id, so a reconnecting
+/// EventSource resumes after its `Last-Event-ID` from the notifications the feed still keeps
+async fn sync_feed_events(
+    Query(query): Query<FeedQuery>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures_util::stream::{self, StreamExt};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let filter = match FeedFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(rejection) => return rejection,
+    };
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    let resumed = state.syncer.feed.resume(last_id);
+
+    let notification_event = |id: u64, notification: &SyncNotification| {
+        Event::default().id(id.to_string()).data(serde_json::to_string(notification).expect("notifications serialize"))
+    };
+    let mut replay = Vec::new();
+    if resumed.gap {
+        // Some were dropped since Last-Event-ID: the client catches up over REST
+        replay.push(Event::default().data(serde_json::json!({ "type": "lagged" }).to_string()));
+    }
+    replay.extend(resumed.missed.iter().filter(|(_, notification)| filter.wants(notification)).map(|(id, notification)| notification_event(*id, notification)));
+
+    let live = stream::unfold((resumed.receiver, resumed.next_id, filter), move |(mut receiver, mut next_id, filter)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(notification) => {
+                    let id = next_id;
+                    next_id += 1;
+                    if !filter.wants(&notification) {
+                        continue;
+                    }
+                    notification_event(id, &notification)
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    next_id += missed;
+                    Event::default().data(serde_json::json!({ "type": "lagged", "missed": missed }).to_string())
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((event, (receiver, next_id, filter)));
+        }
+    });
+    let events = stream::iter(replay).chain(live).map(Ok::<_, std::convert::Infallible>);
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
 }
 
+// ==================== Per-Contract Endpoints ====================
+
+fn unknown_contract(address: &str) -> axum::response::Response {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({
+        "error": "Contract is not synced by this ASP",
+        "address": address,
+    }))).into_response()
+}
+
+/// Every synced contract with its deposit root and sync progress
+#[utoipa::path(
+    get,
+    path = "/contracts",
+    tag = "contracts",
+    responses(
+        (status = 200, description = "Synced contracts with their roots and sync status", body = serde_json::Value),
+    )
+)]
+async fn list_contracts(State(state): State<AppState>) -> impl IntoResponse {
+    let contracts: Vec<_> = state
+        .contracts
+        .iter()
+        .map(|contract| {
+            let (root, leaf_count) = {
+                let tree = contract.deposit_tree.lock().unwrap();
+                (format!("0x{:x}", tree.get_root()), tree.get_leaf_count())
+            };
+            serde_json::json!({
+                "address": format!("0x{:064x}", contract.address),
+                "primary": Arc::ptr_eq(contract, state.contracts.primary()),
+                "root": root,
+                "leaf_count": leaf_count,
+                "sync": api_version::SyncStatusV1::from(contract.sync_status.status()),
+            })
+        })
+        .collect();
+    Json(contracts)
+}
+
+#[utoipa::path(
+    get,
+    path = "/contracts/{address}/deposit/root",
+    tag = "contracts",
+    params(("address" = String, Path, description = "Contract address")),
+    responses(
+        (status = 200, description = "The contract's deposit tree root", body = String, headers(("x-asp-leaf-count" = u32, description = "Leaves under the root, with ASP_SIGNING_KEY"), ("x-asp-signature" = String, description = "Operator signature of the root (ResponseSignature JSON), with ASP_SIGNING_KEY"))),
+        (status = 404, description = "Contract not synced by this ASP"),
+    )
+)]
+async fn get_contract_deposit_root(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let Some(contract) = state.contracts.get(&address) else {
+        return unknown_contract(&address);
+    };
+    let tree = contract.deposit_tree.lock().unwrap();
+    signed_root(&state, format!("0x{:x}", tree.get_root()), tree.get_leaf_count())
+}
+
+#[utoipa::path(
+    get,
+    path = "/contracts/{address}/deposit/proof/{index}",
+    tag = "contracts",
+    params(("address" = String, Path, description = "Contract address"), ("index" = u32, Path, description = "Leaf index")),
+    responses(
+        (status = 200, description = "Merkle proof of the contract's leaf", body = MerkleProof),
+        (status = 404, description = "Unknown contract or leaf"),
+    )
+)]
+async fn get_contract_deposit_proof(
+    Path((address, index)): Path<(String, u32)>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    println!("[ASP] 📥 GET /contracts/{}/deposit/proof/{}", address, index);
+    let Some(contract) = state.contracts.get(&address) else {
+        return unknown_contract(&address);
+    };
+    let tree = contract.deposit_tree.lock().unwrap();
+    match tree.get_proof(index) {
+        Some(mut proof) => {
+            if let Some(signer) = &state.signer {
+                signer.sign_proof(&mut proof);
+            }
+            Json(proof).into_response()
+        }
+        None => {
+            let leaf_count = tree.get_leaf_count();
+            println!("[ASP] ❌ Leaf not found at index {} ({} leaves)", state.redaction.redact(Sensitive::LeafIndex, index), leaf_count);
+            (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                "error": "Leaf not found at index",
+                "index": index,
+                "tree_leaf_count": leaf_count,
+                "valid_indices": if leaf_count > 0 { format!("0-{}", leaf_count - 1) } else { "none".to_string() }
+            }))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/contracts/{address}/deposit/sync",
+    tag = "contracts",
+    params(("address" = String, Path, description = "Contract address")),
+    responses(
+        (status = 200, description = "The contract's syncer progress", body = api_version::SyncStatusV1),
+        (status = 404, description = "Contract not synced by this ASP"),
+    )
+)]
+async fn get_contract_sync_status(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match state.contracts.get(&address) {
+        Some(contract) => Json(api_version::SyncStatusV1::from(contract.sync_status.status())).into_response(),
+        None => unknown_contract(&address),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/deposit/info",
+    tag = "deposit",
+    responses(
+        (status = 200, description = "Deposit tree root, leaf count and depth", body = TreeInfo),
+    )
+)]
 async fn get_deposit_info(State(state): State<AppState>) -> impl IntoResponse {
     println!("[ASP] 📥 GET /deposit/info");
     let tree = state.deposit_tree.lock().unwrap();
@@ -233,65 +924,326 @@ async fn get_deposit_info(State(state): State<AppState>) -> impl IntoResponse {
         println!("[ASP] 📊 Tree status: {} leaves", leaf_count);
         for i in 0..leaf_count.min(5) {
             if let Some(leaf) = tree.nodes.get(&(0, i)) {
-                println!("  [{}]: 0x{:x}", i, leaf);
+                println!("  [{}]: {}", i, state.redaction.redact(Sensitive::Commitment, format_args!("0x{:x}", leaf)));
             }
         }
     } else {
         println!("⚠️  Tree is empty - no deposits synced yet");
     }
     
+    let root = format!("0x{:x}", tree.get_root());
+    let signature = state.signer.as_ref().and_then(|signer| {
+        signer
+            .sign(response_signing::Statement::Root { root: &root, leaf_count })
+            .map_err(|e| eprintln!("[ASP] ⚠️  Failed to sign root {}: {}", root, e))
+            .ok()
+    });
     Json(TreeInfo {
-        root: format!("0x{:x}", tree.get_root()),
+        root,
         leaf_count,
         depth: tree.depth,
+        signature,
     })
 }
 
-/// Force re-sync from a specific block
-/// This will reset the syncer state and start syncing from the specified block
-/// Body: { "from_block": 4438440 } (optional, defaults to contract deployment block)
-async fn force_resync(
-    Json(payload): Json<serde_json::Value>,
+/// Compare the tree at the sync checkpoint with the contract's root at the same block
+/// Returns the divergence report; `divergences` is empty when both agree
+#[utoipa::path(
+    get,
+    path = "/deposit/consistency",
+    tag = "sync",
+    responses(
+        (status = 200, description = "Divergences between the synced tree and the on-chain root", body = serde_json::Value),
+    )
+)]
+async fn check_deposit_consistency(State(state): State<AppState>) -> impl IntoResponse {
+    println!("[ASP] 📥 GET /deposit/consistency");
+    match state.syncer.verify_consistency().await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, format!("Consistency check failed: {}", e)).into_response(),
+    }
+}
+
+/// Merkle path of a deposit by commitment: siblings, path indices, leaf index and the root
+/// they lead to, which clients check with is_root_known before proving
+#[utoipa::path(
+    get,
+    path = "/deposit/path/{commitment}",
+    tag = "deposit",
+    params(("commitment" = String, Path, description = "Deposit commitment, hex felt")),
+    responses(
+        (status = 200, description = "Merkle path and root of the commitment (also /commitment/{commitment}/path)", body = MerkleProof),
+        (status = 400, description = "Invalid commitment"),
+        (status = 404, description = "Commitment not in the deposit tree"),
+    )
+)]
+async fn get_deposit_path(
+    Path(commitment): Path<String>,
+    State(state): State<AppState>,
 ) -> impl IntoResponse {
-    use std::fs;
-    
-    println!("\n[ASP] ========================================");
-    println!("[ASP] 🔄 POST /deposit/resync - Force re-sync requested");
-    println!("[ASP] ========================================");
-    
-    let block_number = payload.get("from_block")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(4438440); // Default to contract deployment block
-    
-    println!("[ASP] 📋 Resetting sync state to block {}", block_number);
-    
-    let state = serde_json::json!({
-        "last_synced_block": block_number
-    });
-    
-    if let Ok(json) = serde_json::to_string(&state) {
-        if let Err(e) = fs::write("asp_state.json", json) {
-            println!("[ASP] ❌ Failed to write state: {}", e);
-            println!("[ASP] ========================================\n");
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write state: {}", e)).into_response();
+    let felt = match starknet::core::types::FieldElement::from_hex_be(&commitment) {
+        Ok(felt) => felt,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid commitment format: {}", e)).into_response(),
+    };
+    println!("[ASP] 📥 GET /deposit/path/{}", state.redaction.redact(Sensitive::Commitment, &commitment));
+
+    let tree = state.deposit_tree.lock().unwrap();
+    match commitment_proof(&state.syncer, &tree, &felt) {
+        Some(mut proof) => {
+            if let Some(signer) = &state.signer {
+                signer.sign_proof(&mut proof);
+            }
+            Json(proof).into_response()
         }
-    } else {
-        println!("[ASP] ❌ Failed to serialize state");
-        println!("[ASP] ========================================\n");
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to serialize state").into_response();
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Commitment not in the deposit tree",
+            "tree_leaf_count": tree.get_leaf_count(),
+        }))).into_response(),
     }
-    
-    println!("[ASP] ✅ State file updated successfully");
-    println!("[ASP] ⚠️  IMPORTANT: Restart the ASP server for changes to take effect");
-    println!("[ASP] ========================================\n");
-    
+}
+
+/// Path of `commitment` in `tree`: the syncer's index answers directly; the tree is searched
+/// for leaves added by lookups
+fn commitment_proof(syncer: &Syncer, tree: &MerkleTree, commitment: &starknet::core::types::FieldElement) -> Option<MerkleProof> {
+    syncer
+        .commitments
+        .get(commitment)
+        .and_then(|index| tree.get_proof(index))
+        .filter(|proof| proof.leaf == format!("0x{:x}", commitment))
+        .or_else(|| tree.get_proof_for_commitment(&num_bigint::BigUint::from_bytes_be(&commitment.to_bytes_be())))
+}
+
+/// Most commitments a `POST /paths` request may ask for
+const MAX_BATCH_PATHS: usize = 1000;
+
+#[derive(Deserialize, ToSchema)]
+struct BatchPathsRequest {
+    /// Deposit commitments, hex felts
+    commitments: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BatchPathsResponse {
+    /// Root every path leads to
+    root: String,
+    leaf_count: u32,
+    /// Path of each commitment found, in request order
+    paths: Vec<MerkleProof>,
+    /// Commitments not in the deposit tree
+    missing: Vec<String>,
+}
+
+/// Merkle paths of many commitments against one root, read under a single lock of the tree
+/// so no deposit lands between two of them; for wallets restoring many notes at once
+#[utoipa::path(
+    post,
+    path = "/paths",
+    tag = "deposit",
+    request_body = BatchPathsRequest,
+    responses(
+        (status = 200, description = "Paths of the commitments found, all against `root`", body = BatchPathsResponse),
+        (status = 400, description = "Invalid commitment, or more than 1000 of them"),
+    )
+)]
+async fn get_batch_paths(
+    State(state): State<AppState>,
+    Json(payload): Json<BatchPathsRequest>,
+) -> impl IntoResponse {
+    if payload.commitments.len() > MAX_BATCH_PATHS {
+        return (StatusCode::BAD_REQUEST, format!("At most {} commitments per request", MAX_BATCH_PATHS)).into_response();
+    }
+    let mut commitments = Vec::with_capacity(payload.commitments.len());
+    for commitment in &payload.commitments {
+        match starknet::core::types::FieldElement::from_hex_be(commitment) {
+            Ok(felt) => commitments.push(felt),
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid commitment {}: {}", commitment, e)).into_response(),
+        }
+    }
+    println!("[ASP] 📥 POST /paths ({} commitments)", commitments.len());
+
+    let tree = state.deposit_tree.lock().unwrap();
+    let mut response = BatchPathsResponse {
+        root: format!("0x{:x}", tree.get_root()),
+        leaf_count: tree.get_leaf_count(),
+        paths: Vec::with_capacity(commitments.len()),
+        missing: Vec::new(),
+    };
+    for commitment in &commitments {
+        match commitment_proof(&state.syncer, &tree, commitment) {
+            Some(proof) => response.paths.push(proof),
+            None => response.missing.push(format!("0x{:x}", commitment)),
+        }
+    }
+    drop(tree);
+    if let Some(signer) = &state.signer {
+        response.paths.iter_mut().for_each(|proof| signer.sign_proof(proof));
+    }
+    Json(response).into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RootHistoryQuery {
+    /// Records to return, newest first
+    limit: Option<usize>,
+    /// Blocks a recommended root must be behind the chain tip
+    confirmations: Option<u64>,
+}
+
+/// Roots the deposit tree went through, and the newest one both validated on-chain and
+/// `confirmations` blocks old, a safe root to build proofs against
+#[utoipa::path(
+    get,
+    path = "/deposit/roots",
+    tag = "deposit",
+    params(RootHistoryQuery),
+    responses(
+        (status = 200, description = "Recent roots and a validated root to prove against", body = serde_json::Value),
+    )
+)]
+async fn get_root_history(
+    Query(query): Query<RootHistoryQuery>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let roots = &state.syncer.roots;
+    let tip = state.sync_status.status().target_block;
+    let recommended = roots.latest_validated(tip.saturating_sub(query.confirmations.unwrap_or(10)));
     Json(serde_json::json!({
-        "success": true,
-        "message": format!("Re-sync will start from block {}", block_number),
-        "note": "Restart the ASP server for changes to take effect"
-    })).into_response()
+        "roots": roots.recent(query.limit.unwrap_or(50)),
+        "recommended": recommended,
+        "chain_tip": tip,
+    }))
 }
 
+/// Path of leaf `index` in the tree as it was at `root`, served from the published versions
+/// so it neither waits for the syncer nor changes as deposits keep coming
+#[utoipa::path(
+    get,
+    path = "/deposit/roots/{root}/proof/{index}",
+    tag = "deposit",
+    params(("root" = String, Path, description = "Recorded root, hex"), ("index" = u32, Path, description = "Leaf index")),
+    responses(
+        (status = 200, description = "Merkle proof against the recorded root", body = MerkleProof),
+        (status = 404, description = "Root not recorded, or leaf not in the tree at that root"),
+    )
+)]
+async fn get_deposit_proof_at_root(
+    Path((root, index)): Path<(String, u32)>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    use num_bigint::BigUint;
+    use num_traits::Num;
+
+    let root_value = match BigUint::from_str_radix(root.trim_start_matches("0x"), 16) {
+        Ok(root) => root,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid root format: {}", e)).into_response(),
+    };
+    match state.syncer.versions.proof_at(&root_value, index) {
+        Some(mut proof) => {
+            if let Some(signer) = &state.signer {
+                signer.sign_proof(&mut proof);
+            }
+            Json(proof).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Root not recorded, or leaf not in the tree at that root",
+            "root": root,
+            "index": index,
+        }))).into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DiffQuery {
+    /// Cursor: commitments after this leaf index (`next_after` of the previous page)
+    after: Option<u32>,
+    /// Commitments in the page (1000 by default, at most 10000)
+    limit: Option<usize>,
+}
+
+/// Commitments appended between two recorded roots, so a wallet holding a path for `from`
+/// can bring it up to `to` without downloading the tree
+#[utoipa::path(
+    get,
+    path = "/deposit/roots/{root}/diff/{to}",
+    tag = "deposit",
+    params(("root" = String, Path, description = "Older recorded root, hex"), ("to" = String, Path, description = "Newer recorded root, hex"), DiffQuery),
+    responses(
+        (status = 200, description = "Commitments appended between the two roots", body = tree_versions::TreeDiff),
+        (status = 404, description = "Roots not recorded"),
+    )
+)]
+async fn get_deposit_diff(
+    Path((from, to)): Path<(String, String)>,
+    Query(query): Query<DiffQuery>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    use num_bigint::BigUint;
+    use num_traits::Num;
+
+    let (from_root, to_root) = match (
+        BigUint::from_str_radix(from.trim_start_matches("0x"), 16),
+        BigUint::from_str_radix(to.trim_start_matches("0x"), 16),
+    ) {
+        (Ok(from_root), Ok(to_root)) => (from_root, to_root),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::BAD_REQUEST, format!("Invalid root format: {}", e)).into_response(),
+    };
+    let limit = query.limit.unwrap_or(1000).clamp(1, 10_000);
+    match state.syncer.versions.diff(&from_root, &to_root, query.after, limit) {
+        Some(diff) => Json(diff).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Roots not recorded, or `from` is newer than `to`",
+            "from": from,
+            "to": to,
+        }))).into_response(),
+    }
+}
+
+/// Leaf index, block and transaction of a commitment from the syncer's index, without any
+/// RPC call; a miss means it wasn't deposited up to `synced_block`
+#[utoipa::path(
+    get,
+    path = "/deposit/contains/{commitment}",
+    tag = "deposit",
+    params(("commitment" = String, Path, description = "Deposit commitment, hex felt")),
+    responses(
+        (status = 200, description = "Leaf index, block and transaction of the commitment", body = serde_json::Value),
+        (status = 404, description = "Not deposited up to the synced block"),
+    )
+)]
+async fn get_deposit_leaf_info(
+    Path(commitment): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let commitment_felt = match starknet::core::types::FieldElement::from_hex_be(&commitment) {
+        Ok(commitment) => commitment,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid commitment format: {}", e)).into_response(),
+    };
+    let synced_block = state.syncer.commitments.synced_block();
+    match state.syncer.commitments.contains(&commitment_felt) {
+        Some(info) => Json(serde_json::json!({
+            "found": true,
+            "leaf": info,
+            "synced_block": synced_block,
+        })).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "found": false,
+            "synced_block": synced_block,
+        }))).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/deposit/index/{commitment}",
+    tag = "deposit",
+    params(("commitment" = String, Path, description = "Deposit commitment, hex felt")),
+    responses(
+        (status = 200, description = "Leaf index of the commitment", body = serde_json::Value),
+        (status = 404, description = "Commitment not found"),
+    )
+)]
 async fn get_deposit_index(
     Path(commitment): Path<String>,
     State(state): State<AppState>,
@@ -310,7 +1262,7 @@ async fn get_deposit_index(
     };
 
     println!("\n[ASP] ========================================");
-    println!("[ASP] 🔍 GET /deposit/index/{}", commitment_str.chars().take(20).collect::<String>());
+    println!("[ASP] 🔍 GET /deposit/index/{}", state.redaction.redact(Sensitive::Commitment, commitment_str.chars().take(20).collect::<String>()));
     println!("[ASP] ========================================");
     
     // First, check local tree (fast path)
@@ -326,11 +1278,12 @@ async fn get_deposit_index(
     if found_locally {
         let tree = state.deposit_tree.lock().unwrap();
         if let Some(index) = tree.find_commitment_index(&commitment_bigint) {
-            println!("[ASP] ✅ Found commitment in local tree at index {}", index);
+            println!("[ASP] ✅ Found commitment in local tree at index {}", state.redaction.redact(Sensitive::LeafIndex, index));
             println!("[ASP] ========================================\n");
             return Json(serde_json::json!({
                 "index": index,
                 "found": true,
+                "confirmed": true,
                 "source": "local_tree"
             })).into_response();
         }
@@ -339,9 +1292,20 @@ async fn get_deposit_index(
     // Not found locally - search in contract events directly (fast lookup)
     println!("[ASP] 🔍 Commitment not in local tree. Searching in contract events...");
     
-    match state.blockchain.find_commitment_in_events(&format!("0x{:x}", commitment_bigint)).await {
-        Ok(Some(index)) => {
-            println!("[ASP] ✅ Found commitment in events at index {}. Adding to local tree...", index);
+    match state.blockchain.find_commitment_labeled(&format!("0x{:x}", commitment_bigint)).await {
+        Ok(Some((index, Confirmation::Unconfirmed))) => {
+            // Not in an accepted block yet: report it, but keep it out of the tree
+            println!("[ASP] ⏳ Found commitment in the pending block at index {}", state.redaction.redact(Sensitive::LeafIndex, index));
+            println!("[ASP] ========================================\n");
+            Json(serde_json::json!({
+                "index": index,
+                "found": true,
+                "confirmed": false,
+                "source": "pending_block"
+            })).into_response()
+        },
+        Ok(Some((index, Confirmation::Confirmed))) => {
+            println!("[ASP] ✅ Found commitment in events at index {}. Adding to local tree...", state.redaction.redact(Sensitive::LeafIndex, index));
             
             // Add to local tree for future queries
             // Get zero_leaf first (before acquiring mutable lock)
@@ -358,21 +1322,25 @@ async fn get_deposit_index(
                 let gaps = index - current_count;
                 println!("   Filling {} gap(s) before index {}", gaps, index);
                 for i in 0..gaps {
-                    tree.insert_at_index(current_count + i, zero_leaf.clone());
+                    tree.insert_at_index(current_count + i, zero_leaf.clone()).expect("the zero leaf is a felt");
                 }
             }
             
             // Insert the commitment
-            if index == tree.get_leaf_count() {
-                tree.insert(commitment_bigint.clone());
+            let inserted = if index == tree.get_leaf_count() {
+                tree.insert(commitment_bigint.clone())
             } else {
-                tree.insert_at_index(index, commitment_bigint.clone());
+                tree.insert_at_index(index, commitment_bigint.clone())
+            };
+            if let Err(e) = inserted {
+                return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
             }
             
             println!("[ASP] ========================================\n");
             Json(serde_json::json!({
                 "index": index,
                 "found": true,
+                "confirmed": true,
                 "source": "contract_events"
             })).into_response()
         },
@@ -397,30 +1365,162 @@ async fn get_deposit_index(
     }
 }
 
-/// List all deposits in the tree with their indices
-async fn list_deposits(State(state): State<AppState>) -> impl IntoResponse {
-    let tree = state.deposit_tree.lock().unwrap();
-    let leaf_count = tree.get_leaf_count();
-    
-    let mut deposits = Vec::new();
-    for i in 0..leaf_count {
-        if let Some(leaf) = tree.nodes.get(&(0, i)) {
-            deposits.push(serde_json::json!({
-                "index": i,
-                "commitment": format!("0x{:x}", leaf),
-                "commitment_hex_no_prefix": format!("{:x}", leaf)
-            }));
+/// Leaf index of the deposit made by a transaction (read from its receipt)
+#[utoipa::path(
+    get,
+    path = "/deposit/tx/{tx_hash}",
+    tag = "deposit",
+    params(("tx_hash" = String, Path, description = "Deposit transaction hash")),
+    responses(
+        (status = 200, description = "Leaf index of the transaction's deposit", body = serde_json::Value),
+        (status = 404, description = "No deposit in this transaction"),
+    )
+)]
+async fn get_deposit_index_for_tx(
+    Path(tx_hash): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match state.blockchain.deposit_index_for_tx(&tx_hash).await {
+        Ok(index) => {
+            println!("[ASP] ✅ Deposit tx {} is at index {}", tx_hash, state.redaction.redact(Sensitive::LeafIndex, index));
+            Json(serde_json::json!({
+                "index": index,
+                "found": true,
+                "source": "tx_receipt"
+            })).into_response()
+        }
+        Err(e @ error::BlockchainError::InvalidInput(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        Err(e @ (error::BlockchainError::TransactionNotFound(_)
+            | error::BlockchainError::TransactionPending(_)
+            | error::BlockchainError::NoDepositEvent(_))) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e) => {
+            eprintln!("[ASP] ❌ Error reading deposit tx receipt: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read tx receipt: {}", e)).into_response()
         }
     }
-    
-    Json(serde_json::json!({
-        "count": leaf_count,
-        "deposits": deposits
-    })).into_response()
+}
+
+/// Export the deposit set discovered from contract events as a JSON snapshot
+#[utoipa::path(
+    get,
+    path = "/deposit/snapshot",
+    tag = "sync",
+    responses(
+        (status = 200, description = "JSON snapshot of the deposit set from contract events", body = serde_json::Value),
+    )
+)]
+async fn get_deposit_snapshot(State(state): State<AppState>) -> impl IntoResponse {
+    match state.blockchain.export_snapshot().await {
+        Ok(snapshot) => Json(snapshot).into_response(),
+        Err(e) => {
+            eprintln!("[ASP] ❌ Error exporting snapshot: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to export snapshot: {}", e)).into_response()
+        }
+    }
+}
+
+/// Binary snapshot of the synced deposit tree (leaves, frontier, root, block), for
+/// another instance to bootstrap from
+#[utoipa::path(
+    get,
+    path = "/deposit/snapshot.bin",
+    tag = "sync",
+    responses(
+        (status = 200, description = "Binary snapshot of the synced deposit tree", content_type = "application/octet-stream"),
+    )
+)]
+async fn get_deposit_snapshot_binary(State(state): State<AppState>) -> impl IntoResponse {
+    ([(axum::http::header::CONTENT_TYPE, "application/octet-stream")], state.syncer.snapshot_bytes())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DepositListQuery {
+    /// Cursor: leaves after this index (`next_after` of the previous page)
+    after: Option<u32>,
+    /// First leaf index of the page, an alternative to `after`
+    from: Option<u32>,
+    /// Cursor: leaves deposited after this block
+    after_block: Option<u64>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    tx_hash: Option<String>,
+    commitment: Option<String>,
+    /// Leaves in the page (1000 by default, at most 10000)
+    limit: Option<usize>,
+}
+
+/// A page of the saved deposits with their block and transaction, in leaf order, narrowed by
+/// block range, transaction or commitment
+#[utoipa::path(
+    get,
+    path = "/deposit/list",
+    tag = "deposit",
+    params(DepositListQuery),
+    responses(
+        (status = 200, description = "A page of saved deposits and the cursor of the next one", body = serde_json::Value),
+        (status = 400, description = "Invalid filter"),
+    )
+)]
+async fn list_deposits(
+    Query(query): Query<DepositListQuery>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let parse = |name: &str, value: &Option<String>| {
+        value
+            .as_deref()
+            .map(|value| starknet::core::types::FieldElement::from_hex_be(value).map_err(|e| format!("Invalid {}: {}", name, e)))
+            .transpose()
+    };
+    let (transaction_hash, commitment) = match (parse("tx_hash", &query.tx_hash), parse("commitment", &query.commitment)) {
+        (Ok(transaction_hash), Ok(commitment)) => (transaction_hash, commitment),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let filter = LeafFilter {
+        after: query.after.or(query.from.and_then(|from| from.checked_sub(1))),
+        after_block: query.after_block,
+        from_block: query.from_block,
+        to_block: query.to_block,
+        transaction_hash,
+        commitment,
+        commitment_prefix: None,
+    };
+    let Some(store) = state.syncer.checkpoints.clone() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "No sync state store".to_string()).into_response();
+    };
+    let leaf_count = state.deposit_tree.lock().unwrap().get_leaf_count();
+    let limit = query.limit.unwrap_or(1000).clamp(1, 10_000);
+    match tokio::task::spawn_blocking(move || leaf_query::query(store.as_ref(), leaf_count, &filter, limit)).await {
+        Ok(Ok(page)) => Json(serde_json::json!({
+            "deposits": page.leaves.iter().map(|leaf| serde_json::json!({
+                "index": leaf.index,
+                "commitment": format!("0x{:x}", leaf.commitment),
+                "block_number": leaf.origin.map(|origin| origin.block_number),
+                "transaction_hash": leaf.origin.map(|origin| format!("0x{:x}", origin.transaction_hash)),
+            })).collect::<Vec<_>>(),
+            "next_after": page.next_after,
+        })).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read deposits: {}", e)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read deposits: {}", e)).into_response(),
+    }
 }
 
 // ==================== Associated Set Endpoints ====================
 
+#[utoipa::path(
+    get,
+    path = "/associated/proof/{index}",
+    tag = "associated",
+    params(("index" = u32, Path, description = "Leaf index")),
+    responses(
+        (status = 200, description = "Merkle proof in the associated set", body = MerkleProof),
+        (status = 404, description = "No leaf at this index"),
+    )
+)]
 async fn get_associated_proof(
     Path(index): Path<u32>,
     State(state): State<AppState>,
@@ -433,23 +1533,50 @@ async fn get_associated_proof(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/associated/root",
+    tag = "associated",
+    responses(
+        (status = 200, description = "Associated set root", body = String),
+    )
+)]
 async fn get_associated_root(State(state): State<AppState>) -> impl IntoResponse {
     let tree = state.associated_tree.lock().unwrap();
     let root = tree.get_root();
     Json(format!("0x{:x}", root))
 }
 
+#[utoipa::path(
+    get,
+    path = "/associated/info",
+    tag = "associated",
+    responses(
+        (status = 200, description = "Associated set root, leaf count and depth", body = TreeInfo),
+    )
+)]
 async fn get_associated_info(State(state): State<AppState>) -> impl IntoResponse {
     let tree = state.associated_tree.lock().unwrap();
     Json(TreeInfo {
         root: format!("0x{:x}", tree.get_root()),
         leaf_count: tree.get_leaf_count(),
         depth: tree.depth,
+        signature: None,
     })
 }
 
 /// Insert a commitment into the associated set tree
 /// This is used by operators to build compliance sets
+#[utoipa::path(
+    post,
+    path = "/associated/insert",
+    tag = "associated",
+    request_body = InsertRequest,
+    responses(
+        (status = 200, description = "Leaf index of the inserted commitment", body = serde_json::Value),
+        (status = 400, description = "Invalid commitment"),
+    )
+)]
 async fn insert_associated(
     State(state): State<AppState>,
     Json(payload): Json<InsertRequest>,
@@ -465,9 +1592,11 @@ async fn insert_associated(
             return (StatusCode::BAD_REQUEST, "Invalid commitment format").into_response()
         }
     };
-
     let mut tree = state.associated_tree.lock().unwrap();
-    let new_root = tree.insert(commitment);
+    let new_root = match tree.insert(commitment) {
+        Ok(root) => root,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
     let leaf_index = tree.get_leaf_count() - 1;
 
     Json(serde_json::json!({
@@ -480,9 +1609,21 @@ async fn insert_associated(
 
 // ==================== Blockchain Read Endpoints ====================
 
+#[utoipa::path(
+    get,
+    path = "/api/pool/root",
+    tag = "pool",
+    responses(
+        (status = 200, description = "On-chain Merkle root", body = serde_json::Value),
+    )
+)]
 async fn get_pool_root(State(state): State<AppState>) -> impl IntoResponse {
-    match state.blockchain.get_merkle_root().await {
-        Ok(root) => Json(serde_json::json!({ "root": root })).into_response(),
+    match state.blockchain.get_merkle_root_labeled().await {
+        Ok((root, confirmation)) => Json(serde_json::json!({
+            "root": root,
+            "confirmed": confirmation == Confirmation::Confirmed,
+        }))
+        .into_response(),
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get merkle root: {}", e))
                 .into_response()
@@ -490,6 +1631,14 @@ async fn get_pool_root(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/pool/initialized",
+    tag = "pool",
+    responses(
+        (status = 200, description = "Whether the pool is initialized", body = serde_json::Value),
+    )
+)]
 async fn check_pool_initialized(State(state): State<AppState>) -> impl IntoResponse {
     match state.blockchain.is_pool_initialized().await {
         Ok(initialized) => Json(serde_json::json!({ "initialized": initialized })).into_response(),
@@ -500,6 +1649,14 @@ async fn check_pool_initialized(State(state): State<AppState>) -> impl IntoRespo
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/pool/info",
+    tag = "pool",
+    responses(
+        (status = 200, description = "Pool state", body = serde_json::Value),
+    )
+)]
 async fn get_pool_info(State(state): State<AppState>) -> impl IntoResponse {
     // First check if pool is initialized
     let is_initialized = match state.blockchain.is_pool_initialized().await {
@@ -537,12 +1694,46 @@ async fn get_pool_info(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/pool/reserves",
+    tag = "pool",
+    responses(
+        (status = 200, description = "Pool reserves and liquidity", body = serde_json::Value),
+    )
+)]
+async fn get_pool_reserves(State(state): State<AppState>) -> impl IntoResponse {
+    match state.blockchain.get_pool_reserves().await {
+        Ok(reserves) => Json(reserves).into_response(),
+        Err(e @ error::BlockchainError::PoolNotInitialized) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get pool reserves: {}", e))
+                .into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/nullifier/{nullifier}",
+    tag = "nullifier",
+    params(("nullifier" = String, Path, description = "Nullifier, hex felt")),
+    responses(
+        (status = 200, description = "Whether the contract has the nullifier spent (also /api/nullifier/{nullifier})", body = serde_json::Value),
+        (status = 400, description = "Invalid nullifier"),
+    )
+)]
 async fn check_nullifier(
     Path(nullifier): Path<String>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
     match state.blockchain.is_nullifier_spent(&nullifier).await {
         Ok(spent) => Json(serde_json::json!({ "spent": spent })).into_response(),
+        Err(e @ error::BlockchainError::InvalidInput(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to check nullifier: {}", e))
                 .into_response()
@@ -550,6 +1741,59 @@ async fn check_nullifier(
     }
 }
 
+/// Root of the spent nullifier tree, as synced from NullifierSpent events
+#[utoipa::path(
+    get,
+    path = "/nullifier/root",
+    tag = "nullifier",
+    responses(
+        (status = 200, description = "Root of the spent nullifier tree", body = serde_json::Value),
+    )
+)]
+async fn get_nullifier_root(State(state): State<AppState>) -> impl IntoResponse {
+    let tree = state.syncer.nullifier_tree.lock().unwrap();
+    Json(serde_json::json!({
+        "root": format!("0x{:x}", tree.root()),
+        "spent_count": tree.spent_count(),
+        "depth": nullifier_tree::NULLIFIER_TREE_DEPTH,
+    }))
+}
+
+/// Non-membership proof of a nullifier in the spent nullifier tree
+/// 409 if the nullifier is spent (as far as the syncer has seen)
+#[utoipa::path(
+    get,
+    path = "/nullifier/proof/{nullifier}",
+    tag = "nullifier",
+    params(("nullifier" = String, Path, description = "Nullifier, hex felt")),
+    responses(
+        (status = 200, description = "Non-membership proof", body = serde_json::Value),
+        (status = 409, description = "Nullifier already spent"),
+    )
+)]
+async fn get_nullifier_non_membership_proof(
+    Path(nullifier): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let nullifier = match starknet::core::types::FieldElement::from_hex_be(&nullifier) {
+        Ok(nullifier) => nullifier,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid nullifier: {}", e)).into_response(),
+    };
+    match state.syncer.nullifier_tree.lock().unwrap().prove_non_membership(&nullifier) {
+        Some(proof) => Json(proof).into_response(),
+        None => (StatusCode::CONFLICT, "Nullifier already spent".to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/token/{address}/balance/{owner}",
+    tag = "token",
+    params(("address" = String, Path, description = "Token address"), ("owner" = String, Path, description = "Owner address")),
+    responses(
+        (status = 200, description = "Token balance", body = serde_json::Value),
+    )
+)]
 async fn get_token_balance(
     Path((token_address, owner)): Path<(String, String)>,
     State(state): State<AppState>,
@@ -559,6 +1803,9 @@ async fn get_token_balance(
             "low": low.to_string(),
             "high": high.to_string()
         })).into_response(),
+        Err(e @ error::BlockchainError::InvalidInput(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get token balance: {}", e))
                 .into_response()
@@ -566,6 +1813,15 @@ async fn get_token_balance(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/token/{address}/allowance/{owner}/{spender}",
+    tag = "token",
+    params(("address" = String, Path, description = "Token address"), ("owner" = String, Path, description = "Owner address"), ("spender" = String, Path, description = "Spender address")),
+    responses(
+        (status = 200, description = "Token allowance", body = serde_json::Value),
+    )
+)]
 async fn get_token_allowance(
     Path((token_address, owner, spender)): Path<(String, String, String)>,
     State(state): State<AppState>,
@@ -575,6 +1831,9 @@ async fn get_token_allowance(
             "low": low.to_string(),
             "high": high.to_string()
         })).into_response(),
+        Err(e @ error::BlockchainError::InvalidInput(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get token allowance: {}", e))
                 .into_response()
@@ -584,34 +1843,43 @@ async fn get_token_allowance(
 
 // ==================== Transaction Preparation Endpoints ====================
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct PrepareDepositRequest {
     amount: String,
     token_address: String,
     user_address: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct PreparedTransaction {
     contract_address: String,
     entry_point: String,
     calldata: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct DepositPrepareResponse {
     transactions: Vec<PreparedTransaction>,
     commitment: String,
     note_data: NoteData,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct NoteData {
     secret: String,
     nullifier: String,
     amount: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/deposit/prepare",
+    tag = "prepare",
+    request_body = PrepareDepositRequest,
+    responses(
+        (status = 200, description = "Transactions to sign and the new note", body = DepositPrepareResponse),
+    )
+)]
 async fn prepare_deposit(
     State(state): State<AppState>,
     Json(payload): Json<PrepareDepositRequest>,
@@ -690,7 +1958,7 @@ async fn prepare_deposit(
     .into_response()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct PrepareSwapRequest {
     // Input note data (user must provide this)
     secret: String,
@@ -707,13 +1975,22 @@ struct PrepareSwapRequest {
     new_amount: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct SwapPrepareResponse {
     merkle_proof: MerkleProof,
     new_commitment: String,
     output_note_data: NoteData,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/swap/prepare",
+    tag = "prepare",
+    request_body = PrepareSwapRequest,
+    responses(
+        (status = 200, description = "Merkle proof of the input note and the output note", body = SwapPrepareResponse),
+    )
+)]
 async fn prepare_swap(
     state: State<AppState>,
     payload: Json<PrepareSwapRequest>,
@@ -798,7 +2075,178 @@ async fn prepare_swap(
     .into_response()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+struct MembershipWitnessRequest {
+    /// Note secret, hex
+    secret: String,
+    /// Note nullifier, hex
+    nullifier: String,
+    /// Note amount, decimal
+    amount: String,
+}
+
+/// Inputs of the membership circuit for a note, with its current Merkle path, checked the way
+/// the circuit checks them; ready for the witness calculator without a snarkjs input step
+#[utoipa::path(
+    post,
+    path = "/api/witness/membership",
+    tag = "proof",
+    request_body = MembershipWitnessRequest,
+    responses(
+        (status = 200, description = "Circuit input, public inputs and the intermediate signals", body = serde_json::Value),
+        (status = 400, description = "Invalid secret, nullifier or amount"),
+        (status = 404, description = "The note's commitment is not in the deposit tree"),
+        (status = 422, description = "The path doesn't satisfy the circuit"),
+    )
+)]
+async fn membership_witness_endpoint(
+    State(state): State<AppState>,
+    Json(payload): Json<MembershipWitnessRequest>,
+) -> impl IntoResponse {
+    let (witness, proof) = match note_membership_witness(&state, &payload, "/api/witness/membership") {
+        Ok(built) => built,
+        Err(rejection) => return rejection,
+    };
+    Json(serde_json::json!({
+        "input": witness.input_json(),
+        "public_inputs": witness.public_inputs().iter().map(|value| value.to_str_radix(10)).collect::<Vec<_>>(),
+        "leaf_index": proof.leaf_index,
+        "signals": {
+            "inner_hash": witness.inner_hash.to_str_radix(10),
+            "level_hashes": witness.level_hashes.iter().map(|value| value.to_str_radix(10)).collect::<Vec<_>>(),
+        },
+    })).into_response()
+}
+
+/// Membership witness of the note in `payload` along its current path, or the response
+/// explaining why there is none
+fn note_membership_witness(
+    state: &AppState,
+    payload: &MembershipWitnessRequest,
+    route: &str,
+) -> Result<(witness::MembershipWitness, MerkleProof), axum::response::Response> {
+    let amount = payload.amount.trim().parse::<u128>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid amount: {}", e)).into_response())?;
+    let opening = witness::NoteOpening::from_hex(&payload.secret, &payload.nullifier, amount)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let commitment = starknet::core::types::FieldElement::from_byte_slice_be(&opening.commitment().to_bytes_be())
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Commitment out of range: {}", e)).into_response())?;
+    println!("[ASP] 📥 POST {} ({})", route, state.redaction.redact(Sensitive::Commitment, format_args!("0x{:x}", commitment)));
+
+    let proof = {
+        let tree = state.deposit_tree.lock().unwrap();
+        commitment_proof(&state.syncer, &tree, &commitment)
+    };
+    let Some(proof) = proof else {
+        return Err((StatusCode::NOT_FOUND, "The note's commitment is not in the deposit tree").into_response());
+    };
+    let witness = witness::membership_witness(&opening, &proof)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e).into_response())?;
+    Ok((witness, proof))
+}
+
+/// Groth16 proof that a note is in the deposit tree, generated in process from its current
+/// path; snarkjs proof.json and public signals, for membership verifiers and tests
+#[cfg(feature = "prover")]
+#[utoipa::path(
+    post,
+    path = "/api/proof/membership",
+    tag = "proof",
+    request_body = MembershipWitnessRequest,
+    responses(
+        (status = 200, description = "snarkjs proof and public signals (root, commitment)", body = serde_json::Value),
+        (status = 400, description = "Invalid secret, nullifier or amount"),
+        (status = 404, description = "The note's commitment is not in the deposit tree"),
+        (status = 422, description = "The path doesn't satisfy the circuit"),
+        (status = 500, description = "Proof generation failed"),
+    )
+)]
+async fn membership_proof_endpoint(
+    State(state): State<AppState>,
+    Json(payload): Json<MembershipWitnessRequest>,
+) -> impl IntoResponse {
+    let (witness, proof) = match note_membership_witness(&state, &payload, "/api/proof/membership") {
+        Ok(built) => built,
+        Err(rejection) => return rejection,
+    };
+    let files = match state.artifacts.circuit("membership").await {
+        Ok(files) => files,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    let start = std::time::Instant::now();
+    let proving = tokio::task::spawn_blocking(move || {
+        let membership = prover::prove_membership(&files, &witness)?;
+        verifier::verify_circuit_proof(&files.vkey, "membership", &membership.proof)?;
+        Ok::<_, String>(membership)
+    });
+    match proving.await {
+        Ok(Ok(membership)) => {
+            println!("[ASP] ✅ Membership proof generated in {:.2}s", start.elapsed().as_secs_f64());
+            let (snarkjs_proof, public_signals) = membership.proof.to_snarkjs();
+            Json(serde_json::json!({
+                "proof": snarkjs_proof,
+                "public_signals": public_signals,
+                "root": format!("0x{:x}", membership.public.root),
+                "commitment": format!("0x{:x}", membership.public.commitment),
+                "leaf_index": proof.leaf_index,
+            })).into_response()
+        }
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Proof generation failed: {}", e)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Prover task failed: {}", e)).into_response(),
+    }
+}
+
+/// Circuits with a verification key under circuits/build/vkeys
+#[cfg(feature = "prover")]
+const VERIFIABLE_CIRCUITS: [&str; 4] = ["membership", "withdraw", "swap", "lp"];
+
+#[cfg(feature = "prover")]
+#[derive(Deserialize, ToSchema)]
+struct VerifyProofRequest {
+    /// membership, withdraw, swap or lp
+    circuit: String,
+    /// snarkjs proof.json
+    proof: serde_json::Value,
+    /// snarkjs public.json, decimal strings
+    public_signals: serde_json::Value,
+}
+
+/// Check a Groth16 proof against the circuit's verification key (the one its Garaga verifier
+/// was generated from) before paying for an on-chain verify call
+#[cfg(feature = "prover")]
+#[utoipa::path(
+    post,
+    path = "/api/proof/verify",
+    tag = "proof",
+    request_body = VerifyProofRequest,
+    responses(
+        (status = 200, description = "`valid`, and the reason in `error` when it isn't", body = serde_json::Value),
+        (status = 400, description = "Unknown circuit or malformed proof"),
+        (status = 500, description = "The circuit's verification key can't be loaded"),
+    )
+)]
+async fn verify_proof_endpoint(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyProofRequest>,
+) -> impl IntoResponse {
+    if !VERIFIABLE_CIRCUITS.contains(&payload.circuit.as_str()) {
+        return (StatusCode::BAD_REQUEST, format!("Unknown circuit {}; expected one of {:?}", payload.circuit, VERIFIABLE_CIRCUITS)).into_response();
+    }
+    let proof = match prover::Groth16Proof::from_snarkjs(&payload.proof, &payload.public_signals) {
+        Ok(proof) => proof,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let verifier = match state.artifacts.verification_key(&payload.circuit).await.and_then(|vkey| verifier::CircuitVerifier::load(&vkey)) {
+        Ok(verifier) => verifier,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    match verifier.verify(&proof) {
+        Ok(()) => Json(serde_json::json!({ "valid": true })).into_response(),
+        Err(e) => Json(serde_json::json!({ "valid": false, "error": e })).into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
 struct SwapProofRequest {
     // Public inputs
     nullifier: String,
@@ -826,6 +2274,15 @@ struct SwapProofRequest {
     // Removed note_index fallback - frontend must call prepareSwap first
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/proof/swap",
+    tag = "proof",
+    request_body = SwapProofRequest,
+    responses(
+        (status = 200, description = "Swap proof and its calldata", body = serde_json::Value),
+    )
+)]
 async fn generate_swap_proof_endpoint(
     state: State<AppState>,
     payload: Json<SwapProofRequest>,
@@ -985,16 +2442,6 @@ async fn generate_swap_proof_endpoint(
     println!("[ASP]    Amount specified: {}", amount_specified);
     println!("[ASP]    Zero for one: {}", payload.zero_for_one);
     
-    // Get circuits path (relative to ASP directory, go up to project root)
-    let circuits_path = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("circuits")
-        .to_str()
-        .unwrap()
-        .to_string();
-    
     // Build input JSON directly from request payload (frontend already formats it correctly)
     // Update root and pathElements/pathIndices if we fetched them
     let input_json = serde_json::json!({
@@ -1018,11 +2465,19 @@ async fn generate_swap_proof_endpoint(
         "liquidity": payload.liquidity,
     });
     
+    // Retries with unchanged inputs get the proof already generated against a still fresh root
+    let cache_key = proof_cache::ProofCache::key("swap", &input_json);
+    if let Some(cached) = state.proof_cache.get(&cache_key, |root| proof_cache::root_is_fresh(&state.syncer.roots, root)) {
+        println!("[ASP] ♻️  Returning the cached proof for these inputs");
+        println!("[ASP] ========================================\n");
+        return Json(cached).into_response();
+    }
+
     println!("[ASP] 🔧 Generating ZK proof...");
-    println!("[ASP]    Circuits path: {}", circuits_path);
+    println!("[ASP]    Circuits path: {}", state.artifacts.circuits_dir().display());
     
     // Generate proof - pass JSON directly to proof generator
-    match proof::generate_swap_proof(&circuits_path, input_json).await {
+    match proof::generate_swap_proof(&state.artifacts, state.prover_backend, input_json).await {
         Ok(swap_proof) => {
             let elapsed = start_time.elapsed().as_secs_f64();
             println!("[ASP] ✅ ZK proof generated successfully in {:.2}s", elapsed);
@@ -1041,10 +2496,12 @@ async fn generate_swap_proof_endpoint(
             
             println!("[ASP] ========================================\n");
             
-            Json(serde_json::json!({
+            let response = serde_json::json!({
                 "full_proof_with_hints": swap_proof.proof,
                 "public_inputs": swap_proof.public_inputs,
-            })).into_response()
+            });
+            state.proof_cache.insert(cache_key, root, response.clone());
+            Json(response).into_response()
         }
         Err(e) => {
             let elapsed = start_time.elapsed().as_secs_f64();
@@ -1057,7 +2514,7 @@ async fn generate_swap_proof_endpoint(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct LpProofRequest {
     // Public inputs
     nullifier: String,
@@ -1079,8 +2536,17 @@ struct LpProofRequest {
     path_indices: Vec<u32>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/proof/lp-mint",
+    tag = "proof",
+    request_body = LpProofRequest,
+    responses(
+        (status = 200, description = "LP mint proof and its calldata", body = serde_json::Value),
+    )
+)]
 async fn generate_lp_proof_endpoint(
-    _state: State<AppState>,
+    state: State<AppState>,
     payload: Json<LpProofRequest>,
 ) -> impl IntoResponse {
     println!("\n[ASP] ========================================");
@@ -1109,16 +2575,6 @@ async fn generate_lp_proof_endpoint(
     println!("[ASP]    Root: {}", root);
     println!("[ASP]    Path length: {}", merkle_path.len());
     
-    // Get circuits path (relative to ASP directory, go up to project root)
-    let circuits_path = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("circuits")
-        .to_str()
-        .unwrap()
-        .to_string();
-    
     // Build input JSON directly from request payload
     let input_json = serde_json::json!({
         "nullifier": payload.nullifier,
@@ -1137,11 +2593,19 @@ async fn generate_lp_proof_endpoint(
         "pathIndices": merkle_path_indices.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
     });
     
+    // Retries with unchanged inputs get the proof already generated against a still fresh root
+    let cache_key = proof_cache::ProofCache::key("lp", &input_json);
+    if let Some(cached) = state.proof_cache.get(&cache_key, |root| proof_cache::root_is_fresh(&state.syncer.roots, root)) {
+        println!("[ASP] ♻️  Returning the cached proof for these inputs");
+        println!("[ASP] ========================================\n");
+        return Json(cached).into_response();
+    }
+
     println!("[ASP] 🔧 Generating ZK proof with rapidsnark...");
-    println!("[ASP]    Circuits path: {}", circuits_path);
+    println!("[ASP]    Circuits path: {}", state.artifacts.circuits_dir().display());
     
     // Generate proof using rapidsnark
-    match proof::generate_lp_proof(&circuits_path, input_json).await {
+    match proof::generate_lp_proof(&state.artifacts, state.prover_backend, input_json).await {
         Ok(lp_proof) => {
             let elapsed = start_time.elapsed().as_secs_f64();
             println!("[ASP] ✅ ZK proof generated successfully in {:.2}s", elapsed);
@@ -1160,10 +2624,12 @@ async fn generate_lp_proof_endpoint(
             
             println!("[ASP] ========================================\n");
             
-            Json(serde_json::json!({
+            let response = serde_json::json!({
                 "full_proof_with_hints": lp_proof.proof,
                 "public_inputs": lp_proof.public_inputs,
-            })).into_response()
+            });
+            state.proof_cache.insert(cache_key, root, response.clone());
+            Json(response).into_response()
         }
         Err(e) => {
             let elapsed = start_time.elapsed().as_secs_f64();
@@ -1176,7 +2642,117 @@ async fn generate_lp_proof_endpoint(
     }
 }
 
-#[derive(Deserialize)]
+/// Body of a finished proof endpoint response: the JSON on success, the error text otherwise
+async fn proof_job_result(response: axum::response::Response) -> Result<serde_json::Value, String> {
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.map_err(|e| format!("Failed to read the proof response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("{}: {}", status, String::from_utf8_lossy(&body)));
+    }
+    serde_json::from_slice(&body).map_err(|e| format!("Invalid proof response: {}", e))
+}
+
+/// Queue the swap proof of /api/proof/swap and answer with the job at once
+#[utoipa::path(
+    post,
+    path = "/api/proof/jobs/swap",
+    tag = "proof",
+    request_body = SwapProofRequest,
+    responses(
+        (status = 202, description = "The queued job; poll /api/proof/jobs/{id} for its result", body = serde_json::Value),
+        (status = 429, description = "Too many proofs pending"),
+    )
+)]
+async fn submit_swap_proof_job(
+    State(state): State<AppState>,
+    Json(payload): Json<SwapProofRequest>,
+) -> impl IntoResponse {
+    let jobs = state.proof_jobs.clone();
+    let work = async move { proof_job_result(generate_swap_proof_endpoint(State(state), Json(payload)).await.into_response()).await };
+    match jobs.submit("swap", work) {
+        Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Err(e) => (StatusCode::TOO_MANY_REQUESTS, e).into_response(),
+    }
+}
+
+/// Queue the LP proof of /api/proof/lp-mint and answer with the job at once
+#[utoipa::path(
+    post,
+    path = "/api/proof/jobs/lp-mint",
+    tag = "proof",
+    request_body = LpProofRequest,
+    responses(
+        (status = 202, description = "The queued job; poll /api/proof/jobs/{id} for its result", body = serde_json::Value),
+        (status = 429, description = "Too many proofs pending"),
+    )
+)]
+async fn submit_lp_proof_job(
+    State(state): State<AppState>,
+    Json(payload): Json<LpProofRequest>,
+) -> impl IntoResponse {
+    let jobs = state.proof_jobs.clone();
+    let work = async move { proof_job_result(generate_lp_proof_endpoint(State(state), Json(payload)).await.into_response()).await };
+    match jobs.submit("lp", work) {
+        Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Err(e) => (StatusCode::TOO_MANY_REQUESTS, e).into_response(),
+    }
+}
+
+/// A proof job: `queued`, `running`, `done` with the proof in `result`, or `failed` with `error`
+#[utoipa::path(
+    get,
+    path = "/api/proof/jobs/{id}",
+    tag = "proof",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "The job and its status", body = serde_json::Value),
+        (status = 404, description = "Unknown or expired job"),
+    )
+)]
+async fn get_proof_job(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match state.proof_jobs.get(&id) {
+        Some(job) => Json(job).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No proof job {}", id)).into_response(),
+    }
+}
+
+/// Server-sent events with the job's state on each change, closed once it finishes
+#[utoipa::path(
+    get,
+    path = "/api/proof/jobs/{id}/events",
+    tag = "proof",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "text/event-stream of the job as it changes"),
+        (status = 404, description = "Unknown or expired job"),
+    )
+)]
+async fn proof_job_events(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures_util::stream;
+
+    let Some(receiver) = state.proof_jobs.subscribe(&id) else {
+        return (StatusCode::NOT_FOUND, format!("No proof job {}", id)).into_response();
+    };
+    // The current state first, then one event per change; the stream ends after the final one
+    let events = stream::unfold((receiver, false, false), |(mut receiver, started, finished)| async move {
+        if finished || (started && receiver.changed().await.is_err()) {
+            return None;
+        }
+        let job = receiver.borrow_and_update().clone();
+        let event = Event::default().data(serde_json::to_string(&job).expect("jobs serialize"));
+        Some((Ok::<_, std::convert::Infallible>(event), (receiver, true, job.status.is_finished())))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
 struct PrepareWithdrawRequest {
     // Input note data (user must provide this)
     secret: String,
@@ -1188,6 +2764,15 @@ struct PrepareWithdrawRequest {
     token_address: Option<String>, // Optional, will use note's token if not provided
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/withdraw/prepare",
+    tag = "prepare",
+    request_body = PrepareWithdrawRequest,
+    responses(
+        (status = 501, description = "Not implemented yet"),
+    )
+)]
 async fn prepare_withdraw(
     _state: State<AppState>,
     _payload: Json<PrepareWithdrawRequest>,
@@ -1196,7 +2781,7 @@ async fn prepare_withdraw(
     (StatusCode::NOT_IMPLEMENTED, "ZK proof generation not yet implemented")
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct PrepareLiquidityRequest {
     // Input note data
     secret: String,
@@ -1213,6 +2798,15 @@ struct PrepareLiquidityRequest {
     new_amount: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/liquidity/mint/prepare",
+    tag = "prepare",
+    request_body = PrepareLiquidityRequest,
+    responses(
+        (status = 501, description = "Not implemented yet"),
+    )
+)]
 async fn prepare_mint_liquidity(
     _state: State<AppState>,
     _payload: Json<PrepareLiquidityRequest>,
@@ -1221,6 +2815,15 @@ async fn prepare_mint_liquidity(
     (StatusCode::NOT_IMPLEMENTED, "ZK proof generation not yet implemented")
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/liquidity/burn/prepare",
+    tag = "prepare",
+    request_body = PrepareLiquidityRequest,
+    responses(
+        (status = 501, description = "Not implemented yet"),
+    )
+)]
 async fn prepare_burn_liquidity(
     _state: State<AppState>,
     _payload: Json<PrepareLiquidityRequest>,
@@ -1230,7 +2833,7 @@ async fn prepare_burn_liquidity(
 }
 
 /// Request to prepare initialize transaction
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct PrepareInitializeRequest {
     token0: Option<String>,
     token1: Option<String>,
@@ -1240,6 +2843,15 @@ struct PrepareInitializeRequest {
 }
 
 /// Prepare initialize transaction
+#[utoipa::path(
+    post,
+    path = "/api/initialize/prepare",
+    tag = "prepare",
+    request_body = PrepareInitializeRequest,
+    responses(
+        (status = 200, description = "Initialize transaction to sign", body = serde_json::Value),
+    )
+)]
 #[axum::debug_handler]
 async fn prepare_initialize(
     State(state): State<AppState>,
@@ -1333,9 +2945,79 @@ fn u256_to_low_high_bigint(value: &BigUint) -> (u128, u128) {
 
 // ==================== Health Check ====================
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "ops",
+    responses(
+        (status = 200, description = "Server is up", body = serde_json::Value),
+    )
+)]
 async fn health_check() -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "ok",
         "version": "0.1.0"
     }))
 }
+
+/// Liveness: the process serves requests
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "ops",
+    responses(
+        (status = 200, description = "Server is up", body = serde_json::Value),
+    )
+)]
+async fn liveness() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness: 503 unless every syncer is running and at most `ready_max_blocks_behind` blocks
+/// behind the tip its RPC provider reports now (so an unreachable provider fails it too)
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "ops",
+    responses(
+        (status = 200, description = "Every syncer is near the chain tip", body = serde_json::Value),
+        (status = 503, description = "A syncer lags, is down, or the RPC is unreachable", body = serde_json::Value),
+    )
+)]
+async fn readiness(State(state): State<AppState>) -> impl IntoResponse {
+    let mut ready = true;
+    let mut contracts = Vec::new();
+    for contract in state.contracts.iter() {
+        let status = contract.sync_status.status();
+        let (chain_tip, reason) = match contract.blockchain.latest_block_number().await {
+            Ok(tip) => (Some(tip), status.unready_reason(tip, state.ready_max_blocks_behind)),
+            Err(e) => (None, Some(format!("RPC provider unreachable: {}", e))),
+        };
+        ready &= reason.is_none();
+        contracts.push(serde_json::json!({
+            "address": format!("0x{:064x}", contract.address),
+            "current_block": status.current_block,
+            "chain_tip": chain_tip,
+            "ready": reason.is_none(),
+            "reason": reason,
+        }));
+    }
+    let code = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(serde_json::json!({ "ready": ready, "contracts": contracts })))
+}
+
+/// Per-method RPC metrics in the Prometheus text format
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "ops",
+    responses(
+        (status = 200, description = "RPC metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+async fn rpc_metrics() -> impl IntoResponse {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::MetricsRegistry::global().render_prometheus(),
+    )
+}
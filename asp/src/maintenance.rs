@@ -0,0 +1,98 @@
+// Store maintenance
+// Databases backing the sync state fragment as leaves are rewritten and event data is
+// pruned: SQLite keeps freed pages until a VACUUM, RocksDB keeps tombstones until its levels
+// are compacted, PostgreSQL keeps dead rows until vacuumed. A background task runs
+// StateStore::maintain every MAINTENANCE_INTERVAL_SECS, only within MAINTENANCE_HOURS (UTC)
+// so it stays out of the way of proof traffic.
+
+use crate::state_store::StateStore;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// When maintenance may run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    /// Time between two runs
+    pub interval: Duration,
+    /// UTC hours `[start, end)` a run may start in; wraps past midnight when start > end
+    pub hours: Option<(u32, u32)>,
+}
+
+impl MaintenanceConfig {
+    /// MAINTENANCE_INTERVAL_SECS (maintenance is off without it) and MAINTENANCE_HOURS
+    /// (`2-5`: from 02:00 to 05:00 UTC; any hour if unset)
+    pub fn from_env() -> Option<Self> {
+        let interval = std::env::var("MAINTENANCE_INTERVAL_SECS").ok()?.parse().ok().filter(|secs| *secs > 0)?;
+        let hours = std::env::var("MAINTENANCE_HOURS").ok().and_then(|hours| {
+            let parsed = parse_hours(&hours);
+            if parsed.is_none() {
+                eprintln!("⚠️  Invalid MAINTENANCE_HOURS {:?} (expected e.g. 2-5), running at any hour", hours);
+            }
+            parsed
+        });
+        Some(Self {
+            interval: Duration::from_secs(interval),
+            hours,
+        })
+    }
+
+    /// Whether a run may start at `hour` (UTC)
+    pub fn allows(&self, hour: u32) -> bool {
+        match self.hours {
+            None => true,
+            Some((start, end)) if start <= end => (start..end).contains(&hour),
+            Some((start, end)) => hour >= start || hour < end,
+        }
+    }
+}
+
+/// `start-end`, both hours of the day
+fn parse_hours(hours: &str) -> Option<(u32, u32)> {
+    let (start, end) = hours.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start < 24 && end <= 24 && start != end).then_some((start, end))
+}
+
+fn utc_hour() -> u32 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    (secs % 86_400 / 3_600) as u32
+}
+
+/// Run `store` maintenance every `config.interval`, waiting for the allowed hours
+pub fn spawn_maintenance(store: Arc<dyn StateStore>, config: MaintenanceConfig) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(config.interval).await;
+            while !config.allows(utc_hour()) {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            let store = store.clone();
+            let started = std::time::Instant::now();
+            match tokio::task::spawn_blocking(move || store.maintain()).await {
+                Ok(Ok(())) => println!("[ASP] 🧹 Store maintenance done in {:?}", started.elapsed()),
+                Ok(Err(e)) => eprintln!("[ASP] ⚠️  Store maintenance failed: {}", e),
+                Err(e) => eprintln!("[ASP] ⚠️  Store maintenance panicked: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_hours() {
+        assert_eq!(parse_hours("2-5"), Some((2, 5)));
+        assert_eq!(parse_hours("22 - 4"), Some((22, 4)));
+        assert_eq!(parse_hours("5"), None);
+        assert_eq!(parse_hours("3-25"), None);
+
+        let night = MaintenanceConfig { interval: Duration::from_secs(3600), hours: Some((22, 4)) };
+        assert!(night.allows(23) && night.allows(0) && night.allows(3));
+        assert!(!night.allows(4) && !night.allows(12));
+        let early = MaintenanceConfig { hours: Some((2, 5)), ..night };
+        assert!(early.allows(2) && !early.allows(5) && !early.allows(1));
+        assert!(MaintenanceConfig { hours: None, ..night }.allows(12));
+    }
+}
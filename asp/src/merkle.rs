@@ -1,10 +1,15 @@
+use crate::hasher::{DomainSeparatedHasher, Hasher, PedersenHasher, PoseidonHasher};
 use ark_bn254::Fr;
 use ark_ff::{BigInteger, PrimeField};
 use light_poseidon::{Poseidon, PoseidonHasher};
 use num_bigint::BigUint;
 use num_traits::Num;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use starknet::core::types::FieldElement;
 use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
 
 /// Merkle Tree Depth (matches Cairo contract)
 /// Contract uses depth 25
@@ -14,12 +19,167 @@ pub const TREE_DEPTH: usize = 25;
 /// 0x3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff (250 bits)
 const MASK: &str = "3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Membership path of a leaf: what a prover needs for the membership circuit
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct MerkleProof {
     pub leaf: String,
+    /// Position of the leaf in the tree
+    #[serde(default)]
+    pub leaf_index: u32,
+    /// Sibling of each level, from the leaf up
     pub path: Vec<String>,
+    /// 0 where the path node is a left child, 1 where it is a right child
     pub path_indices: Vec<u32>,
+    /// Root the path leads to, to check against `is_root_known` before proving
     pub root: String,
+    /// Operator's signature of the path, when the ASP signs its responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<crate::response_signing::ResponseSignature>,
+}
+
+/// A leaf the tree can't take: nodes are hashed as felt252, and this value isn't one
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Merkle leaf 0x{0:x} is not a felt252")]
+pub struct InvalidLeaf(pub BigUint);
+
+/// Serializable dump of the deposit tree, as discovered from Deposit events
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TreeSnapshot {
+    pub depth: usize,
+    /// Leaves in index order
+    #[serde(with = "crate::conv::felt_hex_vec")]
+    pub leaves: Vec<FieldElement>,
+    pub root: String,
+    /// Block up to which events were scanned
+    pub synced_block: u64,
+}
+
+/// Poseidon BN254 (circom) masked to 250 bits, the node hash of the Zylith contract
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bn254PoseidonHasher;
+
+static MASK_VALUE: Lazy<BigUint> = Lazy::new(|| BigUint::from_str_radix(MASK, 16).unwrap());
+
+impl Hasher for Bn254PoseidonHasher {
+    fn hash_two(&self, a: FieldElement, b: FieldElement) -> FieldElement {
+        let result = MerkleTree::hash_and_mask(&[felt_to_biguint(a), felt_to_biguint(b)], &MASK_VALUE);
+        biguint_to_felt(&result)
+    }
+}
+
+/// Node hash of the deposit tree; must be the one the deployed contract hashes with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeHash {
+    /// Poseidon over BN254 masked to 250 bits (current Zylith contract)
+    #[default]
+    Bn254Poseidon,
+    /// Starknet Poseidon over the Stark field
+    Poseidon,
+    /// Starknet Pedersen
+    Pedersen,
+}
+
+impl TreeHash {
+    /// `bn254_poseidon`, `poseidon` or `pedersen`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bn254_poseidon" | "bn254" => Some(TreeHash::Bn254Poseidon),
+            "poseidon" => Some(TreeHash::Poseidon),
+            "pedersen" => Some(TreeHash::Pedersen),
+            _ => None,
+        }
+    }
+
+    /// Hash selected by MERKLE_HASH, the contract's BN254 Poseidon by default
+    pub fn from_env() -> Self {
+        match std::env::var("MERKLE_HASH") {
+            Ok(name) => Self::parse(&name).unwrap_or_else(|| {
+                eprintln!("⚠️  Unknown MERKLE_HASH {:?}, using bn254_poseidon", name);
+                TreeHash::default()
+            }),
+            Err(_) => TreeHash::default(),
+        }
+    }
+
+    pub fn hasher(self) -> Arc<dyn Hasher> {
+        match self {
+            TreeHash::Bn254Poseidon => Arc::new(Bn254PoseidonHasher),
+            TreeHash::Poseidon => Arc::new(PoseidonHasher),
+            TreeHash::Pedersen => Arc::new(PedersenHasher),
+        }
+    }
+}
+
+/// Shape and hashing of the deposit tree for one deployment, so the ASP can follow contracts
+/// built for other circuit versions; checked against the contract at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeConfig {
+    pub depth: usize,
+    pub hash: TreeHash,
+    /// Domain separation constant mixed into every node hash, if the circuit uses one
+    pub domain: Option<FieldElement>,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            depth: TREE_DEPTH,
+            hash: TreeHash::default(),
+            domain: None,
+        }
+    }
+}
+
+impl TreeConfig {
+    /// MERKLE_DEPTH, MERKLE_HASH and MERKLE_DOMAIN, the current contract's tree by default
+    pub fn from_env() -> Self {
+        let depth = match std::env::var("MERKLE_DEPTH") {
+            Ok(value) => match value.trim().parse::<usize>() {
+                Ok(depth) if (1..=32).contains(&depth) => depth,
+                _ => {
+                    eprintln!("⚠️  Invalid MERKLE_DEPTH {:?} (1-32), using {}", value, TREE_DEPTH);
+                    TREE_DEPTH
+                }
+            },
+            Err(_) => TREE_DEPTH,
+        };
+        let domain = std::env::var("MERKLE_DOMAIN").ok().and_then(|value| match FieldElement::from_hex_be(value.trim()) {
+            Ok(domain) => Some(domain),
+            Err(_) => {
+                eprintln!("⚠️  Invalid MERKLE_DOMAIN {:?}, hashing without a domain", value);
+                None
+            }
+        });
+        Self {
+            depth,
+            hash: TreeHash::from_env(),
+            domain,
+        }
+    }
+
+    pub fn hasher(&self) -> Arc<dyn Hasher> {
+        match self.domain {
+            Some(domain) => Arc::new(DomainSeparatedHasher {
+                inner: self.hash.hasher(),
+                domain,
+            }),
+            None => self.hash.hasher(),
+        }
+    }
+
+    pub fn empty_tree(&self) -> MerkleTree {
+        MerkleTree::with_hasher(self.depth, self.hasher())
+    }
+
+    pub fn empty_root(&self) -> FieldElement {
+        biguint_to_felt(&self.empty_tree().get_root())
+    }
+
+    /// Root of the empty tree with a single leaf, what the contract emits with its first deposit
+    pub fn root_after_first_leaf(&self, leaf: &FieldElement) -> FieldElement {
+        let root = self.empty_tree().preview_append(felt_to_biguint(*leaf)).expect("felts are valid leaves");
+        biguint_to_felt(&root)
+    }
 }
 
 /// Merkle Tree with proper intermediate node storage for correct proof generation
@@ -33,12 +193,26 @@ pub struct MerkleTree {
     pub zeros: Vec<BigUint>,
     /// Current root (updated on each insert)
     pub current_root: BigUint,
+    /// Filled subtrees: for each level, the latest left node on the append frontier
+    /// Lets `append` compute a new root without reading the node map, like the contract does
+    pub filled_subtrees: Vec<BigUint>,
     /// Mask for BN254 -> felt252 conversion
     pub mask: BigUint,
+    /// Node hash function (Bn254PoseidonHasher to match the contract)
+    pub hasher: Arc<dyn Hasher>,
+    /// Nodes written since the last `take_changed`, when tracking is on (for the node file)
+    changed: Option<Vec<(usize, u32)>>,
+    /// Leaves before this index aren't stored (tree resumed from a frontier): no proofs for them
+    first_leaf: u32,
 }
 
 impl MerkleTree {
     pub fn new(depth: usize) -> Self {
+        Self::with_hasher(depth, Arc::new(Bn254PoseidonHasher))
+    }
+
+    /// Create an empty tree hashing nodes with `hasher`
+    pub fn with_hasher(depth: usize, hasher: Arc<dyn Hasher>) -> Self {
         let mask = BigUint::from_str_radix(MASK, 16).unwrap();
         
         // CRITICAL: Cairo contract uses 0 for empty nodes, not recursive hash
@@ -54,27 +228,210 @@ impl MerkleTree {
             nodes: HashMap::new(),
             zeros,
             current_root: initial_root,
+            filled_subtrees: vec![BigUint::from(0u8); depth],
             mask,
+            hasher,
+            changed: None,
+            first_leaf: 0,
+        }
+    }
+
+    /// Build a tree from a full list of leaves, hashing level by level
+    /// This is a full O(n) rebuild; use `append` to add leaves to a live tree
+    pub fn from_leaves(depth: usize, leaves: &[BigUint]) -> Self {
+        Self::from_leaves_with_hasher(depth, leaves, Arc::new(Bn254PoseidonHasher))
+    }
+
+    /// `from_leaves` hashing nodes with `hasher`
+    pub fn from_leaves_with_hasher(depth: usize, leaves: &[BigUint], hasher: Arc<dyn Hasher>) -> Self {
+        let mut tree = Self::with_hasher(depth, hasher);
+        if leaves.is_empty() {
+            return tree;
+        }
+
+        let mut level_nodes: Vec<BigUint> = leaves.to_vec();
+        for (index, leaf) in level_nodes.iter().enumerate() {
+            tree.nodes.insert((0, index as u32), leaf.clone());
+        }
+
+        for level in 0..depth {
+            // Missing right siblings are 0 (matching Cairo contract)
+            let parents: Vec<BigUint> = level_nodes
+                .chunks(2)
+                .map(|pair| {
+                    let left = pair[0].clone();
+                    let right = pair.get(1).cloned().unwrap_or_else(|| BigUint::from(0u8));
+                    tree.hash_pair(left, right)
+                })
+                .collect();
+            for (index, node) in parents.iter().enumerate() {
+                tree.nodes.insert((level + 1, index as u32), node.clone());
+            }
+            level_nodes = parents;
+        }
+
+        tree.next_index = leaves.len() as u32;
+        tree.current_root = level_nodes[0].clone();
+        tree.refresh_filled_subtrees();
+        tree
+    }
+
+    /// Tree made of already hashed `nodes` (e.g. read back from a node file), without rehashing
+    pub fn from_nodes(depth: usize, hasher: Arc<dyn Hasher>, nodes: HashMap<(usize, u32), BigUint>) -> Self {
+        let mut tree = Self::with_hasher(depth, hasher);
+        tree.next_index = nodes.keys().filter(|(level, _)| *level == 0).map(|(_, index)| index + 1).max().unwrap_or(0);
+        tree.current_root = nodes.get(&(depth, 0)).cloned().unwrap_or_else(|| BigUint::from(0u8));
+        tree.nodes = nodes;
+        tree.refresh_filled_subtrees();
+        tree
+    }
+
+    /// Tree resuming appends after `next_index` leaves from the append frontier alone, like the
+    /// contract's incremental tree: O(depth) to build, no leaf needs loading
+    /// Roots and proofs of leaves appended from there on are exact; earlier leaves have no proof.
+    pub fn from_frontier(depth: usize, hasher: Arc<dyn Hasher>, next_index: u32, frontier: &[BigUint], root: BigUint) -> Self {
+        let mut tree = Self::with_hasher(depth, hasher);
+        tree.next_index = next_index;
+        tree.first_leaf = next_index;
+        tree.current_root = root;
+        tree.filled_subtrees = frontier.to_vec();
+        // Frontier nodes left of the next index are the siblings later paths need
+        for level in 0..depth {
+            let index = next_index >> level;
+            if index % 2 == 1 {
+                tree.nodes.insert((level, index - 1), frontier[level].clone());
+            }
+        }
+        tree
+    }
+
+    /// Start recording the nodes written by inserts, to persist only what changed
+    pub fn track_changes(&mut self) {
+        self.changed.get_or_insert_with(Vec::new);
+    }
+
+    pub fn is_tracking_changes(&self) -> bool {
+        self.changed.is_some()
+    }
+
+    /// Nodes written since the last call (in write order, possibly repeated); tracking stays on
+    pub fn take_changed(&mut self) -> Vec<(usize, u32)> {
+        self.changed.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Store a node, recording it if changes are tracked
+    fn set_node(&mut self, level: usize, index: u32, value: BigUint) {
+        if let Some(changed) = &mut self.changed {
+            changed.push((level, index));
         }
+        self.nodes.insert((level, index), value);
     }
 
     /// Insert a leaf at the next available index and update the tree, returning the new root
-    pub fn insert(&mut self, leaf: BigUint) -> BigUint {
+    pub fn insert(&mut self, leaf: BigUint) -> Result<BigUint, InvalidLeaf> {
+        self.append(leaf)
+    }
+
+    /// Append a leaf at the next available index, returning the new root
+    /// Only the O(depth) nodes on the new leaf's path are recomputed: left siblings come
+    /// from the filled-subtree cache and right siblings of a freshly appended leaf are always 0
+    pub fn append(&mut self, leaf: BigUint) -> Result<BigUint, InvalidLeaf> {
+        check_leaf(&leaf)?;
         let index = self.next_index;
         self.next_index += 1;
-        self.insert_at_index(index, leaf)
+        self.set_node(0, index, leaf.clone());
+
+        let mut current_hash = leaf;
+        let mut current_idx = index;
+
+        for level in 0..self.depth {
+            let (left, right) = if current_idx % 2 == 0 {
+                // Left child: becomes the frontier node for this level
+                self.filled_subtrees[level] = current_hash.clone();
+                (current_hash, BigUint::from(0u8))
+            } else {
+                (self.filled_subtrees[level].clone(), current_hash)
+            };
+
+            current_hash = self.hash_pair(left, right);
+            current_idx /= 2;
+            self.set_node(level + 1, current_idx, current_hash.clone());
+        }
+
+        self.current_root = current_hash.clone();
+        Ok(current_hash)
+    }
+
+    /// Append `leaves` at the next available indices, returning the new root
+    /// Every internal node above the new leaves is hashed once, so n leaves cost about
+    /// n + depth hashes instead of the n * depth of repeated `append`s. Nothing is inserted
+    /// if any of them is invalid.
+    pub fn insert_batch(&mut self, leaves: &[BigUint]) -> Result<BigUint, InvalidLeaf> {
+        leaves.iter().try_for_each(check_leaf)?;
+        if leaves.is_empty() {
+            return Ok(self.current_root.clone());
+        }
+        let start = self.next_index;
+        for (offset, leaf) in leaves.iter().enumerate() {
+            self.set_node(0, start + offset as u32, leaf.clone());
+        }
+        self.next_index = start + leaves.len() as u32;
+
+        // Parents of the new leaves form a contiguous range at every level
+        let (mut first, mut last) = (start, self.next_index - 1);
+        for level in 0..self.depth {
+            first /= 2;
+            last /= 2;
+            for index in first..=last {
+                let left = self.node_or_zero(level, 2 * index);
+                let right = self.node_or_zero(level, 2 * index + 1);
+                let parent = self.hash_pair(left, right);
+                self.set_node(level + 1, index, parent);
+            }
+        }
+
+        self.current_root = self.node_or_zero(self.depth, 0);
+        self.refresh_filled_subtrees();
+        Ok(self.current_root.clone())
+    }
+
+    /// Stored node, 0 if missing (matching Cairo contract)
+    fn node_or_zero(&self, level: usize, index: u32) -> BigUint {
+        self.nodes.get(&(level, index)).cloned().unwrap_or_else(|| BigUint::from(0u8))
+    }
+
+    /// Root the tree would have after `append(leaf)`, leaving the tree untouched
+    /// Walks the same path as `append` against the filled-subtree frontier, so it is O(depth)
+    pub fn preview_append(&self, leaf: BigUint) -> Result<BigUint, InvalidLeaf> {
+        check_leaf(&leaf)?;
+        let mut current_hash = leaf;
+        let mut current_idx = self.next_index;
+
+        for level in 0..self.depth {
+            let (left, right) = if current_idx % 2 == 0 {
+                (current_hash, BigUint::from(0u8))
+            } else {
+                (self.filled_subtrees[level].clone(), current_hash)
+            };
+
+            current_hash = self.hash_pair(left, right);
+            current_idx /= 2;
+        }
+
+        Ok(current_hash)
     }
 
     /// Insert a leaf at a specific index and update the tree, returning the new root
     /// This is used when syncing events that may have gaps
-    pub fn insert_at_index(&mut self, index: u32, leaf: BigUint) -> BigUint {
+    pub fn insert_at_index(&mut self, index: u32, leaf: BigUint) -> Result<BigUint, InvalidLeaf> {
+        check_leaf(&leaf)?;
         // Update next_index if we're inserting beyond it
         if index >= self.next_index {
             self.next_index = index + 1;
         }
 
         // Store leaf at level 0
-        self.nodes.insert((0, index), leaf.clone());
+        self.set_node(0, index, leaf.clone());
 
         // Update path from leaf to root
         let mut current_hash = leaf;
@@ -105,20 +462,39 @@ impl MerkleTree {
             };
 
             // Compute parent hash
-            current_hash = Self::hash_and_mask(&[left, right], &self.mask);
+            current_hash = self.hash_pair(left, right);
 
             // Move to parent level
             let parent_idx = current_idx / 2;
-            self.nodes.insert((level + 1, parent_idx), current_hash.clone());
+            self.set_node(level + 1, parent_idx, current_hash.clone());
             current_idx = parent_idx;
         }
 
         self.current_root = current_hash.clone();
-        current_hash
+        // Out-of-order inserts can change frontier nodes; resync the cache from the node map
+        self.refresh_filled_subtrees();
+        Ok(current_hash)
+    }
+
+    /// Recompute the filled-subtree cache from stored nodes
+    /// `append` only reads filled_subtrees[level] when the next index is a right child
+    /// at that level, in which case it needs its left sibling
+    fn refresh_filled_subtrees(&mut self) {
+        for level in 0..self.depth {
+            let left_idx = (self.next_index >> level) & !1;
+            self.filled_subtrees[level] = self
+                .nodes
+                .get(&(level, left_idx))
+                .cloned()
+                .unwrap_or_else(|| BigUint::from(0u8));
+        }
     }
 
     /// Generate a Merkle proof for a leaf at the given index
     pub fn get_proof(&self, index: u32) -> Option<MerkleProof> {
+        if index < self.first_leaf {
+            return None;
+        }
         // Check if leaf exists
         let leaf = self.nodes.get(&(0, index))?;
 
@@ -153,22 +529,51 @@ impl MerkleTree {
 
         Some(MerkleProof {
             leaf: format!("0x{:x}", leaf),
+            leaf_index: index,
             path,
             path_indices,
             root: format!("0x{:x}", self.current_root),
+            signature: None,
         })
     }
 
+    /// Membership path of `commitment`, if it is a leaf of the tree
+    pub fn get_proof_for_commitment(&self, commitment: &BigUint) -> Option<MerkleProof> {
+        self.get_proof(self.find_commitment_index(commitment)?)
+    }
+
     /// Get the current root
     pub fn get_root(&self) -> BigUint {
         self.current_root.clone()
     }
 
+    /// Whether the root is the hash of its two children, i.e. the nodes were hashed with this
+    /// tree's hasher (cheap check of nodes loaded from disk)
+    pub fn root_matches_children(&self) -> bool {
+        if self.depth == 0 || self.next_index == 0 {
+            return true;
+        }
+        let child = |index| self.nodes.get(&(self.depth - 1, index)).cloned().unwrap_or_else(|| BigUint::from(0u8));
+        self.hash_pair(child(0), child(1)) == self.current_root
+    }
+
     /// Get number of leaves inserted
     pub fn get_leaf_count(&self) -> u32 {
         self.next_index
     }
 
+    /// Leaves in index order, zero for gaps never filled
+    pub fn leaves(&self) -> Vec<BigUint> {
+        self.leaves_from(0)
+    }
+
+    /// Leaves from index `from` on, like `leaves`
+    pub fn leaves_from(&self, from: u32) -> Vec<BigUint> {
+        (from..self.next_index)
+            .map(|index| self.nodes.get(&(0, index)).cloned().unwrap_or_else(|| self.zeros[0].clone()))
+            .collect()
+    }
+
     /// Find the index of a commitment in the tree
     /// Returns None if the commitment is not found
     pub fn find_commitment_index(&self, commitment: &BigUint) -> Option<u32> {
@@ -183,6 +588,13 @@ impl MerkleTree {
         None
     }
 
+    /// Hash two nodes (left, right) with the tree's hasher
+    /// Nodes are felt252 values: leaves are checked on insert, and hashes are felts
+    fn hash_pair(&self, left: BigUint, right: BigUint) -> BigUint {
+        let hash = self.hasher.hash_two(biguint_to_felt(&left), biguint_to_felt(&right));
+        felt_to_biguint(hash)
+    }
+
     /// Hash two nodes using Poseidon BN254 and mask to felt252
     fn hash_and_mask(inputs: &[BigUint], mask: &BigUint) -> BigUint {
         // Convert BigUint to Fr field elements
@@ -212,6 +624,46 @@ impl MerkleTree {
     }
 }
 
+/// Whether `proof` leads from `leaf` to `root` when hashed with `hasher`
+/// Also rejects paths whose indices aren't the bits of `proof.leaf_index` or whose length
+/// differs from the index count, so index and ordering bugs show up before proving
+pub fn verify_merkle_path(root: &BigUint, leaf: &BigUint, proof: &MerkleProof, hasher: &dyn Hasher) -> bool {
+    if proof.path.len() != proof.path_indices.len() || proof.path.len() > 32 {
+        return false;
+    }
+    let mut current = match FieldElement::from_byte_slice_be(&leaf.to_bytes_be()) {
+        Ok(leaf) => leaf,
+        Err(_) => return false,
+    };
+    for (level, (sibling, bit)) in proof.path.iter().zip(&proof.path_indices).enumerate() {
+        if *bit != (proof.leaf_index as u64 >> level) as u32 & 1 {
+            return false;
+        }
+        let sibling = match FieldElement::from_hex_be(sibling) {
+            Ok(sibling) => sibling,
+            Err(_) => return false,
+        };
+        current = if *bit == 0 { hasher.hash_two(current, sibling) } else { hasher.hash_two(sibling, current) };
+    }
+    felt_to_biguint(current) == *root
+}
+
+fn felt_to_biguint(felt: FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&felt.to_bytes_be())
+}
+
+/// Leaves must be felt252 for `hash_pair` to take them
+fn check_leaf(leaf: &BigUint) -> Result<(), InvalidLeaf> {
+    match FieldElement::from_byte_slice_be(&leaf.to_bytes_be()) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(InvalidLeaf(leaf.clone())),
+    }
+}
+
+fn biguint_to_felt(value: &BigUint) -> FieldElement {
+    FieldElement::from_byte_slice_be(&value.to_bytes_be()).expect("Merkle tree node must be a felt252")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,10 +684,10 @@ mod tests {
         let leaf1 = BigUint::from(12345u64);
         let leaf2 = BigUint::from(67890u64);
 
-        let root1 = tree.insert(leaf1.clone());
+        let root1 = tree.insert(leaf1.clone()).unwrap();
         assert_eq!(tree.get_leaf_count(), 1);
 
-        let root2 = tree.insert(leaf2.clone());
+        let root2 = tree.insert(leaf2.clone()).unwrap();
         assert_eq!(tree.get_leaf_count(), 2);
         assert_ne!(root1, root2);
 
@@ -252,7 +704,7 @@ mod tests {
         let mask = BigUint::from_str_radix(MASK, 16).unwrap();
 
         let leaf = BigUint::from(12345u64);
-        let _root = tree.insert(leaf.clone());
+        let _root = tree.insert(leaf.clone()).unwrap();
 
         let proof = tree.get_proof(0).expect("Proof should exist");
 
@@ -270,4 +722,172 @@ mod tests {
 
         assert_eq!(format!("0x{:x}", current_hash), proof.root);
     }
+
+    #[test]
+    fn test_verify_merkle_path() {
+        let leaves: Vec<BigUint> = (1..=5u64).map(|i| BigUint::from(i * 100)).collect();
+        let tree = MerkleTree::from_leaves(6, &leaves);
+        let root = tree.get_root();
+        let hasher = tree.hasher.clone();
+
+        let proof = tree.get_proof(3).unwrap();
+        assert!(verify_merkle_path(&root, &leaves[3], &proof, hasher.as_ref()));
+        // Another leaf, a stale root or the wrong hash don't check out
+        assert!(!verify_merkle_path(&root, &leaves[2], &proof, hasher.as_ref()));
+        assert!(!verify_merkle_path(&MerkleTree::from_leaves(6, &leaves[..4]).get_root(), &leaves[3], &proof, hasher.as_ref()));
+        assert!(!verify_merkle_path(&root, &leaves[3], &proof, &PedersenHasher));
+
+        // Indices that aren't the leaf index bits
+        let mut flipped = proof.clone();
+        flipped.path_indices[0] ^= 1;
+        assert!(!verify_merkle_path(&root, &leaves[3], &flipped, hasher.as_ref()));
+        let mut wrong_index = proof.clone();
+        wrong_index.leaf_index = 2;
+        assert!(!verify_merkle_path(&root, &leaves[3], &wrong_index, hasher.as_ref()));
+        // Siblings out of order
+        let mut swapped = proof;
+        swapped.path.swap(0, 1);
+        assert!(!verify_merkle_path(&root, &leaves[3], &swapped, hasher.as_ref()));
+    }
+
+    #[test]
+    fn test_proof_for_commitment() {
+        let leaves: Vec<BigUint> = (1..=5u64).map(|i| BigUint::from(i * 100)).collect();
+        let tree = MerkleTree::from_leaves(6, &leaves);
+
+        let proof = tree.get_proof_for_commitment(&BigUint::from(300u64)).unwrap();
+        assert_eq!(proof.leaf_index, 2);
+        assert_eq!(proof.path, tree.get_proof(2).unwrap().path);
+        assert_eq!(proof.path_indices[0], 0);
+        assert_eq!(proof.root, format!("0x{:x}", tree.get_root()));
+        assert!(tree.get_proof_for_commitment(&BigUint::from(301u64)).is_none());
+    }
+
+    #[test]
+    fn test_append_matches_full_rebuild() {
+        let depth = 6;
+        let mut tree = MerkleTree::new(depth);
+        let mut leaves = Vec::new();
+
+        for i in 1..=9u64 {
+            let leaf = BigUint::from(i * 1000 + 7);
+            leaves.push(leaf.clone());
+
+            let root = tree.append(leaf).unwrap();
+            let rebuilt = MerkleTree::from_leaves(depth, &leaves);
+            assert_eq!(root, rebuilt.get_root(), "root mismatch after {} leaves", i);
+            assert_eq!(tree.get_leaf_count(), rebuilt.get_leaf_count());
+        }
+    }
+
+    #[test]
+    fn test_configured_hash_is_kept_by_rebuilds() {
+        let depth = 4;
+        let leaves: Vec<BigUint> = (1..=3u64).map(BigUint::from).collect();
+        let mut tree = MerkleTree::with_hasher(depth, TreeHash::Pedersen.hasher());
+        for leaf in &leaves {
+            tree.append(leaf.clone()).unwrap();
+        }
+
+        let rebuilt = MerkleTree::from_leaves_with_hasher(depth, &leaves, tree.hasher.clone());
+        assert_eq!(rebuilt.get_root(), tree.get_root());
+        assert_ne!(MerkleTree::from_leaves(depth, &leaves).get_root(), tree.get_root());
+
+        assert_eq!(TreeHash::parse(" Pedersen"), Some(TreeHash::Pedersen));
+        assert_eq!(TreeHash::parse("keccak"), None);
+    }
+
+    #[test]
+    fn test_append_after_out_of_order_insert() {
+        // insert_at_index must leave the frontier cache consistent for later appends
+        let depth = 5;
+        let leaves: Vec<BigUint> = (1..=4u64).map(BigUint::from).collect();
+
+        let mut tree = MerkleTree::new(depth);
+        tree.insert_at_index(0, leaves[0].clone()).unwrap();
+        tree.insert_at_index(1, leaves[1].clone()).unwrap();
+        tree.insert_at_index(2, leaves[2].clone()).unwrap();
+        let root = tree.append(leaves[3].clone()).unwrap();
+
+        assert_eq!(root, MerkleTree::from_leaves(depth, &leaves).get_root());
+    }
+
+    #[test]
+    fn test_custom_hasher_node_ordering() {
+        use crate::hasher::RecordingHasher;
+
+        let hasher = Arc::new(RecordingHasher::default());
+        let mut tree = MerkleTree::with_hasher(2, hasher.clone());
+        tree.append(BigUint::from(3u8)).unwrap();
+        tree.append(BigUint::from(5u8)).unwrap();
+
+        let felt = |v: u64| FieldElement::from(v);
+        // leaf 0: (3, 0) then (3 + 0, 0); leaf 1: (3, 5) then (3 + 10, 0)
+        assert_eq!(
+            *hasher.calls.lock().unwrap(),
+            vec![(felt(3), felt(0)), (felt(3), felt(0)), (felt(3), felt(5)), (felt(13), felt(0))]
+        );
+        assert_eq!(tree.get_root(), BigUint::from(13u8));
+    }
+
+    #[test]
+    fn test_bn254_hasher_matches_hash_and_mask() {
+        let mask = BigUint::from_str_radix(MASK, 16).unwrap();
+        let expected = MerkleTree::hash_and_mask(&[BigUint::from(1u8), BigUint::from(2u8)], &mask);
+        let hash = Bn254PoseidonHasher.hash_two(FieldElement::ONE, FieldElement::from(2u8));
+        assert_eq!(felt_to_biguint(hash), expected);
+    }
+
+    #[test]
+    fn test_insert_batch_matches_appends() {
+        let leaves: Vec<BigUint> = (1u64..=11).map(BigUint::from).collect();
+        let mut appended = MerkleTree::new(TREE_DEPTH);
+        for leaf in &leaves {
+            appended.append(leaf.clone()).unwrap();
+        }
+
+        // In two batches, the second starting on a right child
+        let mut batched = MerkleTree::new(TREE_DEPTH);
+        batched.insert_batch(&leaves[..5]).unwrap();
+        let root = batched.insert_batch(&leaves[5..]).unwrap();
+        assert_eq!(root, appended.get_root());
+        assert_eq!(batched.get_leaf_count(), 11);
+        assert_eq!(batched.get_proof(6).unwrap().path, appended.get_proof(6).unwrap().path);
+
+        // The frontier stays right for later appends
+        assert_eq!(batched.append(BigUint::from(12u8)), appended.append(BigUint::from(12u8)));
+    }
+
+    #[test]
+    fn test_from_frontier_appends_like_the_full_tree() {
+        let leaves: Vec<BigUint> = (1u64..=13).map(BigUint::from).collect();
+        let mut full = MerkleTree::from_leaves(TREE_DEPTH, &leaves);
+        let frontier = full.filled_subtrees.clone();
+        let mut resumed = MerkleTree::from_frontier(TREE_DEPTH, full.hasher.clone(), 13, &frontier, full.get_root());
+        assert!(resumed.nodes.len() <= TREE_DEPTH);
+
+        for leaf in 14u64..=17 {
+            assert_eq!(resumed.append(BigUint::from(leaf)), full.append(BigUint::from(leaf)));
+        }
+        assert_eq!(resumed.insert_batch(&[BigUint::from(18u8)]), full.insert_batch(&[BigUint::from(18u8)]));
+        assert_eq!(resumed.get_proof(15).unwrap().path, full.get_proof(15).unwrap().path);
+        assert!(resumed.get_proof(12).is_none());
+    }
+
+    #[test]
+    fn test_leaves_that_are_not_felts_are_rejected() {
+        let mut tree = MerkleTree::new(TREE_DEPTH);
+        tree.append(BigUint::from(1u8)).unwrap();
+        let root = tree.get_root();
+        let too_big = BigUint::from(1u8) << 252u32;
+
+        assert_eq!(tree.append(too_big.clone()), Err(InvalidLeaf(too_big.clone())));
+        assert!(tree.insert_at_index(3, too_big.clone()).is_err());
+        assert!(tree.preview_append(too_big.clone()).is_err());
+        // A batch goes in whole or not at all
+        assert!(tree.insert_batch(&[BigUint::from(2u8), too_big]).is_err());
+        assert_eq!(tree.get_root(), root);
+        assert_eq!(tree.get_leaf_count(), 1);
+    }
+
 }
@@ -0,0 +1,168 @@
+// RPC metrics
+// Per-method counters and histograms of the requests sent to the Starknet node: how many,
+// how long they took, how large the results were and why they failed. Filled by
+// InstrumentedProvider and served in the Prometheus text format on /metrics.
+
+use crate::error::BlockchainError;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the request duration buckets
+const DURATION_BUCKETS: [f64; 9] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+/// Upper bounds of the result size buckets (felts, events or calls depending on the method)
+const SIZE_BUCKETS: [f64; 7] = [1.0, 4.0, 16.0, 64.0, 256.0, 1024.0, 4096.0];
+
+/// Cumulative histogram with fixed buckets
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    /// Observations at or below each bound, same order as the bounds
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; bounds.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(bounds) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// Everything recorded for one RPC method
+#[derive(Debug, Clone, Default)]
+pub struct MethodMetrics {
+    pub requests: u64,
+    /// Failed requests by error class (see BlockchainError::class)
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Seconds, successful and failed requests alike
+    pub duration: Histogram,
+    /// Successful requests only
+    pub result_size: Histogram,
+}
+
+#[derive(Default)]
+pub struct MetricsRegistry {
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+}
+
+/// Registry shared by every instrumented provider of the process
+static GLOBAL: Lazy<Arc<MetricsRegistry>> = Lazy::new(|| Arc::new(MetricsRegistry::default()));
+
+impl MetricsRegistry {
+    pub fn global() -> Arc<MetricsRegistry> {
+        GLOBAL.clone()
+    }
+
+    /// Record one request: its duration and either the result size or the error
+    pub fn record(&self, method: &'static str, duration: Duration, outcome: Result<usize, &BlockchainError>) {
+        let mut methods = self.methods.lock().unwrap();
+        let metrics = methods.entry(method).or_default();
+        metrics.requests += 1;
+        metrics.duration.observe(&DURATION_BUCKETS, duration.as_secs_f64());
+        match outcome {
+            Ok(size) => metrics.result_size.observe(&SIZE_BUCKETS, size as f64),
+            Err(e) => *metrics.errors.entry(e.class()).or_default() += 1,
+        }
+    }
+
+    /// Metrics of one method, if it was called at least once
+    pub fn method(&self, method: &str) -> Option<MethodMetrics> {
+        self.methods.lock().unwrap().get(method).cloned()
+    }
+
+    /// Prometheus text exposition of every method
+    pub fn render_prometheus(&self) -> String {
+        let methods = self.methods.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP asp_rpc_requests_total RPC requests sent to the Starknet node\n");
+        out.push_str("# TYPE asp_rpc_requests_total counter\n");
+        for (method, metrics) in methods.iter() {
+            let _ = writeln!(out, "asp_rpc_requests_total{{method=\"{}\"}} {}", method, metrics.requests);
+        }
+
+        out.push_str("# HELP asp_rpc_errors_total Failed RPC requests by error class\n");
+        out.push_str("# TYPE asp_rpc_errors_total counter\n");
+        for (method, metrics) in methods.iter() {
+            for (class, count) in &metrics.errors {
+                let _ = writeln!(out, "asp_rpc_errors_total{{method=\"{}\",class=\"{}\"}} {}", method, class, count);
+            }
+        }
+
+        render_histogram(&mut out, "asp_rpc_duration_seconds", "RPC request duration", &DURATION_BUCKETS, &methods, |m| &m.duration);
+        render_histogram(&mut out, "asp_rpc_result_size", "Size of successful RPC results", &SIZE_BUCKETS, &methods, |m| &m.result_size);
+        out
+    }
+}
+
+fn render_histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    bounds: &[f64],
+    methods: &BTreeMap<&'static str, MethodMetrics>,
+    histogram: fn(&MethodMetrics) -> &Histogram,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (method, metrics) in methods {
+        let histogram = histogram(metrics);
+        if histogram.count == 0 {
+            continue;
+        }
+        for (bound, count) in bounds.iter().zip(&histogram.buckets) {
+            let _ = writeln!(out, "{}_bucket{{method=\"{}\",le=\"{}\"}} {}", name, method, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{method=\"{}\",le=\"+Inf\"}} {}", name, method, histogram.count);
+        let _ = writeln!(out, "{}_sum{{method=\"{}\"}} {}", name, method, histogram.sum);
+        let _ = writeln!(out, "{}_count{{method=\"{}\"}} {}", name, method, histogram.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_per_method() {
+        let registry = MetricsRegistry::default();
+        registry.record("call", Duration::from_millis(30), Ok(1));
+        registry.record("call", Duration::from_secs(3), Err(&BlockchainError::Timeout("call".to_string())));
+        registry.record("get_events", Duration::from_millis(200), Ok(100));
+
+        let call = registry.method("call").unwrap();
+        assert_eq!(call.requests, 2);
+        assert_eq!(call.errors.get("timeout"), Some(&1));
+        assert_eq!(call.duration.count, 2);
+        // 30ms falls in every bucket from 0.05s up, 3s only from 5s up
+        assert_eq!(call.duration.buckets[1], 1);
+        assert_eq!(call.duration.buckets[7], 2);
+        assert_eq!(call.result_size.count, 1);
+        assert!(registry.method("get_storage_at").is_none());
+    }
+
+    #[test]
+    fn test_prometheus_output() {
+        let registry = MetricsRegistry::default();
+        registry.record("get_events", Duration::from_millis(200), Ok(100));
+        registry.record("get_events", Duration::from_millis(20), Err(&BlockchainError::Transport("reset".to_string())));
+
+        let text = registry.render_prometheus();
+        assert!(text.contains("asp_rpc_requests_total{method=\"get_events\"} 2"));
+        assert!(text.contains("asp_rpc_errors_total{method=\"get_events\",class=\"transport\"} 1"));
+        assert!(text.contains("asp_rpc_duration_seconds_bucket{method=\"get_events\",le=\"+Inf\"} 2"));
+        assert!(text.contains("asp_rpc_result_size_bucket{method=\"get_events\",le=\"64\"} 0"));
+        assert!(text.contains("asp_rpc_result_size_bucket{method=\"get_events\",le=\"256\"} 1"));
+    }
+}
@@ -0,0 +1,119 @@
+// Storage schema migrations
+// The SQL backends list their schema as numbered migrations and store the version they
+// reached in the database, so a release that changes the format upgrades existing state when
+// it opens it instead of asking for a wipe and resync. Every migration can be undone: going
+// back to an older release is STATE_SCHEMA_VERSION=<its version> with the newer one, which
+// migrates down and stops, then starting the older binary.
+
+use std::io;
+
+/// One schema change, applied (and undone) in a single transaction
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: &'static [&'static str],
+    pub down: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+impl Migration {
+    /// Statements to run in `direction`
+    pub fn statements(&self, direction: Direction) -> &'static [&'static str] {
+        match direction {
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+        }
+    }
+}
+
+/// Version of the last of `migrations`, the one this build reads and writes
+pub fn latest_version(migrations: &[Migration]) -> u32 {
+    migrations.last().map_or(0, |migration| migration.version)
+}
+
+/// Version to migrate to: STATE_SCHEMA_VERSION, the latest one by default
+pub fn target_version(migrations: &[Migration]) -> io::Result<u32> {
+    match std::env::var("STATE_SCHEMA_VERSION") {
+        Ok(version) => version
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid STATE_SCHEMA_VERSION {:?}", version))),
+        Err(_) => Ok(latest_version(migrations)),
+    }
+}
+
+/// Migrations to run, in order, to go from schema `current` to `target`
+pub fn plan(migrations: &'static [Migration], current: u32, target: u32) -> io::Result<Vec<(&'static Migration, Direction)>> {
+    let latest = latest_version(migrations);
+    if current > latest {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("state schema {} was written by a newer release (this one knows up to {})", current, latest),
+        ));
+    }
+    if target > latest {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no state schema {} (latest is {})", target, latest)));
+    }
+    Ok(if target >= current {
+        migrations
+            .iter()
+            .filter(|migration| migration.version > current && migration.version <= target)
+            .map(|migration| (migration, Direction::Up))
+            .collect()
+    } else {
+        migrations
+            .iter()
+            .rev()
+            .filter(|migration| migration.version <= current && migration.version > target)
+            .map(|migration| (migration, Direction::Down))
+            .collect()
+    })
+}
+
+/// After migrating to `target`: an error to stop on if it is older than this build's schema
+pub fn check_downgrade(migrations: &[Migration], target: u32) -> io::Result<()> {
+    let latest = latest_version(migrations);
+    if target < latest {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("state migrated down to schema {}: start the release using it (this one needs {})", target, latest),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration { version: 1, description: "tables", up: &["CREATE a"], down: &["DROP a"] },
+        Migration { version: 2, description: "index", up: &["CREATE i"], down: &["DROP i"] },
+        Migration { version: 3, description: "column", up: &["ALTER add"], down: &["ALTER drop"] },
+    ];
+
+    fn versions(steps: &[(&Migration, Direction)]) -> Vec<(u32, Direction)> {
+        steps.iter().map(|(migration, direction)| (migration.version, *direction)).collect()
+    }
+
+    #[test]
+    fn test_migration_plans() {
+        assert_eq!(versions(&plan(MIGRATIONS, 0, 3).unwrap()), [(1, Direction::Up), (2, Direction::Up), (3, Direction::Up)]);
+        assert_eq!(versions(&plan(MIGRATIONS, 2, 3).unwrap()), [(3, Direction::Up)]);
+        assert!(plan(MIGRATIONS, 3, 3).unwrap().is_empty());
+        // Down to 1 undoes 3, then 2
+        assert_eq!(versions(&plan(MIGRATIONS, 3, 1).unwrap()), [(3, Direction::Down), (2, Direction::Down)]);
+        assert_eq!(plan(MIGRATIONS, 3, 1).unwrap()[0].0.statements(Direction::Down), ["ALTER drop"]);
+
+        // A newer release's state, or an unknown target
+        assert!(plan(MIGRATIONS, 4, 3).is_err());
+        assert!(plan(MIGRATIONS, 1, 4).is_err());
+        assert!(check_downgrade(MIGRATIONS, 2).is_err());
+        assert!(check_downgrade(MIGRATIONS, 3).is_ok());
+    }
+}
@@ -0,0 +1,387 @@
+// In-memory StarknetProvider for unit tests
+// Behaves like a real node for the calls it knows about: unknown contracts
+// produce ContractNotFound, exactly like the JSON-RPC `CONTRACT_NOT_FOUND` error,
+// and unset storage slots read as zero.
+
+use crate::error::BlockchainError;
+use crate::events::deposit_selector;
+use crate::provider::{InvocationTrace, StarknetProvider, TxReceipt};
+use async_trait::async_trait;
+use starknet::core::types::{BlockId, BlockTag, EmittedEvent, EventFilter, EventsPage, FieldElement, FunctionCall};
+use starknet::core::utils::{get_selector_from_name, starknet_keccak};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Default)]
+pub struct MockProvider {
+    /// Deployed contracts: address -> class hash
+    contracts: Mutex<HashMap<FieldElement, FieldElement>>,
+    /// Deployment blocks: historical class lookups before them find no contract
+    deployed_at: Mutex<HashMap<FieldElement, u64>>,
+    /// Storage: (contract, key) -> value
+    storage: Mutex<HashMap<(FieldElement, FieldElement), FieldElement>>,
+    /// View call results: (contract, selector) -> returned felts
+    call_results: Mutex<HashMap<(FieldElement, FieldElement), Vec<FieldElement>>>,
+    /// Transaction receipts: tx hash -> receipt
+    receipts: Mutex<HashMap<FieldElement, TxReceipt>>,
+    /// Latest block number
+    block_number: Mutex<u64>,
+    /// Block hashes overriding the default (the block number itself)
+    block_hashes: Mutex<HashMap<u64, FieldElement>>,
+    /// Block traces: block number -> transaction traces (tracing unsupported if empty)
+    traces: Mutex<HashMap<u64, Vec<InvocationTrace>>>,
+    /// Emitted events, in chain order
+    events: Mutex<Vec<EmittedEvent>>,
+    /// get_events also honours a numbered `from_block` (off: every event up to `to_block`)
+    filter_from_block: bool,
+    /// When set, every request fails with this error (node down, bad URL, ...)
+    failure: Mutex<Option<BlockchainError>>,
+    /// Errors returned by the next requests, one per request (flaky node)
+    transient_failures: Mutex<Vec<BlockchainError>>,
+    /// Simulated round-trip time of every request
+    latency: Mutex<Option<Duration>>,
+    /// Simulated connection pool: each in-flight request holds a permit until it completes
+    /// or is dropped (unlimited if unset)
+    connections: Mutex<Option<Arc<Semaphore>>>,
+    /// Number of view calls served
+    calls: AtomicUsize,
+    /// Block of every view call, in order
+    call_blocks: Mutex<Vec<BlockId>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a deployed contract at `address`
+    pub fn with_contract(self, address: FieldElement, class_hash: FieldElement) -> Self {
+        self.contracts.lock().unwrap().insert(address, class_hash);
+        self
+    }
+
+    /// Register a contract deployed at block `block` (deployed since genesis otherwise)
+    pub fn with_contract_deployed_at(self, address: FieldElement, class_hash: FieldElement, block: u64) -> Self {
+        self.deployed_at.lock().unwrap().insert(address, block);
+        self.with_contract(address, class_hash)
+    }
+
+    /// Set a storage slot (also marks the contract as deployed)
+    pub fn with_storage(self, address: FieldElement, key: FieldElement, value: FieldElement) -> Self {
+        self.mark_deployed(address);
+        self.storage.lock().unwrap().insert((address, key), value);
+        self
+    }
+
+    /// Set the result of calling `function` on `address`, whatever the calldata
+    /// (also marks the contract as deployed)
+    pub fn with_call_result(self, address: FieldElement, function: &str, result: Vec<FieldElement>) -> Self {
+        self.mark_deployed(address);
+        let selector = get_selector_from_name(function).unwrap();
+        self.call_results.lock().unwrap().insert((address, selector), result);
+        self
+    }
+
+    /// Add events of the pending block (emitted but without a block number yet)
+    pub fn with_pending_events(self, events: Vec<EmittedEvent>) -> Self {
+        self.events.lock().unwrap().extend(events.into_iter().map(|event| EmittedEvent {
+            block_hash: None,
+            block_number: None,
+            ..event
+        }));
+        self
+    }
+
+    /// Set the receipt returned for `tx_hash`
+    pub fn with_receipt(self, tx_hash: FieldElement, receipt: TxReceipt) -> Self {
+        self.receipts.lock().unwrap().insert(tx_hash, receipt);
+        self
+    }
+
+    /// Set the latest block number
+    pub fn with_block_number(self, block_number: u64) -> Self {
+        *self.block_number.lock().unwrap() = block_number;
+        self
+    }
+
+    /// Set the hash of `block`; blocks without one hash to their number
+    pub fn with_block_hash(self, block: u64, hash: FieldElement) -> Self {
+        self.block_hashes.lock().unwrap().insert(block, hash);
+        self
+    }
+
+    /// Change a block hash after construction (e.g. a reorg replacing the block)
+    pub fn set_block_hash(&self, block: u64, hash: FieldElement) {
+        self.block_hashes.lock().unwrap().insert(block, hash);
+    }
+
+    /// Drop events emitted after `block` (e.g. a reorg orphaning them)
+    pub fn truncate_events_after(&self, block: u64) {
+        self.events.lock().unwrap().retain(|e| e.block_number.is_none_or(|number| number <= block));
+    }
+
+    /// Set the transaction traces of `block` (enables tracing on the mock)
+    pub fn with_block_trace(self, block: u64, traces: Vec<InvocationTrace>) -> Self {
+        self.traces.lock().unwrap().insert(block, traces);
+        self
+    }
+
+    /// Append events returned by get_events
+    pub fn with_events(self, events: Vec<EmittedEvent>) -> Self {
+        self.events.lock().unwrap().extend(events);
+        self
+    }
+
+    /// Make get_events skip events before a numbered `filter.from_block`
+    pub fn with_from_block_filter(mut self) -> Self {
+        self.filter_from_block = true;
+        self
+    }
+
+    /// Append events after construction (e.g. a deposit mined while a client waits)
+    pub fn push_events(&self, events: Vec<EmittedEvent>) {
+        self.events.lock().unwrap().extend(events);
+    }
+
+    /// Advance the latest block after construction
+    pub fn set_block_number(&self, block_number: u64) {
+        *self.block_number.lock().unwrap() = block_number;
+    }
+
+    /// Make every request fail with `error`
+    pub fn failing(self, error: BlockchainError) -> Self {
+        *self.failure.lock().unwrap() = Some(error);
+        self
+    }
+
+    /// Make the next `count` requests fail with `error`, then serve normally
+    pub fn with_transient_failures(self, count: usize, error: BlockchainError) -> Self {
+        self.transient_failures.lock().unwrap().extend(std::iter::repeat(error).take(count));
+        self
+    }
+
+    /// Delay every request by `latency`
+    pub fn with_latency(self, latency: Duration) -> Self {
+        *self.latency.lock().unwrap() = Some(latency);
+        self
+    }
+
+    /// Change the simulated latency after construction
+    pub fn set_latency(&self, latency: Option<Duration>) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    /// Allow at most `max` requests in flight; further requests fail as if the HTTP
+    /// connection pool were exhausted
+    pub fn with_connection_limit(self, max: usize) -> Self {
+        *self.connections.lock().unwrap() = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Number of `call` requests received so far
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Blocks the view calls were evaluated at, in order
+    pub fn call_blocks(&self) -> Vec<BlockId> {
+        self.call_blocks.lock().unwrap().clone()
+    }
+
+    /// Check out a connection and wait out the simulated latency
+    /// The returned permit must be held for the whole request
+    async fn checkout(&self) -> Result<Option<OwnedSemaphorePermit>, BlockchainError> {
+        let connections = self.connections.lock().unwrap().clone();
+        let permit = match connections {
+            Some(connections) => Some(
+                connections
+                    .try_acquire_owned()
+                    .map_err(|_| BlockchainError::Transport("connection pool exhausted".to_string()))?,
+            ),
+            None => None,
+        };
+        let latency = *self.latency.lock().unwrap();
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+        Ok(permit)
+    }
+
+    fn check_failure(&self) -> Result<(), BlockchainError> {
+        let mut transient = self.transient_failures.lock().unwrap();
+        if !transient.is_empty() {
+            return Err(transient.remove(0));
+        }
+        drop(transient);
+        match self.failure.lock().unwrap().clone() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn mark_deployed(&self, address: FieldElement) {
+        self.contracts
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_insert(FieldElement::ONE);
+    }
+
+    fn ensure_deployed(&self, address: FieldElement) -> Result<(), BlockchainError> {
+        self.check_failure()?;
+        if self.contracts.lock().unwrap().contains_key(&address) {
+            Ok(())
+        } else {
+            Err(BlockchainError::ContractNotFound(format!("0x{:x}", address)))
+        }
+    }
+}
+
+#[async_trait]
+impl StarknetProvider for MockProvider {
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.call_blocks.lock().unwrap().push(block);
+        self.ensure_deployed(call.contract_address)?;
+        self.call_results
+            .lock()
+            .unwrap()
+            .get(&(call.contract_address, call.entry_point_selector))
+            .cloned()
+            .ok_or_else(|| {
+                BlockchainError::EntryPointNotFound(format!(
+                    "0x{:x} in contract 0x{:x}",
+                    call.entry_point_selector, call.contract_address
+                ))
+            })
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        _block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.ensure_deployed(address)?;
+        Ok(self
+            .storage
+            .lock()
+            .unwrap()
+            .get(&(address, key))
+            .copied()
+            .unwrap_or(FieldElement::ZERO))
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.ensure_deployed(address)?;
+        if let (BlockId::Number(number), Some(&deployed)) = (block, self.deployed_at.lock().unwrap().get(&address)) {
+            if number < deployed {
+                return Err(BlockchainError::ContractNotFound(format!("0x{:x}", address)));
+            }
+        }
+        Ok(self.contracts.lock().unwrap()[&address])
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.check_failure()?;
+        self.receipts
+            .lock()
+            .unwrap()
+            .get(&tx_hash)
+            .cloned()
+            .ok_or_else(|| BlockchainError::TransactionNotFound(format!("0x{:x}", tx_hash)))
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.check_failure()?;
+        Ok(*self.block_number.lock().unwrap())
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.check_failure()?;
+        if block > *self.block_number.lock().unwrap() {
+            return Err(BlockchainError::MissingData(format!("Block {} not found", block)));
+        }
+        Ok(self.block_hashes.lock().unwrap().get(&block).copied().unwrap_or_else(|| FieldElement::from(block)))
+    }
+
+    /// Pages through events from `filter.address` up to a numbered `filter.to_block` (and from
+    /// a numbered `filter.from_block` with `with_from_block_filter`); the continuation token is
+    /// the next offset
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.check_failure()?;
+        let offset: usize = continuation_token.map(|t| t.parse().unwrap()).unwrap_or(0);
+        let matching: Vec<EmittedEvent> = self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| filter.address.is_none_or(|address| e.from_address == address))
+            .filter(|e| match (filter.to_block, e.block_number) {
+                (Some(BlockId::Number(to)), Some(number)) => number <= to,
+                // Pending events have no block number and only show up up to the pending tag
+                (Some(BlockId::Number(_)), None) => false,
+                _ => true,
+            })
+            .filter(|e| !matches!(filter.from_block, Some(BlockId::Tag(BlockTag::Pending))) || e.block_number.is_none())
+            .filter(|e| match (self.filter_from_block, filter.from_block, e.block_number) {
+                (true, Some(BlockId::Number(from)), Some(number)) => number >= from,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+
+        let end = (offset + chunk_size as usize).min(matching.len());
+        Ok(EventsPage {
+            events: matching[offset.min(end)..end].to_vec(),
+            continuation_token: (end < matching.len()).then(|| end.to_string()),
+        })
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        let _connection = self.checkout().await?;
+        self.check_failure()?;
+        let traces = self.traces.lock().unwrap();
+        if traces.is_empty() {
+            return Err(BlockchainError::Unsupported("starknet_traceBlockTransactions".to_string()));
+        }
+        Ok(traces.get(&block).cloned().unwrap_or_default())
+    }
+}
+
+/// A Zylith Deposit event as returned by starknet_getEvents
+pub fn deposit_emitted_event(
+    zylith_address: FieldElement,
+    commitment: FieldElement,
+    leaf_index: u32,
+    root: FieldElement,
+    block_number: u64,
+) -> EmittedEvent {
+    EmittedEvent {
+        from_address: zylith_address,
+        keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), deposit_selector()],
+        data: vec![commitment, FieldElement::from(leaf_index), root],
+        block_hash: Some(FieldElement::from(block_number)),
+        block_number: Some(block_number),
+        transaction_hash: commitment,
+    }
+}
@@ -0,0 +1,249 @@
+// Multicall: several view calls evaluated at the same block
+// Reads that must agree with each other (merkle root, nullifier status, balances) are
+// grouped here. With an on-chain aggregator they run inside a single `aggregate` call;
+// otherwise the latest block number is resolved once and the calls are batched against it.
+
+use crate::blockchain::BlockchainClient;
+use crate::conv::{felt_to_u128, felt_to_u32, U256};
+use crate::error::BlockchainError;
+use crate::provider::{CallResult, StarknetProvider};
+use starknet::core::types::{BlockId, BlockTag, FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
+
+/// Builder for a group of view calls, created by `BlockchainClient::multicall`
+pub struct Multicall<'a, P> {
+    client: &'a BlockchainClient<P>,
+    aggregator: Option<FieldElement>,
+    calls: Vec<FunctionCall>,
+}
+
+/// Results of a multicall, in the order the calls were added
+#[derive(Debug, Clone)]
+pub struct MulticallResult {
+    /// Block every call was evaluated at
+    pub block_number: u64,
+    pub results: Vec<CallResult>,
+}
+
+impl<'a, P: StarknetProvider> Multicall<'a, P> {
+    pub fn new(client: &'a BlockchainClient<P>, aggregator: Option<FieldElement>) -> Self {
+        Self {
+            client,
+            aggregator,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Add a call to `function` on `contract`
+    pub fn add(mut self, contract: FieldElement, function: &str, calldata: Vec<FieldElement>) -> Self {
+        self.calls.push(FunctionCall {
+            contract_address: contract,
+            entry_point_selector: get_selector_from_name(function).unwrap_or(FieldElement::ZERO),
+            calldata,
+        });
+        self
+    }
+
+    /// Add an already built call
+    pub fn add_call(mut self, call: FunctionCall) -> Self {
+        self.calls.push(call);
+        self
+    }
+
+    /// Run every call at the same block
+    pub async fn execute(self) -> Result<MulticallResult, BlockchainError> {
+        match self.aggregator {
+            Some(aggregator) => self.execute_aggregated(aggregator).await,
+            None => self.execute_pinned().await,
+        }
+    }
+
+    /// Resolve the latest block once, then batch every call against it
+    async fn execute_pinned(self) -> Result<MulticallResult, BlockchainError> {
+        let block_number = self.client.latest_block_number().await?;
+        let results = self
+            .client
+            .batch_call_at(self.calls, BlockId::Number(block_number))
+            .await?;
+        Ok(MulticallResult { block_number, results })
+    }
+
+    /// One `aggregate` call on the aggregator contract
+    /// The aggregator reverts as a whole, so a failing call fails the whole multicall
+    async fn execute_aggregated(self, aggregator: FieldElement) -> Result<MulticallResult, BlockchainError> {
+        let count = self.calls.len();
+        let call = FunctionCall {
+            contract_address: aggregator,
+            entry_point_selector: get_selector_from_name("aggregate").unwrap(),
+            calldata: encode_aggregate(&self.calls),
+        };
+        let mut results = self
+            .client
+            .batch_call_at(vec![call], BlockId::Tag(BlockTag::Latest))
+            .await?;
+        let output = results
+            .pop()
+            .ok_or_else(|| BlockchainError::Decode("Empty response from aggregate".to_string()))??;
+        decode_aggregate(&output, count)
+    }
+}
+
+impl MulticallResult {
+    /// Raw felts returned by call `index`
+    pub fn raw(&self, index: usize) -> Result<&[FieldElement], BlockchainError> {
+        match self.results.get(index) {
+            Some(Ok(felts)) => Ok(felts),
+            Some(Err(e)) => Err(e.clone()),
+            None => Err(BlockchainError::InvalidInput(format!(
+                "multicall has {} results, no call {}",
+                self.results.len(),
+                index
+            ))),
+        }
+    }
+
+    /// First felt returned by call `index`
+    pub fn felt(&self, index: usize) -> Result<FieldElement, BlockchainError> {
+        self.raw(index)?
+            .first()
+            .copied()
+            .ok_or_else(|| BlockchainError::Decode(format!("Empty result for multicall call {}", index)))
+    }
+
+    /// Cairo bool returned by call `index`
+    pub fn bool(&self, index: usize) -> Result<bool, BlockchainError> {
+        Ok(self.felt(index)? != FieldElement::ZERO)
+    }
+
+    /// u32 returned by call `index`
+    pub fn u32(&self, index: usize) -> Result<u32, BlockchainError> {
+        felt_to_u32(self.felt(index)?)
+    }
+
+    /// u128 returned by call `index`
+    pub fn u128(&self, index: usize) -> Result<u128, BlockchainError> {
+        felt_to_u128(self.felt(index)?)
+    }
+
+    /// u256 (low, high) returned by call `index`
+    pub fn u256(&self, index: usize) -> Result<U256, BlockchainError> {
+        match self.raw(index)? {
+            [low, high, ..] => U256::from_felts(*low, *high),
+            _ => Err(BlockchainError::Decode(format!("Multicall call {} did not return a u256", index))),
+        }
+    }
+}
+
+/// Calldata of `aggregate(calls: Array<Call>)`, Call being { to, selector, calldata: Array<felt252> }
+fn encode_aggregate(calls: &[FunctionCall]) -> Vec<FieldElement> {
+    let mut calldata = vec![FieldElement::from(calls.len() as u64)];
+    for call in calls {
+        calldata.push(call.contract_address);
+        calldata.push(call.entry_point_selector);
+        calldata.push(FieldElement::from(call.calldata.len() as u64));
+        calldata.extend_from_slice(&call.calldata);
+    }
+    calldata
+}
+
+/// Output of `aggregate`: (block_number: u64, results: Array<Span<felt252>>)
+fn decode_aggregate(output: &[FieldElement], expected: usize) -> Result<MulticallResult, BlockchainError> {
+    let malformed = || BlockchainError::Decode("Malformed aggregate response".to_string());
+
+    let (block_number, rest) = output.split_first().ok_or_else(malformed)?;
+    let (count, mut rest) = rest.split_first().ok_or_else(malformed)?;
+    let count = felt_to_u32(*count)? as usize;
+    if count != expected {
+        return Err(BlockchainError::Decode(format!(
+            "aggregate returned {} results for {} calls",
+            count, expected
+        )));
+    }
+
+    let mut results = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (len, tail) = rest.split_first().ok_or_else(malformed)?;
+        let len = felt_to_u32(*len)? as usize;
+        if tail.len() < len {
+            return Err(malformed());
+        }
+        results.push(Ok(tail[..len].to_vec()));
+        rest = &tail[len..];
+    }
+
+    Ok(MulticallResult {
+        block_number: felt_to_u128(*block_number)? as u64,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+
+    fn zylith_address() -> FieldElement {
+        FieldElement::from(0x2a1u64)
+    }
+
+    #[tokio::test]
+    async fn test_multicall_pinned_batch() {
+        let token = FieldElement::from(0x111u64);
+        let provider = MockProvider::new()
+            .with_block_number(77)
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)])
+            .with_call_result(zylith_address(), "is_nullifier_spent", vec![FieldElement::ONE])
+            .with_call_result(token, "balance_of", vec![FieldElement::from(9u64), FieldElement::ZERO]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let result = client
+            .multicall()
+            .add(zylith_address(), "get_merkle_root", vec![])
+            .add(zylith_address(), "is_nullifier_spent", vec![FieldElement::from(5u64)])
+            .add(token, "balance_of", vec![zylith_address()])
+            .execute()
+            .await
+            .unwrap();
+
+        assert_eq!(result.block_number, 77);
+        assert_eq!(result.felt(0).unwrap(), FieldElement::from(0xabcu64));
+        assert!(result.bool(1).unwrap());
+        assert_eq!(result.u256(2).unwrap(), U256::from(9u128));
+        assert!(matches!(result.felt(3), Err(BlockchainError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_multicall_through_aggregator() {
+        let aggregator = FieldElement::from(0xa99u64);
+        // (block 88, [[0xabc], [0x1]])
+        let output = [88u64, 2, 1, 0xabc, 1, 1].map(FieldElement::from).to_vec();
+        let provider = MockProvider::new().with_call_result(aggregator, "aggregate", output);
+        let client = BlockchainClient::with_provider(provider, zylith_address()).with_multicall_aggregator(aggregator);
+
+        let result = client
+            .multicall()
+            .add(zylith_address(), "get_merkle_root", vec![])
+            .add(zylith_address(), "is_nullifier_spent", vec![FieldElement::from(5u64)])
+            .execute()
+            .await
+            .unwrap();
+
+        assert_eq!(result.block_number, 88);
+        assert_eq!(result.felt(0).unwrap(), FieldElement::from(0xabcu64));
+        assert!(result.bool(1).unwrap());
+    }
+
+    #[test]
+    fn test_encode_aggregate() {
+        let call = FunctionCall {
+            contract_address: FieldElement::from(0xau64),
+            entry_point_selector: FieldElement::from(0xbu64),
+            calldata: vec![FieldElement::from(0xcu64), FieldElement::from(0xdu64)],
+        };
+
+        let expected = [1u64, 0xa, 0xb, 2, 0xc, 0xd].map(FieldElement::from).to_vec();
+        assert_eq!(encode_aggregate(&[call]), expected);
+        // A result count that doesn't match the calls is rejected
+        assert!(decode_aggregate(&[FieldElement::ONE, FieldElement::ZERO], 1).is_err());
+    }
+}
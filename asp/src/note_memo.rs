@@ -0,0 +1,155 @@
+// Encrypted note memos
+// A note sent to someone else travels as a memo only its recipient can open: a fresh ephemeral
+// key pair on BN254 G1, ECDH with the recipient's memo public key, SHA-256 of the shared point
+// as an AES-256-GCM key, and the note's secret, nullifier and amount sealed under it. The key is
+// never reused, so the nonce is fixed at zero. Memo: version, ephemeral public key (x, y, 32
+// bytes each), ciphertext and tag; 161 bytes, hex encoded. A wallet's viewing key comes from its
+// note seed (ViewingKey::from_seed), so restoring the seed also restores access to its memos.
+
+use crate::notes::NoteSeed;
+use crate::witness::NoteOpening;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use ark_bn254::{g1, Fq, Fr, G1Affine};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+const MEMO_VERSION: u8 = 1;
+const POINT_LEN: usize = 64;
+/// secret (32), nullifier (32), amount (16)
+const PLAINTEXT_LEN: usize = 80;
+
+/// Scalar that opens the memos sent to its public key
+#[derive(Clone, PartialEq, Eq)]
+pub struct ViewingKey(Fr);
+
+// Never printed: it reveals every note sent to the wallet
+impl std::fmt::Debug for ViewingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ViewingKey(..)")
+    }
+}
+
+impl ViewingKey {
+    pub fn random() -> Self {
+        Self(Fr::rand(&mut rand::thread_rng()))
+    }
+
+    /// Viewing key of a wallet's note seed
+    pub fn from_seed(seed: &NoteSeed) -> Self {
+        Self(Fr::from_be_bytes_mod_order(&seed.viewing_scalar().to_bytes_be()))
+    }
+
+    pub fn public_key(&self) -> MemoPublicKey {
+        MemoPublicKey((generator() * self.0).into())
+    }
+}
+
+/// Public key senders encrypt memos to, shared as 128 hex characters (x then y)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoPublicKey(G1Affine);
+
+impl MemoPublicKey {
+    pub fn from_hex(hex_key: &str) -> Result<Self, String> {
+        let bytes = hex::decode(hex_key.trim().trim_start_matches("0x")).map_err(|e| format!("Invalid memo public key: {}", e))?;
+        point_from_bytes(&bytes).map(Self).ok_or_else(|| "Not a memo public key".to_string())
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(point_to_bytes(&self.0))
+    }
+}
+
+/// Memo that opens to `note` with the viewing key of `recipient`
+pub fn encrypt_note_for(recipient: &MemoPublicKey, note: &NoteOpening) -> Result<String, String> {
+    let field = |name: &str, value: &BigUint| {
+        let bytes = value.to_bytes_be();
+        if bytes.len() > 32 {
+            return Err(format!("The note's {} doesn't fit in 32 bytes", name));
+        }
+        Ok([vec![0u8; 32 - bytes.len()], bytes].concat())
+    };
+    let mut plaintext = Vec::with_capacity(PLAINTEXT_LEN);
+    plaintext.extend(field("secret", &note.secret)?);
+    plaintext.extend(field("nullifier", &note.nullifier)?);
+    plaintext.extend(note.amount.to_be_bytes());
+
+    let ephemeral = Fr::rand(&mut rand::thread_rng());
+    let ephemeral_public = point_to_bytes(&(generator() * ephemeral).into());
+    let ciphertext = cipher(&(recipient.0 * ephemeral).into(), &ephemeral_public)
+        .encrypt(Nonce::from_slice(&[0u8; 12]), plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt the memo".to_string())?;
+    Ok(hex::encode([vec![MEMO_VERSION], ephemeral_public, ciphertext].concat()))
+}
+
+/// The note in `memo` if it was sent to `viewing_key`; None for anyone else's or a malformed memo
+pub fn try_decrypt(viewing_key: &ViewingKey, memo: &str) -> Option<NoteOpening> {
+    let memo = hex::decode(memo.trim().trim_start_matches("0x")).ok()?;
+    let (&version, memo) = memo.split_first()?;
+    if version != MEMO_VERSION || memo.len() <= POINT_LEN {
+        return None;
+    }
+    let (ephemeral_public, ciphertext) = memo.split_at(POINT_LEN);
+    let shared = (point_from_bytes(ephemeral_public)? * viewing_key.0).into();
+    let plaintext = cipher(&shared, ephemeral_public).decrypt(Nonce::from_slice(&[0u8; 12]), ciphertext).ok()?;
+    if plaintext.len() != PLAINTEXT_LEN {
+        return None;
+    }
+    Some(NoteOpening {
+        secret: BigUint::from_bytes_be(&plaintext[..32]),
+        nullifier: BigUint::from_bytes_be(&plaintext[32..64]),
+        amount: u128::from_be_bytes(plaintext[64..].try_into().ok()?),
+    })
+}
+
+/// AES-256-GCM keyed with SHA-256(shared point, ephemeral public key)
+fn cipher(shared: &G1Affine, ephemeral_public: &[u8]) -> Aes256Gcm {
+    let key = Sha256::new().chain_update(b"zylith-note-memo").chain_update(point_to_bytes(shared)).chain_update(ephemeral_public).finalize();
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+fn generator() -> G1Affine {
+    G1Affine::new_unchecked(g1::G1_GENERATOR_X, g1::G1_GENERATOR_Y)
+}
+
+fn point_to_bytes(point: &G1Affine) -> Vec<u8> {
+    [point.x.into_bigint().to_bytes_be(), point.y.into_bigint().to_bytes_be()].concat()
+}
+
+/// A point of G1 other than the identity (the cofactor is 1, so every curve point is in G1)
+fn point_from_bytes(bytes: &[u8]) -> Option<G1Affine> {
+    if bytes.len() != POINT_LEN {
+        return None;
+    }
+    let modulus = BigUint::from(Fq::MODULUS);
+    let coordinate = |bytes: &[u8]| (BigUint::from_bytes_be(bytes) < modulus).then(|| Fq::from_be_bytes_mod_order(bytes));
+    let point = G1Affine::new_unchecked(coordinate(&bytes[..32])?, coordinate(&bytes[32..])?);
+    point.is_on_curve().then_some(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_recipient_opens_a_memo() {
+        let seed = NoteSeed::generate();
+        let viewing_key = ViewingKey::from_seed(&seed);
+        let public_key = MemoPublicKey::from_hex(&viewing_key.public_key().to_hex()).unwrap();
+        let note = crate::notes::random_note(1_000_000);
+
+        let memo = encrypt_note_for(&public_key, &note).unwrap();
+        assert_eq!(memo.len(), 2 * (1 + POINT_LEN + PLAINTEXT_LEN + 16));
+        assert_eq!(try_decrypt(&viewing_key, &memo), Some(note.clone()));
+        // Each memo has its own ephemeral key
+        assert_ne!(encrypt_note_for(&public_key, &note).unwrap(), memo);
+        assert_eq!(try_decrypt(&ViewingKey::random(), &memo), None);
+        assert_eq!(ViewingKey::from_seed(&seed), viewing_key);
+
+        let mut tampered = hex::decode(&memo).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(try_decrypt(&viewing_key, &hex::encode(tampered)), None);
+        assert!(MemoPublicKey::from_hex(&"01".repeat(64)).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+// Note scanning
+// Restores a wallet from its note seed: every synced deposit is tested against the notes the
+// seed derives (notes::NoteSeed), and the ones it opens are returned with their leaf index and
+// whether their nullifier was spent. A commitment binds the amount, which the Deposit event
+// doesn't carry, so it is read from the ERC20 transfer in the deposit's receipt; leaves inserted
+// by swaps and LP operations move no tokens in and are skipped. Derivation indices are tried up
+// to `gap_limit` past the last note found, so a wallet may leave that many unused in a row.
+
+use crate::blockchain::BlockchainClient;
+use crate::checkpoint::SyncCheckpoint;
+use crate::notes::{nullifier_hash, NoteSeed};
+use crate::provider::StarknetProvider;
+use crate::witness::{mask250, poseidon, NoteOpening};
+use num_bigint::BigUint;
+use serde::Serialize;
+use starknet::core::types::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Unused derivation indices tried past the last note found
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// A note of the wallet found in the deposit tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnedNote {
+    pub leaf_index: u32,
+    /// Index the note is derived at from the seed
+    pub note_index: u32,
+    pub amount: u128,
+    pub commitment: String,
+    pub secret: String,
+    pub nullifier: String,
+    pub spent: bool,
+}
+
+pub struct NoteScanner {
+    seed: NoteSeed,
+    gap_limit: u32,
+    /// Poseidon(secret, nullifier) of the notes derived so far, by index
+    inner_hashes: Vec<BigUint>,
+    /// One past the highest index found
+    used: u32,
+}
+
+impl NoteScanner {
+    pub fn new(seed: NoteSeed, gap_limit: u32) -> Self {
+        Self { seed, gap_limit: gap_limit.max(1), inner_hashes: Vec::new(), used: 0 }
+    }
+
+    /// The wallet's note `commitment` opens to, with `amount`, if any
+    pub fn try_open(&mut self, commitment: &BigUint, amount: u128) -> Option<(u32, NoteOpening)> {
+        let window = self.used + self.gap_limit;
+        while (self.inner_hashes.len() as u32) < window {
+            let note = self.seed.note(self.inner_hashes.len() as u32, 0);
+            self.inner_hashes.push(note.inner_hash());
+        }
+        let amount_field = BigUint::from(amount);
+        let index = (0..window).find(|index| &mask250(poseidon(&self.inner_hashes[*index as usize], &amount_field)) == commitment)?;
+        self.used = self.used.max(index + 1);
+        Some((index, self.seed.note(index, amount)))
+    }
+
+    /// Every note of the wallet among the deposits of `checkpoint`, amounts read from their receipts
+    pub async fn scan<P: StarknetProvider>(&mut self, checkpoint: &SyncCheckpoint, blockchain: &BlockchainClient<P>) -> Result<Vec<OwnedNote>, String> {
+        let spent: HashSet<FieldElement> = checkpoint.nullifiers.iter().copied().collect();
+        let mut amounts: HashMap<FieldElement, Option<u128>> = HashMap::new();
+        let mut owned = Vec::new();
+        let mut unknown_origin = 0;
+        for (leaf_index, commitment) in checkpoint.leaves.iter().enumerate() {
+            let Some(origin) = checkpoint.leaf_origins.get(leaf_index).copied().flatten() else {
+                unknown_origin += 1;
+                continue;
+            };
+            let amount = match amounts.get(&origin.transaction_hash) {
+                Some(amount) => *amount,
+                None => {
+                    let deposit = blockchain
+                        .deposit_of_tx(origin.transaction_hash)
+                        .await
+                        .map_err(|e| format!("Failed to read the receipt of 0x{:x}: {}", origin.transaction_hash, e))?;
+                    // u128 is the most a note holds
+                    let amount = deposit.and_then(|deposit| deposit.amount).filter(|amount| amount.high == 0).map(|amount| amount.low);
+                    *amounts.entry(origin.transaction_hash).or_insert(amount)
+                }
+            };
+            let Some(amount) = amount else { continue };
+            let commitment = BigUint::from_bytes_be(&commitment.to_bytes_be());
+            if let Some((note_index, note)) = self.try_open(&commitment, amount) {
+                let nullifier = FieldElement::from_byte_slice_be(&nullifier_hash(&note).to_bytes_be()).map_err(|e| e.to_string())?;
+                owned.push(OwnedNote {
+                    leaf_index: leaf_index as u32,
+                    note_index,
+                    amount,
+                    commitment: format!("0x{:x}", commitment),
+                    secret: crate::notes::to_hex(&note.secret),
+                    nullifier: crate::notes::to_hex(&note.nullifier),
+                    spent: spent.contains(&nullifier),
+                });
+            }
+        }
+        if unknown_origin > 0 {
+            eprintln!("[ASP] ⚠️  {} leaves have no known transaction and weren't scanned", unknown_origin);
+        }
+        Ok(owned)
+    }
+}
+
+/// `zylith-asp notes scan [--gap N] [--state FILE]`, the seed in NOTE_SEED; prints the owned notes as JSON
+pub async fn run(args: &[String]) -> Result<(), String> {
+    if args.first().map(String::as_str) != Some("scan") {
+        return Err("usage: NOTE_SEED=0x... zylith-asp notes scan [--gap N] [--state FILE]".to_string());
+    }
+    let mut gap_limit = DEFAULT_GAP_LIMIT;
+    let mut state_file = None;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--gap" => gap_limit = args.next().and_then(|gap| gap.parse().ok()).ok_or("--gap takes a number")?,
+            "--state" => state_file = args.next().cloned(),
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    // Not an argument, so it stays out of the shell history and the process list
+    let seed = NoteSeed::from_hex(&std::env::var("NOTE_SEED").map_err(|_| "NOTE_SEED is not set")?)?;
+
+    let checkpoint_store = state_file.map_or_else(crate::checkpoint::CheckpointStore::default, crate::checkpoint::CheckpointStore::new);
+    let checkpoint_store = match crate::at_rest::AtRestKey::from_env().map_err(|e| e.to_string())? {
+        Some(key) => checkpoint_store.with_encryption(key),
+        None => checkpoint_store,
+    };
+    let store = crate::state_store::StateBackend::from_env().open(&checkpoint_store).map_err(|e| format!("sync state: {}", e))?;
+    let checkpoint = store.load();
+    let rpc_url = std::env::var("RPC_URL").unwrap_or_else(|_| "https://api.cartridge.gg/x/starknet/sepolia".to_string());
+    let contract_address = crate::contracts::contract_addresses_from_env()[0].clone();
+    let blockchain = BlockchainClient::new(&rpc_url, &contract_address).map_err(|e| e.to_string())?;
+
+    eprintln!("[ASP] 🔎 Scanning {} leaves synced up to block {}...", checkpoint.leaves.len(), checkpoint.last_synced_block);
+    let owned = NoteScanner::new(seed, gap_limit).scan(&checkpoint, &blockchain).await?;
+    eprintln!("[ASP] ✅ {} notes found, {} unspent", owned.len(), owned.iter().filter(|note| !note.spent).count());
+    println!("{}", serde_json::to_string_pretty(&owned).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment_index::LeafOrigin;
+    use crate::events::deposit_selector;
+    use crate::mock_provider::MockProvider;
+    use crate::provider::TxReceipt;
+    use starknet::core::types::Event;
+    use starknet::core::utils::starknet_keccak;
+
+    #[tokio::test]
+    async fn test_finds_the_seed_notes_among_deposits() {
+        let zylith = FieldElement::from(0x2a11u64);
+        let seed = NoteSeed::generate();
+        let felt = |value: &BigUint| FieldElement::from_byte_slice_be(&value.to_bytes_be()).unwrap();
+        // Leaf 0: someone else's; leaf 1: note 0; leaf 2: a swap output; leaf 3: note 2 (1 unused), spent
+        let notes = [(crate::notes::random_note(70), 70u64), (seed.note(0, 500), 500), (seed.note(5, 9), 0), (seed.note(2, 42), 42)];
+        let mut checkpoint = SyncCheckpoint::at_block(0);
+        let mut provider = MockProvider::new();
+        for (leaf_index, (note, transferred)) in notes.iter().enumerate() {
+            let tx = FieldElement::from(0x7000 + leaf_index as u64);
+            let mut events = vec![Event {
+                from_address: zylith,
+                keys: vec![starknet_keccak("PrivacyEvent".as_bytes()), deposit_selector()],
+                data: vec![felt(&note.commitment()), FieldElement::from(leaf_index as u64), FieldElement::ONE],
+            }];
+            if *transferred > 0 {
+                events.insert(0, Event {
+                    from_address: FieldElement::from(0x70c3u64),
+                    keys: vec![starknet_keccak("Transfer".as_bytes()), FieldElement::from(0xa11ceu64), zylith],
+                    data: vec![FieldElement::from(*transferred), FieldElement::ZERO],
+                });
+            }
+            provider = provider.with_receipt(tx, TxReceipt { pending: false, reverted: false, events });
+            checkpoint.leaves.push(felt(&note.commitment()));
+            checkpoint.leaf_origins.push(Some(LeafOrigin { block_number: 10, transaction_hash: tx }));
+        }
+        checkpoint.nullifiers.push(felt(&nullifier_hash(&notes[3].0)));
+        let blockchain = BlockchainClient::with_provider(provider, zylith);
+
+        let owned = NoteScanner::new(seed.clone(), 2).scan(&checkpoint, &blockchain).await.unwrap();
+        assert_eq!(owned.iter().map(|note| (note.leaf_index, note.note_index, note.amount, note.spent)).collect::<Vec<_>>(), vec![(1, 0, 500, false), (3, 2, 42, true)]);
+        assert_eq!(owned[0].secret, crate::notes::to_hex(&seed.note(0, 500).secret));
+        // With no gap allowed past note 0, note 2 is out of reach
+        assert_eq!(NoteScanner::new(seed, 1).scan(&checkpoint, &blockchain).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+// Notes
+// A Zylith note is a secret, a nullifier and an amount. Its commitment, the leaf a deposit
+// inserts, is Mask(Poseidon(Poseidon(secret, nullifier), amount)), and spending it reveals the
+// nullifier, which the contract records as spent (witness::NoteOpening holds both). Notes are
+// random, or derived from a wallet seed so a wallet finds its notes again from the seed alone:
+// the n-th note takes Poseidon(seed, 2n) as secret and Poseidon(seed, 2n + 1) as nullifier.
+// Every value is kept below 2^248, canonical both in BN254, where the circuits hash, and as a felt.
+
+use crate::witness::{poseidon, NoteOpening};
+use num_bigint::BigUint;
+use num_traits::Num;
+use rand::Rng;
+
+/// Bits of secrets, nullifiers and seeds
+pub const NOTE_VALUE_BITS: u64 = 248;
+
+/// A uniformly random secret, nullifier or seed
+pub fn random_value() -> BigUint {
+    let mut bytes = [0u8; (NOTE_VALUE_BITS / 8) as usize];
+    rand::thread_rng().fill(&mut bytes[..]);
+    BigUint::from_bytes_be(&bytes)
+}
+
+/// A fresh note of `amount` with a random secret and nullifier
+pub fn random_note(amount: u128) -> NoteOpening {
+    NoteOpening { secret: random_value(), nullifier: random_value(), amount }
+}
+
+/// The value the circuits expose and the contract marks spent when `note` is spent
+pub fn nullifier_hash(note: &NoteOpening) -> BigUint {
+    crate::witness::reduce(&note.nullifier)
+}
+
+/// 0x and 64 hex digits, as /api/deposit/prepare returns secrets and nullifiers
+pub fn to_hex(value: &BigUint) -> String {
+    format!("0x{:064x}", value)
+}
+
+/// Seed of a wallet's notes
+#[derive(Clone, PartialEq, Eq)]
+pub struct NoteSeed(BigUint);
+
+impl NoteSeed {
+    pub fn generate() -> Self {
+        Self(random_value())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let seed = BigUint::from_str_radix(hex.trim().trim_start_matches("0x"), 16).map_err(|e| format!("Invalid note seed: {}", e))?;
+        if seed.bits() > NOTE_VALUE_BITS {
+            return Err(format!("A note seed has at most {} bits", NOTE_VALUE_BITS));
+        }
+        Ok(Self(seed))
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// The wallet's `index`-th note, holding `amount`
+    pub fn note(&self, index: u32, amount: u128) -> NoteOpening {
+        let derive = |n: u64| truncate(poseidon(&self.0, &BigUint::from(n)));
+        NoteOpening { secret: derive(2 * index as u64), nullifier: derive(2 * index as u64 + 1), amount }
+    }
+
+    /// Poseidon(seed, 2^64), past every note index: the scalar of the wallet's memo viewing key
+    pub fn viewing_scalar(&self) -> BigUint {
+        poseidon(&self.0, &(BigUint::from(1u8) << 64u32))
+    }
+}
+
+// Never printed: the seed spends every note derived from it
+impl std::fmt::Debug for NoteSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NoteSeed(..)")
+    }
+}
+
+fn truncate(value: BigUint) -> BigUint {
+    value & ((BigUint::from(1u8) << NOTE_VALUE_BITS) - 1u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_open_to_the_deposit_commitment() {
+        let note = random_note(1_000_000);
+        assert!(note.secret.bits() <= NOTE_VALUE_BITS && note.nullifier.bits() <= NOTE_VALUE_BITS);
+        let commitment = crate::commitment::generate_commitment(&to_hex(&note.secret), &to_hex(&note.nullifier), 1_000_000).unwrap();
+        assert_eq!(commitment, format!("0x{:x}", note.commitment()));
+        assert_eq!(nullifier_hash(&note), note.nullifier);
+
+        // Derived notes are the same from the same seed, and differ by index
+        let seed = NoteSeed::from_hex(&NoteSeed::generate().to_hex()).unwrap();
+        assert_eq!(seed.note(3, 5), seed.clone().note(3, 5));
+        assert_ne!(seed.note(3, 5).secret, seed.note(4, 5).secret);
+        assert_ne!(seed.note(3, 5).secret, seed.note(3, 5).nullifier);
+        assert!(seed.note(7, 5).nullifier.bits() <= NOTE_VALUE_BITS);
+        assert!(NoteSeed::from_hex(&format!("0x{}", "f".repeat(64))).is_err());
+        assert_eq!(format!("{:?}", seed), "NoteSeed(..)");
+    }
+}
@@ -0,0 +1,129 @@
+// Bounded LRU cache of is_nullifier_spent results
+// Spent is final: a nullifier can never become unspent, so `true` is cached until evicted.
+// Unspent can change at any block, so `false` is only trusted for a short TTL.
+
+use starknet::core::types::FieldElement;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_NULLIFIER_CACHE_CAPACITY: usize = 4096;
+pub const DEFAULT_UNSPENT_TTL: Duration = Duration::from_secs(10);
+
+struct CacheEntry {
+    spent: bool,
+    cached_at: Instant,
+    /// Recency stamp, key into `recency`
+    tick: u64,
+}
+
+pub struct NullifierCache {
+    capacity: usize,
+    unspent_ttl: Duration,
+    entries: HashMap<FieldElement, CacheEntry>,
+    /// tick -> nullifier, oldest first
+    recency: BTreeMap<u64, FieldElement>,
+    next_tick: u64,
+}
+
+impl NullifierCache {
+    /// A capacity of 0 disables caching
+    pub fn new(capacity: usize, unspent_ttl: Duration) -> Self {
+        Self {
+            capacity,
+            unspent_ttl,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    /// Cached spent status, if any and still valid
+    pub fn get(&mut self, nullifier: &FieldElement) -> Option<bool> {
+        let entry = self.entries.get(nullifier)?;
+        if !entry.spent && entry.cached_at.elapsed() >= self.unspent_ttl {
+            self.remove(nullifier);
+            return None;
+        }
+
+        let spent = entry.spent;
+        self.touch(nullifier);
+        Some(spent)
+    }
+
+    /// Record a fresh RPC result
+    pub fn insert(&mut self, nullifier: FieldElement, spent: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        // Never downgrade a spent nullifier, even if a lagging node says otherwise
+        let spent = spent || self.entries.get(&nullifier).is_some_and(|e| e.spent);
+
+        self.remove(&nullifier);
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else { break };
+            self.entries.remove(&oldest);
+        }
+
+        let tick = self.bump_tick();
+        self.recency.insert(tick, nullifier);
+        self.entries.insert(nullifier, CacheEntry { spent, cached_at: Instant::now(), tick });
+    }
+
+    /// Forget everything, spent nullifiers included (their transaction may have been reorged out)
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn touch(&mut self, nullifier: &FieldElement) {
+        let tick = self.bump_tick();
+        if let Some(entry) = self.entries.get_mut(nullifier) {
+            self.recency.remove(&entry.tick);
+            entry.tick = tick;
+            self.recency.insert(tick, *nullifier);
+        }
+    }
+
+    fn remove(&mut self, nullifier: &FieldElement) {
+        if let Some(entry) = self.entries.remove(nullifier) {
+            self.recency.remove(&entry.tick);
+        }
+    }
+
+    fn bump_tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = NullifierCache::new(2, DEFAULT_UNSPENT_TTL);
+        let (a, b, c) = (FieldElement::from(1u8), FieldElement::from(2u8), FieldElement::from(3u8));
+
+        cache.insert(a, true);
+        cache.insert(b, true);
+        // Touch a so b becomes the least recently used
+        assert_eq!(cache.get(&a), Some(true));
+        cache.insert(c, true);
+
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&a), Some(true));
+        assert_eq!(cache.get(&c), Some(true));
+    }
+
+    #[test]
+    fn test_spent_is_never_downgraded() {
+        let mut cache = NullifierCache::new(8, DEFAULT_UNSPENT_TTL);
+        let nullifier = FieldElement::from(7u8);
+
+        cache.insert(nullifier, true);
+        cache.insert(nullifier, false);
+        assert_eq!(cache.get(&nullifier), Some(true));
+    }
+}
@@ -0,0 +1,206 @@
+// Spent nullifier set as a sparse Merkle tree
+// Every felt252 nullifier has a fixed leaf: the one at index = nullifier in a tree of depth
+// 252. Spent nullifiers (from NullifierSpent events, emitted by withdrawals) hold 1, every
+// other leaf is empty. "Not spent yet" is then the membership path of an empty leaf, which
+// a circuit can check against the exported root. Only non-empty nodes are stored; empty
+// subtrees hash to precomputed values.
+
+use crate::hasher::Hasher;
+use crate::merkle::Bn254PoseidonHasher;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::FieldElement;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// One level per bit of a felt252
+pub const NULLIFIER_TREE_DEPTH: usize = 252;
+
+/// Path from the empty leaf of a nullifier to the root
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NonMembershipProof {
+    pub nullifier: String,
+    /// Value of the nullifier's leaf: the empty leaf, 0
+    pub leaf: String,
+    /// Sibling of each level, from the leaf up
+    pub path: Vec<String>,
+    /// Bits of the nullifier, lowest first: 0 where the path node is a left child, 1 otherwise
+    pub path_indices: Vec<u32>,
+    pub root: String,
+}
+
+pub struct NullifierTree {
+    depth: usize,
+    /// Non-empty nodes: nodes[(level, index)], level 0 = leaves
+    nodes: HashMap<(usize, BigUint), FieldElement>,
+    /// empty[level] = node of an empty subtree at `level`
+    empty: Vec<FieldElement>,
+    /// Spent nullifiers in the order they were seen
+    spent: Vec<FieldElement>,
+    hasher: Arc<dyn Hasher>,
+}
+
+impl Default for NullifierTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NullifierTree {
+    /// Tree of NULLIFIER_TREE_DEPTH hashed with BN254 Poseidon, like the deposit tree
+    pub fn new() -> Self {
+        Self::with_hasher(NULLIFIER_TREE_DEPTH, Arc::new(Bn254PoseidonHasher))
+    }
+
+    pub fn with_hasher(depth: usize, hasher: Arc<dyn Hasher>) -> Self {
+        let mut empty = vec![FieldElement::ZERO];
+        for level in 0..depth {
+            empty.push(hasher.hash_two(empty[level], empty[level]));
+        }
+        Self {
+            depth,
+            nodes: HashMap::new(),
+            empty,
+            spent: Vec::new(),
+            hasher,
+        }
+    }
+
+    /// Mark `nullifier` spent; false if it already was
+    pub fn insert(&mut self, nullifier: FieldElement) -> bool {
+        if self.contains(&nullifier) {
+            return false;
+        }
+        self.set_leaf(&nullifier, FieldElement::ONE);
+        self.spent.push(nullifier);
+        true
+    }
+
+    pub fn contains(&self, nullifier: &FieldElement) -> bool {
+        self.nodes.contains_key(&(0, leaf_index(nullifier)))
+    }
+
+    /// Forget the nullifiers spent after the first `len`, e.g. on a reorg
+    pub fn truncate(&mut self, len: usize) {
+        while self.spent.len() > len {
+            let nullifier = self.spent.pop().expect("longer than len");
+            self.set_leaf(&nullifier, FieldElement::ZERO);
+        }
+    }
+
+    /// Go back to exactly `nullifiers` (in order): rolled back if they are a prefix of the
+    /// spent ones, rebuilt otherwise
+    pub fn reset_to(&mut self, nullifiers: &[FieldElement]) {
+        if !self.spent.starts_with(nullifiers) {
+            self.truncate(0);
+            for nullifier in nullifiers {
+                self.insert(*nullifier);
+            }
+        }
+        self.truncate(nullifiers.len());
+    }
+
+    /// Spent nullifiers in the order they were seen
+    pub fn nullifiers(&self) -> &[FieldElement] {
+        &self.spent
+    }
+
+    pub fn spent_count(&self) -> usize {
+        self.spent.len()
+    }
+
+    pub fn root(&self) -> FieldElement {
+        self.node(self.depth, &BigUint::from(0u8))
+    }
+
+    /// Path of the empty leaf of `nullifier`; None if it is spent
+    pub fn prove_non_membership(&self, nullifier: &FieldElement) -> Option<NonMembershipProof> {
+        if self.contains(nullifier) {
+            return None;
+        }
+        let mut index = leaf_index(nullifier);
+        let mut path = Vec::with_capacity(self.depth);
+        let mut path_indices = Vec::with_capacity(self.depth);
+        for level in 0..self.depth {
+            let bit = index.bit(0);
+            path_indices.push(bit as u32);
+            path.push(format!("0x{:x}", self.node(level, &(index.clone() ^ BigUint::from(1u8)))));
+            index >>= 1;
+        }
+        Some(NonMembershipProof {
+            nullifier: format!("0x{:x}", nullifier),
+            leaf: format!("0x{:x}", self.empty[0]),
+            path,
+            path_indices,
+            root: format!("0x{:x}", self.root()),
+        })
+    }
+
+    fn node(&self, level: usize, index: &BigUint) -> FieldElement {
+        self.nodes.get(&(level, index.clone())).copied().unwrap_or(self.empty[level])
+    }
+
+    /// Set a leaf and rehash its path; nodes back to empty are dropped
+    fn set_leaf(&mut self, nullifier: &FieldElement, value: FieldElement) {
+        let mut index = leaf_index(nullifier);
+        let mut current = value;
+        for level in 0..=self.depth {
+            if current == self.empty[level] {
+                self.nodes.remove(&(level, index.clone()));
+            } else {
+                self.nodes.insert((level, index.clone()), current);
+            }
+            if level == self.depth {
+                break;
+            }
+            let sibling = self.node(level, &(index.clone() ^ BigUint::from(1u8)));
+            current = if index.bit(0) {
+                self.hasher.hash_two(sibling, current)
+            } else {
+                self.hasher.hash_two(current, sibling)
+            };
+            index >>= 1;
+        }
+    }
+}
+
+fn leaf_index(nullifier: &FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&nullifier.to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_of(proof: &NonMembershipProof, hasher: &dyn Hasher) -> String {
+        let mut current = FieldElement::from_hex_be(&proof.leaf).unwrap();
+        for (sibling, bit) in proof.path.iter().zip(&proof.path_indices) {
+            let sibling = FieldElement::from_hex_be(sibling).unwrap();
+            current = if *bit == 1 { hasher.hash_two(sibling, current) } else { hasher.hash_two(current, sibling) };
+        }
+        format!("0x{:x}", current)
+    }
+
+    #[test]
+    fn test_non_membership_proofs_follow_the_spent_set() {
+        let hasher = Bn254PoseidonHasher;
+        let mut tree = NullifierTree::new();
+        let empty_root = tree.root();
+        let spent = FieldElement::from_hex_be("0x3f1a").unwrap();
+        let unspent = FieldElement::from_hex_be("0x3f1b").unwrap();
+
+        assert!(tree.insert(spent));
+        assert!(!tree.insert(spent));
+        assert_ne!(tree.root(), empty_root);
+        assert!(tree.prove_non_membership(&spent).is_none());
+
+        let proof = tree.prove_non_membership(&unspent).unwrap();
+        assert_eq!(proof.path.len(), NULLIFIER_TREE_DEPTH);
+        assert_eq!(root_of(&proof, &hasher), format!("0x{:x}", tree.root()));
+
+        // Rolled back, the nullifier is provably unspent again
+        tree.truncate(0);
+        assert_eq!(tree.root(), empty_root);
+        assert_eq!(root_of(&tree.prove_non_membership(&spent).unwrap(), &hasher), format!("0x{:x}", empty_root));
+    }
+}
@@ -0,0 +1,164 @@
+// OpenAPI document
+// The REST handlers carry `utoipa::path` annotations and their request and response types
+// derive ToSchema, so the OpenAPI 3 document served at /openapi.json is generated from the
+// code it describes and wallet teams can generate clients from it. /docs renders it with
+// Swagger UI, loaded from a CDN so the build doesn't bundle its assets.
+
+use axum::response::Html;
+use axum::Json;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Zylith ASP", description = "Deposit tree, Merkle proofs, nullifiers and transaction preparation for Zylith"),
+    servers((url = "/v1", description = "Current API version; the unversioned paths are deprecated aliases")),
+    paths(
+        crate::get_deposit_proof,
+        crate::get_deposit_root,
+        crate::get_deposit_info,
+        crate::get_sync_status,
+        crate::get_deposit_index,
+        crate::get_deposit_leaf_info,
+        crate::get_deposit_path,
+        crate::get_batch_paths,
+        crate::get_root_history,
+        crate::get_deposit_proof_at_root,
+        crate::get_deposit_diff,
+        crate::get_deposit_index_for_tx,
+        crate::get_root_known,
+        crate::list_deposits,
+        crate::check_deposit_consistency,
+        crate::get_deposit_snapshot,
+        crate::get_deposit_snapshot_binary,
+        crate::list_contracts,
+        crate::get_contract_deposit_root,
+        crate::get_contract_deposit_proof,
+        crate::get_contract_sync_status,
+        crate::get_associated_proof,
+        crate::get_associated_root,
+        crate::get_associated_info,
+        crate::insert_associated,
+        crate::get_pool_root,
+        crate::check_pool_initialized,
+        crate::get_pool_info,
+        crate::get_pool_reserves,
+        crate::check_nullifier,
+        crate::get_nullifier_root,
+        crate::get_nullifier_non_membership_proof,
+        crate::get_token_balance,
+        crate::get_token_allowance,
+        crate::prepare_deposit,
+        crate::prepare_swap,
+        crate::prepare_withdraw,
+        crate::prepare_mint_liquidity,
+        crate::prepare_burn_liquidity,
+        crate::prepare_initialize,
+        crate::generate_swap_proof_endpoint,
+        crate::generate_lp_proof_endpoint,
+        crate::submit_swap_proof_job,
+        crate::submit_lp_proof_job,
+        crate::get_proof_job,
+        crate::proof_job_events,
+        crate::membership_witness_endpoint,
+        crate::health_check,
+        crate::liveness,
+        crate::readiness,
+        crate::rpc_metrics,
+        crate::admin::resync,
+        crate::admin::backfill,
+        crate::admin::prune,
+        crate::admin::stats,
+    ),
+    components(schemas(
+        crate::merkle::MerkleProof,
+        crate::response_signing::ResponseSignature,
+        crate::api_version::SyncStatusV1,
+        crate::sync_status::SyncHealth,
+        crate::tree_versions::TreeDiff,
+        crate::TreeInfo,
+        crate::BatchPathsRequest,
+        crate::BatchPathsResponse,
+        crate::InsertRequest,
+        crate::PrepareDepositRequest,
+        crate::PreparedTransaction,
+        crate::DepositPrepareResponse,
+        crate::NoteData,
+        crate::PrepareSwapRequest,
+        crate::SwapPrepareResponse,
+        crate::SwapProofRequest,
+        crate::LpProofRequest,
+        crate::MembershipWitnessRequest,
+        crate::PrepareWithdrawRequest,
+        crate::PrepareLiquidityRequest,
+        crate::PrepareInitializeRequest,
+        crate::admin::AdminResyncRequest,
+        crate::admin::AdminBackfillRequest,
+        crate::admin::AdminPruneRequest,
+    )),
+    modifiers(&ApiKeyScheme),
+    security(("api_key" = [])),
+)]
+struct ApiDoc;
+
+/// Routes served only when built with the in-process prover
+#[cfg(feature = "prover")]
+#[derive(OpenApi)]
+#[openapi(paths(crate::membership_proof_endpoint, crate::verify_proof_endpoint), components(schemas(crate::VerifyProofRequest)))]
+struct ProverDoc;
+
+/// The `x-api-key` header, required outside the open routes when API_KEYS is set, and the
+/// admin key the /admin routes take as a bearer token
+struct ApiKeyScheme;
+
+impl Modify for ApiKeyScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))));
+        components.add_security_scheme("admin_key", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()));
+    }
+}
+
+/// OpenAPI 3 document of the REST API
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    let document = ApiDoc::openapi();
+    #[cfg(feature = "prover")]
+    let document = {
+        let mut document = document;
+        document.merge(ProverDoc::openapi());
+        document
+    };
+    Json(document)
+}
+
+/// Swagger UI over /openapi.json
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(
+        r##"<!DOCTYPE html>
+<html>
+<head>
+  <title>Zylith ASP API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>"##,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_covers_the_api() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(document["paths"]["/deposit/proof/{index}"]["get"].is_object());
+        assert!(document["paths"]["/api/deposit/prepare"]["post"]["requestBody"].is_object());
+        assert!(document["components"]["schemas"]["MerkleProof"].is_object());
+        assert!(document["components"]["securitySchemes"]["api_key"].is_object());
+    }
+}
@@ -0,0 +1,190 @@
+// Adaptive event page size
+// A fixed starknet_getEvents page size either under-uses a fast provider or trips the limits
+// of a slow one. AdaptivePageSize starts at `initial`, doubles after pages that come back
+// quickly and halves on timeouts and rate limiting, always within `min..=max`. A node that
+// rejects the page size as too big also lowers the ceiling, so the size doesn't climb back.
+
+use crate::error::BlockchainError;
+use starknet::core::types::EventsPage;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageSizeBounds {
+    /// Never go below this many events per page
+    pub min: u64,
+    /// Size of the first page
+    pub initial: u64,
+    /// Never go above this many events per page
+    pub max: u64,
+    /// Pages answered faster than this grow the size
+    pub fast_page: Duration,
+}
+
+impl Default for PageSizeBounds {
+    fn default() -> Self {
+        Self {
+            min: 50,
+            initial: 1000,
+            max: 10_000,
+            fast_page: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PageSizeBounds {
+    /// Always `size` events per page
+    pub fn fixed(size: u64) -> Self {
+        Self {
+            min: size,
+            initial: size,
+            max: size,
+            ..Self::default()
+        }
+    }
+
+    /// Defaults, with EVENT_PAGE_MIN, EVENT_PAGE_SIZE (initial) and EVENT_PAGE_MAX overrides
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let var = |key: &str, default: u64| lookup(key).and_then(|v| v.parse().ok()).filter(|n| *n > 0).unwrap_or(default);
+        let min = var("EVENT_PAGE_MIN", defaults.min);
+        let max = var("EVENT_PAGE_MAX", defaults.max).max(min);
+        Self {
+            min,
+            initial: var("EVENT_PAGE_SIZE", defaults.initial).clamp(min, max),
+            max,
+            ..defaults
+        }
+    }
+}
+
+struct PageSizeState {
+    current: u64,
+    /// `max`, or lower once the node rejected a page size
+    ceiling: u64,
+}
+
+/// Event page size tuned by how the provider copes with the last pages
+/// One instance is shared by every scan of a client or syncer, so what it learns carries over.
+pub struct AdaptivePageSize {
+    bounds: PageSizeBounds,
+    state: Mutex<PageSizeState>,
+}
+
+impl Default for AdaptivePageSize {
+    fn default() -> Self {
+        Self::new(PageSizeBounds::default())
+    }
+}
+
+impl AdaptivePageSize {
+    pub fn new(bounds: PageSizeBounds) -> Self {
+        let max = bounds.max.max(bounds.min);
+        Self {
+            bounds,
+            state: Mutex::new(PageSizeState {
+                current: bounds.initial.clamp(bounds.min, max),
+                ceiling: max,
+            }),
+        }
+    }
+
+    /// Events to ask for in the next page
+    pub fn current(&self) -> u64 {
+        self.state.lock().unwrap().current
+    }
+
+    /// Fetch a page with `fetch(page_size)`, adjusting the size to how it went
+    pub async fn next_page<F, Fut>(&self, fetch: F) -> Result<EventsPage, BlockchainError>
+    where
+        F: FnOnce(u64) -> Fut,
+        Fut: Future<Output = Result<EventsPage, BlockchainError>>,
+    {
+        let started = Instant::now();
+        let result = fetch(self.current()).await;
+        match &result {
+            Ok(_) => self.record_success(started.elapsed()),
+            Err(e) => self.record_failure(e),
+        }
+        result
+    }
+
+    /// A page came back after `elapsed`
+    pub fn record_success(&self, elapsed: Duration) {
+        if elapsed < self.bounds.fast_page {
+            let mut state = self.state.lock().unwrap();
+            state.current = state.current.saturating_mul(2).min(state.ceiling);
+        }
+    }
+
+    /// A page failed with `error`
+    pub fn record_failure(&self, error: &BlockchainError) {
+        let mut state = self.state.lock().unwrap();
+        if error.is_page_too_big() {
+            state.ceiling = (state.current / 2).max(self.bounds.min);
+            state.current = state.ceiling;
+            println!("[ASP] 📉 Node rejected {} events per page, capping pages at {}", state.current * 2, state.ceiling);
+        } else if error.is_overload() {
+            state.current = (state.current / 2).max(self.bounds.min);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grows_when_fast_and_shrinks_under_load() {
+        let bounds = PageSizeBounds { min: 100, initial: 400, max: 1000, fast_page: Duration::from_millis(500) };
+        let pages = AdaptivePageSize::new(bounds);
+
+        pages.record_success(Duration::from_millis(10));
+        assert_eq!(pages.current(), 800);
+        pages.record_success(Duration::from_millis(10));
+        assert_eq!(pages.current(), 1000);
+        // Slow pages keep the size
+        pages.record_success(Duration::from_secs(2));
+        assert_eq!(pages.current(), 1000);
+
+        pages.record_failure(&BlockchainError::Timeout("event page".into()));
+        assert_eq!(pages.current(), 500);
+        pages.record_failure(&BlockchainError::Transport("HTTP 429 Too Many Requests".into()));
+        pages.record_failure(&BlockchainError::Transport("HTTP 429 Too Many Requests".into()));
+        assert_eq!(pages.current(), 125);
+        pages.record_failure(&BlockchainError::Timeout("event page".into()));
+        assert_eq!(pages.current(), 100);
+        // Other errors say nothing about the page size
+        pages.record_failure(&BlockchainError::Decode("bad page".into()));
+        assert_eq!(pages.current(), 100);
+    }
+
+    #[test]
+    fn test_rejected_size_caps_growth() {
+        let pages = AdaptivePageSize::new(PageSizeBounds { fast_page: Duration::from_secs(1), ..PageSizeBounds::default() });
+        pages.record_success(Duration::ZERO);
+        assert_eq!(pages.current(), 2000);
+
+        pages.record_failure(&BlockchainError::Transport("PageSizeTooBig".into()));
+        assert_eq!(pages.current(), 1000);
+        pages.record_success(Duration::ZERO);
+        assert_eq!(pages.current(), 1000);
+    }
+
+    #[test]
+    fn test_bounds_from_env() {
+        let env = |key: &str| match key {
+            "EVENT_PAGE_MIN" => Some("200".to_string()),
+            "EVENT_PAGE_SIZE" => Some("5".to_string()),
+            "EVENT_PAGE_MAX" => Some("oops".to_string()),
+            _ => None,
+        };
+        let bounds = PageSizeBounds::from_lookup(env);
+        assert_eq!((bounds.min, bounds.initial, bounds.max), (200, 200, PageSizeBounds::default().max));
+    }
+}
@@ -0,0 +1,132 @@
+// Reads scoped to a single block
+// get_merkle_root, is_root_known and the event scan each default to the latest block, so a
+// block landing between two of them yields a root that doesn't match the deposits, or a
+// "current" root that is already historical. A PinnedClient runs all of them at one block.
+
+use crate::blockchain::{BlockchainClient, RootStatus};
+use crate::error::BlockchainError;
+use crate::events::DepositEvent;
+use crate::merkle::MerkleTree;
+use crate::provider::{CallResult, StarknetProvider};
+use starknet::core::types::{BlockId, FunctionCall};
+
+/// View of a BlockchainClient where every read is evaluated at the same block
+/// Created by `BlockchainClient::at_block` or `BlockchainClient::pin_latest`
+pub struct PinnedClient<'a, P> {
+    client: &'a BlockchainClient<P>,
+    block: BlockId,
+}
+
+impl<'a, P: StarknetProvider> PinnedClient<'a, P> {
+    pub fn new(client: &'a BlockchainClient<P>, block: BlockId) -> Self {
+        Self { client, block }
+    }
+
+    /// Block every read is evaluated at
+    pub fn block(&self) -> BlockId {
+        self.block
+    }
+
+    /// Pinned block number, None when pinned to a tag or hash
+    pub fn block_number(&self) -> Option<u64> {
+        match self.block {
+            BlockId::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub async fn get_merkle_root(&self) -> Result<String, BlockchainError> {
+        self.client.get_merkle_root_at(self.block).await
+    }
+
+    pub async fn is_root_known(&self, root: &str) -> Result<bool, BlockchainError> {
+        self.client.is_root_known_at(root, self.block).await
+    }
+
+    pub async fn classify_root(&self, root: &str) -> Result<RootStatus, BlockchainError> {
+        self.client.classify_root_at(root, self.block).await
+    }
+
+    pub async fn is_nullifier_spent(&self, nullifier: &str) -> Result<bool, BlockchainError> {
+        self.client.is_nullifier_spent_at(nullifier, self.block).await
+    }
+
+    /// Token balance - returns (low, high) for u256
+    pub async fn get_token_balance(&self, token_address: &str, owner: &str) -> Result<(u128, u128), BlockchainError> {
+        self.client.get_token_balance_at(token_address, owner, self.block).await
+    }
+
+    pub async fn batch_call(&self, calls: Vec<FunctionCall>) -> Result<Vec<CallResult>, BlockchainError> {
+        self.client.batch_call_at(calls, self.block).await
+    }
+
+    pub async fn verify_local_root(&self, tree: &MerkleTree) -> Result<bool, BlockchainError> {
+        self.client.verify_local_root_at(tree, self.block).await
+    }
+
+    /// Deposit events up to and including the pinned block
+    /// Event filters need a block number: pin with `pin_latest` or `BlockId::Number`
+    pub async fn deposits(&self) -> Result<Vec<DepositEvent>, BlockchainError> {
+        let block_number = self.block_number().ok_or_else(|| {
+            BlockchainError::InvalidInput(format!("event scans need a block number, pinned to {:?}", self.block))
+        })?;
+        self.client.scan_deposits(block_number).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::{deposit_emitted_event, MockProvider};
+    use starknet::core::types::{BlockTag, FieldElement};
+
+    const ZYLITH_DEPLOYMENT_BLOCK: u64 = 4438440;
+
+    fn zylith_address() -> FieldElement {
+        FieldElement::from(0x2a1u64)
+    }
+
+    #[tokio::test]
+    async fn test_pin_latest_uses_one_block() {
+        let provider = MockProvider::new()
+            .with_block_number(ZYLITH_DEPLOYMENT_BLOCK + 1)
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::from(0xabcu64)])
+            .with_call_result(zylith_address(), "is_root_known", vec![FieldElement::ONE])
+            .with_events(vec![deposit_emitted_event(
+                zylith_address(),
+                FieldElement::from(1u64),
+                0,
+                FieldElement::from(0xabcu64),
+                ZYLITH_DEPLOYMENT_BLOCK + 1,
+            )]);
+        let client = BlockchainClient::with_provider(provider, zylith_address());
+
+        let pinned = client.pin_latest().await.unwrap();
+        // A deposit lands after the pin and must not show up in the pinned scan
+        client.provider().push_events(vec![deposit_emitted_event(
+            zylith_address(),
+            FieldElement::from(2u64),
+            1,
+            FieldElement::from(0xdefu64),
+            ZYLITH_DEPLOYMENT_BLOCK + 2,
+        )]);
+        client.provider().set_block_number(ZYLITH_DEPLOYMENT_BLOCK + 2);
+
+        assert_eq!(pinned.block_number(), Some(ZYLITH_DEPLOYMENT_BLOCK + 1));
+        assert_eq!(pinned.classify_root("0xabc").await.unwrap(), RootStatus::Current);
+        assert_eq!(pinned.deposits().await.unwrap().len(), 1);
+        assert!(client
+            .provider()
+            .call_blocks()
+            .iter()
+            .all(|block| matches!(block, BlockId::Number(n) if *n == ZYLITH_DEPLOYMENT_BLOCK + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_deposits_need_a_block_number() {
+        let client = BlockchainClient::with_provider(MockProvider::new(), zylith_address());
+
+        let pinned = client.at_block(BlockId::Tag(BlockTag::Latest));
+        assert!(matches!(pinned.deposits().await, Err(BlockchainError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,532 @@
+// PostgreSQL sync state
+// For several API replicas sharing one state: the tables of the SQLite backend, keyed by the
+// name of the checkpoint (one per synced contract) so contracts share a database. Any
+// replica loads the state; only the one holding the session advisory lock of the checkpoint
+// runs the syncer, the others follow what it saves until the lock is released (the holder
+// exits or loses its connection). Like SqliteStore, requests are run by a thread of its own.
+
+use crate::checkpoint::{BlockMark, SyncCheckpoint};
+use crate::commitment_index::LeafOrigin;
+use crate::migrations::{self, Direction, Migration};
+use crate::state_store::{StateStore, StoredLeaf};
+use sqlx::postgres::{PgConnection, PgPool, PgPoolOptions, PgRow};
+use sqlx::{Connection, Row};
+use starknet::core::types::FieldElement;
+use std::io;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Schema versions, recorded in `asp_schema_migrations`
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "sync state, block marks, leaves and nullifiers",
+    up: &[
+        "CREATE TABLE IF NOT EXISTS asp_sync_state (
+            state_key TEXT PRIMARY KEY,
+            last_synced_block BIGINT NOT NULL,
+            last_leaf_index BIGINT,
+            scan_to_block BIGINT,
+            continuation_token TEXT,
+            applied_events TEXT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS asp_block_marks (
+            state_key TEXT NOT NULL,
+            number BIGINT NOT NULL,
+            hash TEXT NOT NULL,
+            leaf_count BIGINT NOT NULL,
+            nullifier_count BIGINT NOT NULL,
+            PRIMARY KEY (state_key, number)
+        )",
+        "CREATE TABLE IF NOT EXISTS asp_leaves (
+            state_key TEXT NOT NULL,
+            leaf_index BIGINT NOT NULL,
+            commitment TEXT NOT NULL,
+            block_number BIGINT,
+            transaction_hash TEXT,
+            PRIMARY KEY (state_key, leaf_index)
+        )",
+        "CREATE INDEX IF NOT EXISTS asp_leaves_by_commitment ON asp_leaves (state_key, commitment)",
+        "CREATE TABLE IF NOT EXISTS asp_nullifiers (
+            state_key TEXT NOT NULL,
+            position BIGINT NOT NULL,
+            nullifier TEXT NOT NULL,
+            PRIMARY KEY (state_key, position)
+        )",
+    ],
+    down: &[
+        "DROP TABLE IF EXISTS asp_nullifiers",
+        "DROP TABLE IF EXISTS asp_leaves",
+        "DROP TABLE IF EXISTS asp_block_marks",
+        "DROP TABLE IF EXISTS asp_sync_state",
+    ],
+}];
+
+enum Request {
+    Load(mpsc::Sender<io::Result<Option<SyncCheckpoint>>>),
+    Save(Box<SyncCheckpoint>, mpsc::Sender<io::Result<()>>),
+    Leaves(u32, u32, mpsc::Sender<io::Result<Vec<StoredLeaf>>>),
+    Maintain(mpsc::Sender<io::Result<()>>),
+    Lock(mpsc::Sender<io::Result<bool>>),
+}
+
+/// Sync state in a PostgreSQL database shared by several instances
+pub struct PostgresStore {
+    state_key: String,
+    requests: Mutex<mpsc::Sender<Request>>,
+}
+
+impl PostgresStore {
+    /// Connect to the database at `url` (creating the tables) for the state named `state_key`
+    pub fn open(url: &str, state_key: impl Into<String>) -> io::Result<Self> {
+        let state_key = state_key.into();
+        let (requests, inbox) = mpsc::channel();
+        let (ready, opened) = mpsc::channel();
+        let (url, key) = (url.to_string(), state_key.clone());
+        thread::Builder::new().name("asp-postgres".to_string()).spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let mut db = match runtime.block_on(Database::connect(&url, key)) {
+                Ok(db) => {
+                    let _ = ready.send(Ok(()));
+                    db
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            for request in inbox {
+                match request {
+                    Request::Load(reply) => {
+                        let _ = reply.send(runtime.block_on(db.load()));
+                    }
+                    Request::Save(checkpoint, reply) => {
+                        let _ = reply.send(runtime.block_on(db.save(&checkpoint)));
+                    }
+                    Request::Leaves(from, to, reply) => {
+                        let _ = reply.send(runtime.block_on(db.leaves(from, to)));
+                    }
+                    Request::Maintain(reply) => {
+                        let _ = reply.send(runtime.block_on(db.maintain()));
+                    }
+                    Request::Lock(reply) => {
+                        let _ = reply.send(runtime.block_on(db.try_lock()));
+                    }
+                }
+            }
+        })?;
+        opened.recv().map_err(|_| closed())??;
+        Ok(Self {
+            state_key,
+            requests: Mutex::new(requests),
+        })
+    }
+
+    pub fn state_key(&self) -> &str {
+        &self.state_key
+    }
+
+    /// Saved checkpoint, None if nothing was saved yet
+    pub fn load_saved(&self) -> io::Result<Option<SyncCheckpoint>> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Load(reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+}
+
+impl StateStore for PostgresStore {
+    fn load(&self) -> SyncCheckpoint {
+        match self.load_saved() {
+            Ok(checkpoint) => checkpoint.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read state {}: {}", self.state_key, e);
+                SyncCheckpoint::default()
+            }
+        }
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let (reply, response) = mpsc::channel();
+        self.requests
+            .lock()
+            .unwrap()
+            .send(Request::Save(Box::new(checkpoint.clone()), reply))
+            .map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Leaves(from, to, reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+
+    fn maintain(&self) -> io::Result<()> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Maintain(reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+
+    fn try_lock_sync(&self) -> io::Result<bool> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Lock(reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+}
+
+/// Connections and the rows as of the last save or load
+struct Database {
+    pool: PgPool,
+    state_key: String,
+    /// Connection holding the advisory lock, once taken; the lock goes with it
+    lock: Option<PgConnection>,
+    leaves: Vec<(FieldElement, Option<LeafOrigin>)>,
+    nullifiers: Vec<FieldElement>,
+}
+
+impl Database {
+    async fn connect(url: &str, state_key: String) -> io::Result<Self> {
+        let pool = PgPoolOptions::new().max_connections(2).connect(url).await.map_err(db_error)?;
+        migrate(&pool).await?;
+        let mut db = Self {
+            pool,
+            state_key,
+            lock: None,
+            leaves: Vec::new(),
+            nullifiers: Vec::new(),
+        };
+        db.load().await?;
+        Ok(db)
+    }
+
+    /// Take the advisory lock of the state on a connection kept out of the pool
+    async fn try_lock(&mut self) -> io::Result<bool> {
+        if let Some(connection) = self.lock.as_mut() {
+            if connection.ping().await.is_ok() {
+                return Ok(true);
+            }
+            // The lock went with the connection: another instance may be syncing now
+            self.lock = None;
+        }
+        let mut connection = self.pool.acquire().await.map_err(db_error)?.detach();
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtextextended($1, 0))")
+            .bind(&self.state_key)
+            .fetch_one(&mut connection)
+            .await
+            .map_err(db_error)?;
+        if locked {
+            self.lock = Some(connection);
+        }
+        Ok(locked)
+    }
+
+    async fn load(&mut self) -> io::Result<Option<SyncCheckpoint>> {
+        let Some(state) = sqlx::query(
+            "SELECT last_synced_block, last_leaf_index, scan_to_block, continuation_token, applied_events
+             FROM asp_sync_state WHERE state_key = $1",
+        )
+        .bind(&self.state_key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        else {
+            return Ok(None);
+        };
+
+        let mut recent_blocks = Vec::new();
+        for row in sqlx::query("SELECT number, hash, leaf_count, nullifier_count FROM asp_block_marks WHERE state_key = $1 ORDER BY number")
+            .bind(&self.state_key)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            recent_blocks.push(BlockMark {
+                number: row.try_get::<i64, _>("number").map_err(db_error)? as u64,
+                hash: parse_felt(&row.try_get::<String, _>("hash").map_err(db_error)?)?,
+                leaf_count: row.try_get::<i64, _>("leaf_count").map_err(db_error)? as u32,
+                nullifier_count: row.try_get::<i64, _>("nullifier_count").map_err(db_error)? as u32,
+            });
+        }
+
+        let mut leaves = Vec::new();
+        for row in sqlx::query("SELECT commitment, block_number, transaction_hash FROM asp_leaves WHERE state_key = $1 ORDER BY leaf_index")
+            .bind(&self.state_key)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            let commitment = parse_felt(&row.try_get::<String, _>("commitment").map_err(db_error)?)?;
+            leaves.push((commitment, parse_origin(&row)?));
+        }
+
+        let mut nullifiers = Vec::new();
+        for row in sqlx::query("SELECT nullifier FROM asp_nullifiers WHERE state_key = $1 ORDER BY position")
+            .bind(&self.state_key)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            nullifiers.push(parse_felt(&row.try_get::<String, _>("nullifier").map_err(db_error)?)?);
+        }
+
+        let applied_events = state.try_get::<String, _>("applied_events").map_err(db_error)?;
+        let checkpoint = SyncCheckpoint {
+            last_synced_block: state.try_get::<i64, _>("last_synced_block").map_err(db_error)? as u64,
+            last_leaf_index: state.try_get::<Option<i64>, _>("last_leaf_index").map_err(db_error)?.map(|index| index as u32),
+            scan_to_block: state.try_get::<Option<i64>, _>("scan_to_block").map_err(db_error)?.map(|block| block as u64),
+            continuation_token: state.try_get::<Option<String>, _>("continuation_token").map_err(db_error)?,
+            leaves: leaves.iter().map(|(leaf, _)| *leaf).collect(),
+            leaf_origins: leaves.iter().map(|(_, origin)| *origin).collect(),
+            recent_blocks,
+            applied_events: serde_json::from_str(&applied_events)?,
+            nullifiers: nullifiers.clone(),
+        };
+        self.leaves = leaves;
+        self.nullifiers = nullifiers;
+        Ok(Some(checkpoint))
+    }
+
+    async fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        let mut leaves = Vec::new();
+        for row in sqlx::query(
+            "SELECT leaf_index, commitment, block_number, transaction_hash FROM asp_leaves
+             WHERE state_key = $1 AND leaf_index >= $2 AND leaf_index < $3 ORDER BY leaf_index",
+        )
+        .bind(&self.state_key)
+        .bind(i64::from(from))
+        .bind(i64::from(to))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        {
+            leaves.push(StoredLeaf {
+                index: row.try_get::<i64, _>("leaf_index").map_err(db_error)? as u32,
+                commitment: parse_felt(&row.try_get::<String, _>("commitment").map_err(db_error)?)?,
+                origin: parse_origin(&row)?,
+            });
+        }
+        Ok(leaves)
+    }
+
+    /// Vacuum and reindex the state tables; only from the syncing instance, so replicas
+    /// don't all do it at once
+    async fn maintain(&self) -> io::Result<()> {
+        if self.lock.is_none() {
+            return Ok(());
+        }
+        for table in ["asp_sync_state", "asp_block_marks", "asp_leaves", "asp_nullifiers"] {
+            sqlx::query(&format!("VACUUM (ANALYZE) {}", table)).execute(&self.pool).await.map_err(db_error)?;
+            sqlx::query(&format!("REINDEX TABLE CONCURRENTLY {}", table)).execute(&self.pool).await.map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    async fn save(&mut self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let leaves: Vec<(FieldElement, Option<LeafOrigin>)> = checkpoint
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (*leaf, checkpoint.leaf_origins.get(index).copied().flatten()))
+            .collect();
+        if self.lock.is_none() {
+            // Without the lock the syncing instance may have written since our last load
+            self.leaves.clear();
+            self.nullifiers.clear();
+        }
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(
+            "INSERT INTO asp_sync_state (state_key, last_synced_block, last_leaf_index, scan_to_block, continuation_token, applied_events)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (state_key) DO UPDATE SET
+                last_synced_block = EXCLUDED.last_synced_block,
+                last_leaf_index = EXCLUDED.last_leaf_index,
+                scan_to_block = EXCLUDED.scan_to_block,
+                continuation_token = EXCLUDED.continuation_token,
+                applied_events = EXCLUDED.applied_events",
+        )
+        .bind(&self.state_key)
+        .bind(checkpoint.last_synced_block as i64)
+        .bind(checkpoint.last_leaf_index.map(i64::from))
+        .bind(checkpoint.scan_to_block.map(|block| block as i64))
+        .bind(checkpoint.continuation_token.as_deref())
+        .bind(serde_json::to_string(&checkpoint.applied_events)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        // At most REORG_WINDOW marks: rewritten whole
+        sqlx::query("DELETE FROM asp_block_marks WHERE state_key = $1")
+            .bind(&self.state_key)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for mark in &checkpoint.recent_blocks {
+            sqlx::query("INSERT INTO asp_block_marks (state_key, number, hash, leaf_count, nullifier_count) VALUES ($1, $2, $3, $4, $5)")
+                .bind(&self.state_key)
+                .bind(mark.number as i64)
+                .bind(format!("0x{:x}", mark.hash))
+                .bind(i64::from(mark.leaf_count))
+                .bind(i64::from(mark.nullifier_count))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+
+        sqlx::query("DELETE FROM asp_leaves WHERE state_key = $1 AND leaf_index >= $2")
+            .bind(&self.state_key)
+            .bind(leaves.len() as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for (index, (leaf, origin)) in leaves.iter().enumerate() {
+            if self.leaves.get(index) == Some(&(*leaf, *origin)) {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO asp_leaves (state_key, leaf_index, commitment, block_number, transaction_hash) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (state_key, leaf_index) DO UPDATE SET
+                    commitment = EXCLUDED.commitment,
+                    block_number = EXCLUDED.block_number,
+                    transaction_hash = EXCLUDED.transaction_hash",
+            )
+            .bind(&self.state_key)
+            .bind(index as i64)
+            .bind(format!("0x{:x}", leaf))
+            .bind(origin.map(|origin| origin.block_number as i64))
+            .bind(origin.map(|origin| format!("0x{:x}", origin.transaction_hash)))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+
+        sqlx::query("DELETE FROM asp_nullifiers WHERE state_key = $1 AND position >= $2")
+            .bind(&self.state_key)
+            .bind(checkpoint.nullifiers.len() as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for (position, nullifier) in checkpoint.nullifiers.iter().enumerate() {
+            if self.nullifiers.get(position) == Some(nullifier) {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO asp_nullifiers (state_key, position, nullifier) VALUES ($1, $2, $3)
+                 ON CONFLICT (state_key, position) DO UPDATE SET nullifier = EXCLUDED.nullifier",
+            )
+            .bind(&self.state_key)
+            .bind(position as i64)
+            .bind(format!("0x{:x}", nullifier))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+
+        tx.commit().await.map_err(db_error)?;
+        self.leaves = leaves;
+        self.nullifiers = checkpoint.nullifiers.clone();
+        Ok(())
+    }
+}
+
+fn parse_felt(hex: &str) -> io::Result<FieldElement> {
+    FieldElement::from_hex_be(hex).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid felt '{}': {}", hex, e)))
+}
+
+/// Bring the schema to the target version in one transaction, under a lock so replicas
+/// starting together don't migrate twice
+async fn migrate(pool: &PgPool) -> io::Result<()> {
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('asp_schema_migrations', 0))")
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS asp_schema_migrations (version BIGINT PRIMARY KEY, description TEXT NOT NULL)")
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let current: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM asp_schema_migrations")
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let target = migrations::target_version(MIGRATIONS)?;
+    for (migration, direction) in migrations::plan(MIGRATIONS, current as u32, target)? {
+        for statement in migration.statements(direction) {
+            sqlx::query(statement).execute(&mut *tx).await.map_err(db_error)?;
+        }
+        let record = match direction {
+            Direction::Up => sqlx::query("INSERT INTO asp_schema_migrations (version, description) VALUES ($1, $2)")
+                .bind(i64::from(migration.version))
+                .bind(migration.description),
+            Direction::Down => sqlx::query("DELETE FROM asp_schema_migrations WHERE version = $1").bind(i64::from(migration.version)),
+        };
+        record.execute(&mut *tx).await.map_err(db_error)?;
+        println!("[ASP] 🗄️  PostgreSQL schema {:?} to version {} ({})", direction, migration.version, migration.description);
+    }
+    tx.commit().await.map_err(db_error)?;
+    migrations::check_downgrade(MIGRATIONS, target)
+}
+
+/// Deposit event of a leaf row, if it was recorded
+fn parse_origin(row: &PgRow) -> io::Result<Option<LeafOrigin>> {
+    let block_number = row.try_get::<Option<i64>, _>("block_number").map_err(db_error)?;
+    match (block_number, row.try_get::<Option<String>, _>("transaction_hash").map_err(db_error)?) {
+        (Some(block_number), Some(transaction_hash)) => Ok(Some(LeafOrigin {
+            block_number: block_number as u64,
+            transaction_hash: parse_felt(&transaction_hash)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+fn db_error(e: sqlx::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "PostgreSQL state thread stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedupe::EventDeduper;
+
+    /// Needs a server: TEST_DATABASE_URL=postgres://... cargo test --features postgres
+    #[test]
+    fn test_postgres_shared_state_and_sync_lock() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let key = format!("asp_postgres_store_test_{}", std::process::id());
+        let leader = PostgresStore::open(&url, key.clone()).unwrap();
+        let follower = PostgresStore::open(&url, key).unwrap();
+        assert!(leader.try_lock_sync().unwrap());
+        assert!(!follower.try_lock_sync().unwrap());
+
+        let checkpoint = SyncCheckpoint {
+            last_synced_block: 20,
+            last_leaf_index: Some(1),
+            scan_to_block: None,
+            continuation_token: None,
+            leaves: vec![FieldElement::from(1000u64), FieldElement::from(1001u64)],
+            leaf_origins: vec![Some(LeafOrigin { block_number: 12, transaction_hash: FieldElement::from(0xabcu64) }), None],
+            recent_blocks: vec![BlockMark { number: 15, hash: FieldElement::from(0x15u64), leaf_count: 1, nullifier_count: 0 }],
+            applied_events: EventDeduper::default(),
+            nullifiers: vec![FieldElement::from(0x3f1au64)],
+        };
+        leader.save(&checkpoint).unwrap();
+        assert_eq!(follower.load(), checkpoint);
+
+        // The lock is released with the leader's connection
+        drop(leader);
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(follower.try_lock_sync().unwrap());
+        follower.save(&SyncCheckpoint::default()).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 // ZK Proof generation using Circom/snarkjs
 // This module will execute Circom circuits to generate proofs
 
+use crate::artifacts::{ArtifactManager, CircuitArtifacts};
+use crate::circuit_inputs::SwapInputs;
+use crate::prover_backend::ProverBackend;
 use std::path::Path;
 use std::fs;
 use serde_json;
@@ -8,12 +11,17 @@ use tokio::process::Command;
 
 /// Generate swap proof using rapidsnark (fast) with correct format conversion
 pub async fn generate_swap_proof(
-    circuits_path: &str,
+    artifacts: &ArtifactManager,
+    backend: ProverBackend,
     input_json: serde_json::Value,
 ) -> Result<SwapProof, String> {
     println!("[Proof] 🔄 Starting swap proof generation with rapidsnark...");
     let start_time = std::time::Instant::now();
     
+    // Inputs breaking a circuit constraint fail here with the reason, before any prover runs
+    let inputs = SwapInputs::from_input_json(&input_json)?;
+    inputs.check()?;
+    
     // Create temporary files
     let temp_dir = std::env::temp_dir();
     let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
@@ -28,181 +36,32 @@ pub async fn generate_swap_proof(
     
     println!("[Proof] 📝 Input file created: {:?}", input_file);
     
-    // Paths to circuit files
-    let circuits_dir = Path::new(circuits_path).canonicalize()
+    // Circuit files, present and matching their pinned hashes
+    let files = artifacts.circuit("swap").await?;
+    let circuits_dir = artifacts.circuits_dir().canonicalize()
         .map_err(|e| format!("Failed to canonicalize circuits path: {}", e))?;
-    let wasm_path = circuits_dir.join("build").join("swap").join("swap_js").join("swap.wasm");
-    let zkey_path = circuits_dir.join("build").join("zkeys").join("swap.zkey");
-    
-    // Check for rapidsnark binary
-    let asp_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
-    let rapidsnark_path = asp_dir.join("bin").join("prover");
-    let use_rapidsnark = rapidsnark_path.exists();
-    
-    if !wasm_path.exists() {
-        return Err(format!("WASM file not found: {:?}", wasm_path));
-    }
-    if !zkey_path.exists() {
-        return Err(format!("ZKey file not found: {:?}", zkey_path));
-    }
-    
-    // Step 1: Calculate witness using snarkjs (this is fast)
-    println!("[Proof] 🔧 Step 1: Calculating witness with snarkjs...");
-    let witness_script = format!(
-        r#"
-        const snarkjs = require('snarkjs');
-        const fs = require('fs');
-        const path = require('path');
-        
-        (async () => {{
-            try {{
-                const input = JSON.parse(fs.readFileSync('{}', 'utf8'));
-                const wasmPath = path.resolve('{}');
-                
-                console.log('Calculating witness...');
-                const startTime = Date.now();
-                
-                const {{ wtns }} = await snarkjs;
-                await wtns.calculate(input, wasmPath, '{}');
-                
-                const elapsed = ((Date.now() - startTime) / 1000).toFixed(2);
-                console.log('Witness calculated in', elapsed, 'seconds');
-            }} catch (error) {{
-                console.error('Error:', error.message);
-                console.error('Stack:', error.stack);
-                process.exit(1);
-            }}
-        }})();
-        "#,
-        input_file.to_str().unwrap().replace('\\', "/"),
-        wasm_path.to_str().unwrap().replace('\\', "/"),
-        witness_file.to_str().unwrap().replace('\\', "/")
-    );
-    
-    let script_file = circuits_dir.join(format!("witness_script_{}.js", timestamp));
-    fs::write(&script_file, witness_script)
-        .map_err(|e| format!("Failed to write witness script: {}", e))?;
-    
-    let witness_start = std::time::Instant::now();
-    let witness_output = Command::new("node")
-        .env("NODE_OPTIONS", "--max-old-space-size=4096")
-        .arg(script_file.file_name().unwrap())
-        .current_dir(&circuits_dir)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run witness calculation: {}", e))?;
-    
-    let _ = fs::remove_file(&script_file);
-    
-    if !witness_output.status.success() {
-        let stderr = String::from_utf8_lossy(&witness_output.stderr);
-        let stdout = String::from_utf8_lossy(&witness_output.stdout);
-        let _ = fs::remove_file(&input_file);
-        return Err(format!("Witness calculation failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
-    }
-    
-    println!("[Proof] ✅ Witness calculated in {:.2}s", witness_start.elapsed().as_secs_f64());
-    
-    // Step 2: Generate proof (use rapidsnark if available, otherwise snarkjs)
-    if use_rapidsnark {
-        println!("[Proof] 🔧 Step 2: Generating proof with rapidsnark (fast C++ prover)...");
-        let proof_start = std::time::Instant::now();
-        
-        let rapidsnark_output = Command::new(&rapidsnark_path)
-            .arg(&zkey_path)
-            .arg(&witness_file)
-            .arg(&proof_file)
-            .arg(&public_file)
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run rapidsnark: {}", e))?;
-        
-        if !rapidsnark_output.status.success() {
-            let stderr = String::from_utf8_lossy(&rapidsnark_output.stderr);
-            let stdout = String::from_utf8_lossy(&rapidsnark_output.stdout);
-            let _ = fs::remove_file(&input_file);
-            let _ = fs::remove_file(&witness_file);
-            return Err(format!("rapidsnark failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
-        }
-        
-        println!("[Proof] ✅ Proof generated with rapidsnark in {:.2}s", proof_start.elapsed().as_secs_f64());
-    } else {
-        println!("[Proof] 🔧 Step 2: Generating proof with snarkjs (fallback)...");
-        let proof_script = format!(
-            r#"
-            const snarkjs = require('snarkjs');
-            const fs = require('fs');
-            
-            (async () => {{
-                try {{
-                    console.log('Generating proof...');
-                    const startTime = Date.now();
-                    
-                    const {{ proof, publicSignals }} = await snarkjs.groth16.prove(
-                        '{}',
-                        '{}'
-                    );
-                    
-                    const elapsed = ((Date.now() - startTime) / 1000).toFixed(2);
-                    console.log('Proof generated in', elapsed, 'seconds');
-                    
-                    fs.writeFileSync('{}', JSON.stringify(proof, null, 2));
-                    fs.writeFileSync('{}', JSON.stringify(publicSignals, null, 2));
-                }} catch (error) {{
-                    console.error('Error:', error.message);
-                    process.exit(1);
-                }}
-            }})();
-            "#,
-            zkey_path.to_str().unwrap().replace('\\', "/"),
-            witness_file.to_str().unwrap().replace('\\', "/"),
-            proof_file.to_str().unwrap().replace('\\', "/"),
-            public_file.to_str().unwrap().replace('\\', "/")
-        );
-        
-        let script_file2 = circuits_dir.join(format!("proof_script_{}.js", timestamp));
-        fs::write(&script_file2, proof_script)
-            .map_err(|e| format!("Failed to write proof script: {}", e))?;
-        
-        let proof_start = std::time::Instant::now();
-        let mut child = Command::new("node")
-            .env("NODE_OPTIONS", "--max-old-space-size=8192")
-            .arg(script_file2.file_name().unwrap())
-            .current_dir(&circuits_dir)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| format!("Failed to spawn node: {}", e))?;
-        
-        // Wait with progress updates
-        let mut last_log = std::time::Instant::now();
-        let output = loop {
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    let output = child.wait_with_output().await
-                        .map_err(|e| format!("Failed to get output: {}", e))?;
-                    break output;
-                }
-                Ok(None) => {
-                    if last_log.elapsed().as_secs() >= 30 {
-                        println!("[Proof] ⏳ Still processing... ({}s elapsed)", proof_start.elapsed().as_secs());
-                        last_log = std::time::Instant::now();
-                    }
-                    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-                }
-                Err(e) => return Err(format!("Error waiting: {}", e)),
+    let wasm_path = files.wasm.canonicalize()
+        .map_err(|e| format!("Failed to canonicalize {:?}: {}", files.wasm, e))?;
+    let zkey_path = files.zkey.canonicalize()
+        .map_err(|e| format!("Failed to canonicalize {:?}: {}", files.zkey, e))?;
+    
+    // Steps 1-2: witness and proof with the configured backend, written to proof.json/public.json
+    let proved = match backend {
+        ProverBackend::Arkworks => prove_swap_in_process(&files, inputs, &proof_file, &public_file).await,
+        ProverBackend::Rapidsnark | ProverBackend::Snarkjs => async {
+            // Step 1: Calculate witness using snarkjs (this is fast)
+            calculate_witness_with_snarkjs(&circuits_dir, &wasm_path, &input_file, &witness_file).await?;
+            // Step 2: Generate proof with rapidsnark or, much slower, snarkjs
+            match backend {
+                ProverBackend::Rapidsnark => prove_with_rapidsnark(&zkey_path, &witness_file, &proof_file, &public_file).await,
+                _ => prove_with_snarkjs(&circuits_dir, &zkey_path, &witness_file, &proof_file, &public_file).await,
             }
-        };
-        
-        let _ = fs::remove_file(&script_file2);
-        
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            return Err(format!("snarkjs proof failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
-        }
-        
-        println!("[Proof] ✅ Proof generated with snarkjs in {:.2}s", proof_start.elapsed().as_secs_f64());
+        }.await.map(|_| ()),
+    };
+    if let Err(e) = proved {
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&witness_file);
+        return Err(e);
     }
     
     // Step 3: Add protocol field to proof (required by convert_garaga.py script)
@@ -424,8 +283,8 @@ pub async fn generate_swap_proof(
     let _ = fs::remove_file(&public_file);
     
     let elapsed = start_time.elapsed().as_secs_f64();
-    println!("[Proof] ✅ Total proof time: {:.2}s ({})", elapsed, 
-        if use_rapidsnark { "with rapidsnark" } else { "with snarkjs" });
+    println!("[Proof] ✅ Total proof time: {:.2}s (with {})", elapsed, 
+        backend);
     
     Ok(SwapProof {
         proof: proof_calldata, // Only the 8 proof elements, not combined with public inputs
@@ -544,7 +403,7 @@ fn normalize_proof_for_garaga(proof: serde_json::Value) -> Result<serde_json::Va
 
 /// Parse Garaga CLI array output format
 /// Garaga can output in different formats, we support the "array" format
-fn parse_garaga_array_output(output: &str) -> Result<Vec<String>, String> {
+pub fn parse_garaga_array_output(output: &str) -> Result<Vec<String>, String> {
     // Garaga array format output looks like:
     // [0x123, 0x456, ...] or array elements on separate lines
     let trimmed = output.trim();
@@ -588,57 +447,79 @@ fn parse_garaga_array_output(output: &str) -> Result<Vec<String>, String> {
     Err(format!("Failed to parse Garaga output: {}", trimmed))
 }
 
-/// Generate withdraw proof using Circom circuit
-pub async fn generate_withdraw_proof(
-    _circuits_path: &str,
-    _inputs: WithdrawProofInputs,
-) -> Result<WithdrawProof, String> {
-    // TODO: Implement Circom proof generation
-    Err("Withdraw proof generation not yet implemented".to_string())
+/// Steps 1-2 of the swap proof with the in-process prover: proof.json and public.json as
+/// rapidsnark writes them
+#[cfg(feature = "prover")]
+async fn prove_swap_in_process(
+    files: &CircuitArtifacts,
+    inputs: SwapInputs,
+    proof_file: &Path,
+    public_file: &Path,
+) -> Result<(), String> {
+    println!("[Proof] 🔧 Steps 1-2: Calculating witness and proof in process (arkworks)...");
+    let proof_start = std::time::Instant::now();
+    let files = files.clone();
+    let swap = tokio::task::spawn_blocking(move || {
+        let swap = crate::prover::prove_swap(&files, &inputs)?;
+        crate::verifier::verify_circuit_proof(&files.vkey, "swap", &swap.proof)?;
+        Ok::<_, String>(swap)
+    })
+        .await
+        .map_err(|e| format!("Prover task failed: {}", e))??;
+    let (proof, public) = swap.proof.to_snarkjs();
+    fs::write(proof_file, proof.to_string()).map_err(|e| format!("Failed to write proof file: {}", e))?;
+    fs::write(public_file, public.to_string()).map_err(|e| format!("Failed to write public signals: {}", e))?;
+    println!("[Proof] ✅ Proof generated in process in {:.2}s (new commitment 0x{:x})",
+        proof_start.elapsed().as_secs_f64(), swap.public.new_commitment);
+    Ok(())
 }
 
-/// Generate LP proof (mint/burn) using rapidsnark (fast) with correct format conversion
-/// This function works for both mint and burn operations (same circuit)
-pub async fn generate_lp_proof(
-    circuits_path: &str,
-    input_json: serde_json::Value,
-) -> Result<LiquidityProof, String> {
-    println!("[Proof] 🔄 Starting LP proof generation with rapidsnark...");
-    let start_time = std::time::Instant::now();
-    
-    // Create temporary files
-    let temp_dir = std::env::temp_dir();
+/// Steps 1-2 of any circuit with the in-process prover, from its input file
+#[cfg(feature = "prover")]
+async fn prove_in_process(
+    files: &CircuitArtifacts,
+    circuit: &'static str,
+    input_json: &serde_json::Value,
+    proof_file: &Path,
+    public_file: &Path,
+) -> Result<(), String> {
+    println!("[Proof] 🔧 Steps 1-2: Calculating witness and proof in process (arkworks)...");
+    let proof_start = std::time::Instant::now();
+    let (files, input_json) = (files.clone(), input_json.clone());
+    let proof = tokio::task::spawn_blocking(move || {
+        let proof = crate::prover::prove_input_json(&files, &input_json)?;
+        crate::verifier::verify_circuit_proof(&files.vkey, circuit, &proof)?;
+        Ok::<_, String>(proof)
+    })
+        .await
+        .map_err(|e| format!("Prover task failed: {}", e))??;
+    let (proof, public) = proof.to_snarkjs();
+    fs::write(proof_file, proof.to_string()).map_err(|e| format!("Failed to write proof file: {}", e))?;
+    fs::write(public_file, public.to_string()).map_err(|e| format!("Failed to write public signals: {}", e))?;
+    println!("[Proof] ✅ Proof generated in process in {:.2}s", proof_start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[cfg(not(feature = "prover"))]
+async fn prove_swap_in_process(_: &CircuitArtifacts, _: SwapInputs, _: &Path, _: &Path) -> Result<(), String> {
+    Err("The arkworks backend needs the in-process prover (--features prover)".to_string())
+}
+
+#[cfg(not(feature = "prover"))]
+async fn prove_in_process(_: &CircuitArtifacts, _: &'static str, _: &serde_json::Value, _: &Path, _: &Path) -> Result<(), String> {
+    Err("The arkworks backend needs the in-process prover (--features prover)".to_string())
+}
+
+/// Step 1 for rapidsnark and snarkjs: the witness of `input_file` with snarkjs' calculator,
+/// written to `witness_file`; returns how long it took
+pub async fn calculate_witness_with_snarkjs(
+    circuits_dir: &Path,
+    wasm_path: &Path,
+    input_file: &Path,
+    witness_file: &Path,
+) -> Result<std::time::Duration, String> {
     let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
         .unwrap().as_nanos();
-    let input_file = temp_dir.join(format!("lp_input_{}.json", timestamp));
-    let witness_file = temp_dir.join(format!("lp_witness_{}.wtns", timestamp));
-    let proof_file = temp_dir.join(format!("lp_proof_{}.json", timestamp));
-    let public_file = temp_dir.join(format!("lp_public_{}.json", timestamp));
-    
-    fs::write(&input_file, serde_json::to_string_pretty(&input_json).unwrap())
-        .map_err(|e| format!("Failed to write input file: {}", e))?;
-    
-    println!("[Proof] 📝 Input file created: {:?}", input_file);
-    
-    // Paths to circuit files
-    let circuits_dir = Path::new(circuits_path).canonicalize()
-        .map_err(|e| format!("Failed to canonicalize circuits path: {}", e))?;
-    let wasm_path = circuits_dir.join("build").join("lp").join("lp_js").join("lp.wasm");
-    let zkey_path = circuits_dir.join("build").join("zkeys").join("lp.zkey");
-    
-    // Check for rapidsnark binary
-    let asp_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
-    let rapidsnark_path = asp_dir.join("bin").join("prover");
-    let use_rapidsnark = rapidsnark_path.exists();
-    
-    if !wasm_path.exists() {
-        return Err(format!("WASM file not found: {:?}", wasm_path));
-    }
-    if !zkey_path.exists() {
-        return Err(format!("ZKey file not found: {:?}", zkey_path));
-    }
-    
-    // Step 1: Calculate witness using snarkjs (this is fast)
     println!("[Proof] 🔧 Step 1: Calculating witness with snarkjs...");
     let witness_script = format!(
         r#"
@@ -679,7 +560,7 @@ pub async fn generate_lp_proof(
     let witness_output = Command::new("node")
         .env("NODE_OPTIONS", "--max-old-space-size=4096")
         .arg(script_file.file_name().unwrap())
-        .current_dir(&circuits_dir)
+        .current_dir(circuits_dir)
         .output()
         .await
         .map_err(|e| format!("Failed to run witness calculation: {}", e))?;
@@ -689,114 +570,237 @@ pub async fn generate_lp_proof(
     if !witness_output.status.success() {
         let stderr = String::from_utf8_lossy(&witness_output.stderr);
         let stdout = String::from_utf8_lossy(&witness_output.stdout);
-        let _ = fs::remove_file(&input_file);
         return Err(format!("Witness calculation failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
     }
     
-    println!("[Proof] ✅ Witness calculated in {:.2}s", witness_start.elapsed().as_secs_f64());
+    let elapsed = witness_start.elapsed();
+    println!("[Proof] ✅ Witness calculated in {:.2}s", elapsed.as_secs_f64());
+    Ok(elapsed)
+}
+
+/// Step 2 with rapidsnark: proof.json and public.json from the witness; returns how long it took
+pub async fn prove_with_rapidsnark(
+    zkey_path: &Path,
+    witness_file: &Path,
+    proof_file: &Path,
+    public_file: &Path,
+) -> Result<std::time::Duration, String> {
+    println!("[Proof] 🔧 Step 2: Generating proof with rapidsnark (fast C++ prover)...");
+    let proof_start = std::time::Instant::now();
+    
+    let rapidsnark_output = Command::new(crate::prover_backend::rapidsnark_path())
+        .arg(zkey_path)
+        .arg(witness_file)
+        .arg(proof_file)
+        .arg(public_file)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run rapidsnark: {}", e))?;
     
-    // Step 2: Generate proof (use rapidsnark if available, otherwise snarkjs)
-    if use_rapidsnark {
-        println!("[Proof] 🔧 Step 2: Generating proof with rapidsnark (fast C++ prover)...");
-        let proof_start = std::time::Instant::now();
-        
-        let rapidsnark_output = Command::new(&rapidsnark_path)
-            .arg(&zkey_path)
-            .arg(&witness_file)
-            .arg(&proof_file)
-            .arg(&public_file)
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run rapidsnark: {}", e))?;
-        
-        if !rapidsnark_output.status.success() {
-            let stderr = String::from_utf8_lossy(&rapidsnark_output.stderr);
-            let stdout = String::from_utf8_lossy(&rapidsnark_output.stdout);
-            let _ = fs::remove_file(&input_file);
-            let _ = fs::remove_file(&witness_file);
-            return Err(format!("rapidsnark failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
-        }
-        
-        println!("[Proof] ✅ Proof generated with rapidsnark in {:.2}s", proof_start.elapsed().as_secs_f64());
-    } else {
-        println!("[Proof] 🔧 Step 2: Generating proof with snarkjs (fallback)...");
-        let proof_script = format!(
-            r#"
-            const snarkjs = require('snarkjs');
-            const fs = require('fs');
-            
-            (async () => {{
-                try {{
-                    console.log('Generating proof...');
-                    const startTime = Date.now();
-                    
-                    const {{ proof, publicSignals }} = await snarkjs.groth16.prove(
-                        '{}',
-                        '{}'
-                    );
-                    
-                    const elapsed = ((Date.now() - startTime) / 1000).toFixed(2);
-                    console.log('Proof generated in', elapsed, 'seconds');
-                    
-                    fs.writeFileSync('{}', JSON.stringify(proof, null, 2));
-                    fs.writeFileSync('{}', JSON.stringify(publicSignals, null, 2));
-                }} catch (error) {{
-                    console.error('Error:', error.message);
-                    process.exit(1);
-                }}
-            }})();
-            "#,
-            zkey_path.to_str().unwrap().replace('\\', "/"),
-            witness_file.to_str().unwrap().replace('\\', "/"),
-            proof_file.to_str().unwrap().replace('\\', "/"),
-            public_file.to_str().unwrap().replace('\\', "/")
-        );
-        
-        let script_file2 = circuits_dir.join(format!("proof_script_{}.js", timestamp));
-        fs::write(&script_file2, proof_script)
-            .map_err(|e| format!("Failed to write proof script: {}", e))?;
-        
-        let proof_start = std::time::Instant::now();
-        let mut child = Command::new("node")
-            .env("NODE_OPTIONS", "--max-old-space-size=4096")
-            .arg(script_file2.file_name().unwrap())
-            .current_dir(&circuits_dir)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| format!("Failed to spawn node: {}", e))?;
+    if !rapidsnark_output.status.success() {
+        let stderr = String::from_utf8_lossy(&rapidsnark_output.stderr);
+        let stdout = String::from_utf8_lossy(&rapidsnark_output.stdout);
+        return Err(format!("rapidsnark failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
+    }
+    
+    let elapsed = proof_start.elapsed();
+    println!("[Proof] ✅ Proof generated with rapidsnark in {:.2}s", elapsed.as_secs_f64());
+    Ok(elapsed)
+}
+
+/// Step 2 with snarkjs, much slower than rapidsnark; returns how long it took
+pub async fn prove_with_snarkjs(
+    circuits_dir: &Path,
+    zkey_path: &Path,
+    witness_file: &Path,
+    proof_file: &Path,
+    public_file: &Path,
+) -> Result<std::time::Duration, String> {
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .unwrap().as_nanos();
+    println!("[Proof] 🔧 Step 2: Generating proof with snarkjs (fallback)...");
+    let proof_script = format!(
+        r#"
+        const snarkjs = require('snarkjs');
+        const fs = require('fs');
         
-        // Wait with progress updates
-        let mut last_log = std::time::Instant::now();
-        let output = loop {
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    let output = child.wait_with_output().await
-                        .map_err(|e| format!("Failed to get output: {}", e))?;
-                    break output;
-                }
-                Ok(None) => {
-                    if last_log.elapsed().as_secs() >= 30 {
-                        println!("[Proof] ⏳ Still processing... ({}s elapsed)", proof_start.elapsed().as_secs());
-                        last_log = std::time::Instant::now();
-                    }
-                    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        (async () => {{
+            try {{
+                console.log('Generating proof...');
+                const startTime = Date.now();
+                
+                const {{ proof, publicSignals }} = await snarkjs.groth16.prove(
+                    '{}',
+                    '{}'
+                );
+                
+                const elapsed = ((Date.now() - startTime) / 1000).toFixed(2);
+                console.log('Proof generated in', elapsed, 'seconds');
+                
+                fs.writeFileSync('{}', JSON.stringify(proof, null, 2));
+                fs.writeFileSync('{}', JSON.stringify(publicSignals, null, 2));
+            }} catch (error) {{
+                console.error('Error:', error.message);
+                process.exit(1);
+            }}
+        }})();
+        "#,
+        zkey_path.to_str().unwrap().replace('\\', "/"),
+        witness_file.to_str().unwrap().replace('\\', "/"),
+        proof_file.to_str().unwrap().replace('\\', "/"),
+        public_file.to_str().unwrap().replace('\\', "/")
+    );
+    
+    let script_file2 = circuits_dir.join(format!("proof_script_{}.js", timestamp));
+    fs::write(&script_file2, proof_script)
+        .map_err(|e| format!("Failed to write proof script: {}", e))?;
+    
+    let proof_start = std::time::Instant::now();
+    let mut child = Command::new("node")
+        .env("NODE_OPTIONS", "--max-old-space-size=8192")
+        .arg(script_file2.file_name().unwrap())
+        .current_dir(circuits_dir)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn node: {}", e))?;
+    
+    // Wait with progress updates
+    let mut last_log = std::time::Instant::now();
+    let output = loop {
+        match child.try_wait() {
+            Ok(Some(_)) => {
+                let output = child.wait_with_output().await
+                    .map_err(|e| format!("Failed to get output: {}", e))?;
+                break output;
+            }
+            Ok(None) => {
+                if last_log.elapsed().as_secs() >= 30 {
+                    println!("[Proof] ⏳ Still processing... ({}s elapsed)", proof_start.elapsed().as_secs());
+                    last_log = std::time::Instant::now();
                 }
-                Err(e) => return Err(format!("Error waiting: {}", e)),
+                tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
             }
-        };
-        
-        let _ = fs::remove_file(&script_file2);
-        
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let _ = fs::remove_file(&input_file);
-            let _ = fs::remove_file(&witness_file);
-            return Err(format!("snarkjs proof failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
+            Err(e) => return Err(format!("Error waiting: {}", e)),
         }
-        
-        println!("[Proof] ✅ Proof generated with snarkjs in {:.2}s", proof_start.elapsed().as_secs_f64());
+    };
+    
+    let _ = fs::remove_file(&script_file2);
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(format!("snarkjs proof failed:\nSTDOUT: {}\nSTDERR: {}", stdout, stderr));
+    }
+    
+    let elapsed = proof_start.elapsed();
+    println!("[Proof] ✅ Proof generated with snarkjs in {:.2}s", elapsed.as_secs_f64());
+    Ok(elapsed)
+}
+
+/// Generate withdraw proof with the in-process prover, as the verifier's full_proof_with_hints
+/// calldata; field inputs are 0x hex or decimal
+#[cfg(feature = "prover")]
+pub async fn generate_withdraw_proof(
+    artifacts: &ArtifactManager,
+    inputs: WithdrawProofInputs,
+) -> Result<WithdrawProof, String> {
+    use crate::circuit_inputs::{parse_field, NotePath, WithdrawWitnessBuilder};
+    use crate::witness::NoteOpening;
+    let path_indices = inputs.merkle_path_indices.iter()
+        .map(|index| u8::try_from(*index).ok().filter(|index| *index <= 1).ok_or("Path indices must be 0 or 1".to_string()))
+        .collect::<Result<_, _>>()?;
+    let withdraw = WithdrawWitnessBuilder::new()
+        .with_note(NoteOpening {
+            secret: parse_field("secret", &inputs.secret)?,
+            nullifier: parse_field("nullifier", &inputs.nullifier)?,
+            amount: inputs.amount,
+        })
+        .with_path(NotePath {
+            root: parse_field("root", &inputs.root)?,
+            elements: inputs.merkle_path.iter().map(|element| parse_field("path element", element)).collect::<Result<_, _>>()?,
+            indices: path_indices,
+        })
+        .with_recipient(parse_field("recipient", &inputs.recipient)?)
+        .build()?;
+    let files = artifacts.circuit("withdraw").await?;
+    let vk_path = files.vkey.clone();
+    let proven = tokio::task::spawn_blocking(move || {
+        let proven = crate::prover::prove_withdraw(&files, &withdraw)?;
+        crate::verifier::verify_circuit_proof(&files.vkey, "withdraw", &proven.proof)?;
+        Ok::<_, String>(proven)
+    })
+        .await
+        .map_err(|e| format!("Prover task failed: {}", e))??;
+    let hints = crate::garaga::cli_hints(&vk_path, &proven.proof).await?;
+    let calldata = crate::garaga::full_proof_with_hints(&proven.proof, &hints);
+    let public = proven.public;
+    Ok(WithdrawProof {
+        proof: calldata.iter().map(|felt| felt.to_string()).collect(),
+        public_inputs: [public.nullifier, public.root, public.recipient, public.amount].iter().map(|value| value.to_str_radix(10)).collect(),
+    })
+}
+
+/// Generate withdraw proof using Circom circuit
+#[cfg(not(feature = "prover"))]
+pub async fn generate_withdraw_proof(
+    _artifacts: &ArtifactManager,
+    _inputs: WithdrawProofInputs,
+) -> Result<WithdrawProof, String> {
+    // TODO: Implement Circom proof generation
+    Err("Withdraw proof generation needs the in-process prover (--features prover)".to_string())
+}
+
+/// Generate LP proof (mint/burn) using rapidsnark (fast) with correct format conversion
+/// This function works for both mint and burn operations (same circuit)
+pub async fn generate_lp_proof(
+    artifacts: &ArtifactManager,
+    backend: ProverBackend,
+    input_json: serde_json::Value,
+) -> Result<LiquidityProof, String> {
+    println!("[Proof] 🔄 Starting LP proof generation with rapidsnark...");
+    let start_time = std::time::Instant::now();
+    
+    // Create temporary files
+    let temp_dir = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .unwrap().as_nanos();
+    let input_file = temp_dir.join(format!("lp_input_{}.json", timestamp));
+    let witness_file = temp_dir.join(format!("lp_witness_{}.wtns", timestamp));
+    let proof_file = temp_dir.join(format!("lp_proof_{}.json", timestamp));
+    let public_file = temp_dir.join(format!("lp_public_{}.json", timestamp));
+    
+    fs::write(&input_file, serde_json::to_string_pretty(&input_json).unwrap())
+        .map_err(|e| format!("Failed to write input file: {}", e))?;
+    
+    println!("[Proof] 📝 Input file created: {:?}", input_file);
+    
+    // Circuit files, present and matching their pinned hashes
+    let files = artifacts.circuit("lp").await?;
+    let circuits_dir = artifacts.circuits_dir().canonicalize()
+        .map_err(|e| format!("Failed to canonicalize circuits path: {}", e))?;
+    let wasm_path = files.wasm.canonicalize()
+        .map_err(|e| format!("Failed to canonicalize {:?}: {}", files.wasm, e))?;
+    let zkey_path = files.zkey.canonicalize()
+        .map_err(|e| format!("Failed to canonicalize {:?}: {}", files.zkey, e))?;
+    
+    // Steps 1-2: witness and proof with the configured backend, written to proof.json/public.json
+    let proved = match backend {
+        ProverBackend::Arkworks => prove_in_process(&files, "lp", &input_json, &proof_file, &public_file).await,
+        ProverBackend::Rapidsnark | ProverBackend::Snarkjs => async {
+            // Step 1: Calculate witness using snarkjs (this is fast)
+            calculate_witness_with_snarkjs(&circuits_dir, &wasm_path, &input_file, &witness_file).await?;
+            // Step 2: Generate proof with rapidsnark or, much slower, snarkjs
+            match backend {
+                ProverBackend::Rapidsnark => prove_with_rapidsnark(&zkey_path, &witness_file, &proof_file, &public_file).await,
+                _ => prove_with_snarkjs(&circuits_dir, &zkey_path, &witness_file, &proof_file, &public_file).await,
+            }
+        }.await.map(|_| ()),
+    };
+    if let Err(e) = proved {
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&witness_file);
+        return Err(e);
     }
     
     // Step 3: Add protocol field to proof (required by convert_garaga.py script)
@@ -1043,8 +1047,8 @@ pub async fn generate_lp_proof(
     let _ = fs::remove_file(&public_file);
     
     let elapsed = start_time.elapsed().as_secs_f64();
-    println!("[Proof] ✅ Total proof time: {:.2}s ({})", elapsed, 
-        if use_rapidsnark { "with rapidsnark" } else { "with snarkjs" });
+    println!("[Proof] ✅ Total proof time: {:.2}s (with {})", elapsed, 
+        backend);
     
     Ok(LiquidityProof {
         proof: proof_calldata, // Only the 8 proof elements, not combined with public inputs
@@ -1054,7 +1058,8 @@ pub async fn generate_lp_proof(
 
 /// Generate mint liquidity proof using Circom circuit
 pub async fn generate_mint_liquidity_proof(
-    circuits_path: &str,
+    artifacts: &ArtifactManager,
+    backend: ProverBackend,
     inputs: MintProofInputs,
 ) -> Result<LiquidityProof, String> {
     // Convert MintProofInputs to JSON for generate_lp_proof
@@ -1075,12 +1080,12 @@ pub async fn generate_mint_liquidity_proof(
         "pathIndices": inputs.merkle_path_indices.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
     });
     
-    generate_lp_proof(circuits_path, input_json).await
+    generate_lp_proof(artifacts, backend, input_json).await
 }
 
 /// Generate burn liquidity proof using Circom circuit
 pub async fn generate_burn_liquidity_proof(
-    _circuits_path: &str,
+    _artifacts: &ArtifactManager,
     _inputs: BurnProofInputs,
 ) -> Result<LiquidityProof, String> {
     // TODO: Implement Circom proof generation
@@ -0,0 +1,159 @@
+// Proof cache
+// Wallets retry /api/proof/swap and /api/proof/lp-mint with the same inputs (a timeout, a
+// rejected transaction) and every retry used to prove again for up to minutes. Responses are
+// cached under the circuit and the SHA-256 of its input, for PROOF_CACHE_TTL_SECS or until the
+// root they prove against leaves the root history (or the contract says it doesn't know it),
+// whichever comes first. With PROOF_CACHE_DIR set entries are also kept on disk across restarts.
+
+use crate::root_history::RootHistory;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use starknet::core::types::FieldElement;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_PROOF_CACHE_TTL: Duration = Duration::from_secs(600);
+pub const DEFAULT_PROOF_CACHE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Root the proof is against, as the circuit input had it
+    root: String,
+    /// Unix seconds
+    created_at: u64,
+    response: serde_json::Value,
+}
+
+pub struct ProofCache {
+    /// Zero disables the cache
+    ttl: Duration,
+    capacity: usize,
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ProofCache {
+    pub fn new(ttl: Duration, capacity: usize, dir: Option<PathBuf>) -> Self {
+        Self { ttl, capacity, dir, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// PROOF_CACHE_TTL_SECS (0 disables) and PROOF_CACHE_DIR
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("PROOF_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()).map(Duration::from_secs).unwrap_or(DEFAULT_PROOF_CACHE_TTL);
+        let dir = std::env::var("PROOF_CACHE_DIR").ok().filter(|dir| !dir.trim().is_empty()).map(PathBuf::from);
+        if let Some(dir) = &dir {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                eprintln!("[ASP] ⚠️  Proof cache kept in memory only, failed to create {}: {}", dir.display(), e);
+                return Self::new(ttl, DEFAULT_PROOF_CACHE_CAPACITY, None);
+            }
+        }
+        Self::new(ttl, DEFAULT_PROOF_CACHE_CAPACITY, dir)
+    }
+
+    /// `<circuit>-<sha256 of the input>`; serde_json sorts object keys, so equal inputs hash alike
+    pub fn key(circuit: &str, input: &serde_json::Value) -> String {
+        let digest = Sha256::new().chain_update(circuit.as_bytes()).chain_update([0u8]).chain_update(input.to_string().as_bytes()).finalize();
+        format!("{}-{}", circuit, hex::encode(digest))
+    }
+
+    /// Cached response under `key`, if it hasn't expired and `root_is_fresh` still holds for its root
+    pub fn get(&self, key: &str, root_is_fresh: impl Fn(&str) -> bool) -> Option<serde_json::Value> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let entry = match entries.get(key) {
+            Some(entry) => entry.clone(),
+            None => self.read(key)?,
+        };
+        if now() >= entry.created_at + self.ttl.as_secs() || !root_is_fresh(&entry.root) {
+            entries.remove(key);
+            if let Some(path) = self.path(key) {
+                let _ = std::fs::remove_file(path);
+            }
+            return None;
+        }
+        let response = entry.response.clone();
+        entries.insert(key.to_string(), entry);
+        Some(response)
+    }
+
+    /// Keep the response of a proof against `root`
+    pub fn insert(&self, key: String, root: String, response: serde_json::Value) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let entry = Entry { root, created_at: now(), response };
+        if let Some(path) = self.path(&key) {
+            if let Err(e) = serde_json::to_vec(&entry).map_err(|e| e.to_string()).and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| e.to_string())) {
+                eprintln!("[ASP] ⚠️  Failed to write {}: {}", path.display(), e);
+            }
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            // Oldest out; it stays on disk until it expires
+            if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.created_at).map(|(key, _)| key.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, entry);
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{}.json", key)))
+    }
+
+    fn read(&self, key: &str) -> Option<Entry> {
+        serde_json::from_slice(&std::fs::read(self.path(key)?).ok()?).ok()
+    }
+}
+
+/// Whether proofs against `root` (decimal or 0x hex) are still worth serving: it is in the recent
+/// history and the contract hasn't said it doesn't know it
+pub fn root_is_fresh(roots: &RootHistory, root: &str) -> bool {
+    let felt = match root.trim().strip_prefix("0x") {
+        Some(_) => FieldElement::from_hex_be(root.trim()).ok(),
+        None => FieldElement::from_dec_str(root.trim()).ok(),
+    };
+    felt.and_then(|felt| roots.get(&format!("0x{:x}", felt))).is_some_and(|record| record.known_on_chain != Some(false))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_follow_their_root() {
+        let dir = std::env::temp_dir().join(format!("zylith_proof_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let roots = RootHistory::new(2);
+        roots.record("0xab".to_string(), 10, 1);
+        assert!(root_is_fresh(&roots, "171"));
+        assert!(root_is_fresh(&roots, "0xAB"));
+
+        let input = serde_json::json!({"root": "171", "amount_in": "5"});
+        let key = ProofCache::key("swap", &input);
+        assert_eq!(key, ProofCache::key("swap", &serde_json::json!({"amount_in": "5", "root": "171"})));
+        assert_ne!(key, ProofCache::key("lp", &input));
+
+        let cache = ProofCache::new(DEFAULT_PROOF_CACHE_TTL, 4, Some(dir.clone()));
+        cache.insert(key.clone(), "171".to_string(), serde_json::json!({"public_inputs": ["1"]}));
+        assert!(cache.get(&key, |root| root_is_fresh(&roots, root)).is_some());
+        // A restarted ASP finds it on disk
+        let restarted = ProofCache::new(DEFAULT_PROOF_CACHE_TTL, 4, Some(dir.clone()));
+        assert_eq!(restarted.get(&key, |root| root_is_fresh(&roots, root)), Some(serde_json::json!({"public_inputs": ["1"]})));
+
+        // Once the root leaves the history the proof is dropped, from disk too
+        roots.record("0xac".to_string(), 11, 2);
+        roots.record("0xad".to_string(), 12, 3);
+        assert!(restarted.get(&key, |root| root_is_fresh(&roots, root)).is_none());
+        assert!(ProofCache::new(DEFAULT_PROOF_CACHE_TTL, 4, Some(dir.clone())).get(&key, |_| true).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,157 @@
+// Proof jobs
+// Swap and LP proofs take from seconds to minutes, longer than many clients and proxies keep a
+// request open. A job is submitted instead and answered at once with its id; the client polls
+// /api/proof/jobs/:id or follows its status over server-sent events until the proof is done.
+// At most PROOF_JOB_CONCURRENCY proofs run at a time, the rest wait queued, and submissions past
+// PROOF_JOB_QUEUE_LIMIT pending jobs are refused. Finished jobs are kept PROOF_JOB_TTL_SECS.
+
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Semaphore};
+
+pub const DEFAULT_PROOF_JOB_CONCURRENCY: usize = 2;
+pub const DEFAULT_PROOF_JOB_QUEUE_LIMIT: usize = 64;
+pub const DEFAULT_PROOF_JOB_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done { result: serde_json::Value },
+    Failed { error: String },
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Done { .. } | JobStatus::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    /// swap or lp
+    pub circuit: String,
+    #[serde(flatten)]
+    pub status: JobStatus,
+    /// Unix seconds
+    pub submitted_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+pub struct ProofJobs {
+    permits: Arc<Semaphore>,
+    queue_limit: usize,
+    ttl: Duration,
+    /// Latest state of each job; receivers follow its changes
+    jobs: Mutex<HashMap<String, watch::Sender<Job>>>,
+}
+
+impl ProofJobs {
+    pub fn new(concurrency: usize, queue_limit: usize, ttl: Duration) -> Self {
+        Self { permits: Arc::new(Semaphore::new(concurrency.max(1))), queue_limit, ttl, jobs: Mutex::new(HashMap::new()) }
+    }
+
+    /// PROOF_JOB_CONCURRENCY, PROOF_JOB_QUEUE_LIMIT and PROOF_JOB_TTL_SECS
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Self::new(
+            var("PROOF_JOB_CONCURRENCY").map_or(DEFAULT_PROOF_JOB_CONCURRENCY, |v| v as usize),
+            var("PROOF_JOB_QUEUE_LIMIT").map_or(DEFAULT_PROOF_JOB_QUEUE_LIMIT, |v| v as usize),
+            var("PROOF_JOB_TTL_SECS").map_or(DEFAULT_PROOF_JOB_TTL, Duration::from_secs),
+        )
+    }
+
+    /// Queue `work` as a job proving `circuit`; it runs once a slot frees up. Err when the queue is full
+    pub fn submit<F>(self: &Arc<Self>, circuit: &str, work: F) -> Result<Job, String>
+    where
+        F: Future<Output = Result<serde_json::Value, String>> + Send + 'static,
+    {
+        let job = Job {
+            id: hex::encode(rand::thread_rng().gen::<[u8; 16]>()),
+            circuit: circuit.to_string(),
+            status: JobStatus::Queued,
+            submitted_at: now(),
+            finished_at: None,
+        };
+        let (sender, _) = watch::channel(job.clone());
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let ttl = self.ttl.as_secs();
+            jobs.retain(|_, sender| sender.borrow().finished_at.is_none_or(|finished| now() < finished + ttl));
+            let pending = jobs.values().filter(|sender| !sender.borrow().status.is_finished()).count();
+            if pending >= self.queue_limit {
+                return Err(format!("{} proofs already pending, try again later", pending));
+            }
+            jobs.insert(job.id.clone(), sender.clone());
+        }
+
+        let permits = self.permits.clone();
+        tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("the semaphore is never closed");
+            sender.send_modify(|job| job.status = JobStatus::Running);
+            // A panicking prover fails its job instead of leaving it running forever
+            let status = match tokio::spawn(work).await {
+                Ok(Ok(result)) => JobStatus::Done { result },
+                Ok(Err(error)) => JobStatus::Failed { error },
+                Err(e) => JobStatus::Failed { error: format!("Proof task failed: {}", e) },
+            };
+            sender.send_modify(|job| {
+                job.status = status;
+                job.finished_at = Some(now());
+            });
+        });
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).map(|sender| sender.borrow().clone())
+    }
+
+    /// The job's current state, then each change until it finishes
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<Job>> {
+        self.jobs.lock().unwrap().get(id).map(|sender| sender.subscribe())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn test_jobs_run_one_at_a_time_and_report_their_result() {
+        let jobs = Arc::new(ProofJobs::new(1, 2, DEFAULT_PROOF_JOB_TTL));
+        let (release_first, first_gate) = oneshot::channel::<()>();
+        let first = jobs
+            .submit("swap", async move {
+                first_gate.await.unwrap();
+                Ok(serde_json::json!({"proof": ["1"]}))
+            })
+            .unwrap();
+        let second = jobs.submit("lp", async { Err("witness failed".to_string()) }).unwrap();
+        // Two pending jobs fill the queue
+        assert!(jobs.submit("swap", async { Ok(serde_json::Value::Null) }).is_err());
+
+        let mut first_status = jobs.subscribe(&first.id).unwrap();
+        first_status.wait_for(|job| job.status == JobStatus::Running).await.unwrap();
+        assert_eq!(jobs.get(&second.id).unwrap().status, JobStatus::Queued);
+
+        release_first.send(()).unwrap();
+        let done = first_status.wait_for(|job| job.status.is_finished()).await.unwrap().clone();
+        assert_eq!(done.status, JobStatus::Done { result: serde_json::json!({"proof": ["1"]}) });
+        let failed = jobs.subscribe(&second.id).unwrap().wait_for(|job| job.status.is_finished()).await.unwrap().clone();
+        assert_eq!(failed.status, JobStatus::Failed { error: "witness failed".to_string() });
+        assert_eq!(serde_json::to_value(&failed).unwrap()["status"], "failed");
+        assert!(jobs.get("unknown").is_none());
+    }
+}
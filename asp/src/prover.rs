@@ -0,0 +1,320 @@
+// Groth16 prover
+// With `--features prover` proofs are generated in process with arkworks: the circuit's wasm
+// computes the witness through the generate_witness.js circom emits next to it, and ark-groth16
+// proves it against the snarkjs zkey, so neither snarkjs nor the rapidsnark binary is needed. A
+// circuit's zkey is read on its first proof and kept in memory. Proving is CPU-bound and blocks
+// for seconds; async callers use spawn_blocking.
+
+use crate::artifacts::CircuitArtifacts;
+use crate::circom::{read_zkey, CircomReduction, WitnessCalculator};
+use crate::circuit_inputs::{json_field, SwapInputs, WithdrawInputs};
+use crate::witness::MembershipWitness;
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_groth16::{Groth16, Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use num_bigint::{BigInt, BigUint};
+use num_traits::Num;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// A Groth16 proof over BN254, points in affine coordinates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Groth16Proof {
+    pub a: [BigUint; 2],
+    /// G2 point: x and y as [c0, c1] of Fq2
+    pub b: [[BigUint; 2]; 2],
+    pub c: [BigUint; 2],
+    /// Public signals in the circuit's order
+    pub public_inputs: Vec<BigUint>,
+}
+
+impl Groth16Proof {
+    fn from_ark(proof: &Proof<Bn254>, public_inputs: Vec<BigUint>) -> Self {
+        Self {
+            a: [to_biguint(&proof.a.x), to_biguint(&proof.a.y)],
+            b: [
+                [to_biguint(&proof.b.x.c0), to_biguint(&proof.b.x.c1)],
+                [to_biguint(&proof.b.y.c0), to_biguint(&proof.b.y.c1)],
+            ],
+            c: [to_biguint(&proof.c.x), to_biguint(&proof.c.y)],
+            public_inputs,
+        }
+    }
+
+    /// proof.json and public.json as snarkjs and rapidsnark write them
+    pub fn to_snarkjs(&self) -> (serde_json::Value, serde_json::Value) {
+        let decimal = |value: &BigUint| value.to_str_radix(10);
+        let proof = serde_json::json!({
+            "pi_a": [decimal(&self.a[0]), decimal(&self.a[1]), "1"],
+            "pi_b": [
+                [decimal(&self.b[0][0]), decimal(&self.b[0][1])],
+                [decimal(&self.b[1][0]), decimal(&self.b[1][1])],
+                ["1", "0"],
+            ],
+            "pi_c": [decimal(&self.c[0]), decimal(&self.c[1]), "1"],
+            "protocol": "groth16",
+            "curve": "bn128",
+        });
+        let public = serde_json::json!(self.public_inputs.iter().map(decimal).collect::<Vec<_>>());
+        (proof, public)
+    }
+
+    /// Proof from snarkjs' proof.json and public.json
+    pub fn from_snarkjs(proof: &serde_json::Value, public: &serde_json::Value) -> Result<Self, String> {
+        let value = |value: &serde_json::Value| match value {
+            serde_json::Value::String(value) => BigUint::from_str_radix(value, 10).map_err(|e| format!("Invalid proof value {}: {}", value, e)),
+            other => Err(format!("Proof value {} is not a decimal string", other)),
+        };
+        let pair = |point: &serde_json::Value| Ok::<_, String>([value(&point[0])?, value(&point[1])?]);
+        let public = public.as_array().ok_or("Public signals must be a list")?;
+        Ok(Self {
+            a: pair(&proof["pi_a"])?,
+            b: [pair(&proof["pi_b"][0])?, pair(&proof["pi_b"][1])?],
+            c: pair(&proof["pi_c"])?,
+            public_inputs: public.iter().map(value).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A proof with its public signals by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitProof<P> {
+    pub proof: Groth16Proof,
+    pub public: P,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipPublic {
+    pub root: BigUint,
+    pub commitment: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawPublic {
+    pub nullifier: BigUint,
+    pub root: BigUint,
+    pub recipient: BigUint,
+    pub amount: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapPublic {
+    pub nullifier: BigUint,
+    pub root: BigUint,
+    pub new_commitment: BigUint,
+    pub amount_specified: BigUint,
+    pub zero_for_one: BigUint,
+    pub amount0_delta: BigUint,
+    pub amount1_delta: BigUint,
+    pub new_sqrt_price_x128: BigUint,
+    pub new_tick: BigUint,
+}
+
+/// Prove membership.circom for a witness built by `witness::membership_witness`
+pub fn prove_membership(files: &CircuitArtifacts, witness: &MembershipWitness) -> Result<CircuitProof<MembershipPublic>, String> {
+    let inputs = vec![
+        scalar("root", &witness.root),
+        scalar("commitment", &witness.commitment),
+        scalar("secret", &witness.secret),
+        scalar("nullifier", &witness.nullifier),
+        scalar("amount", &witness.amount),
+        array("pathElements", &witness.path_elements),
+        indices("pathIndices", &witness.path_indices),
+    ];
+    let proof = circuit(files)?.prove(inputs)?;
+    let [root, commitment] = public_signals(&proof, "membership")?;
+    Ok(CircuitProof { proof, public: MembershipPublic { root, commitment } })
+}
+
+/// Prove withdraw.circom
+pub fn prove_withdraw(files: &CircuitArtifacts, inputs: &WithdrawInputs) -> Result<CircuitProof<WithdrawPublic>, String> {
+    let signals = vec![
+        scalar("nullifier", &inputs.nullifier),
+        scalar("root", &inputs.root),
+        scalar("recipient", &inputs.recipient),
+        scalar("amount", &inputs.amount),
+        scalar("secret", &inputs.secret),
+        array("pathElements", &inputs.path_elements),
+        indices("pathIndices", &inputs.path_indices),
+    ];
+    let proof = circuit(files)?.prove(signals)?;
+    let [nullifier, root, recipient, amount] = public_signals(&proof, "withdraw")?;
+    Ok(CircuitProof { proof, public: WithdrawPublic { nullifier, root, recipient, amount } })
+}
+
+/// Prove swap.circom
+pub fn prove_swap(files: &CircuitArtifacts, inputs: &SwapInputs) -> Result<CircuitProof<SwapPublic>, String> {
+    let signals = vec![
+        scalar("nullifier", &inputs.nullifier),
+        scalar("root", &inputs.root),
+        scalar("new_commitment", &inputs.new_commitment),
+        scalar("amount_specified", &inputs.amount_specified),
+        scalar("zero_for_one", &inputs.zero_for_one),
+        scalar("amount0_delta", &inputs.amount0_delta),
+        scalar("amount1_delta", &inputs.amount1_delta),
+        scalar("new_sqrt_price_x128", &inputs.new_sqrt_price_x128),
+        scalar("new_tick", &inputs.new_tick),
+        scalar("secret_in", &inputs.secret_in),
+        scalar("amount_in", &inputs.amount_in),
+        scalar("secret_out", &inputs.secret_out),
+        scalar("nullifier_out", &inputs.nullifier_out),
+        scalar("amount_out", &inputs.amount_out),
+        array("pathElements", &inputs.path_elements),
+        indices("pathIndices", &inputs.path_indices),
+        scalar("sqrt_price_old", &inputs.sqrt_price_old),
+        scalar("liquidity", &inputs.liquidity),
+    ];
+    let proof = circuit(files)?.prove(signals)?;
+    let [nullifier, root, new_commitment, amount_specified, zero_for_one, amount0_delta, amount1_delta, new_sqrt_price_x128, new_tick] =
+        public_signals(&proof, "swap")?;
+    Ok(CircuitProof {
+        proof,
+        public: SwapPublic { nullifier, root, new_commitment, amount_specified, zero_for_one, amount0_delta, amount1_delta, new_sqrt_price_x128, new_tick },
+    })
+}
+
+/// Prove any circuit from its witness calculator input file (snarkjs' input.json)
+pub fn prove_input_json(files: &CircuitArtifacts, input: &serde_json::Value) -> Result<Groth16Proof, String> {
+    circuit(files)?.prove(input_signals(input)?)
+}
+
+/// Time witness calculation and proving of one proof from `input`, once the circuit is loaded
+pub fn time_proof(files: &CircuitArtifacts, input: &serde_json::Value) -> Result<(Duration, Duration), String> {
+    let circuit = circuit(files)?;
+    let inputs = input_signals(input)?;
+    let start = Instant::now();
+    let assignment = circuit.calculate_witness(inputs)?;
+    let witness = start.elapsed();
+    let start = Instant::now();
+    circuit.prove_witness(&assignment)?;
+    Ok((witness, start.elapsed()))
+}
+
+/// Proving key, constraints and witness calculator of one circuit
+struct LoadedCircuit {
+    proving_key: ProvingKey<Bn254>,
+    matrices: ConstraintMatrices<Fr>,
+    witness: WitnessCalculator,
+}
+
+impl LoadedCircuit {
+    fn load(wasm: &Path, zkey: &Path) -> Result<Self, String> {
+        let start = std::time::Instant::now();
+        let (proving_key, matrices) = read_zkey(zkey).map_err(|e| format!("Failed to read {}: {}", zkey.display(), e))?;
+        let witness = WitnessCalculator::new(wasm).map_err(|e| format!("Failed to load {}: {}", wasm.display(), e))?;
+        println!("[Proof] 📦 Loaded {} in {:.2}s", zkey.display(), start.elapsed().as_secs_f64());
+        Ok(Self { proving_key, matrices, witness })
+    }
+
+    fn prove(&self, inputs: Vec<(String, Vec<BigInt>)>) -> Result<Groth16Proof, String> {
+        self.prove_witness(&self.calculate_witness(inputs)?)
+    }
+
+    fn calculate_witness(&self, inputs: Vec<(String, Vec<BigInt>)>) -> Result<Vec<Fr>, String> {
+        self.witness.calculate(inputs).map_err(|e| format!("Witness calculation failed: {}", e))
+    }
+
+    fn prove_witness(&self, assignment: &[Fr]) -> Result<Groth16Proof, String> {
+        let mut rng = rand::thread_rng();
+        let (r, s) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        let matrices = &self.matrices;
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &self.proving_key,
+            r,
+            s,
+            matrices,
+            matrices.num_instance_variables,
+            matrices.num_constraints,
+            assignment,
+        )
+        .map_err(|e| format!("Proving failed: {}", e))?;
+        // assignment[0] is the constant 1, the public signals follow
+        let public_inputs = assignment[1..matrices.num_instance_variables].iter().map(to_biguint).collect();
+        Ok(Groth16Proof::from_ark(&proof, public_inputs))
+    }
+}
+
+/// Circuit of `files`, loaded once per process
+fn circuit(files: &CircuitArtifacts) -> Result<Arc<LoadedCircuit>, String> {
+    static LOADED: OnceLock<Mutex<HashMap<PathBuf, Arc<LoadedCircuit>>>> = OnceLock::new();
+
+    // Loading holds the lock so concurrent first proofs don't read the zkey twice
+    let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap();
+    if let Some(circuit) = loaded.get(&files.zkey) {
+        return Ok(circuit.clone());
+    }
+    let circuit = Arc::new(LoadedCircuit::load(&files.wasm, &files.zkey)?);
+    loaded.insert(files.zkey.clone(), circuit.clone());
+    Ok(circuit)
+}
+
+/// The proof's public signals, checked against how many the circuit declares
+fn public_signals<const N: usize>(proof: &Groth16Proof, circuit: &str) -> Result<[BigUint; N], String> {
+    <[BigUint; N]>::try_from(proof.public_inputs.clone())
+        .map_err(|signals| format!("The {} circuit has {} public signals, the zkey gave {}", circuit, N, signals.len()))
+}
+
+fn scalar(name: &str, value: &BigUint) -> (String, Vec<BigInt>) {
+    (name.to_string(), vec![BigInt::from(value.clone())])
+}
+
+fn array(name: &str, values: &[BigUint]) -> (String, Vec<BigInt>) {
+    (name.to_string(), values.iter().cloned().map(BigInt::from).collect())
+}
+
+fn indices(name: &str, values: &[u8]) -> (String, Vec<BigInt>) {
+    (name.to_string(), values.iter().map(|index| BigInt::from(*index)).collect())
+}
+
+/// Signals of an input file: each key a field or an array of fields
+fn input_signals(input: &serde_json::Value) -> Result<Vec<(String, Vec<BigInt>)>, String> {
+    let object = input.as_object().ok_or("The circuit input must be a JSON object")?;
+    object
+        .iter()
+        .map(|(name, value)| {
+            let values = match value {
+                serde_json::Value::Array(values) => values.iter().map(|value| json_field(name, value)).collect::<Result<Vec<_>, _>>()?,
+                value => vec![json_field(name, value)?],
+            };
+            Ok((name.clone(), values.into_iter().map(BigInt::from).collect()))
+        })
+        .collect()
+}
+
+fn to_biguint<F: PrimeField>(value: &F) -> BigUint {
+    BigUint::from_bytes_be(&value.into_bigint().to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_inputs::parse_field;
+
+    #[test]
+    fn test_signals_and_snarkjs_proof() {
+        let modulus = BigUint::from(Fr::MODULUS);
+        assert_eq!(parse_field("tick", "-1").unwrap(), &modulus - 1u8);
+        assert_eq!(parse_field("root", "0xff").unwrap(), BigUint::from(255u8));
+        assert_eq!(parse_field("amount", "1000").unwrap(), BigUint::from(1000u16));
+        assert!(parse_field("amount", "1e3").is_err());
+
+        let one = || BigUint::from(1u8);
+        let proof = Groth16Proof {
+            a: [one(), BigUint::from(2u8)],
+            b: [[BigUint::from(3u8), BigUint::from(4u8)], [BigUint::from(5u8), BigUint::from(6u8)]],
+            c: [BigUint::from(7u8), BigUint::from(8u8)],
+            public_inputs: vec![BigUint::from(9u8), BigUint::from(10u8)],
+        };
+        let (json, public) = proof.to_snarkjs();
+        assert_eq!(json["pi_b"][1], serde_json::json!(["5", "6"]));
+        assert_eq!(json["pi_a"][2], "1");
+        assert_eq!(public, serde_json::json!(["9", "10"]));
+        assert_eq!(Groth16Proof::from_snarkjs(&json, &public).unwrap(), proof);
+        let [root, commitment] = public_signals(&proof, "membership").unwrap();
+        assert_eq!((root, commitment), (BigUint::from(9u8), BigUint::from(10u8)));
+        assert!(public_signals::<4>(&proof, "withdraw").unwrap_err().contains("gave 2"));
+    }
+}
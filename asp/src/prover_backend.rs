@@ -0,0 +1,254 @@
+// Prover backends
+// The Groth16 proof of a circuit comes from arkworks in process (`--features prover`, with the
+// witness from circom's generate_witness.js), from snarkjs' witness calculator followed by
+// rapidsnark (asp/bin/prover, or RAPIDSNARK_PATH), or from snarkjs alone. PROVER_BACKEND picks one; `auto`, the default, takes
+// the first available in that order. `zylith-asp prover bench` times witness generation and
+// proving of each circuit on every available backend, to choose from numbers on the machine.
+
+use crate::artifacts::ArtifactManager;
+use crate::merkle::MerkleTree;
+use crate::witness::{membership_witness, NoteOpening, MEMBERSHIP_DEPTH};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProverBackend {
+    Arkworks,
+    Rapidsnark,
+    Snarkjs,
+}
+
+impl ProverBackend {
+    /// In order of preference
+    pub const ALL: [ProverBackend; 3] = [ProverBackend::Arkworks, ProverBackend::Rapidsnark, ProverBackend::Snarkjs];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "arkworks" | "ark" => Some(ProverBackend::Arkworks),
+            "rapidsnark" => Some(ProverBackend::Rapidsnark),
+            "snarkjs" => Some(ProverBackend::Snarkjs),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ProverBackend::Arkworks => "arkworks",
+            ProverBackend::Rapidsnark => "rapidsnark",
+            ProverBackend::Snarkjs => "snarkjs",
+        }
+    }
+
+    /// Built in, or its binary present; snarkjs only needs node and the circuits' node_modules
+    pub fn is_available(self) -> bool {
+        match self {
+            ProverBackend::Arkworks => cfg!(feature = "prover"),
+            ProverBackend::Rapidsnark => rapidsnark_path().exists(),
+            ProverBackend::Snarkjs => true,
+        }
+    }
+
+    pub fn available() -> Vec<Self> {
+        Self::ALL.into_iter().filter(|backend| backend.is_available()).collect()
+    }
+
+    /// PROVER_BACKEND: arkworks, rapidsnark, snarkjs or auto (default)
+    pub fn from_env() -> Result<Self, String> {
+        select(std::env::var("PROVER_BACKEND").ok().as_deref(), &Self::available())
+    }
+}
+
+impl std::fmt::Display for ProverBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The configured backend if it is available, the preferred available one for `auto`
+fn select(configured: Option<&str>, available: &[ProverBackend]) -> Result<ProverBackend, String> {
+    match configured.map(str::trim).filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("auto")) {
+        None => available.first().copied().ok_or_else(|| "No prover backend available".to_string()),
+        Some(name) => {
+            let backend = ProverBackend::parse(name).ok_or_else(|| format!("Unknown PROVER_BACKEND {:?}; expected arkworks, rapidsnark, snarkjs or auto", name))?;
+            if !available.contains(&backend) {
+                return Err(match backend {
+                    ProverBackend::Arkworks => "PROVER_BACKEND=arkworks needs a build with --features prover".to_string(),
+                    _ => format!("PROVER_BACKEND={} but {} is not installed", backend, rapidsnark_path().display()),
+                });
+            }
+            Ok(backend)
+        }
+    }
+}
+
+/// RAPIDSNARK_PATH, asp/bin/prover by default
+pub fn rapidsnark_path() -> PathBuf {
+    std::env::var("RAPIDSNARK_PATH").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bin").join("prover"))
+}
+
+/// Mean time of one proof of a circuit on a backend
+#[derive(Debug, Clone)]
+pub struct Timing {
+    pub circuit: String,
+    pub backend: ProverBackend,
+    pub witness: Duration,
+    pub proving: Duration,
+}
+
+/// Time `runs` proofs of `circuit` from `input` (its witness calculator input) on `backend`
+pub async fn bench_circuit(
+    artifacts: &ArtifactManager,
+    backend: ProverBackend,
+    circuit: &str,
+    input: &serde_json::Value,
+    runs: u32,
+) -> Result<Timing, String> {
+    let files = artifacts.circuit(circuit).await?;
+    let (mut witness, mut proving) = (Duration::ZERO, Duration::ZERO);
+    match backend {
+        ProverBackend::Arkworks => {
+            #[cfg(feature = "prover")]
+            for _ in 0..runs {
+                let (files, input) = (files.clone(), input.clone());
+                let (witness_time, proving_time) = tokio::task::spawn_blocking(move || crate::prover::time_proof(&files, &input))
+                    .await
+                    .map_err(|e| format!("Prover task failed: {}", e))??;
+                witness += witness_time;
+                proving += proving_time;
+            }
+            #[cfg(not(feature = "prover"))]
+            return Err("The arkworks backend needs the in-process prover (--features prover)".to_string());
+        }
+        ProverBackend::Rapidsnark | ProverBackend::Snarkjs => {
+            let circuits_dir = artifacts.circuits_dir().canonicalize().map_err(|e| format!("Failed to canonicalize circuits path: {}", e))?;
+            let canonical = |path: &PathBuf| path.canonicalize().map_err(|e| format!("Failed to canonicalize {:?}: {}", path, e));
+            let (wasm_path, zkey_path) = (canonical(&files.wasm)?, canonical(&files.zkey)?);
+            let temp_dir = std::env::temp_dir().join(format!("zylith_bench_{}_{}", circuit, std::process::id()));
+            std::fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create {}: {}", temp_dir.display(), e))?;
+            let input_file = temp_dir.join("input.json");
+            let witness_file = temp_dir.join("witness.wtns");
+            let proof_file = temp_dir.join("proof.json");
+            let public_file = temp_dir.join("public.json");
+            std::fs::write(&input_file, input.to_string()).map_err(|e| format!("Failed to write input file: {}", e))?;
+            let timed = async {
+                for _ in 0..runs {
+                    witness += crate::proof::calculate_witness_with_snarkjs(&circuits_dir, &wasm_path, &input_file, &witness_file).await?;
+                    proving += match backend {
+                        ProverBackend::Rapidsnark => crate::proof::prove_with_rapidsnark(&zkey_path, &witness_file, &proof_file, &public_file).await?,
+                        _ => crate::proof::prove_with_snarkjs(&circuits_dir, &zkey_path, &witness_file, &proof_file, &public_file).await?,
+                    };
+                }
+                Ok::<_, String>(())
+            }
+            .await;
+            let _ = std::fs::remove_dir_all(&temp_dir);
+            timed?;
+        }
+    }
+    Ok(Timing { circuit: circuit.to_string(), backend, witness: witness / runs, proving: proving / runs })
+}
+
+/// Inputs the bench proves without being given any: a note alone in a fresh tree, spent by
+/// membership and withdraw. swap and lp need real pool state, passed with --input
+pub fn sample_inputs() -> Result<Vec<(String, serde_json::Value)>, String> {
+    let opening = NoteOpening::from_hex("0x1234567890abcdef", "0xfedcba0987654321", 1_000_000)?;
+    let mut tree = MerkleTree::new(MEMBERSHIP_DEPTH);
+    tree.insert(opening.commitment()).map_err(|e| e.to_string())?;
+    let proof = tree.get_proof(0).ok_or("The sample note has no path")?;
+    let membership = membership_witness(&opening, &proof)?.input_json();
+    let mut withdraw = membership.clone();
+    let signals = withdraw.as_object_mut().expect("input_json is an object");
+    signals.remove("commitment");
+    signals.insert("recipient".to_string(), serde_json::json!("1"));
+    Ok(vec![("membership".to_string(), membership), ("withdraw".to_string(), withdraw)])
+}
+
+/// `zylith-asp prover bench [--runs N] [--backend NAME]... [--circuit NAME]... [--input CIRCUIT=FILE]...`
+pub async fn run(args: &[String]) -> Result<(), String> {
+    if args.first().map(String::as_str) != Some("bench") {
+        return Err("usage: zylith-asp prover bench [--runs N] [--backend NAME] [--circuit NAME] [--input CIRCUIT=FILE]".to_string());
+    }
+    let mut runs = 3u32;
+    let mut backends = Vec::new();
+    let mut circuits = Vec::new();
+    let mut inputs = sample_inputs()?;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--runs" => runs = value()?.parse().ok().filter(|runs| *runs > 0).ok_or("--runs takes a positive number")?,
+            "--backend" => backends.push(select(Some(&value()?), &ProverBackend::available())?),
+            "--circuit" => circuits.push(value()?),
+            "--input" => {
+                let value = value()?;
+                let (circuit, path) = value.split_once('=').ok_or("--input takes CIRCUIT=FILE")?;
+                let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                let input = serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+                inputs.retain(|(name, _)| name != circuit);
+                inputs.push((circuit.to_string(), input));
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    if backends.is_empty() {
+        backends = ProverBackend::available();
+    }
+    if !circuits.is_empty() {
+        inputs.retain(|(name, _)| circuits.contains(name));
+    }
+
+    let artifacts = ArtifactManager::from_env()?;
+    let mut timings = Vec::new();
+    for (circuit, input) in &inputs {
+        for backend in &backends {
+            println!("[Proof] ⏱️  {} on {} ({} runs)...", circuit, backend, runs);
+            match bench_circuit(&artifacts, *backend, circuit, input, runs).await {
+                Ok(timing) => timings.push(timing),
+                Err(e) => eprintln!("[Proof] ❌ {} on {}: {}", circuit, backend, e),
+            }
+        }
+    }
+
+    println!("\n{:<12} {:<12} {:>10} {:>10} {:>10}", "circuit", "backend", "witness", "proving", "total");
+    for timing in &timings {
+        let seconds = |duration: Duration| format!("{:.2}s", duration.as_secs_f64());
+        println!(
+            "{:<12} {:<12} {:>10} {:>10} {:>10}",
+            timing.circuit,
+            timing.backend,
+            seconds(timing.witness),
+            seconds(timing.proving),
+            seconds(timing.witness + timing.proving)
+        );
+    }
+    // Over the circuits every backend got through, the fastest in total
+    let complete = backends.iter().filter(|backend| inputs.iter().all(|(circuit, _)| timings.iter().any(|t| t.backend == **backend && &t.circuit == circuit)));
+    let total = |backend: ProverBackend| timings.iter().filter(|t| t.backend == backend).map(|t| t.witness + t.proving).sum::<Duration>();
+    match complete.min_by_key(|backend| total(**backend)) {
+        Some(fastest) => println!("\nFastest here: {} (PROVER_BACKEND={})", fastest, fastest),
+        None => return Err("No backend proved every circuit".to_string()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_selection_and_sample_inputs() {
+        let all = [ProverBackend::Rapidsnark, ProverBackend::Snarkjs];
+        assert_eq!(select(None, &all), Ok(ProverBackend::Rapidsnark));
+        assert_eq!(select(Some(" AUTO "), &all), Ok(ProverBackend::Rapidsnark));
+        assert_eq!(select(Some("snarkjs"), &all), Ok(ProverBackend::Snarkjs));
+        assert!(select(Some("arkworks"), &all).unwrap_err().contains("--features prover"));
+        assert!(select(Some("bellman"), &all).unwrap_err().contains("Unknown"));
+        assert!(select(Some("rapidsnark"), &[ProverBackend::Snarkjs]).is_err());
+
+        let inputs = sample_inputs().unwrap();
+        let withdraw = &inputs.iter().find(|(circuit, _)| circuit == "withdraw").unwrap().1;
+        assert_eq!(withdraw["recipient"], "1");
+        assert!(withdraw.get("commitment").is_none());
+        assert_eq!(withdraw["pathElements"].as_array().unwrap().len(), MEMBERSHIP_DEPTH);
+    }
+}
@@ -0,0 +1,664 @@
+use crate::error::BlockchainError;
+use crate::rate_limit::{shared_bucket, RateLimit, TokenBucket};
+use async_trait::async_trait;
+use starknet::core::types::{
+    BlockId, BlockTag, Event, EventFilter, EventsPage, ExecuteInvocation, ExecutionResult, FieldElement,
+    FunctionCall, FunctionInvocation, MaybePendingBlockWithTxHashes, MaybePendingTransactionReceipt,
+    PendingTransactionReceipt, StarknetError, TransactionReceipt, TransactionTrace,
+};
+use starknet::providers::jsonrpc::{HttpTransport, JsonRpcClient, JsonRpcTransport};
+use starknet::providers::{Provider, ProviderError};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OnceCell, Semaphore, SemaphorePermit};
+use url::Url;
+
+/// The parts of a transaction receipt the ASP reads
+#[derive(Debug, Clone, Default)]
+pub struct TxReceipt {
+    /// Still in the pending block (not yet accepted on L2)
+    pub pending: bool,
+    /// Reverted transactions keep a receipt but their events are discarded
+    pub reverted: bool,
+    pub events: Vec<Event>,
+}
+
+/// An event emitted inside a call, with its position in the transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedEvent {
+    pub order: u64,
+    pub keys: Vec<FieldElement>,
+    pub data: Vec<FieldElement>,
+}
+
+/// Call tree of a transaction's execution, reduced to what event extraction needs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvocationTrace {
+    /// Contract whose storage/events this call acts on (the caller for library calls)
+    pub contract_address: FieldElement,
+    pub events: Vec<TracedEvent>,
+    pub calls: Vec<InvocationTrace>,
+}
+
+impl InvocationTrace {
+    /// All events of the call tree, inner calls included, in emission order
+    pub fn flatten_events(&self) -> Vec<Event> {
+        let mut ordered = Vec::new();
+        self.collect_events(&mut ordered);
+        ordered.sort_by_key(|(order, _)| *order);
+        ordered.into_iter().map(|(_, event)| event).collect()
+    }
+
+    fn collect_events(&self, out: &mut Vec<(u64, Event)>) {
+        for event in &self.events {
+            out.push((
+                event.order,
+                Event {
+                    from_address: self.contract_address,
+                    keys: event.keys.clone(),
+                    data: event.data.clone(),
+                },
+            ));
+        }
+        for call in &self.calls {
+            call.collect_events(out);
+        }
+    }
+}
+
+impl From<&FunctionInvocation> for InvocationTrace {
+    fn from(invocation: &FunctionInvocation) -> Self {
+        Self {
+            contract_address: invocation.contract_address,
+            events: invocation
+                .events
+                .iter()
+                .map(|e| TracedEvent {
+                    order: e.order,
+                    keys: e.keys.clone(),
+                    data: e.data.clone(),
+                })
+                .collect(),
+            calls: invocation.calls.iter().map(InvocationTrace::from).collect(),
+        }
+    }
+}
+
+/// Outcome of one view call of a batch
+pub type CallResult = Result<Vec<FieldElement>, BlockchainError>;
+
+/// Read-only view of a Starknet node used by the ASP
+/// Abstracted so BlockchainClient and the Syncer can run against an in-memory mock in tests
+#[async_trait]
+pub trait StarknetProvider: Send + Sync {
+    /// Call a view function
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError>;
+
+    /// Call several view functions at `block`, results in call order
+    /// The outer error means the request as a whole failed; a revert only fails its own call.
+    /// Sends the calls one by one unless the provider can batch them.
+    async fn batch_call(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        Ok(call_each(self, calls, block).await)
+    }
+
+    /// Read a raw storage slot of a contract
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError>;
+
+    /// Get the class hash of the contract deployed at `address`
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError>;
+
+    /// Get the receipt of a transaction
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError>;
+
+    /// Latest block number
+    async fn block_number(&self) -> Result<u64, BlockchainError>;
+
+    /// Hash of the accepted block `block`
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError>;
+
+    /// Fetch one page of events matching `filter`
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError>;
+
+    /// Execution traces of the successful invoke transactions of a block
+    /// (`starknet_traceBlockTransactions`); not every node enables tracing
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError>;
+}
+
+#[async_trait]
+impl<T> StarknetProvider for JsonRpcClient<T>
+where
+    T: JsonRpcTransport + Send + Sync + 'static,
+{
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        let address = call.contract_address;
+        Provider::call(self, call, block)
+            .await
+            .map_err(|e| map_provider_error(e, address))
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        Provider::get_storage_at(self, address, key, block)
+            .await
+            .map_err(|e| map_provider_error(e, address))
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        Provider::get_class_hash_at(self, block, address)
+            .await
+            .map_err(|e| map_provider_error(e, address))
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        let receipt = match Provider::get_transaction_receipt(self, tx_hash).await {
+            Ok(receipt) => receipt,
+            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+                return Err(BlockchainError::TransactionNotFound(format!("0x{:x}", tx_hash)))
+            }
+            Err(e) => return Err(BlockchainError::Transport(e.to_string())),
+        };
+
+        // Deposits are always invoke transactions; other receipt types carry no Zylith events
+        Ok(match receipt {
+            MaybePendingTransactionReceipt::Receipt(TransactionReceipt::Invoke(r)) => TxReceipt {
+                pending: false,
+                reverted: matches!(r.execution_result, ExecutionResult::Reverted { .. }),
+                events: r.events,
+            },
+            MaybePendingTransactionReceipt::PendingReceipt(PendingTransactionReceipt::Invoke(r)) => TxReceipt {
+                pending: true,
+                reverted: matches!(r.execution_result, ExecutionResult::Reverted { .. }),
+                events: r.events,
+            },
+            MaybePendingTransactionReceipt::Receipt(_) => TxReceipt::default(),
+            MaybePendingTransactionReceipt::PendingReceipt(_) => TxReceipt {
+                pending: true,
+                ..TxReceipt::default()
+            },
+        })
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        Provider::block_number(self)
+            .await
+            .map_err(|e| BlockchainError::Transport(e.to_string()))
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        match Provider::get_block_with_tx_hashes(self, BlockId::Number(block)).await {
+            Ok(MaybePendingBlockWithTxHashes::Block(accepted)) => Ok(accepted.block_hash),
+            Ok(MaybePendingBlockWithTxHashes::PendingBlock(_)) => {
+                Err(BlockchainError::MissingData(format!("Block {} is still pending", block)))
+            }
+            Err(ProviderError::StarknetError(StarknetError::BlockNotFound)) => {
+                Err(BlockchainError::MissingData(format!("Block {} not found", block)))
+            }
+            Err(e) => Err(BlockchainError::Transport(e.to_string())),
+        }
+    }
+
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        let address = filter.address.unwrap_or(FieldElement::ZERO);
+        Provider::get_events(self, filter, continuation_token, chunk_size)
+            .await
+            .map_err(|e| map_provider_error(e, address))
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        let traces = Provider::trace_block_transactions(self, BlockId::Number(block))
+            .await
+            .map_err(|e| {
+                let message = e.to_string();
+                if message.contains("-32601") || message.to_lowercase().contains("method not found") {
+                    BlockchainError::Unsupported("starknet_traceBlockTransactions".to_string())
+                } else {
+                    BlockchainError::Transport(message)
+                }
+            })?;
+
+        Ok(traces
+            .iter()
+            .filter_map(|trace| match &trace.trace_root {
+                TransactionTrace::Invoke(invoke) => match &invoke.execute_invocation {
+                    ExecuteInvocation::Success(invocation) => Some(InvocationTrace::from(invocation)),
+                    // Reverted executions emit no events
+                    ExecuteInvocation::Reverted(_) => None,
+                },
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// Connection pool settings for the HTTP transport
+/// High-throughput relayers should keep idle sockets around instead of reconnecting per call
+#[derive(Debug, Clone, Default)]
+pub struct HttpPoolConfig {
+    /// Max idle connections kept per host (reqwest default: unlimited)
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept before being closed
+    pub idle_timeout: Option<Duration>,
+    /// TCP keep-alive interval for open connections
+    pub tcp_keepalive: Option<Duration>,
+    /// Max time to establish a connection (TCP and TLS)
+    pub connect_timeout: Option<Duration>,
+    /// Max concurrent requests to the endpoint; further ones wait for a free slot
+    pub max_connections: Option<usize>,
+    /// Request rate allowed per endpoint (unlimited if unset)
+    pub rate_limit: Option<RateLimit>,
+    /// Rate limits of specific endpoints by URL, overriding `rate_limit`
+    pub endpoint_rate_limits: Vec<(String, RateLimit)>,
+}
+
+impl HttpPoolConfig {
+    /// Rate limit applying to the endpoint at `rpc_url`
+    pub fn rate_limit_for(&self, rpc_url: &str) -> Option<RateLimit> {
+        self.endpoint_rate_limits
+            .iter()
+            .find(|(url, _)| url.trim_end_matches('/') == rpc_url.trim_end_matches('/'))
+            .map(|(_, limit)| *limit)
+            .or(self.rate_limit)
+    }
+}
+
+/// JSON-RPC over HTTP provider
+/// The underlying client is built either up front or, in lazy mode, on first use
+pub struct HttpProvider {
+    rpc_url: Url,
+    pool: HttpPoolConfig,
+    /// JSON-RPC client, plus the HTTP client behind it for requests starknet-rs can't send (batches)
+    client: OnceCell<(JsonRpcClient<HttpTransport>, reqwest::Client)>,
+    /// One permit per in-flight request when `max_connections` is set
+    connections: Option<Semaphore>,
+    /// Token bucket shared with every provider of the same endpoint, when rate limited
+    rate_limiter: Option<Arc<TokenBucket>>,
+}
+
+impl HttpProvider {
+    pub fn new(rpc_url: Url, pool: HttpPoolConfig) -> Result<Self, BlockchainError> {
+        let client = build_json_rpc_client(&rpc_url, &pool)?;
+        Ok(Self {
+            connections: pool.max_connections.map(Semaphore::new),
+            rate_limiter: pool.rate_limit_for(rpc_url.as_str()).map(|limit| shared_bucket(rpc_url.as_str(), limit)),
+            rpc_url,
+            pool,
+            client: OnceCell::from(client),
+        })
+    }
+
+    /// Defer building the HTTP client until the first request
+    pub fn lazy(rpc_url: Url, pool: HttpPoolConfig) -> Self {
+        Self {
+            connections: pool.max_connections.map(Semaphore::new),
+            rate_limiter: pool.rate_limit_for(rpc_url.as_str()).map(|limit| shared_bucket(rpc_url.as_str(), limit)),
+            rpc_url,
+            pool,
+            client: OnceCell::new(),
+        }
+    }
+
+    pub fn rpc_url(&self) -> &Url {
+        &self.rpc_url
+    }
+
+    async fn client(&self) -> Result<&JsonRpcClient<HttpTransport>, BlockchainError> {
+        Ok(&self.clients().await?.0)
+    }
+
+    /// Wait for the rate limiter and a free connection slot, held until the returned permit is dropped
+    async fn connection(&self) -> Result<Option<SemaphorePermit<'_>>, BlockchainError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        match &self.connections {
+            Some(connections) => connections
+                .acquire()
+                .await
+                .map(Some)
+                .map_err(|e| BlockchainError::Transport(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    async fn clients(&self) -> Result<&(JsonRpcClient<HttpTransport>, reqwest::Client), BlockchainError> {
+        self.client
+            .get_or_try_init(|| async { build_json_rpc_client(&self.rpc_url, &self.pool) })
+            .await
+    }
+
+    /// Send `calls` as one JSON-RPC batch request
+    /// Unsupported if the node rejects batches
+    async fn send_call_batch(&self, calls: &[FunctionCall], block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        let (_, http) = self.clients().await?;
+        let _connection = self.connection().await?;
+        let response = http
+            .post(self.rpc_url.clone())
+            .header("content-type", "application/json")
+            .body(encode_call_batch(calls, block).to_string())
+            .send()
+            .await
+            .map_err(|e| BlockchainError::Transport(e.to_string()))?;
+
+        let status = response.status();
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(BlockchainError::Unsupported(format!("JSON-RPC batch requests (HTTP {})", status)));
+        }
+        if !status.is_success() {
+            return Err(BlockchainError::Transport(format!("HTTP {}", status)));
+        }
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| BlockchainError::Transport(e.to_string()))?;
+        let body: serde_json::Value = serde_json::from_slice(&body)
+            .map_err(|e| BlockchainError::Decode(format!("batch response: {}", e)))?;
+
+        decode_call_batch(body, calls)
+    }
+}
+
+fn build_json_rpc_client(
+    rpc_url: &Url,
+    pool: &HttpPoolConfig,
+) -> Result<(JsonRpcClient<HttpTransport>, reqwest::Client), BlockchainError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(max_idle) = pool.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout) = pool.idle_timeout {
+        builder = builder.pool_idle_timeout(idle_timeout);
+    }
+    if let Some(keepalive) = pool.tcp_keepalive {
+        builder = builder.tcp_keepalive(keepalive);
+    }
+    if let Some(connect_timeout) = pool.connect_timeout {
+        builder = builder.connect_timeout(connect_timeout);
+    }
+    let http = builder
+        .build()
+        .map_err(|e| BlockchainError::Config(format!("Failed to build HTTP client: {}", e)))?;
+
+    let rpc = JsonRpcClient::new(HttpTransport::new_with_client(rpc_url.clone(), http.clone()));
+    Ok((rpc, http))
+}
+
+#[async_trait]
+impl StarknetProvider for HttpProvider {
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::call(self.client().await?, call, block).await
+    }
+
+    async fn batch_call(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self.send_call_batch(&calls, block).await {
+            Err(e @ BlockchainError::Unsupported(_)) => {
+                eprintln!("[ASP] ⚠️  {}, sending calls one by one", e);
+                Ok(call_each(self, calls, block).await)
+            }
+            result => result,
+        }
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::get_storage_at(self.client().await?, address, key, block).await
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::get_class_hash_at(self.client().await?, block, address).await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::get_transaction_receipt(self.client().await?, tx_hash).await
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::block_number(self.client().await?).await
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::block_hash(self.client().await?, block).await
+    }
+
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::get_events(self.client().await?, filter, continuation_token, chunk_size).await
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        let _connection = self.connection().await?;
+        StarknetProvider::trace_block(self.client().await?, block).await
+    }
+}
+
+/// Send `calls` one after the other on the same provider
+async fn call_each<P: StarknetProvider + ?Sized>(provider: &P, calls: Vec<FunctionCall>, block: BlockId) -> Vec<CallResult> {
+    let mut results = Vec::with_capacity(calls.len());
+    for call in calls {
+        results.push(provider.call(call, block).await);
+    }
+    results
+}
+
+/// JSON-RPC batch of starknet_call requests, the request id being the call's position
+fn encode_call_batch(calls: &[FunctionCall], block: BlockId) -> serde_json::Value {
+    let block_id = match block {
+        BlockId::Hash(hash) => serde_json::json!({ "block_hash": format!("0x{:x}", hash) }),
+        BlockId::Number(number) => serde_json::json!({ "block_number": number }),
+        BlockId::Tag(BlockTag::Latest) => serde_json::json!("latest"),
+        BlockId::Tag(BlockTag::Pending) => serde_json::json!("pending"),
+    };
+    let requests: Vec<serde_json::Value> = calls
+        .iter()
+        .enumerate()
+        .map(|(id, call)| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "starknet_call",
+                "params": {
+                    "request": {
+                        "contract_address": format!("0x{:x}", call.contract_address),
+                        "entry_point_selector": format!("0x{:x}", call.entry_point_selector),
+                        "calldata": call.calldata.iter().map(|felt| format!("0x{:x}", felt)).collect::<Vec<_>>(),
+                    },
+                    "block_id": block_id,
+                }
+            })
+        })
+        .collect();
+    serde_json::Value::Array(requests)
+}
+
+/// Match a batch response back to its calls
+/// Servers may answer in any order; a single (non-array) error object means batches aren't supported
+fn decode_call_batch(body: serde_json::Value, calls: &[FunctionCall]) -> Result<Vec<CallResult>, BlockchainError> {
+    let responses = match body {
+        serde_json::Value::Array(responses) => responses,
+        other => {
+            return Err(BlockchainError::Unsupported(format!("JSON-RPC batch requests ({})", other)));
+        }
+    };
+
+    let mut results: Vec<Option<CallResult>> = vec![None; calls.len()];
+    for response in responses {
+        let id = response
+            .get("id")
+            .and_then(serde_json::Value::as_u64)
+            .filter(|id| (*id as usize) < calls.len())
+            .ok_or_else(|| BlockchainError::Decode(format!("batch response without a valid id: {}", response)))?
+            as usize;
+        let address = calls[id].contract_address;
+
+        results[id] = Some(match (response.get("result"), response.get("error")) {
+            (Some(serde_json::Value::Array(felts)), _) => felts
+                .iter()
+                .map(|felt| {
+                    felt.as_str()
+                        .and_then(|hex| FieldElement::from_hex_be(hex).ok())
+                        .ok_or_else(|| BlockchainError::Decode(format!("invalid felt in call result: {}", felt)))
+                })
+                .collect(),
+            (_, Some(error)) => Err(map_rpc_error(error, address)),
+            _ => Err(BlockchainError::Decode(format!("malformed batch response: {}", response))),
+        });
+    }
+
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(id, result)| {
+            result.ok_or_else(|| BlockchainError::Decode(format!("batch response is missing call {}", id)))
+        })
+        .collect()
+}
+
+/// Map a raw JSON-RPC error object to our error type (same mapping as map_provider_error)
+fn map_rpc_error(error: &serde_json::Value, address: FieldElement) -> BlockchainError {
+    let code = error.get("code").and_then(serde_json::Value::as_i64).unwrap_or_default();
+    let message = error.get("message").and_then(serde_json::Value::as_str).unwrap_or_default();
+    let detail = error.get("data").map(|data| data.to_string()).unwrap_or_default();
+
+    match code {
+        // CONTRACT_NOT_FOUND
+        20 => BlockchainError::ContractNotFound(format!("0x{:x}", address)),
+        // CONTRACT_ERROR
+        40 if detail.contains("ENTRYPOINT_NOT_FOUND") => {
+            BlockchainError::EntryPointNotFound(format!("0x{:x}: {}", address, detail))
+        }
+        40 => BlockchainError::ContractCall(format!("0x{:x}: {}", address, detail)),
+        -32601 => BlockchainError::Unsupported(message.to_string()),
+        _ => BlockchainError::Transport(format!("JSON-RPC error {}: {} {}", code, message, detail)),
+    }
+}
+
+/// Map a starknet-rs provider error to our error type
+/// `address` is the contract the request targeted, used for ContractNotFound
+fn map_provider_error(err: ProviderError, address: FieldElement) -> BlockchainError {
+    match err {
+        ProviderError::StarknetError(StarknetError::ContractNotFound) => {
+            BlockchainError::ContractNotFound(format!("0x{:x}", address))
+        }
+        ProviderError::StarknetError(StarknetError::ContractError(data))
+            if data.revert_error.contains("ENTRYPOINT_NOT_FOUND") =>
+        {
+            BlockchainError::EntryPointNotFound(format!("0x{:x}: {}", address, data.revert_error))
+        }
+        ProviderError::StarknetError(StarknetError::ContractError(data)) => {
+            BlockchainError::ContractCall(format!("0x{:x}: {}", address, data.revert_error))
+        }
+        other => BlockchainError::Transport(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(address: u64) -> FunctionCall {
+        FunctionCall {
+            contract_address: FieldElement::from(address),
+            entry_point_selector: FieldElement::ONE,
+            calldata: vec![FieldElement::from(2u64)],
+        }
+    }
+
+    #[test]
+    fn test_endpoint_rate_limit_overrides_default() {
+        let pool = HttpPoolConfig {
+            rate_limit: Some(RateLimit::new(10, 10)),
+            endpoint_rate_limits: vec![("https://rpc-a.example".to_string(), RateLimit::new(2, 4))],
+            ..HttpPoolConfig::default()
+        };
+
+        // Url adds a trailing slash to bare hosts
+        assert_eq!(pool.rate_limit_for("https://rpc-a.example/"), Some(RateLimit::new(2, 4)));
+        assert_eq!(pool.rate_limit_for("https://rpc-b.example/"), Some(RateLimit::new(10, 10)));
+    }
+
+    #[test]
+    fn test_encode_call_batch() {
+        let batch = encode_call_batch(&[call(0xa), call(0xb)], BlockId::Number(42));
+
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+        assert_eq!(batch[1]["id"], 1);
+        assert_eq!(batch[1]["method"], "starknet_call");
+        assert_eq!(batch[1]["params"]["request"]["contract_address"], "0xb");
+        assert_eq!(batch[1]["params"]["request"]["calldata"][0], "0x2");
+        assert_eq!(batch[1]["params"]["block_id"]["block_number"], 42);
+    }
+
+    #[test]
+    fn test_decode_call_batch_out_of_order_with_errors() {
+        let calls = [call(0xa), call(0xb), call(0xc)];
+        let body = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 2, "error": { "code": 40, "message": "Contract error", "data": { "revert_error": "ENTRYPOINT_NOT_FOUND" } } },
+            { "jsonrpc": "2.0", "id": 0, "result": ["0x7", "0x0"] },
+            { "jsonrpc": "2.0", "id": 1, "error": { "code": 20, "message": "Contract not found" } },
+        ]);
+
+        let results = decode_call_batch(body, &calls).unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &vec![FieldElement::from(7u64), FieldElement::ZERO]);
+        assert!(matches!(results[1], Err(BlockchainError::ContractNotFound(_))));
+        assert!(matches!(results[2], Err(BlockchainError::EntryPointNotFound(_))));
+    }
+
+    #[test]
+    fn test_decode_call_batch_rejected() {
+        // Nodes without batch support answer with a single error object
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32600, "message": "Invalid request" } });
+        assert!(matches!(decode_call_batch(body, &[call(0xa)]), Err(BlockchainError::Unsupported(_))));
+
+        // A response missing one of the calls is not silently padded
+        let body = serde_json::json!([{ "jsonrpc": "2.0", "id": 0, "result": [] }]);
+        assert!(matches!(decode_call_batch(body, &[call(0xa), call(0xb)]), Err(BlockchainError::Decode(_))));
+    }
+}
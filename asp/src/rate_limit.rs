@@ -0,0 +1,152 @@
+// Client-side rate limiting of outbound RPC requests
+// Public RPC providers throttle per client, so each endpoint gets a token bucket. Buckets
+// are shared process-wide by endpoint URL: the API's BlockchainClient and the syncer hit the
+// same provider quota, so they draw from the same bucket.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Allowed request rate of one endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained requests per second
+    pub requests_per_second: u32,
+    /// Requests that may be sent at once after an idle period
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            requests_per_second: requests_per_second.max(1),
+            burst: burst.max(1),
+        }
+    }
+}
+
+struct BucketState {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket: holds up to `burst` tokens, refilled at `requests_per_second`
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                limit,
+                tokens: limit.burst as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent, then take a token
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                state.refill();
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / state.limit.requests_per_second as f64)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take a token if one is available, otherwise the time until one is
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - state.tokens) / state.limit.requests_per_second as f64))
+    }
+
+    /// Whether the bucket refilled to its burst, i.e. is as good as a new one
+    pub fn is_full(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+        state.tokens >= state.limit.burst as f64
+    }
+
+    /// Change the rate, keeping the tokens already accumulated (capped at the new burst)
+    pub fn set_limit(&self, limit: RateLimit) {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+        state.limit = limit;
+        state.tokens = state.tokens.min(limit.burst as f64);
+    }
+}
+
+impl BucketState {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.requests_per_second as f64).min(self.limit.burst as f64);
+        self.refilled_at = now;
+    }
+}
+
+/// Buckets by endpoint URL
+static BUCKETS: Lazy<Mutex<HashMap<String, Arc<TokenBucket>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The process-wide bucket of `endpoint`, created or updated to `limit`
+pub fn shared_bucket(endpoint: &str, limit: RateLimit) -> Arc<TokenBucket> {
+    let mut buckets = BUCKETS.lock().unwrap();
+    match buckets.get(endpoint) {
+        Some(bucket) => {
+            bucket.set_limit(limit);
+            bucket.clone()
+        }
+        None => {
+            let bucket = Arc::new(TokenBucket::new(limit));
+            buckets.insert(endpoint.to_string(), bucket.clone());
+            bucket
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_burst_then_throttle() {
+        let bucket = TokenBucket::new(RateLimit::new(50, 2));
+        let started = Instant::now();
+
+        // The burst goes out immediately...
+        bucket.acquire().await;
+        bucket.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(15));
+
+        // ...then requests are spaced by 1/50 s
+        bucket.acquire().await;
+        bucket.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(35));
+    }
+
+    #[test]
+    fn test_buckets_are_shared_by_endpoint() {
+        let a = shared_bucket("https://rate-limit-test.example", RateLimit::new(10, 5));
+        let b = shared_bucket("https://rate-limit-test.example", RateLimit::new(20, 5));
+        let other = shared_bucket("https://other-rate-limit-test.example", RateLimit::new(10, 5));
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &other));
+        assert_eq!(a.state.lock().unwrap().limit.requests_per_second, 20);
+    }
+}
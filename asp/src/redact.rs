@@ -0,0 +1,132 @@
+// Log redaction for privacy-sensitive values
+// Every log line that may contain a commitment, nullifier, recipient or the leaf index
+// of a looked-up commitment goes through RedactionPolicy, so operators have a single
+// switch (LOG_REDACTION) for the privacy/debugging tradeoff.
+
+use starknet::core::types::FieldElement;
+use std::fmt;
+use std::str::FromStr;
+
+/// How much of a sensitive value may appear in logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactionPolicy {
+    /// Log everything verbatim (local debugging only)
+    RedactNone,
+    /// Mask commitments and the leaf indices that identify them
+    #[default]
+    RedactCommitments,
+    /// Mask every sensitive value
+    RedactAll,
+}
+
+/// Kinds of values that can deanonymize users when logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensitive {
+    Commitment,
+    /// Leaf index of a specific commitment (links the commitment to its deposit)
+    LeafIndex,
+    Nullifier,
+    Recipient,
+}
+
+impl fmt::Display for Sensitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Sensitive::Commitment => "commitment",
+            Sensitive::LeafIndex => "leaf index",
+            Sensitive::Nullifier => "nullifier",
+            Sensitive::Recipient => "recipient",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for RedactionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(RedactionPolicy::RedactNone),
+            "commitments" => Ok(RedactionPolicy::RedactCommitments),
+            "all" => Ok(RedactionPolicy::RedactAll),
+            other => Err(format!(
+                "Invalid redaction policy '{}' (expected none, commitments or all)",
+                other
+            )),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// Read the policy from LOG_REDACTION (none | commitments | all), defaulting to commitments
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_REDACTION") {
+            Ok(value) => value.parse().unwrap_or_else(|e| {
+                eprintln!("⚠️  {} - using default", e);
+                RedactionPolicy::default()
+            }),
+            Err(_) => RedactionPolicy::default(),
+        }
+    }
+
+    /// Whether values of this kind must be masked
+    pub fn masks(&self, kind: Sensitive) -> bool {
+        match self {
+            RedactionPolicy::RedactNone => false,
+            RedactionPolicy::RedactCommitments => {
+                matches!(kind, Sensitive::Commitment | Sensitive::LeafIndex)
+            }
+            RedactionPolicy::RedactAll => true,
+        }
+    }
+
+    /// Format a value for logging, masking it if the policy requires
+    pub fn redact(&self, kind: Sensitive, value: impl fmt::Display) -> String {
+        if self.masks(kind) {
+            format!("<redacted {}>", kind)
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Format a felt as 0x-hex for logging, masking it if the policy requires
+    pub fn felt(&self, kind: Sensitive, value: &FieldElement) -> String {
+        self.redact(kind, format_args!("0x{:x}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_masks_commitments() {
+        let commitment = FieldElement::from_hex_be("0xabc123").unwrap();
+        let policy = RedactionPolicy::default();
+
+        let logged = policy.felt(Sensitive::Commitment, &commitment);
+        assert_eq!(logged, "<redacted commitment>");
+        assert!(!logged.contains("abc123"));
+        assert_eq!(policy.redact(Sensitive::LeafIndex, 7), "<redacted leaf index>");
+        // Nullifiers are only masked under RedactAll
+        assert_eq!(policy.felt(Sensitive::Nullifier, &commitment), "0xabc123");
+    }
+
+    #[test]
+    fn test_redact_none_shows_commitments() {
+        let commitment = FieldElement::from_hex_be("0xabc123").unwrap();
+        let policy = RedactionPolicy::RedactNone;
+
+        assert_eq!(policy.felt(Sensitive::Commitment, &commitment), "0xabc123");
+        assert_eq!(policy.redact(Sensitive::LeafIndex, 7), "7");
+    }
+
+    #[test]
+    fn test_redact_all_masks_everything() {
+        let value = FieldElement::from_hex_be("0x1").unwrap();
+        let policy: RedactionPolicy = "all".parse().unwrap();
+
+        assert_eq!(policy.felt(Sensitive::Recipient, &value), "<redacted recipient>");
+        assert_eq!(policy.felt(Sensitive::Nullifier, &value), "<redacted nullifier>");
+    }
+}
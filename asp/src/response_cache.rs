@@ -0,0 +1,211 @@
+// TTL cache of view call results
+// The API and the syncer ask for the same root, root checks and token metadata many times
+// per block. Results are keyed by contract, function, calldata and block; each function
+// has its own TTL and functions without one are never cached. Results at the latest block
+// are dropped as soon as a newer block is observed, results at a numbered block never
+// change and only expire with their TTL.
+
+use starknet::core::types::{BlockId, BlockTag, FieldElement, FunctionCall};
+use starknet::core::utils::get_selector_from_name;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RESPONSE_CACHE_CAPACITY: usize = 1024;
+
+/// Default TTL per view function
+pub fn default_ttls() -> Vec<(&'static str, Duration)> {
+    vec![
+        ("get_merkle_root", Duration::from_secs(5)),
+        ("is_root_known", Duration::from_secs(30)),
+        ("get_known_roots_count", Duration::from_secs(5)),
+        ("name", Duration::from_secs(3600)),
+        ("symbol", Duration::from_secs(3600)),
+        ("decimals", Duration::from_secs(3600)),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BlockKey {
+    Latest,
+    Pending,
+    Number(u64),
+    Hash(FieldElement),
+}
+
+impl From<BlockId> for BlockKey {
+    fn from(block: BlockId) -> Self {
+        match block {
+            BlockId::Tag(BlockTag::Latest) => BlockKey::Latest,
+            BlockId::Tag(BlockTag::Pending) => BlockKey::Pending,
+            BlockId::Number(number) => BlockKey::Number(number),
+            BlockId::Hash(hash) => BlockKey::Hash(hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    contract: FieldElement,
+    selector: FieldElement,
+    calldata: Vec<FieldElement>,
+    block: BlockKey,
+}
+
+struct CacheEntry {
+    result: Vec<FieldElement>,
+    expires_at: Instant,
+}
+
+pub struct ResponseCache {
+    capacity: usize,
+    /// selector -> TTL
+    ttls: HashMap<FieldElement, Duration>,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Newest block reported through `observe_block`
+    latest_block: u64,
+}
+
+impl ResponseCache {
+    /// A capacity of 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        let mut cache = Self {
+            capacity,
+            ttls: HashMap::new(),
+            entries: HashMap::new(),
+            latest_block: 0,
+        };
+        for (function, ttl) in default_ttls() {
+            cache.set_ttl(function, Some(ttl));
+        }
+        cache
+    }
+
+    /// Cache `function` for `ttl`, or stop caching it with None
+    pub fn set_ttl(&mut self, function: &str, ttl: Option<Duration>) {
+        let Ok(selector) = get_selector_from_name(function) else { return };
+        match ttl {
+            Some(ttl) if !ttl.is_zero() => {
+                self.ttls.insert(selector, ttl);
+            }
+            _ => {
+                self.ttls.remove(&selector);
+                self.entries.retain(|key, _| key.selector != selector);
+            }
+        }
+    }
+
+    /// Cached result of `call` at `block`, if still valid
+    pub fn get(&mut self, call: &FunctionCall, block: BlockId) -> Option<Vec<FieldElement>> {
+        let key = cache_key(call, block);
+        match self.entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.result.clone()),
+            Some(_) => {
+                self.entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Record a fresh result; ignored for functions without a TTL
+    pub fn insert(&mut self, call: &FunctionCall, block: BlockId, result: Vec<FieldElement>) {
+        let Some(ttl) = self.ttls.get(&call.entry_point_selector).copied() else { return };
+        if self.capacity == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        if self.entries.len() >= self.capacity {
+            self.entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if self.entries.len() >= self.capacity {
+            // Still full of live entries: drop the one closest to expiry
+            if let Some(key) = self.entries.iter().min_by_key(|(_, entry)| entry.expires_at).map(|(key, _)| key.clone()) {
+                self.entries.remove(&key);
+            }
+        }
+
+        self.entries.insert(cache_key(call, block), CacheEntry { result, expires_at: now + ttl });
+    }
+
+    /// A new block was seen: drop every result read at the latest or pending block
+    /// Returns true if `block_number` is newer than the last one observed
+    pub fn observe_block(&mut self, block_number: u64) -> bool {
+        if block_number <= self.latest_block {
+            return false;
+        }
+        self.latest_block = block_number;
+        self.invalidate_latest();
+        true
+    }
+
+    /// Drop every result read at the latest or pending block
+    pub fn invalidate_latest(&mut self) {
+        self.entries
+            .retain(|key, _| !matches!(key.block, BlockKey::Latest | BlockKey::Pending));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn cache_key(call: &FunctionCall, block: BlockId) -> CacheKey {
+    CacheKey {
+        contract: call.contract_address,
+        selector: call.entry_point_selector,
+        calldata: call.calldata.clone(),
+        block: block.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function: &str, calldata: Vec<FieldElement>) -> FunctionCall {
+        FunctionCall {
+            contract_address: FieldElement::from(0x2a1u64),
+            entry_point_selector: get_selector_from_name(function).unwrap(),
+            calldata,
+        }
+    }
+
+    const LATEST: BlockId = BlockId::Tag(BlockTag::Latest);
+
+    #[test]
+    fn test_keyed_by_calldata_and_block() {
+        let mut cache = ResponseCache::new(16);
+        let known = call("is_root_known", vec![FieldElement::ONE]);
+        cache.insert(&known, LATEST, vec![FieldElement::ONE]);
+
+        assert_eq!(cache.get(&known, LATEST), Some(vec![FieldElement::ONE]));
+        assert_eq!(cache.get(&call("is_root_known", vec![FieldElement::from(2u64)]), LATEST), None);
+        assert_eq!(cache.get(&known, BlockId::Number(5)), None);
+    }
+
+    #[test]
+    fn test_only_functions_with_a_ttl_are_cached() {
+        let mut cache = ResponseCache::new(16);
+        let nullifier = call("is_nullifier_spent", vec![FieldElement::ONE]);
+        cache.insert(&nullifier, LATEST, vec![FieldElement::ZERO]);
+        assert_eq!(cache.get(&nullifier, LATEST), None);
+
+        cache.set_ttl("get_merkle_root", None);
+        cache.insert(&call("get_merkle_root", vec![]), LATEST, vec![FieldElement::ONE]);
+        assert_eq!(cache.get(&call("get_merkle_root", vec![]), LATEST), None);
+    }
+
+    #[test]
+    fn test_new_block_invalidates_latest_only() {
+        let mut cache = ResponseCache::new(16);
+        let root = call("get_merkle_root", vec![]);
+        cache.insert(&root, LATEST, vec![FieldElement::ONE]);
+        cache.insert(&root, BlockId::Number(10), vec![FieldElement::ONE]);
+
+        assert!(cache.observe_block(11));
+        assert!(!cache.observe_block(11));
+        assert_eq!(cache.get(&root, LATEST), None);
+        assert_eq!(cache.get(&root, BlockId::Number(10)), Some(vec![FieldElement::ONE]));
+    }
+}
@@ -0,0 +1,143 @@
+// Signed responses
+// With ASP_SIGNING_KEY set (the operator's Stark private key), Merkle paths and roots are
+// served with a `signature`: the operator's ECDSA signature over a Poseidon hash of what the
+// response vouches for and when it was signed. A wallet pinning the operator's public key
+// checks it (frontend/src/lib/asp-signature.ts) and so notices a compromised or misbehaving
+// ASP handing out paths to roots the operator never served.
+
+use crate::merkle::MerkleProof;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::FieldElement;
+use starknet::core::utils::cairo_short_string_to_felt;
+use starknet::signers::SigningKey;
+use starknet_crypto::poseidon_hash_many;
+use utoipa::ToSchema;
+
+/// Operator signature attached to a response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ResponseSignature {
+    /// Operator's Stark public key
+    pub public_key: String,
+    /// Unix seconds at which the response was signed
+    pub timestamp: u64,
+    pub r: String,
+    pub s: String,
+}
+
+/// What a signature vouches for
+pub enum Statement<'a> {
+    /// The deposit tree had `leaf_count` leaves under `root`
+    Root { root: &'a str, leaf_count: u32 },
+    /// `proof.leaf` sits at `proof.leaf_index` under `proof.root` along `proof.path`
+    Path(&'a MerkleProof),
+}
+
+impl Statement<'_> {
+    /// Poseidon hash of the statement signed at `timestamp`:
+    /// root: H('zylith-asp-root', timestamp, root, leaf_count)
+    /// path: H('zylith-asp-path', timestamp, root, leaf, leaf_index, depth, path.., path_indices..)
+    pub fn hash(&self, timestamp: u64) -> Result<FieldElement, String> {
+        let felt = |value: &str| FieldElement::from_hex_be(value).map_err(|e| format!("{} is not a felt: {}", value, e));
+        let mut elements = Vec::new();
+        match self {
+            Statement::Root { root, leaf_count } => {
+                elements.extend([tag("zylith-asp-root"), timestamp.into(), felt(root)?, (*leaf_count).into()]);
+            }
+            Statement::Path(proof) => {
+                elements.extend([tag("zylith-asp-path"), timestamp.into(), felt(&proof.root)?, felt(&proof.leaf)?]);
+                elements.extend([proof.leaf_index.into(), (proof.path.len() as u64).into()]);
+                for sibling in &proof.path {
+                    elements.push(felt(sibling)?);
+                }
+                elements.extend(proof.path_indices.iter().map(|index| FieldElement::from(*index)));
+            }
+        }
+        Ok(poseidon_hash_many(&elements))
+    }
+}
+
+fn tag(name: &str) -> FieldElement {
+    cairo_short_string_to_felt(name).expect("tags are short strings")
+}
+
+/// Signs responses with the operator's key
+pub struct ResponseSigner {
+    key: SigningKey,
+}
+
+impl ResponseSigner {
+    /// Key from ASP_SIGNING_KEY (hex), None if unset
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("ASP_SIGNING_KEY") {
+            Ok(key) => {
+                let secret = FieldElement::from_hex_be(key.trim()).map_err(|e| format!("ASP_SIGNING_KEY is not a hex felt: {}", e))?;
+                Ok(Some(Self { key: SigningKey::from_secret_scalar(secret) }))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn public_key(&self) -> FieldElement {
+        self.key.verifying_key().scalar()
+    }
+
+    /// Signature of `statement` as of now
+    pub fn sign(&self, statement: Statement) -> Result<ResponseSignature, String> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_err(|e| e.to_string())?.as_secs();
+        self.sign_at(statement, timestamp)
+    }
+
+    fn sign_at(&self, statement: Statement, timestamp: u64) -> Result<ResponseSignature, String> {
+        let signature = self.key.sign(&statement.hash(timestamp)?).map_err(|e| e.to_string())?;
+        Ok(ResponseSignature {
+            public_key: format!("0x{:x}", self.public_key()),
+            timestamp,
+            r: format!("0x{:x}", signature.r),
+            s: format!("0x{:x}", signature.s),
+        })
+    }
+
+    /// Attach a signature to `proof`, leaving it unsigned if that fails
+    pub fn sign_proof(&self, proof: &mut MerkleProof) {
+        proof.signature = None;
+        match self.sign(Statement::Path(proof)) {
+            Ok(signature) => proof.signature = Some(signature),
+            Err(e) => eprintln!("[ASP] ⚠️  Failed to sign path to {}: {}", proof.root, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures_verify_against_the_operator_key() {
+        let signer = ResponseSigner { key: SigningKey::from_secret_scalar(FieldElement::from(0x5eedu64)) };
+        let mut proof = MerkleProof {
+            leaf: "0x1234".to_string(),
+            leaf_index: 3,
+            path: vec!["0x1".to_string(), "0x2".to_string()],
+            path_indices: vec![1, 1],
+            root: "0xabcd".to_string(),
+            signature: None,
+        };
+        signer.sign_proof(&mut proof);
+        let signature = proof.signature.clone().unwrap();
+        assert_eq!(signature.public_key, format!("0x{:x}", signer.public_key()));
+
+        let verifies = |statement: Statement, signature: &ResponseSignature| {
+            let hash = statement.hash(signature.timestamp).unwrap();
+            let r = FieldElement::from_hex_be(&signature.r).unwrap();
+            let s = FieldElement::from_hex_be(&signature.s).unwrap();
+            starknet_crypto::verify(&signer.public_key(), &hash, &r, &s).unwrap()
+        };
+        assert!(verifies(Statement::Path(&proof), &signature));
+        // Any change to the path, or a replayed timestamp, breaks it
+        proof.path_indices[0] = 0;
+        assert!(!verifies(Statement::Path(&proof), &signature));
+        let root = signer.sign_at(Statement::Root { root: "0xabcd", leaf_count: 4 }, 1_700_000_000).unwrap();
+        assert!(verifies(Statement::Root { root: "0xabcd", leaf_count: 4 }, &root));
+        assert!(!verifies(Statement::Root { root: "0xabcd", leaf_count: 4 }, &ResponseSignature { timestamp: 1_700_000_001, ..root }));
+    }
+}
@@ -0,0 +1,243 @@
+// Retry layer for RPC calls
+// RetryProvider wraps any StarknetProvider and re-sends requests that failed for transient
+// reasons (network error, timeout) with exponential backoff, so the client and the syncer
+// get the same retry behaviour without retry loops at every call site.
+
+use crate::error::BlockchainError;
+use crate::provider::{CallResult, InvocationTrace, StarknetProvider, TxReceipt};
+use async_trait::async_trait;
+use rand::Rng;
+use starknet::core::types::{BlockId, EventFilter, EventsPage, FieldElement, FunctionCall};
+use std::future::Future;
+use std::time::Duration;
+
+/// When and how often a failed request is sent again
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every following one
+    pub base_delay: Duration,
+    /// Upper bound of a single delay
+    pub max_delay: Duration,
+    /// Random share (0.0 to 1.0) removed from each delay, so clients don't retry in lockstep
+    pub jitter: f64,
+    /// Which errors are worth retrying
+    pub retry_on: fn(&BlockchainError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            retry_on: BlockchainError::is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that sends every request exactly once
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Backoff before retry number `retry` (1-based), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..=jitter))
+    }
+}
+
+/// StarknetProvider middleware retrying transient failures of the inner provider
+pub struct RetryProvider<P> {
+    inner: P,
+    policy: RetryPolicy,
+}
+
+impl<P: StarknetProvider> RetryProvider<P> {
+    pub fn new(inner: P, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    async fn with_retry<'a, T, F, Fut>(&'a self, operation: &str, request: F) -> Result<T, BlockchainError>
+    where
+        F: Fn(&'a P) -> Fut,
+        Fut: Future<Output = Result<T, BlockchainError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request(&self.inner).await {
+                Err(e) if attempt < self.policy.max_attempts && (self.policy.retry_on)(&e) => {
+                    let delay = self.policy.delay(attempt);
+                    eprintln!(
+                        "[ASP] ⚠️  {} failed ({}), retry {}/{} in {:?}",
+                        operation,
+                        e,
+                        attempt,
+                        self.policy.max_attempts - 1,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: StarknetProvider> StarknetProvider for RetryProvider<P> {
+    async fn call(&self, call: FunctionCall, block: BlockId) -> Result<Vec<FieldElement>, BlockchainError> {
+        self.with_retry("call", |provider| provider.call(call.clone(), block)).await
+    }
+
+    async fn batch_call(&self, calls: Vec<FunctionCall>, block: BlockId) -> Result<Vec<CallResult>, BlockchainError> {
+        self.with_retry("batch_call", |provider| provider.batch_call(calls.clone(), block)).await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: FieldElement,
+        key: FieldElement,
+        block: BlockId,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.with_retry("get_storage_at", |provider| provider.get_storage_at(address, key, block))
+            .await
+    }
+
+    async fn get_class_hash_at(
+        &self,
+        block: BlockId,
+        address: FieldElement,
+    ) -> Result<FieldElement, BlockchainError> {
+        self.with_retry("get_class_hash_at", |provider| provider.get_class_hash_at(block, address))
+            .await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: FieldElement) -> Result<TxReceipt, BlockchainError> {
+        self.with_retry("get_transaction_receipt", |provider| provider.get_transaction_receipt(tx_hash))
+            .await
+    }
+
+    async fn block_number(&self) -> Result<u64, BlockchainError> {
+        self.with_retry("block_number", |provider| provider.block_number()).await
+    }
+
+    async fn block_hash(&self, block: u64) -> Result<FieldElement, BlockchainError> {
+        self.with_retry("block_hash", |provider| provider.block_hash(block)).await
+    }
+
+    async fn get_events(
+        &self,
+        filter: EventFilter,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, BlockchainError> {
+        self.with_retry("get_events", |provider| {
+            provider.get_events(filter.clone(), continuation_token.clone(), chunk_size)
+        })
+        .await
+    }
+
+    async fn trace_block(&self, block: u64) -> Result<Vec<InvocationTrace>, BlockchainError> {
+        self.with_retry("trace_block", |provider| provider.trace_block(block)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+    use starknet::core::types::BlockTag;
+    use starknet::core::utils::get_selector_from_name;
+
+    fn zylith_address() -> FieldElement {
+        FieldElement::from(0x2a1u64)
+    }
+
+    fn root_call() -> FunctionCall {
+        FunctionCall {
+            contract_address: zylith_address(),
+            entry_point_selector: get_selector_from_name("get_merkle_root").unwrap(),
+            calldata: vec![],
+        }
+    }
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn flaky(failures: usize) -> MockProvider {
+        MockProvider::new()
+            .with_call_result(zylith_address(), "get_merkle_root", vec![FieldElement::ONE])
+            .with_transient_failures(failures, BlockchainError::Transport("connection reset".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let provider = RetryProvider::new(flaky(2), fast_policy(3));
+
+        let root = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap();
+        assert_eq!(root, vec![FieldElement::ONE]);
+        assert_eq!(provider.inner().call_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let provider = RetryProvider::new(flaky(5), fast_policy(3));
+
+        let err = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::Transport(_)));
+        assert_eq!(provider.inner().call_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_definitive_errors() {
+        // Nothing deployed: retrying can't help
+        let provider = RetryProvider::new(MockProvider::new(), fast_policy(3));
+
+        let err = provider.call(root_call(), BlockId::Tag(BlockTag::Latest)).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ContractNotFound(_)));
+        assert_eq!(provider.inner().call_count(), 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max_delay() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(350),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+    }
+}
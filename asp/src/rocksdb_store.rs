@@ -0,0 +1,332 @@
+// RocksDB sync state
+// For deployments with millions of deposits, where rewriting a JSON file or scanning SQLite
+// pages on every save gets costly. Column families: `leaves` (commitment per leaf index),
+// `events` (block and transaction of each leaf's Deposit event), `nullifiers` (in spend
+// order) and `marks` (scanned blocks kept for reorgs); the scan position sits in the default
+// one. Keys are big-endian positions, so the append-heavy sync writes in key order. Roots
+// aren't stored: they follow from the leaves (and the node file keeps the hashed tree).
+
+use crate::checkpoint::{BlockMark, SyncCheckpoint};
+use crate::commitment_index::LeafOrigin;
+use crate::state_store::{StateStore, StoredLeaf};
+use rocksdb::{ColumnFamilyDescriptor, DBCompressionType, Direction, IteratorMode, Options, WriteBatch, WriteOptions, DB};
+use starknet::core::types::FieldElement;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const LEAVES: &str = "leaves";
+const EVENTS: &str = "events";
+const NULLIFIERS: &str = "nullifiers";
+const MARKS: &str = "marks";
+/// Scan position (SyncCheckpoint without its per-leaf data), as JSON in the default family
+const STATE_KEY: &[u8] = b"sync_state";
+/// Layout version of the database (u32, big-endian) in the default family
+const SCHEMA_KEY: &[u8] = b"schema_version";
+/// Layout this build reads and writes
+const SCHEMA_VERSION: u32 = 1;
+/// Past every 4-byte position key, to delete a tail with one range tombstone
+const END_OF_POSITIONS: &[u8] = &[0xff; 5];
+
+/// Sync state in a RocksDB database
+pub struct RocksDbStore {
+    path: PathBuf,
+    db: DB,
+    /// Leaves and nullifiers as of the last save or load, to write only what changed
+    saved: Mutex<Saved>,
+}
+
+#[derive(Default)]
+struct Saved {
+    leaves: Vec<(FieldElement, Option<LeafOrigin>)>,
+    nullifiers: Vec<FieldElement>,
+}
+
+impl RocksDbStore {
+    /// Open (or create) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options.increase_parallelism(std::thread::available_parallelism().map_or(2, |n| n.get() as i32));
+        // Large memtables and dynamic level sizes: the workload is a stream of appends
+        options.set_write_buffer_size(64 << 20);
+        options.set_max_write_buffer_number(4);
+        options.set_level_compaction_dynamic_level_bytes(true);
+        options.set_compression_type(DBCompressionType::Lz4);
+
+        let families = [LEAVES, EVENTS, NULLIFIERS, MARKS].map(|name| ColumnFamilyDescriptor::new(name, options.clone()));
+        let db = DB::open_cf_descriptors(&options, &path, families).map_err(db_error)?;
+        let store = Self {
+            path,
+            db,
+            saved: Mutex::new(Saved::default()),
+        };
+        store.check_schema()?;
+        store.load_saved()?;
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record the layout version of a new database; refuse one from a newer release
+    fn check_schema(&self) -> io::Result<()> {
+        match self.db.get(SCHEMA_KEY).map_err(db_error)? {
+            Some(version) => {
+                let version = position(&version)?;
+                if version > SCHEMA_VERSION {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("RocksDB state layout {} was written by a newer release (this one knows up to {})", version, SCHEMA_VERSION),
+                    ));
+                }
+                Ok(())
+            }
+            None => self.db.put(SCHEMA_KEY, SCHEMA_VERSION.to_be_bytes()).map_err(db_error),
+        }
+    }
+
+    /// Saved checkpoint, None if nothing was saved yet
+    pub fn load_saved(&self) -> io::Result<Option<SyncCheckpoint>> {
+        let Some(state) = self.db.get(STATE_KEY).map_err(db_error)? else {
+            return Ok(None);
+        };
+        let mut checkpoint: SyncCheckpoint = serde_json::from_slice(&state)?;
+
+        let leaves: Vec<FieldElement> = self.values(LEAVES)?.iter().map(|value| felt(value)).collect::<io::Result<_>>()?;
+        let mut origins = vec![None; leaves.len()];
+        for (key, value) in self.entries(EVENTS)? {
+            let index = position(&key)? as usize;
+            if index < origins.len() {
+                origins[index] = Some(decode_origin(&value)?);
+            }
+        }
+        checkpoint.nullifiers = self.values(NULLIFIERS)?.iter().map(|value| felt(value)).collect::<io::Result<_>>()?;
+        checkpoint.recent_blocks = self.values(MARKS)?.iter().map(|value| decode_mark(value)).collect::<io::Result<_>>()?;
+
+        *self.saved.lock().unwrap() = Saved {
+            leaves: leaves.iter().copied().zip(origins.iter().copied()).collect(),
+            nullifiers: checkpoint.nullifiers.clone(),
+        };
+        checkpoint.leaves = leaves;
+        checkpoint.leaf_origins = origins;
+        Ok(Some(checkpoint))
+    }
+
+    fn family(&self, name: &str) -> io::Result<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("column family {}", name)))
+    }
+
+    fn entries(&self, name: &str) -> io::Result<Vec<(Box<[u8]>, Box<[u8]>)>> {
+        self.db
+            .iterator_cf(self.family(name)?, IteratorMode::Start)
+            .collect::<Result<_, _>>()
+            .map_err(db_error)
+    }
+
+    /// Values of a family in key order
+    fn values(&self, name: &str) -> io::Result<Vec<Box<[u8]>>> {
+        Ok(self.entries(name)?.into_iter().map(|(_, value)| value).collect())
+    }
+}
+
+impl StateStore for RocksDbStore {
+    fn load(&self) -> SyncCheckpoint {
+        match self.load_saved() {
+            Ok(checkpoint) => checkpoint.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read {}: {}", self.path.display(), e);
+                SyncCheckpoint::default()
+            }
+        }
+    }
+
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        let (leaves_cf, events_cf) = (self.family(LEAVES)?, self.family(EVENTS)?);
+        let start = key(from as usize);
+        let mut leaves = Vec::new();
+        for entry in self.db.iterator_cf(leaves_cf, IteratorMode::From(&start, Direction::Forward)) {
+            let (key, value) = entry.map_err(db_error)?;
+            let index = position(&key)?;
+            if index >= to {
+                break;
+            }
+            let origin = match self.db.get_cf(events_cf, &key).map_err(db_error)? {
+                Some(event) => Some(decode_origin(&event)?),
+                None => None,
+            };
+            leaves.push(StoredLeaf { index, commitment: felt(&value)?, origin });
+        }
+        Ok(leaves)
+    }
+
+    /// Compact every family, dropping the tombstones of rolled back and pruned entries
+    fn maintain(&self) -> io::Result<()> {
+        self.db.compact_range::<&[u8], &[u8]>(None, None);
+        for name in [LEAVES, EVENTS, NULLIFIERS, MARKS] {
+            self.db.compact_range_cf::<&[u8], &[u8]>(self.family(name)?, None, None);
+        }
+        Ok(())
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let leaves: Vec<(FieldElement, Option<LeafOrigin>)> = checkpoint
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (*leaf, checkpoint.leaf_origins.get(index).copied().flatten()))
+            .collect();
+        let mut saved = self.saved.lock().unwrap();
+        let (leaves_cf, events_cf) = (self.family(LEAVES)?, self.family(EVENTS)?);
+        let (nullifiers_cf, marks_cf) = (self.family(NULLIFIERS)?, self.family(MARKS)?);
+
+        let mut batch = WriteBatch::default();
+        let position_state = SyncCheckpoint {
+            leaves: Vec::new(),
+            leaf_origins: Vec::new(),
+            recent_blocks: Vec::new(),
+            nullifiers: Vec::new(),
+            ..checkpoint.clone()
+        };
+        batch.put(STATE_KEY, serde_json::to_vec(&position_state)?);
+
+        batch.delete_range_cf(leaves_cf, &key(leaves.len())[..], END_OF_POSITIONS);
+        batch.delete_range_cf(events_cf, &key(leaves.len())[..], END_OF_POSITIONS);
+        for (index, (leaf, origin)) in leaves.iter().enumerate() {
+            if saved.leaves.get(index) == Some(&(*leaf, *origin)) {
+                continue;
+            }
+            batch.put_cf(leaves_cf, key(index), leaf.to_bytes_be());
+            match origin {
+                Some(origin) => batch.put_cf(events_cf, key(index), encode_origin(origin)),
+                None => batch.delete_cf(events_cf, key(index)),
+            }
+        }
+
+        batch.delete_range_cf(nullifiers_cf, &key(checkpoint.nullifiers.len())[..], END_OF_POSITIONS);
+        for (position, nullifier) in checkpoint.nullifiers.iter().enumerate() {
+            if saved.nullifiers.get(position) != Some(nullifier) {
+                batch.put_cf(nullifiers_cf, key(position), nullifier.to_bytes_be());
+            }
+        }
+
+        // At most REORG_WINDOW marks: rewritten whole
+        batch.delete_range_cf(marks_cf, &[0u8; 8][..], &[0xffu8; 9][..]);
+        for mark in &checkpoint.recent_blocks {
+            batch.put_cf(marks_cf, mark.number.to_be_bytes(), encode_mark(mark));
+        }
+
+        // Synced so a power loss can't drop a save the syncer already moved past
+        let mut write_options = WriteOptions::default();
+        write_options.set_sync(true);
+        self.db.write_opt(batch, &write_options).map_err(db_error)?;
+        *saved = Saved {
+            leaves,
+            nullifiers: checkpoint.nullifiers.clone(),
+        };
+        Ok(())
+    }
+}
+
+fn key(position: usize) -> [u8; 4] {
+    (position as u32).to_be_bytes()
+}
+
+fn position(key: &[u8]) -> io::Result<u32> {
+    key.try_into().map(u32::from_be_bytes).map_err(|_| invalid("position key"))
+}
+
+fn felt(bytes: &[u8]) -> io::Result<FieldElement> {
+    FieldElement::from_byte_slice_be(bytes).map_err(|_| invalid("felt"))
+}
+
+/// Block number (8 bytes), transaction hash (32)
+fn encode_origin(origin: &LeafOrigin) -> Vec<u8> {
+    let mut value = origin.block_number.to_be_bytes().to_vec();
+    value.extend_from_slice(&origin.transaction_hash.to_bytes_be());
+    value
+}
+
+fn decode_origin(value: &[u8]) -> io::Result<LeafOrigin> {
+    if value.len() != 40 {
+        return Err(invalid("deposit event"));
+    }
+    Ok(LeafOrigin {
+        block_number: u64::from_be_bytes(value[..8].try_into().unwrap()),
+        transaction_hash: felt(&value[8..])?,
+    })
+}
+
+/// Block number (8 bytes), block hash (32), leaf count (4), nullifier count (4)
+fn encode_mark(mark: &BlockMark) -> Vec<u8> {
+    let mut value = mark.number.to_be_bytes().to_vec();
+    value.extend_from_slice(&mark.hash.to_bytes_be());
+    value.extend_from_slice(&mark.leaf_count.to_be_bytes());
+    value.extend_from_slice(&mark.nullifier_count.to_be_bytes());
+    value
+}
+
+fn decode_mark(value: &[u8]) -> io::Result<BlockMark> {
+    if value.len() != 48 {
+        return Err(invalid("block mark"));
+    }
+    Ok(BlockMark {
+        number: u64::from_be_bytes(value[..8].try_into().unwrap()),
+        hash: felt(&value[8..40])?,
+        leaf_count: u32::from_be_bytes(value[40..44].try_into().unwrap()),
+        nullifier_count: u32::from_be_bytes(value[44..].try_into().unwrap()),
+    })
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupt {} in RocksDB state", what))
+}
+
+fn db_error(e: rocksdb::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedupe::EventDeduper;
+
+    #[test]
+    fn test_rocksdb_round_trip_and_rollback() {
+        let path = std::env::temp_dir().join(format!("asp_rocksdb_store_test_{}", std::process::id()));
+        let store = RocksDbStore::open(&path).unwrap();
+        assert_eq!(store.load_saved().unwrap(), None);
+
+        let origin = LeafOrigin { block_number: 12, transaction_hash: FieldElement::from(0xabcu64) };
+        let mut checkpoint = SyncCheckpoint {
+            last_synced_block: 20,
+            last_leaf_index: Some(2),
+            scan_to_block: Some(40),
+            continuation_token: Some("3".to_string()),
+            leaves: (1000u64..1003).map(FieldElement::from).collect(),
+            leaf_origins: vec![Some(origin), None, Some(origin)],
+            recent_blocks: vec![BlockMark { number: 15, hash: FieldElement::from(0x15u64), leaf_count: 1, nullifier_count: 1 }],
+            applied_events: EventDeduper::default(),
+            nullifiers: vec![FieldElement::from(0x3f1au64), FieldElement::from(0x3f1bu64)],
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load(), checkpoint);
+        let range = store.leaves(1, 3).unwrap();
+        assert_eq!(range.iter().map(|leaf| (leaf.index, leaf.origin)).collect::<Vec<_>>(), [(1, None), (2, Some(origin))]);
+
+        // Rolled back to the mark, then saved again: later entries are gone, also after a reopen
+        checkpoint.roll_back_to(checkpoint.recent_blocks[0]);
+        store.save(&checkpoint).unwrap();
+        drop(store);
+        let reopened = RocksDbStore::open(&path).unwrap();
+        assert_eq!(reopened.load(), checkpoint);
+
+        drop(reopened);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+// Deposit tree root history
+// Every root the local tree produces is recorded with the block of the deposit that produced
+// it and the leaf count at that point. A background check samples recorded roots and asks the
+// contract whether it knows them (`is_root_known`), so a drifting local tree shows up before
+// proofs against it get rejected. Clients pick a recent root that is a few blocks old and
+// validated when they build proofs.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// Roots kept in memory, oldest dropped first
+pub const DEFAULT_ROOT_HISTORY_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RootRecord {
+    pub root: String,
+    /// Block of the deposit that produced this root
+    pub block_number: u64,
+    /// Leaves in the tree with this root
+    pub leaf_count: u32,
+    /// Result of the last `is_root_known` check, None until checked
+    pub known_on_chain: Option<bool>,
+}
+
+struct HistoryState {
+    records: VecDeque<RootRecord>,
+    capacity: usize,
+    /// Rotates validation through the history
+    next_check: usize,
+}
+
+/// Shared root history; clones see the same records
+#[derive(Clone)]
+pub struct RootHistory {
+    state: Arc<Mutex<HistoryState>>,
+}
+
+impl Default for RootHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROOT_HISTORY_CAPACITY)
+    }
+}
+
+impl RootHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(HistoryState {
+                records: VecDeque::new(),
+                capacity: capacity.max(1),
+                next_check: 0,
+            })),
+        }
+    }
+
+    /// The tree reached `root` with `leaf_count` leaves at `block_number`
+    pub fn record(&self, root: String, block_number: u64, leaf_count: u32) {
+        let mut state = self.state.lock().unwrap();
+        if state.records.back().is_some_and(|last| last.root == root) {
+            return;
+        }
+        if state.records.len() == state.capacity {
+            state.records.pop_front();
+        }
+        state.records.push_back(RootRecord {
+            root,
+            block_number,
+            leaf_count,
+            known_on_chain: None,
+        });
+    }
+
+    /// Forget roots of trees larger than `leaf_count` leaves (after a reorg or a resync)
+    pub fn truncate_to(&self, leaf_count: u32) {
+        let mut state = self.state.lock().unwrap();
+        while state.records.back().is_some_and(|last| last.leaf_count > leaf_count) {
+            state.records.pop_back();
+        }
+    }
+
+    /// Up to `limit` records, newest first
+    pub fn recent(&self, limit: usize) -> Vec<RootRecord> {
+        self.state.lock().unwrap().records.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// Record of `root` (lowercase 0x hex, no padding), if it is still in the history
+    pub fn get(&self, root: &str) -> Option<RootRecord> {
+        self.state.lock().unwrap().records.iter().rev().find(|record| record.root == root).cloned()
+    }
+
+    /// Newest root produced at or before `max_block` that the contract is known to accept
+    pub fn latest_validated(&self, max_block: u64) -> Option<RootRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .records
+            .iter()
+            .rev()
+            .find(|record| record.block_number <= max_block && record.known_on_chain == Some(true))
+            .cloned()
+    }
+
+    /// Check `sample` roots with `is_known`: the newest one, plus older ones in rotation
+    /// Returns the roots the contract doesn't know.
+    pub async fn validate_sample<F, Fut, E>(&self, sample: usize, is_known: F) -> Vec<String>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<bool, E>>,
+        E: std::fmt::Display,
+    {
+        let roots: Vec<String> = {
+            let mut state = self.state.lock().unwrap();
+            let len = state.records.len();
+            if len == 0 || sample == 0 {
+                return Vec::new();
+            }
+            let mut picked = vec![state.records[len - 1].root.clone()];
+            for _ in 0..len {
+                if picked.len() >= sample {
+                    break;
+                }
+                let index = state.next_check % len;
+                state.next_check = index + 1;
+                let root = state.records[index].root.clone();
+                if !picked.contains(&root) {
+                    picked.push(root);
+                }
+            }
+            picked
+        };
+
+        let mut unknown = Vec::new();
+        for root in roots {
+            match is_known(root.clone()).await {
+                Ok(known) => {
+                    if !known {
+                        eprintln!("[ASP] ❌ Local root {} is not known to the contract", root);
+                        unknown.push(root.clone());
+                    }
+                    let mut state = self.state.lock().unwrap();
+                    if let Some(record) = state.records.iter_mut().find(|record| record.root == root) {
+                        record.known_on_chain = Some(known);
+                    }
+                }
+                Err(e) => eprintln!("[ASP] ⚠️  Could not check root {}: {}", root, e),
+            }
+        }
+        unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_history_and_validation() {
+        let history = RootHistory::new(3);
+        for leaves in 1..=4u32 {
+            history.record(format!("0x{}", leaves), 100 + leaves as u64, leaves);
+        }
+        // Repeated roots are recorded once, the oldest fell out
+        history.record("0x4".to_string(), 110, 4);
+        let recent = history.recent(10);
+        assert_eq!(recent.iter().map(|r| r.root.as_str()).collect::<Vec<_>>(), vec!["0x4", "0x3", "0x2"]);
+        assert_eq!(history.get("0x3").map(|r| r.block_number), Some(103));
+        assert_eq!(history.get("0x1"), None);
+
+        // The newest root, then the oldest one
+        let unknown = history
+            .validate_sample(2, |root| async move { Ok::<_, String>(root != "0x3") })
+            .await;
+        assert!(unknown.is_empty());
+        assert_eq!(history.recent(1)[0].known_on_chain, Some(true));
+        assert_eq!(history.recent(2)[1].known_on_chain, None);
+
+        // Every root gets checked in turn
+        let unknown = history
+            .validate_sample(3, |root| async move { Ok::<_, String>(root != "0x3") })
+            .await;
+        assert_eq!(unknown, vec!["0x3".to_string()]);
+        assert_eq!(history.latest_validated(104).unwrap().root, "0x4");
+        assert_eq!(history.latest_validated(103).unwrap().root, "0x2");
+
+        // A reorg back to 2 leaves drops the roots after it
+        history.truncate_to(2);
+        assert_eq!(history.recent(10).len(), 1);
+    }
+}
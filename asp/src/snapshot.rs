@@ -0,0 +1,164 @@
+// Binary tree snapshots
+// The JSON TreeSnapshot spells every leaf out in hex. The binary form packs the same tree in
+// fixed-size fields, about a third of the size, and adds the append frontier so an instance
+// moved elsewhere can take new deposits without rebuilding first. Layout, big endian:
+// magic "ZSNP", version (1), depth (1), synced block (8), leaf count (4),
+// frontier (depth x 32), leaves (leaf count x 32), root (32).
+
+use crate::error::BlockchainError;
+use crate::hasher::Hasher;
+use crate::merkle::{MerkleTree, TreeSnapshot};
+use num_bigint::BigUint;
+use starknet::core::types::FieldElement;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"ZSNP";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 1 + 8 + 4;
+
+/// Decoded binary snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub snapshot: TreeSnapshot,
+    /// Filled subtree of each level, as `MerkleTree::filled_subtrees`
+    pub frontier: Vec<FieldElement>,
+}
+
+impl SnapshotFile {
+    /// Append-only tree continuing after the snapshot, built from the frontier without hashing
+    /// the leaves; see `MerkleTree::from_frontier`
+    pub fn frontier_tree(&self, hasher: Arc<dyn Hasher>) -> Result<MerkleTree, BlockchainError> {
+        let root = FieldElement::from_hex_be(&self.snapshot.root)
+            .map_err(|e| BlockchainError::Decode(format!("snapshot root {}: {}", self.snapshot.root, e)))?;
+        let frontier: Vec<BigUint> = self.frontier.iter().map(|node| BigUint::from_bytes_be(&node.to_bytes_be())).collect();
+        Ok(MerkleTree::from_frontier(
+            self.snapshot.depth,
+            hasher,
+            self.snapshot.leaves.len() as u32,
+            &frontier,
+            BigUint::from_bytes_be(&root.to_bytes_be()),
+        ))
+    }
+}
+
+/// Whether `bytes` hold a binary snapshot rather than a JSON one
+pub fn is_binary_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Binary snapshot of `tree`, synced up to `synced_block`
+pub fn encode_snapshot(tree: &MerkleTree, synced_block: u64) -> Vec<u8> {
+    let leaves = tree.leaves();
+    let mut buf = Vec::with_capacity(HEADER_LEN + (tree.depth + leaves.len() + 1) * 32);
+    buf.extend_from_slice(MAGIC);
+    buf.push(VERSION);
+    buf.push(tree.depth as u8);
+    buf.extend_from_slice(&synced_block.to_be_bytes());
+    buf.extend_from_slice(&(leaves.len() as u32).to_be_bytes());
+    for node in tree.filled_subtrees.iter().chain(&leaves).chain(std::iter::once(&tree.current_root)) {
+        let bytes = node.to_bytes_be();
+        buf.extend(std::iter::repeat(0u8).take(32 - bytes.len()));
+        buf.extend_from_slice(&bytes);
+    }
+    buf
+}
+
+pub fn decode_snapshot(bytes: &[u8]) -> Result<SnapshotFile, BlockchainError> {
+    if !is_binary_snapshot(bytes) || bytes.len() < HEADER_LEN {
+        return Err(BlockchainError::Decode("not a binary tree snapshot".to_string()));
+    }
+    if bytes[4] != VERSION {
+        return Err(BlockchainError::Decode(format!("unsupported snapshot version {}", bytes[4])));
+    }
+    let depth = bytes[5] as usize;
+    let synced_block = u64::from_be_bytes(bytes[6..14].try_into().unwrap());
+    let leaf_count = u32::from_be_bytes(bytes[14..18].try_into().unwrap()) as usize;
+    let expected = HEADER_LEN + (depth + leaf_count + 1) * 32;
+    if bytes.len() != expected {
+        return Err(BlockchainError::Decode(format!(
+            "snapshot of {} leaves should be {} bytes, got {}",
+            leaf_count,
+            expected,
+            bytes.len()
+        )));
+    }
+
+    let felts = bytes[HEADER_LEN..]
+        .chunks_exact(32)
+        .map(|chunk| {
+            FieldElement::from_byte_slice_be(chunk)
+                .map_err(|_| BlockchainError::Decode(format!("snapshot node 0x{:x} is not a felt", BigUint::from_bytes_be(chunk))))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (frontier, rest) = felts.split_at(depth);
+    let (leaves, root) = rest.split_at(leaf_count);
+    Ok(SnapshotFile {
+        snapshot: TreeSnapshot {
+            depth,
+            leaves: leaves.to_vec(),
+            root: format!("0x{:x}", root[0]),
+            synced_block,
+        },
+        frontier: frontier.to_vec(),
+    })
+}
+
+/// Write the binary snapshot of `tree` to `path`, atomically
+pub fn export_snapshot(path: impl AsRef<Path>, tree: &MerkleTree, synced_block: u64) -> io::Result<()> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, encode_snapshot(tree, synced_block))?;
+    fs::rename(&tmp, path)
+}
+
+/// Snapshot in `bytes`, binary or JSON (/deposit/snapshot)
+pub fn parse_snapshot(bytes: &[u8]) -> Result<TreeSnapshot, BlockchainError> {
+    if is_binary_snapshot(bytes) {
+        return decode_snapshot(bytes).map(|file| file.snapshot);
+    }
+    serde_json::from_slice(bytes).map_err(|e| BlockchainError::Decode(format!("snapshot: {}", e)))
+}
+
+/// Read the snapshot file at `path`, binary or JSON; it still has to be verified before use
+pub fn import_snapshot(path: impl AsRef<Path>) -> Result<TreeSnapshot, BlockchainError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| BlockchainError::Config(format!("snapshot {}: {}", path.display(), e)))?;
+    parse_snapshot(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let leaves: Vec<BigUint> = (1000u64..1005).map(BigUint::from).collect();
+        let tree = MerkleTree::from_leaves(TREE_DEPTH, &leaves);
+        let path = std::env::temp_dir().join(format!("asp_snapshot_test_{}.bin", std::process::id()));
+
+        export_snapshot(&path, &tree, 4242).unwrap();
+        let snapshot = import_snapshot(&path).unwrap();
+        assert_eq!(snapshot.synced_block, 4242);
+        assert_eq!(snapshot.depth, TREE_DEPTH);
+        assert_eq!(snapshot.leaves, (1000u64..1005).map(FieldElement::from).collect::<Vec<_>>());
+        assert_eq!(snapshot.root, format!("0x{:x}", tree.get_root()));
+
+        let bytes = fs::read(&path).unwrap();
+        let frontier = decode_snapshot(&bytes).unwrap().frontier;
+        assert_eq!(frontier.len(), TREE_DEPTH);
+        assert_eq!(frontier[0], FieldElement::from(1004u64));
+        let mut resumed = decode_snapshot(&bytes).unwrap().frontier_tree(tree.hasher.clone()).unwrap();
+        let mut full = tree;
+        assert_eq!(resumed.append(BigUint::from(1005u64)), full.append(BigUint::from(1005u64)));
+        // Truncated files are rejected
+        assert!(decode_snapshot(&bytes[..bytes.len() - 1]).is_err());
+        // JSON snapshots still import
+        fs::write(&path, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+        assert_eq!(import_snapshot(&path).unwrap(), snapshot);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,457 @@
+// SQLite sync state
+// The checkpoint split over tables: the scan position, the block marks kept for reorgs, one
+// row per deposit leaf (commitment, block and transaction of its Deposit event) and one per
+// spent nullifier. Saves write only the rows that changed since the previous save, in one
+// transaction. sqlx is async and the syncer saves from sync code, so the database is owned
+// by a thread of its own that runs each request to completion.
+
+use crate::checkpoint::{BlockMark, SyncCheckpoint};
+use crate::commitment_index::LeafOrigin;
+use crate::migrations::{self, Direction, Migration};
+use crate::state_store::{StateStore, StoredLeaf};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous};
+use sqlx::{Row, SqlitePool};
+use starknet::core::types::FieldElement;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Schema versions, recorded in `schema_migrations`; databases from before versioning have
+/// the tables of the first one already
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "sync state, block marks, leaves and nullifiers",
+    up: &[
+        "CREATE TABLE IF NOT EXISTS sync_state (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            last_synced_block INTEGER NOT NULL,
+            last_leaf_index INTEGER,
+            scan_to_block INTEGER,
+            continuation_token TEXT,
+            applied_events TEXT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS block_marks (
+            number INTEGER PRIMARY KEY,
+            hash TEXT NOT NULL,
+            leaf_count INTEGER NOT NULL,
+            nullifier_count INTEGER NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS leaves (
+            leaf_index INTEGER PRIMARY KEY,
+            commitment TEXT NOT NULL,
+            block_number INTEGER,
+            transaction_hash TEXT
+        )",
+        "CREATE INDEX IF NOT EXISTS leaves_by_commitment ON leaves (commitment)",
+        "CREATE TABLE IF NOT EXISTS nullifiers (
+            position INTEGER PRIMARY KEY,
+            nullifier TEXT NOT NULL
+        )",
+    ],
+    down: &[
+        "DROP TABLE IF EXISTS nullifiers",
+        "DROP INDEX IF EXISTS leaves_by_commitment",
+        "DROP TABLE IF EXISTS leaves",
+        "DROP TABLE IF EXISTS block_marks",
+        "DROP TABLE IF EXISTS sync_state",
+    ],
+}];
+
+enum Request {
+    Load(mpsc::Sender<io::Result<Option<SyncCheckpoint>>>),
+    Save(Box<SyncCheckpoint>, mpsc::Sender<io::Result<()>>),
+    Leaves(u32, u32, mpsc::Sender<io::Result<Vec<StoredLeaf>>>),
+    Maintain(mpsc::Sender<io::Result<()>>),
+}
+
+/// Sync state in a SQLite database
+pub struct SqliteStore {
+    path: PathBuf,
+    requests: Mutex<mpsc::Sender<Request>>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (requests, inbox) = mpsc::channel();
+        let (ready, opened) = mpsc::channel();
+        let db_path = path.clone();
+        thread::Builder::new().name("asp-sqlite".to_string()).spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let mut db = match runtime.block_on(Database::connect(&db_path)) {
+                Ok(db) => {
+                    let _ = ready.send(Ok(()));
+                    db
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            for request in inbox {
+                match request {
+                    Request::Load(reply) => {
+                        let _ = reply.send(runtime.block_on(db.load()));
+                    }
+                    Request::Save(checkpoint, reply) => {
+                        let _ = reply.send(runtime.block_on(db.save(&checkpoint)));
+                    }
+                    Request::Leaves(from, to, reply) => {
+                        let _ = reply.send(runtime.block_on(db.leaves(from, to)));
+                    }
+                    Request::Maintain(reply) => {
+                        let _ = reply.send(runtime.block_on(db.maintain()));
+                    }
+                }
+            }
+        })?;
+        opened.recv().map_err(|_| closed())??;
+        Ok(Self {
+            path,
+            requests: Mutex::new(requests),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Saved checkpoint, None if nothing was saved yet
+    pub fn load_saved(&self) -> io::Result<Option<SyncCheckpoint>> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Load(reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn load(&self) -> SyncCheckpoint {
+        match self.load_saved() {
+            Ok(checkpoint) => checkpoint.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read {}: {}", self.path.display(), e);
+                SyncCheckpoint::default()
+            }
+        }
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let (reply, response) = mpsc::channel();
+        self.requests
+            .lock()
+            .unwrap()
+            .send(Request::Save(Box::new(checkpoint.clone()), reply))
+            .map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Leaves(from, to, reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+
+    fn maintain(&self) -> io::Result<()> {
+        let (reply, response) = mpsc::channel();
+        self.requests.lock().unwrap().send(Request::Maintain(reply)).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    }
+}
+
+/// Connection and the rows as of the last save or load
+struct Database {
+    pool: SqlitePool,
+    leaves: Vec<(FieldElement, Option<LeafOrigin>)>,
+    nullifiers: Vec<FieldElement>,
+}
+
+impl Database {
+    async fn connect(path: &Path) -> io::Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            // Each save is one transaction; FULL syncs the WAL on commit so it survives a power loss
+            .synchronous(SqliteSynchronous::Full);
+        let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await.map_err(db_error)?;
+        migrate(&pool).await?;
+        let mut db = Self {
+            pool,
+            leaves: Vec::new(),
+            nullifiers: Vec::new(),
+        };
+        db.load().await?;
+        Ok(db)
+    }
+
+    async fn load(&mut self) -> io::Result<Option<SyncCheckpoint>> {
+        let Some(state) = sqlx::query(
+            "SELECT last_synced_block, last_leaf_index, scan_to_block, continuation_token, applied_events FROM sync_state WHERE id = 0",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        else {
+            return Ok(None);
+        };
+
+        let mut recent_blocks = Vec::new();
+        for row in sqlx::query("SELECT number, hash, leaf_count, nullifier_count FROM block_marks ORDER BY number")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            recent_blocks.push(BlockMark {
+                number: row.try_get::<i64, _>("number").map_err(db_error)? as u64,
+                hash: parse_felt(&row.try_get::<String, _>("hash").map_err(db_error)?)?,
+                leaf_count: row.try_get::<i64, _>("leaf_count").map_err(db_error)? as u32,
+                nullifier_count: row.try_get::<i64, _>("nullifier_count").map_err(db_error)? as u32,
+            });
+        }
+
+        let mut leaves = Vec::new();
+        for row in sqlx::query("SELECT commitment, block_number, transaction_hash FROM leaves ORDER BY leaf_index")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            let commitment = parse_felt(&row.try_get::<String, _>("commitment").map_err(db_error)?)?;
+            leaves.push((commitment, parse_origin(&row)?));
+        }
+
+        let mut nullifiers = Vec::new();
+        for row in sqlx::query("SELECT nullifier FROM nullifiers ORDER BY position")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+        {
+            nullifiers.push(parse_felt(&row.try_get::<String, _>("nullifier").map_err(db_error)?)?);
+        }
+
+        let applied_events = state.try_get::<String, _>("applied_events").map_err(db_error)?;
+        let checkpoint = SyncCheckpoint {
+            last_synced_block: state.try_get::<i64, _>("last_synced_block").map_err(db_error)? as u64,
+            last_leaf_index: state.try_get::<Option<i64>, _>("last_leaf_index").map_err(db_error)?.map(|index| index as u32),
+            scan_to_block: state.try_get::<Option<i64>, _>("scan_to_block").map_err(db_error)?.map(|block| block as u64),
+            continuation_token: state.try_get::<Option<String>, _>("continuation_token").map_err(db_error)?,
+            leaves: leaves.iter().map(|(leaf, _)| *leaf).collect(),
+            leaf_origins: leaves.iter().map(|(_, origin)| *origin).collect(),
+            recent_blocks,
+            applied_events: serde_json::from_str(&applied_events)?,
+            nullifiers: nullifiers.clone(),
+        };
+        self.leaves = leaves;
+        self.nullifiers = nullifiers;
+        Ok(Some(checkpoint))
+    }
+
+    async fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        let mut leaves = Vec::new();
+        for row in sqlx::query(
+            "SELECT leaf_index, commitment, block_number, transaction_hash FROM leaves
+             WHERE leaf_index >= ? AND leaf_index < ? ORDER BY leaf_index",
+        )
+        .bind(i64::from(from))
+        .bind(i64::from(to))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        {
+            leaves.push(StoredLeaf {
+                index: row.try_get::<i64, _>("leaf_index").map_err(db_error)? as u32,
+                commitment: parse_felt(&row.try_get::<String, _>("commitment").map_err(db_error)?)?,
+                origin: parse_origin(&row)?,
+            });
+        }
+        Ok(leaves)
+    }
+
+    /// Fold the WAL into the database, rebuild the indexes and give freed pages back
+    async fn maintain(&self) -> io::Result<()> {
+        for statement in ["PRAGMA wal_checkpoint(TRUNCATE)", "REINDEX", "ANALYZE", "VACUUM"] {
+            sqlx::query(statement).execute(&self.pool).await.map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    async fn save(&mut self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        let leaves: Vec<(FieldElement, Option<LeafOrigin>)> = checkpoint
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| (*leaf, checkpoint.leaf_origins.get(index).copied().flatten()))
+            .collect();
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO sync_state (id, last_synced_block, last_leaf_index, scan_to_block, continuation_token, applied_events)
+             VALUES (0, ?, ?, ?, ?, ?)",
+        )
+        .bind(checkpoint.last_synced_block as i64)
+        .bind(checkpoint.last_leaf_index.map(i64::from))
+        .bind(checkpoint.scan_to_block.map(|block| block as i64))
+        .bind(checkpoint.continuation_token.as_deref())
+        .bind(serde_json::to_string(&checkpoint.applied_events)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        // At most REORG_WINDOW marks: rewritten whole
+        sqlx::query("DELETE FROM block_marks").execute(&mut *tx).await.map_err(db_error)?;
+        for mark in &checkpoint.recent_blocks {
+            sqlx::query("INSERT INTO block_marks (number, hash, leaf_count, nullifier_count) VALUES (?, ?, ?, ?)")
+                .bind(mark.number as i64)
+                .bind(format!("0x{:x}", mark.hash))
+                .bind(i64::from(mark.leaf_count))
+                .bind(i64::from(mark.nullifier_count))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+
+        sqlx::query("DELETE FROM leaves WHERE leaf_index >= ?")
+            .bind(leaves.len() as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for (index, (leaf, origin)) in leaves.iter().enumerate() {
+            if self.leaves.get(index) == Some(&(*leaf, *origin)) {
+                continue;
+            }
+            sqlx::query("INSERT OR REPLACE INTO leaves (leaf_index, commitment, block_number, transaction_hash) VALUES (?, ?, ?, ?)")
+                .bind(index as i64)
+                .bind(format!("0x{:x}", leaf))
+                .bind(origin.map(|origin| origin.block_number as i64))
+                .bind(origin.map(|origin| format!("0x{:x}", origin.transaction_hash)))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+
+        sqlx::query("DELETE FROM nullifiers WHERE position >= ?")
+            .bind(checkpoint.nullifiers.len() as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        for (position, nullifier) in checkpoint.nullifiers.iter().enumerate() {
+            if self.nullifiers.get(position) == Some(nullifier) {
+                continue;
+            }
+            sqlx::query("INSERT OR REPLACE INTO nullifiers (position, nullifier) VALUES (?, ?)")
+                .bind(position as i64)
+                .bind(format!("0x{:x}", nullifier))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+        }
+
+        tx.commit().await.map_err(db_error)?;
+        self.leaves = leaves;
+        self.nullifiers = checkpoint.nullifiers.clone();
+        Ok(())
+    }
+}
+
+fn parse_felt(hex: &str) -> io::Result<FieldElement> {
+    FieldElement::from_hex_be(hex).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid felt '{}': {}", hex, e)))
+}
+
+/// Bring the schema to the target version, one transaction per migration
+async fn migrate(pool: &SqlitePool) -> io::Result<()> {
+    sqlx::query("CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY, description TEXT NOT NULL)")
+        .execute(pool)
+        .await
+        .map_err(db_error)?;
+    let current: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_migrations")
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    let target = migrations::target_version(MIGRATIONS)?;
+    for (migration, direction) in migrations::plan(MIGRATIONS, current as u32, target)? {
+        let mut tx = pool.begin().await.map_err(db_error)?;
+        for statement in migration.statements(direction) {
+            sqlx::query(statement).execute(&mut *tx).await.map_err(db_error)?;
+        }
+        let record = match direction {
+            Direction::Up => sqlx::query("INSERT INTO schema_migrations (version, description) VALUES (?, ?)")
+                .bind(i64::from(migration.version))
+                .bind(migration.description),
+            Direction::Down => sqlx::query("DELETE FROM schema_migrations WHERE version = ?").bind(i64::from(migration.version)),
+        };
+        record.execute(&mut *tx).await.map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+        println!("[ASP] 🗄️  SQLite schema {:?} to version {} ({})", direction, migration.version, migration.description);
+    }
+    migrations::check_downgrade(MIGRATIONS, target)
+}
+
+/// Deposit event of a leaf row, if it was recorded
+fn parse_origin(row: &SqliteRow) -> io::Result<Option<LeafOrigin>> {
+    let block_number = row.try_get::<Option<i64>, _>("block_number").map_err(db_error)?;
+    match (block_number, row.try_get::<Option<String>, _>("transaction_hash").map_err(db_error)?) {
+        (Some(block_number), Some(transaction_hash)) => Ok(Some(LeafOrigin {
+            block_number: block_number as u64,
+            transaction_hash: parse_felt(&transaction_hash)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+fn db_error(e: sqlx::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "SQLite state thread stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedupe::EventDeduper;
+
+    #[test]
+    fn test_sqlite_round_trip_and_rollback() {
+        let dir = std::env::temp_dir().join(format!("asp_sqlite_store_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.sqlite");
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.load_saved().unwrap(), None);
+
+        let origin = LeafOrigin { block_number: 12, transaction_hash: FieldElement::from(0xabcu64) };
+        let mut checkpoint = SyncCheckpoint {
+            last_synced_block: 20,
+            last_leaf_index: Some(2),
+            scan_to_block: Some(40),
+            continuation_token: Some("3".to_string()),
+            leaves: (1000u64..1003).map(FieldElement::from).collect(),
+            leaf_origins: vec![Some(origin), None, Some(origin)],
+            recent_blocks: vec![BlockMark { number: 15, hash: FieldElement::from(0x15u64), leaf_count: 1, nullifier_count: 1 }],
+            applied_events: EventDeduper::default(),
+            nullifiers: vec![FieldElement::from(0x3f1au64), FieldElement::from(0x3f1bu64)],
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load(), checkpoint);
+        let range = store.leaves(1, 3).unwrap();
+        assert_eq!(range.iter().map(|leaf| (leaf.index, leaf.origin)).collect::<Vec<_>>(), [(1, None), (2, Some(origin))]);
+
+        // Rolled back to the mark, then saved again: later rows are gone, also after a reopen
+        checkpoint.roll_back_to(checkpoint.recent_blocks[0]);
+        store.save(&checkpoint).unwrap();
+        drop(store);
+        let reopened = SqliteStore::open(&path).unwrap();
+        assert_eq!(reopened.load(), checkpoint);
+        assert_eq!(reopened.load().leaves, vec![FieldElement::from(1000u64)]);
+
+        drop(reopened);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
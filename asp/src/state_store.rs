@@ -0,0 +1,243 @@
+// Sync state backends
+// The syncer persists its checkpoint through StateStore. By default that is the JSON
+// checkpoint file; STATE_BACKEND=sqlite keeps the same state in an embedded SQLite database
+// next to it, with a row per leaf and per nullifier so each save only writes what changed.
+// STATE_BACKEND=rocksdb does the same in RocksDB, for high-volume deployments (needs the
+// `rocksdb` feature). STATE_BACKEND=postgres shares the state between API replicas through
+// PostgreSQL (STATE_DATABASE_URL, `postgres` feature); only one of them syncs at a time.
+// STATE_BACKEND=memory keeps nothing across restarts (tests, throwaway instances). Callers
+// only see StateStore: a save is applied whole or not at all, and leaf ranges are read
+// without loading the rest of the state where the backend allows it.
+
+use crate::checkpoint::{CheckpointStore, SyncCheckpoint};
+use crate::commitment_index::LeafOrigin;
+use crate::sqlite_store::SqliteStore;
+use starknet::core::types::FieldElement;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Deposit leaf as saved, with its Deposit event when known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoredLeaf {
+    pub index: u32,
+    pub commitment: FieldElement,
+    pub origin: Option<LeafOrigin>,
+}
+
+/// Where sync checkpoints are saved and loaded from
+pub trait StateStore: Send + Sync {
+    /// Saved checkpoint; missing or unreadable state starts from scratch
+    fn load(&self) -> SyncCheckpoint;
+
+    /// Replace the saved state with `checkpoint`, atomically
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()>;
+
+    /// Saved leaves with an index in `from..to`, in index order
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        Ok(leaves_of(&self.load(), from, to))
+    }
+
+    /// Compact the store and rebuild its indexes, reclaiming the space of deleted data; run
+    /// by the maintenance task. Nothing to do for the checkpoint file.
+    fn maintain(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Take the right to sync this state, kept until the process exits; false while another
+    /// instance holds it. Stores of a single instance always grant it.
+    fn try_lock_sync(&self) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl StateStore for CheckpointStore {
+    fn load(&self) -> SyncCheckpoint {
+        CheckpointStore::load(self)
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        CheckpointStore::save(self, checkpoint)
+    }
+}
+
+impl<S: StateStore + ?Sized> StateStore for Arc<S> {
+    fn load(&self) -> SyncCheckpoint {
+        (**self).load()
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        (**self).save(checkpoint)
+    }
+
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        (**self).leaves(from, to)
+    }
+
+    fn maintain(&self) -> io::Result<()> {
+        (**self).maintain()
+    }
+
+    fn try_lock_sync(&self) -> io::Result<bool> {
+        (**self).try_lock_sync()
+    }
+}
+
+/// Leaves of `checkpoint` with an index in `from..to`
+pub fn leaves_of(checkpoint: &SyncCheckpoint, from: u32, to: u32) -> Vec<StoredLeaf> {
+    let end = (to as usize).min(checkpoint.leaves.len());
+    (from as usize..end)
+        .map(|index| StoredLeaf {
+            index: index as u32,
+            commitment: checkpoint.leaves[index],
+            origin: checkpoint.leaf_origins.get(index).copied().flatten(),
+        })
+        .collect()
+}
+
+/// Sync state kept in memory only
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<SyncCheckpoint>,
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self) -> SyncCheckpoint {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn save(&self, checkpoint: &SyncCheckpoint) -> io::Result<()> {
+        *self.state.lock().unwrap() = checkpoint.clone();
+        Ok(())
+    }
+
+    fn leaves(&self, from: u32, to: u32) -> io::Result<Vec<StoredLeaf>> {
+        Ok(leaves_of(&self.state.lock().unwrap(), from, to))
+    }
+}
+
+/// Storage backend of the sync state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StateBackend {
+    /// JSON checkpoint file
+    #[default]
+    Json,
+    /// Nothing saved: every start syncs from scratch
+    Memory,
+    /// SQLite database next to the checkpoint file (`.sqlite`)
+    Sqlite,
+    /// RocksDB database directory next to the checkpoint file (`.rocksdb`)
+    RocksDb,
+    /// PostgreSQL database at STATE_DATABASE_URL, shared by several instances
+    Postgres,
+}
+
+impl StateBackend {
+    /// `json`, `memory`, `sqlite`, `rocksdb` or `postgres`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "json" | "file" => Some(StateBackend::Json),
+            "memory" => Some(StateBackend::Memory),
+            "sqlite" => Some(StateBackend::Sqlite),
+            "rocksdb" => Some(StateBackend::RocksDb),
+            "postgres" | "postgresql" => Some(StateBackend::Postgres),
+            _ => None,
+        }
+    }
+
+    /// Backend selected by STATE_BACKEND, the JSON file by default
+    pub fn from_env() -> Self {
+        match std::env::var("STATE_BACKEND") {
+            Ok(name) => Self::parse(&name).unwrap_or_else(|| {
+                eprintln!("⚠️  Unknown STATE_BACKEND {:?}, using json", name);
+                StateBackend::Json
+            }),
+            Err(_) => StateBackend::Json,
+        }
+    }
+
+    /// Open the store of this backend at the location of `checkpoint`
+    /// A new database starts from the checkpoint file if there is one, so switching backends
+    /// doesn't resync
+    pub fn open(self, checkpoint: &CheckpointStore) -> io::Result<Arc<dyn StateStore>> {
+        checkpoint.check_readable()?;
+        if checkpoint.is_encrypted() && !matches!(self, StateBackend::Json | StateBackend::Memory) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "STATE_ENCRYPTION_KEY only encrypts the json backend's checkpoint file",
+            ));
+        }
+        match self {
+            StateBackend::Json => Ok(Arc::new(checkpoint.clone())),
+            StateBackend::Memory => Ok(Arc::new(MemoryStore::default())),
+            StateBackend::Sqlite => {
+                let store = SqliteStore::open(checkpoint.path().with_extension("sqlite"))?;
+                if store.load_saved()?.is_none() && checkpoint.path().exists() {
+                    store.save(&checkpoint.load())?;
+                    println!("[ASP] 📦 Imported {} into {}", checkpoint.path().display(), store.path().display());
+                }
+                Ok(Arc::new(store))
+            }
+            #[cfg(feature = "rocksdb")]
+            StateBackend::RocksDb => {
+                let store = crate::rocksdb_store::RocksDbStore::open(checkpoint.path().with_extension("rocksdb"))?;
+                if store.load_saved()?.is_none() && checkpoint.path().exists() {
+                    store.save(&checkpoint.load())?;
+                    println!("[ASP] 📦 Imported {} into {}", checkpoint.path().display(), store.path().display());
+                }
+                Ok(Arc::new(store))
+            }
+            #[cfg(not(feature = "rocksdb"))]
+            StateBackend::RocksDb => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "STATE_BACKEND=rocksdb needs a build with the rocksdb feature",
+            )),
+            #[cfg(feature = "postgres")]
+            StateBackend::Postgres => {
+                let url = std::env::var("STATE_DATABASE_URL")
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "STATE_BACKEND=postgres needs STATE_DATABASE_URL"))?;
+                // One state per checkpoint file name, i.e. per synced contract
+                let key = checkpoint.path().file_stem().map_or_else(|| "asp_state".into(), |stem| stem.to_string_lossy());
+                let store = crate::postgres_store::PostgresStore::open(&url, key)?;
+                // Only the instance allowed to sync imports, so replicas don't race on it
+                if store.load_saved()?.is_none() && checkpoint.path().exists() && store.try_lock_sync()? {
+                    store.save(&checkpoint.load())?;
+                    println!("[ASP] 📦 Imported {} into PostgreSQL state {}", checkpoint.path().display(), store.state_key());
+                }
+                Ok(Arc::new(store))
+            }
+            #[cfg(not(feature = "postgres"))]
+            StateBackend::Postgres => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "STATE_BACKEND=postgres needs a build with the postgres feature",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store_leaf_ranges() {
+        let store: Arc<dyn StateStore> = StateBackend::parse("memory").unwrap().open(&CheckpointStore::new("unused.json")).unwrap();
+        assert!(store.leaves(0, 10).unwrap().is_empty());
+
+        let origin = LeafOrigin { block_number: 7, transaction_hash: FieldElement::from(0x77u64) };
+        let checkpoint = SyncCheckpoint {
+            leaves: (1000u64..1005).map(FieldElement::from).collect(),
+            leaf_origins: vec![None, Some(origin)],
+            ..SyncCheckpoint::default()
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load(), checkpoint);
+        assert_eq!(
+            store.leaves(1, 3).unwrap(),
+            vec![
+                StoredLeaf { index: 1, commitment: FieldElement::from(1001u64), origin: Some(origin) },
+                StoredLeaf { index: 2, commitment: FieldElement::from(1002u64), origin: None },
+            ]
+        );
+        assert_eq!(store.leaves(4, 100).unwrap().len(), 1);
+    }
+}
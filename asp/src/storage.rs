@@ -0,0 +1,102 @@
+// Cairo storage addresses
+// Follows the Starknet storage layout: a variable lives at sn_keccak(name); a storage node
+// member at pedersen(base, sn_keccak(member)); a Map entry at pedersen(base, k) chained over
+// every felt of the serialized key; values spanning several slots (u256, structs) take
+// consecutive addresses from there. Addresses are reduced modulo 2^251 - 256.
+
+use crate::hasher::{Hasher, PedersenHasher};
+use starknet::core::types::FieldElement;
+use starknet::core::utils::starknet_keccak;
+
+/// Exclusive upper bound of storage addresses, 2^251 - 256
+const ADDRESS_BOUND: [u8; 32] = {
+    let mut bound = [0xffu8; 32];
+    bound[0] = 0x07;
+    bound[31] = 0x00;
+    bound
+};
+
+/// One step from a storage variable down to a value
+#[derive(Debug, Clone, Copy)]
+pub enum PathSegment<'a> {
+    /// Member of a storage node
+    Member(&'a str),
+    /// Key of a Map, as its serialized felts
+    Key(&'a [FieldElement]),
+}
+
+/// Storage address of `var` followed by `path`, hashing with Pedersen like the contract
+pub fn storage_address_of(var: &str, path: &[PathSegment]) -> FieldElement {
+    storage_address_with(&PedersenHasher, var, path)
+}
+
+/// Same as `storage_address_of` with an injected hasher
+pub fn storage_address_with(hasher: &dyn Hasher, var: &str, path: &[PathSegment]) -> FieldElement {
+    let mut address = starknet_keccak(var.as_bytes());
+    for segment in path {
+        match segment {
+            PathSegment::Member(member) => {
+                address = hasher.hash_two(address, starknet_keccak(member.as_bytes()));
+            }
+            PathSegment::Key(key) => {
+                for felt in key.iter() {
+                    address = hasher.hash_two(address, *felt);
+                }
+            }
+        }
+    }
+    // Like the contract, only the final hash is reduced, not the intermediate states
+    normalize(address)
+}
+
+/// Address of slot `offset` of a value stored at `base` (e.g. 1 for the high word of a u256)
+pub fn slot_offset(base: FieldElement, offset: u8) -> FieldElement {
+    normalize(base + FieldElement::from(offset))
+}
+
+/// Reduce a felt into the storage address range
+fn normalize(address: FieldElement) -> FieldElement {
+    let bound = FieldElement::from_bytes_be(&ADDRESS_BOUND).expect("2^251 - 256 is a felt");
+    if address.to_bytes_be() >= ADDRESS_BOUND {
+        address - bound
+    } else {
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::RecordingHasher;
+
+    #[test]
+    fn test_storage_node_member_uses_hasher() {
+        let hasher = RecordingHasher::default();
+        storage_address_with(&hasher, "pool", &[PathSegment::Member("liquidity")]);
+        assert_eq!(
+            *hasher.calls.lock().unwrap(),
+            vec![(starknet_keccak("pool".as_bytes()), starknet_keccak("liquidity".as_bytes()))]
+        );
+    }
+
+    #[test]
+    fn test_map_key_felts_are_chained() {
+        let hasher = RecordingHasher::default();
+        let key = [FieldElement::ONE, FieldElement::from(2u64)];
+        storage_address_with(&hasher, "positions", &[PathSegment::Member("positions"), PathSegment::Key(&key)]);
+
+        let calls = hasher.calls.lock().unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[1].1, FieldElement::ONE);
+        assert_eq!(calls[2], (calls[1].0 + FieldElement::ONE + FieldElement::ONE, FieldElement::from(2u64)));
+    }
+
+    #[test]
+    fn test_plain_variable_and_offsets() {
+        assert_eq!(storage_address_of("initialized", &[]), starknet_keccak("initialized".as_bytes()));
+
+        let bound = FieldElement::from_bytes_be(&ADDRESS_BOUND).unwrap();
+        assert_eq!(normalize(bound + FieldElement::from(3u64)), FieldElement::from(3u64));
+        assert_eq!(slot_offset(FieldElement::from(10u64), 1), FieldElement::from(11u64));
+    }
+}
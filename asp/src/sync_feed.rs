@@ -0,0 +1,176 @@
+// Live sync notifications
+// The syncer publishes every deposit it applies, every root the tree reaches and every spent
+// nullifier to a broadcast channel, which the `/ws` endpoint streams to clients: a wallet
+// waiting for its commitment to be included subscribes instead of polling. Each notification
+// gets a sequence number and the latest ones are kept, so the `/events/stream` SSE endpoint
+// resumes a reconnecting client from its Last-Event-ID; older gaps are caught up over REST.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Notifications buffered per subscriber before a slow one starts missing some
+const FEED_CAPACITY: usize = 1024;
+/// Latest notifications kept for clients resuming after a reconnect
+const REPLAY_CAPACITY: usize = 1024;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncNotification {
+    Deposit {
+        commitment: String,
+        leaf_index: u32,
+        block_number: u64,
+        transaction_hash: String,
+    },
+    Root {
+        root: String,
+        leaf_count: u32,
+        block_number: u64,
+    },
+    NullifierSpent {
+        nullifier: String,
+        block_number: u64,
+    },
+}
+
+/// Kinds of notification a client subscribes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    Deposit,
+    Root,
+    NullifierSpent,
+}
+
+impl Topic {
+    /// `deposit`, `root` or `nullifier_spent`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "deposit" => Some(Topic::Deposit),
+            "root" => Some(Topic::Root),
+            "nullifier_spent" => Some(Topic::NullifierSpent),
+            _ => None,
+        }
+    }
+}
+
+impl SyncNotification {
+    pub fn topic(&self) -> Topic {
+        match self {
+            SyncNotification::Deposit { .. } => Topic::Deposit,
+            SyncNotification::Root { .. } => Topic::Root,
+            SyncNotification::NullifierSpent { .. } => Topic::NullifierSpent,
+        }
+    }
+}
+
+/// Shared notification channel; clones publish to the same subscribers
+#[derive(Clone)]
+pub struct SyncFeed {
+    sender: broadcast::Sender<SyncNotification>,
+    replay: Arc<Mutex<Replay>>,
+}
+
+/// Latest notifications with their sequence numbers
+#[derive(Default)]
+struct Replay {
+    next_id: u64,
+    recent: VecDeque<(u64, SyncNotification)>,
+}
+
+/// A subscription resumed after `last_id`
+pub struct Resumed {
+    /// Kept notifications after `last_id`, oldest first
+    pub missed: Vec<(u64, SyncNotification)>,
+    /// Whether some notifications after `last_id` are no longer kept
+    pub gap: bool,
+    /// Sequence number of the first notification `receiver` gets
+    pub next_id: u64,
+    pub receiver: broadcast::Receiver<SyncNotification>,
+}
+
+impl Default for SyncFeed {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(FEED_CAPACITY).0,
+            replay: Arc::default(),
+        }
+    }
+}
+
+impl SyncFeed {
+    /// Send `notification` to the current subscribers, if any
+    pub fn publish(&self, notification: SyncNotification) {
+        // Numbered and sent under the lock, so subscribers see sequence numbers in order
+        let mut replay = self.replay.lock().unwrap();
+        let id = replay.next_id;
+        replay.next_id += 1;
+        if replay.recent.len() == REPLAY_CAPACITY {
+            replay.recent.pop_front();
+        }
+        replay.recent.push_back((id, notification.clone()));
+        let _ = self.sender.send(notification);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SyncNotification> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribe, first replaying the kept notifications after `last_id` (none if None)
+    pub fn resume(&self, last_id: Option<u64>) -> Resumed {
+        let replay = self.replay.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let (missed, gap) = match last_id {
+            // Numbered by an earlier run of the server: all kept ones are new to the client
+            Some(last_id) if last_id >= replay.next_id => (replay.recent.iter().cloned().collect(), true),
+            Some(last_id) => {
+                let missed: Vec<_> = replay.recent.iter().filter(|(id, _)| *id > last_id).cloned().collect();
+                let first_kept = replay.recent.front().map_or(replay.next_id, |(id, _)| *id);
+                (missed, last_id.saturating_add(1) < first_kept)
+            }
+            None => (Vec::new(), false),
+        };
+        Resumed { missed, gap, next_id: replay.next_id, receiver }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_feed_reaches_subscribers() {
+        let feed = SyncFeed::default();
+        // Nobody listening yet: dropped
+        feed.publish(SyncNotification::Root { root: "0x1".to_string(), leaf_count: 1, block_number: 5 });
+
+        let mut subscriber = feed.clone().subscribe();
+        feed.publish(SyncNotification::NullifierSpent { nullifier: "0x3f1a".to_string(), block_number: 6 });
+        let received = subscriber.recv().await.unwrap();
+        assert_eq!(Some(received.topic()), Topic::parse("nullifier_spent"));
+        assert_eq!(
+            serde_json::to_value(&received).unwrap(),
+            serde_json::json!({ "type": "nullifier_spent", "nullifier": "0x3f1a", "block_number": 6 })
+        );
+    }
+
+    #[test]
+    fn test_resume_after_last_id() {
+        let feed = SyncFeed::default();
+        for block_number in 0..(REPLAY_CAPACITY as u64 + 2) {
+            feed.publish(SyncNotification::NullifierSpent { nullifier: "0x1".to_string(), block_number });
+        }
+        let resumed = feed.resume(Some(REPLAY_CAPACITY as u64 - 1));
+        assert!(!resumed.gap);
+        assert_eq!(resumed.missed.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![REPLAY_CAPACITY as u64, REPLAY_CAPACITY as u64 + 1]);
+        assert_eq!(resumed.next_id, REPLAY_CAPACITY as u64 + 2);
+        // The first two are no longer kept
+        assert!(feed.resume(Some(0)).gap);
+        assert!(!feed.resume(Some(1)).gap);
+        assert!(feed.resume(None).missed.is_empty());
+        let restarted = feed.resume(Some(5000));
+        assert!(restarted.gap);
+        assert_eq!(restarted.missed.len(), REPLAY_CAPACITY);
+    }
+}
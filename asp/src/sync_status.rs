@@ -0,0 +1,228 @@
+// Sync progress
+// The syncer reports every page or chunk it applies to a SyncTracker; the API and any other
+// holder of the tracker read a consistent SyncStatus from it, to tell whether proofs served
+// now reflect the chain tip and, if not, roughly when they will. The syncer also beats a
+// heartbeat here, which the watchdog checks to notice a hung or dead sync task.
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct SyncStatus {
+    /// Every event up to this block is in the tree
+    pub current_block: u64,
+    /// Chain tip the syncer is catching up to
+    pub target_block: u64,
+    /// Leaves in the deposit tree
+    pub leaves_indexed: u32,
+    /// Event throughput of the current (or last) scan
+    pub events_per_second: f64,
+    /// Estimated seconds until `target_block` is reached, unknown until some progress is made
+    pub eta_seconds: Option<u64>,
+    /// The tree reflects the chain as of `target_block`
+    pub synced: bool,
+    /// Whether the sync task is alive, as seen by the watchdog
+    pub health: SyncHealth,
+    /// Times the watchdog restarted the sync task
+    pub restarts: u32,
+}
+
+/// State of the sync task; while not `Running`, served roots may be stale
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncHealth {
+    /// Beating its heartbeat
+    #[default]
+    Running,
+    /// No heartbeat for too long: hung on the provider or deadlocked
+    Stalled,
+    /// The task panicked or returned
+    Crashed,
+    /// Waiting out the backoff before the task is started again
+    Restarting,
+}
+
+impl SyncStatus {
+    pub fn blocks_behind(&self) -> u64 {
+        self.target_block.saturating_sub(self.current_block)
+    }
+
+    /// Why proofs served now shouldn't be trusted with the chain at `chain_tip`, if they can be:
+    /// the sync task is down, or more than `max_blocks_behind` blocks behind the tip
+    pub fn unready_reason(&self, chain_tip: u64, max_blocks_behind: u64) -> Option<String> {
+        if self.health != SyncHealth::Running {
+            return Some(format!("sync task {:?}", self.health).to_lowercase());
+        }
+        let behind = chain_tip.saturating_sub(self.current_block);
+        (behind > max_blocks_behind).then(|| format!("{} blocks behind the chain tip (at most {})", behind, max_blocks_behind))
+    }
+}
+
+struct Scan {
+    started: Instant,
+    start_block: u64,
+    events: u64,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    status: SyncStatus,
+    scan: Option<Scan>,
+    last_heartbeat: Option<Instant>,
+    restart_requested: bool,
+}
+
+/// Shared handle on the syncer's progress; clones see the same status
+#[derive(Clone, Default)]
+pub struct SyncTracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl SyncTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current status snapshot
+    pub fn status(&self) -> SyncStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    /// The sync task is alive and making progress
+    pub fn heartbeat(&self) {
+        self.state.lock().unwrap().last_heartbeat = Some(Instant::now());
+    }
+
+    /// When the sync task last beat, None if it never did
+    pub fn last_heartbeat(&self) -> Option<Instant> {
+        self.state.lock().unwrap().last_heartbeat
+    }
+
+    /// Move to `health`, returning the previous state
+    pub fn set_health(&self, health: SyncHealth) -> SyncHealth {
+        std::mem::replace(&mut self.state.lock().unwrap().status.health, health)
+    }
+
+    /// Ask the watchdog to restart the sync task at its next check (e.g. after a resync)
+    pub fn request_restart(&self) {
+        self.state.lock().unwrap().restart_requested = true;
+    }
+
+    /// Whether a restart was asked for since the last call
+    pub fn take_restart_request(&self) -> bool {
+        std::mem::take(&mut self.state.lock().unwrap().restart_requested)
+    }
+
+    /// The watchdog started the sync task again
+    pub fn record_restart(&self) {
+        self.state.lock().unwrap().status.restarts += 1;
+    }
+
+    /// A scan of `from_block..=target_block` begins
+    pub fn start_scan(&self, from_block: u64, target_block: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.scan = Some(Scan {
+            started: Instant::now(),
+            start_block: from_block,
+            events: 0,
+        });
+        state.status.current_block = from_block;
+        state.status.target_block = target_block;
+        state.status.synced = from_block >= target_block;
+        state.status.eta_seconds = None;
+    }
+
+    /// `events` more events were applied; the tree now covers up to `current_block`
+    pub fn record_progress(&self, current_block: u64, events: usize, leaves_indexed: u32) {
+        let mut state = self.state.lock().unwrap();
+        let TrackerState { status, scan, .. } = &mut *state;
+        status.current_block = current_block.max(status.current_block);
+        status.leaves_indexed = leaves_indexed;
+        if let Some(scan) = scan {
+            scan.events += events as u64;
+            let (events_per_second, eta) = rates(
+                status.current_block.saturating_sub(scan.start_block),
+                scan.events,
+                status.blocks_behind(),
+                scan.started.elapsed(),
+            );
+            status.events_per_second = events_per_second;
+            status.eta_seconds = eta;
+        }
+    }
+
+    /// The tree is up to date with `block`
+    pub fn finish_scan(&self, block: u64, leaves_indexed: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.scan = None;
+        let status = &mut state.status;
+        status.current_block = block;
+        status.target_block = status.target_block.max(block);
+        status.leaves_indexed = leaves_indexed;
+        status.eta_seconds = Some(0);
+        status.synced = block >= status.target_block;
+    }
+}
+
+/// Event rate and ETA after scanning `scanned_blocks` blocks holding `events` events in `elapsed`
+fn rates(scanned_blocks: u64, events: u64, remaining_blocks: u64, elapsed: Duration) -> (f64, Option<u64>) {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return (0.0, None);
+    }
+    let events_per_second = events as f64 / seconds;
+    let eta = (scanned_blocks > 0).then(|| {
+        let blocks_per_second = scanned_blocks as f64 / seconds;
+        (remaining_blocks as f64 / blocks_per_second).ceil() as u64
+    });
+    (events_per_second, eta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_and_eta() {
+        // 1000 blocks and 50 events in 10s, 3000 blocks to go
+        let (events_per_second, eta) = rates(1000, 50, 3000, Duration::from_secs(10));
+        assert_eq!(events_per_second, 5.0);
+        assert_eq!(eta, Some(30));
+        // No block scanned yet: no estimate
+        assert_eq!(rates(0, 0, 3000, Duration::from_secs(1)).1, None);
+    }
+
+    #[test]
+    fn test_readiness() {
+        let status = SyncStatus { current_block: 990, target_block: 990, ..SyncStatus::default() };
+        assert_eq!(status.unready_reason(1000, 10), None);
+        // The tip moved on since the syncer last looked
+        assert!(status.unready_reason(1001, 10).unwrap().contains("11 blocks behind"));
+        let restarting = SyncStatus { health: SyncHealth::Restarting, ..status };
+        assert_eq!(restarting.unready_reason(990, 10).as_deref(), Some("sync task restarting"));
+    }
+
+    #[test]
+    fn test_tracker_lifecycle() {
+        let tracker = SyncTracker::new();
+        let api_view = tracker.clone();
+
+        tracker.start_scan(100, 500);
+        assert!(!api_view.status().synced);
+        assert_eq!(api_view.status().blocks_behind(), 400);
+
+        tracker.record_progress(300, 12, 12);
+        let status = api_view.status();
+        assert_eq!(status.current_block, 300);
+        assert_eq!(status.leaves_indexed, 12);
+        assert!(!status.synced);
+
+        tracker.finish_scan(500, 20);
+        let status = api_view.status();
+        assert!(status.synced);
+        assert_eq!(status.eta_seconds, Some(0));
+        assert_eq!(status.blocks_behind(), 0);
+    }
+}
@@ -1,20 +1,39 @@
-use crate::merkle::{MerkleTree, TREE_DEPTH};
+use crate::bootstrap::{load_snapshot, verify_snapshot};
+use crate::checkpoint::{BlockMark, CheckpointStore, SyncCheckpoint};
+use crate::commitment_index::{CommitmentIndex, LeafOrigin};
+use crate::config::ClientConfig;
+use crate::consistency::{ConsistencyReport, Divergence, CONSISTENCY_LOOKBACK_BLOCKS};
+use crate::dedupe::EventKey;
+use crate::error::BlockchainError;
+use crate::event_fetcher::{fetch_chunks, FetchConfig};
+use crate::events::{deposit_selector, nullifier_spent_selector, DepositEvent};
+use crate::failover::FailoverProvider;
+use crate::hasher::Hasher;
+use crate::instrument::InstrumentedProvider;
+use crate::merkle::MerkleTree;
+use crate::nullifier_tree::NullifierTree;
+use crate::page_size::AdaptivePageSize;
+use crate::provider::StarknetProvider;
+use crate::redact::{RedactionPolicy, Sensitive};
+use crate::retry::{RetryPolicy, RetryProvider};
+use crate::root_history::RootHistory;
+use crate::snapshot::{encode_snapshot, export_snapshot};
+use crate::state_store::StateStore;
+use crate::sync_feed::{SyncFeed, SyncNotification};
+use crate::sync_status::SyncTracker;
+use crate::tree_store::TreeStore;
+use crate::tree_versions::TreeVersions;
+use async_trait::async_trait;
+use futures_util::StreamExt;
 use num_bigint::BigUint;
 use starknet::{
-    core::types::{BlockId, EventFilter, FieldElement},
-    core::utils::starknet_keccak,
-    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+    core::types::{BlockId, EmittedEvent, EventFilter, FieldElement, FunctionCall},
+    core::utils::{get_selector_from_name, starknet_keccak},
 };
-use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
 use tokio::time::{sleep, Duration};
-use url::Url;
-
-/// Deposit event selector: starknet_keccak("Deposit")
-/// This is the hash of the event name used to filter deposit events
-/// Calculated as: starknet_keccak(b"Deposit") truncated to 250 bits
-const DEPOSIT_EVENT_SELECTOR: &str =
-    "0x9149d2123147c5f43d258257fef0b7b969db78269369ebcf5ebb9eef8592f2";
 
 /// Calculate event selector from name
 fn get_event_selector(name: &str) -> FieldElement {
@@ -23,31 +42,95 @@ fn get_event_selector(name: &str) -> FieldElement {
     hash & FieldElement::from_hex_be("0x3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap()
 }
 
-/// State file for persistence
-const STATE_FILE: &str = "asp_state.json";
+/// Delay between sync rounds when polling, and the longest wait for a new head
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Outcome of a `backfill`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct BackfillReport {
+    /// Deposits found in the range
+    pub deposits: usize,
+    /// Leaves that were missing (never synced, or a zero-filled gap) and are now in the tree
+    pub inserted: usize,
+    /// Leaves already in the tree with the same commitment
+    pub already_present: usize,
+    /// Leaves that held a different commitment and were corrected
+    pub replaced: usize,
+}
 
-#[derive(serde::Serialize, serde::Deserialize, Default)]
-struct SyncerState {
-    last_synced_block: u64,
+/// Pushes the events of a contract as its blocks are accepted (e.g. a WebSocket subscription)
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    /// Events emitted by `address` from now on, in chain order; the receiver ends on disconnect
+    async fn subscribe_events(&self, address: FieldElement) -> Result<mpsc::UnboundedReceiver<EmittedEvent>, BlockchainError>;
 }
 
 pub struct Syncer {
-    pub provider: Arc<JsonRpcClient<HttpTransport>>,
+    pub provider: Arc<dyn StarknetProvider>,
     pub contract_address: FieldElement,
     pub tree: Arc<Mutex<MerkleTree>>,
     pub deposit_selector: FieldElement,
+    pub nullifier_spent_selector: FieldElement,
     pub swap_selector: FieldElement,
     pub pool_event_selector: FieldElement,
     pub blockchain_client: Option<Arc<crate::blockchain::BlockchainClient>>,
+    pub redaction: RedactionPolicy,
+    /// New block numbers pushed by the node (WebSocket subscription); polls if unset
+    pub new_heads: Option<tokio::sync::Mutex<broadcast::Receiver<u64>>>,
+    /// Where progress is saved between restarts; progress is kept in memory only if unset
+    pub checkpoints: Option<Arc<dyn StateStore>>,
+    /// Node file the tree is saved to, so a restart doesn't rehash it; rebuilt from the leaves if unset
+    pub tree_store: Option<TreeStore>,
+    /// How block ranges are split and fetched
+    pub fetch: FetchConfig,
+    /// Event page size, adapted to the provider within `fetch.page_size`
+    event_pages: AdaptivePageSize,
+    /// Progress reported to the API
+    pub status: SyncTracker,
+    /// Leaf index of every deposit applied, for lookups without event scans
+    pub commitments: CommitmentIndex,
+    /// Roots the tree went through, with their block and leaf count
+    pub roots: RootHistory,
+    /// The tree at each recorded root, for proofs without the tree lock
+    pub versions: TreeVersions,
+    /// Nullifiers spent on chain, for non-membership proofs
+    pub nullifier_tree: Arc<Mutex<NullifierTree>>,
+    /// Deposits, roots and spent nullifiers as they are applied, for push clients
+    pub feed: SyncFeed,
+    /// Event subscriptions followed instead of polling get_events; polls if unset
+    pub event_source: Option<Arc<dyn EventSource>>,
+    /// Snapshot (URL or file) a fresh syncer starts from instead of the deployment block
+    pub snapshot_source: Option<String>,
+    /// Blocks the Deposit event (block, transaction) of a leaf is kept for; forever if unset
+    pub history_retention: Option<u64>,
+    /// Live subscription from `event_source`
+    subscription: tokio::sync::Mutex<Option<Subscription>>,
+    /// Block the next run rebuilds the tree from, set by `schedule_resync`
+    pending_resync: Mutex<Option<u64>>,
+    /// Saved leaves or origins were changed (backfill, prune): the next save copies them all
+    /// instead of only the leaves appended since the last one
+    leaves_rewritten: AtomicBool,
+}
+
+/// Event subscription, with the chain tip right after it started
+struct Subscription {
+    events: mpsc::UnboundedReceiver<EmittedEvent>,
+    /// Blocks up to this one may predate the subscription and are scanned by polling
+    started_at: u64,
 }
 
 impl Syncer {
     pub fn new(rpc_url: &str, contract_address: &str, tree: Arc<Mutex<MerkleTree>>) -> Self {
-        let provider = Arc::new(JsonRpcClient::new(HttpTransport::new(
-            Url::parse(rpc_url).unwrap(),
-        )));
+        // Same transport settings as the API client, so both share the endpoints' rate limits
+        let failover = FailoverProvider::from_urls(rpc_url, ClientConfig::from_env().pool(), false).unwrap();
+        let provider = Arc::new(RetryProvider::new(InstrumentedProvider::new(failover), RetryPolicy::default()));
         let contract_address = FieldElement::from_hex_be(contract_address).unwrap();
-        let deposit_selector = FieldElement::from_hex_be(DEPOSIT_EVENT_SELECTOR).unwrap();
+        Self::with_provider(provider, contract_address, tree).with_checkpoint_store(CheckpointStore::default())
+    }
+
+    /// Build a syncer on top of an already constructed provider
+    pub fn with_provider(provider: Arc<dyn StarknetProvider>, contract_address: FieldElement, tree: Arc<Mutex<MerkleTree>>) -> Self {
+        let deposit_selector = deposit_selector();
         
         // Calculate selectors for other events
         let swap_selector = get_event_selector("Swap");
@@ -58,9 +141,28 @@ impl Syncer {
             contract_address,
             tree,
             deposit_selector,
+            nullifier_spent_selector: nullifier_spent_selector(),
             swap_selector,
             pool_event_selector,
             blockchain_client: None,
+            redaction: RedactionPolicy::default(),
+            new_heads: None,
+            checkpoints: None,
+            tree_store: None,
+            fetch: FetchConfig::default(),
+            event_pages: AdaptivePageSize::default(),
+            status: SyncTracker::new(),
+            commitments: CommitmentIndex::new(),
+            roots: RootHistory::default(),
+            versions: TreeVersions::default(),
+            nullifier_tree: Arc::new(Mutex::new(NullifierTree::new())),
+            feed: SyncFeed::default(),
+            event_source: None,
+            snapshot_source: None,
+            history_retention: None,
+            subscription: tokio::sync::Mutex::new(None),
+            pending_resync: Mutex::new(None),
+            leaves_rewritten: AtomicBool::new(false),
         }
     }
 
@@ -69,38 +171,512 @@ impl Syncer {
         self
     }
 
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Sync as soon as the node announces a block instead of every POLL_INTERVAL
+    pub fn with_new_heads(mut self, new_heads: broadcast::Receiver<u64>) -> Self {
+        self.new_heads = Some(tokio::sync::Mutex::new(new_heads));
+        self
+    }
+
+    /// Wait for the next sync round: a new head, or POLL_INTERVAL without one
+    async fn wait_for_next_block(&self) {
+        let Some(new_heads) = &self.new_heads else {
+            sleep(POLL_INTERVAL).await;
+            return;
+        };
+        let mut new_heads = new_heads.lock().await;
+        tokio::select! {
+            head = new_heads.recv() => {
+                if let Err(broadcast::error::RecvError::Closed) = head {
+                    // Subscription gone for good: don't spin on the closed channel
+                    sleep(POLL_INTERVAL).await;
+                }
+                // Lagged only means several blocks arrived while syncing; sync them at once
+            }
+            _ = sleep(POLL_INTERVAL) => {}
+        }
+    }
+
+    /// Follow the Zylith events pushed by `source`, polling get_events only while unsubscribed
+    pub fn with_event_source(mut self, source: Arc<dyn EventSource>) -> Self {
+        self.event_source = Some(source);
+        self
+    }
+
+    /// Apply the events the subscription pushes, waiting up to POLL_INTERVAL for the first one
+    /// Returns false when this round must poll instead: no event source, blocks from before
+    /// the subscription still to scan, a reorg to recover from or a dropped subscription.
+    async fn follow_events(&self, checkpoint: &mut SyncCheckpoint) -> bool {
+        let Some(source) = &self.event_source else {
+            return false;
+        };
+        let mut subscription = self.subscription.lock().await;
+        if subscription.is_none() {
+            match self.subscribe(source.as_ref()).await {
+                Ok(live) => {
+                    println!("[Syncer] 📡 Subscribed to Zylith events at block {}", live.started_at);
+                    *subscription = Some(live);
+                }
+                Err(e) => eprintln!("[Syncer] ⚠️  Event subscription failed ({}) - polling", e),
+            }
+            return false;
+        }
+        let live = subscription.as_mut().unwrap();
+        if checkpoint.last_synced_block < live.started_at || checkpoint.scan_in_progress() {
+            return false;
+        }
+
+        let mut next = match tokio::time::timeout(POLL_INTERVAL, live.events.recv()).await {
+            Ok(Some(event)) => Some(event),
+            Ok(None) => {
+                eprintln!("[Syncer] ⚠️  Event subscription dropped - polling until it is renewed");
+                *subscription = None;
+                return false;
+            }
+            Err(_) => return self.follow_quiet_chain(checkpoint).await,
+        };
+
+        let mut swap_events_seen = 0u32;
+        let mut applied = 0;
+        while let Some(event) = next {
+            // Pending events come again once accepted; events already applied are replays
+            if let Some(block) = event.block_number.filter(|_| checkpoint.applied_events.key_for(&event).is_some()) {
+                if let Err(e) = self.apply_event(&event, checkpoint, &mut swap_events_seen) {
+                    eprintln!("[Syncer] ❌ {} - dropping the event subscription", e);
+                    *subscription = None;
+                    return false;
+                }
+                // Events come in block order: every block before this one is complete. An event
+                // of a block a quiet tick already scanned, served late, moves the checkpoint back
+                // before that block, dropping the block's mark
+                checkpoint.last_synced_block = block - 1;
+                checkpoint.recent_blocks.retain(|mark| mark.number < block);
+                applied += 1;
+            }
+            next = live.events.try_recv().ok();
+        }
+        if applied > 0 {
+            let leaf_count = self.tree.lock().unwrap().get_leaf_count();
+            self.status.record_progress(checkpoint.last_synced_block, applied, leaf_count);
+            self.save_progress(checkpoint);
+        }
+        true
+    }
+
+    /// No Zylith event for POLL_INTERVAL: check that the blocks synced so far are still there,
+    /// then scan up to the chain tip, so a quiet chain doesn't read as lagging. Events the
+    /// subscription pushes later for the scanned blocks are told from replays by their key.
+    /// Returns false when a reorg was rolled back or the scan failed and this round must poll.
+    async fn follow_quiet_chain(&self, checkpoint: &mut SyncCheckpoint) -> bool {
+        match self.handle_reorg(checkpoint).await {
+            Ok(true) => return false,
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("[Syncer] ❌ Reorg check failed: {}", e);
+                return true;
+            }
+        }
+        let tip = match self.provider.block_number().await {
+            Ok(tip) => tip,
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read the chain tip: {}", e);
+                return true;
+            }
+        };
+        if tip > checkpoint.last_synced_block {
+            let mut swap_events_seen = 0u32;
+            self.status.start_scan(checkpoint.last_synced_block, tip);
+            if let Err(e) = self.scan_pages(checkpoint, tip, None, &mut swap_events_seen).await {
+                eprintln!("[Syncer] ⚠️  Failed to scan up to the chain tip: {}", e);
+                return false;
+            }
+            if let Some(ref blockchain) = self.blockchain_client {
+                blockchain.observe_block(tip);
+            }
+        }
+        true
+    }
+
+    /// Start an event subscription; the tip is read once it is live, so no block falls between
+    /// the polled range and the subscribed one
+    async fn subscribe(&self, source: &dyn EventSource) -> Result<Subscription, BlockchainError> {
+        let events = source.subscribe_events(self.contract_address).await?;
+        let started_at = self.provider.block_number().await?;
+        Ok(Subscription { events, started_at })
+    }
+
+    /// Persist progress to `store` and resume from it on start
+    pub fn with_checkpoint_store(mut self, store: impl StateStore + 'static) -> Self {
+        self.checkpoints = Some(Arc::new(store));
+        self
+    }
+
+    /// Keep the tree's nodes in `store` and load them back on start
+    pub fn with_tree_store(mut self, store: TreeStore) -> Self {
+        self.tree_store = Some(store);
+        self
+    }
+
+    /// Chunk size, parallelism and page size bounds of event fetching
+    pub fn with_fetch_config(mut self, fetch: FetchConfig) -> Self {
+        self.fetch = fetch;
+        self.event_pages = AdaptivePageSize::new(fetch.page_size);
+        self
+    }
+
+    /// Report progress to `tracker` (e.g. one shared with the API)
+    pub fn with_status_tracker(mut self, tracker: SyncTracker) -> Self {
+        self.status = tracker;
+        self
+    }
+
+    /// Record applied deposits in `index` (e.g. one shared with the API client)
+    pub fn with_commitment_index(mut self, index: CommitmentIndex) -> Self {
+        self.commitments = index;
+        self
+    }
+
+    /// Start a fresh sync from the snapshot at `source` (URL or file), once it is verified
+    pub fn with_snapshot_source(mut self, source: impl Into<String>) -> Self {
+        self.snapshot_source = Some(source.into());
+        self
+    }
+
+    /// Forget the Deposit event of leaves more than `blocks` behind the synced block (the
+    /// leaves themselves and the roots stay)
+    pub fn with_history_retention(mut self, blocks: u64) -> Self {
+        self.history_retention = Some(blocks);
+        self
+    }
+
+    /// Record produced roots in `history` (e.g. one shared with the API)
+    pub fn with_root_history(mut self, history: RootHistory) -> Self {
+        self.roots = history;
+        self
+    }
+
+    /// Publish the tree at every recorded root to `versions` (e.g. ones shared with the API)
+    pub fn with_tree_versions(mut self, versions: TreeVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Track spent nullifiers in `tree` (e.g. one shared with the API)
+    pub fn with_nullifier_tree(mut self, tree: Arc<Mutex<NullifierTree>>) -> Self {
+        self.nullifier_tree = tree;
+        self
+    }
+
+    /// Publish applied deposits, roots and nullifiers to `feed` (e.g. one shared with the API)
+    pub fn with_sync_feed(mut self, feed: SyncFeed) -> Self {
+        self.feed = feed;
+        self
+    }
+
+    /// Rebuild the tree from `block`, without restarting the process: the watchdog is asked to
+    /// restart the sync task, which drops the tree and syncs again from there
+    pub fn schedule_resync(&self, block: u64) {
+        *self.pending_resync.lock().unwrap() = Some(block);
+        self.status.request_restart();
+    }
+
+    /// If a resync was scheduled since the last call, start `state` over from its block with
+    /// an empty tree
+    fn apply_pending_resync(&self, state: &mut SyncCheckpoint) {
+        let Some(block) = self.pending_resync.lock().unwrap().take() else {
+            return;
+        };
+        println!("[Syncer] 🔄 Resync requested: rebuilding the tree from block {}", block);
+        *state = SyncCheckpoint::at_block(block);
+        self.save_state(state);
+        self.restore(state);
+    }
+
+    /// Handle on the sync progress, updated as the syncer runs
+    pub fn tracker(&self) -> SyncTracker {
+        self.status.clone()
+    }
+
     /// Load persisted state
-    fn load_state() -> SyncerState {
-        fs::read_to_string(STATE_FILE)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
+    fn load_state(&self) -> SyncCheckpoint {
+        self.checkpoints.as_ref().map(|store| store.load()).unwrap_or_default()
     }
 
     /// Save state to file
-    fn save_state(state: &SyncerState) {
-        if let Ok(json) = serde_json::to_string(state) {
-            let _ = fs::write(STATE_FILE, json);
+    fn save_state(&self, state: &SyncCheckpoint) {
+        if let Some(store) = &self.checkpoints {
+            if let Err(e) = store.save(state) {
+                eprintln!("[Syncer] ⚠️  Failed to save checkpoint: {}", e);
+            }
+        }
+    }
+
+    /// Save the tree with `checkpoint`; every deposit up to its block is now indexed
+    /// Only leaves appended since the last save are copied into the checkpoint, unless saved
+    /// leaves or origins changed since.
+    fn save_progress(&self, checkpoint: &mut SyncCheckpoint) {
+        let pruned = match self.history_retention {
+            Some(blocks) => self.commitments.prune_origins(checkpoint.last_synced_block.saturating_sub(blocks)),
+            None => 0,
+        };
+        let rewritten = self.leaves_rewritten.swap(false, Ordering::SeqCst) || pruned > 0;
+        let mut tree = self.tree.lock().unwrap();
+        let first = if rewritten {
+            checkpoint.record_tree(&tree);
+            0
+        } else {
+            checkpoint.record_appended(&tree)
+        };
+        self.save_nodes(&mut tree);
+        drop(tree);
+        let first = first.min(checkpoint.leaf_origins.len());
+        checkpoint.leaf_origins.truncate(first);
+        checkpoint.leaf_origins.extend(self.commitments.origins(&checkpoint.leaves[first..]));
+        checkpoint.record_nullifiers(&self.nullifier_tree.lock().unwrap());
+        self.save_state(checkpoint);
+        self.commitments.set_synced_block(checkpoint.last_synced_block);
+        self.status.heartbeat();
+    }
+
+    /// Put the tree and the commitment index back to the state of `checkpoint`
+    fn restore(&self, checkpoint: &SyncCheckpoint) {
+        let mut tree = self.tree.lock().unwrap();
+        // On start, the node file spares rehashing every leaf if it matches the checkpoint
+        let saved = if tree.get_leaf_count() == 0 { self.load_nodes(checkpoint, tree.depth, tree.hasher.clone()) } else { None };
+        match saved {
+            Some(saved) => *tree = saved,
+            None => {
+                *tree = checkpoint.restore_tree_with(tree.depth, tree.hasher.clone());
+                self.save_nodes(&mut tree);
+            }
+        }
+        // Roots of leaves rolled back are gone; the restored one is the latest
+        self.roots.truncate_to(tree.get_leaf_count());
+        self.versions.truncate_to(tree.get_leaf_count());
+        if tree.get_leaf_count() > 0 {
+            self.record_root(&tree, checkpoint.last_synced_block);
+        }
+        drop(tree);
+        self.nullifier_tree.lock().unwrap().reset_to(&checkpoint.nullifiers);
+        self.commitments.rebuild(&checkpoint.leaves, &checkpoint.leaf_origins, checkpoint.last_synced_block);
+    }
+
+    /// Tree in the node file, if it holds exactly the leaves of `checkpoint`
+    fn load_nodes(&self, checkpoint: &SyncCheckpoint, depth: usize, hasher: Arc<dyn Hasher>) -> Option<MerkleTree> {
+        let mut tree = match self.tree_store.as_ref()?.load(depth, hasher) {
+            Ok(tree) => tree?,
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Failed to read tree nodes: {}", e);
+                return None;
+            }
+        };
+        let leaves: Vec<BigUint> = checkpoint.leaves.iter().map(|leaf| BigUint::from_bytes_be(&leaf.to_bytes_be())).collect();
+        if tree.leaves() != leaves {
+            println!("[Syncer] ⚠️  Tree nodes on disk don't match the checkpoint - rebuilding from the leaves");
+            return None;
+        }
+        tree.track_changes();
+        Some(tree)
+    }
+
+    /// Write the nodes changed since the last save to the node file (all of them the first time)
+    fn save_nodes(&self, tree: &mut MerkleTree) {
+        let Some(store) = &self.tree_store else {
+            return;
+        };
+        let result = if tree.is_tracking_changes() {
+            // A failed append may leave a gap in the file: write it whole instead
+            store.append(tree).or_else(|_| store.rewrite(tree))
+        } else {
+            tree.track_changes();
+            store.rewrite(tree)
+        };
+        if let Err(e) = result {
+            eprintln!("[Syncer] ⚠️  Failed to save tree nodes: {}", e);
+        }
+    }
+
+    /// Forget the Deposit event of leaves deposited before `before_block`, returning how many
+    /// were dropped; the next save removes them from the sync state
+    pub fn prune_history(&self, before_block: u64) -> usize {
+        let pruned = self.commitments.prune_origins(before_block);
+        if pruned > 0 {
+            self.leaves_rewritten.store(true, Ordering::SeqCst);
+            println!("[Syncer] ✂️  Pruned the deposit events of {} leaves before block {}", pruned, before_block);
+        }
+        pruned
+    }
+
+    /// Binary snapshot of the tree as of the saved checkpoint
+    pub fn snapshot_bytes(&self) -> Vec<u8> {
+        self.with_checkpoint_tree(encode_snapshot)
+    }
+
+    /// Write the binary snapshot of the tree to `path`, e.g. to move this instance or publish it
+    pub fn export_snapshot(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.with_checkpoint_tree(|tree, synced_block| export_snapshot(path, tree, synced_block))
+    }
+
+    /// Run `f` on the tree holding exactly the checkpoint leaves, with the checkpoint block
+    fn with_checkpoint_tree<R>(&self, f: impl FnOnce(&MerkleTree, u64) -> R) -> R {
+        let checkpoint = self.load_state();
+        let tree = self.tree.lock().unwrap();
+        // Between two saves the tree can be ahead of the checkpoint
+        if tree.get_leaf_count() as usize == checkpoint.leaves.len() {
+            return f(&tree, checkpoint.last_synced_block);
+        }
+        let (depth, hasher) = (tree.depth, tree.hasher.clone());
+        drop(tree);
+        f(&checkpoint.restore_tree_with(depth, hasher), checkpoint.last_synced_block)
+    }
+
+    /// Checkpoint of the configured snapshot, verified and saved; None to sync from scratch
+    async fn bootstrap_from_snapshot(&self) -> Option<SyncCheckpoint> {
+        let source = self.snapshot_source.as_deref()?;
+        println!("[Syncer] 📦 Bootstrapping from snapshot {}", source);
+        let verified = match load_snapshot(source).await {
+            Ok(snapshot) => {
+                let (depth, hasher) = {
+                    let tree = self.tree.lock().unwrap();
+                    (tree.depth, tree.hasher.clone())
+                };
+                verify_snapshot(self.provider.as_ref(), self.contract_address, &snapshot, depth, hasher).await
+            }
+            Err(e) => Err(e),
+        };
+        let mut checkpoint = match verified {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Snapshot rejected ({}), syncing from scratch", e);
+                return None;
+            }
+        };
+
+        self.restore(&checkpoint);
+        self.status.start_scan(checkpoint.last_synced_block, checkpoint.last_synced_block);
+        self.complete_scan(&mut checkpoint, checkpoint.last_synced_block).await;
+        self.save_progress(&mut checkpoint);
+        println!(
+            "[Syncer] ✅ Snapshot verified on-chain: {} leaves up to block {}",
+            checkpoint.leaves.len(),
+            checkpoint.last_synced_block
+        );
+        Some(checkpoint)
+    }
+
+    /// Block a sync from scratch starts after: the one before the contract deployment
+    async fn fresh_start_block(&self) -> u64 {
+        let blockchain = match self.blockchain_client {
+            Some(ref blockchain) => blockchain,
+            None => return 0,
+        };
+        match blockchain.deployment_block().await {
+            Ok(block) => block.saturating_sub(1),
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Deployment block unknown ({}), syncing from genesis", e);
+                0
+            }
+        }
+    }
+
+    /// Bring the tree and indexes to `checkpoint`, saved by another instance: leaves appended
+    /// since the last one are hashed in, anything else (a reorg, a resync) restores it whole
+    fn follow(&self, checkpoint: &SyncCheckpoint) {
+        let mut tree = self.tree.lock().unwrap();
+        let leaves: Vec<BigUint> = checkpoint.leaves.iter().map(|leaf| BigUint::from_bytes_be(&leaf.to_bytes_be())).collect();
+        let current = tree.leaves();
+        if current.is_empty() || current.len() > leaves.len() || leaves[..current.len()] != current[..] {
+            drop(tree);
+            return self.restore(checkpoint);
+        }
+        for leaf in &leaves[current.len()..] {
+            tree.append(leaf.clone()).expect("felts are valid leaves");
+        }
+        if leaves.len() > current.len() {
+            self.record_root(&tree, checkpoint.last_synced_block);
+            self.save_nodes(&mut tree);
+        }
+        drop(tree);
+        self.nullifier_tree.lock().unwrap().reset_to(&checkpoint.nullifiers);
+        self.commitments.rebuild(&checkpoint.leaves, &checkpoint.leaf_origins, checkpoint.last_synced_block);
+    }
+
+    /// With a state shared between instances, follow what the syncing one saves until this
+    /// one may sync
+    async fn wait_for_sync_lock(&self) {
+        let Some(store) = self.checkpoints.clone() else {
+            return;
+        };
+        let mut followed = None;
+        loop {
+            self.status.heartbeat();
+            match store.try_lock_sync() {
+                Ok(true) => {
+                    if followed.is_some() {
+                        println!("[Syncer] 🔓 Sync lock taken - this instance syncs from now on");
+                    }
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!("[Syncer] ⚠️  Failed to take the sync lock: {}", e),
+            }
+            let state = self.load_state();
+            if followed != Some(state.last_synced_block) {
+                if followed.is_none() {
+                    println!("[Syncer] 🔒 Another instance is syncing - following its saved state");
+                }
+                self.follow(&state);
+                followed = Some(state.last_synced_block);
+            }
+            sleep(POLL_INTERVAL).await;
         }
     }
 
     pub async fn run(&self) {
-        let mut state = Self::load_state();
+        self.wait_for_sync_lock().await;
+        self.status.heartbeat();
+        let mut state = self.load_state();
+        if self.tree.is_poisoned() {
+            // A previous run panicked holding the tree: don't trust what it left behind
+            self.tree.clear_poison();
+            self.restore(&state);
+        }
         
         // Check if we should force re-sync from a specific block
         if let Ok(reset_block_str) = std::env::var("RESYNC_FROM_BLOCK") {
             if let Ok(reset_block) = reset_block_str.parse::<u64>() {
-                state.last_synced_block = reset_block;
-                Self::save_state(&state);
+                state = SyncCheckpoint::at_block(reset_block);
+                self.save_state(&state);
             }
         }
+        self.apply_pending_resync(&mut state);
         
-        // If state file doesn't exist or last_synced_block is 0, start from block 0 (genesis)
-        // This ensures we sync ALL events from the beginning
+        // A fresh syncer may start from a verified snapshot instead of the deployment block
+        if state.last_synced_block == 0 && state.leaves.is_empty() {
+            if let Some(checkpoint) = self.bootstrap_from_snapshot().await {
+                state = checkpoint;
+            }
+        }
+
+        // If state file doesn't exist or last_synced_block is 0, start right before the
+        // contract deployment (block 0 when it can't be found) to sync ALL its events
         if state.last_synced_block == 0 {
-            state.last_synced_block = 0;
-            Self::save_state(&state);
-            println!("[Syncer] 🚀 Starting fresh sync from block 0 (genesis)");
+            state.last_synced_block = self.fresh_start_block().await;
+            self.save_state(&state);
+            println!("[Syncer] 🚀 Starting fresh sync after block {}", state.last_synced_block);
+        } else if !state.leaves.is_empty() {
+            // Pick up the tree where the last run left it
+            self.restore(&state);
+            println!(
+                "[Syncer] 📂 Resuming from checkpoint at block {} ({} leaves{})",
+                state.last_synced_block,
+                state.leaves.len(),
+                if state.scan_in_progress() { ", mid-scan" } else { "" }
+            );
         }
         
         // Check if tree is empty but contract has deposits
@@ -113,10 +689,10 @@ impl Syncer {
             if let Some(ref blockchain) = self.blockchain_client {
                 match blockchain.get_merkle_root().await {
                     Ok(contract_root) if contract_root != "0x0" && contract_root != "0x0000000000000000000000000000000000000000000000000000000000000000" => {
-                        // If contract has deposits but tree is empty, start from block 0 to sync everything
-                        state.last_synced_block = 0;
-                        Self::save_state(&state);
-                        println!("[Syncer] 🚀 Contract has deposits but tree is empty - starting sync from block 0");
+                        // If contract has deposits but tree is empty, sync everything since the deployment
+                        state = SyncCheckpoint::at_block(self.fresh_start_block().await);
+                        self.save_state(&state);
+                        println!("[Syncer] 🚀 Contract has deposits but tree is empty - starting sync after block {}", state.last_synced_block);
                     }
                     _ => {}
                 }
@@ -124,23 +700,9 @@ impl Syncer {
         }
 
         loop {
-            // Reload state from file in each iteration to pick up resync requests
-            // This allows the /deposit/resync endpoint to trigger immediate resync
-            let current_state = Self::load_state();
-            if current_state.last_synced_block < state.last_synced_block {
-                // State file was reset to an earlier block - force resync
-                println!("[Syncer] 🔄 Detected resync request - resetting to block {}", current_state.last_synced_block);
-                state.last_synced_block = current_state.last_synced_block;
-                
-                // Clear the tree to force full resync
-                {
-                    let mut tree = self.tree.lock().unwrap();
-                    *tree = MerkleTree::new(TREE_DEPTH); // Reset tree - use TREE_DEPTH constant
-                }
-            } else if current_state.last_synced_block != state.last_synced_block {
-                // State was updated but not reset - just update our state
-                state.last_synced_block = current_state.last_synced_block;
-            }
+            self.status.heartbeat();
+            // A resync scheduled while running starts over right away, without reloading the state
+            self.apply_pending_resync(&mut state);
             
             // TEMPORARILY DISABLED FOR DEBUGGING - Root check causes infinite loop
             // First, verify our tree root matches the contract
@@ -174,7 +736,7 @@ impl Syncer {
                         false // Don't resync in debug mode
                     }
                     Err(e) => {
-                        eprintln!("[Syncer] ❌ Failed to get contract root: {:?}", e);
+                        eprintln!("[Syncer] ❌ Failed to get contract root: {}", e);
                         false
                     }
                 }
@@ -193,8 +755,8 @@ impl Syncer {
                 
                 println!("[Syncer] 🔄 Root mismatch detected - starting full resync from block 0");
                 println!("[Syncer]    Current tree has {} leaves", leaf_count);
-                state.last_synced_block = 0; // Start from genesis to sync everything
-                Self::save_state(&state);
+                state = SyncCheckpoint::at_block(0); // Start from genesis to sync everything
+                self.save_state(&state);
                 
                 // Clear the tree to force full resync
                 {
@@ -205,12 +767,19 @@ impl Syncer {
             }
             */
 
-            match self.sync_events(state.last_synced_block).await {
+            // Events pushed by the subscription replace this round's get_events poll
+            if self.follow_events(&mut state).await {
+                continue;
+            }
+
+            let old_block = state.last_synced_block;
+            match self.sync_events(&mut state).await {
                 Ok(new_last_block) => {
-                    if new_last_block > state.last_synced_block {
-                        let old_block = state.last_synced_block;
-                        state.last_synced_block = new_last_block;
-                        Self::save_state(&state);
+                    if let Some(ref blockchain) = self.blockchain_client {
+                        // Cached latest-block reads (root, known roots) are stale past this block
+                        blockchain.observe_block(new_last_block);
+                    }
+                    if new_last_block > old_block {
                         
                         // Log progress if we synced a significant number of blocks
                         if new_last_block - old_block > 100 {
@@ -223,156 +792,867 @@ impl Syncer {
                     }
                 }
                 Err(e) => {
-                    eprintln!("[Syncer] ❌ Sync error: {:?}", e);
+                    eprintln!("[Syncer] ❌ Sync error: {}", e);
                     // Continue trying - don't exit on error
                 }
             }
-            sleep(Duration::from_secs(5)).await;
+            self.wait_for_next_block().await;
         }
     }
 
-    async fn sync_events(&self, from_block: u64) -> Result<u64, Box<dyn std::error::Error>> {
-        let latest_block = self.provider.block_number().await?;
-        if from_block >= latest_block {
-            return Ok(from_block);
+    /// Re-scan `from_block..=to_block` and repair the tree with the deposits found there,
+    /// e.g. to recover deposits missed during an RPC outage without a full resync
+    /// Deposits are matched against the tree by leaf index; the sync position is left as is.
+    pub async fn backfill(&self, from_block: u64, to_block: u64) -> Result<BackfillReport, BlockchainError> {
+        if from_block > to_block {
+            return Err(BlockchainError::InvalidInput(format!(
+                "backfill range {}..={} is empty",
+                from_block, to_block
+            )));
+        }
+        println!("[Syncer] 🩹 Backfilling blocks {} to {}", from_block, to_block);
+
+        let deposits = self.scan_deposits(from_block, to_block).await?;
+        let mut report = BackfillReport {
+            deposits: deposits.len(),
+            ..BackfillReport::default()
+        };
+        {
+            let mut tree = self.tree.lock().unwrap();
+            for deposit in &deposits {
+                let commitment = BigUint::from_bytes_be(&deposit.commitment.to_bytes_be());
+                let existing = tree.nodes.get(&(0, deposit.leaf_index)).filter(|leaf| **leaf != tree.zeros[0]);
+                match existing {
+                    Some(leaf) if *leaf == commitment => {
+                        report.already_present += 1;
+                        continue;
+                    }
+                    Some(_) => {
+                        eprintln!(
+                            "[Syncer] ⚠️  Leaf {} held another commitment, replacing it",
+                            self.redaction.redact(Sensitive::LeafIndex, deposit.leaf_index)
+                        );
+                        report.replaced += 1;
+                    }
+                    None => report.inserted += 1,
+                }
+                // Skipped indices past the end read as zero leaves, like gaps in apply_event
+                tree.insert_at_index(deposit.leaf_index, commitment).expect("felts are valid leaves");
+            }
         }
 
+        if report.inserted + report.replaced > 0 {
+            let mut checkpoint = self.load_state();
+            checkpoint.record_tree(&self.tree.lock().unwrap());
+            // Backfilled leaves come from a scan without their transactions: no origin
+            checkpoint.leaf_origins = self.commitments.origins(&checkpoint.leaves);
+            self.save_state(&checkpoint);
+            // Replaced leaves leave stale entries behind: start over from the repaired tree
+            self.commitments.rebuild(&checkpoint.leaves, &checkpoint.leaf_origins, self.commitments.synced_block());
+            self.leaves_rewritten.store(true, Ordering::SeqCst);
+        }
+        println!(
+            "[Syncer] ✅ Backfill done: {} deposit(s), {} inserted, {} replaced, {} already present",
+            report.deposits, report.inserted, report.replaced, report.already_present
+        );
+        Ok(report)
+    }
+
+    /// Deposit events of `from_block..=to_block`, by leaf index
+    async fn scan_deposits(&self, from_block: u64, to_block: u64) -> Result<Vec<DepositEvent>, BlockchainError> {
+        let mut deposits = Vec::new();
+        let mut chunks = std::pin::pin!(fetch_chunks(
+            self.provider.as_ref(),
+            self.contract_address,
+            from_block,
+            to_block,
+            self.fetch,
+            &self.event_pages,
+        ));
+        while let Some(chunk) = chunks.next().await {
+            let (_, events) = chunk?;
+            for event in &events {
+                match DepositEvent::decode(&event.keys, &event.data) {
+                    Ok(Some(deposit)) => deposits.push(deposit),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("[Syncer] ⚠️  Skipping malformed Deposit event: {}", e),
+                }
+            }
+        }
+        deposits.sort_by_key(|d| d.leaf_index);
+        deposits.dedup_by_key(|d| d.leaf_index);
+        Ok(deposits)
+    }
+
+    /// Compare the root of the checkpoint leaves with the contract's root at the checkpoint
+    /// block; on a mismatch, the last CONSISTENCY_LOOKBACK_BLOCKS blocks are rescanned to tell
+    /// which leaves are missing or differ
+    pub async fn verify_consistency(&self) -> Result<ConsistencyReport, BlockchainError> {
+        let checkpoint = self.load_state();
+        let block = checkpoint.last_synced_block;
+        let (depth, hasher) = {
+            let tree = self.tree.lock().unwrap();
+            (tree.depth, tree.hasher.clone())
+        };
+        let local = checkpoint.restore_tree_with(depth, hasher);
+
+        let call = FunctionCall {
+            contract_address: self.contract_address,
+            entry_point_selector: get_selector_from_name("get_merkle_root").unwrap(),
+            calldata: vec![],
+        };
+        let result = self.provider.call(call, BlockId::Number(block)).await?;
+        let on_chain = result
+            .first()
+            .ok_or_else(|| BlockchainError::Decode("Empty response from get_merkle_root".to_string()))?;
+
+        let local_root = format!("0x{:x}", local.get_root());
+        let on_chain_root = format!("0x{:x}", on_chain);
+        let mut report = ConsistencyReport {
+            block,
+            local_root: local_root.clone(),
+            on_chain_root: on_chain_root.clone(),
+            local_leaf_count: local.get_leaf_count(),
+            on_chain_leaf_count: None,
+            scanned_blocks: None,
+            divergences: Vec::new(),
+        };
+        if local_root == on_chain_root {
+            report.on_chain_leaf_count = Some(report.local_leaf_count);
+            return Ok(report);
+        }
+        report.divergences.push(Divergence::RootMismatch { local: local_root, on_chain: on_chain_root.clone() });
+
+        let from_block = block.saturating_sub(CONSISTENCY_LOOKBACK_BLOCKS - 1);
+        let deposits = self.scan_deposits(from_block, block).await?;
+        let known_leaf_count = self
+            .roots
+            .recent(usize::MAX)
+            .into_iter()
+            .find(|record| record.root == on_chain_root)
+            .map(|record| record.leaf_count);
+        report.scanned_blocks = Some((from_block, block));
+        report.diagnose(&checkpoint.leaves, &deposits, known_leaf_count);
+        println!(
+            "[Syncer] ⚠️  Tree diverges from the contract at block {}: {} leaves locally, {} on chain",
+            block,
+            report.local_leaf_count,
+            report.on_chain_leaf_count.map_or("unknown".to_string(), |count| count.to_string())
+        );
+        Ok(report)
+    }
+
+    /// Check the remembered block hashes against the chain; if the newest one was replaced,
+    /// roll the tree and checkpoint back to the last block still on the chain
+    /// Returns true if a reorg was found.
+    async fn handle_reorg(&self, checkpoint: &mut SyncCheckpoint) -> Result<bool, BlockchainError> {
+        let Some(newest) = checkpoint.recent_blocks.last().copied() else {
+            return Ok(false);
+        };
+        if self.provider.block_hash(newest.number).await? == newest.hash {
+            return Ok(false);
+        }
+
+        let mut ancestor = None;
+        for mark in checkpoint.recent_blocks.iter().rev().skip(1) {
+            if self.provider.block_hash(mark.number).await? == mark.hash {
+                ancestor = Some(*mark);
+                break;
+            }
+        }
+
+        match ancestor {
+            Some(mark) => {
+                println!(
+                    "[Syncer] ⚠️  Reorg detected at block {} - rolling back to block {} ({} leaves)",
+                    newest.number, mark.number, mark.leaf_count
+                );
+                checkpoint.roll_back_to(mark);
+            }
+            None => {
+                println!(
+                    "[Syncer] ⚠️  Reorg detected at block {} deeper than the remembered blocks - resyncing from block 0",
+                    newest.number
+                );
+                *checkpoint = SyncCheckpoint::at_block(0);
+            }
+        }
+
+        self.restore(checkpoint);
+        self.save_state(checkpoint);
+        if let Some(ref blockchain) = self.blockchain_client {
+            blockchain.on_reorg();
+        }
+        Ok(true)
+    }
+
+    /// Scan new Deposit events into the tree, saving `checkpoint` as it goes
+    /// Long ranges are fetched in parallel chunks and checkpointed after each chunk; short
+    /// ones page by page, resuming the scan recorded in the checkpoint if it stopped between
+    /// two pages. Returns the last fully scanned block.
+    async fn sync_events(&self, checkpoint: &mut SyncCheckpoint) -> Result<u64, BlockchainError> {
+        self.handle_reorg(checkpoint).await?;
+        let from_block = checkpoint.last_synced_block;
+        let mut swap_events_seen = 0u32;
+
+        match (checkpoint.scan_to_block, checkpoint.continuation_token.clone()) {
+            (Some(to_block), Some(token)) => {
+                self.status.start_scan(from_block, to_block);
+                self.scan_pages(checkpoint, to_block, Some(token), &mut swap_events_seen).await?
+            }
+            _ => {
+                let latest_block = self.provider.block_number().await?;
+                if from_block >= latest_block {
+                    self.status.start_scan(from_block, latest_block);
+                    self.status.finish_scan(from_block, self.tree.lock().unwrap().get_leaf_count());
+                    return Ok(from_block);
+                }
+                self.status.start_scan(from_block, latest_block);
+                if self.fetch.is_parallel_for(from_block + 1, latest_block) {
+                    self.scan_chunks(checkpoint, latest_block, &mut swap_events_seen).await?
+                } else {
+                    self.scan_pages(checkpoint, latest_block, None, &mut swap_events_seen).await?
+                }
+            }
+        }
+
+        // Only log if swap events were found
+        if swap_events_seen > 0 {
+            println!("[Syncer] 🔄 Found {} swap event(s)", swap_events_seen);
+        }
+
+        Ok(checkpoint.last_synced_block)
+    }
+
+    /// Scan `last_synced_block + 1..=to_block` one page after the other, saving the
+    /// continuation token after each page
+    async fn scan_pages(
+        &self,
+        checkpoint: &mut SyncCheckpoint,
+        to_block: u64,
+        mut continuation_token: Option<String>,
+        swap_events_seen: &mut u32,
+    ) -> Result<(), BlockchainError> {
         // Filter for events from our contract
         // Note: For nested enum events (Event::PrivacyEvent::Deposit), the Deposit selector
         // is in keys[2], not keys[0]. So we filter only by contract address and check
-        // all keys in the loop below.
+        // all keys in apply_event.
         let filter = EventFilter {
-            from_block: Some(BlockId::Number(from_block + 1)),
-            to_block: Some(BlockId::Number(latest_block)),
+            from_block: Some(BlockId::Number(checkpoint.last_synced_block + 1)),
+            to_block: Some(BlockId::Number(to_block)),
             address: Some(self.contract_address),
-            keys: None, // Don't filter by keys - we'll check in the loop for nested events
+            keys: None, // Don't filter by keys - we'll check in apply_event for nested events
         };
 
-        let chunk_size = 1000;
-        let mut continuation_token = None;
-        let mut swap_events_seen = 0u32;
-        let mut _total_events_seen = 0u32;
-        let mut _is_first_page = true;
-
         loop {
             let events_page = self
-                .provider
-                .get_events(filter.clone(), continuation_token.clone(), chunk_size)
+                .event_pages
+                .next_page(|page_size| self.provider.get_events(filter.clone(), continuation_token.clone(), page_size))
                 .await?;
-            
-            _total_events_seen += events_page.events.len() as u32;
-            
-            for event in events_page.events {
-                // For nested enum events (PrivacyEvent::Deposit), the structure is:
-                // keys[0] = PrivacyEvent enum selector
-                // keys[1] = Deposit variant selector (if nested)
-                // OR keys[0] = Deposit selector (if direct)
-                // Check all keys to find the Deposit variant selector
-                let is_deposit_event = !event.keys.is_empty() && 
-                    event.keys.iter().any(|key| *key == self.deposit_selector);
-                
-                // Check for PoolEvent enum (which contains Swap)
-                // Structure: keys[0] = Event enum, keys[1] = PoolEvent enum, keys[2] = Swap variant
-                let is_pool_event = !event.keys.is_empty() && 
-                    event.keys.iter().any(|key| *key == self.pool_event_selector);
-                
-                // Check for Swap events - can be at keys[1] or keys[2] depending on nesting
-                let is_swap_event = !event.keys.is_empty() && (
-                    event.keys.iter().any(|key| *key == self.swap_selector) ||
-                    (is_pool_event && event.keys.len() >= 2 && event.keys[1] == self.swap_selector) ||
-                    (is_pool_event && event.keys.len() >= 3 && event.keys[2] == self.swap_selector)
-                );
-                
-                // Only log swap events
-                if !is_deposit_event {
-                    if is_swap_event {
-                        swap_events_seen += 1;
-                        println!(
-                            "[Syncer] 🔄 Swap event #{} detected: keys={:?}, data_len={}",
-                            swap_events_seen,
-                            event.keys.iter().map(|k| format!("0x{:x}", k)).collect::<Vec<_>>(),
-                            event.data.len()
-                        );
-                        if event.data.len() >= 6 {
-                            println!(
-                                "  📊 Swap details: sender=0x{:x}, recipient=0x{:x}, amount0={:?}, amount1={:?}",
-                                event.data[0], event.data[1], event.data[2], event.data[3]
-                            );
-                        }
-                    }
-                    continue;
+            self.apply_events(&events_page.events, checkpoint, swap_events_seen)?;
+            // Events come in block order: everything before the last one's block is in
+            let reached = events_page.events.last().and_then(|e| e.block_number).map_or(0, |block| block.saturating_sub(1));
+            self.status.record_progress(reached, events_page.events.len(), self.tree.lock().unwrap().get_leaf_count());
+
+            continuation_token = events_page.continuation_token;
+            match &continuation_token {
+                Some(token) => {
+                    checkpoint.scan_to_block = Some(to_block);
+                    checkpoint.continuation_token = Some(token.clone());
                 }
-                
-                // Skip verbose deposit event logging - only log summary
-
-                // Parse Deposit event data:
-                // For nested events, the data structure is:
-                // data[0] = commitment (felt252)
-                // data[1] = leaf_index (u32)
-                // data[2] = root (felt252)
-                if event.data.len() >= 3 {
-                        let commitment_felt = event.data[0];
-                    let leaf_index_felt = event.data[1];
-
-                    // Convert to BigUint for our Merkle tree
-                    let commitment = BigUint::from_bytes_be(&commitment_felt.to_bytes_be());
-                    let leaf_index: u32 = {
-                        let bytes = leaf_index_felt.to_bytes_be();
-                        let mut arr = [0u8; 4];
-                        let start = bytes.len().saturating_sub(4);
-                        arr.copy_from_slice(&bytes[start..]);
-                        u32::from_be_bytes(arr)
-                    };
-
-                    // Get zero leaf and current count before acquiring mutable lock
-                    let (current_count, zero_leaf) = {
-                        let tree = self.tree.lock().unwrap();
-                        (tree.get_leaf_count(), tree.zeros[0].clone())
-                    };
-
-                    // Insert into our tree
-                    let mut tree = self.tree.lock().unwrap();
+                None => self.complete_scan(checkpoint, to_block).await,
+            }
+            self.save_progress(checkpoint);
 
-                    // Handle gaps: if leaf_index is greater than current count, insert empty leaves
-                    if leaf_index > current_count {
-                        let gaps = leaf_index - current_count;
-                        // Insert empty leaves (zeros) to fill the gap
-                        for i in 0..gaps {
-                            tree.insert_at_index(current_count + i, zero_leaf.clone());
-                        }
-                    } else if leaf_index < current_count {
-                        // Check if this commitment already exists at this index
-                        if let Some(existing_leaf) = tree.nodes.get(&(0, leaf_index)) {
-                            if existing_leaf == &commitment {
-                                // Skip silently - already processed
-                                continue;
-                            }
-                        }
-                    }
+            if continuation_token.is_none() {
+                return Ok(());
+            }
+        }
+    }
 
-                    // Insert the commitment at the correct index
-                    if leaf_index == current_count {
-                        // Normal sequential insert
-                        tree.insert(commitment.clone());
-                    } else {
-                        // Insert at specific index (filling gaps already handled above)
-                        tree.insert_at_index(leaf_index, commitment.clone());
-                    }
-                    // Process silently - no logging
+    /// Scan `last_synced_block + 1..=to_block` in chunks fetched concurrently, applied and
+    /// saved in block order; an error keeps the chunks applied so far
+    async fn scan_chunks(
+        &self,
+        checkpoint: &mut SyncCheckpoint,
+        to_block: u64,
+        swap_events_seen: &mut u32,
+    ) -> Result<(), BlockchainError> {
+        let from_block = checkpoint.last_synced_block + 1;
+        let mut chunks = std::pin::pin!(fetch_chunks(
+            self.provider.as_ref(),
+            self.contract_address,
+            from_block,
+            to_block,
+            self.fetch,
+            &self.event_pages,
+        ));
+
+        while let Some(chunk) = chunks.next().await {
+            let ((_, chunk_end), events) = chunk?;
+            self.apply_events(&events, checkpoint, swap_events_seen)?;
+            self.status.record_progress(chunk_end, events.len(), self.tree.lock().unwrap().get_leaf_count());
+            if chunk_end == to_block {
+                self.complete_scan(checkpoint, to_block).await;
+            } else {
+                checkpoint.last_synced_block = chunk_end;
+            }
+            self.save_progress(checkpoint);
+        }
+        Ok(())
+    }
+
+    /// Every event up to `to_block` is in: move the checkpoint there
+    async fn complete_scan(&self, checkpoint: &mut SyncCheckpoint, to_block: u64) {
+        checkpoint.last_synced_block = to_block;
+        checkpoint.scan_to_block = None;
+        checkpoint.continuation_token = None;
+        self.status.finish_scan(to_block, self.tree.lock().unwrap().get_leaf_count());
+        // Remember where this scan ended to notice if the chain later replaces it
+        match self.provider.block_hash(to_block).await {
+            Ok(hash) => checkpoint.push_mark(BlockMark {
+                number: to_block,
+                hash,
+                leaf_count: self.tree.lock().unwrap().get_leaf_count(),
+                nullifier_count: self.nullifier_tree.lock().unwrap().spent_count() as u32,
+            }),
+            Err(e) => eprintln!("[Syncer] ⚠️  No hash for block {}, reorgs past it go unnoticed: {}", to_block, e),
+        }
+    }
+
+    /// Apply a page of events in order. Runs of deposits extending the tree are inserted with
+    /// one `insert_batch`, recording only the root after the run; anything else goes through
+    /// `apply_event` once the run before it is in.
+    fn apply_events(&self, events: &[EmittedEvent], checkpoint: &mut SyncCheckpoint, swap_events_seen: &mut u32) -> Result<(), BlockchainError> {
+        let mut batch: Vec<(DepositEvent, LeafOrigin)> = Vec::new();
+        for event in events {
+            match self.appending_deposit(event, checkpoint, batch.len() as u32) {
+                Some((deposit, key)) => {
+                    checkpoint.applied_events.remember(event, key);
+                    batch.push((deposit, LeafOrigin { block_number: key.block_number, transaction_hash: key.transaction_hash }));
+                }
+                None => {
+                    self.insert_deposits(&mut batch, checkpoint);
+                    self.apply_event(event, checkpoint, swap_events_seen)?;
                 }
             }
+        }
+        self.insert_deposits(&mut batch, checkpoint);
+        Ok(())
+    }
 
-            continuation_token = events_page.continuation_token;
-            if continuation_token.is_none() {
-                break;
+    /// Deposit in `event` if it is new and lands right after the tree and `pending` batched leaves
+    fn appending_deposit(&self, event: &EmittedEvent, checkpoint: &SyncCheckpoint, pending: u32) -> Option<(DepositEvent, EventKey)> {
+        if !event.keys.iter().any(|key| *key == self.deposit_selector) {
+            return None;
+        }
+        let key = checkpoint.applied_events.key_for(event)?;
+        let deposit = DepositEvent::decode(&event.keys, &event.data).ok()??;
+        (deposit.leaf_index == self.tree.lock().unwrap().get_leaf_count() + pending).then_some((deposit, key))
+    }
+
+    /// Append the batched deposits to the tree in one go
+    fn insert_deposits(&self, batch: &mut Vec<(DepositEvent, LeafOrigin)>, checkpoint: &mut SyncCheckpoint) {
+        let Some(&(last, LeafOrigin { block_number, .. })) = batch.last() else {
+            return;
+        };
+        let leaves: Vec<BigUint> = batch
+            .iter()
+            .map(|(deposit, _)| BigUint::from_bytes_be(&deposit.commitment.to_bytes_be()))
+            .collect();
+        let mut tree = self.tree.lock().unwrap();
+        tree.insert_batch(&leaves).expect("felts are valid leaves");
+        self.record_root(&tree, block_number);
+        drop(tree);
+
+        for (deposit, origin) in batch.drain(..) {
+            self.index_deposit(&deposit, origin);
+        }
+        checkpoint.last_leaf_index = Some(last.leaf_index);
+    }
+
+    /// Record the root `tree` (held locked) reached at `block_number`, and publish it as a version
+    fn record_root(&self, tree: &MerkleTree, block_number: u64) {
+        let root = format!("0x{:x}", tree.get_root());
+        self.feed.publish(SyncNotification::Root { root: root.clone(), leaf_count: tree.get_leaf_count(), block_number });
+        self.roots.record(root, block_number, tree.get_leaf_count());
+        self.versions.publish(tree);
+    }
+
+    /// Index the deposit applied to the tree and notify subscribers
+    fn index_deposit(&self, deposit: &DepositEvent, origin: LeafOrigin) {
+        self.commitments.insert(deposit.commitment, deposit.leaf_index, origin);
+        self.feed.publish(SyncNotification::Deposit {
+            commitment: format!("0x{:x}", deposit.commitment),
+            leaf_index: deposit.leaf_index,
+            block_number: origin.block_number,
+            transaction_hash: format!("0x{:x}", origin.transaction_hash),
+        });
+    }
+
+    /// Insert a Deposit event into the tree and a NullifierSpent one into the nullifier tree
+    /// (other events are only logged). Events already applied are skipped. Deposits must
+    /// extend the tree in leaf index order: a gap or a different commitment at an existing
+    /// index is a LeafOrder error.
+    fn apply_event(&self, event: &EmittedEvent, checkpoint: &mut SyncCheckpoint, swap_events_seen: &mut u32) -> Result<(), BlockchainError> {
+        let Some(key) = checkpoint.applied_events.key_for(event) else {
+            return Ok(());
+        };
+
+        if event.keys.iter().any(|key| *key == self.nullifier_spent_selector) {
+            match event.data.first() {
+                Some(nullifier) if event.data.len() == 1 => {
+                    self.nullifier_tree.lock().unwrap().insert(*nullifier);
+                    self.feed.publish(SyncNotification::NullifierSpent {
+                        nullifier: format!("0x{:x}", nullifier),
+                        block_number: key.block_number,
+                    });
+                }
+                _ => eprintln!("[Syncer] ⚠️  Skipping malformed NullifierSpent event: {} data fields", event.data.len()),
             }
+            checkpoint.applied_events.remember(event, key);
+            return Ok(());
         }
 
-        // Only log if swap events were found
-        if swap_events_seen > 0 {
-            println!("[Syncer] 🔄 Found {} swap event(s)", swap_events_seen);
+        // For nested enum events (PrivacyEvent::Deposit), the structure is:
+        // keys[0] = PrivacyEvent enum selector
+        // keys[1] = Deposit variant selector (if nested)
+        // OR keys[0] = Deposit selector (if direct)
+        // Check all keys to find the Deposit variant selector
+        let is_deposit_event = !event.keys.is_empty() && 
+            event.keys.iter().any(|key| *key == self.deposit_selector);
+        
+        // Check for PoolEvent enum (which contains Swap)
+        // Structure: keys[0] = Event enum, keys[1] = PoolEvent enum, keys[2] = Swap variant
+        let is_pool_event = !event.keys.is_empty() && 
+            event.keys.iter().any(|key| *key == self.pool_event_selector);
+        
+        // Check for Swap events - can be at keys[1] or keys[2] depending on nesting
+        let is_swap_event = !event.keys.is_empty() && (
+            event.keys.iter().any(|key| *key == self.swap_selector) ||
+            (is_pool_event && event.keys.len() >= 2 && event.keys[1] == self.swap_selector) ||
+            (is_pool_event && event.keys.len() >= 3 && event.keys[2] == self.swap_selector)
+        );
+        
+        // Only log swap events
+        if !is_deposit_event {
+            if is_swap_event {
+                *swap_events_seen += 1;
+                println!(
+                    "[Syncer] 🔄 Swap event #{} detected: keys={:?}, data_len={}",
+                    swap_events_seen,
+                    event.keys.iter().map(|k| format!("0x{:x}", k)).collect::<Vec<_>>(),
+                    event.data.len()
+                );
+                if event.data.len() >= 6 {
+                    println!(
+                        "  📊 Swap details: sender={}, recipient={}, amount0={:?}, amount1={:?}",
+                        self.redaction.felt(Sensitive::Recipient, &event.data[0]),
+                        self.redaction.felt(Sensitive::Recipient, &event.data[1]),
+                        event.data[2], event.data[3]
+                    );
+                }
+            }
+            checkpoint.applied_events.remember(event, key);
+            return Ok(());
+        }
+        
+        // Parse Deposit event data: [commitment, leaf_index, root]
+        let deposit = match DepositEvent::decode(&event.keys, &event.data) {
+            Ok(Some(deposit)) => deposit,
+            Ok(None) => {
+                checkpoint.applied_events.remember(event, key);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("[Syncer] ⚠️  Skipping malformed Deposit event: {}", e);
+                checkpoint.applied_events.remember(event, key);
+                return Ok(());
+            }
+        };
+
+        let commitment = BigUint::from_bytes_be(&deposit.commitment.to_bytes_be());
+        let leaf_index = deposit.leaf_index;
+        let mut tree = self.tree.lock().unwrap();
+        let leaf_count = tree.get_leaf_count();
+
+        if leaf_index > leaf_count {
+            eprintln!(
+                "[Syncer] ❌ Deposit {} arrived with {} leaves in the tree - deposits are missing, backfill the blocks before block {}",
+                leaf_index,
+                leaf_count,
+                key.block_number
+            );
+            return Err(BlockchainError::LeafOrder(format!(
+                "deposit {} in block {} with {} leaves in the tree: leaves {}..{} are missing",
+                leaf_index, key.block_number, leaf_count, leaf_count, leaf_index
+            )));
         }
+        if leaf_index < leaf_count {
+            // The leaf may have gone in (or been a placeholder) when the checkpoint was saved
+            self.leaves_rewritten.store(true, Ordering::SeqCst);
+            // A zero leaf is a placeholder (e.g. a gap filled by /deposit/index), anything else
+            // must be this very deposit
+            match tree.nodes.get(&(0, leaf_index)).filter(|leaf| **leaf != tree.zeros[0]) {
+                Some(existing) if *existing == commitment => {}
+                Some(_) => {
+                    eprintln!("[Syncer] ❌ Leaf {} already holds another commitment", leaf_index);
+                    return Err(BlockchainError::LeafOrder(format!(
+                        "deposit {} in block {} conflicts with the leaf already in the tree",
+                        leaf_index, key.block_number
+                    )));
+                }
+                None => {
+                    tree.insert_at_index(leaf_index, commitment).expect("felts are valid leaves");
+                    // Versions with the placeholder leaf no longer match the tree
+                    self.versions.truncate_to(leaf_index);
+                    self.record_root(&tree, key.block_number);
+                }
+            }
+        } else {
+            tree.insert(commitment).expect("felts are valid leaves");
+            self.record_root(&tree, key.block_number);
+        }
+        drop(tree);
+
+        self.index_deposit(&deposit, LeafOrigin { block_number: key.block_number, transaction_hash: key.transaction_hash });
+        checkpoint.last_leaf_index = Some(leaf_index);
+        checkpoint.applied_events.remember(event, key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+    use crate::mock_provider::{deposit_emitted_event, MockProvider};
+    use crate::page_size::PageSizeBounds;
+
+    #[tokio::test]
+    async fn test_sync_events_from_mock_provider() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let events = (0..3u32)
+            .map(|i| deposit_emitted_event(zylith, FieldElement::from(1000u64 + i as u64), i, FieldElement::from(2000u64 + i as u64), 10))
+            .collect();
+        let provider = MockProvider::new().with_block_number(20).with_events(events);
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree.clone());
+
+        assert_eq!(syncer.sync_events(&mut SyncCheckpoint::default()).await.unwrap(), 20);
+        let leaves: Vec<BigUint> = (1000u64..1003).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+    }
+
+    #[tokio::test]
+    async fn test_parallel_chunks_apply_in_block_order() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let events = (0..6u32)
+            .map(|i| deposit_emitted_event(zylith, FieldElement::from(1000u64 + i as u64), i, FieldElement::from(2000u64 + i as u64), 3 * i as u64 + 1))
+            .collect();
+        let provider = MockProvider::new().with_block_number(20).with_events(events).with_from_block_filter();
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree.clone())
+            .with_fetch_config(FetchConfig { chunk_blocks: 4, parallelism: 3, page_size: PageSizeBounds::fixed(1) });
+        let mut checkpoint = SyncCheckpoint::default();
+
+        assert_eq!(syncer.sync_events(&mut checkpoint).await.unwrap(), 20);
+        let leaves: Vec<BigUint> = (1000u64..1006).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+        assert_eq!(checkpoint.last_leaf_index, Some(5));
+        assert_eq!(checkpoint.recent_blocks.last().unwrap().number, 20);
+
+        let status = syncer.tracker().status();
+        assert!(status.synced);
+        assert_eq!((status.current_block, status.target_block, status.leaves_indexed), (20, 20, 6));
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_saved_checkpoint() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let events = (0..3u32)
+            .map(|i| deposit_emitted_event(zylith, FieldElement::from(1000u64 + i as u64), i, FieldElement::from(2000u64 + i as u64), 10))
+            .collect();
+        let provider = MockProvider::new().with_block_number(20).with_events(events);
+        let dir = std::env::temp_dir().join(format!("asp_syncer_checkpoint_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = CheckpointStore::new(dir.join("state.json"));
+
+        // A previous run stopped after the page holding the first two deposits
+        let mut saved = SyncCheckpoint {
+            last_synced_block: 5,
+            last_leaf_index: Some(1),
+            scan_to_block: Some(20),
+            continuation_token: Some("2".to_string()),
+            leaves: Vec::new(),
+            leaf_origins: Vec::new(),
+            recent_blocks: Vec::new(),
+            applied_events: Default::default(),
+            nullifiers: Vec::new(),
+        };
+        saved.record_tree(&MerkleTree::from_leaves(TREE_DEPTH, &[BigUint::from(1000u64), BigUint::from(1001u64)]));
+        let tree = Arc::new(Mutex::new(saved.restore_tree(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree.clone()).with_checkpoint_store(store.clone());
+
+        assert_eq!(syncer.sync_events(&mut saved).await.unwrap(), 20);
+        let leaves: Vec<BigUint> = (1000u64..1003).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+
+        let checkpoint = store.load();
+        assert_eq!(checkpoint.last_synced_block, 20);
+        assert_eq!(checkpoint.last_leaf_index, Some(2));
+        assert!(!checkpoint.scan_in_progress());
+        assert_eq!(checkpoint.leaves.len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bootstraps_from_verified_snapshot_then_syncs_on() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let deposit = |commitment: u64, leaf_index: u32, block: u64| {
+            deposit_emitted_event(zylith, FieldElement::from(commitment), leaf_index, FieldElement::ONE, block)
+        };
+        let snapshot_tree = MerkleTree::from_leaves(TREE_DEPTH, &[BigUint::from(1000u64), BigUint::from(1001u64)]);
+        let snapshot_root = FieldElement::from_byte_slice_be(&snapshot_tree.get_root().to_bytes_be()).unwrap();
+        // Only the deposit after the snapshot block is still to be scanned
+        let provider = MockProvider::new()
+            .with_block_number(20)
+            .with_call_result(zylith, "get_merkle_root", vec![snapshot_root])
+            .with_events(vec![deposit(1000, 0, 3), deposit(1001, 1, 4), deposit(1002, 2, 15)])
+            .with_from_block_filter();
+        let dir = std::env::temp_dir().join(format!("asp_syncer_snapshot_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let snapshot = crate::merkle::TreeSnapshot {
+            depth: TREE_DEPTH,
+            leaves: vec![FieldElement::from(1000u64), FieldElement::from(1001u64)],
+            root: format!("0x{:x}", snapshot_root),
+            synced_block: 10,
+        };
+        std::fs::write(dir.join("snapshot.json"), serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let store = CheckpointStore::new(dir.join("state.json"));
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree.clone())
+            .with_checkpoint_store(store.clone())
+            .with_snapshot_source(dir.join("snapshot.json").to_str().unwrap());
+
+        let mut checkpoint = syncer.bootstrap_from_snapshot().await.unwrap();
+        assert_eq!(store.load().last_synced_block, 10);
+        assert_eq!(tree.lock().unwrap().get_root(), snapshot_tree.get_root());
+        assert_eq!(syncer.commitments.get(&FieldElement::from(1001u64)), Some(1));
+
+        assert_eq!(syncer.sync_events(&mut checkpoint).await.unwrap(), 20);
+        let leaves: Vec<BigUint> = (1000u64..1003).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+
+        // A snapshot the chain disagrees with is not used
+        std::fs::write(
+            dir.join("snapshot.json"),
+            serde_json::to_string(&crate::merkle::TreeSnapshot { synced_block: 12, ..snapshot.clone() }).unwrap(),
+        )
+        .unwrap();
+        let provider = MockProvider::new().with_block_number(20).with_call_result(zylith, "get_merkle_root", vec![FieldElement::from(7u64)]);
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH))))
+            .with_snapshot_source(dir.join("snapshot.json").to_str().unwrap());
+        assert!(syncer.bootstrap_from_snapshot().await.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_consistency_report_finds_missing_deposit() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let leaves: Vec<BigUint> = (1000u64..1003).map(BigUint::from).collect();
+        let root_of = |count: usize| FieldElement::from_byte_slice_be(&MerkleTree::from_leaves(TREE_DEPTH, &leaves[..count]).get_root().to_bytes_be()).unwrap();
+        let events = (0..3u32)
+            .map(|i| deposit_emitted_event(zylith, FieldElement::from(1000u64 + i as u64), i, root_of(i as usize + 1), 10 * (i as u64 + 1)))
+            .collect();
+        let provider = MockProvider::new()
+            .with_block_number(60)
+            .with_events(events)
+            .with_call_result(zylith, "get_merkle_root", vec![root_of(3)]);
+        let dir = std::env::temp_dir().join(format!("asp_syncer_consistency_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = CheckpointStore::new(dir.join("state.json"));
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree).with_checkpoint_store(store.clone());
+
+        // The checkpoint claims block 50 but the deposit of block 30 never made it in
+        let mut checkpoint = SyncCheckpoint::at_block(50);
+        checkpoint.record_tree(&MerkleTree::from_leaves(TREE_DEPTH, &leaves[..2]));
+        store.save(&checkpoint).unwrap();
+        let report = syncer.verify_consistency().await.unwrap();
+        assert_eq!(report.on_chain_leaf_count, Some(3));
+        assert!(report.divergences.contains(&Divergence::LeafCountMismatch { local: 2, on_chain: 3 }));
+        assert!(report.divergences.contains(&Divergence::MissingEvents { leaf_indices: vec![2] }));
+
+        checkpoint.record_tree(&MerkleTree::from_leaves(TREE_DEPTH, &leaves));
+        store.save(&checkpoint).unwrap();
+        assert!(syncer.verify_consistency().await.unwrap().is_consistent());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reorg_rolls_back_to_common_ancestor() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let deposit = |commitment: u64, index: u32, block: u64| {
+            deposit_emitted_event(zylith, FieldElement::from(commitment), index, FieldElement::from(2000u64 + index as u64), block)
+        };
+        let provider = Arc::new(MockProvider::new().with_block_number(12).with_events(vec![deposit(1000, 0, 10), deposit(1001, 1, 10)]));
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(provider.clone(), zylith, tree.clone());
+        let mut checkpoint = SyncCheckpoint::default();
+
+        syncer.sync_events(&mut checkpoint).await.unwrap();
+        provider.push_events(vec![deposit(1002, 2, 15)]);
+        provider.set_block_number(16);
+        syncer.sync_events(&mut checkpoint).await.unwrap();
+        assert_eq!(tree.lock().unwrap().get_leaf_count(), 3);
+
+        // Block 15 is replaced by a block holding a different third deposit
+        provider.set_block_hash(16, FieldElement::from(0xdeadu64));
+        provider.truncate_events_after(14);
+        provider.push_events(vec![deposit(2222, 2, 15)]);
+        provider.set_block_number(18);
+
+        assert!(syncer.handle_reorg(&mut checkpoint).await.unwrap());
+        assert_eq!(checkpoint.last_synced_block, 12);
+        assert_eq!(tree.lock().unwrap().get_leaf_count(), 2);
+
+        assert_eq!(syncer.sync_events(&mut checkpoint).await.unwrap(), 18);
+        let leaves: Vec<BigUint> = [1000u64, 1001, 2222].into_iter().map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+        assert!(!syncer.handle_reorg(&mut checkpoint).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_new_head_wakes_the_syncer() {
+        let (heads, receiver) = broadcast::channel(4);
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(MockProvider::new()), FieldElement::ONE, tree).with_new_heads(receiver);
+
+        heads.send(21).unwrap();
+        tokio::time::timeout(Duration::from_millis(500), syncer.wait_for_next_block())
+            .await
+            .expect("a new head should end the wait before the poll interval");
+    }
+
+    #[tokio::test]
+    async fn test_backfill_repairs_missed_deposits() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let deposit = |commitment: u64, index: u32, block: u64| {
+            deposit_emitted_event(zylith, FieldElement::from(commitment), index, FieldElement::from(2000u64 + index as u64), block)
+        };
+        let provider = MockProvider::new()
+            .with_block_number(40)
+            .with_events((0..4u32).map(|i| deposit(1000 + i as u64, i, 10 * i as u64 + 5)).collect())
+            .with_from_block_filter();
+        // Leaf 1 was missed (zero-filled gap) and leaf 3 never synced
+        let leaves: Vec<BigUint> = [1000u64, 0, 1002].into_iter().map(BigUint::from).collect();
+        let tree = Arc::new(Mutex::new(MerkleTree::from_leaves(TREE_DEPTH, &leaves)));
+        let syncer = Syncer::with_provider(Arc::new(provider), zylith, tree.clone());
+
+        let report = syncer.backfill(10, 40).await.unwrap();
+        assert_eq!(report, BackfillReport { deposits: 3, inserted: 2, already_present: 1, replaced: 0 });
+        let expected: Vec<BigUint> = (1000u64..1004).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &expected).get_root());
+
+        // Running it again changes nothing
+        let report = syncer.backfill(0, 40).await.unwrap();
+        assert_eq!(report.already_present, 4);
+        assert!(matches!(syncer.backfill(41, 40).await, Err(BlockchainError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_events_skipped_and_gaps_rejected() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let deposit = |commitment: u64, index: u32, block: u64| {
+            deposit_emitted_event(zylith, FieldElement::from(commitment), index, FieldElement::from(2000u64 + index as u64), block)
+        };
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(Arc::new(MockProvider::new()), zylith, tree.clone());
+        let mut checkpoint = SyncCheckpoint::default();
+        let mut swaps = 0;
+
+        syncer.apply_event(&deposit(1000, 0, 5), &mut checkpoint, &mut swaps).unwrap();
+        syncer.apply_event(&deposit(1000, 0, 5), &mut checkpoint, &mut swaps).unwrap();
+        syncer.apply_event(&deposit(1001, 1, 6), &mut checkpoint, &mut swaps).unwrap();
+        assert_eq!(tree.lock().unwrap().get_leaf_count(), 2);
+
+        // Leaf 2 never arrived
+        let err = syncer.apply_event(&deposit(1003, 3, 7), &mut checkpoint, &mut swaps).unwrap_err();
+        assert!(matches!(err, BlockchainError::LeafOrder(_)));
+        assert_eq!(tree.lock().unwrap().get_leaf_count(), 2);
+        // A different commitment for an existing leaf
+        let err = syncer.apply_event(&deposit(9999, 1, 7), &mut checkpoint, &mut swaps).unwrap_err();
+        assert!(matches!(err, BlockchainError::LeafOrder(_)));
+        // The rejected event isn't remembered: once the gap is filled it goes in
+        syncer.apply_event(&deposit(1002, 2, 7), &mut checkpoint, &mut swaps).unwrap();
+        syncer.apply_event(&deposit(1003, 3, 7), &mut checkpoint, &mut swaps).unwrap();
+        assert_eq!(checkpoint.last_leaf_index, Some(3));
+    }
+
+    /// Hands out the receivers queued by the test, one per subscription
+    struct QueuedSource(Mutex<Vec<mpsc::UnboundedReceiver<EmittedEvent>>>);
+
+    #[async_trait]
+    impl EventSource for QueuedSource {
+        async fn subscribe_events(&self, _address: FieldElement) -> Result<mpsc::UnboundedReceiver<EmittedEvent>, BlockchainError> {
+            self.0.lock().unwrap().pop().ok_or_else(|| BlockchainError::Transport("no subscription".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follows_subscribed_events_and_falls_back_on_disconnect() {
+        let zylith = FieldElement::from(0x2a1u64);
+        let deposit = |commitment: u64, index: u32, block: u64| {
+            deposit_emitted_event(zylith, FieldElement::from(commitment), index, FieldElement::from(2000u64 + index as u64), block)
+        };
+        let provider = Arc::new(MockProvider::new().with_block_number(10).with_events(vec![deposit(1000, 0, 8)]));
+        let (push, events) = mpsc::unbounded_channel();
+        let tree = Arc::new(Mutex::new(MerkleTree::new(TREE_DEPTH)));
+        let syncer = Syncer::with_provider(provider.clone(), zylith, tree.clone())
+            .with_event_source(Arc::new(QueuedSource(Mutex::new(vec![events]))));
+        let mut checkpoint = SyncCheckpoint::default();
+
+        // Subscribing, then catching up with the blocks before the subscription, is polling
+        assert!(!syncer.follow_events(&mut checkpoint).await);
+        assert!(!syncer.follow_events(&mut checkpoint).await);
+        syncer.sync_events(&mut checkpoint).await.unwrap();
+
+        // Then events arrive without a single get_events; a replayed one is skipped
+        push.send(deposit(1000, 0, 8)).unwrap();
+        push.send(deposit(1001, 1, 11)).unwrap();
+        push.send(deposit(1002, 2, 12)).unwrap();
+        assert!(syncer.follow_events(&mut checkpoint).await);
+        assert_eq!(checkpoint.last_synced_block, 11);
+        let leaves: Vec<BigUint> = [1000u64, 1001, 1002].into_iter().map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
+
+        // Quiet chain: the blocks up to the tip are scanned, and the checkpoint and the reported
+        // progress follow it
+        provider.set_block_number(30);
+        assert!(syncer.follow_quiet_chain(&mut checkpoint).await);
+        assert_eq!(checkpoint.last_synced_block, 30);
+        assert_eq!(syncer.tracker().status().current_block, 30);
+        assert_eq!(checkpoint.recent_blocks.last().map(|mark| (mark.number, mark.leaf_count)), Some((30, 3)));
+        // A deposit of the tip block pushed after the quiet tick still goes in, and the tip's
+        // mark, taken without it, goes
+        push.send(deposit(1003, 3, 30)).unwrap();
+        assert!(syncer.follow_events(&mut checkpoint).await);
+        assert_eq!(checkpoint.last_synced_block, 29);
+        assert!(checkpoint.recent_blocks.iter().all(|mark| mark.number < 30));
+        // Rescanning that block, or a push of what a quiet tick scanned, applies nothing twice
+        provider.push_events(vec![deposit(1003, 3, 30), deposit(1004, 4, 35)]);
+        provider.set_block_number(35);
+        assert!(syncer.follow_quiet_chain(&mut checkpoint).await);
+        push.send(deposit(1004, 4, 35)).unwrap();
+        assert!(syncer.follow_events(&mut checkpoint).await);
+        assert_eq!(checkpoint.last_synced_block, 35);
+        let leaves: Vec<BigUint> = (1000u64..1005).map(BigUint::from).collect();
+        assert_eq!(tree.lock().unwrap().get_root(), MerkleTree::from_leaves(TREE_DEPTH, &leaves).get_root());
 
-        Ok(latest_block)
+        // Disconnected: back to polling
+        drop(push);
+        assert!(!syncer.follow_events(&mut checkpoint).await);
+        assert!(syncer.subscription.lock().await.is_none());
     }
 }
@@ -0,0 +1,57 @@
+// Per-operation RPC timeouts
+// Event scans legitimately take minutes while a nullifier check should fail fast,
+// so each kind of request gets its own budget, falling back to a global default.
+
+use crate::error::BlockchainError;
+use std::future::Future;
+use std::time::Duration;
+
+/// Global default applied to operations without an override
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutProfile {
+    /// Fallback for every operation below that has no override
+    pub default: Duration,
+    /// View function calls (roots, nullifiers, balances, receipts)
+    pub read_call: Option<Duration>,
+    /// Raw storage reads (pool state)
+    pub storage_read: Option<Duration>,
+    /// One page of an event scan
+    pub event_page: Option<Duration>,
+}
+
+impl Default for TimeoutProfile {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_CALL_TIMEOUT,
+            read_call: None,
+            storage_read: Some(Duration::from_secs(5)),
+            event_page: None,
+        }
+    }
+}
+
+impl TimeoutProfile {
+    pub fn read_call(&self) -> Duration {
+        self.read_call.unwrap_or(self.default)
+    }
+
+    pub fn storage_read(&self) -> Duration {
+        self.storage_read.unwrap_or(self.default)
+    }
+
+    pub fn event_page(&self) -> Duration {
+        self.event_page.unwrap_or(self.default)
+    }
+}
+
+/// Run an RPC future with a deadline, mapping expiry to BlockchainError::Timeout
+pub async fn with_timeout<T, F>(duration: Duration, operation: &str, fut: F) -> Result<T, BlockchainError>
+where
+    F: Future<Output = Result<T, BlockchainError>>,
+{
+    tokio::time::timeout(duration, fut)
+        .await
+        .map_err(|_| BlockchainError::Timeout(format!("{} after {:?}", operation, duration)))?
+}
@@ -0,0 +1,135 @@
+// On-disk deposit tree nodes
+// Rebuilding the tree from the checkpoint leaves rehashes every node, which takes a while
+// once the tree holds hundreds of thousands of leaves. The node file keeps every hashed node
+// next to the checkpoint: after each saved page the syncer appends the nodes that changed,
+// and a restart reads them back without hashing. Records are fixed size (level, index,
+// value) and the last record of a node wins; the file is compacted when it grows stale.
+
+use crate::checkpoint::{write_durably, CheckpointStore};
+use crate::hasher::Hasher;
+use crate::merkle::MerkleTree;
+use num_bigint::BigUint;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Level (1 byte), index (4 bytes BE), value (32 bytes BE)
+const RECORD_LEN: usize = 37;
+
+/// Node file of a deposit tree
+#[derive(Debug, Clone)]
+pub struct TreeStore {
+    path: PathBuf,
+}
+
+impl TreeStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Node file kept next to the checkpoint file of `store`
+    pub fn for_checkpoint(store: &CheckpointStore) -> Self {
+        Self::new(store.path().with_extension("nodes"))
+    }
+
+    /// Tree saved in the node file, if there is one and it was hashed with `hasher`
+    pub fn load(&self, depth: usize, hasher: Arc<dyn Hasher>) -> io::Result<Option<MerkleTree>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // A torn last record (crash mid-append) is dropped
+        let records = bytes.chunks_exact(RECORD_LEN);
+        let mut nodes = HashMap::with_capacity(records.len());
+        for record in records.clone() {
+            let level = record[0] as usize;
+            if level > depth {
+                return Ok(None);
+            }
+            let index = u32::from_be_bytes([record[1], record[2], record[3], record[4]]);
+            nodes.insert((level, index), BigUint::from_bytes_be(&record[5..]));
+        }
+        let stale = records.len() > 2 * nodes.len() || bytes.len() % RECORD_LEN != 0;
+
+        let tree = MerkleTree::from_nodes(depth, hasher, nodes);
+        if !tree.root_matches_children() {
+            return Ok(None);
+        }
+        if stale {
+            self.rewrite(&tree)?;
+        }
+        Ok(Some(tree))
+    }
+
+    /// Append the nodes `tree` changed since the last call (tracking must be on)
+    pub fn append(&self, tree: &mut MerkleTree) -> io::Result<()> {
+        let mut seen = HashSet::new();
+        let mut buf = Vec::new();
+        for key in tree.take_changed().into_iter().rev() {
+            if seen.insert(key) {
+                push_record(&mut buf, key, &tree.nodes[&key]);
+            }
+        }
+        if buf.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&buf)
+    }
+
+    /// Replace the node file with every node of `tree`, atomically
+    pub fn rewrite(&self, tree: &MerkleTree) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(tree.nodes.len() * RECORD_LEN);
+        for (key, value) in &tree.nodes {
+            push_record(&mut buf, *key, value);
+        }
+        write_durably(&self.path, &self.path.with_extension("nodes.tmp"), &buf)
+    }
+}
+
+fn push_record(buf: &mut Vec<u8>, (level, index): (usize, u32), value: &BigUint) {
+    buf.push(level as u8);
+    buf.extend_from_slice(&index.to_be_bytes());
+    let bytes = value.to_bytes_be();
+    buf.extend(std::iter::repeat(0u8).take(32 - bytes.len()));
+    buf.extend_from_slice(&bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{TreeHash, TREE_DEPTH};
+
+    #[test]
+    fn test_nodes_survive_a_restart_without_rehashing() {
+        let dir = std::env::temp_dir().join(format!("asp_tree_store_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = TreeStore::for_checkpoint(&CheckpointStore::new(dir.join("state.json")));
+        let hasher = TreeHash::default().hasher();
+        assert!(store.load(TREE_DEPTH, hasher.clone()).unwrap().is_none());
+
+        let mut tree = MerkleTree::from_leaves(TREE_DEPTH, &[BigUint::from(1u8), BigUint::from(2u8)]);
+        store.rewrite(&tree).unwrap();
+        tree.track_changes();
+        for leaf in 3u8..=5 {
+            tree.append(BigUint::from(leaf)).unwrap();
+            store.append(&mut tree).unwrap();
+        }
+
+        let loaded = store.load(TREE_DEPTH, hasher).unwrap().expect("node file was written");
+        assert_eq!(loaded.get_root(), tree.get_root());
+        assert_eq!(loaded.get_leaf_count(), 5);
+        assert_eq!(loaded.get_proof(4).unwrap().path, tree.get_proof(4).unwrap().path);
+
+        // Nodes hashed with another function are not taken
+        assert!(store.load(TREE_DEPTH, TreeHash::Pedersen.hasher()).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
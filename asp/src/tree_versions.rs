@@ -0,0 +1,234 @@
+// Versioned deposit tree reads
+// Proof requests arrive while the syncer appends leaves under the tree lock. Every root the
+// syncer produces is also published here as a version that readers prove against without that
+// lock. Appends never change a node whose subtree is already full, so full nodes are shared by
+// all versions; a version only keeps its root and the partly filled node of each level. A
+// proof for a recorded root is the path as it was at that root, whatever was appended since.
+
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::root_history::DEFAULT_ROOT_HISTORY_CAPACITY;
+use num_bigint::BigUint;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use utoipa::ToSchema;
+
+/// Tree as of one root
+#[derive(Debug, Clone)]
+struct TreeVersion {
+    root: BigUint,
+    leaf_count: u32,
+    /// partial[level] = node `leaf_count >> level` of the level when its subtree is partly
+    /// filled (None otherwise, or when the tree didn't hold it)
+    partial: Vec<Option<BigUint>>,
+}
+
+/// Leaves appended between two recorded roots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TreeDiff {
+    pub from_root: String,
+    pub to_root: String,
+    /// Leaves at `from_root`: the index of the first appended leaf
+    pub from_leaf_count: u32,
+    pub to_leaf_count: u32,
+    /// Commitments appended, in leaf order from `from_leaf_count` (or the page's first leaf)
+    pub commitments: Vec<String>,
+    /// Leaf index to pass as `after` for the rest of the diff; None on its last page
+    pub next_after: Option<u32>,
+}
+
+struct VersionState {
+    depth: usize,
+    /// full[level][index] = node whose subtree is full; None where the tree didn't hold it
+    /// (tree resumed from a frontier)
+    full: Vec<Vec<Option<BigUint>>>,
+    versions: VecDeque<TreeVersion>,
+    capacity: usize,
+}
+
+impl VersionState {
+    fn clear(&mut self, depth: usize) {
+        self.depth = depth;
+        self.full = vec![Vec::new(); depth];
+        self.versions.clear();
+    }
+}
+
+/// Shared tree versions; clones see the same versions
+#[derive(Clone)]
+pub struct TreeVersions {
+    state: Arc<RwLock<VersionState>>,
+}
+
+impl Default for TreeVersions {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROOT_HISTORY_CAPACITY)
+    }
+}
+
+impl TreeVersions {
+    /// Keep the last `capacity` versions, like the root history
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(VersionState {
+                depth: 0,
+                full: Vec::new(),
+                versions: VecDeque::new(),
+                capacity: capacity.max(1),
+            })),
+        }
+    }
+
+    /// Publish the current state of `tree` (held locked by the caller) as a version
+    pub fn publish(&self, tree: &MerkleTree) {
+        let leaf_count = tree.get_leaf_count();
+        let root = tree.get_root();
+        let mut state = self.state.write().unwrap();
+        if state.depth != tree.depth {
+            state.clear(tree.depth);
+        }
+        if state.versions.back().is_some_and(|last| last.root == root && last.leaf_count == leaf_count) {
+            return;
+        }
+
+        let mut partial = Vec::with_capacity(tree.depth);
+        for level in 0..tree.depth {
+            let nodes = &mut state.full[level];
+            while nodes.len() < (leaf_count as u64 >> level) as usize {
+                nodes.push(tree.nodes.get(&(level, nodes.len() as u32)).cloned());
+            }
+            let partly_filled = leaf_count as u64 % (1u64 << level) != 0;
+            partial.push(partly_filled.then(|| tree.nodes.get(&(level, leaf_count >> level)).cloned()).flatten());
+        }
+        if state.versions.len() == state.capacity {
+            state.versions.pop_front();
+        }
+        state.versions.push_back(TreeVersion { root, leaf_count, partial });
+    }
+
+    /// Forget versions with more than `leaf_count` leaves, and nodes covering later leaves
+    /// (after a reorg, or before a leaf below the count is filled in)
+    pub fn truncate_to(&self, leaf_count: u32) {
+        let mut state = self.state.write().unwrap();
+        while state.versions.back().is_some_and(|last| last.leaf_count > leaf_count) {
+            state.versions.pop_back();
+        }
+        for (level, nodes) in state.full.iter_mut().enumerate() {
+            nodes.truncate((leaf_count as u64 >> level) as usize);
+        }
+    }
+
+    /// Path of leaf `index` in the tree as it was at `root`; None if `root` isn't a kept
+    /// version or the leaf wasn't in it
+    pub fn proof_at(&self, root: &BigUint, index: u32) -> Option<MerkleProof> {
+        let state = self.state.read().unwrap();
+        let version = state.versions.iter().rev().find(|version| version.root == *root)?;
+        if index >= version.leaf_count {
+            return None;
+        }
+        let leaf = state.full[0].get(index as usize)?.clone()?;
+
+        let count = version.leaf_count as u64;
+        let mut path = Vec::with_capacity(state.depth);
+        let mut path_indices = Vec::with_capacity(state.depth);
+        for level in 0..state.depth {
+            let node = index as u64 >> level;
+            let sibling = node ^ 1;
+            let value = if (sibling + 1) << level <= count {
+                state.full[level].get(sibling as usize)?.clone()?
+            } else if sibling << level >= count {
+                // Empty subtree: 0, like the contract
+                BigUint::from(0u8)
+            } else {
+                version.partial[level].clone()?
+            };
+            path.push(format!("0x{:x}", value));
+            path_indices.push((node & 1) as u32);
+        }
+
+        Some(MerkleProof {
+            leaf: format!("0x{:x}", leaf),
+            leaf_index: index,
+            path,
+            path_indices,
+            root: format!("0x{:x}", version.root),
+            signature: None,
+        })
+    }
+
+    /// Leaves appended to go from `from` to `to`, e.g. for a wallet updating a cached path, at
+    /// most `limit` of them after leaf `after`; None unless both are kept versions and `from`
+    /// is not after `to`
+    pub fn diff(&self, from: &BigUint, to: &BigUint, after: Option<u32>, limit: usize) -> Option<TreeDiff> {
+        let state = self.state.read().unwrap();
+        let count_of = |root: &BigUint| state.versions.iter().rev().find(|version| version.root == *root).map(|version| version.leaf_count);
+        let (from_count, to_count) = (count_of(from)?, count_of(to)?);
+        if from_count > to_count {
+            return None;
+        }
+        let start = after.map_or(from_count, |after| after.saturating_add(1).max(from_count)).min(to_count);
+        let end = to_count.min(start.saturating_add(limit.min(u32::MAX as usize) as u32));
+        let commitments = state.full[0]
+            .get(start as usize..end as usize)?
+            .iter()
+            .map(|leaf| leaf.as_ref().map(|leaf| format!("0x{:x}", leaf)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(TreeDiff {
+            from_root: format!("0x{:x}", from),
+            to_root: format!("0x{:x}", to),
+            from_leaf_count: from_count,
+            to_leaf_count: to_count,
+            commitments,
+            next_after: (end < to_count).then(|| end - 1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proofs_at_old_roots_survive_appends() {
+        let depth = 5;
+        let leaves: Vec<BigUint> = (1000u64..1011).map(BigUint::from).collect();
+        let versions = TreeVersions::default();
+        let mut tree = MerkleTree::new(depth);
+        let mut roots = Vec::new();
+        for leaf in &leaves {
+            tree.append(leaf.clone()).unwrap();
+            versions.publish(&tree);
+            roots.push(tree.get_root());
+        }
+
+        // Every root still proves exactly what a tree of that many leaves proves
+        for (count, root) in roots.iter().enumerate().map(|(i, root)| (i + 1, root)) {
+            let expected = MerkleTree::from_leaves(depth, &leaves[..count]);
+            for index in 0..count as u32 {
+                let proof = versions.proof_at(root, index).unwrap();
+                let want = expected.get_proof(index).unwrap();
+                assert_eq!((proof.path, proof.root), (want.path, want.root));
+            }
+            assert!(versions.proof_at(root, count as u32).is_none());
+        }
+
+        let diff = versions.diff(&roots[2], &roots[5], None, usize::MAX).unwrap();
+        assert_eq!((diff.from_leaf_count, diff.to_leaf_count), (3, 6));
+        assert_eq!(diff.commitments, ["0x3eb", "0x3ec", "0x3ed"]);
+        assert_eq!(diff.next_after, None);
+        assert!(versions.diff(&roots[5], &roots[2], None, usize::MAX).is_none());
+        // In pages of two
+        let first = versions.diff(&roots[2], &roots[5], None, 2).unwrap();
+        assert_eq!((first.commitments.len(), first.next_after), (2, Some(4)));
+        let rest = versions.diff(&roots[2], &roots[5], first.next_after, 2).unwrap();
+        assert_eq!((rest.commitments, rest.next_after), (vec!["0x3ed".to_string()], None));
+
+        // Rolled back to 6 leaves, then a different leaf 6
+        versions.truncate_to(6);
+        assert!(versions.proof_at(&roots[7], 0).is_none());
+        let mut forked = MerkleTree::from_leaves(depth, &leaves[..6]);
+        forked.append(BigUint::from(7u8)).unwrap();
+        versions.publish(&forked);
+        assert_eq!(versions.proof_at(&forked.get_root(), 3).unwrap().path, forked.get_proof(3).unwrap().path);
+    }
+}
@@ -0,0 +1,187 @@
+// Local proof verification
+// Groth16 proofs are checked against the circuit's snarkjs verification key
+// (circuits/build/vkeys/<circuit>_vk.json, the one its Garaga verifier was generated from)
+// before they leave the ASP, so a bad witness or a zkey that doesn't match the deployed
+// verifier is caught here instead of by a verify_groth16_proof_bn254 call that reverts and
+// still costs the sender gas. /api/proof/verify runs the same check for relayers and wallets.
+
+use crate::prover::Groth16Proof;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use num_bigint::BigUint;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A circuit's verification key, prepared for pairing checks
+pub struct CircuitVerifier {
+    public_inputs: usize,
+    prepared: PreparedVerifyingKey<Bn254>,
+}
+
+impl CircuitVerifier {
+    /// Key from a snarkjs verification_key.json file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let json = serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        Self::from_snarkjs(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Key from a snarkjs verification_key.json
+    pub fn from_snarkjs(json: &serde_json::Value) -> Result<Self, String> {
+        if json["protocol"] != "groth16" || json["curve"] != "bn128" {
+            return Err("not a Groth16 BN254 verification key".to_string());
+        }
+        let ic = json["IC"].as_array().ok_or("missing IC")?;
+        let vk = VerifyingKey::<Bn254> {
+            alpha_g1: g1(&json["vk_alpha_1"])?,
+            beta_g2: g2(&json["vk_beta_2"])?,
+            gamma_g2: g2(&json["vk_gamma_2"])?,
+            delta_g2: g2(&json["vk_delta_2"])?,
+            gamma_abc_g1: ic.iter().map(g1).collect::<Result<_, _>>()?,
+        };
+        if vk.gamma_abc_g1.is_empty() {
+            return Err("empty IC".to_string());
+        }
+        Ok(Self { public_inputs: vk.gamma_abc_g1.len() - 1, prepared: prepare_verifying_key(&vk) })
+    }
+
+    /// Ok if `proof` verifies for its public inputs, the reason otherwise
+    pub fn verify(&self, proof: &Groth16Proof) -> Result<(), String> {
+        if proof.public_inputs.len() != self.public_inputs {
+            return Err(format!("The key takes {} public inputs, the proof has {}", self.public_inputs, proof.public_inputs.len()));
+        }
+        let inputs = proof
+            .public_inputs
+            .iter()
+            .map(|input| field::<Fr>(input).ok_or_else(|| format!("Public input {} is not in the scalar field", input)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ark_proof = Proof::<Bn254> {
+            a: g1_point(&proof.a[0], &proof.a[1])?,
+            b: g2_point(&proof.b)?,
+            c: g1_point(&proof.c[0], &proof.c[1])?,
+        };
+        match Groth16::<Bn254>::verify_proof(&self.prepared, &ark_proof, &inputs) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("The proof doesn't verify against the circuit's verification key".to_string()),
+            Err(e) => Err(format!("Verification failed: {}", e)),
+        }
+    }
+}
+
+/// Check a proof of `circuit` against its verification key at `vkey`
+pub fn verify_circuit_proof(vkey: &Path, circuit: &str, proof: &Groth16Proof) -> Result<(), String> {
+    CircuitVerifier::load(vkey)?.verify(proof).map_err(|e| format!("Invalid {} proof: {}", circuit, e))
+}
+
+/// `value` as a field element, None if it isn't canonical
+fn field<F: PrimeField>(value: &BigUint) -> Option<F> {
+    (*value < BigUint::from(F::MODULUS)).then(|| F::from(value.clone()))
+}
+
+fn coordinate(value: &BigUint) -> Result<Fq, String> {
+    field::<Fq>(value).ok_or_else(|| format!("{} is not in the base field", value))
+}
+
+fn g1_point(x: &BigUint, y: &BigUint) -> Result<G1Affine, String> {
+    let point = G1Affine::new_unchecked(coordinate(x)?, coordinate(y)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("G1 point not on the curve".to_string());
+    }
+    Ok(point)
+}
+
+/// G2 point from [[x.c0, x.c1], [y.c0, y.c1]]
+fn g2_point(b: &[[BigUint; 2]; 2]) -> Result<G2Affine, String> {
+    let x = Fq2::new(coordinate(&b[0][0])?, coordinate(&b[0][1])?);
+    let y = Fq2::new(coordinate(&b[1][0])?, coordinate(&b[1][1])?);
+    let point = G2Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("G2 point not on the curve or outside the subgroup".to_string());
+    }
+    Ok(point)
+}
+
+fn decimal(value: &serde_json::Value) -> Result<BigUint, String> {
+    let value = value.as_str().ok_or_else(|| format!("{} is not a decimal string", value))?;
+    BigUint::from_str(value).map_err(|e| format!("Invalid coordinate {}: {}", value, e))
+}
+
+/// snarkjs G1 point: [x, y, "1"]
+fn g1(json: &serde_json::Value) -> Result<G1Affine, String> {
+    g1_point(&decimal(&json[0])?, &decimal(&json[1])?)
+}
+
+/// snarkjs G2 point: [[x.c0, x.c1], [y.c0, y.c1], ["1", "0"]]
+fn g2(json: &serde_json::Value) -> Result<G2Affine, String> {
+    g2_point(&[[decimal(&json[0][0])?, decimal(&json[0][1])?], [decimal(&json[1][0])?, decimal(&json[1][1])?]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{BigInteger, One};
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+    /// x * x = y, y public
+    struct Square(Fr);
+
+    impl ConstraintSynthesizer<Fr> for Square {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let y = cs.new_input_variable(|| Ok(self.0 * self.0))?;
+            let x = cs.new_witness_variable(|| Ok(self.0))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+        }
+    }
+
+    fn to_biguint<F: PrimeField>(value: F) -> BigUint {
+        BigUint::from_bytes_be(&value.into_bigint().to_bytes_be())
+    }
+
+    fn g1_json(point: G1Affine) -> serde_json::Value {
+        serde_json::json!([to_biguint(point.x).to_string(), to_biguint(point.y).to_string(), "1"])
+    }
+
+    fn g2_json(point: G2Affine) -> serde_json::Value {
+        let coordinates = |value: Fq2| [to_biguint(value.c0).to_string(), to_biguint(value.c1).to_string()];
+        serde_json::json!([coordinates(point.x), coordinates(point.y), ["1", "0"]])
+    }
+
+    #[test]
+    fn test_verifies_against_a_snarkjs_key() {
+        let mut rng = rand::thread_rng();
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(Square(Fr::one()), &mut rng).unwrap();
+        let vk = pk.vk.clone();
+        let json = serde_json::json!({
+            "protocol": "groth16",
+            "curve": "bn128",
+            "nPublic": 1,
+            "vk_alpha_1": g1_json(vk.alpha_g1),
+            "vk_beta_2": g2_json(vk.beta_g2),
+            "vk_gamma_2": g2_json(vk.gamma_g2),
+            "vk_delta_2": g2_json(vk.delta_g2),
+            "IC": vk.gamma_abc_g1.iter().map(|point| g1_json(*point)).collect::<Vec<_>>(),
+        });
+        let verifier = CircuitVerifier::from_snarkjs(&json).unwrap();
+
+        let x = Fr::from(3u8);
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(Square(x), &pk, &mut rng).unwrap();
+        let proof = Groth16Proof {
+            a: [to_biguint(proof.a.x), to_biguint(proof.a.y)],
+            b: [[to_biguint(proof.b.x.c0), to_biguint(proof.b.x.c1)], [to_biguint(proof.b.y.c0), to_biguint(proof.b.y.c1)]],
+            c: [to_biguint(proof.c.x), to_biguint(proof.c.y)],
+            public_inputs: vec![to_biguint(x * x)],
+        };
+        assert_eq!(verifier.verify(&proof), Ok(()));
+
+        // Another public input, a swapped G2 coordinate or a point off the curve are rejected
+        let wrong_input = Groth16Proof { public_inputs: vec![BigUint::from(10u8)], ..proof.clone() };
+        assert!(verifier.verify(&wrong_input).unwrap_err().contains("doesn't verify"));
+        let mut swapped = proof.clone();
+        swapped.b[0].swap(0, 1);
+        assert!(verifier.verify(&swapped).is_err());
+        let off_curve = Groth16Proof { a: [BigUint::from(1u8), BigUint::from(1u8)], ..proof.clone() };
+        assert!(verifier.verify(&off_curve).unwrap_err().contains("not on the curve"));
+    }
+}
@@ -0,0 +1,136 @@
+// Verification key registry
+// build/vk_registry.json records, for each circuit, every version of its verification key: the
+// SHA-256 of the <c>_vk.json it was exported to and the class hash of the Garaga verifier
+// generated from it. At startup the local VKs are looked up there and the verifier each one
+// needs is compared with the class Zylith actually calls, so proofs from a zkey the deployed
+// verifiers don't accept stop the server instead of reverting on-chain one by one.
+
+use crate::artifacts::{sha256_file, ArtifactManager};
+use crate::blockchain::BlockchainClient;
+use crate::provider::StarknetProvider;
+use serde::Deserialize;
+use starknet::core::types::FieldElement;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Circuits with a verifier on the Zylith contract
+pub const VERIFIED_CIRCUITS: [&str; 4] = ["membership", "swap", "withdraw", "lp"];
+
+/// One version of a circuit's verification key and the verifier generated from it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VkRelease {
+    pub version: u32,
+    pub vk_sha256: String,
+    #[serde(deserialize_with = "felt_from_hex")]
+    pub verifier_class_hash: FieldElement,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct VkRegistry {
+    /// Circuit -> its releases, oldest first
+    circuits: HashMap<String, Vec<VkRelease>>,
+}
+
+impl VkRegistry {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let registry: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for (circuit, releases) in &registry.circuits {
+            if let Some(release) = releases.iter().find(|release| release.vk_sha256.len() != 64 || !release.vk_sha256.chars().all(|c| c.is_ascii_hexdigit())) {
+                return Err(format!("{} v{}: {} is not a SHA-256", circuit, release.version, release.vk_sha256));
+            }
+        }
+        Ok(registry)
+    }
+
+    /// VK_REGISTRY, <CIRCUITS_DIR>/build/vk_registry.json by default; None when there is none
+    pub fn from_env(circuits_dir: &Path) -> Result<Option<Self>, String> {
+        let path = std::env::var("VK_REGISTRY").map(PathBuf::from).unwrap_or_else(|_| circuits_dir.join("build").join("vk_registry.json"));
+        match std::fs::read_to_string(&path) {
+            Ok(json) => Self::parse(&json).map(Some).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// The release of `circuit` whose VK hashes to `vk_sha256`
+    pub fn release(&self, circuit: &str, vk_sha256: &str) -> Option<&VkRelease> {
+        self.circuits.get(circuit)?.iter().find(|release| release.vk_sha256.eq_ignore_ascii_case(vk_sha256))
+    }
+}
+
+/// Check every local VK is a registered release and Zylith calls the verifier generated from
+/// it; returns the version in use per circuit, or every mismatch found
+pub async fn check_deployed_verifiers<P: StarknetProvider>(
+    registry: &VkRegistry,
+    artifacts: &ArtifactManager,
+    blockchain: &BlockchainClient<P>,
+) -> Result<Vec<(&'static str, u32)>, String> {
+    let mut versions = Vec::new();
+    let mut mismatches = Vec::new();
+    for circuit in VERIFIED_CIRCUITS {
+        let vkey = artifacts.verification_key(circuit).await?;
+        let digest = sha256_file(&vkey).map_err(|e| format!("Failed to hash {}: {}", vkey.display(), e))?;
+        let Some(release) = registry.release(circuit, &digest) else {
+            mismatches.push(format!("{} (sha256 {}) is not a registered {} VK", vkey.display(), digest, circuit));
+            continue;
+        };
+        let (verifier, class_hash) = blockchain.verifier_class_hash(circuit).await.map_err(|e| format!("Failed to read the {} verifier: {}", circuit, e))?;
+        if class_hash != release.verifier_class_hash {
+            mismatches.push(format!(
+                "{} verifier 0x{:x} has class 0x{:x}, but {} VK v{} needs 0x{:x}",
+                circuit, verifier, class_hash, circuit, release.version, release.verifier_class_hash
+            ));
+            continue;
+        }
+        versions.push((circuit, release.version));
+    }
+    match mismatches.is_empty() {
+        true => Ok(versions),
+        false => Err(mismatches.join("\n")),
+    }
+}
+
+fn felt_from_hex<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<FieldElement, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    FieldElement::from_hex_be(&hex).map_err(|e| serde::de::Error::custom(format!("invalid class hash {}: {}", hex, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_provider::MockProvider;
+    use crate::storage::storage_address_of;
+
+    #[tokio::test]
+    async fn test_local_vks_must_match_the_deployed_verifiers() {
+        let dir = std::env::temp_dir().join(format!("zylith_vk_registry_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("build").join("vkeys")).unwrap();
+        let mut registry = serde_json::Map::new();
+        let zylith = FieldElement::from(0x2a1u64);
+        let mut provider = MockProvider::new();
+        for (i, circuit) in VERIFIED_CIRCUITS.iter().enumerate() {
+            let vkey = dir.join("build").join("vkeys").join(format!("{}_vk.json", circuit));
+            std::fs::write(&vkey, format!("{{\"circuit\": \"{}\"}}", circuit)).unwrap();
+            let release = serde_json::json!({"version": 2, "vk_sha256": sha256_file(&vkey).unwrap(), "verifier_class_hash": format!("0x{:x}", 100 + i)});
+            registry.insert(circuit.to_string(), serde_json::json!([{"version": 1, "vk_sha256": "00".repeat(32), "verifier_class_hash": "0x1"}, release]));
+            let verifier = FieldElement::from(10 + i as u64);
+            provider = provider
+                .with_storage(zylith, storage_address_of(&format!("{}_verifier", circuit), &[]), verifier)
+                .with_contract(verifier, FieldElement::from(100 + i as u64));
+        }
+        let registry = VkRegistry::parse(&serde_json::Value::Object(registry).to_string()).unwrap();
+        let artifacts = ArtifactManager::new(dir.clone(), None, None);
+        let blockchain = BlockchainClient::with_provider(provider, zylith);
+        let versions = check_deployed_verifiers(&registry, &artifacts, &blockchain).await.unwrap();
+        assert_eq!(versions, VERIFIED_CIRCUITS.iter().map(|circuit| (*circuit, 2)).collect::<Vec<_>>());
+
+        // A regenerated swap VK matches no release
+        std::fs::write(dir.join("build").join("vkeys").join("swap_vk.json"), "{}").unwrap();
+        let err = check_deployed_verifiers(&registry, &artifacts, &blockchain).await.unwrap_err();
+        assert!(err.contains("is not a registered swap VK"));
+        assert_eq!(err.lines().count(), 1);
+        assert!(VkRegistry::parse(r#"{"swap": [{"version": 1, "vk_sha256": "abc", "verifier_class_hash": "0x1"}]}"#).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+// Sync watchdog
+// A panicking sync task or a provider call that never returns would leave the ASP serving
+// stale roots without a sign. The watchdog runs the sync task, checks the heartbeat it beats
+// on the SyncTracker, and restarts it with exponential backoff when it dies or stops beating.
+// Every transition is logged and shows up as `health` in the sync status.
+
+use crate::sync_status::{SyncHealth, SyncTracker};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WatchdogConfig {
+    /// Without a heartbeat for this long, the task is considered hung
+    pub stall_after: Duration,
+    /// How often the heartbeat is checked
+    pub check_interval: Duration,
+    /// Delay before the first restart, doubled for every consecutive one
+    pub restart_delay: Duration,
+    /// Upper bound of the restart delay
+    pub max_restart_delay: Duration,
+    /// A task that ran this long before failing starts the backoff over
+    pub reset_after: Duration,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            stall_after: Duration::from_secs(300),
+            check_interval: Duration::from_secs(5),
+            restart_delay: Duration::from_secs(1),
+            max_restart_delay: Duration::from_secs(60),
+            reset_after: Duration::from_secs(600),
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Defaults, with the stall threshold from SYNC_STALL_SECS
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            stall_after: std::env::var("SYNC_STALL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map_or(defaults.stall_after, Duration::from_secs),
+            ..defaults
+        }
+    }
+
+    /// Delay before restart number `restart` (1-based) in a row
+    pub fn backoff(&self, restart: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restart.saturating_sub(1));
+        self.restart_delay.saturating_mul(factor).min(self.max_restart_delay)
+    }
+}
+
+pub struct Watchdog {
+    config: WatchdogConfig,
+    tracker: SyncTracker,
+}
+
+impl Watchdog {
+    /// Watch the heartbeat the sync task beats on `tracker`
+    pub fn new(config: WatchdogConfig, tracker: SyncTracker) -> Self {
+        Self { config, tracker }
+    }
+
+    /// Run the task `start` creates, starting a new one whenever it crashes or stalls; never returns
+    pub async fn supervise<F, Fut>(&self, mut start: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut consecutive = 0u32;
+        loop {
+            let started = Instant::now();
+            let mut task = tokio::spawn(start());
+            let failure = loop {
+                sleep(self.config.check_interval).await;
+                if self.tracker.take_restart_request() {
+                    println!("[Watchdog] 🔄 Restart requested, stopping the sync task");
+                    task.abort();
+                    // Stopped before the next one touches the tree
+                    let _ = (&mut task).await;
+                    break None;
+                }
+                if task.is_finished() {
+                    match (&mut task).await {
+                        Err(e) if e.is_panic() => eprintln!("[Watchdog] 💥 Sync task panicked"),
+                        _ => eprintln!("[Watchdog] 💥 Sync task exited"),
+                    }
+                    break Some(SyncHealth::Crashed);
+                }
+                // The new task gets a full stall_after to beat for the first time
+                let last_beat = self.tracker.last_heartbeat().map_or(started, |beat| beat.max(started));
+                if last_beat.elapsed() > self.config.stall_after {
+                    eprintln!("[Watchdog] 🧊 No sync heartbeat for {:?}, stopping the task", last_beat.elapsed());
+                    task.abort();
+                    break Some(SyncHealth::Stalled);
+                }
+                if last_beat > started {
+                    self.transition(SyncHealth::Running);
+                }
+            };
+            let Some(failure) = failure else {
+                // Not a failure: started again right away, outside the backoff
+                self.transition(SyncHealth::Restarting);
+                continue;
+            };
+            self.transition(failure);
+
+            consecutive = if started.elapsed() >= self.config.reset_after { 1 } else { consecutive + 1 };
+            let delay = self.config.backoff(consecutive);
+            self.transition(SyncHealth::Restarting);
+            println!("[Watchdog] 🔁 Restarting the sync task in {:?} (attempt {})", delay, consecutive);
+            sleep(delay).await;
+            self.tracker.record_restart();
+        }
+    }
+
+    fn transition(&self, health: SyncHealth) {
+        let previous = self.tracker.set_health(health);
+        if previous != health {
+            println!("[Watchdog] Sync health: {:?} -> {:?}", previous, health);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    fn fast_config() -> WatchdogConfig {
+        WatchdogConfig {
+            stall_after: Duration::from_millis(100),
+            check_interval: Duration::from_millis(10),
+            restart_delay: Duration::from_millis(10),
+            max_restart_delay: Duration::from_millis(40),
+            reset_after: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = fast_config();
+        assert_eq!(config.backoff(1), Duration::from_millis(10));
+        assert_eq!(config.backoff(2), Duration::from_millis(20));
+        assert_eq!(config.backoff(5), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_stalled_and_crashed_tasks() {
+        let tracker = SyncTracker::new();
+        let starts = Arc::new(AtomicU32::new(0));
+        let watchdog = Watchdog::new(fast_config(), tracker.clone());
+        let counter = starts.clone();
+        let task_tracker = tracker.clone();
+        let supervisor = tokio::spawn(async move {
+            watchdog
+                .supervise(move || {
+                    let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                    let tracker = task_tracker.clone();
+                    async move {
+                        match attempt {
+                            // Beats once, then hangs
+                            0 => {
+                                tracker.heartbeat();
+                                std::future::pending::<()>().await
+                            }
+                            1 => panic!("sync task failure"),
+                            // Healthy from then on
+                            _ => loop {
+                                tracker.heartbeat();
+                                sleep(Duration::from_millis(10)).await;
+                            },
+                        }
+                    }
+                })
+                .await
+        });
+
+        sleep(Duration::from_millis(600)).await;
+        let status = tracker.status();
+        supervisor.abort();
+
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.health, SyncHealth::Running);
+    }
+
+    #[tokio::test]
+    async fn test_requested_restart_skips_the_backoff() {
+        let tracker = SyncTracker::new();
+        let starts = Arc::new(AtomicU32::new(0));
+        let watchdog = Watchdog::new(WatchdogConfig { restart_delay: Duration::from_secs(60), ..fast_config() }, tracker.clone());
+        let counter = starts.clone();
+        let task_tracker = tracker.clone();
+        let supervisor = tokio::spawn(async move {
+            watchdog
+                .supervise(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let tracker = task_tracker.clone();
+                    async move {
+                        loop {
+                            tracker.heartbeat();
+                            sleep(Duration::from_millis(10)).await;
+                        }
+                    }
+                })
+                .await
+        });
+
+        sleep(Duration::from_millis(50)).await;
+        tracker.request_restart();
+        sleep(Duration::from_millis(100)).await;
+        let status = tracker.status();
+        supervisor.abort();
+
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+        // Not counted as a failure restart
+        assert_eq!(status.restarts, 0);
+        assert_eq!(status.health, SyncHealth::Running);
+    }
+}
@@ -0,0 +1,182 @@
+// Membership witness
+// Inputs of circuits/membership.circom built in Rust from a note and the ASP's Merkle path,
+// along with the signals the circuit derives from them (the commitment opening and the node
+// at every level, hashed with BN254 Poseidon and masked to 250 bits like the circuit does), so
+// a wrong note or a stale path fails here instead of in the witness calculator. `input_json`
+// is the input file snarkjs and rapidsnark take, with every signal as a decimal string.
+
+use crate::merkle::MerkleProof;
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use num_bigint::BigUint;
+use num_traits::Num;
+
+/// Levels of the membership circuit (Membership(25))
+pub const MEMBERSHIP_DEPTH: usize = 25;
+
+/// What a deposit commits to: Mask(Poseidon(Poseidon(secret, nullifier), amount))
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteOpening {
+    pub secret: BigUint,
+    pub nullifier: BigUint,
+    pub amount: u128,
+}
+
+impl NoteOpening {
+    /// Opening from hex secret and nullifier (0x optional), as /api/deposit/prepare returns them
+    pub fn from_hex(secret: &str, nullifier: &str, amount: u128) -> Result<Self, String> {
+        let parse = |name: &str, value: &str| {
+            BigUint::from_str_radix(value.trim().trim_start_matches("0x"), 16).map_err(|e| format!("Invalid {}: {}", name, e))
+        };
+        Ok(Self { secret: parse("secret", secret)?, nullifier: parse("nullifier", nullifier)?, amount })
+    }
+
+    /// Poseidon(secret, nullifier), unmasked as in the circuit
+    pub fn inner_hash(&self) -> BigUint {
+        poseidon(&self.secret, &self.nullifier)
+    }
+
+    pub fn commitment(&self) -> BigUint {
+        mask250(poseidon(&self.inner_hash(), &BigUint::from(self.amount)))
+    }
+}
+
+/// Every input of the membership circuit and the intermediate signals they lead to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipWitness {
+    // Public inputs
+    pub root: BigUint,
+    pub commitment: BigUint,
+    // Private inputs
+    pub secret: BigUint,
+    pub nullifier: BigUint,
+    pub amount: BigUint,
+    pub path_elements: Vec<BigUint>,
+    pub path_indices: Vec<u8>,
+    /// poseidon1.out
+    pub inner_hash: BigUint,
+    /// Node above the leaf at every level (hashers[i].hash), the last one being the root
+    pub level_hashes: Vec<BigUint>,
+}
+
+/// Witness proving `opening` is the leaf `proof` leads from; fails where the circuit would
+pub fn membership_witness(opening: &NoteOpening, proof: &MerkleProof) -> Result<MembershipWitness, String> {
+    if proof.path.len() != MEMBERSHIP_DEPTH || proof.path_indices.len() != MEMBERSHIP_DEPTH {
+        return Err(format!("The membership circuit takes a path of {} levels, got {}", MEMBERSHIP_DEPTH, proof.path.len()));
+    }
+    let hex = |name: &str, value: &str| {
+        BigUint::from_str_radix(value.trim_start_matches("0x"), 16).map_err(|e| format!("Invalid {} {}: {}", name, value, e))
+    };
+    let commitment = opening.commitment();
+    if commitment != hex("leaf", &proof.leaf)? {
+        return Err(format!("The note opens to 0x{:x}, not to the leaf 0x{}", commitment, proof.leaf.trim_start_matches("0x")));
+    }
+    let root = hex("root", &proof.root)?;
+
+    let mut path_elements = Vec::with_capacity(MEMBERSHIP_DEPTH);
+    let mut path_indices = Vec::with_capacity(MEMBERSHIP_DEPTH);
+    let mut level_hashes = Vec::with_capacity(MEMBERSHIP_DEPTH);
+    let mut current = commitment.clone();
+    for (sibling, index) in proof.path.iter().zip(&proof.path_indices) {
+        let sibling = hex("path element", sibling)?;
+        // DualMux: 0 keeps the node on the left, 1 swaps it to the right
+        current = match index {
+            0 => mask250(poseidon(&current, &sibling)),
+            1 => mask250(poseidon(&sibling, &current)),
+            _ => return Err(format!("Path index {} is not 0 or 1", index)),
+        };
+        path_elements.push(sibling);
+        path_indices.push(*index as u8);
+        level_hashes.push(current.clone());
+    }
+    if current != root {
+        return Err(format!("The path leads to 0x{:x}, not to the root {}", current, proof.root));
+    }
+
+    Ok(MembershipWitness {
+        root,
+        commitment,
+        secret: reduce(&opening.secret),
+        nullifier: reduce(&opening.nullifier),
+        amount: BigUint::from(opening.amount),
+        path_elements,
+        path_indices,
+        inner_hash: opening.inner_hash(),
+        level_hashes,
+    })
+}
+
+impl MembershipWitness {
+    /// Public signals in the circuit's order: root, commitment
+    pub fn public_inputs(&self) -> [BigUint; 2] {
+        [self.root.clone(), self.commitment.clone()]
+    }
+
+    /// Input file of the witness calculator
+    pub fn input_json(&self) -> serde_json::Value {
+        let decimal = |values: &[BigUint]| values.iter().map(|value| value.to_str_radix(10)).collect::<Vec<_>>();
+        serde_json::json!({
+            "root": self.root.to_str_radix(10),
+            "commitment": self.commitment.to_str_radix(10),
+            "secret": self.secret.to_str_radix(10),
+            "nullifier": self.nullifier.to_str_radix(10),
+            "amount": self.amount.to_str_radix(10),
+            "pathElements": decimal(&self.path_elements),
+            "pathIndices": self.path_indices.iter().map(|index| index.to_string()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Circom's Poseidon(2) over BN254
+pub fn poseidon(a: &BigUint, b: &BigUint) -> BigUint {
+    let mut hasher = Poseidon::<Fr>::new_circom(2).expect("Poseidon(2) parameters");
+    let hash = hasher.hash(&[to_fr(a), to_fr(b)]).expect("two inputs");
+    BigUint::from_bytes_be(&hash.into_bigint().to_bytes_be())
+}
+
+fn to_fr(value: &BigUint) -> Fr {
+    Fr::from_le_bytes_mod_order(&value.to_bytes_le())
+}
+
+/// `value` as the circuit sees it: reduced modulo the BN254 scalar field
+pub fn reduce(value: &BigUint) -> BigUint {
+    BigUint::from_bytes_be(&to_fr(value).into_bigint().to_bytes_be())
+}
+
+/// Mask250: the low 250 bits
+pub fn mask250(value: BigUint) -> BigUint {
+    value & ((BigUint::from(1u8) << 250u32) - 1u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    #[test]
+    fn test_witness_from_asp_path() {
+        let opening = NoteOpening::from_hex("0x1234567890abcdef", "0xfedcba0987654321", 1_000_000).unwrap();
+        let expected = crate::commitment::generate_commitment("0x1234567890abcdef", "0xfedcba0987654321", 1_000_000).unwrap();
+        assert_eq!(format!("0x{:x}", opening.commitment()), expected);
+
+        let mut tree = MerkleTree::new(MEMBERSHIP_DEPTH);
+        tree.insert(BigUint::from(7u8)).unwrap();
+        tree.insert(opening.commitment()).unwrap();
+        let proof = tree.get_proof(1).unwrap();
+        let witness = membership_witness(&opening, &proof).unwrap();
+        assert_eq!(witness.level_hashes.last(), Some(&tree.get_root()));
+        assert_eq!(witness.path_indices[0], 1);
+        assert_eq!(witness.public_inputs()[1], opening.commitment());
+        let input = witness.input_json();
+        assert_eq!(input["pathElements"].as_array().unwrap().len(), MEMBERSHIP_DEPTH);
+        assert_eq!(input["amount"], "1000000");
+
+        // Another note, or a path to another leaf, is caught before proving
+        let other = NoteOpening { amount: 1, ..opening.clone() };
+        assert!(membership_witness(&other, &proof).unwrap_err().contains("opens to"));
+        let mut stale = proof.clone();
+        stale.root = "0x1".to_string();
+        assert!(membership_witness(&opening, &stale).unwrap_err().contains("leads to"));
+    }
+}
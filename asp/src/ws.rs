@@ -0,0 +1,306 @@
+// JSON-RPC over WebSocket
+// WsTransport plugs into starknet-rs' JsonRpcClient like HttpTransport does, so a
+// `JsonRpcClient<WsTransport>` is a full StarknetProvider. On top of that it can subscribe
+// to new block headers (`starknet_subscribeNewHeads`), letting the syncer react to blocks
+// as they are produced instead of polling, and to the events of a contract
+// (`starknet_subscribeEvents`). The socket is reopened on the next request after it drops and
+// the new heads subscription renewed with it; event subscriptions end with the socket, their
+// receivers see the disconnect and subscribe again.
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use starknet::core::types::{EmittedEvent, FieldElement};
+use starknet::providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse, JsonRpcTransport};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::{self, Message};
+use url::Url;
+
+use crate::error::BlockchainError;
+use crate::syncer::EventSource;
+
+/// New heads kept for a syncer that is busy when they arrive
+const NEW_HEADS_BUFFER: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WsTransportError {
+    #[error("WebSocket error: {0}")]
+    Socket(#[from] tungstenite::Error),
+    #[error("WebSocket connection closed")]
+    Closed,
+    #[error("Invalid JSON-RPC message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("JSON-RPC error: {0}")]
+    Rpc(String),
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+type EventSubscribers = Arc<Mutex<Vec<mpsc::UnboundedSender<EmittedEvent>>>>;
+
+/// An open socket: requests are queued to the task owning it
+#[derive(Clone)]
+struct Connection {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+    /// Receivers of `starknet_subscriptionEvents` on this socket
+    event_subscribers: EventSubscribers,
+}
+
+struct WsInner {
+    url: Url,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+    next_id: AtomicU64,
+    new_heads: broadcast::Sender<u64>,
+    subscribed: AtomicBool,
+}
+
+/// JSON-RPC transport over a WebSocket; clones share the same socket
+#[derive(Clone)]
+pub struct WsTransport {
+    inner: Arc<WsInner>,
+}
+
+impl WsTransport {
+    /// The socket is opened on first use
+    pub fn new(url: &str) -> Result<Self, WsTransportError> {
+        let url = Url::parse(url).map_err(|e| WsTransportError::Rpc(format!("Invalid WebSocket URL '{}': {}", url, e)))?;
+        let (new_heads, _) = broadcast::channel(NEW_HEADS_BUFFER);
+        Ok(Self {
+            inner: Arc::new(WsInner {
+                url,
+                connection: tokio::sync::Mutex::new(None),
+                next_id: AtomicU64::new(1),
+                new_heads,
+                subscribed: AtomicBool::new(false),
+            }),
+        })
+    }
+
+    /// Subscribe to new block headers; the receiver yields block numbers
+    /// Fails if the node doesn't support subscriptions (JSON-RPC spec before 0.8)
+    pub async fn subscribe_new_heads(&self) -> Result<broadcast::Receiver<u64>, WsTransportError> {
+        let receiver = self.inner.new_heads.subscribe();
+        let response = self.request(Value::from("starknet_subscribeNewHeads"), Value::Object(Default::default())).await?;
+        if let Some(error) = response.get("error") {
+            return Err(WsTransportError::Rpc(error.to_string()));
+        }
+        self.inner.subscribed.store(true, Ordering::SeqCst);
+        Ok(receiver)
+    }
+
+    /// Subscribe to the events emitted by `address` in new blocks
+    /// The receiver ends when the socket closes: events may have been missed from then on.
+    pub async fn subscribe_events(&self, address: FieldElement) -> Result<mpsc::UnboundedReceiver<EmittedEvent>, WsTransportError> {
+        let connection = self.connection().await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        // Registered first, so that no event sent right after the response is lost
+        connection.event_subscribers.lock().unwrap().push(sender);
+        let params = serde_json::json!({ "from_address": format!("0x{:x}", address) });
+        let response = self.request(Value::from("starknet_subscribeEvents"), params).await?;
+        if let Some(error) = response.get("error") {
+            return Err(WsTransportError::Rpc(error.to_string()));
+        }
+        Ok(receiver)
+    }
+
+    /// Send one request and wait for the response with the same id
+    async fn request(&self, method: Value, params: Value) -> Result<Value, WsTransportError> {
+        let connection = self.connection().await?;
+        let id = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+        let (respond, response) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(id, respond);
+
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if connection.outgoing.send(Message::Text(request.to_string())).is_err() {
+            connection.pending.lock().unwrap().remove(&id);
+            return Err(WsTransportError::Closed);
+        }
+        // The socket task drops every pending sender when the connection ends
+        response.await.map_err(|_| WsTransportError::Closed)
+    }
+
+    /// The open connection, (re)connecting if there is none
+    async fn connection(&self) -> Result<Connection, WsTransportError> {
+        let mut connection = self.inner.connection.lock().await;
+        if let Some(open) = connection.as_ref().filter(|c| !c.outgoing.is_closed()) {
+            return Ok(open.clone());
+        }
+
+        let (socket, _) = connect_async(self.inner.url.as_str()).await?;
+        let (outgoing, queue) = mpsc::unbounded_channel();
+        let open = Connection {
+            outgoing,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            event_subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+        tokio::spawn(run_socket(socket, queue, open.clone(), self.inner.new_heads.clone()));
+
+        if self.inner.subscribed.load(Ordering::SeqCst) {
+            // Subscriptions die with the socket; the response carries an id nobody waits for
+            let id = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+            let renew = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": "starknet_subscribeNewHeads", "params": {} });
+            let _ = open.outgoing.send(Message::Text(renew.to_string()));
+            eprintln!("[ASP] ⚠️  WebSocket {} reconnected, new block subscription renewed", self.inner.url);
+        }
+
+        *connection = Some(open.clone());
+        Ok(open)
+    }
+}
+
+/// Own the socket: write queued requests, route responses and notifications
+async fn run_socket<S>(socket: S, mut queue: mpsc::UnboundedReceiver<Message>, connection: Connection, new_heads: broadcast::Sender<u64>)
+where
+    S: futures_util::Stream<Item = Result<Message, tungstenite::Error>> + futures_util::Sink<Message> + Unpin,
+{
+    let (mut sink, mut source) = socket.split();
+    loop {
+        tokio::select! {
+            outgoing = queue.recv() => match outgoing {
+                Some(message) => {
+                    if sink.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            incoming = source.next() => match incoming {
+                Some(Ok(Message::Text(text))) => dispatch(&text, &connection, &new_heads),
+                Some(Ok(Message::Ping(payload))) => {
+                    let _ = sink.send(Message::Pong(payload)).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    // Wake every waiting request with Closed, and end the event subscriptions
+    connection.pending.lock().unwrap().clear();
+    connection.event_subscribers.lock().unwrap().clear();
+}
+
+/// Route one incoming message: a response to its request, a notification to the subscribers
+fn dispatch(text: &str, connection: &Connection, new_heads: &broadcast::Sender<u64>) {
+    let Ok(message) = serde_json::from_str::<Value>(text) else {
+        eprintln!("[ASP] ⚠️  Ignoring malformed WebSocket message");
+        return;
+    };
+
+    if let Some(id) = message.get("id").and_then(Value::as_u64) {
+        if let Some(respond) = connection.pending.lock().unwrap().remove(&id) {
+            let _ = respond.send(message);
+        }
+        return;
+    }
+
+    match message.get("method").and_then(Value::as_str) {
+        Some("starknet_subscriptionNewHeads") => {
+            if let Some(block_number) = message
+                .pointer("/params/result/block_number")
+                .and_then(Value::as_u64)
+            {
+                // No receiver only means nobody is listening right now
+                let _ = new_heads.send(block_number);
+            }
+        }
+        Some("starknet_subscriptionEvents") => {
+            let event = message
+                .pointer("/params/result")
+                .cloned()
+                .map(serde_json::from_value::<EmittedEvent>);
+            match event {
+                Some(Ok(event)) => connection
+                    .event_subscribers
+                    .lock()
+                    .unwrap()
+                    .retain(|subscriber| subscriber.send(event.clone()).is_ok()),
+                _ => eprintln!("[ASP] ⚠️  Ignoring malformed event notification"),
+            }
+        }
+        _ => {}
+    }
+}
+
+#[async_trait]
+impl JsonRpcTransport for WsTransport {
+    type Error = WsTransportError;
+
+    async fn send_request<P, R>(&self, method: JsonRpcMethod, params: P) -> Result<JsonRpcResponse<R>, Self::Error>
+    where
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let response = self.request(serde_json::to_value(method)?, serde_json::to_value(params)?).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+}
+
+#[async_trait]
+impl EventSource for WsTransport {
+    async fn subscribe_events(&self, address: FieldElement) -> Result<mpsc::UnboundedReceiver<EmittedEvent>, BlockchainError> {
+        WsTransport::subscribe_events(self, address)
+            .await
+            .map_err(|e| BlockchainError::Transport(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        Connection {
+            outgoing: mpsc::unbounded_channel().0,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            event_subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_routes_responses_and_new_heads() {
+        let connection = connection();
+        let (new_heads, mut heads) = broadcast::channel(4);
+        let (respond, mut response) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(7, respond);
+
+        dispatch(r#"{"jsonrpc":"2.0","id":7,"result":"0x1"}"#, &connection, &new_heads);
+        dispatch(
+            r#"{"jsonrpc":"2.0","method":"starknet_subscriptionNewHeads","params":{"subscription_id":1,"result":{"block_number":4438500}}}"#,
+            &connection,
+            &new_heads,
+        );
+
+        assert_eq!(response.try_recv().unwrap()["result"], "0x1");
+        assert!(connection.pending.lock().unwrap().is_empty());
+        assert_eq!(heads.try_recv().unwrap(), 4438500);
+    }
+
+    #[test]
+    fn test_dispatch_forwards_events_to_live_subscribers() {
+        let connection = connection();
+        let (new_heads, _) = broadcast::channel(4);
+        let (subscriber, mut events) = mpsc::unbounded_channel();
+        let (gone, _) = mpsc::unbounded_channel();
+        connection.event_subscribers.lock().unwrap().extend([subscriber, gone]);
+
+        dispatch(
+            r#"{"jsonrpc":"2.0","method":"starknet_subscriptionEvents","params":{"subscription_id":2,"result":{
+                "from_address":"0x2a1","keys":["0x1"],"data":["0x3e8","0x0","0x7d0"],
+                "block_hash":"0x10","block_number":16,"transaction_hash":"0x99"}}}"#,
+            &connection,
+            &new_heads,
+        );
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.block_number, Some(16));
+        assert_eq!(event.data[0], FieldElement::from(0x3e8u64));
+        // The dropped receiver was forgotten
+        assert_eq!(connection.event_subscribers.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,41 @@
+import { describe, it, expect } from '@jest/globals'
+import { getStarkKey, sign } from 'micro-starknet'
+import { pathStatementHash, verifySignedPath } from '../asp-signature'
+import type { MerkleProof } from '@/types/api-types'
+
+describe('ASP response signatures', () => {
+  const privateKey = '0x5eed'
+  const publicKey = getStarkKey(privateKey)
+
+  const signedPath = (): MerkleProof => {
+    const proof: MerkleProof = {
+      leaf: '0x1234',
+      leaf_index: 3,
+      path: ['0x1', '0x2'],
+      path_indices: [1, 1],
+      root: '0xabcd',
+    }
+    const timestamp = 1700000000
+    const hash = pathStatementHash(proof, timestamp)
+    const signature = sign('0x' + hash.toString(16).padStart(64, '0'), privateKey)
+    proof.signature = {
+      public_key: publicKey,
+      timestamp,
+      r: '0x' + signature.r.toString(16),
+      s: '0x' + signature.s.toString(16),
+    }
+    return proof
+  }
+
+  it('accepts a path signed by the operator', () => {
+    expect(verifySignedPath(signedPath(), publicKey)).toBe(true)
+  })
+
+  it('rejects a tampered path, another key or a stale signature', () => {
+    const tampered = signedPath()
+    tampered.path_indices[0] = 0
+    expect(verifySignedPath(tampered, publicKey)).toBe(false)
+    expect(verifySignedPath(signedPath(), getStarkKey('0x1234'))).toBe(false)
+    expect(verifySignedPath(signedPath(), publicKey, 60)).toBe(false)
+  })
+})
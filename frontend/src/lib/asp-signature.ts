@@ -0,0 +1,112 @@
+import { poseidonHashMany, verify, Signature } from "micro-starknet";
+import type { MerkleProof, ResponseSignature, TreeInfo } from "@/types/api-types";
+
+/**
+ * Verification of ASP response signatures
+ * An ASP run with ASP_SIGNING_KEY signs the Merkle paths and roots it serves with the
+ * operator's Stark key. Checking them against the operator's published public key catches a
+ * compromised or misbehaving ASP before a proof is built on its data. The hashes mirror
+ * asp/src/response_signing.rs.
+ */
+
+/** Cairo short string as a felt */
+function shortString(value: string): bigint {
+  return [...value].reduce((felt, char) => (felt << 8n) | BigInt(char.charCodeAt(0)), 0n);
+}
+
+function toHex(value: bigint): string {
+  return "0x" + value.toString(16).padStart(64, "0");
+}
+
+/** H('zylith-asp-root', timestamp, root, leaf_count) */
+export function rootStatementHash(root: string, leafCount: number, timestamp: number): bigint {
+  return poseidonHashMany([shortString("zylith-asp-root"), BigInt(timestamp), BigInt(root), BigInt(leafCount)]);
+}
+
+/** H('zylith-asp-path', timestamp, root, leaf, leaf_index, depth, path.., path_indices..) */
+export function pathStatementHash(proof: MerkleProof, timestamp: number): bigint {
+  return poseidonHashMany([
+    shortString("zylith-asp-path"),
+    BigInt(timestamp),
+    BigInt(proof.root),
+    BigInt(proof.leaf),
+    BigInt(proof.leaf_index ?? 0),
+    BigInt(proof.path.length),
+    ...proof.path.map((sibling) => BigInt(sibling)),
+    ...proof.path_indices.map((index) => BigInt(index)),
+  ]);
+}
+
+/**
+ * Whether `signature` over `hash` was made by `operatorPublicKey` (the Stark key, an x
+ * coordinate) no more than `maxAgeSeconds` ago
+ */
+export function verifyAspSignature(
+  hash: bigint,
+  signature: ResponseSignature | undefined,
+  operatorPublicKey: string,
+  maxAgeSeconds?: number
+): boolean {
+  if (!signature || BigInt(signature.public_key) !== BigInt(operatorPublicKey)) {
+    return false;
+  }
+  if (maxAgeSeconds !== undefined && Date.now() / 1000 - signature.timestamp > maxAgeSeconds) {
+    return false;
+  }
+  const sig = new Signature(BigInt(signature.r), BigInt(signature.s));
+  const x = BigInt(operatorPublicKey).toString(16).padStart(64, "0");
+  // A Stark key is only the x coordinate: either point with it may be the signer's
+  return ["02", "03"].some((prefix) => {
+    try {
+      return verify(sig, toHex(hash), prefix + x);
+    } catch {
+      return false;
+    }
+  });
+}
+
+/** Whether a Merkle path from the ASP carries a valid signature of the operator */
+export function verifySignedPath(proof: MerkleProof, operatorPublicKey: string, maxAgeSeconds?: number): boolean {
+  if (!proof.signature) {
+    return false;
+  }
+  return verifyAspSignature(pathStatementHash(proof, proof.signature.timestamp), proof.signature, operatorPublicKey, maxAgeSeconds);
+}
+
+/** Whether the root in a /deposit/info response carries a valid signature of the operator */
+export function verifySignedRoot(info: TreeInfo, operatorPublicKey: string, maxAgeSeconds?: number): boolean {
+  if (!info.signature) {
+    return false;
+  }
+  return verifyAspSignature(
+    rootStatementHash(info.root, info.leaf_count, info.signature.timestamp),
+    info.signature,
+    operatorPublicKey,
+    maxAgeSeconds
+  );
+}
+
+/**
+ * Whether a bare root (/deposit/root, /contracts/:address/deposit/root) carries a valid
+ * signature of the operator; those keep the root as the body and send the signature in the
+ * X-ASP-Leaf-Count / X-ASP-Signature headers
+ */
+export function verifySignedRootHeaders(
+  root: string,
+  headers: Headers,
+  operatorPublicKey: string,
+  maxAgeSeconds?: number
+): boolean {
+  const leafCount = headers.get("x-asp-leaf-count");
+  const signature = headers.get("x-asp-signature");
+  if (leafCount === null || signature === null) {
+    return false;
+  }
+  let parsed: ResponseSignature;
+  try {
+    parsed = JSON.parse(signature);
+  } catch {
+    return false;
+  }
+  return verifyAspSignature(rootStatementHash(root, Number(leafCount), parsed.timestamp), parsed, operatorPublicKey, maxAgeSeconds);
+}
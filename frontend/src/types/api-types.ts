@@ -24,15 +24,26 @@ export interface Note {
 // API types
 export interface MerkleProof {
   leaf: string
+  leaf_index?: number
   path: string[]
   path_indices: number[]
   root: string
+  signature?: ResponseSignature
 }
 
 export interface TreeInfo {
   root: string
   leaf_count: number
   depth: number
+  signature?: ResponseSignature
+}
+
+// Operator signature on ASP responses (ASP_SIGNING_KEY), checked with lib/asp-signature
+export interface ResponseSignature {
+  public_key: string
+  timestamp: number
+  r: string
+  s: string
 }
 
 export interface ProofRequest {